@@ -0,0 +1,687 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    hash::{DefaultHasher, Hasher},
+};
+
+use rs_db_parser::{
+    ast::{
+        commands::{create::SqlType, select::SortOrder},
+        expr::{AggregateFunction, Expr},
+    },
+    decimal::{DIVISION_SCALE, MAX_PRECISION},
+    errors::ParseError,
+    eval::Evaluator,
+    value::{Collation, Value},
+};
+
+use crate::{
+    error::ExecError,
+    exchange::{parallel_map, MORSEL_SIZE},
+    executor::Config,
+    operator::{eval, BoxedOperator, Operator},
+    plan::{AggregateCall, Layout},
+    spill::{row_size, SpillFile},
+    Row,
+};
+
+/// The number of temporary files a hash aggregation splits the rows of new groups into
+/// once its groups don't fit the memory budget.
+const PARTITIONS: u64 = 16;
+
+/// The hash of the values of a group, `NULL` included.
+fn group_hash(keys: &[Value]) -> u64 {
+    let mut state = DefaultHasher::new();
+    for key in keys {
+        key.hash_comparable(&mut state);
+    }
+    state.finish()
+}
+
+/// Whether two values are the same group key or distinct argument: they compare equal, or
+/// are both `NULL`.
+fn same(a: &Value, b: &Value) -> Result<bool, ExecError> {
+    Ok(a.sql_cmp(b, SortOrder::Asc, true, Collation::Binary)? == Ordering::Equal)
+}
+
+/// The values an aggregate with `DISTINCT` has seen, by their hash.
+#[derive(Default)]
+struct Distinct(HashMap<u64, Vec<Value>>);
+
+impl Distinct {
+    /// Adds `value`, returns whether it wasn't seen before.
+    fn insert(&mut self, value: &Value) -> Result<bool, ExecError> {
+        let mut state = DefaultHasher::new();
+        value.hash_comparable(&mut state);
+        let seen = self.0.entry(state.finish()).or_default();
+        for other in seen.iter() {
+            if same(value, other)? {
+                return Ok(false);
+            }
+        }
+        seen.push(value.clone());
+        Ok(true)
+    }
+}
+
+/// What an aggregate keeps of the rows of a group it has seen.
+enum State {
+    Count(i64),
+    /// The sum so far on 128 bits, or as an `F64` or a decimal, `NULL` before the first
+    /// value.
+    Sum(Value),
+    /// The sum and the number of the values, and the type of the first one, which the
+    /// average has unless it's an integer.
+    Avg {
+        sum: Value,
+        count: i64,
+        tp: Option<SqlType>,
+    },
+    Min(Value),
+    Max(Value),
+}
+
+/// The type of the average of integers, the one of the quotient of two decimals.
+const AVG_INTEGER_TYPE: SqlType = SqlType::Decimal {
+    precision: MAX_PRECISION,
+    scale: DIVISION_SCALE,
+};
+
+/// An aggregate over the rows of a group.
+struct Accumulator {
+    state: State,
+    distinct: Option<Distinct>,
+}
+
+impl Accumulator {
+    fn new(call: &AggregateCall<'_>) -> Self {
+        let state = match call.function {
+            AggregateFunction::Count => State::Count(0),
+            AggregateFunction::Sum => State::Sum(Value::Null),
+            AggregateFunction::Avg => State::Avg {
+                sum: Value::Null,
+                count: 0,
+                tp: None,
+            },
+            AggregateFunction::Min => State::Min(Value::Null),
+            AggregateFunction::Max => State::Max(Value::Null),
+        };
+        Self {
+            state,
+            distinct: call.distinct.then(Distinct::default),
+        }
+    }
+
+    /// Adds the argument of a row, `None` for `COUNT(*)`. `NULL`s are skipped, and values
+    /// already seen with `DISTINCT`. Returns the bytes the accumulator grew by.
+    fn update(&mut self, value: Option<&Value>) -> Result<usize, ExecError> {
+        let Some(value) = value else {
+            if let State::Count(count) = &mut self.state {
+                *count += 1;
+            }
+            return Ok(0);
+        };
+        if value.is_null() {
+            return Ok(0);
+        }
+        let mut size = 0;
+        if let Some(distinct) = &mut self.distinct {
+            if !distinct.insert(value)? {
+                return Ok(0);
+            }
+            size = row_size(std::slice::from_ref(value));
+        }
+        match &mut self.state {
+            State::Count(count) => *count += 1,
+            State::Sum(sum) => *sum = add(sum, value)?,
+            State::Avg { sum, count, tp } => {
+                *sum = add(sum, value)?;
+                *count += 1;
+                tp.get_or_insert_with(|| value.sql_type().unwrap_or(SqlType::F64));
+            }
+            State::Min(min) => {
+                if min.is_null() || value.compare(min)? == Some(Ordering::Less) {
+                    *min = value.clone();
+                }
+            }
+            State::Max(max) => {
+                if max.is_null() || value.compare(max)? == Some(Ordering::Greater) {
+                    *max = value.clone();
+                }
+            }
+        }
+        Ok(size)
+    }
+
+    fn finish(self) -> Result<Value, ExecError> {
+        Ok(match self.state {
+            State::Count(count) => Value::I64(count),
+            State::Sum(sum) | State::Min(sum) | State::Max(sum) => sum,
+            State::Avg { count: 0, .. } => Value::Null,
+            // The average of integers is a decimal, so it keeps the fraction of the mean.
+            State::Avg { sum, count, tp } => {
+                let (sum, tp) = match tp {
+                    Some(tp) if tp.is_integer() => {
+                        let sum = sum.as_decimal().ok_or(ParseError::ArithmeticOverflow)?;
+                        (Value::Decimal(sum), Some(AVG_INTEGER_TYPE))
+                    }
+                    tp => (sum, tp),
+                };
+                let avg = sum.div(&Value::I64(count))?;
+                match tp {
+                    Some(tp) => avg.cast(tp).map_err(|_| ParseError::ArithmeticOverflow)?,
+                    None => avg,
+                }
+            }
+        })
+    }
+}
+
+/// `sum + value`, `value` being first widened to the type of the sum: 128 bits for
+/// integers, `F64` for floats.
+fn add(sum: &Value, value: &Value) -> Result<Value, ExecError> {
+    let tp = match value.sql_type() {
+        Some(tp) if tp.is_float() => Some(SqlType::F64),
+        Some(tp) => tp.integer_range().map(|(min, _)| {
+            if min >= 0 {
+                SqlType::U128
+            } else {
+                SqlType::I128
+            }
+        }),
+        None => None,
+    };
+    let value = match tp {
+        Some(tp) => value.cast(tp).map_err(|_| ParseError::InvalidOperand)?,
+        None => value.clone(),
+    };
+    if sum.is_null() {
+        return Ok(value);
+    }
+    Ok(sum.add(&value)?)
+}
+
+/// The groups of a hash aggregation, or of one of its partitions, by the hash of their
+/// values.
+#[derive(Default)]
+struct Groups {
+    keys: Vec<Row>,
+    accumulators: Vec<Vec<Accumulator>>,
+    buckets: HashMap<u64, Vec<usize>>,
+    /// The bytes the groups take, roughly.
+    size: usize,
+}
+
+impl Groups {
+    fn find(&self, hash: u64, keys: &[Value]) -> Result<Option<usize>, ExecError> {
+        for index in self.buckets.get(&hash).into_iter().flatten() {
+            let mut equal = true;
+            for (a, b) in keys.iter().zip(&self.keys[*index]) {
+                if !same(a, b)? {
+                    equal = false;
+                    break;
+                }
+            }
+            if equal {
+                return Ok(Some(*index));
+            }
+        }
+        Ok(None)
+    }
+
+    fn insert(&mut self, hash: u64, keys: Row, aggregates: &[AggregateCall<'_>]) -> usize {
+        let index = self.keys.len();
+        self.buckets.entry(hash).or_default().push(index);
+        self.size += row_size(&keys) + aggregates.len() * std::mem::size_of::<Accumulator>();
+        self.keys.push(keys);
+        self.accumulators
+            .push(aggregates.iter().map(Accumulator::new).collect());
+        index
+    }
+
+    /// Adds the arguments of a row to the aggregates of the group at `index`.
+    fn update(
+        &mut self,
+        index: usize,
+        args: &[Value],
+        aggregates: &[AggregateCall<'_>],
+    ) -> Result<(), ExecError> {
+        for ((accumulator, arg), call) in self.accumulators[index]
+            .iter_mut()
+            .zip(args)
+            .zip(aggregates)
+        {
+            self.size += accumulator.update(call.arg.as_ref().map(|_| arg))?;
+        }
+        Ok(())
+    }
+
+    /// The rows of the groups, their values then the ones of their aggregates.
+    fn finish(self) -> Result<Vec<Row>, ExecError> {
+        self.keys
+            .into_iter()
+            .zip(self.accumulators)
+            .map(|(mut row, accumulators)| {
+                for accumulator in accumulators {
+                    row.push(accumulator.finish()?);
+                }
+                Ok(row)
+            })
+            .collect()
+    }
+}
+
+/// Computes the aggregates of each group of rows in a hash table of the groups. Once the
+/// groups exceed the memory budget, the rows of the groups already in the table keep
+/// updating them, while the rows of new groups are split by the hash of their values into
+/// temporary files, each aggregated in turn after the groups in memory are returned. A
+/// file is loaded whole even if its groups still exceed the budget.
+pub(crate) struct HashAggregate<'p, 'a> {
+    pub(crate) input: BoxedOperator<'p>,
+    pub(crate) group_by: &'p [Expr<'a>],
+    pub(crate) aggregates: &'p [AggregateCall<'a>],
+    /// The layout of the input rows.
+    pub(crate) layout: Layout,
+    pub(crate) evaluator: Evaluator<'p>,
+    pub(crate) config: &'p Config,
+    /// The rows of new groups written once the groups didn't fit the memory budget, their
+    /// values then the arguments of the aggregates.
+    pub(crate) partitions: Vec<SpillFile>,
+    pub(crate) output: Option<std::vec::IntoIter<Row>>,
+}
+
+/// What putting rows in their groups takes, shared by the threads of a parallel
+/// aggregation.
+#[derive(Clone, Copy)]
+struct Grouping<'p, 'a> {
+    group_by: &'p [Expr<'a>],
+    aggregates: &'p [AggregateCall<'a>],
+    layout: &'p Layout,
+    evaluator: Evaluator<'p>,
+}
+
+impl Grouping<'_, '_> {
+    /// The values of the group of `row`, then the arguments of the aggregates, `NULL` for
+    /// `COUNT(*)`.
+    fn evaluate(&self, row: &[Value]) -> Result<Row, ExecError> {
+        self.group_by
+            .iter()
+            .map(Some)
+            .chain(self.aggregates.iter().map(|call| call.arg.as_ref()))
+            .map(|expr| match expr {
+                Some(expr) => eval(self.evaluator, expr, self.layout, row),
+                None => Ok(Value::Null),
+            })
+            .collect()
+    }
+
+    /// Adds a row made by [`Self::evaluate`], whose values hash to `hash`, to its group. If
+    /// it's a new one and `spill`, the row is returned instead, to be written to a
+    /// partition.
+    fn add(
+        &self,
+        groups: &mut Groups,
+        hash: u64,
+        row: Row,
+        spill: bool,
+    ) -> Result<Option<Row>, ExecError> {
+        let width = self.group_by.len();
+        if let Some(index) = groups.find(hash, &row[..width])? {
+            groups.update(index, &row[width..], self.aggregates)?;
+            return Ok(None);
+        }
+        if spill {
+            return Ok(Some(row));
+        }
+        let mut row = row;
+        let args = row.split_off(width);
+        let index = groups.insert(hash, row, self.aggregates);
+        groups.update(index, &args, self.aggregates)?;
+        Ok(None)
+    }
+}
+
+/// The groups a thread of a parallel aggregation keeps, and the position in the input of
+/// the first row of each, to return them in the order a single thread finds them.
+#[derive(Default)]
+struct Partition {
+    groups: Groups,
+    firsts: Vec<u64>,
+}
+
+impl HashAggregate<'_, '_> {
+    fn grouping(&self) -> Grouping<'_, '_> {
+        Grouping {
+            group_by: self.group_by,
+            aggregates: self.aggregates,
+            layout: &self.layout,
+            evaluator: self.evaluator,
+        }
+    }
+
+    fn spill(&mut self, row: &[Value]) -> Result<(), ExecError> {
+        #[allow(clippy::cast_possible_truncation)]
+        let partition = (group_hash(&row[..self.group_by.len()]) % PARTITIONS) as usize;
+        self.partitions[partition].write(row)
+    }
+
+    fn create_partitions(&mut self) -> Result<(), ExecError> {
+        self.partitions = (0..PARTITIONS)
+            .map(|_| SpillFile::create(&self.config.spill_dir))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    fn aggregate_input(&mut self) -> Result<Vec<Row>, ExecError> {
+        if self.config.max_parallelism > 1 {
+            return self.aggregate_input_parallel(self.config.max_parallelism);
+        }
+        let mut groups = Groups::default();
+        while let Some(row) = self.input.next()? {
+            let grouping = self.grouping();
+            let row = grouping.evaluate(&row)?;
+            let hash = group_hash(&row[..self.group_by.len()]);
+            if let Some(row) = grouping.add(&mut groups, hash, row, !self.partitions.is_empty())? {
+                self.spill(&row)?;
+            } else if self.partitions.is_empty() && groups.size > self.config.memory_budget {
+                self.create_partitions()?;
+            }
+        }
+        if self.group_by.is_empty() && groups.keys.is_empty() {
+            groups.insert(0, Vec::new(), self.aggregates);
+        }
+        groups.finish()
+    }
+
+    /// Aggregates the input on `workers` threads: the rows are read by morsels, evaluated in
+    /// parallel, then repartitioned by the hash of their group so each thread owns the
+    /// groups of a partition. The memory budget is checked once the threads are done with
+    /// their morsels, so the groups spilled, and their order, may differ from the ones of a
+    /// single thread.
+    fn aggregate_input_parallel(&mut self, workers: usize) -> Result<Vec<Row>, ExecError> {
+        let width = self.group_by.len();
+        let mut partitions = (0..workers)
+            .map(|_| Partition::default())
+            .collect::<Vec<_>>();
+        let mut position = 0;
+        let mut done = false;
+        while !done {
+            let mut morsels = Vec::with_capacity(workers);
+            let mut error = None;
+            while morsels.len() < workers && !done {
+                let mut morsel = Vec::with_capacity(MORSEL_SIZE);
+                while morsel.len() < MORSEL_SIZE {
+                    match self.input.next() {
+                        Ok(Some(row)) => morsel.push(row),
+                        Ok(None) => done = true,
+                        Err(e) => {
+                            error = Some(e);
+                            done = true;
+                        }
+                    }
+                    if done {
+                        break;
+                    }
+                }
+                if !morsel.is_empty() {
+                    morsels.push(morsel);
+                }
+            }
+            let grouping = self.grouping();
+            let evaluated = parallel_map(morsels, workers, |morsel| {
+                let mut rows = Vec::with_capacity(morsel.len());
+                for row in morsel {
+                    match grouping.evaluate(&row) {
+                        Ok(row) => rows.push((group_hash(&row[..width]), row)),
+                        Err(e) => return (rows, Some(e)),
+                    }
+                }
+                (rows, None)
+            });
+            // The rows up to the first failure, the error of the input being after them.
+            let mut repartitioned = vec![Vec::new(); workers];
+            let mut rows = 0;
+            let mut failure = None;
+            for (evaluated, e) in evaluated {
+                for (hash, row) in evaluated {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let partition = (hash % workers as u64) as usize;
+                    repartitioned[partition].push((position, hash, row));
+                    position += 1;
+                    rows += 1;
+                }
+                if let Some(e) = e {
+                    failure = Some((position, e));
+                    break;
+                }
+            }
+            let failure = failure.or_else(|| error.map(|e| (position, e)));
+            let spill = !self.partitions.is_empty();
+            let workers = if rows > MORSEL_SIZE { workers } else { 1 };
+            let added = parallel_map(
+                partitions.iter_mut().zip(repartitioned).collect(),
+                workers,
+                |(partition, rows)| {
+                    let mut spilled = Vec::new();
+                    for (position, hash, row) in rows {
+                        let groups = partition.groups.keys.len();
+                        match grouping.add(&mut partition.groups, hash, row, spill) {
+                            Ok(Some(row)) => spilled.push((position, row)),
+                            Ok(None) if partition.groups.keys.len() > groups => {
+                                partition.firsts.push(position);
+                            }
+                            Ok(None) => {}
+                            Err(e) => return (spilled, Some((position, e))),
+                        }
+                    }
+                    (spilled, None)
+                },
+            );
+            let mut spilled = Vec::new();
+            let mut failures = Vec::from_iter(failure);
+            for (rows, failure) in added {
+                spilled.extend(rows);
+                failures.extend(failure);
+            }
+            if let Some((_, e)) = failures.into_iter().min_by_key(|(position, _)| *position) {
+                return Err(e);
+            }
+            // Written in the order of the input, as a single thread does.
+            spilled.sort_unstable_by_key(|(position, _)| *position);
+            for (_, row) in spilled {
+                self.spill(&row)?;
+            }
+            let size = partitions.iter().map(|p| p.groups.size).sum::<usize>();
+            if self.partitions.is_empty() && size > self.config.memory_budget {
+                self.create_partitions()?;
+            }
+        }
+        let mut groups = Vec::new();
+        for partition in partitions {
+            groups.extend(partition.firsts.into_iter().zip(partition.groups.finish()?));
+        }
+        groups.sort_unstable_by_key(|(first, _)| *first);
+        if self.group_by.is_empty() && groups.is_empty() {
+            let mut empty = Groups::default();
+            empty.insert(0, Vec::new(), self.aggregates);
+            return empty.finish();
+        }
+        Ok(groups.into_iter().map(|(_, row)| row).collect())
+    }
+
+    fn next_partition(&mut self) -> Result<Option<Vec<Row>>, ExecError> {
+        while let Some(partition) = self.partitions.pop() {
+            if partition.rows() == 0 {
+                continue;
+            }
+            let mut groups = Groups::default();
+            for row in partition.into_reader()? {
+                let row = row?;
+                let hash = group_hash(&row[..self.group_by.len()]);
+                self.grouping().add(&mut groups, hash, row, false)?;
+            }
+            return groups.finish().map(Some);
+        }
+        Ok(None)
+    }
+}
+
+impl Operator for HashAggregate<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        if self.output.is_none() {
+            self.output = Some(self.aggregate_input()?.into_iter());
+        }
+        loop {
+            if let Some(row) = self.output.as_mut().and_then(Iterator::next) {
+                return Ok(Some(row));
+            }
+            match self.next_partition()? {
+                Some(rows) => self.output = Some(rows.into_iter()),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{
+        decimal::Decimal,
+        functions::FunctionRegistry,
+        parse::{Parse, RawSpan},
+    };
+
+    use super::*;
+    use crate::{
+        cancel::CancellationToken,
+        operator::{build, Context},
+        plan::{Field, Plan},
+        storage::testing::TestStorage,
+    };
+
+    fn aggregate(config: &Config) -> Vec<Row> {
+        let field = |name: &str| Field {
+            table: Some("t".into()),
+            name: name.into(),
+        };
+        let rows = (0..300)
+            .map(|i: i64| {
+                let key = if i % 7 == 0 {
+                    Value::Null
+                } else {
+                    Value::I64(i % 50)
+                };
+                vec![key, Value::I64(i), Value::I32((i % 3) as i32)]
+            })
+            .collect();
+        let expr = |input: &'static str| Expr::parse(RawSpan::new(input)).unwrap().1;
+        let call = |function, distinct, arg: &'static str| AggregateCall {
+            function,
+            distinct,
+            arg: Some(expr(arg)),
+        };
+        let plan = Plan::Aggregate {
+            input: Box::new(Plan::Values {
+                rows,
+                layout: Layout(vec![field("k"), field("v"), field("w")]),
+            }),
+            group_by: vec![expr("k")],
+            aggregates: vec![
+                AggregateCall {
+                    function: AggregateFunction::Count,
+                    distinct: false,
+                    arg: None,
+                },
+                call(AggregateFunction::Sum, false, "v"),
+                call(AggregateFunction::Avg, false, "v"),
+                call(AggregateFunction::Min, false, "w"),
+                call(AggregateFunction::Max, false, "v"),
+                call(AggregateFunction::Count, true, "w"),
+            ],
+            layout: Layout::default(),
+        };
+        let (storage, _) = TestStorage::new(&[]);
+        let context = Context {
+            storage: &storage,
+            functions: FunctionRegistry::builtins(),
+            config,
+            profile: None,
+            cancellation: &CancellationToken::new(),
+        };
+        let mut operator = build(&plan, context).unwrap();
+        let mut rows = Vec::new();
+        while let Some(row) = operator.next().unwrap() {
+            rows.push(row);
+        }
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_hash_aggregate() {
+        let rows = aggregate(&Config {
+            max_parallelism: 1,
+            ..Config::default()
+        });
+        // 50 keys and the `NULL` one.
+        assert_eq!(rows.len(), 51);
+        let null = rows.iter().find(|row| row[0].is_null()).unwrap();
+        assert_eq!(null[1], Value::I64(43));
+        assert_eq!(null[2], Value::I128((0..300).step_by(7).sum()));
+        let one = rows.iter().find(|row| row[0] == Value::I64(1)).unwrap();
+        // 1, 51, 101, 151, 201 and 251, but not 0 + 7k.
+        assert_eq!(
+            one[1..],
+            [
+                Value::I64(6),
+                Value::I128(756),
+                Value::Decimal(Decimal::from_integer(126).unwrap()),
+                Value::I32(0),
+                Value::I64(251),
+                Value::I64(3),
+            ]
+        );
+        // New groups go to temporary files as soon as the first one is in memory.
+        let spilled = aggregate(&Config {
+            memory_budget: 0,
+            max_parallelism: 1,
+            ..Config::default()
+        });
+        assert_eq!(spilled, rows);
+        for memory_budget in [0, usize::MAX] {
+            let parallel = aggregate(&Config {
+                memory_budget,
+                max_parallelism: 4,
+                ..Config::default()
+            });
+            assert_eq!(parallel, rows);
+        }
+    }
+
+    #[test]
+    fn test_avg() {
+        let avg = |values: &[Value]| {
+            let mut accumulator = Accumulator::new(&AggregateCall {
+                function: AggregateFunction::Avg,
+                distinct: false,
+                arg: None,
+            });
+            for value in values {
+                accumulator.update(Some(value)).unwrap();
+            }
+            accumulator.finish().unwrap()
+        };
+        // The average of integers keeps its fraction.
+        assert_eq!(
+            avg(&[Value::I32(-32), Value::I32(-31)]),
+            Value::Decimal("-31.5".parse().unwrap())
+        );
+        assert_eq!(
+            avg(&[Value::I8(1), Value::I8(2), Value::I8(2)]),
+            Value::Decimal("1.666667".parse().unwrap())
+        );
+        assert_eq!(avg(&[Value::F64(1.0), Value::F64(2.0)]), Value::F64(1.5));
+        assert_eq!(avg(&[Value::Null]), Value::Null);
+    }
+}
@@ -0,0 +1,370 @@
+use rs_db_parser::value::Value;
+
+use crate::Row;
+
+/// The rows the vectorized operators process at once by default, see
+/// [`Config::batch_size`](crate::executor::Config::batch_size).
+pub const BATCH_SIZE: usize = 1024;
+
+/// A set of positions, a bit per position packed in words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    /// `len` positions, all set when `set` is true.
+    #[must_use]
+    pub fn new(len: usize, set: bool) -> Self {
+        let mut bitmap = Self {
+            words: vec![if set { u64::MAX } else { 0 }; len.div_ceil(64)],
+            len,
+        };
+        bitmap.clear_tail();
+        bitmap
+    }
+
+    /// Unsets the bits past the last position, so the words can be compared and counted.
+    fn clear_tail(&mut self) {
+        if !self.len.is_multiple_of(64) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << (self.len % 64)) - 1;
+            }
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn set(&mut self, index: usize, set: bool) {
+        if set {
+            self.words[index / 64] |= 1 << (index % 64);
+        } else {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    /// The number of positions set.
+    #[must_use]
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Whether no position is set.
+    #[must_use]
+    pub fn none(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    /// The positions set in both bitmaps, of the same length.
+    #[must_use]
+    pub fn and(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a & b)
+    }
+
+    /// The positions set in either bitmap, of the same length.
+    #[must_use]
+    pub fn or(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a | b)
+    }
+
+    /// The positions set in this bitmap and not in `other`, of the same length.
+    #[must_use]
+    pub fn and_not(&self, other: &Self) -> Self {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    /// The positions not set.
+    #[must_use]
+    pub fn not(&self) -> Self {
+        let mut bitmap = Self {
+            words: self.words.iter().map(|w| !w).collect(),
+            len: self.len,
+        };
+        bitmap.clear_tail();
+        bitmap
+    }
+
+    fn zip(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        debug_assert_eq!(self.len, other.len);
+        Self {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+            len: self.len,
+        }
+    }
+
+    /// The positions set, in order.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+/// The values of a [`Vector`], in an array of their type when they all have one of the
+/// common types. The positions of the `NULL`s hold a default value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorData {
+    Bool(Vec<bool>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    F64(Vec<f64>),
+    VarChar(Vec<Box<str>>),
+    /// Values of any other type, or of several types.
+    Values(Vec<Value>),
+}
+
+/// The values of a column of a [`Batch`], and the positions of its `NULL`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector {
+    pub data: VectorData,
+    pub nulls: Bitmap,
+}
+
+impl Vector {
+    /// Stores `values` in the array of their type, see [`VectorData`].
+    #[must_use]
+    pub fn from_values(values: Vec<Value>) -> Self {
+        let mut nulls = Bitmap::new(values.len(), false);
+        let mut kind = None;
+        let mut mixed = false;
+        for (i, value) in values.iter().enumerate() {
+            if value.is_null() {
+                nulls.set(i, true);
+                continue;
+            }
+            let discriminant = std::mem::discriminant(value);
+            mixed |= *kind.get_or_insert(discriminant) != discriminant;
+        }
+        macro_rules! typed {
+            ($variant:ident, $default:expr) => {
+                VectorData::$variant(
+                    values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::$variant(value) => value,
+                            _ => $default,
+                        })
+                        .collect(),
+                )
+            };
+        }
+        let data = match values.iter().find(|value| !value.is_null()) {
+            _ if mixed => VectorData::Values(values),
+            Some(Value::Bool(_)) => typed!(Bool, false),
+            Some(Value::I32(_)) => typed!(I32, 0),
+            Some(Value::I64(_)) => typed!(I64, 0),
+            Some(Value::F64(_)) => typed!(F64, 0.0),
+            Some(Value::VarChar(_)) => typed!(VarChar, Box::default()),
+            _ => VectorData::Values(values),
+        };
+        Self { data, nulls }
+    }
+
+    /// `len` times `value`.
+    #[must_use]
+    pub fn repeat(value: &Value, len: usize) -> Self {
+        Self::from_values(vec![value.clone(); len])
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.nulls.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.nulls.is_empty()
+    }
+
+    /// The value at `index`.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Value {
+        if self.nulls.get(index) {
+            return Value::Null;
+        }
+        match &self.data {
+            VectorData::Bool(values) => Value::Bool(values[index]),
+            VectorData::I32(values) => Value::I32(values[index]),
+            VectorData::I64(values) => Value::I64(values[index]),
+            VectorData::F64(values) => Value::F64(values[index]),
+            VectorData::VarChar(values) => Value::VarChar(values[index].clone()),
+            VectorData::Values(values) => values[index].clone(),
+        }
+    }
+
+    /// The values at the positions set in `selection`.
+    #[must_use]
+    pub fn filter(&self, selection: &Bitmap) -> Self {
+        fn pick<T: Clone>(values: &[T], selection: &Bitmap) -> Vec<T> {
+            selection.iter_ones().map(|i| values[i].clone()).collect()
+        }
+        let data = match &self.data {
+            VectorData::Bool(values) => VectorData::Bool(pick(values, selection)),
+            VectorData::I32(values) => VectorData::I32(pick(values, selection)),
+            VectorData::I64(values) => VectorData::I64(pick(values, selection)),
+            VectorData::F64(values) => VectorData::F64(pick(values, selection)),
+            VectorData::VarChar(values) => VectorData::VarChar(pick(values, selection)),
+            VectorData::Values(values) => VectorData::Values(pick(values, selection)),
+        };
+        let mut nulls = Bitmap::new(selection.count_ones(), false);
+        for (i, position) in selection.iter_ones().enumerate() {
+            nulls.set(i, self.nulls.get(position));
+        }
+        Self { data, nulls }
+    }
+}
+
+/// Rows stored by column, what the vectorized operators pass each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub columns: Vec<Vector>,
+    /// The number of rows, kept apart for a batch without columns.
+    len: usize,
+}
+
+impl Batch {
+    /// The batch of `rows`, each with `width` values.
+    #[must_use]
+    pub fn from_rows(rows: Vec<Row>, width: usize) -> Self {
+        let len = rows.len();
+        let mut columns = vec![Vec::with_capacity(len); width];
+        for row in rows {
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+        Self {
+            columns: columns.into_iter().map(Vector::from_values).collect(),
+            len,
+        }
+    }
+
+    /// A batch of `len` rows with the values of `columns`, each of `len` values.
+    #[must_use]
+    pub fn new(columns: Vec<Vector>, len: usize) -> Self {
+        debug_assert!(columns.iter().all(|column| column.len() == len));
+        Self { columns, len }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The row at `index`.
+    #[must_use]
+    pub fn row(&self, index: usize) -> Row {
+        self.columns
+            .iter()
+            .map(|column| column.get(index))
+            .collect()
+    }
+
+    /// The rows at the positions set in `selection`.
+    #[must_use]
+    pub fn filter(&self, selection: &Bitmap) -> Self {
+        Self {
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.filter(selection))
+                .collect(),
+            len: selection.count_ones(),
+        }
+    }
+
+    /// The first `len` rows.
+    #[must_use]
+    pub fn prefix(&self, len: usize) -> Self {
+        let mut selection = Bitmap::new(self.len, false);
+        for i in 0..len {
+            selection.set(i, true);
+        }
+        self.filter(&selection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_bitmap() {
+        let mut bitmap = Bitmap::new(130, false);
+        for i in [0, 63, 64, 129] {
+            bitmap.set(i, true);
+        }
+        assert_eq!(bitmap.iter_ones().collect::<Vec<_>>(), [0, 63, 64, 129]);
+        assert_eq!(bitmap.not().count_ones(), 126);
+        assert!(!bitmap.not().get(129));
+        let all = Bitmap::new(130, true);
+        assert_eq!(all.count_ones(), 130);
+        assert_eq!(all.and(&bitmap), bitmap);
+        assert_eq!(all.and_not(&bitmap), bitmap.not());
+        assert_eq!(bitmap.or(&bitmap.not()), all);
+        assert!(all.not().none());
+    }
+
+    #[test]
+    fn test_vector() {
+        let values = vec![Value::I32(1), Value::Null, Value::I32(3)];
+        let vector = Vector::from_values(values.clone());
+        assert_eq!(vector.data, VectorData::I32(vec![1, 0, 3]));
+        assert_eq!((0..3).map(|i| vector.get(i)).collect::<Vec<_>>(), values);
+        // Values of several types are kept as they are.
+        let mixed = vec![Value::I32(1), Value::I64(2), Value::Null];
+        let vector = Vector::from_values(mixed.clone());
+        assert_eq!(vector.data, VectorData::Values(mixed));
+        assert_eq!(vector.get(2), Value::Null);
+
+        let batch = Batch::from_rows(
+            vec![
+                vec![Value::I32(1), Value::VarChar("a".into())],
+                vec![Value::Null, Value::VarChar("b".into())],
+                vec![Value::I32(3), Value::Null],
+            ],
+            2,
+        );
+        let mut selection = Bitmap::new(3, false);
+        selection.set(1, true);
+        selection.set(2, true);
+        let filtered = batch.filter(&selection);
+        assert_eq!(filtered.len(), 2);
+        assert_eq!(filtered.row(0), [Value::Null, Value::VarChar("b".into())]);
+        assert_eq!(filtered.row(1), [Value::I32(3), Value::Null]);
+        assert_eq!(batch.prefix(1).row(0), batch.row(0));
+        assert_eq!(batch.prefix(1).len(), 1);
+    }
+}
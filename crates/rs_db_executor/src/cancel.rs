@@ -0,0 +1,88 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::error::ExecError;
+
+/// Stops the statements run with it, from any thread: the operators check it as they read
+/// rows, and fail with [`ExecError::QueryCancelled`] once it is cancelled or past its
+/// deadline. The clones of a token are cancelled together.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token and its clones, the statements running with them stop at their
+    /// next check.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// A token cancelled with this one, and once `timeout` has passed.
+    #[must_use]
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let deadline = Instant::now().checked_add(timeout);
+        Self {
+            cancelled: Arc::clone(&self.cancelled),
+            deadline: match (self.deadline, deadline) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// # Errors
+    /// Returns [`ExecError::QueryCancelled`] if the token is cancelled.
+    pub fn check(&self) -> Result<(), ExecError> {
+        if self.is_cancelled() {
+            return Err(ExecError::QueryCancelled);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+        let clone = token.clone();
+        let timed = token.with_timeout(Duration::from_secs(3600));
+        std::thread::spawn(move || clone.cancel()).join().unwrap();
+        assert_eq!(token.check(), Err(ExecError::QueryCancelled));
+        assert!(timed.is_cancelled());
+
+        let expired = CancellationToken::new().with_timeout(Duration::ZERO);
+        assert!(expired.is_cancelled());
+        // The earliest deadline wins.
+        assert!(expired
+            .with_timeout(Duration::from_secs(3600))
+            .is_cancelled());
+        assert!(!CancellationToken::new()
+            .with_timeout(Duration::MAX)
+            .is_cancelled());
+    }
+}
@@ -0,0 +1,530 @@
+use std::{collections::HashSet, fmt};
+
+use rs_db_parser::{
+    ast::{
+        commands::create::{Constraint, ConstraintKind},
+        expr::Expr,
+        table::TableName,
+    },
+    eval::Evaluator,
+    parse::{Parse, RawSpan, TableMap},
+    value::Value,
+};
+
+use crate::{error::ExecError, operator::eval, plan::Layout, storage::Storage, Row};
+
+/// A constraint of the catalog bound to the rows of its table, whose columns are sorted by
+/// name, see [`bind`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BoundConstraint {
+    pub name: Box<str>,
+    pub kind: BoundKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BoundKind {
+    /// The position of the column.
+    NotNull(usize),
+    /// The positions of the columns of the key, of the primary key with `primary`.
+    Unique { columns: Vec<usize>, primary: bool },
+    /// The condition as SQL text, parsed when the statement runs.
+    Check(Box<str>),
+    /// The positions of the `columns` of a row and of the `referenced` columns of a row of
+    /// `table`, a [`table_key`](crate::storage::table_key).
+    ForeignKey {
+        columns: Vec<usize>,
+        table: Box<str>,
+        referenced: Vec<usize>,
+    },
+}
+
+/// Which kind of constraint a row violates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    NotNull,
+    Unique,
+    PrimaryKey,
+    Check,
+    ForeignKey,
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotNull => "NOT NULL",
+            Self::Unique => "UNIQUE",
+            Self::PrimaryKey => "PRIMARY KEY",
+            Self::Check => "CHECK",
+            Self::ForeignKey => "FOREIGN KEY",
+        })
+    }
+}
+
+/// A row that can't be written as it breaks a constraint of its table, with the values of
+/// the columns of the constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolation {
+    pub constraint: Box<str>,
+    pub kind: ViolationKind,
+    /// The columns of the constraint, the ones its condition reads for a `CHECK`.
+    pub columns: Vec<Box<str>>,
+    /// The values of `columns` in the row.
+    pub values: Row,
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Row violates {} constraint `{}`: ({}) = (",
+            self.kind,
+            self.constraint,
+            self.columns.join(", ")
+        )?;
+        for (i, value) in self.values.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{value}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// The positions of `names` among `columns`, the names of the columns of a table sorted.
+fn positions(columns: &[&str], names: &[Box<str>]) -> Result<Vec<usize>, ExecError> {
+    names
+        .iter()
+        .map(|name| {
+            columns
+                .binary_search(&&**name)
+                .map_err(|_| ExecError::ColumnNotFound(name.clone()))
+        })
+        .collect()
+}
+
+/// The names of the columns of a table of `table_map`, sorted, as its rows hold them.
+fn sorted_columns<'m>(
+    table_map: &'m TableMap,
+    schema: Option<&str>,
+    table: &str,
+) -> Result<Vec<&'m str>, ExecError> {
+    let columns = table_map
+        .get_qualified(schema, table)
+        .ok_or_else(|| ExecError::TableNotFound(table.into()))?;
+    let mut names = columns.keys().map(|name| &**name).collect::<Vec<_>>();
+    names.sort_unstable();
+    Ok(names)
+}
+
+/// Binds the constraints `table_map` keeps for `table` to its rows, resolving the tables
+/// and the primary keys their foreign keys reference.
+/// # Errors
+/// Returns an error if a constraint names a table or a column that doesn't exist, or if a
+/// foreign key references a table without a primary key.
+pub fn bind(
+    table: &TableName<'_>,
+    table_map: &TableMap,
+) -> Result<Vec<BoundConstraint>, ExecError> {
+    let schema = table.schema_name();
+    let name = *table.name.fragment();
+    let columns = sorted_columns(table_map, schema, name)?;
+    table_map
+        .constraints(schema, name)
+        .iter()
+        .map(|Constraint { name, kind }| {
+            let kind = match kind {
+                ConstraintKind::NotNull(column) => {
+                    BoundKind::NotNull(positions(&columns, std::slice::from_ref(column))?[0])
+                }
+                ConstraintKind::PrimaryKey(key) => BoundKind::Unique {
+                    columns: positions(&columns, key)?,
+                    primary: true,
+                },
+                ConstraintKind::Unique(key) => BoundKind::Unique {
+                    columns: positions(&columns, key)?,
+                    primary: false,
+                },
+                ConstraintKind::Check(condition) => BoundKind::Check(condition.clone()),
+                ConstraintKind::ForeignKey {
+                    columns: key,
+                    schema: referenced_schema,
+                    table_name,
+                    referenced,
+                } => {
+                    let referenced_schema = referenced_schema
+                        .as_deref()
+                        .unwrap_or_else(|| table_map.default_schema());
+                    let referenced = if referenced.is_empty() {
+                        table_map
+                            .constraints(Some(referenced_schema), table_name)
+                            .iter()
+                            .find_map(|constraint| match &constraint.kind {
+                                ConstraintKind::PrimaryKey(key) => Some(key.clone()),
+                                _ => None,
+                            })
+                            .ok_or(ExecError::Unsupported(
+                                "A foreign key to a table without a primary key",
+                            ))?
+                    } else {
+                        referenced.clone()
+                    };
+                    let referenced_columns =
+                        sorted_columns(table_map, Some(referenced_schema), table_name)?;
+                    BoundKind::ForeignKey {
+                        columns: positions(&columns, key)?,
+                        table: format!("{referenced_schema}.{table_name}").into(),
+                        referenced: positions(&referenced_columns, &referenced)?,
+                    }
+                }
+            };
+            Ok(BoundConstraint {
+                name: name.clone(),
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// The values at `positions` of `row`, `None` if one of them is `NULL`: a key with a
+/// `NULL` is never equal to another one, nor references a row.
+fn key(row: &[Value], positions: &[usize]) -> Option<Row> {
+    positions
+        .iter()
+        .map(|i| (!row[*i].is_null()).then(|| row[*i].clone()))
+        .collect()
+}
+
+/// A [`BoundConstraint`] ready to check rows.
+enum Checked<'p> {
+    NotNull(usize),
+    Unique {
+        columns: &'p [usize],
+        primary: bool,
+        /// The keys of the rows of the table, read on the first row checked.
+        keys: Option<HashSet<Row>>,
+    },
+    Check {
+        condition: Expr<'p>,
+        columns: Vec<usize>,
+    },
+    ForeignKey {
+        columns: &'p [usize],
+        table: &'p str,
+        referenced: &'p [usize],
+        /// The keys of the rows of `table`, read on the first row checked.
+        keys: Option<HashSet<Row>>,
+    },
+}
+
+/// Checks the rows written into a table against its constraints, see [`Constraints::check`].
+pub(crate) struct Constraints<'p> {
+    table: &'p str,
+    layout: Layout,
+    storage: &'p dyn Storage,
+    evaluator: Evaluator<'p>,
+    constraints: Vec<(&'p str, Checked<'p>)>,
+}
+
+impl<'p> Constraints<'p> {
+    /// The checks of `constraints` for the rows of `table`, which have the `layout` of the
+    /// table.
+    /// # Errors
+    /// Returns an error if a condition of a `CHECK` can't be parsed, or names a column the
+    /// table doesn't have.
+    pub(crate) fn new(
+        table: &'p str,
+        constraints: &'p [BoundConstraint],
+        layout: Layout,
+        storage: &'p dyn Storage,
+        evaluator: Evaluator<'p>,
+    ) -> Result<Self, ExecError> {
+        let constraints = constraints
+            .iter()
+            .map(|constraint| {
+                let checked = match &constraint.kind {
+                    BoundKind::NotNull(column) => Checked::NotNull(*column),
+                    BoundKind::Unique { columns, primary } => Checked::Unique {
+                        columns,
+                        primary: *primary,
+                        keys: None,
+                    },
+                    BoundKind::Check(condition) => {
+                        let (_, condition) = Expr::parse(RawSpan::new(condition))?;
+                        let mut columns = Vec::new();
+                        for column in condition.columns() {
+                            let position = layout.index(&column).ok_or_else(|| {
+                                ExecError::ColumnNotFound((*column.name.fragment()).into())
+                            })?;
+                            if !columns.contains(&position) {
+                                columns.push(position);
+                            }
+                        }
+                        Checked::Check { condition, columns }
+                    }
+                    BoundKind::ForeignKey {
+                        columns,
+                        table,
+                        referenced,
+                    } => Checked::ForeignKey {
+                        columns,
+                        table,
+                        referenced,
+                        keys: None,
+                    },
+                };
+                Ok((&*constraint.name, checked))
+            })
+            .collect::<Result<_, ExecError>>()?;
+        Ok(Self {
+            table,
+            layout,
+            storage,
+            evaluator,
+            constraints,
+        })
+    }
+
+    /// The keys at `positions` of the rows of `table`.
+    fn read_keys(&self, table: &str, positions: &[usize]) -> Result<HashSet<Row>, ExecError> {
+        let mut keys = HashSet::new();
+        for row in self.storage.scan(table)? {
+            keys.extend(key(&row?, positions));
+        }
+        Ok(keys)
+    }
+
+    /// Checks `row`, about to be written into the table, and remembers its keys, so the
+    /// next rows of the statement are checked against it. The tables are read on the first
+    /// row, the rows written since by others aren't seen.
+    /// # Errors
+    /// Returns [`ExecError::ConstraintViolation`] for the first constraint the row
+    /// violates, or an error if a table can't be read or a `CHECK` can't be evaluated.
+    pub(crate) fn check(&mut self, row: &[Value]) -> Result<(), ExecError> {
+        let violation = |constraint: &str, kind, columns: &[usize]| {
+            ExecError::ConstraintViolation(Box::new(ConstraintViolation {
+                constraint: constraint.into(),
+                kind,
+                columns: columns
+                    .iter()
+                    .map(|i| self.layout.0[*i].name.clone())
+                    .collect(),
+                values: columns.iter().map(|i| row[*i].clone()).collect(),
+            }))
+        };
+        let mut read = Vec::new();
+        for (i, (_, checked)) in self.constraints.iter().enumerate() {
+            match checked {
+                Checked::Unique {
+                    columns,
+                    keys: None,
+                    ..
+                } => read.push((i, self.read_keys(self.table, columns)?)),
+                Checked::ForeignKey {
+                    table,
+                    referenced,
+                    keys: None,
+                    ..
+                } => read.push((i, self.read_keys(table, referenced)?)),
+                _ => {}
+            }
+        }
+        for (i, read) in read {
+            if let (_, Checked::Unique { keys, .. } | Checked::ForeignKey { keys, .. }) =
+                &mut self.constraints[i]
+            {
+                *keys = Some(read);
+            }
+        }
+
+        for (name, checked) in &self.constraints {
+            match checked {
+                Checked::NotNull(column) => {
+                    if row[*column].is_null() {
+                        return Err(violation(name, ViolationKind::NotNull, &[*column]));
+                    }
+                }
+                Checked::Unique {
+                    columns,
+                    primary,
+                    keys,
+                } => {
+                    let kind = if *primary {
+                        ViolationKind::PrimaryKey
+                    } else {
+                        ViolationKind::Unique
+                    };
+                    match key(row, columns) {
+                        Some(key) if keys.as_ref().is_some_and(|keys| keys.contains(&key)) => {
+                            return Err(violation(name, kind, columns));
+                        }
+                        None if *primary => return Err(violation(name, kind, columns)),
+                        _ => {}
+                    }
+                }
+                Checked::Check { condition, columns } => {
+                    let value = eval(self.evaluator, condition, &self.layout, row)?;
+                    if value == Value::Bool(false) {
+                        return Err(violation(name, ViolationKind::Check, columns));
+                    }
+                }
+                Checked::ForeignKey {
+                    columns,
+                    table,
+                    referenced,
+                    keys,
+                } => {
+                    let Some(key) = key(row, columns) else {
+                        continue;
+                    };
+                    // A row may reference itself.
+                    let found = keys.as_ref().is_some_and(|keys| keys.contains(&key))
+                        || (*table == self.table
+                            && referenced.iter().map(|i| &row[*i]).eq(key.iter()));
+                    if !found {
+                        return Err(violation(name, ViolationKind::ForeignKey, columns));
+                    }
+                }
+            }
+        }
+
+        for (_, checked) in &mut self.constraints {
+            match checked {
+                Checked::Unique {
+                    columns,
+                    keys: Some(keys),
+                    ..
+                } => keys.extend(key(row, columns)),
+                Checked::ForeignKey {
+                    table,
+                    referenced,
+                    keys: Some(keys),
+                    ..
+                } if *table == self.table => keys.extend(key(row, referenced)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::ast::statement::Statement;
+
+    use super::*;
+    use crate::{
+        executor::{execute, Config, Output},
+        storage::testing::TestStorage,
+    };
+
+    fn run(storage: &TestStorage, table_map: &TableMap, input: &str) -> Result<Output, ExecError> {
+        let (_, statement) = Statement::parse_with_table_map(table_map, RawSpan::new(input))
+            .unwrap_or_else(|e| panic!("{input}: {e:?}"));
+        execute(&statement, table_map, storage, &Config::default())
+    }
+
+    fn violation(output: Result<Output, ExecError>) -> ConstraintViolation {
+        match output {
+            Err(ExecError::ConstraintViolation(violation)) => *violation,
+            output => panic!("{output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_constraints() {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE users (id INT32 PRIMARY KEY, email VARCHAR(20) UNIQUE, age INT32 CHECK (age >= 18))",
+            "CREATE TABLE orders (id INT32, user_id INT32 REFERENCES users, parent INT32, total INT64, UNIQUE (user_id, total), FOREIGN KEY (parent) REFERENCES orders (id), CHECK (total > 0 OR total IS NULL))",
+        ]);
+        let run = |input| run(&storage, &table_map, input);
+        run("INSERT INTO users (id, email, age) VALUES (1, 'a@x', 30), (2, NULL, NULL), (3, NULL, 18)")
+            .unwrap();
+
+        assert_eq!(
+            violation(run(
+                "INSERT INTO users (id, email) VALUES (4, 'b@x'), (1, 'c@x')"
+            )),
+            ConstraintViolation {
+                constraint: "users_pkey".into(),
+                kind: ViolationKind::PrimaryKey,
+                columns: vec!["id".into()],
+                values: vec![Value::I32(1)],
+            }
+        );
+        // The rows of the statement are checked against each other.
+        let duplicate = violation(run(
+            "INSERT INTO users (id, email) VALUES (5, 'd@x'), (6, 'd@x')",
+        ));
+        assert_eq!(duplicate.constraint, "users_email_key".into());
+        assert_eq!(duplicate.kind, ViolationKind::Unique);
+        let check = violation(run("INSERT INTO users (id, age) VALUES (7, 17)"));
+        assert_eq!(
+            check.to_string(),
+            "Row violates CHECK constraint `users_age_check`: (age) = (17)"
+        );
+
+        // A key with a NULL references nothing, a row may reference itself.
+        run("INSERT INTO orders (id, user_id, parent, total) VALUES (1, 1, NULL, 10), (2, NULL, 2, NULL), (3, 2, 1, 10)")
+            .unwrap();
+        let foreign_key = violation(run(
+            "INSERT INTO orders (id, user_id, total) VALUES (4, 9, 5)",
+        ));
+        assert_eq!(
+            (foreign_key.constraint, foreign_key.kind, foreign_key.values),
+            (
+                "orders_user_id_fkey".into(),
+                ViolationKind::ForeignKey,
+                vec![Value::I32(9)]
+            )
+        );
+        assert_eq!(
+            violation(run("INSERT INTO orders (id, parent) VALUES (5, 8)")).constraint,
+            "orders_parent_fkey".into()
+        );
+        let unique = violation(run(
+            "INSERT INTO orders (id, user_id, total) VALUES (6, 2, 10)",
+        ));
+        assert_eq!(
+            (unique.constraint, unique.columns),
+            (
+                "orders_user_id_total_key".into(),
+                vec!["user_id".into(), "total".into()]
+            )
+        );
+        assert_eq!(
+            violation(run("INSERT INTO orders (id, total) VALUES (7, -1)")).constraint,
+            "orders_check".into()
+        );
+        assert_eq!(storage.row_count("public.orders"), Some(3));
+    }
+
+    #[test]
+    fn test_not_null() {
+        let (storage, mut table_map) =
+            TestStorage::new(&["CREATE TABLE t (id INT32 NOT NULL, name VARCHAR(10))"]);
+        // The parser rejects a NULL for the column, the executor checks the rows all the
+        // same, here with a catalog whose column is nullable but keeps the constraint.
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            RawSpan::new("INSERT INTO t (id, name) VALUES (NULL, 'a')")
+        )
+        .is_err());
+        let constraints = table_map.constraints(None, "t").to_vec();
+        let columns = table_map.get("t").unwrap().clone();
+        let mut nullable = columns.clone();
+        nullable.get_mut("id").unwrap().nullable = true;
+        table_map.insert("t".into(), nullable);
+        table_map.set_constraints(None, "t", constraints);
+        let violation = violation(run(
+            &storage,
+            &table_map,
+            "INSERT INTO t (id, name) VALUES (NULL, 'a')",
+        ));
+        assert_eq!(
+            violation.to_string(),
+            "Row violates NOT NULL constraint `t_id_not_null`: (id) = (NULL)"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use rs_db_parser::errors::{DecodeError, ErrorReport, ParseError, RawParseError};
+use rs_db_storage::error::StorageError;
+
+use crate::constraint::ConstraintViolation;
+
+/// Why a statement could not be run.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ExecError {
+    /// An expression failed, like a division by zero. The report points at the part of the
+    /// statement that failed.
+    #[error("{}", .0.message)]
+    Expression(ErrorReport),
+
+    /// An operation on values failed outside of an expression, like comparing the keys of
+    /// a sort.
+    #[error(transparent)]
+    Value(#[from] ParseError),
+
+    #[error("Table `{0}` not found")]
+    TableNotFound(Box<str>),
+
+    #[error("Column `{0}` not found")]
+    ColumnNotFound(Box<str>),
+
+    #[error("{0} is not supported by the executor")]
+    Unsupported(&'static str),
+
+    /// Reading or writing the temporary file of an operator that spilled to disk failed.
+    #[error("I/O error: {0}")]
+    Io(Box<str>),
+
+    /// A row read back from a temporary file is corrupted.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    /// The statement was cancelled with its
+    /// [`CancellationToken`](crate::cancel::CancellationToken), or ran past its timeout.
+    #[error("Query cancelled")]
+    QueryCancelled,
+
+    /// The transaction waited for a lock held by a transaction that waits for it, directly
+    /// or through others, see [`TransactionManager`](crate::transaction::TransactionManager).
+    /// It was rolled back so the others can go on.
+    #[error("Deadlock detected, the transaction was rolled back")]
+    Deadlock,
+
+    #[error("A transaction is already in progress")]
+    TransactionInProgress,
+
+    #[error("No transaction is in progress")]
+    NoTransaction,
+
+    #[error("Savepoint `{0}` not found")]
+    SavepointNotFound(Box<str>),
+
+    /// A row an `INSERT` writes breaks a constraint of its table.
+    #[error("{0}")]
+    ConstraintViolation(Box<ConstraintViolation>),
+
+    /// A row an `INSERT` writes into a partitioned table is in none of its partitions, as
+    /// its value is above the bound of the last range partition.
+    #[error("No partition of table `{0}` holds the row")]
+    NoPartition(Box<str>),
+
+    /// The storage failed to read or write a table, for another reason than the ones above.
+    #[error(transparent)]
+    Storage(StorageError),
+}
+
+impl From<StorageError> for ExecError {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::TableNotFound(table) => Self::TableNotFound(table),
+            StorageError::Io(err) => Self::Io(err),
+            StorageError::Decode(err) => Self::Decode(err),
+            err => Self::Storage(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ExecError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.to_string().into())
+    }
+}
+
+impl From<nom::Err<RawParseError<'_>>> for ExecError {
+    fn from(err: nom::Err<RawParseError<'_>>) -> Self {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => Self::Expression(err.into()),
+            nom::Err::Incomplete(_) => Self::Unsupported("Incomplete input"),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+    thread,
+};
+
+use crate::{
+    batch::{Batch, BATCH_SIZE},
+    error::ExecError,
+    vectorized::{BatchOperator, BoxedBatchOperator},
+};
+
+/// The rows of a morsel, the unit of work the threads of a parallel operator take from its
+/// input.
+pub(crate) const MORSEL_SIZE: usize = BATCH_SIZE;
+
+/// Applies `f` to each of `items` on up to `workers` threads, and returns the results in the
+/// order of the items. Each thread takes the next item as soon as it is done with one, so a
+/// slow item doesn't hold the others back. With a single worker or item, `f` runs on the
+/// calling thread.
+pub(crate) fn parallel_map<T: Send, R: Send>(
+    items: Vec<T>,
+    workers: usize,
+    f: impl Fn(T) -> R + Sync,
+) -> Vec<R> {
+    let workers = workers.min(items.len());
+    if workers <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+    let len = items.len();
+    let items = items
+        .into_iter()
+        .map(|item| Mutex::new(Some(item)))
+        .collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = Vec::new();
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                return done;
+            };
+            let item = item.lock().unwrap_or_else(PoisonError::into_inner).take();
+            if let Some(item) = item {
+                done.push((index, f(item)));
+            }
+        }
+    };
+    let mut results = thread::scope(|scope| {
+        let threads = (1..workers).map(|_| scope.spawn(work)).collect::<Vec<_>>();
+        let mut results = work();
+        for thread in threads {
+            results.extend(
+                thread
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+            );
+        }
+        results
+    });
+    debug_assert_eq!(results.len(), len);
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// What an [`Exchange`] does to each batch of its input, on any of its threads.
+pub(crate) trait Transform: Sync {
+    /// The batch made of `batch`. If a row fails, the batch made of the rows before it and
+    /// the error.
+    fn apply(&self, batch: Batch) -> (Batch, Option<ExecError>);
+}
+
+/// Runs a [`Transform`] on the batches of its input, the morsels, in parallel: it reads as
+/// many batches as it has workers, transforms them on as many threads, and returns the
+/// results in the order of the input. A failing batch ends the output, after the rows it
+/// kept, so the rows and the error are the ones of a single thread.
+pub(crate) struct Exchange<'p, T> {
+    input: BoxedBatchOperator<'p>,
+    transform: T,
+    workers: usize,
+    ready: VecDeque<(Batch, Option<ExecError>)>,
+    error: Option<ExecError>,
+}
+
+impl<'p, T: Transform> Exchange<'p, T> {
+    pub(crate) fn new(input: BoxedBatchOperator<'p>, transform: T, workers: usize) -> Self {
+        Self {
+            input,
+            transform,
+            workers: workers.max(1),
+            ready: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// Reads and transforms the next batches, returns whether there were any.
+    fn fill(&mut self) -> bool {
+        let mut batches = Vec::with_capacity(self.workers);
+        let mut error = None;
+        while batches.len() < self.workers {
+            match self.input.next_batch() {
+                Ok(Some(batch)) => batches.push(batch),
+                Ok(None) => break,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        if batches.is_empty() && error.is_none() {
+            return false;
+        }
+        let transform = &self.transform;
+        self.ready
+            .extend(parallel_map(batches, self.workers, |batch| {
+                transform.apply(batch)
+            }));
+        if error.is_some() {
+            self.ready.push_back((Batch::new(Vec::new(), 0), error));
+        }
+        true
+    }
+}
+
+impl<T: Transform> BatchOperator for Exchange<'_, T> {
+    fn next_batch(&mut self) -> Result<Option<Batch>, ExecError> {
+        loop {
+            if let Some(error) = self.error.take() {
+                return Err(error);
+            }
+            let Some((batch, error)) = self.ready.pop_front() else {
+                if self.fill() {
+                    continue;
+                }
+                return Ok(None);
+            };
+            if error.is_some() {
+                self.ready.clear();
+                self.error = error;
+            }
+            if !batch.is_empty() {
+                return Ok(Some(batch));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{ast::statement::Statement, parse::RawSpan, value::Value};
+
+    use super::*;
+    use crate::{
+        executor::{execute, Config, Output},
+        storage::{testing::TestStorage, Storage},
+        Row,
+    };
+
+    #[test]
+    fn test_parallel_map() {
+        let items = (0..100u64).collect::<Vec<_>>();
+        let squares = parallel_map(items.clone(), 4, |i| {
+            // The first items take longer, the other threads go on with the next ones.
+            if i < 4 {
+                thread::sleep(std::time::Duration::from_millis(10));
+            }
+            (i * i, thread::current().id())
+        });
+        assert_eq!(
+            squares
+                .iter()
+                .map(|(square, _)| *square)
+                .collect::<Vec<_>>(),
+            items.iter().map(|i| i * i).collect::<Vec<_>>()
+        );
+        let mut threads = squares.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+        threads.sort_by_key(|id| format!("{id:?}"));
+        threads.dedup();
+        assert!(threads.len() > 1, "{threads:?}");
+        assert_eq!(parallel_map(vec![1], 4, |i| i + 1), [2]);
+        assert!(parallel_map(Vec::<u8>::new(), 4, |i| i).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_execution() {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE t (a INT32, b INT64, c VARCHAR(10))",
+            "CREATE TABLE u (id INT64, tag VARCHAR(10))",
+        ]);
+        for i in 0..5000 {
+            let c = if i % 11 == 0 {
+                Value::Null
+            } else {
+                Value::VarChar(format!("c{}", i % 13).into())
+            };
+            storage
+                .insert(
+                    "public.t",
+                    vec![Value::I32(i), Value::I64(i64::from(i % 700)), c],
+                )
+                .unwrap();
+        }
+        for i in 0..300 {
+            let tag = Value::VarChar(format!("u{i}").into());
+            storage
+                .insert("public.u", vec![Value::I64(i * 2), tag])
+                .unwrap();
+        }
+        let run = |input: &str, config: &Config| -> Result<Vec<Row>, String> {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            match execute(&statement, &table_map, &storage, config) {
+                Ok(Output::Rows { rows, .. }) => Ok(rows),
+                Ok(output) => panic!("{output:?}"),
+                Err(error) => Err(error.to_string()),
+            }
+        };
+        let serial = Config {
+            max_parallelism: 1,
+            ..Config::default()
+        };
+        for input in [
+            "SELECT a, b FROM t WHERE a % 3 = 0 AND c <> 'c2'",
+            "SELECT c, count(*), sum(b), min(a) FROM t GROUP BY c",
+            "SELECT b % 97, count(DISTINCT c) FROM t WHERE a > 10 GROUP BY b % 97",
+            "SELECT count(*) FROM t WHERE a < 0",
+            "SELECT t.a, u.tag FROM t JOIN u ON t.b = u.id",
+            "SELECT a FROM t WHERE 100 / (a - 4000) > 0",
+            "SELECT a FROM t WHERE 100 / (a - 4000) > 0 LIMIT 10",
+            "SELECT sum(100 / (a - 4500)) FROM t",
+            "SELECT c, sum(b * 10000000000000000) FROM t GROUP BY c",
+        ] {
+            let expected = run(input, &serial);
+            for config in [
+                Config {
+                    max_parallelism: 4,
+                    ..Config::default()
+                },
+                Config {
+                    max_parallelism: 3,
+                    batch_size: 0,
+                    ..Config::default()
+                },
+                Config {
+                    max_parallelism: 4,
+                    memory_budget: 0,
+                    ..Config::default()
+                },
+            ] {
+                let rows = run(input, &config);
+                if config.memory_budget == 0 {
+                    // The rows spilled, and so the order of the groups and of the joined
+                    // rows, depend on the threads.
+                    let sorted = |rows: Result<Vec<Row>, String>| {
+                        rows.map(|mut rows| {
+                            rows.sort();
+                            rows
+                        })
+                    };
+                    assert_eq!(sorted(rows), sorted(expected.clone()), "{input}");
+                } else {
+                    assert_eq!(rows, expected, "{input} {config:?}");
+                }
+            }
+        }
+        assert!(run("SELECT a FROM t WHERE 100 / (a - 4000) > 0", &serial).is_err());
+    }
+}
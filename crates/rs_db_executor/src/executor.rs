@@ -0,0 +1,682 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use rs_db_parser::{
+    ast::{
+        commands::{analyze, backup, explain, explain::ExplainFormat, vacuum},
+        statement::Statement,
+    },
+    functions::FunctionRegistry,
+    parse::{TableMap, TableStatistics},
+    value::Value,
+};
+
+use crate::{
+    batch::BATCH_SIZE,
+    cancel::CancellationToken,
+    error::ExecError,
+    explain::{explain_json, explain_text, Profile},
+    operator::{build, Context},
+    optimizer::Optimizer,
+    partition::PartitionedStorage,
+    plan::Plan,
+    statistics,
+    storage::{table_key, Storage, CATALOG_FILE},
+    Row,
+};
+
+/// The resources the operators of a statement may use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// The bytes of rows an operator that holds many of them, like a hash join, keeps in
+    /// memory before it writes them to temporary files, see [`row_size`](crate::spill::row_size).
+    pub memory_budget: usize,
+    /// The directory of the temporary files.
+    pub spill_dir: PathBuf,
+    /// The rows the filters and projections over a scan process at once, as a
+    /// [`Batch`](crate::batch::Batch) whose columns are compared in tight loops. `0` runs
+    /// them a row at a time.
+    pub batch_size: usize,
+    /// The threads an operator may use. The filters and projections over a scan, the hash
+    /// aggregations and the builds of hash joins split their input in morsels they work on
+    /// in parallel, and return the rows a single thread would. `1` runs them on the thread
+    /// that runs the statement.
+    pub max_parallelism: usize,
+    /// How long a statement may run before it is cancelled, see
+    /// [`execute_with_cancellation`].
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            memory_budget: 64 * 1024 * 1024,
+            spill_dir: std::env::temp_dir(),
+            batch_size: BATCH_SIZE,
+            max_parallelism: std::thread::available_parallelism().map_or(1, usize::from),
+            timeout: None,
+        }
+    }
+}
+
+/// What running a statement produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// The rows of a `SELECT` or of the `RETURNING` clause of an `INSERT`.
+    Rows {
+        columns: Vec<Box<str>>,
+        rows: Vec<Row>,
+    },
+    /// The number of rows an `INSERT` without `RETURNING` added.
+    Inserted(u64),
+    /// The statistics `ANALYZE` collected, with the schema and the name of their table, to
+    /// store with [`TableMap::set_statistics`].
+    Analyzed(Vec<(Box<str>, Box<str>, TableStatistics)>),
+    /// The bytes `VACUUM` reclaimed from each of its tables, with their schema and name.
+    Vacuumed(Vec<(Box<str>, Box<str>, u64)>),
+    /// The bytes `BACKUP` wrote for each table, with their schema and name.
+    BackedUp(Vec<(Box<str>, Box<str>, u64)>),
+    /// A statement that returns nothing, like `BEGIN`, ran.
+    Done,
+}
+
+/// Plans `statement`, a `SELECT` or an `INSERT` parsed with `table_map`, optimizes the plan
+/// with the [standard](Optimizer::standard) rules and runs it against `storage` with the
+/// builtin functions, within `config`. An `ANALYZE` collects the statistics of its tables
+/// instead, a `VACUUM` reclaims the space of their removed rows, a `BACKUP` copies them to
+/// its directory, and an `EXPLAIN` returns the plan of its statement as rows of text, or a
+/// single row of JSON, see [`explain_text`] and [`explain_json`]. The partitioned tables of
+/// `table_map` are read and written through their partitions, see [`PartitionedStorage`].
+/// # Errors
+/// Returns an error if the statement can't be planned, see [`Plan::from_statement`], or if
+/// running it fails.
+pub fn execute(
+    statement: &Statement<'_>,
+    table_map: &TableMap,
+    storage: &dyn Storage,
+    config: &Config,
+) -> Result<Output, ExecError> {
+    execute_with_cancellation(
+        statement,
+        table_map,
+        storage,
+        config,
+        &CancellationToken::new(),
+    )
+}
+
+/// Runs `statement` like [`execute`], until `cancellation` is cancelled or the timeout of
+/// `config` has passed.
+/// # Errors
+/// Returns [`ExecError::QueryCancelled`] if the statement is cancelled before it is done,
+/// or the errors of [`execute`].
+pub fn execute_with_cancellation(
+    statement: &Statement<'_>,
+    table_map: &TableMap,
+    storage: &dyn Storage,
+    config: &Config,
+    cancellation: &CancellationToken,
+) -> Result<Output, ExecError> {
+    let cancellation = &with_timeout(cancellation, config);
+    let storage = &PartitionedStorage::new(storage, table_map);
+    if let Statement::Analyze(analyze) = statement {
+        return self::analyze(analyze, table_map, storage, cancellation);
+    }
+    if let Statement::Vacuum(vacuum) = statement {
+        return self::vacuum(vacuum, table_map, storage, cancellation);
+    }
+    if let Statement::Backup(backup) = statement {
+        return self::backup(backup, table_map, storage, cancellation);
+    }
+    if let Statement::Explain(explain) = statement {
+        return self::explain(explain, table_map, storage, config, cancellation);
+    }
+    let functions = FunctionRegistry::builtins();
+    let plan = Optimizer::standard(functions, storage)
+        .optimize(Plan::from_statement(statement, table_map)?);
+    run(&plan, storage, config, cancellation)
+}
+
+/// `cancellation`, also cancelled once the timeout of `config` has passed.
+pub(crate) fn with_timeout(cancellation: &CancellationToken, config: &Config) -> CancellationToken {
+    match config.timeout {
+        Some(timeout) => cancellation.with_timeout(timeout),
+        None => cancellation.clone(),
+    }
+}
+
+/// Runs the optimized `plan` of a `SELECT` or an `INSERT`.
+pub(crate) fn run(
+    plan: &Plan<'_>,
+    storage: &dyn Storage,
+    config: &Config,
+    cancellation: &CancellationToken,
+) -> Result<Output, ExecError> {
+    let context = Context {
+        storage,
+        functions: FunctionRegistry::builtins(),
+        config,
+        profile: None,
+        cancellation,
+    };
+    let mut operator = build(plan, context)?;
+    let mut rows = Vec::new();
+    while let Some(row) = operator.next()? {
+        rows.push(row);
+    }
+    if let Plan::Insert { .. } = plan {
+        return Ok(Output::Inserted(rows.len() as u64));
+    }
+    Ok(Output::Rows {
+        columns: plan.layout().names(),
+        rows,
+    })
+}
+
+fn explain(
+    statement: &explain::Statement<'_>,
+    table_map: &TableMap,
+    storage: &dyn Storage,
+    config: &Config,
+    cancellation: &CancellationToken,
+) -> Result<Output, ExecError> {
+    let functions = FunctionRegistry::builtins();
+    let plan = Optimizer::standard(functions, storage)
+        .optimize(Plan::from_statement(&statement.statement, table_map)?);
+    let profile = Profile::default();
+    if statement.analyze {
+        let context = Context {
+            storage,
+            functions,
+            config,
+            profile: Some(&profile),
+            cancellation,
+        };
+        let mut operator = build(&plan, context)?;
+        while operator.next()?.is_some() {}
+    }
+    let stats = statement.analyze.then(|| profile.nodes());
+    let rows = match statement.format {
+        ExplainFormat::Text => explain_text(&plan, storage, stats.as_deref())
+            .into_iter()
+            .map(|line| vec![Value::VarChar(line.into())])
+            .collect(),
+        ExplainFormat::Json => {
+            let json = explain_json(&plan, storage, stats.as_deref());
+            vec![vec![Value::VarChar(json.to_string().into())]]
+        }
+    };
+    Ok(Output::Rows {
+        columns: vec!["QUERY PLAN".into()],
+        rows,
+    })
+}
+
+fn analyze(
+    statement: &analyze::Statement<'_>,
+    table_map: &TableMap,
+    storage: &dyn Storage,
+    cancellation: &CancellationToken,
+) -> Result<Output, ExecError> {
+    let mut tables = match &statement.table_name {
+        Some(name) => {
+            let schema = name
+                .schema_name()
+                .unwrap_or_else(|| table_map.default_schema());
+            let columns = name
+                .lookup(table_map)
+                .map_err(|_| ExecError::TableNotFound(table_key(name, table_map)))?;
+            vec![(schema, *name.name.fragment(), columns)]
+        }
+        None => table_map
+            .schemas()
+            .flat_map(|(schema, tables)| {
+                tables
+                    .iter()
+                    .map(move |(table, columns)| (schema, table.as_ref(), columns))
+            })
+            .collect(),
+    };
+    tables.sort_by_key(|(schema, table, _)| (*schema, *table));
+    let mut analyzed = Vec::with_capacity(tables.len());
+    for (schema, table, columns) in tables {
+        let mut names = columns.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        let statistics =
+            statistics::analyze(storage, &format!("{schema}.{table}"), &names, cancellation)?;
+        analyzed.push((schema.into(), table.into(), statistics));
+    }
+    Ok(Output::Analyzed(analyzed))
+}
+
+fn vacuum(
+    statement: &vacuum::Statement<'_>,
+    table_map: &TableMap,
+    storage: &dyn Storage,
+    cancellation: &CancellationToken,
+) -> Result<Output, ExecError> {
+    let mut tables = match &statement.table_name {
+        Some(name) => {
+            let schema = name
+                .schema_name()
+                .unwrap_or_else(|| table_map.default_schema());
+            name.lookup(table_map)
+                .map_err(|_| ExecError::TableNotFound(table_key(name, table_map)))?;
+            vec![(schema, *name.name.fragment())]
+        }
+        None => table_map
+            .schemas()
+            .flat_map(|(schema, tables)| tables.keys().map(move |table| (schema, table.as_ref())))
+            .collect(),
+    };
+    tables.sort_unstable();
+    let mut vacuumed = Vec::with_capacity(tables.len());
+    for (schema, table) in tables {
+        cancellation.check()?;
+        let bytes = storage.vacuum(&format!("{schema}.{table}"))?;
+        vacuumed.push((schema.into(), table.into(), bytes));
+    }
+    Ok(Output::Vacuumed(vacuumed))
+}
+
+fn backup(
+    statement: &backup::Statement<'_>,
+    table_map: &TableMap,
+    storage: &dyn Storage,
+    cancellation: &CancellationToken,
+) -> Result<Output, ExecError> {
+    let dir = Path::new(&*statement.path.1);
+    let mut tables = table_map
+        .schemas()
+        .flat_map(|(schema, tables)| tables.keys().map(move |table| (schema, table.as_ref())))
+        .collect::<Vec<_>>();
+    tables.sort_unstable();
+    cancellation.check()?;
+    // The catalog is written first, the copies of the tables are only restored with it.
+    std::fs::create_dir_all(dir)?;
+    let catalog = table_map
+        .to_json()
+        .map_err(|error| ExecError::Io(error.to_string().into()))?;
+    std::fs::File::create_new(dir.join(CATALOG_FILE))?.write_all(catalog.as_bytes())?;
+    let keys = tables
+        .iter()
+        .map(|(schema, table)| format!("{schema}.{table}"))
+        .collect::<Vec<_>>();
+    let written = storage.backup(&keys.iter().map(AsRef::as_ref).collect::<Vec<_>>(), dir)?;
+    let backed_up = tables
+        .into_iter()
+        .zip(written)
+        .map(|((schema, table), bytes)| (schema.into(), table.into(), bytes))
+        .collect();
+    Ok(Output::BackedUp(backed_up))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{decimal::Decimal, errors::ParseError, parse::RawSpan, value::Value};
+
+    use super::*;
+    use crate::storage::{testing::TestStorage, RowIter};
+
+    fn run(input: &str, table_map: &TableMap, storage: &TestStorage) -> Result<Output, ExecError> {
+        let (_, statement) = Statement::parse_with_table_map(table_map, RawSpan::new(input))
+            .unwrap_or_else(|e| panic!("{input}: {e:?}"));
+        execute(&statement, table_map, storage, &Config::default())
+    }
+
+    fn rows(output: Result<Output, ExecError>) -> Vec<Row> {
+        match output.unwrap() {
+            Output::Rows { rows, .. } => rows,
+            output => panic!("{output:?}"),
+        }
+    }
+
+    fn setup() -> (TestStorage, TableMap) {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE users (id INT32, name VARCHAR(10), active BOOL DEFAULT TRUE)",
+            "CREATE TABLE orders (id INT32, user_id INT32, total INT64)",
+        ]);
+        for input in [
+            "INSERT INTO users (id, name) VALUES (1, 'ana'), (2, 'bob'), (3, 'cid')",
+            "INSERT INTO orders (id, user_id, total) VALUES (10, 1, 5), (11, 1, 7), (12, 2, 3)",
+        ] {
+            run(input, &table_map, &storage).unwrap();
+        }
+        (storage, table_map)
+    }
+
+    #[test]
+    fn test_execute_insert() {
+        let (storage, table_map) = setup();
+        assert_eq!(
+            run(
+                "INSERT INTO users (id, name, active) VALUES (4, 'dan', FALSE)",
+                &table_map,
+                &storage
+            ),
+            Ok(Output::Inserted(1))
+        );
+        assert_eq!(
+            run(
+                "INSERT INTO users (id) VALUES (5) RETURNING name, active",
+                &table_map,
+                &storage
+            ),
+            Ok(Output::Rows {
+                columns: vec!["name".into(), "active".into()],
+                rows: vec![vec![Value::Null, Value::Bool(true)]],
+            })
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT id FROM users WHERE NOT active",
+                &table_map,
+                &storage
+            )),
+            [[Value::I32(4)]]
+        );
+    }
+
+    #[test]
+    fn test_execute_select() {
+        let (storage, table_map) = setup();
+        assert_eq!(
+            run(
+                "SELECT name, id * 10 AS big FROM users WHERE id > 1 ORDER BY big DESC",
+                &table_map,
+                &storage
+            ),
+            Ok(Output::Rows {
+                columns: vec!["name".into(), "big".into()],
+                rows: vec![
+                    vec![Value::VarChar("cid".into()), Value::I128(30)],
+                    vec![Value::VarChar("bob".into()), Value::I128(20)],
+                ],
+            })
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT id FROM users ORDER BY id LIMIT 1 OFFSET 1",
+                &table_map,
+                &storage
+            )),
+            [[Value::I32(2)]]
+        );
+        assert_eq!(
+            run("SELECT 1 / (id - 1) FROM users", &table_map, &storage)
+                .unwrap_err()
+                .to_string(),
+            ParseError::DivisionByZero.to_string()
+        );
+    }
+
+    #[test]
+    fn test_execute_join() {
+        let (storage, table_map) = setup();
+        assert_eq!(
+            rows(run(
+                "SELECT u.name, o.total FROM users u JOIN orders o ON o.user_id = u.id \
+                 ORDER BY o.total",
+                &table_map,
+                &storage
+            )),
+            [
+                [Value::VarChar("bob".into()), Value::I64(3)],
+                [Value::VarChar("ana".into()), Value::I64(5)],
+                [Value::VarChar("ana".into()), Value::I64(7)],
+            ]
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT u.name, o.id FROM users u LEFT JOIN orders o ON o.user_id = u.id \
+                 WHERE o.id IS NULL",
+                &table_map,
+                &storage
+            )),
+            [[Value::VarChar("cid".into()), Value::Null]]
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT users.id, orders.id FROM users RIGHT JOIN orders USING (id)",
+                &table_map,
+                &storage
+            ))
+            .len(),
+            3
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT * FROM users CROSS JOIN orders",
+                &table_map,
+                &storage
+            ))
+            .len(),
+            9
+        );
+    }
+
+    #[test]
+    fn test_execute_coalesce() {
+        let (storage, table_map) = TestStorage::new(&["CREATE TABLE t (a INT32, b INT16)"]);
+        run(
+            "INSERT INTO t (a, b) VALUES (1, 2), (NULL, 3), (NULL, NULL)",
+            &table_map,
+            &storage,
+        )
+        .unwrap();
+        // The values of a column have its type, whichever argument they come from.
+        assert_eq!(
+            rows(run(
+                "SELECT coalesce(a, 0), coalesce(a, b) FROM t ORDER BY b",
+                &table_map,
+                &storage
+            )),
+            [
+                [Value::I128(1), Value::I128(1)],
+                [Value::I128(0), Value::I128(3)],
+                [Value::I128(0), Value::Null],
+            ]
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT coalesce(a, a) FROM t WHERE b = 2",
+                &table_map,
+                &storage
+            )),
+            [[Value::I32(1)]]
+        );
+    }
+
+    #[test]
+    fn test_execute_aggregate() {
+        let (storage, table_map) = setup();
+        assert_eq!(
+            run(
+                "SELECT u.name, count(*) AS n, sum(o.total), avg(o.total), max(o.id) \
+                 FROM users u JOIN orders o ON o.user_id = u.id \
+                 GROUP BY u.name HAVING count(*) > 0 ORDER BY n DESC",
+                &table_map,
+                &storage
+            ),
+            Ok(Output::Rows {
+                columns: vec![
+                    "name".into(),
+                    "n".into(),
+                    "sum(o.total)".into(),
+                    "avg(o.total)".into(),
+                    "max(o.id)".into()
+                ],
+                rows: vec![
+                    vec![
+                        Value::VarChar("ana".into()),
+                        Value::I64(2),
+                        Value::I128(12),
+                        Value::Decimal(Decimal::from_integer(6).unwrap()),
+                        Value::I32(11)
+                    ],
+                    vec![
+                        Value::VarChar("bob".into()),
+                        Value::I64(1),
+                        Value::I128(3),
+                        Value::Decimal(Decimal::from_integer(3).unwrap()),
+                        Value::I32(12)
+                    ],
+                ],
+            })
+        );
+        assert_eq!(
+            rows(run(
+                "SELECT user_id % 2, count(DISTINCT user_id), min(total) FROM orders \
+                 GROUP BY user_id % 2 ORDER BY user_id % 2",
+                &table_map,
+                &storage
+            )),
+            [
+                [Value::I128(0), Value::I64(1), Value::I64(3)],
+                [Value::I128(1), Value::I64(1), Value::I64(5)],
+            ]
+        );
+        // Without GROUP BY there is a row even without rows.
+        assert_eq!(
+            rows(run(
+                "SELECT count(*), sum(total) FROM orders WHERE total > 100",
+                &table_map,
+                &storage
+            )),
+            [[Value::I64(0), Value::Null]]
+        );
+    }
+
+    /// A table of endless rows, the token is cancelled at the 100th.
+    struct Endless(CancellationToken);
+
+    impl Storage for Endless {
+        fn scan(&self, _table: &str) -> Result<RowIter<'_>, ExecError> {
+            let token = self.0.clone();
+            Ok(Box::new((0..).map(move |i| {
+                if i == 100 {
+                    token.cancel();
+                }
+                Ok(vec![Value::I32(i)])
+            })))
+        }
+
+        fn insert(&self, _table: &str, _row: Row) -> Result<(), ExecError> {
+            Err(ExecError::Unsupported("INSERT"))
+        }
+    }
+
+    #[test]
+    fn test_execute_cancelled() {
+        let (_, table_map) = TestStorage::new(&["CREATE TABLE t (a INT32)"]);
+        for input in [
+            "SELECT a + 1 FROM t WHERE a > 5",
+            "SELECT count(*) FROM t",
+            "SELECT x.a FROM t AS x JOIN t AS y ON x.a = y.a",
+            "ANALYZE t",
+        ] {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            for batch_size in [0, BATCH_SIZE] {
+                let token = CancellationToken::new();
+                let config = Config {
+                    batch_size,
+                    ..Config::default()
+                };
+                let storage = Endless(token.clone());
+                assert_eq!(
+                    execute_with_cancellation(&statement, &table_map, &storage, &config, &token),
+                    Err(ExecError::QueryCancelled),
+                    "{input}"
+                );
+            }
+            let timeout = Config {
+                timeout: Some(Duration::from_millis(20)),
+                ..Config::default()
+            };
+            let storage = Endless(CancellationToken::new());
+            assert_eq!(
+                execute(&statement, &table_map, &storage, &timeout),
+                Err(ExecError::QueryCancelled),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_execute_analyze() {
+        let (storage, mut table_map) = setup();
+        let Ok(Output::Analyzed(analyzed)) = run("ANALYZE", &table_map, &storage) else {
+            panic!("ANALYZE");
+        };
+        assert_eq!(
+            analyzed
+                .iter()
+                .map(|(schema, table, statistics)| (&**schema, &**table, statistics.row_count))
+                .collect::<Vec<_>>(),
+            [("public", "orders", 3), ("public", "users", 3)]
+        );
+        assert_eq!(analyzed[0].2.columns["user_id"].distinct, 2);
+        for (schema, table, statistics) in analyzed {
+            assert!(table_map.set_statistics(Some(&schema), &table, statistics));
+        }
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            RawSpan::new("SELECT id FROM orders WHERE user_id = 1"),
+        )
+        .unwrap();
+        let plan = Plan::from_statement(&statement, &table_map).unwrap();
+        assert_eq!(plan.estimated_rows(&storage), Some(2));
+        let Ok(Output::Analyzed(analyzed)) = run("ANALYZE users", &table_map, &storage) else {
+            panic!("ANALYZE users");
+        };
+        assert_eq!(analyzed.len(), 1);
+        assert_eq!(
+            analyzed[0].2.columns["name"].min,
+            Some(Value::VarChar("ana".into()))
+        );
+    }
+
+    #[test]
+    fn test_execute_explain() {
+        let (storage, table_map) = setup();
+        let Ok(Output::Rows {
+            columns,
+            rows: plan,
+        }) = run("EXPLAIN SELECT name FROM users", &table_map, &storage)
+        else {
+            panic!("EXPLAIN");
+        };
+        assert_eq!(columns, ["QUERY PLAN".into()]);
+        assert_eq!(
+            plan,
+            [
+                vec![Value::VarChar("Scan (estimated rows=3)".into())],
+                vec![Value::VarChar("    Table: public.users".into())],
+                vec![Value::VarChar("    Columns: name".into())],
+            ]
+        );
+        // The statement runs with ANALYZE.
+        let output = rows(run(
+            "EXPLAIN ANALYZE FORMAT JSON INSERT INTO users (id) VALUES (4)",
+            &table_map,
+            &storage,
+        ));
+        let Value::VarChar(json) = &output[0][0] else {
+            panic!("{output:?}");
+        };
+        let json = serde_json::from_str::<serde_json::Value>(json.as_ref()).unwrap();
+        assert_eq!(
+            (&json["node"], &json["rows"]),
+            (&"Insert".into(), &1.into())
+        );
+        assert_eq!(
+            rows(run("SELECT COUNT(*) FROM users", &table_map, &storage)),
+            [[Value::I64(4)]]
+        );
+    }
+}
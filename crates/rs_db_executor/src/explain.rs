@@ -0,0 +1,590 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::Bound,
+    time::{Duration, Instant},
+};
+
+use rs_db_parser::{
+    ast::{
+        commands::select::SortOrder,
+        expr::Expr,
+        table::JoinKind,
+        visit::{walk_expr_mut, VisitorMut},
+    },
+    parse::RawSpan,
+};
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    batch::Batch,
+    error::ExecError,
+    operator::{BoxedOperator, Operator},
+    plan::{AggregateCall, Field, JoinAlgorithm, Plan, ProjectItem},
+    storage::Storage,
+    vectorized::{BatchOperator, BoxedBatchOperator},
+    Row,
+};
+
+/// What the operator of a node of a plan did while it ran, see [`Profile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeStats {
+    /// The rows it returned.
+    pub rows: u64,
+    /// The time spent in its `next` calls, the one of its inputs included.
+    pub time: Duration,
+}
+
+/// The [`NodeStats`] of the operators [`build`](crate::operator::build) builds with it, in
+/// the order of the nodes of the plan: a node before its inputs, the left one first for a
+/// join.
+#[derive(Debug, Default)]
+pub struct Profile {
+    nodes: RefCell<Vec<NodeStats>>,
+}
+
+impl Profile {
+    /// Adds a node, returning its position.
+    pub(crate) fn add_node(&self) -> usize {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(NodeStats::default());
+        nodes.len() - 1
+    }
+
+    /// Wraps the operator of the node at `node` so it records its stats.
+    pub(crate) fn instrument<'p>(
+        &'p self,
+        node: usize,
+        input: BoxedOperator<'p>,
+    ) -> BoxedOperator<'p> {
+        Box::new(Profiled {
+            profile: self,
+            node,
+            input,
+        })
+    }
+
+    /// Wraps the vectorized operator of the node at `node` so it records its stats.
+    pub(crate) fn instrument_batch<'p>(
+        &'p self,
+        node: usize,
+        input: BoxedBatchOperator<'p>,
+    ) -> BoxedBatchOperator<'p> {
+        Box::new(ProfiledBatch {
+            profile: self,
+            node,
+            input,
+        })
+    }
+
+    #[must_use]
+    pub fn nodes(&self) -> Vec<NodeStats> {
+        self.nodes.borrow().clone()
+    }
+}
+
+struct Profiled<'p> {
+    profile: &'p Profile,
+    node: usize,
+    input: BoxedOperator<'p>,
+}
+
+impl Operator for Profiled<'_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        let start = Instant::now();
+        let row = self.input.next();
+        let stats = &mut self.profile.nodes.borrow_mut()[self.node];
+        stats.time += start.elapsed();
+        if let Ok(Some(_)) = row {
+            stats.rows += 1;
+        }
+        row
+    }
+}
+
+struct ProfiledBatch<'p> {
+    profile: &'p Profile,
+    node: usize,
+    input: BoxedBatchOperator<'p>,
+}
+
+impl BatchOperator for ProfiledBatch<'_> {
+    fn next_batch(&mut self) -> Result<Option<Batch>, ExecError> {
+        let start = Instant::now();
+        let batch = self.input.next_batch();
+        let stats = &mut self.profile.nodes.borrow_mut()[self.node];
+        stats.time += start.elapsed();
+        if let Ok(Some(batch)) = &batch {
+            stats.rows += batch.len() as u64;
+        }
+        batch
+    }
+}
+
+/// The expressions the columns computed by the aggregations of a plan stand for.
+type Computed<'a> = HashMap<Field, Expr<'a>>;
+
+fn computed<'a>(plan: &Plan<'a>, computed: &mut Computed<'a>) {
+    if let Plan::Aggregate {
+        group_by,
+        aggregates,
+        layout,
+        ..
+    } = plan
+    {
+        let calls = aggregates.iter().map(aggregate_expr);
+        for (field, expr) in layout.0.iter().zip(group_by.iter().cloned().chain(calls)) {
+            if field.is_computed() {
+                computed.insert(field.clone(), expr);
+            }
+        }
+    }
+    for child in plan.children() {
+        self::computed(child, computed);
+    }
+}
+
+/// Puts back the expressions of the computed columns, so they show as written.
+struct Uncompute<'c, 'a>(&'c Computed<'a>);
+
+impl<'a> VisitorMut<'a> for Uncompute<'_, 'a> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+        if let Expr::Column(column) = expr {
+            if let Some(original) = Field::of_computed(column).and_then(|f| self.0.get(&f)) {
+                *expr = original.clone();
+            }
+            return;
+        }
+        walk_expr_mut(self, expr);
+    }
+}
+
+fn show<'a>(expr: &Expr<'a>, computed: &Computed<'a>) -> String {
+    let mut expr = expr.clone();
+    Uncompute(computed).visit_expr_mut(&mut expr);
+    expr.to_string()
+}
+
+/// The name of a node and what it does, as labels and values.
+fn describe<'a>(
+    plan: &Plan<'a>,
+    computed: &Computed<'a>,
+) -> (&'static str, Vec<(&'static str, String)>) {
+    let list = |items: Vec<String>| items.join(", ");
+    let show = |expr: &Expr<'a>| show(expr, computed);
+    let field_name = |field: &Field| match computed.get(field) {
+        Some(expr) => expr.to_string(),
+        None => field_name(field),
+    };
+    match plan {
+        Plan::Scan {
+            table,
+            layout,
+            columns,
+            ranges,
+            partitions,
+            ..
+        } => {
+            let mut details = vec![("Table", table.to_string())];
+            // The columns are only listed when some are left out.
+            if columns.is_some() {
+                details.push(("Columns", layout.names().join(", ")));
+            }
+            if !ranges.is_empty() {
+                let ranges = ranges
+                    .iter()
+                    .map(|range| {
+                        let position = match columns {
+                            Some(columns) => columns.iter().position(|c| *c == range.column),
+                            None => Some(range.column),
+                        };
+                        let column = position
+                            .and_then(|position| layout.0.get(position))
+                            .map_or_else(|| format!("#{}", range.column), |f| f.name.to_string());
+                        bounds(&column, &range.lower, &range.upper, &show)
+                    })
+                    .collect();
+                details.push(("Ranges", list(ranges)));
+            }
+            if let Some(partitions) = partitions {
+                let prefix = format!("{table}#");
+                let names = partitions
+                    .iter()
+                    .map(|partition| partition.trim_start_matches(&prefix).to_string())
+                    .collect();
+                details.push(("Partitions", list(names)));
+            }
+            ("Scan", details)
+        }
+        Plan::IndexScan {
+            table,
+            index,
+            key,
+            layout,
+            columns,
+            index_only,
+            ..
+        } => {
+            let key = index
+                .columns
+                .iter()
+                .zip(key)
+                .map(|(column, expr)| format!("{column} = {}", show(expr)))
+                .collect();
+            let mut details = vec![
+                ("Table", table.to_string()),
+                ("Index", index.name.to_string()),
+                ("Key", list(key)),
+            ];
+            if columns.is_some() {
+                details.push(("Columns", layout.names().join(", ")));
+            }
+            let name = if *index_only {
+                "Index Only Scan"
+            } else {
+                "Index Scan"
+            };
+            (name, details)
+        }
+        Plan::IndexRangeScan {
+            table,
+            index,
+            lower,
+            upper,
+            layout,
+            columns,
+            index_only,
+            ..
+        } => {
+            let mut details = vec![
+                ("Table", table.to_string()),
+                ("Index", index.name.to_string()),
+                ("Range", bounds(&index.columns[0], lower, upper, &show)),
+            ];
+            if columns.is_some() {
+                details.push(("Columns", layout.names().join(", ")));
+            }
+            let name = if *index_only {
+                "Index Only Range Scan"
+            } else {
+                "Index Range Scan"
+            };
+            (name, details)
+        }
+        Plan::Values { rows, .. } => ("Values", vec![("Rows", rows.len().to_string())]),
+        Plan::Filter { predicate, .. } => ("Filter", vec![("Predicate", show(predicate))]),
+        Plan::Project { input, items, .. } => {
+            let layout = input.layout();
+            let items = items
+                .iter()
+                .map(|item| match item {
+                    ProjectItem::Column(index) => field_name(&layout.0[*index]),
+                    ProjectItem::Expr(expr) => show(expr),
+                })
+                .collect();
+            ("Project", vec![("Columns", list(items))])
+        }
+        Plan::Join {
+            kind,
+            algorithm,
+            left,
+            right,
+            keys,
+            condition,
+        } => {
+            let name = match algorithm {
+                JoinAlgorithm::NestedLoop => "Nested Loop Join",
+                JoinAlgorithm::Hash => "Hash Join",
+                JoinAlgorithm::Merge => "Merge Join",
+            };
+            let kind = match kind {
+                JoinKind::Inner => "inner",
+                JoinKind::Left => "left",
+                JoinKind::Right => "right",
+                JoinKind::Full => "full",
+                JoinKind::Cross => "cross",
+            };
+            let mut details = vec![("Kind", kind.to_string())];
+            if !keys.is_empty() {
+                let (left, right) = (left.layout(), right.layout());
+                let keys = keys
+                    .iter()
+                    .map(|(l, r)| {
+                        format!("{} = {}", field_name(&left.0[*l]), field_name(&right.0[*r]))
+                    })
+                    .collect();
+                details.push(("Keys", list(keys)));
+            }
+            if let Some(condition) = condition {
+                details.push(("Condition", show(condition)));
+            }
+            (name, details)
+        }
+        Plan::Aggregate {
+            group_by,
+            aggregates,
+            ..
+        } => {
+            let mut details = Vec::new();
+            if !group_by.is_empty() {
+                let group_by = group_by.iter().map(show).collect();
+                details.push(("Group By", list(group_by)));
+            }
+            if !aggregates.is_empty() {
+                let aggregates = aggregates
+                    .iter()
+                    .map(|call| aggregate_expr(call).to_string())
+                    .collect();
+                details.push(("Aggregates", list(aggregates)));
+            }
+            ("Hash Aggregate", details)
+        }
+        Plan::Sort { keys, .. } => {
+            let keys = keys
+                .iter()
+                .map(|key| {
+                    let order = match key.order {
+                        SortOrder::Asc => "ASC",
+                        SortOrder::Desc => "DESC",
+                    };
+                    let nulls = if key.nulls_first { "FIRST" } else { "LAST" };
+                    format!("{} {order} NULLS {nulls}", show(&key.expr))
+                })
+                .collect();
+            ("Sort", vec![("Keys", list(keys))])
+        }
+        Plan::Limit { limit, offset, .. } => {
+            let mut details = Vec::new();
+            if let Some(limit) = limit {
+                details.push(("Limit", limit.to_string()));
+            }
+            if *offset > 0 {
+                details.push(("Offset", offset.to_string()));
+            }
+            ("Limit", details)
+        }
+        Plan::Insert { table, .. } => ("Insert", vec![("Table", table.to_string())]),
+    }
+}
+
+/// The comparisons of `column` with the bounds of a range, joined with `AND`.
+fn bounds<'a>(
+    column: &str,
+    lower: &Bound<Box<Expr<'a>>>,
+    upper: &Bound<Box<Expr<'a>>>,
+    show: &dyn Fn(&Expr<'a>) -> String,
+) -> String {
+    let mut range = Vec::new();
+    match lower {
+        Bound::Included(expr) => range.push(format!("{column} >= {}", show(expr))),
+        Bound::Excluded(expr) => range.push(format!("{column} > {}", show(expr))),
+        Bound::Unbounded => {}
+    }
+    match upper {
+        Bound::Included(expr) => range.push(format!("{column} <= {}", show(expr))),
+        Bound::Excluded(expr) => range.push(format!("{column} < {}", show(expr))),
+        Bound::Unbounded => {}
+    }
+    range.join(" AND ")
+}
+
+fn field_name(field: &Field) -> String {
+    match &field.table {
+        Some(table) if !field.is_computed() => format!("{table}.{}", field.name),
+        _ => field.name.to_string(),
+    }
+}
+
+/// The expression of an aggregate call, as the query wrote it.
+fn aggregate_expr<'a>(call: &AggregateCall<'a>) -> Expr<'a> {
+    Expr::Aggregate {
+        function: (RawSpan::new(""), call.function),
+        distinct: call.distinct,
+        arg: call.arg.clone().map(Box::new),
+    }
+}
+
+/// The plan as an indented tree: a line per node with its estimated rows, followed by its
+/// details and its inputs, indented. With the `stats` of a [`Profile`] of the plan, a node
+/// also has the rows it returned and the time it took.
+#[must_use]
+pub fn explain_text(
+    plan: &Plan<'_>,
+    storage: &dyn Storage,
+    stats: Option<&[NodeStats]>,
+) -> Vec<String> {
+    fn write<'a>(
+        plan: &Plan<'a>,
+        computed: &Computed<'a>,
+        storage: &dyn Storage,
+        stats: &mut Option<std::slice::Iter<'_, NodeStats>>,
+        depth: usize,
+        lines: &mut Vec<String>,
+    ) {
+        let indent = "  ".repeat(depth);
+        let (name, details) = describe(plan, computed);
+        let mut metrics = Vec::new();
+        if let Some(rows) = plan.estimated_rows(storage) {
+            metrics.push(format!("estimated rows={rows}"));
+        }
+        if let Some(node) = stats.as_mut().and_then(Iterator::next) {
+            metrics.push(format!("rows={}", node.rows));
+            metrics.push(format!("time={:.3} ms", node.time.as_secs_f64() * 1000.0));
+        }
+        if metrics.is_empty() {
+            lines.push(format!("{indent}{name}"));
+        } else {
+            lines.push(format!("{indent}{name} ({})", metrics.join(", ")));
+        }
+        for (label, value) in details {
+            lines.push(format!("{indent}    {label}: {value}"));
+        }
+        for child in plan.children() {
+            write(child, computed, storage, stats, depth + 1, lines);
+        }
+    }
+    let mut exprs = Computed::new();
+    computed(plan, &mut exprs);
+    let mut lines = Vec::new();
+    write(
+        plan,
+        &exprs,
+        storage,
+        &mut stats.map(<[_]>::iter),
+        0,
+        &mut lines,
+    );
+    lines
+}
+
+/// The plan as a JSON object per node, with the fields of [`explain_text`] in snake case
+/// and its inputs in `inputs`.
+#[must_use]
+pub fn explain_json(plan: &Plan<'_>, storage: &dyn Storage, stats: Option<&[NodeStats]>) -> Json {
+    fn node<'a>(
+        plan: &Plan<'a>,
+        computed: &Computed<'a>,
+        storage: &dyn Storage,
+        stats: &mut Option<std::slice::Iter<'_, NodeStats>>,
+    ) -> Json {
+        let (name, details) = describe(plan, computed);
+        let mut object = Map::new();
+        object.insert("node".into(), name.into());
+        for (label, value) in details {
+            object.insert(label.to_lowercase().replace(' ', "_"), value.into());
+        }
+        object.insert("estimated_rows".into(), plan.estimated_rows(storage).into());
+        if let Some(node) = stats.as_mut().and_then(Iterator::next) {
+            object.insert("rows".into(), node.rows.into());
+            object.insert("time_ms".into(), json!(node.time.as_secs_f64() * 1000.0));
+        }
+        let inputs = plan
+            .children()
+            .into_iter()
+            .map(|child| node(child, computed, storage, stats))
+            .collect::<Vec<_>>();
+        object.insert("inputs".into(), inputs.into());
+        object.into()
+    }
+    let mut exprs = Computed::new();
+    computed(plan, &mut exprs);
+    node(plan, &exprs, storage, &mut stats.map(<[_]>::iter))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{
+        ast::statement::Statement, functions::FunctionRegistry, parse::RawSpan, value::Value,
+    };
+
+    use super::*;
+    use crate::{
+        cancel::CancellationToken,
+        executor::Config,
+        operator::{build, Context},
+        optimizer::Optimizer,
+        storage::testing::TestStorage,
+    };
+
+    #[test]
+    fn test_explain() {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE users (id INT32, name VARCHAR(10))",
+            "CREATE TABLE orders (id INT32, user_id INT32)",
+        ]);
+        for i in 0..4 {
+            storage
+                .insert("public.users", vec![Value::I32(i), Value::Null])
+                .unwrap();
+            storage
+                .insert("public.orders", vec![Value::I32(i), Value::I32(i % 2)])
+                .unwrap();
+        }
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            RawSpan::new(
+                "SELECT u.name, COUNT(*) FROM users u JOIN orders o ON o.user_id = u.id \
+                 WHERE u.id < 3 GROUP BY u.name",
+            ),
+        )
+        .unwrap();
+        let functions = FunctionRegistry::builtins();
+        let plan = Optimizer::standard(functions, &storage)
+            .optimize(Plan::from_statement(&statement, &table_map).unwrap());
+        let lines = explain_text(&plan, &storage, None);
+        assert_eq!(
+            lines,
+            [
+                "Project (estimated rows=4)",
+                "    Columns: u.name, COUNT(*)",
+                "  Hash Aggregate (estimated rows=4)",
+                "      Group By: u.name",
+                "      Aggregates: COUNT(*)",
+                "    Project (estimated rows=4)",
+                "        Columns: u.name",
+                "      Hash Join (estimated rows=4)",
+                "          Kind: inner",
+                "          Keys: o.user_id = u.id",
+                "        Scan (estimated rows=4)",
+                "            Table: public.orders",
+                "            Columns: user_id",
+                "        Filter (estimated rows=2)",
+                "            Predicate: u.id < 3",
+                "          Scan (estimated rows=4)",
+                "              Table: public.users",
+                "              Columns: id, name",
+                "              Ranges: id < 3",
+            ]
+        );
+
+        let config = Config::default();
+        let profile = Profile::default();
+        let context = Context {
+            storage: &storage,
+            functions,
+            config: &config,
+            profile: Some(&profile),
+            cancellation: &CancellationToken::new(),
+        };
+        let mut operator = build(&plan, context).unwrap();
+        while operator.next().unwrap().is_some() {}
+        let stats = profile.nodes();
+        let json = explain_json(&plan, &storage, Some(&stats));
+        assert_eq!(json["node"], "Project");
+        assert_eq!(json["rows"], 1);
+        let aggregate = &json["inputs"][0];
+        assert_eq!(aggregate["node"], "Hash Aggregate");
+        assert_eq!(aggregate["group_by"], "u.name");
+        assert_eq!(aggregate["aggregates"], "COUNT(*)");
+        let join = &aggregate["inputs"][0]["inputs"][0];
+        assert_eq!(join["node"], "Hash Join");
+        assert_eq!(join["rows"], 4);
+        let inputs = join["inputs"].as_array().unwrap();
+        assert_eq!(inputs[0]["rows"], 4);
+        assert_eq!(inputs[1]["node"], "Filter");
+        assert_eq!(inputs[1]["rows"], 3);
+        assert_eq!(inputs[1]["inputs"][0]["rows"], 4);
+        assert!(join["time_ms"].as_f64().unwrap() >= 0.0);
+    }
+}
@@ -0,0 +1,840 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hasher},
+};
+
+use rs_db_parser::{
+    ast::{commands::select::SortOrder, expr::Expr, table::JoinKind},
+    eval::Evaluator,
+    value::Value,
+};
+
+use crate::{
+    error::ExecError,
+    exchange::{parallel_map, MORSEL_SIZE},
+    executor::Config,
+    operator::{matches, BoxedOperator, Operator},
+    plan::Layout,
+    sort::{Sorted, Sorter},
+    spill::{row_size, SpillFile, SpillReader},
+    Row,
+};
+
+/// The number of pairs of temporary files a hash join splits its sides into when the right
+/// one doesn't fit the memory budget.
+const PARTITIONS: u64 = 16;
+
+/// What the join operators share: which rows match and how the rows they return are made,
+/// see [`Plan::Join`](crate::plan::Plan::Join).
+pub(crate) struct JoinSpec<'p, 'a> {
+    pub(crate) kind: JoinKind,
+    pub(crate) keys: &'p [(usize, usize)],
+    pub(crate) condition: Option<&'p Expr<'a>>,
+    /// The layout of the joined rows, the condition is evaluated against it.
+    pub(crate) layout: Layout,
+    pub(crate) left_width: usize,
+    pub(crate) right_width: usize,
+    pub(crate) evaluator: Evaluator<'p>,
+}
+
+impl JoinSpec<'_, '_> {
+    /// Whether the keys of the rows are equal, neither being `NULL`, and the condition is
+    /// TRUE for them.
+    fn matches(&self, left: &[Value], right: &[Value]) -> Result<bool, ExecError> {
+        for (l, r) in self.keys {
+            if left[*l].compare(&right[*r])? != Some(Ordering::Equal) {
+                return Ok(false);
+            }
+        }
+        match self.condition {
+            Some(condition) => {
+                let row = self.joined(left, right);
+                matches(self.evaluator, condition, &self.layout, &row)
+            }
+            None => Ok(true),
+        }
+    }
+
+    fn joined(&self, left: &[Value], right: &[Value]) -> Row {
+        let mut row = Vec::with_capacity(self.left_width + self.right_width);
+        row.extend_from_slice(left);
+        row.extend_from_slice(right);
+        row
+    }
+
+    /// A left row without a match, the columns of the right side are `NULL`.
+    fn left_only(&self, mut left: Row) -> Row {
+        left.resize(self.left_width + self.right_width, Value::Null);
+        left
+    }
+
+    /// A right row without a match, the columns of the left side are `NULL`.
+    fn right_only(&self, right: &[Value]) -> Row {
+        let mut row = vec![Value::Null; self.left_width];
+        row.extend_from_slice(right);
+        row
+    }
+
+    /// Whether the left rows without a match are returned.
+    const fn keeps_left(&self) -> bool {
+        matches!(self.kind, JoinKind::Left | JoinKind::Full)
+    }
+
+    /// Whether the right rows without a match are returned.
+    const fn keeps_right(&self) -> bool {
+        matches!(self.kind, JoinKind::Right | JoinKind::Full)
+    }
+
+    fn left_keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.keys.iter().map(|(l, _)| *l)
+    }
+
+    fn right_keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.keys.iter().map(|(_, r)| *r)
+    }
+}
+
+/// Compares the keys of two rows, the positions of which are given by `a_keys` and
+/// `b_keys`. The keys must not be `NULL`.
+fn compare_keys(
+    a: &[Value],
+    a_keys: impl Iterator<Item = usize>,
+    b: &[Value],
+    b_keys: impl Iterator<Item = usize>,
+) -> Result<Ordering, ExecError> {
+    for (a_key, b_key) in a_keys.zip(b_keys) {
+        match a[a_key].compare(&b[b_key])? {
+            Some(Ordering::Equal) | None => {}
+            Some(ordering) => return Ok(ordering),
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+fn has_null_key(row: &[Value], mut keys: impl Iterator<Item = usize>) -> bool {
+    keys.any(|key| row[key].is_null())
+}
+
+/// The rows of the right side, read once, and which of them matched a left row.
+struct NestedLoopState {
+    right: Vec<Row>,
+    matched: Vec<bool>,
+    /// The current left row, whether it matched, and the next right row to try with it.
+    left: Option<(Row, bool, usize)>,
+    /// Past the end of the left side, the next right row to return if it never matched.
+    unmatched: usize,
+}
+
+/// Tries every right row with every left row, the right side is kept in memory.
+pub(crate) struct NestedLoopJoin<'p, 'a> {
+    spec: JoinSpec<'p, 'a>,
+    left: BoxedOperator<'p>,
+    right: BoxedOperator<'p>,
+    state: Option<NestedLoopState>,
+}
+
+impl<'p, 'a> NestedLoopJoin<'p, 'a> {
+    pub(crate) fn new(
+        spec: JoinSpec<'p, 'a>,
+        left: BoxedOperator<'p>,
+        right: BoxedOperator<'p>,
+    ) -> Self {
+        Self {
+            spec,
+            left,
+            right,
+            state: None,
+        }
+    }
+}
+
+impl Operator for NestedLoopJoin<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        let mut state = match self.state.take() {
+            Some(state) => state,
+            None => {
+                let mut right = Vec::new();
+                while let Some(row) = self.right.next()? {
+                    right.push(row);
+                }
+                NestedLoopState {
+                    matched: vec![false; right.len()],
+                    right,
+                    left: None,
+                    unmatched: 0,
+                }
+            }
+        };
+        let result = loop {
+            let Some((left, left_matched, position)) = &mut state.left else {
+                if let Some(row) = self.left.next()? {
+                    state.left = Some((row, false, 0));
+                    continue;
+                }
+                // The left side is exhausted, the right and full joins end with the right
+                // rows that never matched.
+                if !self.spec.keeps_right() {
+                    break None;
+                }
+                let found = (state.unmatched..state.right.len()).find(|i| !state.matched[*i]);
+                state.unmatched = found.map_or(state.right.len(), |index| index + 1);
+                break found.map(|index| self.spec.right_only(&state.right[index]));
+            };
+            if *position == state.right.len() {
+                let row = (!*left_matched && self.spec.keeps_left())
+                    .then(|| self.spec.left_only(std::mem::take(left)));
+                state.left = None;
+                match row {
+                    Some(row) => break Some(row),
+                    None => continue,
+                }
+            }
+            let index = *position;
+            *position += 1;
+            if self.spec.matches(left, &state.right[index])? {
+                *left_matched = true;
+                state.matched[index] = true;
+                break Some(self.spec.joined(left, &state.right[index]));
+            }
+        };
+        self.state = Some(state);
+        Ok(result)
+    }
+}
+
+/// The hash of the keys of `row` at `keys`, `None` if one of them is `NULL` as the row
+/// can't match then. Keys that compare equal have the same hash, whatever their types.
+fn key_hash(row: &[Value], keys: impl Iterator<Item = usize>) -> Option<u64> {
+    let mut state = DefaultHasher::new();
+    for key in keys {
+        if row[key].is_null() {
+            return None;
+        }
+        row[key].hash_comparable(&mut state);
+    }
+    Some(state.finish())
+}
+
+/// The right rows of a hash join, or of one of its partitions, by the hash of their keys.
+/// The buckets are split by hash between the threads that built them, a map each.
+struct HashTable {
+    rows: Vec<Row>,
+    matched: Vec<bool>,
+    buckets: Vec<HashMap<u64, Vec<usize>>>,
+}
+
+impl HashTable {
+    /// The table of `rows` hashed by `hash`, built on up to `workers` threads: the rows
+    /// are hashed by morsels in parallel, then each thread fills the map of its buckets.
+    fn build(
+        rows: Vec<Row>,
+        workers: usize,
+        hash: impl Fn(&[Value]) -> Option<u64> + Sync,
+    ) -> Self {
+        let workers = if rows.len() > MORSEL_SIZE { workers } else { 1 };
+        let hashes = parallel_map(rows.chunks(MORSEL_SIZE).collect(), workers, |morsel| {
+            morsel.iter().map(|row| hash(row)).collect::<Vec<_>>()
+        })
+        .concat();
+        let buckets = parallel_map((0..workers as u64).collect(), workers, |partition| {
+            let mut buckets = HashMap::<u64, Vec<usize>>::new();
+            for (index, hash) in hashes.iter().enumerate() {
+                match hash {
+                    Some(hash) if hash % workers as u64 == partition => {
+                        buckets.entry(*hash).or_default().push(index);
+                    }
+                    _ => {}
+                }
+            }
+            buckets
+        });
+        Self {
+            matched: vec![false; rows.len()],
+            rows,
+            buckets,
+        }
+    }
+
+    /// The positions of the rows whose keys hash to `hash`.
+    fn bucket(&self, hash: u64) -> Option<&Vec<usize>> {
+        #[allow(clippy::cast_possible_truncation)]
+        let map = self
+            .buckets
+            .get((hash % self.buckets.len() as u64) as usize)?;
+        map.get(&hash)
+    }
+}
+
+/// Where the left rows probing the hash table come from.
+enum Probe {
+    Input,
+    Spilled(SpillReader),
+}
+
+/// A hash table and the left rows looked up in it.
+struct Probing {
+    table: HashTable,
+    probe: Probe,
+    /// The current left row, whether it matched, the right rows with the same hash and the
+    /// next one to try.
+    current: Option<(Row, bool, Vec<usize>, usize)>,
+    probed: bool,
+    /// Once all left rows are probed, the next right row to return if it never matched.
+    unmatched: usize,
+}
+
+impl Probing {
+    const fn new(table: HashTable, probe: Probe) -> Self {
+        Self {
+            table,
+            probe,
+            current: None,
+            probed: false,
+            unmatched: 0,
+        }
+    }
+}
+
+/// Builds a hash table of the right rows, then looks up each left row in it. If the right
+/// rows exceed the memory budget, both sides are split by the hash of their keys into
+/// temporary files, and each pair of files, which holds all the rows that can match each
+/// other, is joined in turn. A pair is loaded whole even if its right rows still exceed the
+/// budget, as when many rows have the same key.
+pub(crate) struct HashJoin<'p, 'a> {
+    spec: JoinSpec<'p, 'a>,
+    left: BoxedOperator<'p>,
+    right: BoxedOperator<'p>,
+    config: &'p Config,
+    /// The pairs of files, right and left rows, not joined yet.
+    partitions: Vec<(SpillFile, SpillFile)>,
+    state: Option<Probing>,
+    started: bool,
+}
+
+impl<'p, 'a> HashJoin<'p, 'a> {
+    pub(crate) fn new(
+        spec: JoinSpec<'p, 'a>,
+        left: BoxedOperator<'p>,
+        right: BoxedOperator<'p>,
+        config: &'p Config,
+    ) -> Self {
+        Self {
+            spec,
+            left,
+            right,
+            config,
+            partitions: Vec::new(),
+            state: None,
+            started: false,
+        }
+    }
+
+    fn build(&mut self) -> Result<Option<Probing>, ExecError> {
+        let mut rows = Vec::new();
+        let mut size = 0;
+        while let Some(row) = self.right.next()? {
+            size += row_size(&row);
+            rows.push(row);
+            if size > self.config.memory_budget {
+                return self.partition(rows);
+            }
+        }
+        let spec = &self.spec;
+        let table = HashTable::build(rows, self.config.max_parallelism, |row| {
+            key_hash(row, spec.right_keys())
+        });
+        Ok(Some(Probing::new(table, Probe::Input)))
+    }
+
+    /// Writes the right rows, `read` and the ones left in the input, then the left rows to
+    /// the partitions of their hash, and loads the first partition.
+    fn partition(&mut self, read: Vec<Row>) -> Result<Option<Probing>, ExecError> {
+        let files = || {
+            (0..PARTITIONS)
+                .map(|_| SpillFile::create(&self.config.spill_dir))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let partition = |hash: Option<u64>| hash.map_or(0, |hash| (hash % PARTITIONS) as usize);
+        let mut right = files()?;
+        for row in read {
+            right[partition(key_hash(&row, self.spec.right_keys()))].write(&row)?;
+        }
+        while let Some(row) = self.right.next()? {
+            right[partition(key_hash(&row, self.spec.right_keys()))].write(&row)?;
+        }
+        let mut left = files()?;
+        while let Some(row) = self.left.next()? {
+            left[partition(key_hash(&row, self.spec.left_keys()))].write(&row)?;
+        }
+        self.partitions = right.into_iter().zip(left).collect();
+        self.next_partition()
+    }
+
+    fn next_partition(&mut self) -> Result<Option<Probing>, ExecError> {
+        while let Some((right, left)) = self.partitions.pop() {
+            if right.rows() == 0 && left.rows() == 0 {
+                continue;
+            }
+            let rows = right.into_reader()?.collect::<Result<Vec<_>, _>>()?;
+            let spec = &self.spec;
+            let table = HashTable::build(rows, self.config.max_parallelism, |row| {
+                key_hash(row, spec.right_keys())
+            });
+            return Ok(Some(Probing::new(
+                table,
+                Probe::Spilled(left.into_reader()?),
+            )));
+        }
+        Ok(None)
+    }
+}
+
+impl Operator for HashJoin<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        if !self.started {
+            self.started = true;
+            self.state = self.build()?;
+        }
+        loop {
+            let Some(state) = &mut self.state else {
+                return Ok(None);
+            };
+            if let Some((left, left_matched, candidates, position)) = &mut state.current {
+                if let Some(index) = candidates.get(*position).copied() {
+                    *position += 1;
+                    if self.spec.matches(left, &state.table.rows[index])? {
+                        *left_matched = true;
+                        state.table.matched[index] = true;
+                        return Ok(Some(self.spec.joined(left, &state.table.rows[index])));
+                    }
+                    continue;
+                }
+                let row = (!*left_matched && self.spec.keeps_left())
+                    .then(|| self.spec.left_only(std::mem::take(left)));
+                state.current = None;
+                match row {
+                    Some(row) => return Ok(Some(row)),
+                    None => continue,
+                }
+            }
+            if !state.probed {
+                let row = match &mut state.probe {
+                    Probe::Input => self.left.next()?,
+                    Probe::Spilled(reader) => reader.next().transpose()?,
+                };
+                if let Some(row) = row {
+                    let candidates = key_hash(&row, self.spec.left_keys())
+                        .and_then(|hash| state.table.bucket(hash))
+                        .cloned()
+                        .unwrap_or_default();
+                    state.current = Some((row, false, candidates, 0));
+                    continue;
+                }
+                state.probed = true;
+            }
+            if self.spec.keeps_right() {
+                let table = &state.table;
+                let found = (state.unmatched..table.rows.len()).find(|i| !table.matched[*i]);
+                if let Some(index) = found {
+                    state.unmatched = index + 1;
+                    return Ok(Some(self.spec.right_only(&table.rows[index])));
+                }
+            }
+            self.state = self.next_partition()?;
+        }
+    }
+}
+
+/// Both sides sorted by key, the rows with a `NULL` key after the others.
+struct MergeState {
+    left: Sorted,
+    right: Sorted,
+    /// The next row of each side to merge.
+    left_next: Option<Row>,
+    right_next: Option<Row>,
+    /// The right rows with the key of the current left rows, and which of them matched.
+    group: Option<(Vec<Row>, Vec<bool>)>,
+    /// The rows made from the last left row or group, not returned yet.
+    pending: VecDeque<Row>,
+}
+
+/// Sorts both sides by key, then walks them together. The right rows with the same key are
+/// kept together, and each left row with that key is joined with them. The sides are
+/// sorted by a [`Sorter`], so they may exceed the memory budget, only a group of right rows
+/// with the same key is kept in memory.
+pub(crate) struct MergeJoin<'p, 'a> {
+    spec: JoinSpec<'p, 'a>,
+    left: BoxedOperator<'p>,
+    right: BoxedOperator<'p>,
+    config: &'p Config,
+    state: Option<MergeState>,
+}
+
+impl<'p, 'a> MergeJoin<'p, 'a> {
+    pub(crate) const fn new(
+        spec: JoinSpec<'p, 'a>,
+        left: BoxedOperator<'p>,
+        right: BoxedOperator<'p>,
+        config: &'p Config,
+    ) -> Self {
+        Self {
+            spec,
+            left,
+            right,
+            config,
+            state: None,
+        }
+    }
+
+    /// The rows of `input` sorted by `keys`. They are first sorted by whether one of their
+    /// keys is `NULL`, so the rows that can't match are after the others, whatever key is
+    /// `NULL`.
+    fn sorted(
+        input: &mut BoxedOperator<'_>,
+        keys: &[usize],
+        config: &Config,
+    ) -> Result<Sorted, ExecError> {
+        let mut sorter = Sorter::new(vec![(SortOrder::Asc, false); keys.len() + 1], config);
+        while let Some(row) = input.next()? {
+            let null = Value::Bool(has_null_key(&row, keys.iter().copied()));
+            let values = std::iter::once(null)
+                .chain(keys.iter().map(|key| row[*key].clone()))
+                .collect();
+            sorter.push(values, row)?;
+        }
+        sorter.finish()
+    }
+
+    fn start(&mut self) -> Result<MergeState, ExecError> {
+        let left_keys = self.spec.left_keys().collect::<Vec<_>>();
+        let right_keys = self.spec.right_keys().collect::<Vec<_>>();
+        let mut left = Self::sorted(&mut self.left, &left_keys, self.config)?;
+        let mut right = Self::sorted(&mut self.right, &right_keys, self.config)?;
+        Ok(MergeState {
+            left_next: left.next_row()?,
+            right_next: right.next_row()?,
+            left,
+            right,
+            group: None,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Joins the next left row, ends the current group or returns the next right row
+    /// without a match, into `pending`. Returns `false` once both sides are merged.
+    fn merge(&self, state: &mut MergeState) -> Result<bool, ExecError> {
+        let spec = &self.spec;
+        let left_keyed = state
+            .left_next
+            .as_ref()
+            .filter(|row| !has_null_key(row, spec.left_keys()));
+        if let Some((group, matched)) = &mut state.group {
+            let same_key = match left_keyed {
+                Some(left) => {
+                    compare_keys(left, spec.left_keys(), &group[0], spec.right_keys())?
+                        == Ordering::Equal
+                }
+                None => false,
+            };
+            if !same_key {
+                if spec.keeps_right() {
+                    let unmatched = group.iter().zip(matched.iter()).filter(|(_, m)| !**m);
+                    state
+                        .pending
+                        .extend(unmatched.map(|(right, _)| spec.right_only(right)));
+                }
+                state.group = None;
+                return Ok(true);
+            }
+            let Some(left) = std::mem::replace(&mut state.left_next, state.left.next_row()?) else {
+                unreachable!("the left row has the key of the group");
+            };
+            let mut left_matched = false;
+            for (right, right_matched) in group.iter().zip(matched.iter_mut()) {
+                if spec.matches(&left, right)? {
+                    left_matched = true;
+                    *right_matched = true;
+                    state.pending.push_back(spec.joined(&left, right));
+                }
+            }
+            if !left_matched && spec.keeps_left() {
+                state.pending.push_back(spec.left_only(left));
+            }
+            return Ok(true);
+        }
+        let right_keyed = state
+            .right_next
+            .as_ref()
+            .filter(|row| !has_null_key(row, spec.right_keys()));
+        let ordering = match (left_keyed, right_keyed) {
+            (Some(left), Some(right)) => {
+                compare_keys(left, spec.left_keys(), right, spec.right_keys())?
+            }
+            // This left row has a `NULL` key, or no right row is left to match.
+            _ if state.left_next.is_some() => Ordering::Less,
+            // The left side is merged, the right rows left never match.
+            _ if state.right_next.is_some() => Ordering::Greater,
+            _ => return Ok(false),
+        };
+        match ordering {
+            Ordering::Less => {
+                let left = std::mem::replace(&mut state.left_next, state.left.next_row()?);
+                if let Some(left) = left.filter(|_| spec.keeps_left()) {
+                    state.pending.push_back(spec.left_only(left));
+                }
+            }
+            Ordering::Greater => {
+                let right = std::mem::replace(&mut state.right_next, state.right.next_row()?);
+                if let Some(right) = right.filter(|_| spec.keeps_right()) {
+                    state.pending.push_back(spec.right_only(&right));
+                }
+            }
+            Ordering::Equal => {
+                // The right row just compared starts the group, the next ones with its key
+                // follow it.
+                let first = std::mem::replace(&mut state.right_next, state.right.next_row()?);
+                let mut group = Vec::from_iter(first);
+                while let Some(right) = state.right_next.take() {
+                    let same_key = !has_null_key(&right, spec.right_keys())
+                        && compare_keys(&group[0], spec.right_keys(), &right, spec.right_keys())?
+                            == Ordering::Equal;
+                    if !same_key {
+                        state.right_next = Some(right);
+                        break;
+                    }
+                    group.push(right);
+                    state.right_next = state.right.next_row()?;
+                }
+                let matched = vec![false; group.len()];
+                state.group = Some((group, matched));
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Operator for MergeJoin<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        let mut state = match self.state.take() {
+            Some(state) => state,
+            None => self.start()?,
+        };
+        let result = loop {
+            if let Some(row) = state.pending.pop_front() {
+                break Some(row);
+            }
+            if !self.merge(&mut state)? {
+                break None;
+            }
+        };
+        self.state = Some(state);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{
+        functions::FunctionRegistry,
+        parse::{Parse, RawSpan},
+    };
+
+    use super::*;
+    use crate::{
+        cancel::CancellationToken,
+        operator::{build, Context},
+        plan::{Field, JoinAlgorithm, Plan},
+        storage::testing::TestStorage,
+    };
+
+    fn side(table: &str, rows: Vec<Row>) -> Box<Plan<'static>> {
+        let field = |name: &str| Field {
+            table: Some(table.into()),
+            name: name.into(),
+        };
+        Box::new(Plan::Values {
+            rows,
+            layout: Layout(vec![field("id"), field("name")]),
+        })
+    }
+
+    fn row(id: Value, name: &str) -> Row {
+        vec![id, Value::VarChar(name.into())]
+    }
+
+    fn join(
+        kind: JoinKind,
+        algorithm: JoinAlgorithm,
+        condition: Option<&'static str>,
+        config: &Config,
+    ) -> Vec<Row> {
+        let left = vec![
+            row(Value::I32(1), "a"),
+            row(Value::I32(2), "b"),
+            row(Value::Null, "n"),
+            row(Value::I32(2), "c"),
+            row(Value::I32(5), "e"),
+        ];
+        let right = vec![
+            row(Value::I64(2), "x"),
+            row(Value::F64(1.0), "w"),
+            row(Value::I64(3), "z"),
+            row(Value::Null, "m"),
+            row(Value::I64(2), "y"),
+        ];
+        let condition = condition.map(|c| Expr::parse(RawSpan::new(c)).unwrap().1);
+        join_rows(
+            kind,
+            algorithm,
+            (left, right),
+            vec![(0, 0)],
+            condition,
+            config,
+        )
+    }
+
+    fn join_rows(
+        kind: JoinKind,
+        algorithm: JoinAlgorithm,
+        (left, right): (Vec<Row>, Vec<Row>),
+        keys: Vec<(usize, usize)>,
+        condition: Option<Expr<'static>>,
+        config: &Config,
+    ) -> Vec<Row> {
+        let plan = Plan::Join {
+            kind,
+            algorithm,
+            left: side("l", left),
+            right: side("r", right),
+            keys,
+            condition,
+        };
+        let (storage, _) = TestStorage::new(&[]);
+        let context = Context {
+            storage: &storage,
+            functions: FunctionRegistry::builtins(),
+            config,
+            profile: None,
+            cancellation: &CancellationToken::new(),
+        };
+        let mut operator = build(&plan, context).unwrap();
+        let mut rows = Vec::new();
+        while let Some(row) = operator.next().unwrap() {
+            rows.push(row);
+        }
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn test_join_algorithms() {
+        let config = Config {
+            max_parallelism: 1,
+            ..Config::default()
+        };
+        let spilling = Config {
+            memory_budget: 0,
+            ..config.clone()
+        };
+        let parallel = Config {
+            max_parallelism: 4,
+            ..Config::default()
+        };
+        for kind in [
+            JoinKind::Inner,
+            JoinKind::Left,
+            JoinKind::Right,
+            JoinKind::Full,
+        ] {
+            for condition in [None, Some("l.name <> 'c' AND r.name <> 'y'")] {
+                let expected = join(kind, JoinAlgorithm::NestedLoop, condition, &config);
+                assert_eq!(
+                    join(kind, JoinAlgorithm::Hash, condition, &config),
+                    expected
+                );
+                assert_eq!(
+                    join(kind, JoinAlgorithm::Hash, condition, &spilling),
+                    expected
+                );
+                assert_eq!(
+                    join(kind, JoinAlgorithm::Hash, condition, &parallel),
+                    expected
+                );
+                assert_eq!(
+                    join(kind, JoinAlgorithm::Merge, condition, &config),
+                    expected
+                );
+                assert_eq!(
+                    join(kind, JoinAlgorithm::Merge, condition, &spilling),
+                    expected
+                );
+            }
+        }
+        let full = join(JoinKind::Full, JoinAlgorithm::Hash, None, &spilling);
+        let name = |row: &Row| match (&row[1], &row[3]) {
+            (Value::VarChar(l), Value::VarChar(r)) => format!("{l}{r}"),
+            (Value::VarChar(l), _) => format!("{l}-"),
+            (_, Value::VarChar(r)) => format!("-{r}"),
+            _ => unreachable!(),
+        };
+        let mut names = full.iter().map(name).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            ["-m", "-z", "aw", "bx", "by", "cx", "cy", "e-", "n-"]
+        );
+    }
+
+    #[test]
+    fn test_join_keys() {
+        let config = Config {
+            max_parallelism: 1,
+            ..Config::default()
+        };
+        let spilling = Config {
+            memory_budget: 0,
+            ..config.clone()
+        };
+        let pair = |a: i32, b: Option<i32>| vec![Value::I32(a), b.map_or(Value::Null, Value::I32)];
+        // A `NULL` in the second key doesn't stop the later rows from matching.
+        let left = vec![
+            pair(1, Some(1)),
+            pair(2, Some(2)),
+            pair(3, None),
+            pair(4, Some(4)),
+            pair(5, Some(5)),
+            pair(6, Some(6)),
+        ];
+        let right = vec![
+            pair(5, Some(5)),
+            pair(1, Some(1)),
+            pair(4, None),
+            pair(2, Some(2)),
+            pair(4, Some(4)),
+            pair(6, Some(7)),
+        ];
+        for kind in [
+            JoinKind::Inner,
+            JoinKind::Left,
+            JoinKind::Right,
+            JoinKind::Full,
+        ] {
+            let run = |algorithm, config| {
+                let sides = (left.clone(), right.clone());
+                join_rows(kind, algorithm, sides, vec![(0, 0), (1, 1)], None, config)
+            };
+            let expected = run(JoinAlgorithm::NestedLoop, &config);
+            let matched = expected
+                .iter()
+                .filter(|row| !row[0].is_null() && !row[2].is_null())
+                .count();
+            assert_eq!(matched, 4);
+            assert_eq!(run(JoinAlgorithm::Hash, &config), expected);
+            assert_eq!(run(JoinAlgorithm::Merge, &config), expected);
+            assert_eq!(run(JoinAlgorithm::Merge, &spilling), expected);
+        }
+    }
+}
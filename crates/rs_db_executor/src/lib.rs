@@ -0,0 +1,26 @@
+use rs_db_parser::value::Value;
+
+mod aggregate;
+pub mod batch;
+pub mod cancel;
+pub mod constraint;
+pub mod error;
+mod exchange;
+pub mod executor;
+pub mod explain;
+mod join;
+pub mod operator;
+pub mod optimizer;
+pub mod partition;
+pub mod plan;
+pub mod prepared;
+pub mod session;
+mod sort;
+pub mod spill;
+pub mod statistics;
+pub mod storage;
+pub mod transaction;
+mod vectorized;
+
+/// The values of a row, in the order of the columns of its [`Layout`](plan::Layout).
+pub type Row = Vec<Value>;
@@ -0,0 +1,447 @@
+use std::ops::Bound;
+
+use rs_db_parser::{ast::expr::Expr, eval::Evaluator, functions::FunctionRegistry, value::Value};
+use rs_db_storage::engine::{ColumnRange, ScanOptions};
+
+use crate::{
+    aggregate::HashAggregate,
+    cancel::CancellationToken,
+    constraint::Constraints,
+    error::ExecError,
+    executor::Config,
+    explain::Profile,
+    join::{HashJoin, JoinSpec, MergeJoin, NestedLoopJoin},
+    plan::{JoinAlgorithm, Layout, Plan, ProjectItem},
+    sort::Sort,
+    storage::{KeyRange, RowIter, Storage},
+    vectorized::{self, Unbatch},
+    Row,
+};
+
+/// A node of a running plan. Each call to `next` pulls the rows it needs from its inputs
+/// and returns the next row it produces, `None` once it is exhausted.
+pub trait Operator {
+    /// The next row.
+    /// # Errors
+    /// Returns an error if the storage fails or an expression can't be evaluated for the
+    /// row.
+    fn next(&mut self) -> Result<Option<Row>, ExecError>;
+}
+
+pub type BoxedOperator<'p> = Box<dyn Operator + 'p>;
+
+/// What the operators of a plan run with.
+#[derive(Clone, Copy)]
+pub struct Context<'p> {
+    pub storage: &'p dyn Storage,
+    pub functions: &'p FunctionRegistry,
+    pub config: &'p Config,
+    /// Where the operators record what they do, for `EXPLAIN ANALYZE`.
+    pub profile: Option<&'p Profile>,
+    /// Checked by the scans before each row.
+    pub cancellation: &'p CancellationToken,
+}
+
+/// The operators that run `plan` in `context`. Filters and projections over a scan run
+/// a batch of rows at a time, see [`Config::batch_size`].
+/// # Errors
+/// Returns an error if a table of the plan doesn't exist or an expression names a column
+/// its input doesn't have.
+pub fn build<'p>(plan: &'p Plan<'_>, context: Context<'p>) -> Result<BoxedOperator<'p>, ExecError> {
+    if context.config.batch_size > 0 && vectorized::supports(plan) {
+        return Ok(Box::new(Unbatch::new(vectorized::build(plan, context)?)));
+    }
+    let evaluator = Evaluator::new(context.functions);
+    let node = context.profile.map(Profile::add_node);
+    let operator: BoxedOperator<'p> = match plan {
+        Plan::Scan { .. } => Box::new(SeqScan {
+            rows: scan(plan, context)?,
+            columns: None,
+            cancellation: context.cancellation,
+        }),
+        Plan::IndexScan { columns, .. } | Plan::IndexRangeScan { columns, .. } => {
+            Box::new(SeqScan {
+                rows: index_scan(plan, context)?,
+                columns: columns.as_deref(),
+                cancellation: context.cancellation,
+            })
+        }
+        Plan::Values { rows, .. } => Box::new(Values { rows: rows.iter() }),
+        Plan::Filter { input, predicate } => {
+            let layout = input.layout();
+            check_columns(predicate, &layout)?;
+            Box::new(Filter {
+                input: build(input, context)?,
+                predicate,
+                layout,
+                evaluator,
+            })
+        }
+        Plan::Project { input, items, .. } => {
+            let layout = input.layout();
+            for item in items {
+                if let ProjectItem::Expr(expr) = item {
+                    check_columns(expr, &layout)?;
+                }
+            }
+            Box::new(Project {
+                input: build(input, context)?,
+                items,
+                layout,
+                evaluator,
+            })
+        }
+        Plan::Join {
+            kind,
+            algorithm,
+            left,
+            right,
+            keys,
+            condition,
+        } => {
+            let (left_width, right_width) = (left.layout().len(), right.layout().len());
+            let layout = left.layout().concat(&right.layout());
+            if let Some(condition) = condition {
+                check_columns(condition, &layout)?;
+            }
+            let spec = JoinSpec {
+                kind: *kind,
+                keys,
+                condition: condition.as_ref(),
+                layout,
+                left_width,
+                right_width,
+                evaluator,
+            };
+            let (left, right) = (build(left, context)?, build(right, context)?);
+            match algorithm {
+                JoinAlgorithm::NestedLoop => Box::new(NestedLoopJoin::new(spec, left, right)),
+                JoinAlgorithm::Hash => Box::new(HashJoin::new(spec, left, right, context.config)),
+                JoinAlgorithm::Merge => Box::new(MergeJoin::new(spec, left, right, context.config)),
+            }
+        }
+        Plan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            ..
+        } => {
+            let layout = input.layout();
+            let args = aggregates.iter().filter_map(|call| call.arg.as_ref());
+            for expr in group_by.iter().chain(args) {
+                check_columns(expr, &layout)?;
+            }
+            Box::new(HashAggregate {
+                input: build(input, context)?,
+                group_by,
+                aggregates,
+                layout,
+                evaluator,
+                config: context.config,
+                partitions: Vec::new(),
+                output: None,
+            })
+        }
+        Plan::Sort { input, keys } => {
+            let layout = input.layout();
+            for key in keys {
+                check_columns(&key.expr, &layout)?;
+            }
+            Box::new(Sort {
+                input: build(input, context)?,
+                keys,
+                layout,
+                evaluator,
+                config: context.config,
+                sorted: None,
+            })
+        }
+        Plan::Limit {
+            input,
+            limit,
+            offset,
+        } => Box::new(Limit {
+            input: build(input, context)?,
+            remaining: *limit,
+            offset: *offset,
+        }),
+        Plan::Insert {
+            table,
+            input,
+            constraints,
+        } => Box::new(Insert {
+            constraints: Constraints::new(
+                table,
+                constraints,
+                input.layout(),
+                context.storage,
+                evaluator,
+            )?,
+            input: build(input, context)?,
+            table,
+            storage: context.storage,
+        }),
+    };
+    Ok(match (context.profile, node) {
+        (Some(profile), Some(node)) => profile.instrument(node, operator),
+        _ => operator,
+    })
+}
+
+/// The rows [`Plan::Scan`] reads, which the storage gives with the columns of its layout
+/// only, from the tables of its partitions when it has some. The bounds of its ranges are
+/// evaluated once, a range with a bound that can't be is left out, the filter above the
+/// scan checks it.
+/// # Errors
+/// Returns an error if the storage can't scan the table.
+pub(crate) fn scan<'p>(plan: &'p Plan<'_>, context: Context<'p>) -> Result<RowIter<'p>, ExecError> {
+    let Plan::Scan {
+        table,
+        columns,
+        ranges,
+        partitions,
+        ..
+    } = plan
+    else {
+        return Err(ExecError::Unsupported("A scan of this node"));
+    };
+    let evaluator = Evaluator::new(context.functions);
+    let bound = |bound: &Bound<Box<Expr<'_>>>| -> Option<Bound<Value>> {
+        let eval = |expr: &Expr<'_>| evaluator.eval(expr, &|_| Value::Null).ok();
+        Some(match bound {
+            Bound::Included(expr) => Bound::Included(eval(expr)?),
+            Bound::Excluded(expr) => Bound::Excluded(eval(expr)?),
+            Bound::Unbounded => Bound::Unbounded,
+        })
+    };
+    let options = ScanOptions {
+        columns: columns.clone(),
+        ranges: ranges
+            .iter()
+            .filter_map(|range| {
+                Some(ColumnRange {
+                    column: range.column,
+                    lower: bound(&range.lower)?,
+                    upper: bound(&range.upper)?,
+                })
+            })
+            .collect(),
+    };
+    let Some(partitions) = partitions else {
+        return context.storage.scan_with(table, &options);
+    };
+    let scans = partitions
+        .iter()
+        .map(|partition| context.storage.scan_with(partition, &options))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Box::new(scans.into_iter().flatten()))
+}
+
+/// The rows [`Plan::IndexScan`] or [`Plan::IndexRangeScan`] reads, their key bounds
+/// evaluated once.
+/// # Errors
+/// Returns an error if a bound can't be evaluated, or the storage can't scan the index.
+pub(crate) fn index_scan<'p>(
+    plan: &'p Plan<'_>,
+    context: Context<'p>,
+) -> Result<RowIter<'p>, ExecError> {
+    let evaluator = Evaluator::new(context.functions);
+    let eval = |expr: &Expr<'_>| -> Result<Value, ExecError> {
+        Ok(evaluator.eval(expr, &|_| Value::Null)?)
+    };
+    let bound = |bound: &Bound<Box<Expr<'_>>>| -> Result<Bound<Row>, ExecError> {
+        Ok(match bound {
+            Bound::Included(expr) => Bound::Included(vec![eval(expr)?]),
+            Bound::Excluded(expr) => Bound::Excluded(vec![eval(expr)?]),
+            Bound::Unbounded => Bound::Unbounded,
+        })
+    };
+    let (table, index, range, index_only) = match plan {
+        Plan::IndexScan {
+            table,
+            index,
+            key,
+            index_only,
+            ..
+        } => {
+            let key = key.iter().map(eval).collect::<Result<Row, _>>()?;
+            let range = KeyRange {
+                lower: Bound::Included(key.clone()),
+                upper: Bound::Included(key),
+            };
+            (table, index, range, index_only)
+        }
+        Plan::IndexRangeScan {
+            table,
+            index,
+            lower,
+            upper,
+            index_only,
+            ..
+        } => {
+            let range = KeyRange {
+                lower: bound(lower)?,
+                upper: bound(upper)?,
+            };
+            (table, index, range, index_only)
+        }
+        _ => return Err(ExecError::Unsupported("An index scan of this node")),
+    };
+    context
+        .storage
+        .index_scan(table, &index.name, &range, *index_only)
+}
+
+/// Fails if a column of `expr` is not in `layout`, so the operators can read the columns
+/// without checking.
+pub(crate) fn check_columns(expr: &Expr<'_>, layout: &Layout) -> Result<(), ExecError> {
+    match expr
+        .columns()
+        .into_iter()
+        .find(|c| layout.index(c).is_none())
+    {
+        Some(column) => Err(ExecError::ColumnNotFound(column.key().into())),
+        None => Ok(()),
+    }
+}
+
+/// Evaluates `expr` for `row`, whose columns are described by `layout`.
+pub(crate) fn eval<'a>(
+    evaluator: Evaluator<'_>,
+    expr: &Expr<'a>,
+    layout: &Layout,
+    row: &[Value],
+) -> Result<Value, ExecError> {
+    let value = evaluator.eval(expr, &|column| {
+        layout
+            .index(column)
+            .map_or(Value::Null, |index| row[index].clone())
+    })?;
+    Ok(value)
+}
+
+pub(crate) fn matches<'a>(
+    evaluator: Evaluator<'_>,
+    expr: &Expr<'a>,
+    layout: &Layout,
+    row: &[Value],
+) -> Result<bool, ExecError> {
+    let matches = evaluator.matches(expr, &|column| {
+        layout
+            .index(column)
+            .map_or(Value::Null, |index| row[index].clone())
+    })?;
+    Ok(matches)
+}
+
+/// Reads the rows of a table, or of an index, keeping the columns at `columns`.
+struct SeqScan<'p> {
+    rows: RowIter<'p>,
+    columns: Option<&'p [usize]>,
+    cancellation: &'p CancellationToken,
+}
+
+impl Operator for SeqScan<'_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        self.cancellation.check()?;
+        let row = self.rows.next().transpose()?;
+        Ok(match (row, self.columns) {
+            (Some(row), Some(columns)) => Some(columns.iter().map(|i| row[*i].clone()).collect()),
+            (row, _) => row,
+        })
+    }
+}
+
+struct Values<'p> {
+    rows: std::slice::Iter<'p, Row>,
+}
+
+impl Operator for Values<'_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        Ok(self.rows.next().cloned())
+    }
+}
+
+struct Filter<'p, 'a> {
+    input: BoxedOperator<'p>,
+    predicate: &'p Expr<'a>,
+    layout: Layout,
+    evaluator: Evaluator<'p>,
+}
+
+impl Operator for Filter<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        while let Some(row) = self.input.next()? {
+            if matches(self.evaluator, self.predicate, &self.layout, &row)? {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+struct Project<'p, 'a> {
+    input: BoxedOperator<'p>,
+    items: &'p [ProjectItem<'a>],
+    layout: Layout,
+    evaluator: Evaluator<'p>,
+}
+
+impl Operator for Project<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        let Some(row) = self.input.next()? else {
+            return Ok(None);
+        };
+        self.items
+            .iter()
+            .map(|item| match item {
+                ProjectItem::Column(index) => Ok(row[*index].clone()),
+                ProjectItem::Expr(expr) => eval(self.evaluator, expr, &self.layout, &row),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+struct Limit<'p> {
+    input: BoxedOperator<'p>,
+    remaining: Option<u64>,
+    offset: u64,
+}
+
+impl Operator for Limit<'_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        while self.offset > 0 {
+            if self.input.next()?.is_none() {
+                return Ok(None);
+            }
+            self.offset -= 1;
+        }
+        match &mut self.remaining {
+            Some(0) => Ok(None),
+            Some(remaining) => {
+                *remaining -= 1;
+                self.input.next()
+            }
+            None => self.input.next(),
+        }
+    }
+}
+
+struct Insert<'p> {
+    input: BoxedOperator<'p>,
+    table: &'p str,
+    storage: &'p dyn Storage,
+    constraints: Constraints<'p>,
+}
+
+impl Operator for Insert<'_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        let Some(row) = self.input.next()? else {
+            return Ok(None);
+        };
+        self.constraints.check(&row)?;
+        self.storage.insert(self.table, row.clone())?;
+        Ok(Some(row))
+    }
+}
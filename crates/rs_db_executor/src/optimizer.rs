@@ -0,0 +1,2010 @@
+use std::ops::Bound;
+
+use rs_db_parser::{
+    ast::{
+        expr::{BinaryOperator, Expr},
+        table::JoinKind,
+        visit::{walk_expr_mut, VisitorMut},
+    },
+    functions::FunctionRegistry,
+    value::Value,
+};
+
+use crate::{
+    plan::{JoinAlgorithm, Layout, Plan, ProjectItem, ScanRange},
+    storage::{Index, Storage},
+};
+
+/// A rewrite of a plan into one that produces the same rows, in the same order when it is
+/// defined, but runs faster.
+pub trait Rule {
+    fn name(&self) -> &'static str;
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a>;
+}
+
+/// Applies rules to a plan, one after the other.
+pub struct Optimizer<'f> {
+    rules: Vec<Box<dyn Rule + 'f>>,
+}
+
+impl<'f> Optimizer<'f> {
+    #[must_use]
+    pub fn new(rules: Vec<Box<dyn Rule + 'f>>) -> Self {
+        Self { rules }
+    }
+
+    /// All the rules, folding the constants first so the filters they decide are gone
+    /// before the others look at the plan. `storage` gives the sizes of the tables joins
+    /// are ordered and planned with, their indexes and their partitions.
+    #[must_use]
+    pub fn standard(functions: &'f FunctionRegistry, storage: &'f dyn Storage) -> Self {
+        Self::new(vec![
+            Box::new(ConstantFolding(functions)),
+            Box::new(PredicatePushdown),
+            Box::new(JoinOrdering::new(storage)),
+            Box::new(IndexSelection::new(storage)),
+            Box::new(ScanRanges),
+            Box::new(PartitionPruning { storage }),
+            Box::new(JoinSelection::new(storage)),
+            Box::new(ProjectionPruning),
+            Box::new(RemoveNoOpProjections),
+        ])
+    }
+
+    /// The names of the rules, in the order they are applied.
+    #[must_use]
+    pub fn rules(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
+    }
+
+    #[must_use]
+    pub fn optimize<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        self.rules.iter().fold(plan, |plan, rule| rule.apply(plan))
+    }
+}
+
+/// Replaces the inputs of `plan` with `f` applied to them.
+fn map_children<'a>(mut plan: Plan<'a>, f: &mut dyn FnMut(Plan<'a>) -> Plan<'a>) -> Plan<'a> {
+    for child in plan.children_mut() {
+        *child = f(std::mem::replace(child, empty(Layout::default())));
+    }
+    plan
+}
+
+const fn empty<'a>(layout: Layout) -> Plan<'a> {
+    Plan::Values {
+        rows: Vec::new(),
+        layout,
+    }
+}
+
+/// Replaces the expressions made only of literals with their value, and removes the filters
+/// they decide: an always TRUE one is dropped, an always FALSE or UNKNOWN one yields no rows.
+/// An expression that fails, like `1 / 0`, is left as is: it only fails the query if it is
+/// evaluated, which `CASE` or `AND` may avoid.
+pub struct ConstantFolding<'f>(pub &'f FunctionRegistry);
+
+impl<'a> VisitorMut<'a> for ConstantFolding<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+        if matches!(expr, Expr::Literal(_)) {
+            return;
+        }
+        match expr.fold(self.0) {
+            Ok(Some(value)) => *expr = Expr::Literal((expr.span(), value)),
+            Ok(None) | Err(_) => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+impl Rule for ConstantFolding<'_> {
+    fn name(&self) -> &'static str {
+        "constant_folding"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        let mut folder = ConstantFolding(self.0);
+        let plan = match plan {
+            Plan::Filter {
+                input,
+                mut predicate,
+            } => {
+                folder.visit_expr_mut(&mut predicate);
+                match predicate {
+                    Expr::Literal((_, Value::Bool(true))) => *input,
+                    Expr::Literal((_, Value::Bool(false) | Value::Null)) => empty(input.layout()),
+                    predicate => Plan::Filter { input, predicate },
+                }
+            }
+            Plan::Project {
+                input,
+                mut items,
+                layout,
+            } => {
+                for item in &mut items {
+                    if let ProjectItem::Expr(expr) = item {
+                        folder.visit_expr_mut(expr);
+                    }
+                }
+                Plan::Project {
+                    input,
+                    items,
+                    layout,
+                }
+            }
+            Plan::Join {
+                kind,
+                algorithm,
+                left,
+                right,
+                keys,
+                mut condition,
+            } => {
+                if let Some(expr) = &mut condition {
+                    folder.visit_expr_mut(expr);
+                }
+                if let Some(Expr::Literal((_, Value::Bool(true)))) = condition {
+                    condition = None;
+                }
+                Plan::Join {
+                    kind,
+                    algorithm,
+                    left,
+                    right,
+                    keys,
+                    condition,
+                }
+            }
+            Plan::Sort { input, mut keys } => {
+                for key in &mut keys {
+                    folder.visit_expr_mut(&mut key.expr);
+                }
+                Plan::Sort { input, keys }
+            }
+            plan => plan,
+        };
+        map_children(plan, &mut |child| self.apply(child))
+    }
+}
+
+/// Moves filters as close to the scans as they can go, so fewer rows reach the joins. The
+/// conjuncts of a filter above a join that only read one side move to that side, unless it
+/// is the side an outer join fills with `NULL`s, and the ones reading both sides of an inner
+/// join become part of its condition.
+pub struct PredicatePushdown;
+
+/// The inputs of a join a predicate reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+    Both,
+}
+
+impl Rule for PredicatePushdown {
+    fn name(&self) -> &'static str {
+        "predicate_pushdown"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        let plan = match plan {
+            Plan::Filter { input, predicate } => push_filter(*input, predicate),
+            plan => plan,
+        };
+        map_children(plan, &mut |child| self.apply(child))
+    }
+}
+
+fn push_filter<'a>(input: Plan<'a>, predicate: Expr<'a>) -> Plan<'a> {
+    match input {
+        Plan::Filter {
+            input,
+            predicate: inner,
+        } => push_filter(*input, conjunction(vec![inner, predicate])),
+        Plan::Sort { input, keys } => Plan::Sort {
+            input: Box::new(push_filter(*input, predicate)),
+            keys,
+        },
+        Plan::Join {
+            kind,
+            algorithm,
+            left,
+            right,
+            keys,
+            mut condition,
+        } => {
+            let (left_layout, right_layout) = (left.layout(), right.layout());
+            let (mut to_left, mut to_right, mut above) = (vec![], vec![], vec![]);
+            for conjunct in conjuncts(predicate) {
+                let inner = matches!(kind, JoinKind::Inner | JoinKind::Cross);
+                match side(&conjunct, &left_layout, &right_layout) {
+                    Some(Side::Left) if inner || kind == JoinKind::Left => to_left.push(conjunct),
+                    Some(Side::Right) if inner || kind == JoinKind::Right => {
+                        to_right.push(conjunct);
+                    }
+                    Some(_) if inner => {
+                        condition = Some(conjunction(
+                            condition.into_iter().chain([conjunct]).collect(),
+                        ));
+                    }
+                    _ => above.push(conjunct),
+                }
+            }
+            let filter = |plan: Box<Plan<'a>>, conjuncts: Vec<Expr<'a>>| {
+                if conjuncts.is_empty() {
+                    plan
+                } else {
+                    Box::new(Plan::Filter {
+                        input: plan,
+                        predicate: conjunction(conjuncts),
+                    })
+                }
+            };
+            let join = Plan::Join {
+                kind: match kind {
+                    JoinKind::Cross if condition.is_some() => JoinKind::Inner,
+                    kind => kind,
+                },
+                algorithm,
+                left: filter(left, to_left),
+                right: filter(right, to_right),
+                keys,
+                condition,
+            };
+            *filter(Box::new(join), above)
+        }
+        input => Plan::Filter {
+            input: Box::new(input),
+            predicate,
+        },
+    }
+}
+
+/// The operands of the `AND`s at the top of `expr`.
+fn conjuncts(expr: Expr<'_>) -> Vec<Expr<'_>> {
+    match expr {
+        Expr::Binary {
+            op: (_, BinaryOperator::And),
+            left,
+            right,
+        } => {
+            let mut all = conjuncts(*left);
+            all.extend(conjuncts(*right));
+            all
+        }
+        Expr::Nested((_, expr))
+            if matches!(
+                *expr,
+                Expr::Binary {
+                    op: (_, BinaryOperator::And),
+                    ..
+                }
+            ) =>
+        {
+            conjuncts(*expr)
+        }
+        expr => vec![expr],
+    }
+}
+
+/// `AND` of `conjuncts`, which must not be empty.
+fn conjunction(conjuncts: Vec<Expr<'_>>) -> Expr<'_> {
+    conjuncts
+        .into_iter()
+        .reduce(|left, right| Expr::Binary {
+            op: (right.span(), BinaryOperator::And),
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+        .expect("a conjunction of no expressions")
+}
+
+/// The side of a join whose columns `expr` reads. `None` if it reads no column, or one
+/// neither side has, so it stays above the join. A column is resolved as in the layout of
+/// the join, an unqualified name both sides have is the left one.
+fn side(expr: &Expr<'_>, left: &Layout, right: &Layout) -> Option<Side> {
+    let mut side = None;
+    for column in expr.columns() {
+        let this = if left.index(&column).is_some() {
+            Side::Left
+        } else if right.index(&column).is_some() {
+            Side::Right
+        } else {
+            return None;
+        };
+        side = match side {
+            Some(side) if side != this => Some(Side::Both),
+            _ => Some(this),
+        };
+    }
+    side
+}
+
+/// Reorders the tables of each tree of inner and cross joins by cost, so the plan of a query
+/// doesn't depend on the order its `FROM` lists them in. The cost of an order is the number
+/// of rows each join produces plus the ones of its right side, which a hash join keeps in a
+/// hash table, as estimated from the statistics of the tables. Up to `exhaustive_tables`
+/// tables every order is tried, by dynamic programming over the sets of tables, above that
+/// the pair of trees joined next is the cheapest one. The conjuncts of the conditions move
+/// to the first join that has the tables they read, and a projection restores the order of
+/// the columns. A tree is left as is when its order is already the cheapest, when the size
+/// of one of its tables is unknown, or when a condition reads a column whose name more than
+/// one of its tables has.
+pub struct JoinOrdering<'s> {
+    pub storage: &'s dyn Storage,
+    pub exhaustive_tables: usize,
+}
+
+impl<'s> JoinOrdering<'s> {
+    #[must_use]
+    pub const fn new(storage: &'s dyn Storage) -> Self {
+        Self {
+            storage,
+            exhaustive_tables: 10,
+        }
+    }
+}
+
+impl Rule for JoinOrdering<'_> {
+    fn name(&self) -> &'static str {
+        "join_ordering"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        if let Some(graph) = JoinGraph::new(&plan, self.storage) {
+            let order = if graph.tables.len() <= self.exhaustive_tables {
+                graph.exhaustive()
+            } else {
+                graph.greedy()
+            };
+            if graph.cost(&order) < graph.cost(&graph.order) {
+                return graph.into_plan(&order, &mut |table| self.apply(table));
+            }
+        }
+        map_children(plan, &mut |child| self.apply(child))
+    }
+}
+
+/// A tree of inner and cross joins, as the plans it joins and the conjuncts of its keys and
+/// conditions. Sets of tables are the bits of their positions.
+struct JoinGraph<'a> {
+    tables: Vec<JoinTable<'a>>,
+    predicates: Vec<JoinPredicate<'a>>,
+    /// The columns of the tables, in the order of the query.
+    layout: Layout,
+    /// The order of the query.
+    order: JoinTree,
+}
+
+/// A plan a [`JoinGraph`] joins, anything but an inner or cross join.
+struct JoinTable<'a> {
+    plan: Plan<'a>,
+    rows: u64,
+    /// The position of its first column in the layout of the graph.
+    offset: usize,
+    width: usize,
+}
+
+/// A conjunct of the keys and conditions of a [`JoinGraph`].
+struct JoinPredicate<'a> {
+    /// The set of the tables it reads.
+    tables: usize,
+    /// The fraction of the rows of these tables it is assumed to keep.
+    selectivity: f64,
+    kind: JoinPredicateKind<'a>,
+}
+
+enum JoinPredicateKind<'a> {
+    Condition(Expr<'a>),
+    /// The positions of two columns that must be equal in the layout of the graph.
+    Key(usize, usize),
+}
+
+/// An order of the joins of a [`JoinGraph`], the tables being their positions.
+enum JoinTree {
+    Table(usize),
+    Join(Box<Self>, Box<Self>),
+}
+
+impl JoinTree {
+    fn tables(&self) -> usize {
+        match self {
+            Self::Table(table) => 1 << table,
+            Self::Join(left, right) => left.tables() | right.tables(),
+        }
+    }
+}
+
+impl<'a> JoinGraph<'a> {
+    /// The graph of `plan` if it is a tree of joins that can be reordered.
+    fn new(plan: &Plan<'a>, storage: &dyn Storage) -> Option<Self> {
+        if !matches!(
+            plan,
+            Plan::Join {
+                kind: JoinKind::Inner | JoinKind::Cross,
+                ..
+            }
+        ) {
+            return None;
+        }
+        let mut graph = Self {
+            tables: Vec::new(),
+            predicates: Vec::new(),
+            layout: Layout::default(),
+            order: JoinTree::Table(0),
+        };
+        let mut conditions = Vec::new();
+        graph.order = graph.collect(plan, storage, &mut conditions)?;
+        for condition in conditions {
+            let mut tables = 0;
+            for column in condition.columns() {
+                let mut positions = graph.layout.positions(&column);
+                let (Some(position), None) = (positions.next(), positions.next()) else {
+                    return None;
+                };
+                tables |= 1 << graph.table_of(position);
+            }
+            let selectivity = graph.condition_selectivity(&condition, tables);
+            graph.predicates.push(JoinPredicate {
+                tables,
+                selectivity,
+                kind: JoinPredicateKind::Condition(condition),
+            });
+        }
+        Some(graph)
+    }
+
+    /// Adds the tables and the keys of `plan`, and its conditions to `conditions` until
+    /// the layout of the graph is complete to resolve their columns.
+    fn collect(
+        &mut self,
+        plan: &Plan<'a>,
+        storage: &dyn Storage,
+        conditions: &mut Vec<Expr<'a>>,
+    ) -> Option<JoinTree> {
+        match plan {
+            Plan::Join {
+                kind: JoinKind::Inner | JoinKind::Cross,
+                left,
+                right,
+                keys,
+                condition,
+                ..
+            } => {
+                let left_offset = self.layout.len();
+                let left = self.collect(left, storage, conditions)?;
+                let right_offset = self.layout.len();
+                let right = self.collect(right, storage, conditions)?;
+                for (left_index, right_index) in keys {
+                    let (a, b) = (left_offset + left_index, right_offset + right_index);
+                    let (table_a, table_b) = (self.table_of(a), self.table_of(b));
+                    let (table_a, table_b) = (&self.tables[table_a], &self.tables[table_b]);
+                    let selectivity = Plan::equi_join_selectivity(
+                        (&table_a.plan, a - table_a.offset, table_a.rows),
+                        (&table_b.plan, b - table_b.offset, table_b.rows),
+                    );
+                    self.predicates.push(JoinPredicate {
+                        tables: 1 << self.table_of(a) | 1 << self.table_of(b),
+                        selectivity,
+                        kind: JoinPredicateKind::Key(a, b),
+                    });
+                }
+                conditions.extend(condition.iter().cloned().flat_map(conjuncts));
+                Some(JoinTree::Join(Box::new(left), Box::new(right)))
+            }
+            plan => {
+                if self.tables.len() == usize::BITS as usize {
+                    return None;
+                }
+                let layout = plan.layout();
+                self.tables.push(JoinTable {
+                    plan: plan.clone(),
+                    rows: plan.estimated_rows(storage)?,
+                    offset: self.layout.len(),
+                    width: layout.len(),
+                });
+                self.layout.0.extend(layout.0);
+                Some(JoinTree::Table(self.tables.len() - 1))
+            }
+        }
+    }
+
+    /// The table whose columns include the one at `position` in the layout of the graph.
+    fn table_of(&self, position: usize) -> usize {
+        self.tables
+            .partition_point(|table| table.offset <= position)
+            - 1
+    }
+
+    /// The selectivity of a condition reading `tables`: the one of a filter for a single
+    /// table, the one of a key for the equality of the columns of two tables, else a third.
+    fn condition_selectivity(&self, condition: &Expr<'_>, tables: usize) -> f64 {
+        if tables.count_ones() == 1 {
+            return self.tables[tables.trailing_zeros() as usize]
+                .plan
+                .selectivity(condition);
+        }
+        if let Expr::Binary {
+            op: (_, BinaryOperator::Eq),
+            left,
+            right,
+        } = condition
+        {
+            if let (Expr::Column(a), Expr::Column(b)) = (&**left, &**right) {
+                let (a, b) = (self.layout.index(a), self.layout.index(b));
+                if let (Some(a), Some(b)) = (a, b) {
+                    let (table_a, table_b) = (
+                        &self.tables[self.table_of(a)],
+                        &self.tables[self.table_of(b)],
+                    );
+                    if !std::ptr::eq(table_a, table_b) {
+                        return Plan::equi_join_selectivity(
+                            (&table_a.plan, a - table_a.offset, table_a.rows),
+                            (&table_b.plan, b - table_b.offset, table_b.rows),
+                        );
+                    }
+                }
+            }
+        }
+        1.0 / 3.0
+    }
+
+    /// The estimated number of rows of the join of a set of tables.
+    #[allow(clippy::cast_precision_loss)]
+    fn rows(&self, set: usize) -> f64 {
+        let rows = self
+            .tables
+            .iter()
+            .enumerate()
+            .filter(|(table, _)| set & 1 << table != 0)
+            .map(|(_, table)| table.rows as f64)
+            .product::<f64>();
+        self.predicates
+            .iter()
+            .filter(|predicate| predicate.tables != 0 && predicate.tables & !set == 0)
+            .fold(rows, |rows, predicate| rows * predicate.selectivity)
+    }
+
+    fn cost(&self, order: &JoinTree) -> f64 {
+        match order {
+            JoinTree::Table(_) => 0.0,
+            JoinTree::Join(left, right) => {
+                self.cost(left)
+                    + self.cost(right)
+                    + self.rows(order.tables())
+                    + self.rows(right.tables())
+            }
+        }
+    }
+
+    /// The cheapest order, found by computing the cheapest way to join each set of tables
+    /// from the ones of its subsets.
+    fn exhaustive(&self) -> JoinTree {
+        fn tree(best: &[(f64, usize)], set: usize) -> JoinTree {
+            if set.is_power_of_two() {
+                return JoinTree::Table(set.trailing_zeros() as usize);
+            }
+            let left = best[set].1;
+            JoinTree::Join(Box::new(tree(best, left)), Box::new(tree(best, set ^ left)))
+        }
+        let sets = 1 << self.tables.len();
+        let rows = (0..sets).map(|set| self.rows(set)).collect::<Vec<_>>();
+        // The cost of the cheapest join of each set, and the set of its left side.
+        let mut best = vec![(0.0, 0); sets];
+        for set in 1..sets {
+            if set.is_power_of_two() {
+                continue;
+            }
+            let mut cheapest = (f64::INFINITY, 0);
+            let mut left = (set - 1) & set;
+            while left != 0 {
+                let right = set ^ left;
+                let cost = best[left].0 + best[right].0 + rows[set] + rows[right];
+                if cost < cheapest.0 {
+                    cheapest = (cost, left);
+                }
+                left = (left - 1) & set;
+            }
+            best[set] = cheapest;
+        }
+        tree(&best, sets - 1)
+    }
+
+    /// An order built by joining the two trees whose join costs the least, until there is
+    /// only one.
+    fn greedy(&self) -> JoinTree {
+        let mut trees = (0..self.tables.len())
+            .map(|table| (1 << table, JoinTree::Table(table)))
+            .collect::<Vec<_>>();
+        while trees.len() > 1 {
+            let mut cheapest = (f64::INFINITY, 0, 1);
+            for (i, (left, _)) in trees.iter().enumerate() {
+                for (j, (right, _)) in trees.iter().enumerate() {
+                    let cost = self.rows(left | right) + self.rows(*right);
+                    if i != j && cost < cheapest.0 {
+                        cheapest = (cost, i, j);
+                    }
+                }
+            }
+            let (_, i, j) = cheapest;
+            let second = trees.remove(i.max(j));
+            let first = trees.remove(i.min(j));
+            let ((left_set, left), (right_set, right)) = if i < j {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            trees.push((
+                left_set | right_set,
+                JoinTree::Join(Box::new(left), Box::new(right)),
+            ));
+        }
+        trees.remove(0).1
+    }
+
+    /// The plan joining the tables in `order`, each one rewritten by `f`, with the columns
+    /// in the order of the query.
+    fn into_plan(self, order: &JoinTree, f: &mut dyn FnMut(Plan<'a>) -> Plan<'a>) -> Plan<'a> {
+        let columns = self
+            .tables
+            .iter()
+            .map(|table| table.offset..table.offset + table.width)
+            .collect::<Vec<_>>();
+        let mut tables = self
+            .tables
+            .into_iter()
+            .map(|table| Some(f(table.plan)))
+            .collect::<Vec<_>>();
+        let mut predicates = self.predicates.into_iter().map(Some).collect::<Vec<_>>();
+        let (mut plan, order) = join_tree(order, &columns, &mut tables, &mut predicates);
+        // The conditions that read no column apply to the whole join.
+        let rest = predicates
+            .into_iter()
+            .flatten()
+            .filter_map(|predicate| match predicate.kind {
+                JoinPredicateKind::Condition(condition) => Some(condition),
+                JoinPredicateKind::Key(..) => None,
+            })
+            .collect::<Vec<_>>();
+        if let Plan::Join {
+            kind, condition, ..
+        } = &mut plan
+        {
+            if !rest.is_empty() {
+                *kind = JoinKind::Inner;
+                *condition = Some(conjunction(
+                    condition.take().into_iter().chain(rest).collect(),
+                ));
+            }
+        }
+        let mut items = vec![ProjectItem::Column(0); order.len()];
+        for (index, position) in order.into_iter().enumerate() {
+            items[position] = ProjectItem::Column(index);
+        }
+        Plan::Project {
+            input: Box::new(plan),
+            items,
+            layout: self.layout,
+        }
+    }
+}
+
+/// The plan of `order`, and the positions of its columns in the layout of the graph. The
+/// predicates whose tables it joins are taken, a table only reads the ones of its own
+/// columns, as a filter.
+fn join_tree<'a>(
+    order: &JoinTree,
+    columns: &[std::ops::Range<usize>],
+    tables: &mut [Option<Plan<'a>>],
+    predicates: &mut [Option<JoinPredicate<'a>>],
+) -> (Plan<'a>, Vec<usize>) {
+    let set = order.tables();
+    let (left, right) = match order {
+        JoinTree::Table(table) => {
+            let plan = tables[*table].take().expect("a table joined twice");
+            let conditions = take_predicates(predicates, |tables| tables == set)
+                .into_iter()
+                .filter_map(|predicate| match predicate {
+                    JoinPredicateKind::Condition(condition) => Some(condition),
+                    JoinPredicateKind::Key(..) => None,
+                })
+                .collect::<Vec<_>>();
+            let plan = if conditions.is_empty() {
+                plan
+            } else {
+                Plan::Filter {
+                    input: Box::new(plan),
+                    predicate: conjunction(conditions),
+                }
+            };
+            return (plan, columns[*table].clone().collect());
+        }
+        JoinTree::Join(left, right) => (left, right),
+    };
+    let (left_set, right_set) = (left.tables(), right.tables());
+    let (left, left_columns) = join_tree(left, columns, tables, predicates);
+    let (right, right_columns) = join_tree(right, columns, tables, predicates);
+    let mut keys = Vec::new();
+    let mut conditions = Vec::new();
+    for predicate in take_predicates(predicates, |tables| {
+        tables & !set == 0 && tables & !left_set != 0 && tables & !right_set != 0
+    }) {
+        match predicate {
+            JoinPredicateKind::Condition(condition) => conditions.push(condition),
+            JoinPredicateKind::Key(a, b) => {
+                let position =
+                    |columns: &[usize], column| columns.iter().position(|c| *c == column);
+                keys.push(
+                    match (position(&left_columns, a), position(&right_columns, b)) {
+                        (Some(a), Some(b)) => (a, b),
+                        _ => (
+                            position(&left_columns, b).expect("a key of the left side"),
+                            position(&right_columns, a).expect("a key of the right side"),
+                        ),
+                    },
+                );
+            }
+        }
+    }
+    let plan = Plan::Join {
+        kind: if keys.is_empty() && conditions.is_empty() {
+            JoinKind::Cross
+        } else {
+            JoinKind::Inner
+        },
+        algorithm: JoinAlgorithm::NestedLoop,
+        left: Box::new(left),
+        right: Box::new(right),
+        keys,
+        condition: (!conditions.is_empty()).then(|| conjunction(conditions)),
+    };
+    (
+        plan,
+        left_columns.into_iter().chain(right_columns).collect(),
+    )
+}
+
+/// Takes the predicates whose set of tables `place` accepts.
+fn take_predicates<'a>(
+    predicates: &mut [Option<JoinPredicate<'a>>],
+    place: impl Fn(usize) -> bool,
+) -> Vec<JoinPredicateKind<'a>> {
+    predicates
+        .iter_mut()
+        .filter(|predicate| {
+            predicate
+                .as_ref()
+                .is_some_and(|p| p.tables != 0 && place(p.tables))
+        })
+        .filter_map(|predicate| predicate.take().map(|predicate| predicate.kind))
+        .collect()
+}
+
+/// Chooses the algorithm of each join. The conjuncts of its condition that equal a column
+/// of each side become keys, then a join with keys is run as a hash join, or as a merge join
+/// when both sides are estimated to have more than `merge_join_rows` rows, as sorting them
+/// costs less than splitting them both to disk. A join without keys stays a nested loop.
+pub struct JoinSelection<'s> {
+    pub storage: &'s dyn Storage,
+    pub merge_join_rows: u64,
+}
+
+impl<'s> JoinSelection<'s> {
+    #[must_use]
+    pub const fn new(storage: &'s dyn Storage) -> Self {
+        Self {
+            storage,
+            merge_join_rows: 1_000_000,
+        }
+    }
+}
+
+impl Rule for JoinSelection<'_> {
+    fn name(&self) -> &'static str {
+        "join_selection"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        let plan = match plan {
+            Plan::Join {
+                kind,
+                left,
+                right,
+                mut keys,
+                condition,
+                ..
+            } => {
+                let (left_layout, right_layout) = (left.layout(), right.layout());
+                let layout = left_layout.concat(&right_layout);
+                let mut rest = Vec::new();
+                for conjunct in condition.into_iter().flat_map(conjuncts) {
+                    match equi_key(&conjunct, &layout, left_layout.len()) {
+                        Some(key) => keys.push(key),
+                        None => rest.push(conjunct),
+                    }
+                }
+                let large = |plan: &Plan<'_>| {
+                    plan.estimated_rows(self.storage)
+                        .is_some_and(|rows| rows > self.merge_join_rows)
+                };
+                let algorithm = if keys.is_empty() {
+                    JoinAlgorithm::NestedLoop
+                } else if large(&left) && large(&right) {
+                    JoinAlgorithm::Merge
+                } else {
+                    JoinAlgorithm::Hash
+                };
+                Plan::Join {
+                    kind: match kind {
+                        JoinKind::Cross if !keys.is_empty() => JoinKind::Inner,
+                        kind => kind,
+                    },
+                    algorithm,
+                    left,
+                    right,
+                    keys,
+                    condition: (!rest.is_empty()).then(|| conjunction(rest)),
+                }
+            }
+            plan => plan,
+        };
+        map_children(plan, &mut |child| self.apply(child))
+    }
+}
+
+/// The positions of the columns `expr` equals if it is `left = right` or `right = left`,
+/// with a column of each side of a join, the left one having `left_width` columns.
+fn equi_key(expr: &Expr<'_>, layout: &Layout, left_width: usize) -> Option<(usize, usize)> {
+    let Expr::Binary {
+        op: (_, BinaryOperator::Eq),
+        left,
+        right,
+    } = expr
+    else {
+        return None;
+    };
+    let (Expr::Column(a), Expr::Column(b)) = (&**left, &**right) else {
+        return None;
+    };
+    let (a, b) = (layout.index(a)?, layout.index(b)?);
+    match (a < left_width, b < left_width) {
+        (true, false) => Some((a, b - left_width)),
+        (false, true) => Some((b, a - left_width)),
+        _ => None,
+    }
+}
+
+/// Reads the rows of a filter over a table through one of its indexes, when the filter
+/// compares the first columns of its key with constants: a [`Plan::IndexScan`] for
+/// equalities on them, else a [`Plan::IndexRangeScan`] for a range of the first one. The
+/// index with the most columns compared for equality is used, and none when the conjuncts
+/// it answers are estimated to keep more than `max_selectivity` of the rows, as reading
+/// most of a table through an index costs more than scanning it. The other conjuncts stay
+/// in the filter.
+pub struct IndexSelection<'s> {
+    pub storage: &'s dyn Storage,
+    pub max_selectivity: f64,
+}
+
+impl<'s> IndexSelection<'s> {
+    #[must_use]
+    pub const fn new(storage: &'s dyn Storage) -> Self {
+        Self {
+            storage,
+            max_selectivity: 0.5,
+        }
+    }
+
+    /// The plan that reads the rows of `scan` for which `predicate` is TRUE.
+    fn select<'a>(&self, scan: Plan<'a>, predicate: Expr<'a>) -> Plan<'a> {
+        let Plan::Scan { table, layout, .. } = &scan else {
+            return Plan::Filter {
+                input: Box::new(scan),
+                predicate,
+            };
+        };
+        let conjuncts = conjuncts(predicate.clone());
+        let comparisons = conjuncts
+            .iter()
+            .map(|conjunct| comparison(conjunct, layout))
+            .collect::<Vec<_>>();
+        let mut best: Option<(IndexAccess<'a>, Vec<usize>)> = None;
+        for index in self.storage.indexes(table) {
+            let Some((access, used)) = IndexAccess::new(index, &comparisons) else {
+                continue;
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(best, _)| access.rank() > best.rank())
+            {
+                best = Some((access, used));
+            }
+        }
+        let Some((access, used)) = best else {
+            return Plan::Filter {
+                input: Box::new(scan),
+                predicate,
+            };
+        };
+        let answered = used.iter().map(|i| conjuncts[*i].clone()).collect();
+        if scan.selectivity(&conjunction(answered)) > self.max_selectivity {
+            return Plan::Filter {
+                input: Box::new(scan),
+                predicate,
+            };
+        }
+        let Plan::Scan {
+            table,
+            layout,
+            columns,
+            statistics,
+            ..
+        } = scan
+        else {
+            unreachable!("checked above");
+        };
+        let plan = match access {
+            IndexAccess::Key(index, key) => Plan::IndexScan {
+                table,
+                index,
+                key,
+                layout,
+                columns,
+                index_only: false,
+                statistics,
+            },
+            IndexAccess::Range(index, lower, upper) => Plan::IndexRangeScan {
+                table,
+                index,
+                lower,
+                upper,
+                layout,
+                columns,
+                index_only: false,
+                statistics,
+            },
+        };
+        let rest = conjuncts
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !used.contains(i))
+            .map(|(_, conjunct)| conjunct)
+            .collect::<Vec<_>>();
+        if rest.is_empty() {
+            plan
+        } else {
+            Plan::Filter {
+                input: Box::new(plan),
+                predicate: conjunction(rest),
+            }
+        }
+    }
+}
+
+impl Rule for IndexSelection<'_> {
+    fn name(&self) -> &'static str {
+        "index_selection"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        match plan {
+            Plan::Filter { input, predicate } if matches!(*input, Plan::Scan { .. }) => {
+                self.select(*input, predicate)
+            }
+            plan => map_children(plan, &mut |child| self.apply(child)),
+        }
+    }
+}
+
+/// Gives a scan under a filter the ranges of the columns its conjuncts compare with
+/// constants, so a storage that keeps the values of a column together, like a columnar
+/// table, skips the rows out of them without reading them, and one that keeps a bloom
+/// filter of a column skips the partitions or segments without the value of an equality.
+/// The filter stays, a storage needn't skip them.
+pub struct ScanRanges;
+
+impl Rule for ScanRanges {
+    fn name(&self) -> &'static str {
+        "scan_ranges"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        match plan {
+            Plan::Filter { input, predicate } if matches!(*input, Plan::Scan { .. }) => {
+                let Plan::Scan {
+                    table,
+                    layout,
+                    columns,
+                    partitions,
+                    statistics,
+                    ..
+                } = *input
+                else {
+                    unreachable!("checked above");
+                };
+                let ranges = conjuncts(predicate.clone())
+                    .iter()
+                    .filter_map(|conjunct| {
+                        let (name, comparison) = comparison(conjunct, &layout)?;
+                        let position = layout.0.iter().position(|field| field.name == name)?;
+                        let (lower, upper) = match comparison {
+                            Comparison::Eq(value) => (
+                                Bound::Included(Box::new(value.clone())),
+                                Bound::Included(Box::new(value)),
+                            ),
+                            Comparison::Lower(bound) => (bound, Bound::Unbounded),
+                            Comparison::Upper(bound) => (Bound::Unbounded, bound),
+                            Comparison::Between(low, high) => (
+                                Bound::Included(Box::new(low)),
+                                Bound::Included(Box::new(high)),
+                            ),
+                        };
+                        Some(ScanRange {
+                            column: columns
+                                .as_ref()
+                                .map_or(position, |columns| columns[position]),
+                            lower,
+                            upper,
+                        })
+                    })
+                    .collect();
+                Plan::Filter {
+                    input: Box::new(Plan::Scan {
+                        table,
+                        layout,
+                        columns,
+                        ranges,
+                        partitions,
+                        statistics,
+                    }),
+                    predicate,
+                }
+            }
+            plan => map_children(plan, &mut |child| self.apply(child)),
+        }
+    }
+}
+
+/// Leaves out of a scan of a partitioned table the partitions its ranges rule out, see
+/// [`Partitioning::prune`](rs_db_parser::ast::commands::create::Partitioning::prune). Only
+/// the bounds that are literals rule partitions out, the
+/// others, like parameters, are only known when the statement runs.
+pub struct PartitionPruning<'s> {
+    pub storage: &'s dyn Storage,
+}
+
+impl Rule for PartitionPruning<'_> {
+    fn name(&self) -> &'static str {
+        "partition_pruning"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        match plan {
+            Plan::Scan {
+                table,
+                layout,
+                columns,
+                ranges,
+                partitions: None,
+                statistics,
+            } => {
+                let partitions = self.storage.partitions(&table).and_then(|partitions| {
+                    let mut read = (0..partitions.tables.len()).collect::<Vec<_>>();
+                    for range in ranges
+                        .iter()
+                        .filter(|range| range.column == partitions.column)
+                    {
+                        let kept = partitions
+                            .partitioning
+                            .prune(literal_bound(&range.lower), literal_bound(&range.upper));
+                        read.retain(|i| kept.contains(i));
+                    }
+                    (read.len() < partitions.tables.len()).then(|| {
+                        read.into_iter()
+                            .map(|i| partitions.tables[i].clone())
+                            .collect()
+                    })
+                });
+                Plan::Scan {
+                    table,
+                    layout,
+                    columns,
+                    ranges,
+                    partitions,
+                    statistics,
+                }
+            }
+            plan => map_children(plan, &mut |child| self.apply(child)),
+        }
+    }
+}
+
+/// The value of `bound` if it is a literal, else `Unbounded`.
+fn literal_bound<'e>(bound: &'e Bound<Box<Expr<'_>>>) -> Bound<&'e Value> {
+    let literal = |expr: &'e Expr<'_>| match expr {
+        Expr::Literal((_, value)) => Some(value),
+        _ => None,
+    };
+    match bound {
+        Bound::Included(expr) => literal(expr).map_or(Bound::Unbounded, Bound::Included),
+        Bound::Excluded(expr) => literal(expr).map_or(Bound::Unbounded, Bound::Excluded),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// How a conjunct compares a column with a constant.
+enum Comparison<'a> {
+    Eq(Expr<'a>),
+    Lower(Bound<Box<Expr<'a>>>),
+    Upper(Bound<Box<Expr<'a>>>),
+    Between(Expr<'a>, Expr<'a>),
+}
+
+/// The name of the column of `layout` that `expr` compares with a constant, and how.
+fn comparison<'a>(expr: &Expr<'a>, layout: &Layout) -> Option<(Box<str>, Comparison<'a>)> {
+    let name = |expr: &Expr<'_>| match expr {
+        Expr::Column(column) => Some(layout.0[layout.index(column)?].name.clone()),
+        _ => None,
+    };
+    let constant = |expr: &Expr<'_>| expr.columns().is_empty();
+    match expr {
+        Expr::Nested((_, expr)) => comparison(expr, layout),
+        Expr::Binary {
+            op: (_, op),
+            left,
+            right,
+        } => {
+            // `constant < column` is `column > constant`.
+            let (column, value, op) = match (name(left), name(right)) {
+                (Some(column), None) if constant(right) => (column, right, *op),
+                (None, Some(column)) if constant(left) => {
+                    let op = match op {
+                        BinaryOperator::Lt => BinaryOperator::Gt,
+                        BinaryOperator::LtEq => BinaryOperator::GtEq,
+                        BinaryOperator::Gt => BinaryOperator::Lt,
+                        BinaryOperator::GtEq => BinaryOperator::LtEq,
+                        op => *op,
+                    };
+                    (column, left, op)
+                }
+                _ => return None,
+            };
+            let value = value.clone();
+            let comparison = match op {
+                BinaryOperator::Eq => Comparison::Eq(*value),
+                BinaryOperator::Gt => Comparison::Lower(Bound::Excluded(value)),
+                BinaryOperator::GtEq => Comparison::Lower(Bound::Included(value)),
+                BinaryOperator::Lt => Comparison::Upper(Bound::Excluded(value)),
+                BinaryOperator::LtEq => Comparison::Upper(Bound::Included(value)),
+                _ => return None,
+            };
+            Some((column, comparison))
+        }
+        Expr::Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        } if constant(low) && constant(high) => Some((
+            name(expr)?,
+            Comparison::Between((**low).clone(), (**high).clone()),
+        )),
+        _ => None,
+    }
+}
+
+/// How [`IndexSelection`] reads an index.
+enum IndexAccess<'a> {
+    /// The equalities on the first columns of the key.
+    Key(Index, Vec<Expr<'a>>),
+    /// The bounds of the first column of the key.
+    Range(Index, Bound<Box<Expr<'a>>>, Bound<Box<Expr<'a>>>),
+}
+
+impl<'a> IndexAccess<'a> {
+    /// The access to `index` the conjuncts of `comparisons` allow, with the positions of
+    /// the ones it answers.
+    fn new(
+        index: Index,
+        comparisons: &[Option<(Box<str>, Comparison<'a>)>],
+    ) -> Option<(Self, Vec<usize>)> {
+        let mut key = Vec::new();
+        let mut used = Vec::new();
+        for column in &index.columns {
+            let found =
+                comparisons
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, comparison)| match comparison {
+                        Some((name, Comparison::Eq(value))) if name == column => Some((i, value)),
+                        _ => None,
+                    });
+            let Some((i, value)) = found else {
+                break;
+            };
+            key.push(value.clone());
+            used.push(i);
+        }
+        if !key.is_empty() {
+            return Some((Self::Key(index, key), used));
+        }
+        let first = index.columns.first()?;
+        let (mut lower, mut upper) = (Bound::Unbounded, Bound::Unbounded);
+        for (i, comparison) in comparisons.iter().enumerate() {
+            match comparison {
+                Some((name, comparison)) if name == first => match comparison {
+                    Comparison::Lower(bound) if lower == Bound::Unbounded => {
+                        lower = bound.clone();
+                    }
+                    Comparison::Upper(bound) if upper == Bound::Unbounded => {
+                        upper = bound.clone();
+                    }
+                    Comparison::Between(low, high)
+                        if lower == Bound::Unbounded && upper == Bound::Unbounded =>
+                    {
+                        lower = Bound::Included(Box::new(low.clone()));
+                        upper = Bound::Included(Box::new(high.clone()));
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            }
+            used.push(i);
+        }
+        (!used.is_empty()).then_some((Self::Range(index, lower, upper), used))
+    }
+
+    /// How few rows the access is assumed to read, an equality on more columns being
+    /// better than one on fewer, and any being better than a range, a closed one first.
+    fn rank(&self) -> (usize, usize) {
+        match self {
+            Self::Key(_, key) => (key.len(), 0),
+            Self::Range(_, lower, upper) => (
+                0,
+                usize::from(*lower != Bound::Unbounded) + usize::from(*upper != Bound::Unbounded),
+            ),
+        }
+    }
+}
+
+/// Reads only the columns of the tables the plan uses, and drops the computed columns
+/// nothing reads, so fewer values are copied from node to node.
+pub struct ProjectionPruning;
+
+impl Rule for ProjectionPruning {
+    fn name(&self) -> &'static str {
+        "projection_pruning"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        let required = vec![true; plan.layout().len()];
+        prune(plan, &required).0
+    }
+}
+
+/// Keeps the columns of the output of `plan` that `required` marks, and the ones its nodes
+/// read. Returns the pruned plan and the new position of each former column of its output.
+fn prune<'a>(plan: Plan<'a>, required: &[bool]) -> (Plan<'a>, Vec<Option<usize>>) {
+    match plan {
+        Plan::Scan {
+            table,
+            layout,
+            columns,
+            ranges,
+            partitions,
+            statistics,
+        } => {
+            let kept = kept(required);
+            let columns = kept
+                .iter()
+                .map(|i| columns.as_ref().map_or(*i, |columns| columns[*i]))
+                .collect();
+            let plan = Plan::Scan {
+                table,
+                layout: Layout(kept.iter().map(|i| layout.0[*i].clone()).collect()),
+                columns: Some(columns),
+                ranges,
+                partitions,
+                statistics,
+            };
+            (plan, positions(required))
+        }
+        Plan::IndexScan {
+            table,
+            index,
+            key,
+            layout,
+            columns,
+            index_only,
+            statistics,
+        } => {
+            let (layout, columns, index_only) =
+                prune_index_scan(&index, &layout, columns.as_deref(), index_only, required);
+            let plan = Plan::IndexScan {
+                table,
+                index,
+                key,
+                layout,
+                columns: Some(columns),
+                index_only,
+                statistics,
+            };
+            (plan, positions(required))
+        }
+        Plan::IndexRangeScan {
+            table,
+            index,
+            lower,
+            upper,
+            layout,
+            columns,
+            index_only,
+            statistics,
+        } => {
+            let (layout, columns, index_only) =
+                prune_index_scan(&index, &layout, columns.as_deref(), index_only, required);
+            let plan = Plan::IndexRangeScan {
+                table,
+                index,
+                lower,
+                upper,
+                layout,
+                columns: Some(columns),
+                index_only,
+                statistics,
+            };
+            (plan, positions(required))
+        }
+        Plan::Values { rows, layout } => {
+            let kept = kept(required);
+            let plan = Plan::Values {
+                rows: rows
+                    .into_iter()
+                    .map(|row| kept.iter().map(|i| row[*i].clone()).collect())
+                    .collect(),
+                layout: Layout(kept.iter().map(|i| layout.0[*i].clone()).collect()),
+            };
+            (plan, positions(required))
+        }
+        Plan::Filter { input, predicate } => {
+            let mut needed = required.to_vec();
+            mark(&predicate, &input.layout(), &mut needed);
+            let (input, mapping) = prune(*input, &needed);
+            let plan = Plan::Filter {
+                input: Box::new(input),
+                predicate,
+            };
+            (plan, mapping)
+        }
+        Plan::Project {
+            input,
+            items,
+            layout,
+        } => {
+            let input_layout = input.layout();
+            let mut needed = vec![false; input_layout.len()];
+            for (item, _) in items.iter().zip(required).filter(|(_, r)| **r) {
+                match item {
+                    ProjectItem::Column(index) => needed[*index] = true,
+                    ProjectItem::Expr(expr) => mark(expr, &input_layout, &mut needed),
+                }
+            }
+            let (input, mapping) = prune(*input, &needed);
+            let items = items
+                .into_iter()
+                .zip(required)
+                .filter(|(_, r)| **r)
+                .map(|(item, _)| match item {
+                    ProjectItem::Column(index) => {
+                        ProjectItem::Column(mapping[index].expect("a required column"))
+                    }
+                    item @ ProjectItem::Expr(_) => item,
+                })
+                .collect();
+            let layout = Layout(
+                layout
+                    .0
+                    .into_iter()
+                    .zip(required)
+                    .filter(|(_, r)| **r)
+                    .map(|(field, _)| field)
+                    .collect(),
+            );
+            let plan = Plan::Project {
+                input: Box::new(input),
+                items,
+                layout,
+            };
+            (plan, positions(required))
+        }
+        Plan::Join {
+            kind,
+            algorithm,
+            left,
+            right,
+            keys,
+            condition,
+        } => {
+            let left_width = left.layout().len();
+            let mut needed = required.to_vec();
+            for (l, r) in &keys {
+                needed[*l] = true;
+                needed[left_width + r] = true;
+            }
+            if let Some(condition) = &condition {
+                mark(
+                    condition,
+                    &left.layout().concat(&right.layout()),
+                    &mut needed,
+                );
+            }
+            let (left, left_mapping) = prune(*left, &needed[..left_width]);
+            let (right, right_mapping) = prune(*right, &needed[left_width..]);
+            let keys = keys
+                .iter()
+                .map(|(l, r)| {
+                    (
+                        left_mapping[*l].expect("a key column"),
+                        right_mapping[*r].expect("a key column"),
+                    )
+                })
+                .collect();
+            let new_width = left.layout().len();
+            let mapping = left_mapping
+                .into_iter()
+                .chain(right_mapping.into_iter().map(|p| p.map(|p| p + new_width)))
+                .collect();
+            let plan = Plan::Join {
+                kind,
+                algorithm,
+                left: Box::new(left),
+                right: Box::new(right),
+                keys,
+                condition,
+            };
+            (plan, mapping)
+        }
+        // The groups and the aggregates are all computed, whatever is read of them.
+        Plan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            layout,
+        } => {
+            let input_layout = input.layout();
+            let mut needed = vec![false; input_layout.len()];
+            let args = aggregates.iter().filter_map(|call| call.arg.as_ref());
+            for expr in group_by.iter().chain(args) {
+                mark(expr, &input_layout, &mut needed);
+            }
+            let (input, _) = prune(*input, &needed);
+            let mapping = (0..layout.len()).map(Some).collect();
+            let plan = Plan::Aggregate {
+                input: Box::new(input),
+                group_by,
+                aggregates,
+                layout,
+            };
+            (plan, mapping)
+        }
+        Plan::Sort { input, keys } => {
+            let mut needed = required.to_vec();
+            let input_layout = input.layout();
+            for key in &keys {
+                mark(&key.expr, &input_layout, &mut needed);
+            }
+            let (input, mapping) = prune(*input, &needed);
+            let plan = Plan::Sort {
+                input: Box::new(input),
+                keys,
+            };
+            (plan, mapping)
+        }
+        Plan::Limit {
+            input,
+            limit,
+            offset,
+        } => {
+            let (input, mapping) = prune(*input, required);
+            let plan = Plan::Limit {
+                input: Box::new(input),
+                limit,
+                offset,
+            };
+            (plan, mapping)
+        }
+        // The rows written keep all the columns of the table.
+        Plan::Insert {
+            table,
+            input,
+            constraints,
+        } => {
+            let all = vec![true; input.layout().len()];
+            let (input, mapping) = prune(*input, &all);
+            let plan = Plan::Insert {
+                table,
+                input: Box::new(input),
+                constraints,
+            };
+            (plan, mapping)
+        }
+    }
+}
+
+/// The layout, the columns and whether only the index is read of an index scan that keeps
+/// the columns `required` marks: the index is enough when its key has them all, the
+/// columns are then their positions in the key.
+fn prune_index_scan(
+    index: &Index,
+    layout: &Layout,
+    columns: Option<&[usize]>,
+    index_only: bool,
+    required: &[bool],
+) -> (Layout, Vec<usize>, bool) {
+    let kept = kept(required);
+    let layout = Layout(kept.iter().map(|i| layout.0[*i].clone()).collect());
+    let in_key = layout
+        .0
+        .iter()
+        .map(|field| {
+            index
+                .columns
+                .iter()
+                .position(|column| *column == field.name)
+        })
+        .collect::<Option<Vec<_>>>();
+    match in_key {
+        Some(positions) if !index_only => (layout, positions, true),
+        _ => {
+            let columns = kept
+                .iter()
+                .map(|i| columns.map_or(*i, |columns| columns[*i]))
+                .collect();
+            (layout, columns, index_only)
+        }
+    }
+}
+
+fn kept(required: &[bool]) -> Vec<usize> {
+    (0..required.len()).filter(|i| required[*i]).collect()
+}
+
+/// The position of each column once the ones `required` doesn't mark are removed.
+fn positions(required: &[bool]) -> Vec<Option<usize>> {
+    let mut next = 0;
+    required
+        .iter()
+        .map(|required| {
+            required.then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+/// Marks the columns of `layout` that `expr` reads.
+fn mark(expr: &Expr<'_>, layout: &Layout, needed: &mut [bool]) {
+    for column in expr.columns() {
+        if let Some(index) = layout.index(&column) {
+            needed[index] = true;
+        }
+    }
+}
+
+/// Removes the projections that return their input as is, like the one of `SELECT *`.
+pub struct RemoveNoOpProjections;
+
+impl Rule for RemoveNoOpProjections {
+    fn name(&self) -> &'static str {
+        "remove_no_op_projections"
+    }
+
+    fn apply<'a>(&self, plan: Plan<'a>) -> Plan<'a> {
+        match plan {
+            Plan::Project {
+                input,
+                items,
+                layout,
+            } if layout == input.layout()
+                && items
+                    .iter()
+                    .enumerate()
+                    .all(|(i, item)| *item == ProjectItem::Column(i)) =>
+            {
+                self.apply(*input)
+            }
+            plan => map_children(plan, &mut |child| self.apply(child)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{ast::statement::Statement, parse::RawSpan, parse::TableMap};
+
+    use super::*;
+    use crate::{
+        cancel::CancellationToken,
+        executor::Config,
+        operator::{build, Context},
+        storage::{testing::TestStorage, Storage},
+        Row,
+    };
+
+    const DDL: &[&str] = &[
+        "CREATE TABLE users (id INT32, name VARCHAR(10), active BOOL)",
+        "CREATE TABLE orders (id INT32, user_id INT32, total INT64)",
+    ];
+
+    fn plan<'a>(input: &'a str, table_map: &'a TableMap) -> Plan<'a> {
+        let (_, statement) =
+            Statement::parse_with_table_map(table_map, RawSpan::new(input)).unwrap();
+        Plan::from_statement(&statement, table_map).unwrap()
+    }
+
+    fn run(plan: &Plan<'_>, storage: &dyn Storage) -> Vec<Row> {
+        let config = Config::default();
+        let context = Context {
+            storage,
+            functions: FunctionRegistry::builtins(),
+            config: &config,
+            profile: None,
+            cancellation: &CancellationToken::new(),
+        };
+        let mut operator = build(plan, context).unwrap();
+        let mut rows = Vec::new();
+        while let Some(row) = operator.next().unwrap() {
+            rows.push(row);
+        }
+        rows
+    }
+
+    /// The input of the projection at the top of `plan`.
+    fn below_project(plan: Plan<'_>) -> Plan<'_> {
+        let Plan::Project { input, .. } = plan else {
+            panic!("{plan:?}");
+        };
+        *input
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let (_, table_map) = TestStorage::new(DDL);
+        let rule = ConstantFolding(FunctionRegistry::builtins());
+
+        let folded = rule.apply(plan(
+            "SELECT id + (1 + 2) FROM users WHERE id > 2 * 3",
+            &table_map,
+        ));
+        let Plan::Project { input, items, .. } = folded else {
+            panic!("{folded:?}");
+        };
+        assert!(matches!(&items[0], ProjectItem::Expr(e) if e.to_string() == "id + 3"));
+        let Plan::Filter { predicate, .. } = *input else {
+            panic!("{input:?}");
+        };
+        assert_eq!(predicate.to_string(), "id > 6");
+
+        let folded = rule.apply(plan("SELECT id FROM users WHERE 1 = 1", &table_map));
+        assert!(matches!(below_project(folded), Plan::Scan { .. }));
+        let folded = rule.apply(plan("SELECT id FROM users WHERE NULL", &table_map));
+        assert!(matches!(below_project(folded), Plan::Values { rows, .. } if rows.is_empty()));
+        // Failing expressions are left for the executor, which may not evaluate them.
+        let folded = rule.apply(plan("SELECT id FROM users WHERE 1 / 0 = 1", &table_map));
+        assert!(matches!(below_project(folded), Plan::Filter { .. }));
+    }
+
+    #[test]
+    fn test_predicate_pushdown() {
+        let (_, table_map) = TestStorage::new(DDL);
+        let pushed = PredicatePushdown.apply(plan(
+            "SELECT u.name FROM users u JOIN orders o ON TRUE \
+             WHERE u.active AND o.total > 1 AND o.user_id = u.id",
+            &table_map,
+        ));
+        let Plan::Join {
+            left,
+            right,
+            condition,
+            ..
+        } = below_project(pushed)
+        else {
+            panic!();
+        };
+        assert!(
+            matches!(*left, Plan::Filter { predicate, .. } if predicate.to_string() == "u.active")
+        );
+        assert!(
+            matches!(*right, Plan::Filter { predicate, .. } if predicate.to_string() == "o.total > 1")
+        );
+        assert_eq!(condition.unwrap().to_string(), "TRUE AND o.user_id = u.id");
+
+        // The side an outer join fills with NULLs is filtered after the join.
+        let pushed = PredicatePushdown.apply(plan(
+            "SELECT u.name FROM users u LEFT JOIN orders o ON o.user_id = u.id \
+             WHERE u.active AND o.id IS NULL",
+            &table_map,
+        ));
+        let Plan::Filter { input, predicate } = below_project(pushed) else {
+            panic!();
+        };
+        assert_eq!(predicate.to_string(), "o.id IS NULL");
+        assert!(matches!(*input, Plan::Join { left, .. } if matches!(*left, Plan::Filter { .. })));
+    }
+
+    #[test]
+    fn test_join_ordering() {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE a (x INT32)",
+            "CREATE TABLE b (y INT32)",
+            "CREATE TABLE c (x INT32, y INT32)",
+        ]);
+        for i in 0..20 {
+            storage.insert("public.a", vec![Value::I32(i)]).unwrap();
+            storage.insert("public.b", vec![Value::I32(i)]).unwrap();
+            if i % 4 == 0 {
+                storage
+                    .insert("public.c", vec![Value::I32(i), Value::I32(i + 1)])
+                    .unwrap();
+            }
+        }
+        fn order(plan: &Plan<'_>) -> String {
+            match plan {
+                Plan::Scan { table, .. } => table.trim_start_matches("public.").into(),
+                Plan::Join {
+                    kind, left, right, ..
+                } => {
+                    assert_ne!(*kind, JoinKind::Cross);
+                    format!("({} {})", order(left), order(right))
+                }
+                plan => order(plan.children()[0]),
+            }
+        }
+        let input = "SELECT * FROM a CROSS JOIN b CROSS JOIN c WHERE a.x = c.x AND b.y = c.y";
+        let query = PredicatePushdown.apply(plan(input, &table_map));
+        let mut rule = JoinOrdering::new(&storage);
+        // The large cross join of `a` and `b` is avoided, greedily too.
+        for (exhaustive_tables, expected) in [(10, "((b c) a)"), (0, "(b (a c))")] {
+            rule.exhaustive_tables = exhaustive_tables;
+            let ordered = rule.apply(query.clone());
+            assert_eq!(order(&below_project(ordered.clone())), expected);
+            assert_eq!(ordered.layout(), query.layout());
+            assert_eq!(run(&ordered, &storage), run(&query, &storage));
+        }
+        // An unqualified name both `a` and `c` have could read the other one once reordered.
+        let query = plan(
+            "SELECT * FROM a JOIN c USING (x) JOIN b ON b.y = x",
+            &table_map,
+        );
+        assert!(JoinGraph::new(&below_project(query), &storage).is_none());
+    }
+
+    #[test]
+    fn test_join_selection() {
+        let (storage, table_map) = TestStorage::new(DDL);
+        storage
+            .insert(
+                "public.users",
+                vec![Value::Null, Value::I32(1), Value::Null],
+            )
+            .unwrap();
+        storage
+            .insert(
+                "public.orders",
+                vec![Value::I32(1), Value::Null, Value::I32(1)],
+            )
+            .unwrap();
+        let join = |rule: &JoinSelection<'_>, input| {
+            let plan = below_project(rule.apply(plan(input, &table_map)));
+            let Plan::Join {
+                kind,
+                algorithm,
+                keys,
+                condition,
+                ..
+            } = plan
+            else {
+                panic!("{plan:?}");
+            };
+            (kind, algorithm, keys, condition.map(|c| c.to_string()))
+        };
+        let mut rule = JoinSelection::new(&storage);
+        assert_eq!(
+            join(
+                &rule,
+                "SELECT * FROM users u JOIN orders o ON o.user_id = u.id AND o.total > u.id"
+            ),
+            (
+                JoinKind::Inner,
+                JoinAlgorithm::Hash,
+                vec![(1, 2)],
+                Some("o.total > u.id".into())
+            )
+        );
+        assert_eq!(
+            join(
+                &rule,
+                "SELECT * FROM users u LEFT JOIN orders o ON u.id < o.id"
+            ),
+            (
+                JoinKind::Left,
+                JoinAlgorithm::NestedLoop,
+                vec![],
+                Some("u.id < o.id".into())
+            )
+        );
+        rule.merge_join_rows = 0;
+        assert_eq!(
+            join(&rule, "SELECT * FROM users JOIN orders USING (id)"),
+            (JoinKind::Inner, JoinAlgorithm::Merge, vec![(1, 0)], None)
+        );
+    }
+
+    #[test]
+    fn test_index_selection() {
+        let (mut storage, table_map) = TestStorage::new(DDL);
+        storage.create_index("public.orders", "orders_id", &["id"]);
+        storage.create_index("public.orders", "orders_user", &["user_id", "total"]);
+        for i in 0..20 {
+            let row = vec![
+                Value::I32(i),
+                Value::I64(i64::from(i % 7)),
+                Value::I32(i % 4),
+            ];
+            storage.insert("public.orders", row).unwrap();
+        }
+        let mut rule = IndexSelection::new(&storage);
+
+        // The equality on the first column of the key is answered by the index, the range on
+        // the second one stays in the filter.
+        let input = "SELECT id FROM orders WHERE user_id = 2 AND total > 3";
+        let selected = below_project(rule.apply(plan(input, &table_map)));
+        let Plan::Filter { input, predicate } = &selected else {
+            panic!("{selected:?}");
+        };
+        assert_eq!(predicate.to_string(), "total > 3");
+        let Plan::IndexScan { index, key, .. } = &**input else {
+            panic!("{input:?}");
+        };
+        assert_eq!(&*index.name, "orders_user");
+        assert_eq!(key[0].to_string(), "2");
+        let ids = run(&selected, &storage)
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect::<Vec<_>>();
+        // In the order of the keys, by `total`.
+        assert_eq!(ids, [18, 6].map(Value::I32));
+
+        // The index has all the columns the query reads, the table isn't read.
+        let input = "SELECT total, user_id FROM orders WHERE user_id = 1 AND 5 = total";
+        let pruned = ProjectionPruning.apply(rule.apply(plan(input, &table_map)));
+        let Plan::IndexScan {
+            key,
+            columns,
+            index_only,
+            ..
+        } = below_project(pruned.clone())
+        else {
+            panic!("{pruned:?}");
+        };
+        assert_eq!(key.len(), 2);
+        assert!(index_only);
+        assert_eq!(columns, Some(vec![1, 0]));
+        assert_eq!(run(&pruned, &storage), [vec![Value::I64(5), Value::I32(1)]]);
+
+        // A range of the first column of the key, the bounds as they compare the column.
+        let input = "SELECT * FROM orders WHERE 10 > id AND id >= 7";
+        let selected = below_project(rule.apply(plan(input, &table_map)));
+        let Plan::IndexRangeScan {
+            lower,
+            upper,
+            index,
+            ..
+        } = &selected
+        else {
+            panic!("{selected:?}");
+        };
+        assert_eq!(&*index.name, "orders_id");
+        assert!(matches!(lower, Bound::Included(e) if e.to_string() == "7"));
+        assert!(matches!(upper, Bound::Excluded(e) if e.to_string() == "10"));
+        let ids = run(&selected, &storage)
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [7, 8, 9].map(Value::I32));
+        let input = "SELECT * FROM orders WHERE id BETWEEN 3 AND 4";
+        let selected = below_project(rule.apply(plan(input, &table_map)));
+        assert_eq!(run(&selected, &storage).len(), 2);
+
+        // No index for the column, or a range expected to keep too many rows.
+        let input = "SELECT * FROM orders WHERE total = 1";
+        let selected = below_project(rule.apply(plan(input, &table_map)));
+        assert!(matches!(selected, Plan::Filter { .. }));
+        rule.max_selectivity = 0.2;
+        let input = "SELECT * FROM orders WHERE id > 3";
+        let selected = below_project(rule.apply(plan(input, &table_map)));
+        assert!(matches!(selected, Plan::Filter { .. }));
+    }
+
+    #[test]
+    fn test_projection_pruning() {
+        let (_, table_map) = TestStorage::new(DDL);
+        let pruned = ProjectionPruning.apply(plan(
+            "SELECT u.name FROM users u JOIN orders o ON o.user_id = u.id",
+            &table_map,
+        ));
+        assert_eq!(pruned.layout().names(), ["name".into()]);
+        let Plan::Project { input, items, .. } = pruned else {
+            panic!("{pruned:?}");
+        };
+        assert_eq!(items, [ProjectItem::Column(1)]);
+        let Plan::Join { left, right, .. } = *input else {
+            panic!("{input:?}");
+        };
+        // The columns of a table are sorted by name: `active`, `id` and `name` for users.
+        assert!(matches!(*left, Plan::Scan { columns: Some(c), .. } if c == [1, 2]));
+        assert!(matches!(*right, Plan::Scan { columns: Some(c), .. } if c == [2]));
+    }
+
+    #[test]
+    fn test_remove_no_op_projections() {
+        let (_, table_map) = TestStorage::new(DDL);
+        let removed = RemoveNoOpProjections.apply(plan("SELECT * FROM users", &table_map));
+        assert!(matches!(removed, Plan::Scan { .. }));
+        let kept = RemoveNoOpProjections.apply(plan("SELECT id, name FROM users", &table_map));
+        assert!(matches!(kept, Plan::Project { .. }));
+    }
+
+    #[test]
+    fn test_optimize_keeps_rows() {
+        let (storage, table_map) = TestStorage::new(DDL);
+        for (table, rows) in [
+            (
+                "public.users",
+                vec![
+                    vec![
+                        Value::Bool(true),
+                        Value::I32(1),
+                        Value::VarChar("ana".into()),
+                    ],
+                    vec![
+                        Value::Bool(false),
+                        Value::I32(2),
+                        Value::VarChar("bob".into()),
+                    ],
+                    vec![Value::Null, Value::I32(3), Value::VarChar("cid".into())],
+                ],
+            ),
+            (
+                "public.orders",
+                vec![
+                    vec![Value::I32(10), Value::I64(5), Value::I32(1)],
+                    vec![Value::I32(11), Value::I64(7), Value::I32(2)],
+                    vec![Value::I32(12), Value::I64(3), Value::I32(4)],
+                ],
+            ),
+        ] {
+            for row in rows {
+                storage.insert(table, row).unwrap();
+            }
+        }
+        let optimizer = Optimizer::standard(FunctionRegistry::builtins(), &storage);
+        for input in [
+            "SELECT * FROM users WHERE 1 < 2 AND id > 1",
+            "SELECT u.name, o.total FROM users u JOIN orders o ON o.user_id = u.id \
+             WHERE u.active AND o.total > 1 ORDER BY o.total",
+            "SELECT u.name, o.id FROM users u FULL JOIN orders o ON o.user_id = u.id \
+             WHERE o.total > 4 OR u.id = 3 ORDER BY u.id",
+            "SELECT o.total * 2 AS t FROM users CROSS JOIN orders o \
+             WHERE users.id = o.user_id ORDER BY t DESC LIMIT 1",
+            "SELECT users.name FROM users JOIN orders USING (id) WHERE orders.total > 1",
+        ] {
+            let plan = plan(input, &table_map);
+            let optimized = optimizer.optimize(plan.clone());
+            assert_ne!(plan, optimized, "{input}");
+            assert_eq!(run(&plan, &storage), run(&optimized, &storage), "{input}");
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::path::Path;
+
+use rs_db_parser::{ast::commands::create::Partitioning, parse::TableMap, value::Value};
+use rs_db_storage::engine::ScanOptions;
+
+use crate::{
+    error::ExecError,
+    storage::{Index, KeyRange, RowIter, Storage},
+    Row,
+};
+
+/// How the rows of a partitioned table are split in tables of the storage, see
+/// [`PartitionedStorage`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Partitions {
+    pub partitioning: Partitioning,
+    /// The position of the column of the partitioning in the rows of the table.
+    pub column: usize,
+    /// The table of each partition, in the order of the partitioning, see
+    /// [`partition_key`].
+    pub tables: Vec<Box<str>>,
+}
+
+/// The name of the table of the partition `partition` of `table`, a
+/// [`table_key`](crate::storage::table_key), in the storage: `schema.table#partition`.
+#[must_use]
+pub fn partition_key(table: &str, partition: &str) -> Box<str> {
+    format!("{table}#{partition}").into()
+}
+
+/// A storage whose partitioned tables, the ones with a [`Partitioning`] in the catalog, are
+/// kept as a table of `storage` per partition, named by [`partition_key`]. A row added to a
+/// partitioned table is written in the table of its partition, and a scan of it reads the
+/// tables of all its partitions, one after the other. The partitions are created with
+/// their table, like the other tables.
+pub struct PartitionedStorage<'s> {
+    storage: &'s dyn Storage,
+    table_map: &'s TableMap,
+}
+
+impl<'s> PartitionedStorage<'s> {
+    #[must_use]
+    pub fn new(storage: &'s dyn Storage, table_map: &'s TableMap) -> Self {
+        Self { storage, table_map }
+    }
+
+    /// The partitioning of `table` and the position of its column in the rows, whose
+    /// columns are sorted by name.
+    fn partitioning(&self, table: &str) -> Option<(&'s Partitioning, usize)> {
+        let (schema, name) = table.split_once('.')?;
+        let partitioning = self.table_map.partitioning(Some(schema), name)?;
+        let columns = self.table_map.get_qualified(Some(schema), name)?;
+        let column = columns
+            .keys()
+            .filter(|column| **column < partitioning.column)
+            .count();
+        Some((partitioning, column))
+    }
+
+    /// The table of the partition of `table` that holds `row`, `None` if none does.
+    fn route(
+        table: &str,
+        partitioning: &Partitioning,
+        column: usize,
+        row: &[Value],
+    ) -> Option<Box<str>> {
+        let partition = partitioning.partition(&row[column])?;
+        Some(partition_key(table, &partitioning.names()[partition]))
+    }
+
+    /// The tables of the partitions of `table`.
+    fn tables(table: &str, partitioning: &Partitioning) -> Vec<Box<str>> {
+        partitioning
+            .names()
+            .iter()
+            .map(|partition| partition_key(table, partition))
+            .collect()
+    }
+}
+
+impl Storage for PartitionedStorage<'_> {
+    fn scan(&self, table: &str) -> Result<RowIter<'_>, ExecError> {
+        let Some((partitioning, _)) = self.partitioning(table) else {
+            return self.storage.scan(table);
+        };
+        let scans = Self::tables(table, partitioning)
+            .iter()
+            .map(|partition| self.storage.scan(partition))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(scans.into_iter().flatten()))
+    }
+
+    fn scan_with(&self, table: &str, options: &ScanOptions) -> Result<RowIter<'_>, ExecError> {
+        let Some((partitioning, _)) = self.partitioning(table) else {
+            return self.storage.scan_with(table, options);
+        };
+        let scans = Self::tables(table, partitioning)
+            .iter()
+            .map(|partition| self.storage.scan_with(partition, options))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(scans.into_iter().flatten()))
+    }
+
+    /// A row no partition of the table holds is an [`ExecError::NoPartition`].
+    fn insert(&self, table: &str, row: Row) -> Result<(), ExecError> {
+        let Some((partitioning, column)) = self.partitioning(table) else {
+            return self.storage.insert(table, row);
+        };
+        let partition = Self::route(table, partitioning, column, &row)
+            .ok_or_else(|| ExecError::NoPartition(table.into()))?;
+        self.storage.insert(&partition, row)
+    }
+
+    fn remove(&self, table: &str, row: &[Value]) -> Result<bool, ExecError> {
+        let Some((partitioning, column)) = self.partitioning(table) else {
+            return self.storage.remove(table, row);
+        };
+        match Self::route(table, partitioning, column, row) {
+            Some(partition) => self.storage.remove(&partition, row),
+            None => Ok(false),
+        }
+    }
+
+    fn vacuum(&self, table: &str) -> Result<u64, ExecError> {
+        let Some((partitioning, _)) = self.partitioning(table) else {
+            return self.storage.vacuum(table);
+        };
+        Self::tables(table, partitioning)
+            .iter()
+            .map(|partition| self.storage.vacuum(partition))
+            .sum()
+    }
+
+    /// The partitions of all the tables are copied together, the bytes of a partitioned
+    /// table are the ones of its partitions.
+    fn backup(&self, tables: &[&str], dir: &Path) -> Result<Vec<u64>, ExecError> {
+        let partitions = tables
+            .iter()
+            .map(|table| match self.partitioning(table) {
+                Some((partitioning, _)) => Self::tables(table, partitioning),
+                None => vec![(*table).into()],
+            })
+            .collect::<Vec<_>>();
+        let names = partitions
+            .iter()
+            .flatten()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>();
+        let mut written = self.storage.backup(&names, dir)?.into_iter();
+        Ok(partitions
+            .iter()
+            .map(|tables| written.by_ref().take(tables.len()).sum())
+            .collect())
+    }
+
+    fn row_count(&self, table: &str) -> Option<u64> {
+        let Some((partitioning, _)) = self.partitioning(table) else {
+            return self.storage.row_count(table);
+        };
+        Self::tables(table, partitioning)
+            .iter()
+            .map(|partition| self.storage.row_count(partition))
+            .sum()
+    }
+
+    fn indexes(&self, table: &str) -> Vec<Index> {
+        self.storage.indexes(table)
+    }
+
+    fn index_scan(
+        &self,
+        table: &str,
+        index: &str,
+        range: &KeyRange,
+        key_only: bool,
+    ) -> Result<RowIter<'_>, ExecError> {
+        self.storage.index_scan(table, index, range, key_only)
+    }
+
+    fn partitions(&self, table: &str) -> Option<Partitions> {
+        let Some((partitioning, column)) = self.partitioning(table) else {
+            return self.storage.partitions(table);
+        };
+        Some(Partitions {
+            partitioning: partitioning.clone(),
+            column,
+            tables: Self::tables(table, partitioning),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{ast::statement::Statement, parse::RawSpan};
+
+    use super::*;
+    use crate::{
+        executor::{execute, Config, Output},
+        storage::testing::TestStorage,
+    };
+
+    fn run(input: &str, table_map: &TableMap, storage: &TestStorage) -> Result<Output, ExecError> {
+        let (_, statement) = Statement::parse_with_table_map(table_map, RawSpan::new(input))
+            .unwrap_or_else(|e| panic!("{input}: {e:?}"));
+        execute(&statement, table_map, storage, &Config::default())
+    }
+
+    fn rows(output: Result<Output, ExecError>) -> Vec<Row> {
+        match output.unwrap() {
+            Output::Rows { rows, .. } => rows,
+            output => panic!("{output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_range_partitions() {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE events (id INT32, day INT32) PARTITION BY RANGE (day) (\
+             PARTITION old VALUES LESS THAN (10), PARTITION new VALUES LESS THAN (20))",
+        ]);
+        run(
+            "INSERT INTO events (id, day) VALUES (1, 1), (2, 15), (3, NULL), (4, 19)",
+            &table_map,
+            &storage,
+        )
+        .unwrap();
+        assert_eq!(storage.row_count("public.events#old"), Some(2));
+        assert_eq!(storage.row_count("public.events#new"), Some(2));
+        assert_eq!(
+            run(
+                "INSERT INTO events (id, day) VALUES (5, 20)",
+                &table_map,
+                &storage
+            ),
+            Err(ExecError::NoPartition("public.events".into()))
+        );
+
+        let ids = |input| {
+            rows(run(input, &table_map, &storage))
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("SELECT id FROM events"), [1, 3, 2, 4].map(Value::I32));
+        assert_eq!(
+            ids("SELECT id FROM events WHERE day >= 10"),
+            [2, 4].map(Value::I32)
+        );
+        let explain = ids("EXPLAIN SELECT id FROM events WHERE day >= 10 AND day < 16");
+        assert!(
+            explain.contains(&Value::VarChar("        Partitions: new".into())),
+            "{explain:?}"
+        );
+        // A bound only known when the statement runs doesn't rule partitions out.
+        let explain = ids("EXPLAIN SELECT id FROM events WHERE day < 2 + id");
+        assert!(!explain
+            .iter()
+            .any(|line| line.to_string().contains("Partitions")));
+
+        let Ok(Output::Analyzed(analyzed)) = run("ANALYZE events", &table_map, &storage) else {
+            panic!("not analyzed");
+        };
+        assert_eq!(analyzed[0].2.row_count, 4);
+    }
+
+    #[test]
+    fn test_hash_partitions() {
+        let (storage, table_map) = TestStorage::new(&[
+            "CREATE TABLE t (id INT32, name VARCHAR(10)) PARTITION BY HASH (id) PARTITIONS 4",
+        ]);
+        for id in 0..20 {
+            run(
+                &format!("INSERT INTO t (id, name) VALUES ({id}, 'n{id}')"),
+                &table_map,
+                &storage,
+            )
+            .unwrap();
+        }
+        let partitioned = PartitionedStorage::new(&storage, &table_map);
+        let partitions = partitioned.partitions("public.t").unwrap();
+        assert_eq!(partitions.column, 0);
+        let counts = partitions
+            .tables
+            .iter()
+            .map(|table| storage.row_count(table).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts.iter().sum::<u64>(), 20);
+        assert!(counts.iter().all(|count| *count < 20), "{counts:?}");
+        assert_eq!(partitioned.row_count("public.t"), Some(20));
+        assert_eq!(partitioned.partitions("public.u"), None);
+
+        assert_eq!(
+            rows(run("SELECT name FROM t WHERE id = 7", &table_map, &storage)),
+            [[Value::VarChar("n7".into())]]
+        );
+        let explain = rows(run(
+            "EXPLAIN SELECT name FROM t WHERE id = 7",
+            &table_map,
+            &storage,
+        ));
+        let partition = partitions.partitioning.partition(&Value::I32(7)).unwrap();
+        assert!(
+            explain.contains(&vec![Value::VarChar(
+                format!("        Partitions: p{partition}").into()
+            )]),
+            "{explain:?}"
+        );
+        assert!(partitioned
+            .remove("public.t", &[Value::I32(7), Value::VarChar("n7".into())])
+            .unwrap());
+        assert_eq!(partitioned.row_count("public.t"), Some(19));
+    }
+}
@@ -0,0 +1,1099 @@
+use std::{ops::Bound, sync::Arc};
+
+use rs_db_parser::{
+    ast::{
+        commands::{
+            insert,
+            select::{self, Projection, SortOrder},
+        },
+        expr::{AggregateFunction, BinaryOperator, ColumnRef, Expr},
+        statement::Statement,
+        table::{JoinConstraint, JoinKind, TableExpr},
+        visit::{walk_expr_mut, VisitorMut},
+    },
+    parse::{ColumnStatistics, RawSpan, TableMap, TableStatistics},
+    value::Value,
+};
+
+use crate::{
+    constraint::{self, BoundConstraint},
+    error::ExecError,
+    storage::{table_key, Index, Storage},
+    Row,
+};
+
+/// The table of the columns [`Plan::Aggregate`] computes for the aggregates and the
+/// `GROUP BY` expressions that aren't columns. A query can't name it, the expressions
+/// evaluated after the aggregation refer to these columns by the position in the source of
+/// the expression they stand for, see [`Field::computed`].
+const COMPUTED: &str = "#";
+
+/// The fraction of the rows a predicate is assumed to keep without statistics to tell.
+const DEFAULT_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// A column of the rows a plan produces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Field {
+    /// The name the query gives to the table of the column, its alias or its name. `None`
+    /// for a computed column.
+    pub table: Option<Box<str>>,
+    pub name: Box<str>,
+}
+
+impl Field {
+    /// The column [`Plan::Aggregate`] computes for the expression at `span`.
+    fn computed(span: RawSpan<'_>) -> Self {
+        Self {
+            table: Some(COMPUTED.into()),
+            name: span.location_offset().to_string().into(),
+        }
+    }
+
+    /// The field of `column` if it refers to a column [`Plan::Aggregate`] computes.
+    pub(crate) fn of_computed(column: &ColumnRef<'_>) -> Option<Self> {
+        column
+            .qualifier
+            .is_some_and(|qualifier| *qualifier.fragment() == COMPUTED)
+            .then(|| Self::computed(column.name))
+    }
+
+    /// Whether the column is one [`Plan::Aggregate`] computes, which `*` doesn't select.
+    #[must_use]
+    pub fn is_computed(&self) -> bool {
+        self.table.as_deref() == Some(COMPUTED)
+    }
+}
+
+/// A reference to the column [`Plan::Aggregate`] computes for the expression at `span`.
+fn computed_column(span: RawSpan<'_>) -> Expr<'_> {
+    Expr::Column(ColumnRef {
+        qualifier: Some(RawSpan::new(COMPUTED)),
+        name: span,
+    })
+}
+
+/// The columns of the rows a plan produces, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Layout(pub Vec<Field>);
+
+impl Layout {
+    /// The position of the column `column` points at. An unqualified name is the first
+    /// column with that name, as `USING` merges the columns of a join into the first one.
+    #[must_use]
+    pub fn index(&self, column: &ColumnRef<'_>) -> Option<usize> {
+        self.positions(column).next()
+    }
+
+    /// The positions of all the columns `column` may point at, in order.
+    pub fn positions<'l>(&'l self, column: &'l ColumnRef<'_>) -> impl Iterator<Item = usize> + 'l {
+        let computed = Field::of_computed(column);
+        self.0
+            .iter()
+            .enumerate()
+            .filter(move |(_, field)| match &computed {
+                Some(computed) => *field == computed,
+                None => {
+                    *field.name == **column.name.fragment()
+                        && column.qualifier.is_none_or(|qualifier| {
+                            field.table.as_deref() == Some(*qualifier.fragment())
+                        })
+                }
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// The columns of `self` followed by the ones of `other`, the layout of a join.
+    #[must_use]
+    pub fn concat(&self, other: &Self) -> Self {
+        Self(self.0.iter().chain(&other.0).cloned().collect())
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<Box<str>> {
+        self.0.iter().map(|field| field.name.clone()).collect()
+    }
+}
+
+/// A column produced by [`Plan::Project`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProjectItem<'a> {
+    /// The column at this position of the input.
+    Column(usize),
+    Expr(Expr<'a>),
+}
+
+/// A key of [`Plan::Sort`], see [`OrderByItem`](select::OrderByItem).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortKey<'a> {
+    pub expr: Expr<'a>,
+    pub order: SortOrder,
+    pub nulls_first: bool,
+}
+
+/// An aggregate call of [`Plan::Aggregate`], see [`Expr::Aggregate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AggregateCall<'a> {
+    pub function: AggregateFunction,
+    pub distinct: bool,
+    /// `None` for `COUNT(*)`.
+    pub arg: Option<Expr<'a>>,
+}
+
+/// How [`Plan::Join`] finds the rows that match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum JoinAlgorithm {
+    /// Tries every right row with every left row, the only one for a join without keys.
+    #[default]
+    NestedLoop,
+    /// Puts the right rows in a hash table by key, then looks up the left rows in it.
+    /// Both sides are split by key into temporary files when the right one doesn't fit the
+    /// memory budget.
+    Hash,
+    /// Sorts both sides by key, then walks them together.
+    Merge,
+}
+
+/// The values of a column of a table between constant bounds, `column` being its position
+/// in the stored rows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScanRange<'a> {
+    pub column: usize,
+    pub lower: Bound<Box<Expr<'a>>>,
+    pub upper: Bound<Box<Expr<'a>>>,
+}
+
+/// What a statement computes, as a tree of relational operators whose leaves read tables.
+/// The expressions are the ones of the statement, their columns are resolved by name in the
+/// [`Layout`] of the input of their node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Plan<'a> {
+    /// The rows of a table, `table` is its [`table_key`]. `columns` are the positions in the
+    /// stored rows of the columns of `layout`, `None` when it has all of them in order.
+    /// The storage may skip the rows out of `ranges`, see
+    /// [`ScanRanges`](crate::optimizer::ScanRanges). `partitions` are the tables of the
+    /// partitions of a partitioned table read, when
+    /// [`PartitionPruning`](crate::optimizer::PartitionPruning) left some out, `None` to read
+    /// the whole table. `statistics` are the ones of the table in the catalog, if it was
+    /// analyzed.
+    Scan {
+        table: Box<str>,
+        layout: Layout,
+        columns: Option<Vec<usize>>,
+        ranges: Vec<ScanRange<'a>>,
+        partitions: Option<Vec<Box<str>>>,
+        statistics: Option<Arc<TableStatistics>>,
+    },
+    /// The rows of `table` whose key in `index` starts with the values of `key`, the
+    /// equalities on its first columns, in the order of the keys. `columns` are the
+    /// positions of the columns of `layout` in the stored rows, or in the keys when
+    /// `index_only`: the index has all the columns the plan reads, the table isn't read.
+    IndexScan {
+        table: Box<str>,
+        index: Index,
+        key: Vec<Expr<'a>>,
+        layout: Layout,
+        columns: Option<Vec<usize>>,
+        index_only: bool,
+        statistics: Option<Arc<TableStatistics>>,
+    },
+    /// Like [`Plan::IndexScan`], the rows whose first column of the key is between `lower`
+    /// and `upper`.
+    IndexRangeScan {
+        table: Box<str>,
+        index: Index,
+        lower: Bound<Box<Expr<'a>>>,
+        upper: Bound<Box<Expr<'a>>>,
+        layout: Layout,
+        columns: Option<Vec<usize>>,
+        index_only: bool,
+        statistics: Option<Arc<TableStatistics>>,
+    },
+    /// Rows given by the statement, like the `VALUES` of an `INSERT`.
+    Values { rows: Vec<Row>, layout: Layout },
+    /// The rows of `input` for which `predicate` is TRUE.
+    Filter {
+        input: Box<Self>,
+        predicate: Expr<'a>,
+    },
+    Project {
+        input: Box<Self>,
+        items: Vec<ProjectItem<'a>>,
+        layout: Layout,
+    },
+    /// The rows of `left` combined with the rows of `right` for which the columns of each
+    /// pair of `keys`, a position in `left` and one in `right`, are equal and `condition`
+    /// is TRUE. The outer kinds also keep the rows without a match, with `NULL`s instead of
+    /// the columns of the other side.
+    Join {
+        kind: JoinKind,
+        algorithm: JoinAlgorithm,
+        left: Box<Self>,
+        right: Box<Self>,
+        keys: Vec<(usize, usize)>,
+        condition: Option<Expr<'a>>,
+    },
+    /// A row per group of rows of `input` with equal values of `group_by`, `NULL`s being
+    /// equal, made of these values then the ones of `aggregates` over the group. Without
+    /// `group_by`, a single row for all the rows, even if there is none.
+    Aggregate {
+        input: Box<Self>,
+        group_by: Vec<Expr<'a>>,
+        aggregates: Vec<AggregateCall<'a>>,
+        layout: Layout,
+    },
+    /// The rows of `input` ordered by `keys`, from the most to the least significant.
+    Sort {
+        input: Box<Self>,
+        keys: Vec<SortKey<'a>>,
+    },
+    /// Skips `offset` rows of `input`, then returns at most `limit` rows.
+    Limit {
+        input: Box<Self>,
+        limit: Option<u64>,
+        offset: u64,
+    },
+    /// Inserts the rows of `input`, which have the layout of the table, into the table and
+    /// returns them. Each row is checked against the `constraints` of the table first.
+    Insert {
+        table: Box<str>,
+        input: Box<Self>,
+        constraints: Vec<BoundConstraint>,
+    },
+}
+
+impl<'a> Plan<'a> {
+    /// Plans a `SELECT` or an `INSERT` bound to `table_map`.
+    /// # Errors
+    /// Returns an error if the statement is of another kind or uses a clause the executor
+    /// doesn't run yet.
+    pub fn from_statement(
+        statement: &Statement<'a>,
+        table_map: &TableMap,
+    ) -> Result<Self, ExecError> {
+        match statement {
+            Statement::Select(select) => Self::select(select, table_map),
+            Statement::Insert(insert) => Self::insert(insert, table_map),
+            Statement::UnboundInsert(_) => Err(ExecError::Unsupported("An unbound INSERT")),
+            _ => Err(ExecError::Unsupported("This statement")),
+        }
+    }
+
+    /// The columns of the rows the plan produces.
+    #[must_use]
+    pub fn layout(&self) -> Layout {
+        match self {
+            Self::Scan { layout, .. }
+            | Self::IndexScan { layout, .. }
+            | Self::IndexRangeScan { layout, .. }
+            | Self::Values { layout, .. }
+            | Self::Project { layout, .. }
+            | Self::Aggregate { layout, .. } => layout.clone(),
+            Self::Filter { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Limit { input, .. }
+            | Self::Insert { input, .. } => input.layout(),
+            Self::Join { left, right, .. } => left.layout().concat(&right.layout()),
+        }
+    }
+
+    /// A guess of the number of rows the plan produces, from the statistics of the tables
+    /// or else the number of rows `storage` knows. `None` if neither knows one of them.
+    #[must_use]
+    pub fn estimated_rows(&self, storage: &dyn Storage) -> Option<u64> {
+        match self {
+            Self::Scan {
+                statistics: Some(statistics),
+                ..
+            } => Some(statistics.row_count),
+            Self::Scan {
+                partitions: Some(partitions),
+                ..
+            } => partitions
+                .iter()
+                .map(|partition| storage.row_count(partition))
+                .sum(),
+            Self::Scan { table, .. } => storage.row_count(table),
+            // The equalities and the ranges of the key keep the rows they would as filters.
+            #[allow(
+                clippy::cast_precision_loss,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss
+            )]
+            Self::IndexScan {
+                table,
+                index,
+                statistics,
+                ..
+            }
+            | Self::IndexRangeScan {
+                table,
+                index,
+                statistics,
+                ..
+            } => {
+                let rows = match statistics {
+                    Some(statistics) => statistics.row_count,
+                    None => storage.row_count(table)?,
+                };
+                let selectivity = match self {
+                    Self::IndexScan { key, .. } => index.columns[..key.len()]
+                        .iter()
+                        .map(|column| {
+                            statistics
+                                .as_ref()
+                                .and_then(|statistics| statistics.columns.get(column))
+                                .map_or(DEFAULT_SELECTIVITY, |column| {
+                                    equality_selectivity(column, rows)
+                                })
+                        })
+                        .product(),
+                    _ => DEFAULT_SELECTIVITY,
+                };
+                Some((rows as f64 * selectivity).ceil() as u64)
+            }
+            Self::Values { rows, .. } => Some(rows.len() as u64),
+            #[allow(
+                clippy::cast_precision_loss,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss
+            )]
+            Self::Filter { input, predicate } => {
+                let rows = input.estimated_rows(storage)?;
+                Some((rows as f64 * input.selectivity(predicate)).ceil() as u64)
+            }
+            Self::Project { input, .. } | Self::Sort { input, .. } | Self::Insert { input, .. } => {
+                input.estimated_rows(storage)
+            }
+            // There are as many groups as combinations of the distinct values of the grouped
+            // columns, without statistics each row is assumed to be its own group.
+            Self::Aggregate {
+                input, group_by, ..
+            } => {
+                let rows = input.estimated_rows(storage)?;
+                let groups = group_by.iter().try_fold(1u64, |groups, expr| match expr {
+                    Expr::Column(column) => input
+                        .column_statistics(column)
+                        .map(|(statistics, _)| groups.saturating_mul(statistics.distinct.max(1))),
+                    _ => None,
+                });
+                Some(groups.map_or(rows, |groups| groups.min(rows)).max(1))
+            }
+            Self::Limit {
+                input,
+                limit,
+                offset,
+            } => {
+                let rows = input.estimated_rows(storage)?.saturating_sub(*offset);
+                Some(limit.map_or(rows, |limit| rows.min(limit)))
+            }
+            // The keys of a join are assumed to be correlated, like the columns of a
+            // composite key, so the most selective one decides the rows it matches.
+            #[allow(
+                clippy::cast_precision_loss,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss
+            )]
+            Self::Join {
+                kind,
+                left,
+                right,
+                keys,
+                ..
+            } => {
+                let (left_rows, right_rows) = (
+                    left.estimated_rows(storage)?,
+                    right.estimated_rows(storage)?,
+                );
+                if keys.is_empty() || *kind == JoinKind::Cross {
+                    return Some(left_rows.saturating_mul(right_rows));
+                }
+                let selectivity = keys
+                    .iter()
+                    .map(|(left_index, right_index)| {
+                        Self::equi_join_selectivity(
+                            (left, *left_index, left_rows),
+                            (right, *right_index, right_rows),
+                        )
+                    })
+                    .fold(1.0, f64::min);
+                Some((left_rows as f64 * right_rows as f64 * selectivity).round() as u64)
+            }
+        }
+    }
+
+    /// The fraction of the rows of the plan `predicate` is assumed to keep. With the
+    /// statistics of the column it compares, `column = constant` keeps the rows of one of
+    /// its distinct values and `column IS [NOT] NULL` the fraction of `NULL`s, or the
+    /// others. Any other predicate is assumed to keep a third of the rows.
+    #[must_use]
+    pub fn selectivity(&self, predicate: &Expr<'_>) -> f64 {
+        let statistics = |expr: &Expr<'_>| match expr {
+            Expr::Column(column) => self.column_statistics(column),
+            _ => None,
+        };
+        match predicate {
+            Expr::Nested((_, expr)) => self.selectivity(expr),
+            Expr::Binary {
+                op: (_, BinaryOperator::And),
+                left,
+                right,
+            } => self.selectivity(left) * self.selectivity(right),
+            Expr::Binary {
+                op: (_, BinaryOperator::Eq),
+                left,
+                right,
+            } => {
+                let column = match (statistics(left), statistics(right)) {
+                    (Some(column), None) if right.columns().is_empty() => column,
+                    (None, Some(column)) if left.columns().is_empty() => column,
+                    _ => return DEFAULT_SELECTIVITY,
+                };
+                let (statistics, rows) = column;
+                equality_selectivity(statistics, rows)
+            }
+            Expr::IsNull { expr, negated } => match statistics(expr) {
+                Some((statistics, rows)) => {
+                    let nulls = statistics.null_fraction(rows);
+                    if *negated {
+                        1.0 - nulls
+                    } else {
+                        nulls
+                    }
+                }
+                None => DEFAULT_SELECTIVITY,
+            },
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+
+    /// The statistics of the table column `column` reads, with the number of rows of the
+    /// table, if it was analyzed. A computed column has none.
+    #[must_use]
+    pub fn column_statistics(&self, column: &ColumnRef<'_>) -> Option<(&ColumnStatistics, u64)> {
+        self.column_statistics_at(self.layout().index(column)?)
+    }
+
+    /// The statistics of the column at `index` of the layout of the plan, see
+    /// [`column_statistics`](Self::column_statistics).
+    #[must_use]
+    pub fn column_statistics_at(&self, index: usize) -> Option<(&ColumnStatistics, u64)> {
+        match self {
+            Self::Scan {
+                layout,
+                statistics: Some(statistics),
+                ..
+            }
+            | Self::IndexScan {
+                layout,
+                statistics: Some(statistics),
+                ..
+            }
+            | Self::IndexRangeScan {
+                layout,
+                statistics: Some(statistics),
+                ..
+            } => {
+                let column = statistics.columns.get(&layout.0.get(index)?.name)?;
+                Some((column, statistics.row_count))
+            }
+            Self::Filter { input, .. } | Self::Sort { input, .. } | Self::Limit { input, .. } => {
+                input.column_statistics_at(index)
+            }
+            Self::Join { left, right, .. } => {
+                let left_width = left.layout().len();
+                if index < left_width {
+                    left.column_statistics_at(index)
+                } else {
+                    right.column_statistics_at(index - left_width)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The fraction of the pairs of a row of `left` and a row of `right` whose columns at
+    /// `left_index` and `right_index` are equal, `left_rows` and `right_rows` being their
+    /// estimated numbers of rows. Each value is assumed to match in the side with the
+    /// fewest distinct values, so it is the inverse of the largest number of distinct
+    /// values. Without statistics, each row of the larger side is assumed to match once.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn equi_join_selectivity(
+        (left, left_index, left_rows): (&Self, usize, u64),
+        (right, right_index, right_rows): (&Self, usize, u64),
+    ) -> f64 {
+        let distinct = |plan: &Self, index| {
+            plan.column_statistics_at(index)
+                .map(|(statistics, _)| statistics.distinct.max(1))
+        };
+        let distinct = match (distinct(left, left_index), distinct(right, right_index)) {
+            (Some(left), Some(right)) => left.max(right),
+            (Some(distinct), None) | (None, Some(distinct)) => distinct,
+            (None, None) => left_rows.min(right_rows).max(1),
+        };
+        1.0 / distinct as f64
+    }
+
+    /// The inputs of the node, the left one first for a join.
+    #[must_use]
+    pub fn children(&self) -> Vec<&Self> {
+        match self {
+            Self::Scan { .. }
+            | Self::IndexScan { .. }
+            | Self::IndexRangeScan { .. }
+            | Self::Values { .. } => vec![],
+            Self::Filter { input, .. }
+            | Self::Project { input, .. }
+            | Self::Aggregate { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Limit { input, .. }
+            | Self::Insert { input, .. } => vec![input],
+            Self::Join { left, right, .. } => vec![left, right],
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Self> {
+        match self {
+            Self::Scan { .. }
+            | Self::IndexScan { .. }
+            | Self::IndexRangeScan { .. }
+            | Self::Values { .. } => vec![],
+            Self::Filter { input, .. }
+            | Self::Project { input, .. }
+            | Self::Aggregate { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Limit { input, .. }
+            | Self::Insert { input, .. } => vec![input],
+            Self::Join { left, right, .. } => vec![left, right],
+        }
+    }
+
+    /// The expressions the node evaluates, not the ones of its inputs.
+    pub fn exprs_mut(&mut self) -> Vec<&mut Expr<'a>> {
+        match self {
+            Self::Values { .. } | Self::Limit { .. } | Self::Insert { .. } => vec![],
+            Self::Scan { ranges, .. } => ranges
+                .iter_mut()
+                .flat_map(|range| [&mut range.lower, &mut range.upper])
+                .filter_map(|bound| match bound {
+                    Bound::Included(expr) | Bound::Excluded(expr) => Some(&mut **expr),
+                    Bound::Unbounded => None,
+                })
+                .collect(),
+            Self::IndexScan { key, .. } => key.iter_mut().collect(),
+            Self::IndexRangeScan { lower, upper, .. } => [lower, upper]
+                .into_iter()
+                .filter_map(|bound| match bound {
+                    Bound::Included(expr) | Bound::Excluded(expr) => Some(&mut **expr),
+                    Bound::Unbounded => None,
+                })
+                .collect(),
+            Self::Filter { predicate, .. } => vec![predicate],
+            Self::Project { items, .. } => items
+                .iter_mut()
+                .filter_map(|item| match item {
+                    ProjectItem::Expr(expr) => Some(expr),
+                    ProjectItem::Column(_) => None,
+                })
+                .collect(),
+            Self::Join { condition, .. } => condition.iter_mut().collect(),
+            Self::Aggregate {
+                group_by,
+                aggregates,
+                ..
+            } => group_by
+                .iter_mut()
+                .chain(aggregates.iter_mut().filter_map(|call| call.arg.as_mut()))
+                .collect(),
+            Self::Sort { keys, .. } => keys.iter_mut().map(|key| &mut key.expr).collect(),
+        }
+    }
+
+    fn select(statement: &select::Statement<'a>, table_map: &TableMap) -> Result<Self, ExecError> {
+        if !statement.with.is_empty() {
+            return Err(ExecError::Unsupported("WITH"));
+        }
+        let mut plan = Self::table_expr(&statement.from, table_map)?;
+        if let Some(selection) = &statement.selection {
+            plan = Self::Filter {
+                input: Box::new(plan),
+                predicate: selection.clone(),
+            };
+        }
+        let mut keys = statement
+            .order_by
+            .iter()
+            .map(|item| SortKey {
+                expr: output_alias(&statement.projection, &item.expr.1)
+                    .unwrap_or_else(|| item.expr.1.clone()),
+                order: item.order,
+                nulls_first: item.nulls_first(),
+            })
+            .collect::<Vec<_>>();
+        let mut projection = statement.projection.to_vec();
+        if statement.is_grouped() {
+            let mut grouping = Grouping {
+                group_by: statement
+                    .group_by
+                    .iter()
+                    .map(|(_, expr)| (expr.to_string(), expr.span()))
+                    .collect(),
+                aggregates: Vec::new(),
+            };
+            for projection in &mut projection {
+                if let Projection::Expr {
+                    expr: (_, expr), ..
+                } = projection
+                {
+                    grouping.visit_expr_mut(expr);
+                }
+            }
+            let mut having = statement.having.clone();
+            if let Some(having) = &mut having {
+                grouping.visit_expr_mut(having);
+            }
+            for key in &mut keys {
+                grouping.visit_expr_mut(&mut key.expr);
+            }
+            plan = Self::aggregate(plan, statement, grouping.aggregates);
+            if let Some(having) = having {
+                plan = Self::Filter {
+                    input: Box::new(plan),
+                    predicate: having,
+                };
+            }
+        }
+        if !keys.is_empty() {
+            plan = Self::Sort {
+                input: Box::new(plan),
+                keys,
+            };
+        }
+        if statement.limit.is_some() || statement.offset.is_some() {
+            plan = Self::Limit {
+                input: Box::new(plan),
+                limit: statement.limit.map(|(_, limit)| limit),
+                offset: statement.offset.map_or(0, |(_, offset)| offset),
+            };
+        }
+        Self::project(plan, &projection)
+    }
+
+    /// Groups the rows of `input` by the `GROUP BY` expressions of `statement` and computes
+    /// `aggregates`, with the columns [`Grouping`] refers to.
+    fn aggregate(
+        input: Self,
+        statement: &select::Statement<'a>,
+        aggregates: Vec<(String, RawSpan<'a>, AggregateCall<'a>)>,
+    ) -> Self {
+        let input_layout = input.layout();
+        let group_by = statement
+            .group_by
+            .iter()
+            .map(|(_, expr)| expr.clone())
+            .collect::<Vec<_>>();
+        let fields = group_by
+            .iter()
+            .map(|expr| match expr {
+                Expr::Column(column) => input_layout.index(column).map_or_else(
+                    || Field::computed(expr.span()),
+                    |i| input_layout.0[i].clone(),
+                ),
+                expr => Field::computed(expr.span()),
+            })
+            .chain(aggregates.iter().map(|(_, span, _)| Field::computed(*span)))
+            .collect();
+        Self::Aggregate {
+            input: Box::new(input),
+            group_by,
+            aggregates: aggregates.into_iter().map(|(.., call)| call).collect(),
+            layout: Layout(fields),
+        }
+    }
+
+    fn table_expr(from: &TableExpr<'a>, table_map: &TableMap) -> Result<Self, ExecError> {
+        match from {
+            TableExpr::Table { name, alias } => {
+                let key = table_key(name, table_map);
+                if table_map
+                    .view(name.schema_name(), name.name.fragment())
+                    .is_some()
+                {
+                    return Err(ExecError::Unsupported("A view"));
+                }
+                let columns = name
+                    .lookup(table_map)
+                    .map_err(|_| ExecError::TableNotFound(key.clone()))?;
+                let visible = alias.unwrap_or(name.name);
+                let mut names = columns.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                let layout = Layout(
+                    names
+                        .into_iter()
+                        .map(|name| Field {
+                            table: Some((*visible.fragment()).into()),
+                            name,
+                        })
+                        .collect(),
+                );
+                Ok(Self::Scan {
+                    table: key,
+                    layout,
+                    columns: None,
+                    ranges: Vec::new(),
+                    partitions: None,
+                    statistics: table_map
+                        .statistics(name.schema_name(), name.name.fragment())
+                        .cloned()
+                        .map(Arc::new),
+                })
+            }
+            TableExpr::SelfReference { .. } => Err(ExecError::Unsupported(
+                "A recursive common table expression",
+            )),
+            TableExpr::Join {
+                kind: (_, kind),
+                left,
+                right,
+                constraint,
+            } => {
+                let left = Self::table_expr(left, table_map)?;
+                let right = Self::table_expr(right, table_map)?;
+                let (keys, condition) = match constraint {
+                    JoinConstraint::On(expr) => (Vec::new(), Some(expr.clone())),
+                    JoinConstraint::Using(columns) => {
+                        let (left_layout, right_layout) = (left.layout(), right.layout());
+                        let position = |layout: &Layout, name: &str| {
+                            layout
+                                .0
+                                .iter()
+                                .position(|field| *field.name == *name)
+                                .ok_or_else(|| ExecError::ColumnNotFound(name.into()))
+                        };
+                        let keys = columns
+                            .iter()
+                            .map(|column| {
+                                Ok((
+                                    position(&left_layout, column.fragment())?,
+                                    position(&right_layout, column.fragment())?,
+                                ))
+                            })
+                            .collect::<Result<_, ExecError>>()?;
+                        (keys, None)
+                    }
+                    JoinConstraint::None => (Vec::new(), None),
+                };
+                Ok(Self::Join {
+                    kind: *kind,
+                    algorithm: JoinAlgorithm::NestedLoop,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    keys,
+                    condition,
+                })
+            }
+        }
+    }
+
+    /// Computes the columns of `projection` from the rows of `input`.
+    fn project(input: Self, projection: &[Projection<'a>]) -> Result<Self, ExecError> {
+        let input_layout = input.layout();
+        let mut items = Vec::new();
+        let mut fields = Vec::new();
+        for projection in projection {
+            match projection {
+                Projection::Wildcard(_) => {
+                    for (index, field) in input_layout.0.iter().enumerate() {
+                        if !field.is_computed() {
+                            items.push(ProjectItem::Column(index));
+                            fields.push(field.clone());
+                        }
+                    }
+                }
+                Projection::Column { column, alias } => {
+                    let index = input_layout
+                        .index(column)
+                        .ok_or_else(|| ExecError::ColumnNotFound(column.key().into()))?;
+                    items.push(ProjectItem::Column(index));
+                    fields.push(match alias {
+                        Some(alias) => Field {
+                            table: None,
+                            name: (*alias.fragment()).into(),
+                        },
+                        None => input_layout.0[index].clone(),
+                    });
+                }
+                Projection::Expr {
+                    expr: (span, expr),
+                    alias,
+                } => {
+                    items.push(ProjectItem::Expr(expr.clone()));
+                    fields.push(Field {
+                        table: None,
+                        name: alias.unwrap_or(*span).fragment().trim().into(),
+                    });
+                }
+            }
+        }
+        Ok(Self::Project {
+            input: Box::new(input),
+            items,
+            layout: Layout(fields),
+        })
+    }
+
+    fn insert(statement: &insert::Statement<'a>, table_map: &TableMap) -> Result<Self, ExecError> {
+        if statement.on_conflict.is_some() {
+            return Err(ExecError::Unsupported("ON CONFLICT"));
+        }
+        let table = table_key(&statement.table_name, table_map);
+        let columns = statement
+            .table_name
+            .lookup(table_map)
+            .map_err(|_| ExecError::TableNotFound(table.clone()))?;
+        let mut columns = columns.values().collect::<Vec<_>>();
+        columns.sort_by(|a, b| a.name.cmp(&b.name));
+        // The columns the statement doesn't give a value take their default.
+        let rows = statement
+            .rows
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        row.values
+                            .iter()
+                            .find(|(name, _)| *name.fragment() == &*column.name)
+                            .map(|(_, (_, value))| value.clone())
+                            .or_else(|| column.default.clone())
+                            .unwrap_or(Value::Null)
+                    })
+                    .collect()
+            })
+            .collect();
+        let visible = statement.table_name.name.fragment();
+        let layout = Layout(
+            columns
+                .iter()
+                .map(|column| Field {
+                    table: Some((*visible).into()),
+                    name: column.name.clone(),
+                })
+                .collect(),
+        );
+        let plan = Self::Insert {
+            table,
+            input: Box::new(Self::Values { rows, layout }),
+            constraints: constraint::bind(&statement.table_name, table_map)?,
+        };
+        if statement.returning.is_empty() {
+            Ok(plan)
+        } else {
+            Self::project(plan, &statement.returning)
+        }
+    }
+}
+
+/// The fraction of the rows of a table whose column of `statistics` equals a constant: the
+/// ones of one of its distinct values, out of `rows`.
+#[allow(clippy::cast_precision_loss)]
+fn equality_selectivity(statistics: &ColumnStatistics, rows: u64) -> f64 {
+    (1.0 - statistics.null_fraction(rows)) / statistics.distinct.max(1) as f64
+}
+
+/// Replaces the aggregate calls and the `GROUP BY` expressions that aren't columns, in the
+/// expressions evaluated after [`Plan::Aggregate`], with the columns it computes for them.
+/// An expression stands for another when it is written the same way, whatever its spacing.
+struct Grouping<'a> {
+    /// The text and the span of each `GROUP BY` expression.
+    group_by: Vec<(String, RawSpan<'a>)>,
+    /// The aggregates found, each with its text and the span of its first call.
+    aggregates: Vec<(String, RawSpan<'a>, AggregateCall<'a>)>,
+}
+
+impl<'a> VisitorMut<'a> for Grouping<'a> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+        if matches!(expr, Expr::Column(_)) {
+            return;
+        }
+        let text = expr.to_string();
+        if let Some((_, span)) = self.group_by.iter().find(|(group, _)| *group == text) {
+            *expr = computed_column(*span);
+            return;
+        }
+        let Expr::Aggregate {
+            function: (span, function),
+            distinct,
+            arg,
+        } = expr
+        else {
+            walk_expr_mut(self, expr);
+            return;
+        };
+        let call = AggregateCall {
+            function: *function,
+            distinct: *distinct,
+            arg: arg.as_deref().cloned(),
+        };
+        let span = match self.aggregates.iter().find(|(other, ..)| *other == text) {
+            Some((_, span, _)) => *span,
+            None => {
+                self.aggregates.push((text, *span, call));
+                *span
+            }
+        };
+        *expr = computed_column(span);
+    }
+}
+
+/// The expression of the projection an `ORDER BY` key names by its alias.
+fn output_alias<'a>(projection: &[Projection<'a>], expr: &Expr<'a>) -> Option<Expr<'a>> {
+    let Expr::Column(ColumnRef {
+        qualifier: None,
+        name,
+    }) = expr
+    else {
+        return None;
+    };
+    projection.iter().find_map(|projection| match projection {
+        Projection::Column {
+            column,
+            alias: Some(alias),
+        } if alias.fragment() == name.fragment() => Some(Expr::Column(*column)),
+        Projection::Expr {
+            expr: (_, expr),
+            alias: Some(alias),
+        } if alias.fragment() == name.fragment() => Some(expr.clone()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::parse::RawSpan;
+
+    use super::*;
+    use crate::storage::testing::TestStorage;
+
+    #[test]
+    fn test_plan_select() {
+        let (_, table_map) = TestStorage::new(&[
+            "CREATE TABLE users (id INT32, name VARCHAR(10))",
+            "CREATE TABLE orders (id INT32, user_id INT32, total INT64)",
+        ]);
+        let input = "SELECT u.name, total * 2 AS double FROM users u JOIN orders USING (id) \
+                     WHERE total > 1 ORDER BY double DESC LIMIT 3";
+        let (_, statement) =
+            Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+        let plan = Plan::from_statement(&statement, &table_map).unwrap();
+        assert_eq!(plan.layout().names(), ["name".into(), "double".into()]);
+
+        let Plan::Project { input, items, .. } = plan else {
+            panic!("{plan:?}");
+        };
+        assert_eq!(items[0], ProjectItem::Column(1));
+        let Plan::Limit { input, limit, .. } = *input else {
+            panic!("{input:?}");
+        };
+        assert_eq!(limit, Some(3));
+        let Plan::Sort { input, keys } = *input else {
+            panic!("{input:?}");
+        };
+        assert_eq!(keys[0].expr.to_string(), "total * 2");
+        assert!(keys[0].nulls_first);
+        let Plan::Filter { input, .. } = *input else {
+            panic!("{input:?}");
+        };
+        let Plan::Join { left, keys, .. } = *input else {
+            panic!("{input:?}");
+        };
+        assert_eq!(keys, [(0, 0)]);
+        assert_eq!(
+            *left,
+            Plan::Scan {
+                table: "public.users".into(),
+                layout: Layout(vec![
+                    Field {
+                        table: Some("u".into()),
+                        name: "id".into()
+                    },
+                    Field {
+                        table: Some("u".into()),
+                        name: "name".into()
+                    },
+                ]),
+                columns: None,
+                ranges: Vec::new(),
+                partitions: None,
+                statistics: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_plan_aggregate() {
+        let (_, table_map) =
+            TestStorage::new(&["CREATE TABLE orders (id INT32, user_id INT32, total INT64)"]);
+        let input = "SELECT user_id + 1, count(*) FROM orders GROUP BY user_id + 1 \
+                     HAVING count(*) > 1 ORDER BY sum(total)";
+        let (_, statement) =
+            Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+        let plan = Plan::from_statement(&statement, &table_map).unwrap();
+        assert_eq!(
+            plan.layout().names(),
+            ["user_id + 1".into(), "count(*)".into()]
+        );
+        let Plan::Project { input, items, .. } = plan else {
+            panic!("{plan:?}");
+        };
+        let Plan::Sort { input, keys } = *input else {
+            panic!("{input:?}");
+        };
+        let Plan::Filter { input, predicate } = *input else {
+            panic!("{input:?}");
+        };
+        let Plan::Aggregate {
+            group_by,
+            aggregates,
+            layout,
+            ..
+        } = *input
+        else {
+            panic!("{input:?}");
+        };
+        assert_eq!(group_by.len(), 1);
+        // `count(*)` is computed once for the projection and `HAVING`.
+        assert_eq!(aggregates.len(), 2);
+        assert!(layout.0.iter().all(Field::is_computed));
+        let column = |expr: &Expr<'_>| match expr {
+            Expr::Column(column) => layout.index(column),
+            _ => None,
+        };
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| match item {
+                    ProjectItem::Expr(expr) => column(expr),
+                    ProjectItem::Column(_) => None,
+                })
+                .collect::<Vec<_>>(),
+            [Some(0), Some(1)]
+        );
+        assert_eq!(column(&keys[0].expr), Some(2));
+        let Expr::Binary { left, .. } = predicate else {
+            panic!("{predicate:?}");
+        };
+        assert_eq!(column(&left), Some(1));
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use nom::{
+    character::complete::{char, multispace0},
+    combinator::{all_consuming, opt},
+    sequence::{terminated, tuple},
+};
+use rs_db_parser::{
+    ast::{
+        expr::Expr,
+        statement::Statement,
+        table::TableName,
+        visit::{walk_expr, walk_expr_mut, Visitor, VisitorMut},
+    },
+    functions::FunctionRegistry,
+    parse::{RawSpan, TableMap},
+    value::Value,
+};
+
+use crate::{
+    cancel::CancellationToken,
+    error::ExecError,
+    executor::{run, with_timeout, Config, Output},
+    optimizer::Optimizer,
+    partition::PartitionedStorage,
+    plan::Plan,
+    storage::Storage,
+};
+
+/// A statement parsed, bound and planned once, to run many times with different values for
+/// its parameter placeholders, `?` or `$n`.
+///
+/// The statement remembers the [version](TableMap::version) of each table it reads or
+/// writes, and the default schema its unqualified names resolve in. When one of them
+/// changed by the time it runs, it is prepared again against the new catalog first, so it
+/// never runs a plan made for columns that are gone.
+#[derive(Debug, Clone)]
+pub struct PreparedStatement<'a> {
+    sql: &'a str,
+    statement: Statement<'a>,
+    /// The optimized plan of a `SELECT`, with the placeholders left in its expressions. The
+    /// values of an `INSERT` are converted to the types of their columns as they are bound,
+    /// so it is planned each time it runs.
+    plan: Option<Plan<'a>>,
+    /// The index of the value of each placeholder, by the offset of its span.
+    parameters: HashMap<usize, usize>,
+    default_schema: Box<str>,
+    /// The schema, the name and the version of each table, `None` for the ones that don't
+    /// exist, like the common table expressions.
+    tables: Vec<(Box<str>, Box<str>, Option<u64>)>,
+}
+
+impl<'a> PreparedStatement<'a> {
+    /// Parses `sql`, a single `SELECT` or `INSERT`, against `table_map` and plans it.
+    /// # Errors
+    /// Returns an error if `sql` is not a valid statement, or if it can't be planned, see
+    /// [`Plan::from_statement`].
+    pub fn prepare(
+        sql: &'a str,
+        table_map: &TableMap,
+        storage: &dyn Storage,
+    ) -> Result<Self, ExecError> {
+        let (_, statement) = all_consuming(terminated(
+            |i| Statement::parse_with_table_map(table_map, i),
+            tuple((multispace0, opt(char(';')), multispace0)),
+        ))(RawSpan::new(sql))?;
+        let storage = &PartitionedStorage::new(storage, table_map);
+        let plan = match &statement {
+            Statement::Select(_) => {
+                let plan = Plan::from_statement(&statement, table_map)?;
+                Some(Optimizer::standard(FunctionRegistry::builtins(), storage).optimize(plan))
+            }
+            Statement::Insert(_) => {
+                // Checks the clauses the executor doesn't run yet.
+                Plan::from_statement(&statement, table_map)?;
+                None
+            }
+            _ => return Err(ExecError::Unsupported("Preparing this statement")),
+        };
+        let mut references = References::default();
+        references.visit_statement(&statement);
+        references.placeholders.sort_unstable();
+        let parameters = references
+            .placeholders
+            .iter()
+            .enumerate()
+            .map(|(position, (offset, n))| {
+                let index = if *n == 0 {
+                    position
+                } else {
+                    usize::from(*n) - 1
+                };
+                (*offset, index)
+            })
+            .collect();
+        let default_schema = table_map.default_schema();
+        let tables = references
+            .tables
+            .into_iter()
+            .map(|(schema, table)| {
+                let schema = schema.unwrap_or(default_schema);
+                let version = table_map.version(Some(schema), table);
+                (schema.into(), table.into(), version)
+            })
+            .collect();
+        Ok(Self {
+            sql,
+            statement,
+            plan,
+            parameters,
+            default_schema: default_schema.into(),
+            tables,
+        })
+    }
+
+    #[must_use]
+    pub const fn statement(&self) -> &Statement<'a> {
+        &self.statement
+    }
+
+    /// Whether the tables of the statement and the default schema are the ones it was
+    /// prepared against.
+    #[must_use]
+    pub fn is_current(&self, table_map: &TableMap) -> bool {
+        *self.default_schema == *table_map.default_schema()
+            && self
+                .tables
+                .iter()
+                .all(|(schema, table, version)| table_map.version(Some(schema), table) == *version)
+    }
+
+    /// Runs the statement with `values`, the first one for `?` or `$1` and so on, like
+    /// [`execute`](crate::executor::execute). It is prepared again first if it isn't
+    /// [current](Self::is_current).
+    /// # Errors
+    /// Returns an error if preparing the statement again fails, if the number of values
+    /// doesn't match the placeholders or a value doesn't fit where it is used, see
+    /// [`bind_parameters`](rs_db_parser::ast::commands::select::Statement::bind_parameters),
+    /// or if running it fails.
+    pub fn execute(
+        &mut self,
+        values: &[Value],
+        table_map: &TableMap,
+        storage: &dyn Storage,
+        config: &Config,
+    ) -> Result<Output, ExecError> {
+        self.execute_with_cancellation(
+            values,
+            table_map,
+            storage,
+            config,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Runs the statement like [`execute`](Self::execute), until `cancellation` is
+    /// cancelled or the timeout of `config` has passed.
+    /// # Errors
+    /// Returns [`ExecError::QueryCancelled`] if the statement is cancelled before it is
+    /// done, or the errors of [`execute`](Self::execute).
+    pub fn execute_with_cancellation(
+        &mut self,
+        values: &[Value],
+        table_map: &TableMap,
+        storage: &dyn Storage,
+        config: &Config,
+        cancellation: &CancellationToken,
+    ) -> Result<Output, ExecError> {
+        if !self.is_current(table_map) {
+            *self = Self::prepare(self.sql, table_map, storage)?;
+        }
+        let cancellation = &with_timeout(cancellation, config);
+        let storage = &PartitionedStorage::new(storage, table_map);
+        let plan = match (&self.statement, &self.plan) {
+            (Statement::Select(select), Some(plan)) => {
+                // Only checks the values, the plan is already made.
+                select.bind_parameters(table_map, values)?;
+                let mut plan = plan.clone();
+                bind(
+                    &mut plan,
+                    &mut Bind {
+                        parameters: &self.parameters,
+                        values,
+                    },
+                );
+                plan
+            }
+            (Statement::Insert(insert), _) => {
+                let statement = Statement::Insert(insert.bind_parameters(table_map, values)?);
+                Optimizer::standard(FunctionRegistry::builtins(), storage)
+                    .optimize(Plan::from_statement(&statement, table_map)?)
+            }
+            _ => return Err(ExecError::Unsupported("Preparing this statement")),
+        };
+        run(&plan, storage, config, cancellation)
+    }
+}
+
+/// The tables and the parameter placeholders of a statement.
+#[derive(Default)]
+struct References<'a> {
+    tables: Vec<(Option<&'a str>, &'a str)>,
+    /// The offset of the span and the number of each placeholder, `0` for `?`.
+    placeholders: Vec<(usize, u16)>,
+}
+
+impl<'a> Visitor<'a> for References<'a> {
+    fn visit_table_name(&mut self, name: &TableName<'a>) {
+        let table = (name.schema_name(), *name.name.fragment());
+        if !self.tables.contains(&table) {
+            self.tables.push(table);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr<'a>) {
+        if let Expr::Parameter((span, n)) = expr {
+            self.placeholders.push((span.location_offset(), *n));
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Replaces the placeholders with their value.
+struct Bind<'p> {
+    /// The index in `values` of the value of each placeholder, by the offset of its span.
+    parameters: &'p HashMap<usize, usize>,
+    values: &'p [Value],
+}
+
+impl<'a> VisitorMut<'a> for Bind<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+        if let Expr::Parameter((span, _)) = expr {
+            if let Some(index) = self.parameters.get(&span.location_offset()) {
+                *expr = Expr::Literal((*span, self.values[*index].clone()));
+            }
+            return;
+        }
+        walk_expr_mut(self, expr);
+    }
+}
+
+/// Binds the placeholders of the expressions of `plan` and of its inputs.
+fn bind<'a>(plan: &mut Plan<'a>, binder: &mut Bind<'_>) {
+    for expr in plan.exprs_mut() {
+        binder.visit_expr_mut(expr);
+    }
+    for child in plan.children_mut() {
+        bind(child, binder);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use rs_db_parser::ast::commands::create::{Constraint, ConstraintKind};
+
+    use crate::{storage::testing::TestStorage, Row};
+
+    fn rows(output: Result<Output, ExecError>) -> Vec<Row> {
+        match output.unwrap() {
+            Output::Rows { rows, .. } => rows,
+            output => panic!("{output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_prepared_statement() {
+        let (storage, mut table_map) = TestStorage::new(&[
+            "CREATE TABLE t (a INT32, b VARCHAR(10))",
+            "CREATE TABLE u (id INT32)",
+        ]);
+        let config = Config::default();
+        let mut insert =
+            PreparedStatement::prepare("INSERT INTO t (a, b) VALUES (?, ?);", &table_map, &storage)
+                .unwrap();
+        for (a, b) in [(1, "x"), (2, "y"), (3, "z")] {
+            let values = [Value::I64(a), Value::VarChar(b.into())];
+            assert_eq!(
+                insert.execute(&values, &table_map, &storage, &config),
+                Ok(Output::Inserted(1))
+            );
+        }
+        assert_eq!(
+            insert.execute(&[Value::I32(4), Value::Null], &table_map, &storage, &config),
+            Ok(Output::Inserted(1))
+        );
+
+        let mut select = PreparedStatement::prepare(
+            "SELECT b, a + $2 FROM t WHERE a > $1 ORDER BY a",
+            &table_map,
+            &storage,
+        )
+        .unwrap();
+        assert_eq!(
+            rows(select.execute(
+                &[Value::I32(2), Value::I32(10)],
+                &table_map,
+                &storage,
+                &config
+            )),
+            [
+                vec![Value::VarChar("z".into()), Value::I32(13)],
+                vec![Value::Null, Value::I32(14)]
+            ]
+        );
+        assert_eq!(
+            rows(select.execute(
+                &[Value::I32(3), Value::I32(0)],
+                &table_map,
+                &storage,
+                &config
+            )),
+            [vec![Value::Null, Value::I32(4)]]
+        );
+        // The values are checked like the ones of an unprepared statement.
+        assert!(select
+            .execute(&[Value::I32(3)], &table_map, &storage, &config)
+            .is_err());
+        assert!(select
+            .execute(
+                &[Value::VarChar("x".into()), Value::I32(0)],
+                &table_map,
+                &storage,
+                &config
+            )
+            .is_err());
+
+        // Changing another table keeps the plan, replacing a table of the statement
+        // prepares it again.
+        let mut star = PreparedStatement::prepare("SELECT * FROM t", &table_map, &storage).unwrap();
+        let (_, other) = TestStorage::new(&[
+            "CREATE TABLE u (id INT64)",
+            "CREATE TABLE t (a INT32, c VARCHAR(10))",
+        ]);
+        table_map.insert("u".into(), other.get("u").unwrap().clone());
+        assert!(star.is_current(&table_map));
+        table_map.insert("t".into(), other.get("t").unwrap().clone());
+        assert!(!star.is_current(&table_map));
+        match star.execute(&[], &table_map, &storage, &config).unwrap() {
+            Output::Rows { columns, .. } => assert_eq!(columns, ["a".into(), "c".into()]),
+            output => panic!("{output:?}"),
+        }
+        assert!(star.is_current(&table_map));
+        assert!(select
+            .execute(
+                &[Value::I32(2), Value::I32(10)],
+                &table_map,
+                &storage,
+                &config
+            )
+            .is_err());
+
+        // So does changing the schema the names resolve in.
+        table_map.set_default_schema("other");
+        assert!(!star.is_current(&table_map));
+        assert!(star.execute(&[], &table_map, &storage, &config).is_err());
+
+        assert!(PreparedStatement::prepare("ANALYZE", &table_map, &storage).is_err());
+        assert!(PreparedStatement::prepare("SELECT 1; SELECT 2", &table_map, &storage).is_err());
+    }
+
+    #[test]
+    fn test_prepared_statement_constraints() {
+        let (storage, mut table_map) = TestStorage::new(&["CREATE TABLE t (a INT32)"]);
+        let config = Config::default();
+        let mut insert =
+            PreparedStatement::prepare("INSERT INTO t (a) VALUES (?)", &table_map, &storage)
+                .unwrap();
+        assert_eq!(
+            insert.execute(&[Value::I32(-1)], &table_map, &storage, &config),
+            Ok(Output::Inserted(1))
+        );
+
+        // Replacing the constraints prepares the statement again, and it checks the new ones.
+        table_map.set_constraints(
+            None,
+            "t",
+            vec![Constraint {
+                name: "t_check".into(),
+                kind: ConstraintKind::Check("a > 0".into()),
+            }],
+        );
+        assert!(!insert.is_current(&table_map));
+        match insert.execute(&[Value::I32(-2)], &table_map, &storage, &config) {
+            Err(ExecError::ConstraintViolation(violation)) => {
+                assert_eq!(violation.constraint, "t_check".into());
+            }
+            output => panic!("{output:?}"),
+        }
+        assert_eq!(
+            insert.execute(&[Value::I32(2)], &table_map, &storage, &config),
+            Ok(Output::Inserted(1))
+        );
+        assert!(insert.is_current(&table_map));
+    }
+}
@@ -0,0 +1,449 @@
+use std::{cell::RefCell, path::Path, sync::Arc};
+
+use rs_db_parser::{
+    ast::{
+        commands::transaction::{self, IsolationLevel},
+        statement::Statement,
+    },
+    parse::TableMap,
+};
+use rs_db_storage::engine::ScanOptions;
+
+use crate::{
+    cancel::CancellationToken,
+    error::ExecError,
+    executor::{execute_with_cancellation, with_timeout, Config, Output},
+    partition::Partitions,
+    storage::{Index, KeyRange, RowIter, Storage},
+    transaction::{LockMode, TransactionId, TransactionManager},
+    Row,
+};
+
+/// The transaction a [`Session`] is in.
+#[derive(Debug)]
+struct Transaction {
+    id: TransactionId,
+    isolation: IsolationLevel,
+    /// The rows the transaction added, with their table, to remove when it is rolled back.
+    undo: Vec<(Box<str>, Row)>,
+    /// The savepoints, the latest last, with the number of rows of `undo` when they were
+    /// set.
+    savepoints: Vec<(Box<str>, usize)>,
+}
+
+/// A connection to the database: the statements it runs are in its transaction, begun with
+/// `BEGIN` and ended with `COMMIT` or `ROLLBACK`. A statement run outside of a transaction
+/// is in one of its own, committed when it succeeds.
+///
+/// The tables a statement reads and writes are locked by the [`TransactionManager`] the
+/// sessions share, see [`IsolationLevel`]: a `SERIALIZABLE` transaction keeps its locks
+/// until it ends, a `READ COMMITTED` one releases the locks of its reads after each
+/// statement. A statement that fails is rolled back, a deadlock rolls its whole
+/// transaction back.
+pub struct Session<'s> {
+    manager: Arc<TransactionManager>,
+    storage: &'s dyn Storage,
+    /// The level of the transactions the session begins, see
+    /// [`transaction::Statement::SetIsolationLevel`].
+    isolation: IsolationLevel,
+    transaction: Option<Transaction>,
+}
+
+impl<'s> Session<'s> {
+    /// A session outside of a transaction, at the default isolation level.
+    #[must_use]
+    pub fn new(manager: Arc<TransactionManager>, storage: &'s dyn Storage) -> Self {
+        Self {
+            manager,
+            storage,
+            isolation: IsolationLevel::default(),
+            transaction: None,
+        }
+    }
+
+    /// The level of the transactions the session begins.
+    #[must_use]
+    pub const fn isolation(&self) -> IsolationLevel {
+        self.isolation
+    }
+
+    /// The transaction the session is in, if it began one.
+    #[must_use]
+    pub fn transaction(&self) -> Option<TransactionId> {
+        self.transaction.as_ref().map(|transaction| transaction.id)
+    }
+
+    /// Runs `statement` in the transaction of the session, like
+    /// [`execute`](crate::executor::execute). A transaction control statement returns
+    /// [`Output::Done`].
+    /// # Errors
+    /// Returns the errors of [`Session::execute_with_cancellation`].
+    pub fn execute(
+        &mut self,
+        statement: &Statement<'_>,
+        table_map: &TableMap,
+        config: &Config,
+    ) -> Result<Output, ExecError> {
+        self.execute_with_cancellation(statement, table_map, config, &CancellationToken::new())
+    }
+
+    /// Runs `statement` like [`Session::execute`], until `cancellation` is cancelled or the
+    /// timeout of `config` has passed, waiting for the locks of its tables meanwhile.
+    /// # Errors
+    /// Returns [`ExecError::TransactionInProgress`] for a `BEGIN` or a
+    /// `SET TRANSACTION` in a transaction, [`ExecError::NoTransaction`] for a `COMMIT`,
+    /// a `ROLLBACK` or a `SAVEPOINT` outside of one, [`ExecError::SavepointNotFound`] for a
+    /// `ROLLBACK TO` an unknown savepoint, [`ExecError::Deadlock`] if a lock can't be
+    /// taken without a deadlock, or the errors of
+    /// [`execute_with_cancellation`](crate::executor::execute_with_cancellation).
+    pub fn execute_with_cancellation(
+        &mut self,
+        statement: &Statement<'_>,
+        table_map: &TableMap,
+        config: &Config,
+        cancellation: &CancellationToken,
+    ) -> Result<Output, ExecError> {
+        let Statement::Transaction(statement) = statement else {
+            return self.run(statement, table_map, config, cancellation);
+        };
+        match statement {
+            transaction::Statement::Begin => {
+                if self.transaction.is_some() {
+                    return Err(ExecError::TransactionInProgress);
+                }
+                self.transaction = Some(self.begin());
+            }
+            transaction::Statement::Commit
+            | transaction::Statement::Rollback { savepoint: None } => {
+                let transaction = self.transaction.take().ok_or(ExecError::NoTransaction)?;
+                let rollback = matches!(statement, transaction::Statement::Rollback { .. });
+                self.end(transaction, rollback)?;
+            }
+            transaction::Statement::Rollback {
+                savepoint: Some(name),
+            } => {
+                let transaction = self.transaction.as_mut().ok_or(ExecError::NoTransaction)?;
+                let position = transaction
+                    .savepoints
+                    .iter()
+                    .rposition(|(savepoint, _)| **savepoint == **name.fragment())
+                    .ok_or_else(|| ExecError::SavepointNotFound((*name.fragment()).into()))?;
+                // The savepoint is kept, the ones set after it are released.
+                let len = transaction.savepoints[position].1;
+                transaction.savepoints.truncate(position + 1);
+                undo(self.storage, &mut transaction.undo, len)?;
+            }
+            transaction::Statement::Savepoint(name) => {
+                let transaction = self.transaction.as_mut().ok_or(ExecError::NoTransaction)?;
+                let len = transaction.undo.len();
+                transaction
+                    .savepoints
+                    .push(((*name.fragment()).into(), len));
+            }
+            transaction::Statement::SetIsolationLevel(level) => {
+                if self.transaction.is_some() {
+                    return Err(ExecError::TransactionInProgress);
+                }
+                self.isolation = *level;
+            }
+        }
+        Ok(Output::Done)
+    }
+
+    fn begin(&self) -> Transaction {
+        Transaction {
+            id: self.manager.begin(),
+            isolation: self.isolation,
+            undo: Vec::new(),
+            savepoints: Vec::new(),
+        }
+    }
+
+    /// Ends `transaction`, removing the rows it added if it is rolled back, and releases
+    /// its locks.
+    fn end(&self, mut transaction: Transaction, rollback: bool) -> Result<(), ExecError> {
+        let undone = if rollback {
+            undo(self.storage, &mut transaction.undo, 0)
+        } else {
+            Ok(())
+        };
+        self.manager.release_all(transaction.id);
+        undone
+    }
+
+    /// Runs a statement that isn't a transaction control one in the transaction of the
+    /// session, or in one of its own.
+    fn run(
+        &mut self,
+        statement: &Statement<'_>,
+        table_map: &TableMap,
+        config: &Config,
+        cancellation: &CancellationToken,
+    ) -> Result<Output, ExecError> {
+        let cancellation = &with_timeout(cancellation, config);
+        let autocommit = self.transaction.is_none();
+        let mut transaction = self.transaction.take().unwrap_or_else(|| self.begin());
+        let storage = LockingStorage {
+            storage: self.storage,
+            manager: &self.manager,
+            transaction: transaction.id,
+            cancellation,
+            inserted: RefCell::default(),
+        };
+        let result =
+            execute_with_cancellation(statement, table_map, &storage, config, cancellation);
+        let mut inserted = storage.inserted.into_inner();
+        if transaction.isolation == IsolationLevel::ReadCommitted {
+            self.manager.release_shared(transaction.id);
+        }
+        let (result, undone) = match result {
+            Err(ExecError::Deadlock) => {
+                transaction.undo.append(&mut inserted);
+                self.end(transaction, true)?;
+                return Err(ExecError::Deadlock);
+            }
+            Ok(output) => {
+                transaction.undo.append(&mut inserted);
+                (Ok(output), Ok(()))
+            }
+            Err(err) => (Err(err), undo(self.storage, &mut inserted, 0)),
+        };
+        if autocommit {
+            self.end(transaction, false)?;
+        } else {
+            self.transaction = Some(transaction);
+        }
+        undone?;
+        result
+    }
+}
+
+impl Drop for Session<'_> {
+    /// Rolls back the transaction the session is in.
+    fn drop(&mut self) {
+        if let Some(transaction) = self.transaction.take() {
+            // The rows that can't be removed stay, like after a failed `ROLLBACK`.
+            let _ = self.end(transaction, true);
+        }
+    }
+}
+
+/// Removes the rows of `undo` past the first `len` ones from `storage`, the latest first.
+fn undo(
+    storage: &dyn Storage,
+    undo: &mut Vec<(Box<str>, Row)>,
+    len: usize,
+) -> Result<(), ExecError> {
+    while undo.len() > len {
+        let Some((table, row)) = undo.pop() else {
+            break;
+        };
+        storage.remove(&table, &row)?;
+    }
+    Ok(())
+}
+
+/// The storage of a session for a statement, which locks each table for the transaction
+/// before reading or writing it, and keeps the rows the statement added.
+struct LockingStorage<'a> {
+    storage: &'a dyn Storage,
+    manager: &'a TransactionManager,
+    transaction: TransactionId,
+    cancellation: &'a CancellationToken,
+    inserted: RefCell<Vec<(Box<str>, Row)>>,
+}
+
+impl LockingStorage<'_> {
+    fn lock(&self, table: &str, mode: LockMode) -> Result<(), ExecError> {
+        self.manager
+            .lock(self.transaction, table, mode, self.cancellation)
+    }
+}
+
+/// Rows aren't removed by the statements, only by the rollbacks of the session, so
+/// [`Storage::remove`] isn't forwarded.
+impl Storage for LockingStorage<'_> {
+    fn scan(&self, table: &str) -> Result<RowIter<'_>, ExecError> {
+        self.lock(table, LockMode::Shared)?;
+        self.storage.scan(table)
+    }
+
+    fn scan_with(&self, table: &str, options: &ScanOptions) -> Result<RowIter<'_>, ExecError> {
+        self.lock(table, LockMode::Shared)?;
+        self.storage.scan_with(table, options)
+    }
+
+    fn insert(&self, table: &str, row: Row) -> Result<(), ExecError> {
+        self.lock(table, LockMode::Exclusive)?;
+        self.storage.insert(table, row.clone())?;
+        self.inserted.borrow_mut().push((table.into(), row));
+        Ok(())
+    }
+
+    fn vacuum(&self, table: &str) -> Result<u64, ExecError> {
+        self.lock(table, LockMode::Exclusive)?;
+        self.storage.vacuum(table)
+    }
+
+    fn backup(&self, tables: &[&str], dir: &Path) -> Result<Vec<u64>, ExecError> {
+        for table in tables {
+            self.lock(table, LockMode::Shared)?;
+        }
+        self.storage.backup(tables, dir)
+    }
+
+    fn row_count(&self, table: &str) -> Option<u64> {
+        self.storage.row_count(table)
+    }
+
+    fn indexes(&self, table: &str) -> Vec<Index> {
+        self.storage.indexes(table)
+    }
+
+    fn index_scan(
+        &self,
+        table: &str,
+        index: &str,
+        range: &KeyRange,
+        key_only: bool,
+    ) -> Result<RowIter<'_>, ExecError> {
+        self.lock(table, LockMode::Shared)?;
+        self.storage.index_scan(table, index, range, key_only)
+    }
+
+    fn partitions(&self, table: &str) -> Option<Partitions> {
+        self.storage.partitions(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::time::Duration;
+
+    use rs_db_parser::{parse::RawSpan, value::Value};
+
+    use super::*;
+    use crate::storage::testing::TestStorage;
+
+    fn run(
+        session: &mut Session<'_>,
+        table_map: &TableMap,
+        input: &str,
+    ) -> Result<Output, ExecError> {
+        let (_, statement) = Statement::parse_with_table_map(table_map, RawSpan::new(input))
+            .unwrap_or_else(|e| panic!("{input}: {e:?}"));
+        let config = Config {
+            timeout: Some(Duration::from_millis(30)),
+            ..Config::default()
+        };
+        session.execute(&statement, table_map, &config)
+    }
+
+    fn ids(session: &mut Session<'_>, table_map: &TableMap) -> Vec<i32> {
+        match run(session, table_map, "SELECT id FROM t ORDER BY id").unwrap() {
+            Output::Rows { rows, .. } => rows
+                .into_iter()
+                .map(|row| match row[0] {
+                    Value::I32(id) => id,
+                    _ => panic!("{row:?}"),
+                })
+                .collect(),
+            output => panic!("{output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_session() {
+        let (storage, table_map) = TestStorage::new(&["CREATE TABLE t (id INT32)"]);
+        let mut session = Session::new(Arc::new(TransactionManager::new()), &storage);
+        let run = |session: &mut Session<'_>, input| run(session, &table_map, input);
+
+        // Outside of a transaction, each statement commits on its own.
+        run(&mut session, "INSERT INTO t (id) VALUES (1)").unwrap();
+        assert_eq!(session.transaction(), None);
+        assert_eq!(run(&mut session, "COMMIT"), Err(ExecError::NoTransaction));
+
+        assert_eq!(run(&mut session, "BEGIN"), Ok(Output::Done));
+        assert_eq!(
+            run(&mut session, "BEGIN"),
+            Err(ExecError::TransactionInProgress)
+        );
+        assert_eq!(
+            run(&mut session, "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE"),
+            Err(ExecError::TransactionInProgress)
+        );
+        run(&mut session, "INSERT INTO t (id) VALUES (2)").unwrap();
+        run(&mut session, "SAVEPOINT a").unwrap();
+        run(&mut session, "INSERT INTO t (id) VALUES (3), (4)").unwrap();
+        run(&mut session, "SAVEPOINT b").unwrap();
+        run(&mut session, "INSERT INTO t (id) VALUES (5)").unwrap();
+        assert_eq!(ids(&mut session, &table_map), [1, 2, 3, 4, 5]);
+        run(&mut session, "ROLLBACK TO SAVEPOINT a").unwrap();
+        assert_eq!(ids(&mut session, &table_map), [1, 2]);
+        assert_eq!(
+            run(&mut session, "ROLLBACK TO b"),
+            Err(ExecError::SavepointNotFound("b".into()))
+        );
+        // A failed statement is rolled back, the transaction goes on.
+        assert!(run(
+            &mut session,
+            "INSERT INTO t (id) VALUES (6), (0) RETURNING 1 / id"
+        )
+        .is_err());
+        run(&mut session, "INSERT INTO t (id) VALUES (7)").unwrap();
+        run(&mut session, "COMMIT").unwrap();
+        assert_eq!(ids(&mut session, &table_map), [1, 2, 7]);
+
+        run(&mut session, "BEGIN").unwrap();
+        run(&mut session, "INSERT INTO t (id) VALUES (8)").unwrap();
+        run(&mut session, "ROLLBACK").unwrap();
+        assert_eq!(ids(&mut session, &table_map), [1, 2, 7]);
+
+        // Dropping a session rolls its transaction back.
+        run(&mut session, "BEGIN").unwrap();
+        run(&mut session, "INSERT INTO t (id) VALUES (9)").unwrap();
+        drop(session);
+        assert_eq!(storage.row_count("public.t"), Some(3));
+    }
+
+    #[test]
+    fn test_isolation_levels() {
+        let (storage, table_map) = TestStorage::new(&["CREATE TABLE t (id INT32)"]);
+        let manager = Arc::new(TransactionManager::new());
+        let mut a = Session::new(Arc::clone(&manager), &storage);
+        let mut b = Session::new(manager, &storage);
+        let run = |session: &mut Session<'_>, input| run(session, &table_map, input);
+
+        // The rows a transaction added aren't read before it commits.
+        run(&mut a, "BEGIN").unwrap();
+        run(&mut a, "INSERT INTO t (id) VALUES (1)").unwrap();
+        assert_eq!(
+            run(&mut b, "SELECT id FROM t"),
+            Err(ExecError::QueryCancelled)
+        );
+        run(&mut a, "COMMIT").unwrap();
+        assert_eq!(ids(&mut b, &table_map), [1]);
+
+        // A READ COMMITTED transaction doesn't keep the tables it read from the others.
+        assert_eq!(a.isolation(), IsolationLevel::ReadCommitted);
+        run(&mut a, "BEGIN").unwrap();
+        assert_eq!(ids(&mut a, &table_map), [1]);
+        run(&mut b, "INSERT INTO t (id) VALUES (2)").unwrap();
+        assert_eq!(ids(&mut a, &table_map), [1, 2]);
+        run(&mut a, "COMMIT").unwrap();
+
+        // A SERIALIZABLE one does, until it ends.
+        run(&mut a, "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE").unwrap();
+        run(&mut a, "BEGIN").unwrap();
+        assert_eq!(ids(&mut a, &table_map), [1, 2]);
+        assert_eq!(
+            run(&mut b, "INSERT INTO t (id) VALUES (3)"),
+            Err(ExecError::QueryCancelled)
+        );
+        assert_eq!(ids(&mut a, &table_map), [1, 2]);
+        run(&mut a, "COMMIT").unwrap();
+        run(&mut b, "INSERT INTO t (id) VALUES (3)").unwrap();
+        assert_eq!(ids(&mut a, &table_map), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+
+use rs_db_parser::{
+    ast::commands::select::SortOrder,
+    eval::Evaluator,
+    value::{Collation, Value},
+};
+
+use crate::{
+    error::ExecError,
+    executor::Config,
+    operator::{eval, BoxedOperator, Operator},
+    plan::{Layout, SortKey},
+    spill::{row_size, SpillFile, SpillReader},
+    Row,
+};
+
+/// The most runs merged at once. More runs are first merged into longer ones, so a sort
+/// never has more temporary files open.
+const MAX_MERGE_WIDTH: usize = 64;
+
+/// A row and the values of the keys it is sorted by.
+type Entry = (Vec<Value>, Row);
+
+/// Sorts rows by the values of their keys, keeping the ones with equal keys in the order
+/// they were pushed. While the rows fit the memory budget they are sorted in memory, then
+/// each time they exceed it they are sorted and written to a temporary file, a run, and the
+/// runs are merged at the end.
+pub(crate) struct Sorter<'c> {
+    /// The order and the place of the `NULL`s of each key.
+    keys: Vec<(SortOrder, bool)>,
+    config: &'c Config,
+    entries: Vec<Entry>,
+    size: usize,
+    runs: Vec<SpillFile>,
+}
+
+impl<'c> Sorter<'c> {
+    pub(crate) const fn new(keys: Vec<(SortOrder, bool)>, config: &'c Config) -> Self {
+        Self {
+            keys,
+            config,
+            entries: Vec::new(),
+            size: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, keys: Vec<Value>, row: Row) -> Result<(), ExecError> {
+        self.size += row_size(&keys) + row_size(&row);
+        self.entries.push((keys, row));
+        if self.size > self.config.memory_budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the rows in memory to a new run, each followed by its keys.
+    fn spill(&mut self) -> Result<(), ExecError> {
+        let entries = self.sorted_entries()?;
+        let mut run = SpillFile::create(&self.config.spill_dir)?;
+        for (keys, mut row) in entries {
+            row.extend(keys);
+            run.write(&row)?;
+        }
+        self.runs.push(run);
+        self.size = 0;
+        Ok(())
+    }
+
+    fn sorted_entries(&mut self) -> Result<Vec<Entry>, ExecError> {
+        let mut entries = std::mem::take(&mut self.entries);
+        // `sort_by` can't fail, so the first error is kept aside and returned after.
+        let mut error = None;
+        entries.sort_by(|(a, _), (b, _)| {
+            compare(&self.keys, a, b).unwrap_or_else(|e| {
+                error.get_or_insert(e);
+                Ordering::Equal
+            })
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(entries),
+        }
+    }
+
+    /// The rows pushed, sorted.
+    pub(crate) fn finish(mut self) -> Result<Sorted, ExecError> {
+        if self.runs.is_empty() {
+            return Ok(Sorted::Memory(self.sorted_entries()?.into_iter()));
+        }
+        if !self.entries.is_empty() {
+            self.spill()?;
+        }
+        let mut runs = std::mem::take(&mut self.runs);
+        // Consecutive runs are merged together, so the earlier rows stay in the earlier runs.
+        while runs.len() > MAX_MERGE_WIDTH {
+            let mut merged = Vec::new();
+            let mut rest = runs.into_iter().peekable();
+            while rest.peek().is_some() {
+                let group = rest.by_ref().take(MAX_MERGE_WIDTH).collect();
+                let mut merge = Merge::new(self.keys.clone(), group)?;
+                let mut run = SpillFile::create(&self.config.spill_dir)?;
+                while let Some((keys, mut row)) = merge.next_entry()? {
+                    row.extend(keys);
+                    run.write(&row)?;
+                }
+                merged.push(run);
+            }
+            runs = merged;
+        }
+        Ok(Sorted::Merge(Merge::new(self.keys, runs)?))
+    }
+}
+
+/// Compares the keys of two rows.
+fn compare(keys: &[(SortOrder, bool)], a: &[Value], b: &[Value]) -> Result<Ordering, ExecError> {
+    for ((order, nulls_first), (a, b)) in keys.iter().zip(a.iter().zip(b)) {
+        match a.sql_cmp(b, *order, *nulls_first, Collation::Binary)? {
+            Ordering::Equal => {}
+            ordering => return Ok(ordering),
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+/// The rows of a [`Sorter`], in order.
+pub(crate) enum Sorted {
+    Memory(std::vec::IntoIter<Entry>),
+    Merge(Merge),
+}
+
+impl Sorted {
+    pub(crate) fn next_row(&mut self) -> Result<Option<Row>, ExecError> {
+        match self {
+            Self::Memory(entries) => Ok(entries.next().map(|(_, row)| row)),
+            Self::Merge(merge) => Ok(merge.next_entry()?.map(|(_, row)| row)),
+        }
+    }
+}
+
+/// Merges sorted runs. The next row is the smallest of the first rows of the runs, the one
+/// of the earliest run among equal ones, so rows with equal keys keep their order.
+pub(crate) struct Merge {
+    keys: Vec<(SortOrder, bool)>,
+    runs: Vec<SpillReader>,
+    heads: Vec<Option<Entry>>,
+}
+
+impl Merge {
+    fn new(keys: Vec<(SortOrder, bool)>, runs: Vec<SpillFile>) -> Result<Self, ExecError> {
+        let mut runs = runs
+            .into_iter()
+            .map(SpillFile::into_reader)
+            .collect::<Result<Vec<_>, _>>()?;
+        let heads = runs
+            .iter_mut()
+            .map(|run| Self::read(keys.len(), run))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys, runs, heads })
+    }
+
+    /// The next row of `run` and its keys, which follow it.
+    fn read(keys: usize, run: &mut SpillReader) -> Result<Option<Entry>, ExecError> {
+        let Some(mut row) = run.next().transpose()? else {
+            return Ok(None);
+        };
+        let keys = row.split_off(row.len() - keys);
+        Ok(Some((keys, row)))
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Entry>, ExecError> {
+        let mut smallest: Option<usize> = None;
+        for (index, head) in self.heads.iter().enumerate() {
+            let Some((keys, _)) = head else {
+                continue;
+            };
+            smallest = match smallest.and_then(|s| self.heads[s].as_ref()) {
+                Some((smallest_keys, _))
+                    if compare(&self.keys, keys, smallest_keys)? != Ordering::Less =>
+                {
+                    smallest
+                }
+                _ => Some(index),
+            };
+        }
+        let Some(index) = smallest else {
+            return Ok(None);
+        };
+        let next = Self::read(self.keys.len(), &mut self.runs[index])?;
+        Ok(std::mem::replace(&mut self.heads[index], next))
+    }
+}
+
+/// Reads all its input, then returns it sorted. The rows that don't fit the memory budget
+/// are sorted in runs written to temporary files, see [`Sorter`].
+pub(crate) struct Sort<'p, 'a> {
+    pub(crate) input: BoxedOperator<'p>,
+    pub(crate) keys: &'p [SortKey<'a>],
+    pub(crate) layout: Layout,
+    pub(crate) evaluator: Evaluator<'p>,
+    pub(crate) config: &'p Config,
+    pub(crate) sorted: Option<Sorted>,
+}
+
+impl Sort<'_, '_> {
+    fn sort(&mut self) -> Result<Sorted, ExecError> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| (key.order, key.nulls_first))
+            .collect();
+        let mut sorter = Sorter::new(keys, self.config);
+        while let Some(row) = self.input.next()? {
+            let keys = self
+                .keys
+                .iter()
+                .map(|key| eval(self.evaluator, &key.expr, &self.layout, &row))
+                .collect::<Result<Vec<_>, _>>()?;
+            sorter.push(keys, row)?;
+        }
+        sorter.finish()
+    }
+}
+
+impl Operator for Sort<'_, '_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        if self.sorted.is_none() {
+            self.sorted = Some(self.sort()?);
+        }
+        self.sorted.as_mut().map_or(Ok(None), Sorted::next_row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn sort(config: &Config, rows: &[(i64, &str)]) -> Vec<Row> {
+        let mut sorter = Sorter::new(vec![(SortOrder::Desc, true)], config);
+        for (key, name) in rows {
+            let key = if *key < 0 {
+                Value::Null
+            } else {
+                Value::I64(*key)
+            };
+            sorter
+                .push(vec![key.clone()], vec![key, Value::VarChar((*name).into())])
+                .unwrap();
+        }
+        let mut sorted = sorter.finish().unwrap();
+        let mut rows = Vec::new();
+        while let Some(row) = sorted.next_row().unwrap() {
+            rows.push(row);
+        }
+        rows
+    }
+
+    #[test]
+    fn test_external_sort() {
+        let rows = (0..500)
+            .map(|i| ((i * 7919) % 101 - 1, ["a", "b", "c"][i as usize % 3]))
+            .collect::<Vec<_>>();
+        let in_memory = sort(&Config::default(), &rows);
+        assert_eq!(in_memory.len(), 500);
+        assert_eq!(in_memory[0][0], Value::Null);
+        assert_eq!(in_memory[499][0], Value::I64(0));
+        // A few rows per run, more runs than are merged at once.
+        let spilled = sort(
+            &Config {
+                memory_budget: 1000,
+                ..Config::default()
+            },
+            &rows,
+        );
+        assert_eq!(spilled, in_memory);
+        // Rows with equal keys keep their order.
+        let mut stable = rows.clone();
+        stable.sort_by_key(|(key, _)| (*key >= 0, std::cmp::Reverse(*key)));
+        let names = |rows: &[Row]| rows.iter().map(|row| row[1].clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names(&spilled),
+            stable
+                .iter()
+                .map(|(_, name)| Value::VarChar((*name).into()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use rs_db_parser::{
+    ast::commands::create::SqlType,
+    codec::{decode_row, encode_row},
+    value::Value,
+};
+
+use crate::{error::ExecError, Row};
+
+/// Numbers the temporary files of the process.
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// The memory a row takes, roughly: its values and the text, bytes and elements they point
+/// to. Operators compare it to their memory budget.
+#[must_use]
+pub fn row_size(row: &[Value]) -> usize {
+    row.iter().map(value_size).sum::<usize>() + std::mem::size_of::<Row>()
+}
+
+fn value_size(value: &Value) -> usize {
+    std::mem::size_of::<Value>()
+        + match value {
+            Value::VarChar(s) | Value::Char(s) => s.len(),
+            Value::Blob(b) => b.len(),
+            Value::Array(values) => values.iter().map(value_size).sum(),
+            // Documents are not measured, a guess is enough to decide when to spill.
+            Value::Json(_) => 64,
+            _ => 0,
+        }
+}
+
+/// Rows an operator writes to a temporary file when they don't fit its memory budget, to
+/// read them back later. The file is deleted when it is dropped.
+///
+/// A row is written as its length in 4 bytes, then a byte that is `1` when the types of its
+/// values follow, as JSON after their length in 4 bytes, or `0` when they are the types of
+/// the previous row, then the row in the format of [`encode_row`].
+#[derive(Debug)]
+pub struct SpillFile {
+    path: TempPath,
+    writer: BufWriter<File>,
+    types: Vec<SqlType>,
+    rows: u64,
+}
+
+impl SpillFile {
+    /// Creates an empty file in `dir`.
+    /// # Errors
+    /// Returns an error if the file can't be created.
+    pub fn create(dir: &Path) -> Result<Self, ExecError> {
+        let id = NEXT_FILE.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("rs_db-{}-{id}.spill", std::process::id()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let writer = BufWriter::new(file);
+        Ok(Self {
+            path: TempPath(path),
+            writer,
+            types: Vec::new(),
+            rows: 0,
+        })
+    }
+
+    /// The number of rows written.
+    #[must_use]
+    pub const fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Appends a row.
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn write(&mut self, row: &[Value]) -> Result<(), ExecError> {
+        // A `NULL` has no type, its place is taken by any type as the null bitmap of the row
+        // says it has no value. The elements of an array are written as their common type.
+        let mut values = Vec::with_capacity(row.len());
+        let mut types = Vec::with_capacity(row.len());
+        for value in row {
+            let tp = value.sql_type().unwrap_or(SqlType::Bool);
+            values.push(match value {
+                Value::Array(_) => value
+                    .clone()
+                    .cast(tp.clone())
+                    .map_err(|e| ExecError::Io(format!("can't write an array: {e}").into()))?,
+                value => value.clone(),
+            });
+            types.push(tp);
+        }
+        let mut record = Vec::new();
+        if types == self.types {
+            record.push(0);
+        } else {
+            record.push(1);
+            let json =
+                serde_json::to_vec(&types).map_err(|e| ExecError::Io(e.to_string().into()))?;
+            record.extend(length(json.len()).to_le_bytes());
+            record.extend(json);
+            self.types = types;
+        }
+        encode_row(&values, &mut record);
+        self.writer.write_all(&length(record.len()).to_le_bytes())?;
+        self.writer.write_all(&record)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Reads back the rows written, in order.
+    /// # Errors
+    /// Returns an error if the file can't be flushed or read.
+    pub fn into_reader(self) -> Result<SpillReader, ExecError> {
+        let mut file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(SpillReader {
+            reader: BufReader::new(file),
+            types: Vec::new(),
+            _path: self.path,
+        })
+    }
+}
+
+/// The path of a temporary file, which is deleted when it is dropped.
+#[derive(Debug)]
+struct TempPath(PathBuf);
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[allow(clippy::expect_used)]
+fn length(len: usize) -> u32 {
+    u32::try_from(len).expect("spilled rows are at most u32::MAX bytes long")
+}
+
+/// The rows of a [`SpillFile`], which is deleted once the reader is dropped.
+#[derive(Debug)]
+pub struct SpillReader {
+    reader: BufReader<File>,
+    types: Vec<SqlType>,
+    _path: TempPath,
+}
+
+impl SpillReader {
+    fn read_row(&mut self) -> Result<Option<Row>, ExecError> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let mut record = vec![0; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut record)?;
+        let corrupted = || ExecError::Io("corrupted spill file".into());
+        let (flag, mut rest) = record.split_first().ok_or_else(corrupted)?;
+        if *flag == 1 {
+            let (len, after) = rest.split_at_checked(4).ok_or_else(corrupted)?;
+            let len = u32::from_le_bytes(len.try_into().map_err(|_| corrupted())?) as usize;
+            let (json, after) = after.split_at_checked(len).ok_or_else(corrupted)?;
+            self.types = serde_json::from_slice(json).map_err(|_| corrupted())?;
+            rest = after;
+        }
+        Ok(Some(decode_row(&self.types, rest)?))
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = Result<Row, ExecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_row().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_spill_round_trip() {
+        let rows = vec![
+            vec![Value::I32(1), Value::VarChar("a".into()), Value::Null],
+            vec![Value::I32(2), Value::VarChar("b".into()), Value::Null],
+            vec![
+                Value::Null,
+                Value::Char("c ".into()),
+                Value::Array([Value::I8(1), Value::Null, Value::I64(3)].into()),
+            ],
+        ];
+        let mut file = SpillFile::create(&std::env::temp_dir()).unwrap();
+        for row in &rows {
+            file.write(row).unwrap();
+        }
+        assert_eq!(file.rows(), 3);
+        let path = file.path.0.clone();
+        let reader = file.into_reader().unwrap();
+        let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read[..2], rows[..2]);
+        // The elements of an array come back as their common type.
+        assert_eq!(
+            read[2][2].compare(&rows[2][2]),
+            Ok(Some(std::cmp::Ordering::Equal))
+        );
+        assert_eq!(read[2][1], Value::Char("c".into()));
+        assert!(!path.exists());
+    }
+}
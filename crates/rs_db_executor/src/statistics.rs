@@ -0,0 +1,219 @@
+use std::{
+    cmp::Ordering,
+    hash::{DefaultHasher, Hasher},
+};
+
+use rs_db_parser::{
+    parse::{ColumnStatistics, TableStatistics},
+    value::Value,
+};
+
+use crate::{cancel::CancellationToken, error::ExecError, storage::Storage};
+
+/// The bits of a hash that choose the register of a [`HyperLogLog`], so it has 4096
+/// registers and estimates within about 1.6%.
+const PRECISION: u32 = 12;
+
+/// Estimates the number of distinct values it is given in a fixed memory: the hash of each
+/// value picks a register, which keeps the longest run of leading zeros seen in the rest of
+/// the hashes. See Flajolet et al., "HyperLogLog: the analysis of a near-optimal
+/// cardinality estimation algorithm".
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; 1 << PRECISION],
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// Adds a value, the values [`Value::compare`] finds equal count once.
+    pub fn insert(&mut self, value: &Value) {
+        let mut state = DefaultHasher::new();
+        value.hash_comparable(&mut state);
+        let hash = state.finish();
+        #[allow(clippy::cast_possible_truncation)]
+        let register = (hash >> (64 - PRECISION)) as usize;
+        // The bit set at the end bounds the run when the rest of the hash is all zeros.
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        #[allow(clippy::cast_possible_truncation)]
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    /// The estimated number of distinct values added.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum = self
+            .registers
+            .iter()
+            .map(|rank| 2f64.powi(-i32::from(*rank)))
+            .sum::<f64>();
+        let estimate = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|rank| **rank == 0).count();
+        // Few values leave registers empty, counting them is more precise then.
+        let estimate = if estimate <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            estimate
+        };
+        estimate.round() as u64
+    }
+}
+
+/// Collects the [`ColumnStatistics`] of the values of a column.
+#[derive(Debug, Clone, Default)]
+struct ColumnCollector {
+    nulls: u64,
+    distinct: HyperLogLog,
+    min: Option<Value>,
+    max: Option<Value>,
+    /// Whether the values could all be compared, min and max are dropped otherwise.
+    unordered: bool,
+}
+
+impl ColumnCollector {
+    fn insert(&mut self, value: &Value) {
+        if value.is_null() {
+            self.nulls += 1;
+            return;
+        }
+        self.distinct.insert(value);
+        if self.unordered {
+            return;
+        }
+        let keep = |bound: &mut Option<Value>, ordering| match bound {
+            Some(current) => match value.compare(current) {
+                Ok(Some(found)) => {
+                    if found == ordering {
+                        *current = value.clone();
+                    }
+                    true
+                }
+                Ok(None) | Err(_) => false,
+            },
+            None => {
+                *bound = Some(value.clone());
+                true
+            }
+        };
+        if !keep(&mut self.min, Ordering::Less) || !keep(&mut self.max, Ordering::Greater) {
+            self.unordered = true;
+            self.min = None;
+            self.max = None;
+        }
+    }
+
+    fn finish(self, rows: u64) -> ColumnStatistics {
+        ColumnStatistics {
+            nulls: self.nulls,
+            // The estimate may exceed the values there are, when there are few of them.
+            distinct: self.distinct.estimate().min(rows - self.nulls),
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// Reads all the rows of `table`, a [`table_key`](crate::storage::table_key) whose rows
+/// hold the values of `columns`, and collects their statistics.
+/// # Errors
+/// Returns an error if the table can't be read, or `cancellation` is cancelled.
+pub fn analyze(
+    storage: &dyn Storage,
+    table: &str,
+    columns: &[Box<str>],
+    cancellation: &CancellationToken,
+) -> Result<TableStatistics, ExecError> {
+    let mut collectors = vec![ColumnCollector::default(); columns.len()];
+    let mut row_count = 0;
+    for row in storage.scan(table)? {
+        cancellation.check()?;
+        for (collector, value) in collectors.iter_mut().zip(&row?) {
+            collector.insert(value);
+        }
+        row_count += 1;
+    }
+    Ok(TableStatistics {
+        row_count,
+        columns: columns
+            .iter()
+            .cloned()
+            .zip(collectors.into_iter().map(|c| c.finish(row_count)))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::storage::testing::TestStorage;
+
+    #[test]
+    fn test_hyper_log_log() {
+        let mut sketch = HyperLogLog::default();
+        assert_eq!(sketch.estimate(), 0);
+        for i in 0..100_000 {
+            sketch.insert(&Value::I64(i % 20_000));
+            // Equal values of other types are the same value.
+            sketch.insert(&Value::F64((i % 20_000) as f64));
+        }
+        let estimate = sketch.estimate();
+        assert!((19_400..20_600).contains(&estimate), "{estimate}");
+        let mut small = HyperLogLog::default();
+        for i in 0..10 {
+            small.insert(&Value::I32(i));
+        }
+        assert_eq!(small.estimate(), 10);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let (storage, _) = TestStorage::new(&["CREATE TABLE t (a INT32, b VARCHAR(10))"]);
+        for i in 0..10 {
+            let a = if i % 5 == 0 {
+                Value::Null
+            } else {
+                Value::I32(i % 4)
+            };
+            storage
+                .insert("public.t", vec![a, Value::VarChar(format!("b{i}").into())])
+                .unwrap();
+        }
+        let statistics = analyze(
+            &storage,
+            "public.t",
+            &["a".into(), "b".into()],
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert_eq!(statistics.row_count, 10);
+        assert_eq!(
+            statistics.columns["a"],
+            ColumnStatistics {
+                nulls: 2,
+                distinct: 4,
+                min: Some(Value::I32(0)),
+                max: Some(Value::I32(3)),
+            }
+        );
+        assert_eq!(statistics.columns["b"].distinct, 10);
+        assert_eq!(
+            statistics.columns["b"].max,
+            Some(Value::VarChar("b9".into()))
+        );
+    }
+}
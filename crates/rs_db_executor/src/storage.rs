@@ -0,0 +1,1143 @@
+use std::{cmp::Ordering, collections::HashMap, ops::Bound, path::Path};
+
+use rs_db_parser::{
+    ast::{commands::create::SqlType, table::TableName},
+    parse::TableMap,
+    value::Value,
+};
+use rs_db_storage::{
+    engine::{ScanOptions, StorageEngine},
+    error::StorageError,
+};
+
+use crate::{
+    error::ExecError,
+    partition::{partition_key, Partitions},
+    Row,
+};
+
+/// The rows of a table, in the order the storage keeps them.
+pub type RowIter<'s> = Box<dyn Iterator<Item = Result<Row, ExecError>> + 's>;
+
+/// Where the executor reads and writes the rows of tables. A table is named by its
+/// [`table_key`] and its rows hold the values of its columns sorted by name, the order the
+/// scope of a query lists them in.
+///
+/// The methods take `&self` so the operators of a plan can share the storage, an
+/// implementation that writes keeps its state behind a lock or a cell.
+pub trait Storage {
+    /// The rows of `table`.
+    /// # Errors
+    /// Returns an error if the table doesn't exist or can't be read.
+    fn scan(&self, table: &str) -> Result<RowIter<'_>, ExecError>;
+
+    /// Like [`Storage::scan`], reading what `options` asks for: the rows have the values
+    /// of its columns only, and the storage may skip the ones out of its ranges.
+    /// # Errors
+    /// Returns an error if the table doesn't exist or can't be read.
+    fn scan_with(&self, table: &str, options: &ScanOptions) -> Result<RowIter<'_>, ExecError> {
+        let options = options.clone();
+        let rows = self.scan(table)?;
+        Ok(Box::new(rows.map(move |row| Ok(options.project(row?)))))
+    }
+
+    /// Adds a row to `table`.
+    /// # Errors
+    /// Returns an error if the table doesn't exist or can't be written.
+    fn insert(&self, table: &str, row: Row) -> Result<(), ExecError>;
+
+    /// Removes a row equal to `row` from `table`, returns `false` if there is none. A
+    /// [`Session`](crate::session::Session) removes the rows a transaction added to roll
+    /// it back.
+    /// # Errors
+    /// Returns an error if the table doesn't exist or can't be written.
+    fn remove(&self, _table: &str, _row: &[Value]) -> Result<bool, ExecError> {
+        Err(ExecError::Unsupported("Removing a row"))
+    }
+
+    /// Reclaims the space the rows removed from `table` left, for `VACUUM`, and returns the
+    /// bytes reclaimed.
+    /// # Errors
+    /// Returns an error if the table doesn't exist or can't be written.
+    fn vacuum(&self, _table: &str) -> Result<u64, ExecError> {
+        Err(ExecError::Unsupported("VACUUM"))
+    }
+
+    /// Copies `tables` to the directory `dir`, for `BACKUP`, and returns the bytes written
+    /// for each of them, in their order. The copies hold the rows the tables had at one
+    /// moment, see [`StorageEngine::snapshot`].
+    /// # Errors
+    /// Returns an error if a table doesn't exist or can't be copied.
+    fn backup(&self, _tables: &[&str], _dir: &Path) -> Result<Vec<u64>, ExecError> {
+        Err(ExecError::Unsupported("BACKUP"))
+    }
+
+    /// The number of rows of `table`, if the storage knows it without reading them. The
+    /// planner uses it to choose how to run a join.
+    fn row_count(&self, _table: &str) -> Option<u64> {
+        None
+    }
+
+    /// The indexes of `table`, which the planner reads the rows of a filter through instead
+    /// of scanning the table, see [`IndexSelection`](crate::optimizer::IndexSelection).
+    fn indexes(&self, _table: &str) -> Vec<Index> {
+        Vec::new()
+    }
+
+    /// The rows of `table` whose key in its index `index` is in `range`, in the order of
+    /// the keys. With `key_only`, a row only has the values of the key, in its order, so
+    /// the index answers without reading the table.
+    /// # Errors
+    /// Returns an error if the table or the index doesn't exist or can't be read, or if a
+    /// key can't be compared with the bounds of `range`.
+    fn index_scan(
+        &self,
+        _table: &str,
+        _index: &str,
+        _range: &KeyRange,
+        _key_only: bool,
+    ) -> Result<RowIter<'_>, ExecError> {
+        Err(ExecError::Unsupported("An index scan"))
+    }
+
+    /// How the rows of `table` are split in tables of the storage, `None` if it isn't
+    /// partitioned. The planner leaves out of a scan the partitions its filter rules out,
+    /// see [`PartitionPruning`](crate::optimizer::PartitionPruning).
+    fn partitions(&self, _table: &str) -> Option<Partitions> {
+        None
+    }
+}
+
+/// An ordered index of a table, like a B+tree: its entries are sorted by the values of the
+/// columns of its key, so the rows whose key is in a range are found without reading the
+/// others.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Index {
+    pub name: Box<str>,
+    /// The names of the columns of the key, the most significant first.
+    pub columns: Vec<Box<str>>,
+}
+
+/// The keys an index scan reads. A bound is compared with as many values of the start of
+/// a key as it has, a bound of one value is one on the first column of the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyRange {
+    pub lower: Bound<Vec<Value>>,
+    pub upper: Bound<Vec<Value>>,
+}
+
+impl KeyRange {
+    /// Whether `key` is in the range. A key with a `NULL` where it is compared with a bound
+    /// isn't, as no comparison with `NULL` is TRUE.
+    /// # Errors
+    /// Returns an error if a value of the key can't be compared with the one of a bound,
+    /// like a string and an integer.
+    pub fn contains(&self, key: &[Value]) -> Result<bool, ExecError> {
+        let compare = |bound: &[Value]| -> Result<Option<Ordering>, ExecError> {
+            for (value, bound) in key.iter().zip(bound) {
+                match value.compare(bound)? {
+                    Some(Ordering::Equal) => {}
+                    ordering => return Ok(ordering),
+                }
+            }
+            Ok(Some(Ordering::Equal))
+        };
+        let above = match &self.lower {
+            Bound::Included(bound) => compare(bound)?.is_some_and(Ordering::is_ge),
+            Bound::Excluded(bound) => compare(bound)?.is_some_and(Ordering::is_gt),
+            Bound::Unbounded => true,
+        };
+        let below = match &self.upper {
+            Bound::Included(bound) => compare(bound)?.is_some_and(Ordering::is_le),
+            Bound::Excluded(bound) => compare(bound)?.is_some_and(Ordering::is_lt),
+            Bound::Unbounded => true,
+        };
+        Ok(above && below)
+    }
+}
+
+/// The tables of a [`StorageEngine`], like a [`DiskStorage`], whose tables are opened
+/// with the types of their columns sorted by name, see [`DiskStorage::open_table`], a
+/// [`MemoryStorage`], or an engine of another crate. A row is removed by scanning the
+/// table for it. The indexes are the ones the engine keeps, see
+/// [`StorageEngine::add_index`], whose keys out of the range of a scan are left out.
+///
+/// [`DiskStorage`]: rs_db_storage::disk::DiskStorage
+/// [`DiskStorage::open_table`]: rs_db_storage::disk::DiskStorage::open_table
+/// [`MemoryStorage`]: rs_db_storage::memory::MemoryStorage
+impl<E: StorageEngine + ?Sized> Storage for E {
+    fn scan(&self, table: &str) -> Result<RowIter<'_>, ExecError> {
+        let rows = self.table(table)?.scan()?;
+        Ok(Box::new(rows.map(|row| Ok(row?.1))))
+    }
+
+    fn scan_with(&self, table: &str, options: &ScanOptions) -> Result<RowIter<'_>, ExecError> {
+        let rows = self.table(table)?.scan_with(options)?;
+        Ok(Box::new(rows.map(|row| Ok(row?.1))))
+    }
+
+    fn insert(&self, table: &str, row: Row) -> Result<(), ExecError> {
+        self.table(table)?.insert(&row)?;
+        Ok(())
+    }
+
+    fn remove(&self, table: &str, row: &[Value]) -> Result<bool, ExecError> {
+        let table = self.table(table)?;
+        for found in table.scan()? {
+            let (id, found) = found?;
+            if found == row {
+                return Ok(table.delete(id)?);
+            }
+        }
+        Ok(false)
+    }
+
+    fn vacuum(&self, table: &str) -> Result<u64, ExecError> {
+        Ok(self.table(table)?.vacuum()?)
+    }
+
+    /// Writes the snapshots of the tables once they are all taken and the tables unlocked.
+    fn backup(&self, tables: &[&str], dir: &Path) -> Result<Vec<u64>, ExecError> {
+        let mut written = HashMap::new();
+        for snapshot in self.snapshot(tables)? {
+            let bytes = snapshot.write(dir)?;
+            written.insert(Box::<str>::from(snapshot.table()), bytes);
+        }
+        Ok(tables
+            .iter()
+            .map(|table| written.get(*table).copied().unwrap_or_default())
+            .collect())
+    }
+
+    fn row_count(&self, table: &str) -> Option<u64> {
+        self.table(table).ok()?.row_count()
+    }
+
+    fn indexes(&self, table: &str) -> Vec<Index> {
+        let Ok(table) = self.table(table) else {
+            return Vec::new();
+        };
+        table
+            .indexes()
+            .into_iter()
+            .map(|index| Index {
+                name: index.name,
+                columns: index.columns,
+            })
+            .collect()
+    }
+
+    fn index_scan(
+        &self,
+        table: &str,
+        index: &str,
+        range: &KeyRange,
+        key_only: bool,
+    ) -> Result<RowIter<'_>, ExecError> {
+        let table = self.table(table)?;
+        let positions = table
+            .indexes()
+            .into_iter()
+            .find(|found| *found.name == *index)
+            .ok_or_else(|| StorageError::IndexNotFound(index.into()))?
+            .positions;
+        let rows = table.index_scan(
+            index,
+            range.lower.as_ref().map(Vec::as_slice),
+            range.upper.as_ref().map(Vec::as_slice),
+            key_only,
+        )?;
+        let range = range.clone();
+        Ok(Box::new(rows.filter_map(move |row| {
+            let row = match row {
+                Ok((_, row)) => row,
+                Err(error) => return Some(Err(error.into())),
+            };
+            let contains = if key_only {
+                range.contains(&row)
+            } else {
+                range.contains(&positions.iter().map(|i| row[*i].clone()).collect::<Row>())
+            };
+            match contains {
+                Ok(true) => Some(Ok(row)),
+                Ok(false) => None,
+                Err(error) => Some(Err(error)),
+            }
+        })))
+    }
+}
+
+/// Tables kept in different storages: the ones [routed](RoutedStorage::route) to a storage
+/// are kept in it, like the tables created `WITH (engine = 'memory')` in a
+/// [`MemoryStorage`], the others in the default storage, like a [`DiskStorage`]. A
+/// statement reads and writes tables of both.
+///
+/// [`DiskStorage`]: rs_db_storage::disk::DiskStorage
+/// [`MemoryStorage`]: rs_db_storage::memory::MemoryStorage
+pub struct RoutedStorage<'s> {
+    default: &'s dyn Storage,
+    routes: HashMap<Box<str>, &'s dyn Storage>,
+}
+
+impl<'s> RoutedStorage<'s> {
+    /// Keeps every table in `default`.
+    #[must_use]
+    pub fn new(default: &'s dyn Storage) -> Self {
+        Self {
+            default,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Keeps `table`, named by its [`table_key`], in `storage`.
+    pub fn route(&mut self, table: impl Into<Box<str>>, storage: &'s dyn Storage) {
+        self.routes.insert(table.into(), storage);
+    }
+
+    /// Keeps `table` in the default storage again.
+    pub fn unroute(&mut self, table: &str) {
+        self.routes.remove(table);
+    }
+
+    fn storage(&self, table: &str) -> &'s dyn Storage {
+        self.routes.get(table).copied().unwrap_or(self.default)
+    }
+}
+
+impl Storage for RoutedStorage<'_> {
+    fn scan(&self, table: &str) -> Result<RowIter<'_>, ExecError> {
+        self.storage(table).scan(table)
+    }
+
+    fn scan_with(&self, table: &str, options: &ScanOptions) -> Result<RowIter<'_>, ExecError> {
+        self.storage(table).scan_with(table, options)
+    }
+
+    fn insert(&self, table: &str, row: Row) -> Result<(), ExecError> {
+        self.storage(table).insert(table, row)
+    }
+
+    fn remove(&self, table: &str, row: &[Value]) -> Result<bool, ExecError> {
+        self.storage(table).remove(table, row)
+    }
+
+    fn vacuum(&self, table: &str) -> Result<u64, ExecError> {
+        self.storage(table).vacuum(table)
+    }
+
+    /// The tables of each storage are copied together, at one moment of their storage.
+    fn backup(&self, tables: &[&str], dir: &Path) -> Result<Vec<u64>, ExecError> {
+        let mut groups: Vec<(&dyn Storage, Vec<usize>)> = Vec::new();
+        for (i, table) in tables.iter().enumerate() {
+            let storage = self.storage(table);
+            match groups
+                .iter_mut()
+                .find(|(found, _)| std::ptr::addr_eq(*found, storage))
+            {
+                Some((_, group)) => group.push(i),
+                None => groups.push((storage, vec![i])),
+            }
+        }
+        let mut written = vec![0; tables.len()];
+        for (storage, group) in groups {
+            let names = group.iter().map(|i| tables[*i]).collect::<Vec<_>>();
+            for (i, bytes) in group.into_iter().zip(storage.backup(&names, dir)?) {
+                written[i] = bytes;
+            }
+        }
+        Ok(written)
+    }
+
+    fn row_count(&self, table: &str) -> Option<u64> {
+        self.storage(table).row_count(table)
+    }
+
+    fn indexes(&self, table: &str) -> Vec<Index> {
+        self.storage(table).indexes(table)
+    }
+
+    fn index_scan(
+        &self,
+        table: &str,
+        index: &str,
+        range: &KeyRange,
+        key_only: bool,
+    ) -> Result<RowIter<'_>, ExecError> {
+        self.storage(table)
+            .index_scan(table, index, range, key_only)
+    }
+
+    fn partitions(&self, table: &str) -> Option<Partitions> {
+        self.storage(table).partitions(table)
+    }
+}
+
+/// The name of a table in the storage, `schema.table`, an unqualified name is in the
+/// default schema of `table_map`.
+#[must_use]
+pub fn table_key(name: &TableName<'_>, table_map: &TableMap) -> Box<str> {
+    let schema = name
+        .schema_name()
+        .unwrap_or_else(|| table_map.default_schema());
+    format!("{schema}.{}", name.name.fragment()).into()
+}
+
+/// The file of a backup directory the catalog is written to, see [`read_catalog`].
+pub const CATALOG_FILE: &str = "catalog.json";
+
+/// The catalog written to the backup directory `dir` by `BACKUP`.
+/// # Errors
+/// Returns an error if the file can't be read or isn't a catalog.
+pub fn read_catalog(dir: &Path) -> Result<TableMap, ExecError> {
+    let json = std::fs::read_to_string(dir.join(CATALOG_FILE))?;
+    TableMap::from_json(&json).map_err(|error| ExecError::Io(error.to_string().into()))
+}
+
+/// The tables of the storage for `table_map`, each partition of a partitioned table on its
+/// own, with the types of their columns sorted by name, to reopen them after a restore.
+#[must_use]
+pub fn table_types(table_map: &TableMap) -> Vec<(Box<str>, Vec<SqlType>)> {
+    let mut tables = Vec::new();
+    for (schema, schema_map) in table_map.schemas() {
+        for (table, columns) in schema_map {
+            let mut names = columns.keys().collect::<Vec<_>>();
+            names.sort_unstable();
+            let types = names
+                .into_iter()
+                .map(|name| columns[name].tp.clone())
+                .collect::<Vec<_>>();
+            let key = format!("{schema}.{table}");
+            match table_map.partitioning(Some(schema), table) {
+                Some(partitioning) => tables.extend(
+                    partitioning
+                        .names()
+                        .iter()
+                        .map(|partition| (partition_key(&key, partition), types.clone())),
+                ),
+                None => tables.push((key.into(), types)),
+            }
+        }
+    }
+    tables.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    tables
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use rs_db_parser::{
+        ast::statement::Statement,
+        parse::{Parse, RawSpan, TableMap},
+    };
+
+    use super::*;
+    use crate::partition::partition_key;
+
+    /// Tables kept in vectors, for the tests of the executor.
+    #[derive(Debug, Default)]
+    pub(crate) struct TestStorage {
+        tables: RefCell<HashMap<Box<str>, Vec<Row>>>,
+        /// The names of the columns of each table, in the order of its rows.
+        columns: HashMap<Box<str>, Vec<Box<str>>>,
+        /// The indexes of each table, with the positions of the columns of their key.
+        indexes: HashMap<Box<str>, Vec<(Index, Vec<usize>)>>,
+    }
+
+    impl TestStorage {
+        /// A storage with an empty table for each `CREATE TABLE` of `ddl`, or for each of its
+        /// partitions, and its catalog.
+        pub(crate) fn new(ddl: &[&str]) -> (Self, TableMap) {
+            let mut table_map = TableMap::new();
+            let mut storage = Self::default();
+            for input in ddl {
+                let Ok((_, Statement::Create(create))) = Statement::parse(RawSpan::new(input))
+                else {
+                    panic!("not a CREATE TABLE: {input}");
+                };
+                let key = table_key(&create.table_name, &table_map);
+                let columns = create.column_map();
+                let mut names = columns.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                table_map.insert_qualified(
+                    create.table_name.schema_name(),
+                    *create.table_name.name.fragment(),
+                    columns,
+                );
+                table_map.set_constraints(
+                    create.table_name.schema_name(),
+                    create.table_name.name.fragment(),
+                    create.table_constraints(),
+                );
+                let tables = match &create.partitioning {
+                    Some(partitioning) => {
+                        table_map.set_partitioning(
+                            create.table_name.schema_name(),
+                            create.table_name.name.fragment(),
+                            partitioning.clone(),
+                        );
+                        partitioning
+                            .names()
+                            .iter()
+                            .map(|partition| partition_key(&key, partition))
+                            .collect()
+                    }
+                    None => vec![key.clone()],
+                };
+                storage
+                    .tables
+                    .borrow_mut()
+                    .extend(tables.into_iter().map(|table| (table, Vec::new())));
+                storage.columns.insert(key, names);
+            }
+            (storage, table_map)
+        }
+
+        /// Adds an index on `columns` to `table`, a [`table_key`].
+        pub(crate) fn create_index(&mut self, table: &str, name: &str, columns: &[&str]) {
+            let names = &self.columns[table];
+            let positions = columns
+                .iter()
+                .map(|column| names.iter().position(|name| **name == **column).unwrap())
+                .collect();
+            let index = Index {
+                name: name.into(),
+                columns: columns.iter().map(|column| (*column).into()).collect(),
+            };
+            self.indexes
+                .entry(table.into())
+                .or_default()
+                .push((index, positions));
+        }
+    }
+
+    impl Storage for TestStorage {
+        fn scan(&self, table: &str) -> Result<RowIter<'_>, ExecError> {
+            let rows = self
+                .tables
+                .borrow()
+                .get(table)
+                .ok_or_else(|| ExecError::TableNotFound(table.into()))?
+                .clone();
+            Ok(Box::new(rows.into_iter().map(Ok)))
+        }
+
+        fn insert(&self, table: &str, row: Row) -> Result<(), ExecError> {
+            self.tables
+                .borrow_mut()
+                .get_mut(table)
+                .ok_or_else(|| ExecError::TableNotFound(table.into()))?
+                .push(row);
+            Ok(())
+        }
+
+        fn remove(&self, table: &str, row: &[Value]) -> Result<bool, ExecError> {
+            let mut tables = self.tables.borrow_mut();
+            let rows = tables
+                .get_mut(table)
+                .ok_or_else(|| ExecError::TableNotFound(table.into()))?;
+            let Some(position) = rows.iter().position(|found| found == row) else {
+                return Ok(false);
+            };
+            rows.remove(position);
+            Ok(true)
+        }
+
+        fn row_count(&self, table: &str) -> Option<u64> {
+            Some(self.tables.borrow().get(table)?.len() as u64)
+        }
+
+        fn indexes(&self, table: &str) -> Vec<Index> {
+            self.indexes
+                .get(table)
+                .map(|indexes| indexes.iter().map(|(index, _)| index.clone()).collect())
+                .unwrap_or_default()
+        }
+
+        /// Sorts the keys of the rows on each scan, the tests only need the order.
+        fn index_scan(
+            &self,
+            table: &str,
+            index: &str,
+            range: &KeyRange,
+            key_only: bool,
+        ) -> Result<RowIter<'_>, ExecError> {
+            let (_, positions) = self
+                .indexes
+                .get(table)
+                .and_then(|indexes| indexes.iter().find(|(i, _)| *i.name == *index))
+                .ok_or_else(|| ExecError::TableNotFound(format!("{table}.{index}").into()))?;
+            let tables = self.tables.borrow();
+            let mut entries = Vec::new();
+            for row in tables
+                .get(table)
+                .ok_or_else(|| ExecError::TableNotFound(table.into()))?
+            {
+                let key = positions.iter().map(|i| row[*i].clone()).collect::<Row>();
+                if range.contains(&key)? {
+                    entries.push((key, row.clone()));
+                }
+            }
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Ok(Box::new(entries.into_iter().map(move |(key, row)| {
+                Ok(if key_only { key } else { row })
+            })))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::sync::{Arc, Mutex};
+
+    use rs_db_parser::{
+        ast::{
+            commands::create::{LengthUnit, SqlType},
+            statement::Statement,
+        },
+        parse::RawSpan,
+        value::Value,
+    };
+    use rs_db_storage::{
+        buffer::BufferStats,
+        columnar::ColumnarStorage,
+        disk::DiskStorage,
+        engine::{Cursor, IndexDefinition, RowId, Table},
+        error::StorageError,
+        memory::MemoryStorage,
+    };
+
+    use super::*;
+    use crate::{
+        executor::{execute, Config, Output},
+        partition::PartitionedStorage,
+    };
+
+    #[test]
+    fn test_disk_storage() {
+        let (_, table_map) =
+            testing::TestStorage::new(&["CREATE TABLE t (id INT32, name VARCHAR(20))"]);
+        let mut names = table_map.get("t").unwrap().keys().collect::<Vec<_>>();
+        names.sort();
+        let types = names
+            .iter()
+            .map(|name| table_map.get("t").unwrap()[*name].tp.clone())
+            .collect::<Vec<SqlType>>();
+        let dir = std::env::temp_dir().join(format!("rs_db-disk-{}", std::process::id()));
+        let run = |storage: &DiskStorage, input: &str| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            execute(&statement, &table_map, storage, &Config::default()).unwrap()
+        };
+
+        let storage = DiskStorage::open(&dir).unwrap();
+        storage.create_table("public.t", types.clone()).unwrap();
+        assert_eq!(
+            run(
+                &storage,
+                "INSERT INTO t (id, name) VALUES (1, 'one'), (2, NULL), (3, 'three')"
+            ),
+            Output::Inserted(3)
+        );
+        // The space of a removed row is reclaimed.
+        assert!(Storage::remove(&storage, "public.t", &[Value::I32(2), Value::Null]).unwrap());
+        let Output::Vacuumed(vacuumed) = run(&storage, "VACUUM t") else {
+            panic!("VACUUM t");
+        };
+        assert_eq!(vacuumed.len(), 1);
+        assert_eq!((&*vacuumed[0].0, &*vacuumed[0].1), ("public", "t"));
+        assert!(vacuumed[0].2 > 0);
+        storage.sync().unwrap();
+        drop(storage);
+
+        let storage = DiskStorage::open(&dir).unwrap();
+        let select = "SELECT name FROM t WHERE id > 1";
+        let (_, statement) =
+            Statement::parse_with_table_map(&table_map, RawSpan::new(select)).unwrap();
+        assert_eq!(
+            execute(&statement, &table_map, &storage, &Config::default()),
+            Err(ExecError::TableNotFound("public.t".into()))
+        );
+        storage.open_table("public.t", types.clone()).unwrap();
+        assert_eq!(
+            run(&storage, select),
+            Output::Rows {
+                columns: vec!["name".into()],
+                rows: vec![vec![Value::VarChar("three".into())]],
+            }
+        );
+
+        // A backup is restored in another directory.
+        let backup = dir.join("backup");
+        let Output::BackedUp(backed_up) =
+            run(&storage, &format!("BACKUP TO '{}'", backup.display()))
+        else {
+            panic!("BACKUP");
+        };
+        assert_eq!((&*backed_up[0].0, &*backed_up[0].1), ("public", "t"));
+        assert!(backed_up[0].2 > 0);
+        // The tables are reopened from the catalog of the backup.
+        let restored = DiskStorage::restore(&backup, dir.join("restored")).unwrap();
+        let catalog = read_catalog(&backup).unwrap();
+        assert_eq!(catalog.get("t"), table_map.get("t"));
+        assert_eq!(
+            table_types(&catalog),
+            [(Box::<str>::from("public.t"), types.clone())]
+        );
+        for (table, types) in table_types(&catalog) {
+            restored.open_table(&table, types).unwrap();
+        }
+        assert_eq!(run(&restored, select), run(&storage, select));
+        // A backup doesn't overwrite another one.
+        assert!(matches!(
+            execute(
+                &Statement::parse_with_table_map(
+                    &table_map,
+                    RawSpan::new(&format!("BACKUP TO '{}'", backup.display()))
+                )
+                .unwrap()
+                .1,
+                &table_map,
+                &storage,
+                &Config::default()
+            ),
+            Err(ExecError::Io(_))
+        ));
+        storage.drop_table("public.t").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        let (_, table_map) = testing::TestStorage::new(&[
+            "CREATE TABLE users (id INT32, name VARCHAR(20))",
+            "CREATE TABLE sessions (user_id INT32, token VARCHAR(20)) WITH (engine = 'memory')",
+        ]);
+        let run = |storage: &dyn Storage, input: &str| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            execute(&statement, &table_map, storage, &Config::default()).unwrap()
+        };
+
+        // The whole database in memory.
+        let memory = MemoryStorage::new();
+        memory.create_table("public.users").unwrap();
+        assert_eq!(
+            run(
+                &memory,
+                "INSERT INTO users (id, name) VALUES (1, 'ana'), (2, 'bo')"
+            ),
+            Output::Inserted(2)
+        );
+        assert!(Storage::remove(
+            &memory,
+            "public.users",
+            &[Value::I32(1), Value::VarChar("ana".into())]
+        )
+        .unwrap());
+        assert_eq!(
+            run(&memory, "SELECT name FROM users"),
+            Output::Rows {
+                columns: vec!["name".into()],
+                rows: vec![vec![Value::VarChar("bo".into())]],
+            }
+        );
+
+        // A table in memory next to the tables on the disk.
+        let dir = std::env::temp_dir().join(format!("rs_db-routed-{}", std::process::id()));
+        let disk = DiskStorage::open(&dir).unwrap();
+        disk.create_table(
+            "public.users",
+            vec![SqlType::I32, SqlType::VarChar(20, LengthUnit::Chars)],
+        )
+        .unwrap();
+        let sessions = MemoryStorage::new();
+        sessions.create_table("public.sessions").unwrap();
+        let mut storage = RoutedStorage::new(&disk);
+        storage.route("public.sessions", &sessions);
+        run(
+            &storage,
+            "INSERT INTO users (id, name) VALUES (1, 'ana'), (2, 'bo')",
+        );
+        run(
+            &storage,
+            "INSERT INTO sessions (user_id, token) VALUES (2, 'x')",
+        );
+        assert_eq!(disk.row_count("public.users"), Some(2));
+        assert_eq!(sessions.row_count("public.sessions"), Some(1));
+        assert_eq!(
+            run(
+                &storage,
+                "SELECT name, token FROM users JOIN sessions ON users.id = sessions.user_id"
+            ),
+            Output::Rows {
+                columns: vec!["name".into(), "token".into()],
+                rows: vec![vec![
+                    Value::VarChar("bo".into()),
+                    Value::VarChar("x".into())
+                ]],
+            }
+        );
+        assert_eq!(
+            run(&storage, "VACUUM sessions"),
+            Output::Vacuumed(vec![("public".into(), "sessions".into(), 0)])
+        );
+        storage.unroute("public.sessions");
+        let (_, statement) =
+            Statement::parse_with_table_map(&table_map, RawSpan::new("SELECT token FROM sessions"))
+                .unwrap();
+        assert!(execute(&statement, &table_map, &storage, &Config::default()).is_err());
+        drop(storage);
+        disk.drop_table("public.users").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_columnar_storage() {
+        let (_, table_map) = testing::TestStorage::new(&[
+            "CREATE TABLE events (id INT64, kind VARCHAR(10), amount INT32) \
+             WITH (engine = 'columnar')",
+        ]);
+        let run = |storage: &dyn Storage, input: &str| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            execute(&statement, &table_map, storage, &Config::default()).unwrap()
+        };
+        let dir = std::env::temp_dir().join(format!("rs_db-columnar-{}", std::process::id()));
+        let columnar = ColumnarStorage::open(&dir).unwrap();
+        // The columns sorted by name: amount, id, kind.
+        columnar
+            .create_table(
+                "public.events",
+                vec![
+                    SqlType::I32,
+                    SqlType::I64,
+                    SqlType::VarChar(10, LengthUnit::Chars),
+                ],
+            )
+            .unwrap();
+        let memory = MemoryStorage::new();
+        let mut storage = RoutedStorage::new(&memory);
+        storage.route("public.events", &columnar);
+        for i in 0..3000 {
+            let kind = if i % 2 == 0 { "click" } else { "view" };
+            Storage::insert(
+                &storage,
+                "public.events",
+                vec![
+                    Value::I32(i % 7),
+                    Value::I64(i.into()),
+                    Value::VarChar(kind.into()),
+                ],
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            run(
+                &storage,
+                "SELECT count(*) FROM events WHERE id BETWEEN 1000 AND 1099 AND kind = 'view'"
+            ),
+            Output::Rows {
+                columns: vec!["count(*)".into()],
+                rows: vec![vec![Value::I64(50)]],
+            }
+        );
+        assert_eq!(
+            run(&storage, "SELECT amount, kind FROM events WHERE id = 2500"),
+            Output::Rows {
+                columns: vec!["amount".into(), "kind".into()],
+                rows: vec![vec![Value::I32(1), Value::VarChar("click".into())]],
+            }
+        );
+        drop(storage);
+        columnar.drop_table("public.events").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bloom_filters() {
+        let (_, table_map) =
+            testing::TestStorage::new(&["CREATE TABLE orders (id INT32, customer VARCHAR(10)) \
+             PARTITION BY HASH (id) PARTITIONS 4 WITH (bloom_filter = 'customer')"]);
+        let dir = std::env::temp_dir().join(format!("rs_db-bloom-{}", std::process::id()));
+        let disk = DiskStorage::open(&dir).unwrap();
+        let storage = PartitionedStorage::new(&disk, &table_map);
+        let partitions = storage.partitions("public.orders").unwrap();
+        for table in &partitions.tables {
+            // The columns sorted by name: customer, id.
+            disk.create_table(
+                table,
+                vec![SqlType::VarChar(10, LengthUnit::Chars), SqlType::I32],
+            )
+            .unwrap();
+            disk.add_bloom_filter(table, 0).unwrap();
+        }
+        for id in 0..400 {
+            Storage::insert(
+                &storage,
+                "public.orders",
+                vec![
+                    Value::VarChar(format!("c{}", id % 50).into()),
+                    Value::I32(id),
+                ],
+            )
+            .unwrap();
+        }
+        let run = |input: &str| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            execute(&statement, &table_map, &storage, &Config::default()).unwrap()
+        };
+        assert_eq!(
+            run("SELECT count(*) FROM orders WHERE customer = 'c7'"),
+            Output::Rows {
+                columns: vec!["count(*)".into()],
+                rows: vec![vec![Value::I64(8)]],
+            }
+        );
+        // No partition holds the customer, none is read.
+        let pins = |stats: BufferStats| stats.hits + stats.misses;
+        let before = pins(disk.buffer_pool().stats());
+        assert_eq!(
+            run("SELECT id FROM orders WHERE customer = 'nobody'"),
+            Output::Rows {
+                columns: vec!["id".into()],
+                rows: Vec::new(),
+            }
+        );
+        assert_eq!(pins(disk.buffer_pool().stats()), before);
+        for table in &partitions.tables {
+            disk.drop_table(table).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Indexes the rows of `engine` and reads them through the index, see
+    /// [`test_engine_indexes`].
+    fn check_indexes<E: StorageEngine>(engine: &E) {
+        let (_, table_map) =
+            testing::TestStorage::new(&["CREATE TABLE orders (id INT32, customer VARCHAR(10))"]);
+        // The columns sorted by name: customer, id.
+        let types = vec![SqlType::VarChar(10, LengthUnit::Chars), SqlType::I32];
+        engine.create_table("public.orders", types).unwrap();
+        for id in (0..200).rev() {
+            let row = vec![Value::VarChar(format!("c{id}").into()), Value::I32(id)];
+            Storage::insert(engine, "public.orders", row).unwrap();
+        }
+        // The rows added before the index are in it, like the ones added after.
+        let index = IndexDefinition {
+            name: "orders_id".into(),
+            columns: vec!["id".into()],
+            positions: vec![1],
+        };
+        engine.add_index("public.orders", index.clone()).unwrap();
+        assert_eq!(
+            engine.add_index("public.orders", index),
+            Err(StorageError::IndexExists("orders_id".into()))
+        );
+        let row = vec![Value::VarChar("late".into()), Value::I32(1000)];
+        Storage::insert(engine, "public.orders", row).unwrap();
+        assert_eq!(
+            engine.indexes("public.orders"),
+            [Index {
+                name: "orders_id".into(),
+                columns: vec!["id".into()],
+            }]
+        );
+
+        let run = |input: &str| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            match execute(&statement, &table_map, engine, &Config::default()).unwrap() {
+                Output::Rows { rows, .. } => rows,
+                output => panic!("{output:?}"),
+            }
+        };
+        let explain = run("EXPLAIN SELECT customer FROM orders WHERE id = 42");
+        assert!(
+            explain.contains(&vec![Value::VarChar("    Index: orders_id".into())]),
+            "{explain:?}"
+        );
+        assert_eq!(
+            run("SELECT customer FROM orders WHERE id = 42"),
+            [[Value::VarChar("c42".into())]]
+        );
+        assert_eq!(
+            run("SELECT customer FROM orders WHERE id = 1000"),
+            [[Value::VarChar("late".into())]]
+        );
+        // The rows come in the order of the keys, not the one they were added in.
+        assert_eq!(
+            run("SELECT id FROM orders WHERE id >= 10 AND id < 13"),
+            [10, 11, 12].map(|id| [Value::I32(id)])
+        );
+
+        // A row removed is gone from the index.
+        assert!(Storage::remove(
+            engine,
+            "public.orders",
+            &[Value::VarChar("c11".into()), Value::I32(11)]
+        )
+        .unwrap());
+        assert_eq!(
+            run("SELECT id FROM orders WHERE id BETWEEN 10 AND 12"),
+            [10, 12].map(|id| [Value::I32(id)])
+        );
+        let range = KeyRange {
+            lower: Bound::Excluded(vec![Value::I64(197)]),
+            upper: Bound::Unbounded,
+        };
+        let keys = engine
+            .index_scan("public.orders", "orders_id", &range, true)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(keys, [198, 199, 1000].map(|id| vec![Value::I32(id)]));
+        assert!(engine
+            .index_scan("public.orders", "orders_name", &range, false)
+            .is_err());
+        engine.drop_table("public.orders").unwrap();
+    }
+
+    #[test]
+    fn test_engine_indexes() {
+        check_indexes(&MemoryStorage::new());
+        let dir = std::env::temp_dir().join(format!("rs_db-index-{}", std::process::id()));
+        check_indexes(&DiskStorage::open(&dir).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// The rows of a [`VecEngine`] table, `None` once deleted, a row's id is its index.
+    type VecRows = Arc<Mutex<Vec<Option<Vec<Value>>>>>;
+
+    /// An engine of another crate, keeping its tables in vectors.
+    #[derive(Default)]
+    struct VecEngine {
+        tables: Mutex<HashMap<Box<str>, VecRows>>,
+    }
+
+    struct VecTable {
+        name: Box<str>,
+        rows: VecRows,
+    }
+
+    impl Table for VecTable {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn insert(&self, row: &[Value]) -> Result<RowId, StorageError> {
+            let mut rows = self.rows.lock().unwrap();
+            rows.push(Some(row.to_vec()));
+            Ok(RowId(rows.len() as u64 - 1))
+        }
+
+        fn get(&self, RowId(id): RowId) -> Result<Option<Vec<Value>>, StorageError> {
+            let rows = self.rows.lock().unwrap();
+            Ok(rows.get(id as usize).cloned().flatten())
+        }
+
+        fn delete(&self, RowId(id): RowId) -> Result<bool, StorageError> {
+            let mut rows = self.rows.lock().unwrap();
+            Ok(rows.get_mut(id as usize).and_then(Option::take).is_some())
+        }
+
+        fn scan(&self) -> Result<Box<dyn Cursor>, StorageError> {
+            let rows = self.rows.lock().unwrap().clone();
+            Ok(Box::new(rows.into_iter().enumerate().filter_map(
+                |(id, row)| Some(Ok((RowId(id as u64), row?))),
+            )))
+        }
+    }
+
+    impl StorageEngine for VecEngine {
+        fn create_table(&self, name: &str, _types: Vec<SqlType>) -> Result<(), StorageError> {
+            self.tables
+                .lock()
+                .unwrap()
+                .insert(name.into(), VecRows::default());
+            Ok(())
+        }
+
+        fn drop_table(&self, name: &str) -> Result<(), StorageError> {
+            self.tables
+                .lock()
+                .unwrap()
+                .remove(name)
+                .map(drop)
+                .ok_or_else(|| StorageError::TableNotFound(name.into()))
+        }
+
+        fn table(&self, name: &str) -> Result<Arc<dyn Table>, StorageError> {
+            let rows = self.tables.lock().unwrap().get(name).cloned();
+            let rows = rows.ok_or_else(|| StorageError::TableNotFound(name.into()))?;
+            Ok(Arc::new(VecTable {
+                name: name.into(),
+                rows,
+            }))
+        }
+
+        fn tables(&self) -> Vec<Box<str>> {
+            let mut tables = self
+                .tables
+                .lock()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            tables.sort();
+            tables
+        }
+    }
+
+    #[test]
+    fn test_storage_engine() {
+        let (_, table_map) =
+            testing::TestStorage::new(&["CREATE TABLE t (id INT32, name VARCHAR(20))"]);
+        let engine = VecEngine::default();
+        let storage: &dyn StorageEngine = &engine;
+        let run = |input: &str| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            execute(&statement, &table_map, &engine, &Config::default())
+        };
+
+        assert_eq!(
+            run("SELECT name FROM t"),
+            Err(ExecError::TableNotFound("public.t".into()))
+        );
+        storage.create_table("public.t", Vec::new()).unwrap();
+        assert_eq!(
+            run("INSERT INTO t (id, name) VALUES (1, 'one'), (2, 'two'), (3, NULL)"),
+            Ok(Output::Inserted(3))
+        );
+        assert!(Storage::remove(
+            storage,
+            "public.t",
+            &[Value::I32(2), Value::VarChar("two".into())]
+        )
+        .unwrap());
+        assert_eq!(
+            run("SELECT name FROM t WHERE id < 3"),
+            Ok(Output::Rows {
+                columns: vec!["name".into()],
+                rows: vec![vec![Value::VarChar("one".into())]],
+            })
+        );
+        // The engine doesn't count its rows, nor has anything to vacuum.
+        assert_eq!(Storage::row_count(storage, "public.t"), None);
+        assert_eq!(
+            run("VACUUM t"),
+            Ok(Output::Vacuumed(vec![("public".into(), "t".into(), 0)]))
+        );
+        assert_eq!(storage.tables(), ["public.t".into()]);
+        let table = storage.table("public.t").unwrap();
+        assert_eq!(table.name(), "public.t");
+        assert_eq!(table.get(RowId(1)).unwrap(), None);
+        assert_eq!(
+            table.get(RowId(2)).unwrap(),
+            Some(vec![Value::I32(3), Value::Null])
+        );
+    }
+}
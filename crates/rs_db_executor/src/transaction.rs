@@ -0,0 +1,264 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use crate::{cancel::CancellationToken, error::ExecError};
+
+/// The number of a transaction, given by [`TransactionManager::begin`].
+pub type TransactionId = u64;
+
+/// How often a transaction waiting for a lock checks its cancellation token.
+const WAIT_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What a transaction may do with a resource it locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LockMode {
+    /// Read it. The transactions that read a resource share its lock.
+    Shared,
+    /// Read and write it. No other transaction holds a lock on the resource.
+    Exclusive,
+}
+
+impl LockMode {
+    const fn conflicts(self, other: Self) -> bool {
+        matches!(self, Self::Exclusive) || matches!(other, Self::Exclusive)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: TransactionId,
+    /// The transactions holding a lock on each resource, with its mode.
+    locks: HashMap<Box<str>, HashMap<TransactionId, LockMode>>,
+    /// The waits-for graph: the transactions each waiting transaction waits for.
+    waits_for: HashMap<TransactionId, HashSet<TransactionId>>,
+}
+
+impl State {
+    /// Whether `from` waits for `to`, directly or through other transactions.
+    fn waits_for(&self, from: TransactionId, to: TransactionId) -> bool {
+        let mut stack = vec![from];
+        let mut seen = HashSet::new();
+        while let Some(txn) = stack.pop() {
+            if !seen.insert(txn) {
+                continue;
+            }
+            for next in self.waits_for.get(&txn).into_iter().flatten() {
+                if *next == to {
+                    return true;
+                }
+                stack.push(*next);
+            }
+        }
+        false
+    }
+}
+
+/// The locks of the transactions on named resources, the tables, for strict two-phase
+/// locking: a transaction locks what it reads or writes before it does, and keeps its
+/// locks until it ends, so the transactions are serializable. A transaction that runs at
+/// [`READ COMMITTED`](rs_db_parser::ast::commands::transaction::IsolationLevel) releases
+/// its shared locks after each statement instead.
+///
+/// A transaction that asks for a lock another one holds in a conflicting mode waits for it
+/// to be released. The waits are kept in a waits-for graph: a wait that would close a cycle
+/// in it is a deadlock, the transaction asking fails with [`ExecError::Deadlock`] instead
+/// of waiting.
+#[derive(Debug, Default)]
+pub struct TransactionManager {
+    state: Mutex<State>,
+    released: Condvar,
+}
+
+impl TransactionManager {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Starts a transaction, which holds no lock.
+    pub fn begin(&self) -> TransactionId {
+        let mut state = self.state();
+        state.next_id += 1;
+        state.next_id
+    }
+
+    /// Locks `resource` in `mode` for `txn`, waiting for the transactions holding it in a
+    /// conflicting mode to release it. A shared lock of `txn` is upgraded to an exclusive
+    /// one, a lock already held in `mode` or a stronger one is kept.
+    /// # Errors
+    /// Returns [`ExecError::Deadlock`] if waiting would close a cycle of transactions
+    /// waiting for each other, or [`ExecError::QueryCancelled`] if `cancellation` is
+    /// cancelled while waiting. The lock isn't taken then.
+    pub fn lock(
+        &self,
+        txn: TransactionId,
+        resource: &str,
+        mode: LockMode,
+        cancellation: &CancellationToken,
+    ) -> Result<(), ExecError> {
+        let mut state = self.state();
+        loop {
+            let holders = state.locks.entry(resource.into()).or_default();
+            let blockers = holders
+                .iter()
+                .filter(|(holder, held)| **holder != txn && held.conflicts(mode))
+                .map(|(holder, _)| *holder)
+                .collect::<HashSet<_>>();
+            if blockers.is_empty() {
+                let held = holders.entry(txn).or_insert(mode);
+                *held = (*held).max(mode);
+                state.waits_for.remove(&txn);
+                return Ok(());
+            }
+            if blockers
+                .iter()
+                .any(|blocker| state.waits_for(*blocker, txn))
+            {
+                state.waits_for.remove(&txn);
+                return Err(ExecError::Deadlock);
+            }
+            state.waits_for.insert(txn, blockers);
+            if let Err(err) = cancellation.check() {
+                state.waits_for.remove(&txn);
+                return Err(err);
+            }
+            state = self
+                .released
+                .wait_timeout(state, WAIT_CHECK_INTERVAL)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Releases the shared locks of `txn`, it keeps its exclusive ones.
+    pub fn release_shared(&self, txn: TransactionId) {
+        self.release(txn, |mode| mode == LockMode::Shared);
+    }
+
+    /// Releases the locks of `txn`, when it ends.
+    pub fn release_all(&self, txn: TransactionId) {
+        self.release(txn, |_| true);
+    }
+
+    fn release(&self, txn: TransactionId, filter: impl Fn(LockMode) -> bool) {
+        let mut state = self.state();
+        state.locks.retain(|_, holders| {
+            if holders.get(&txn).is_some_and(|mode| filter(*mode)) {
+                holders.remove(&txn);
+            }
+            !holders.is_empty()
+        });
+        drop(state);
+        self.released.notify_all();
+    }
+
+    /// The resources `txn` holds a lock on, with its mode, sorted by resource.
+    #[must_use]
+    pub fn locks(&self, txn: TransactionId) -> Vec<(Box<str>, LockMode)> {
+        let mut locks = self
+            .state()
+            .locks
+            .iter()
+            .filter_map(|(resource, holders)| Some((resource.clone(), *holders.get(&txn)?)))
+            .collect::<Vec<_>>();
+        locks.sort();
+        locks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_locks() {
+        let manager = TransactionManager::new();
+        let token = CancellationToken::new();
+        let (a, b) = (manager.begin(), manager.begin());
+        manager.lock(a, "t", LockMode::Shared, &token).unwrap();
+        manager.lock(b, "t", LockMode::Shared, &token).unwrap();
+        manager.lock(a, "u", LockMode::Exclusive, &token).unwrap();
+        manager.lock(a, "u", LockMode::Shared, &token).unwrap();
+        assert_eq!(
+            manager.locks(a),
+            [
+                ("t".into(), LockMode::Shared),
+                ("u".into(), LockMode::Exclusive)
+            ]
+        );
+
+        // `a` can't upgrade its lock while `b` shares it.
+        let timeout = token.with_timeout(Duration::from_millis(30));
+        assert_eq!(
+            manager.lock(a, "t", LockMode::Exclusive, &timeout),
+            Err(ExecError::QueryCancelled)
+        );
+        assert_eq!(
+            manager.lock(b, "u", LockMode::Shared, &timeout),
+            Err(ExecError::QueryCancelled)
+        );
+        manager.release_shared(a);
+        assert_eq!(manager.locks(a), [("u".into(), LockMode::Exclusive)]);
+
+        // A waiting transaction gets the lock once it is released.
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                sender.send(()).unwrap();
+                manager.lock(b, "u", LockMode::Exclusive, &token)
+            });
+            receiver.recv().unwrap();
+            thread::sleep(Duration::from_millis(20));
+            assert!(!waiter.is_finished());
+            manager.release_all(a);
+            waiter.join().unwrap().unwrap();
+        });
+        assert_eq!(
+            manager.locks(b),
+            [
+                ("t".into(), LockMode::Shared),
+                ("u".into(), LockMode::Exclusive)
+            ]
+        );
+        assert!(manager.locks(a).is_empty());
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let manager = TransactionManager::new();
+        let token = CancellationToken::new();
+        let (a, b) = (manager.begin(), manager.begin());
+        manager.lock(a, "t", LockMode::Exclusive, &token).unwrap();
+        manager.lock(b, "u", LockMode::Exclusive, &token).unwrap();
+        // Each waits for the lock of the other, the one closing the cycle gives up and
+        // releases its locks, so the other one goes on.
+        let results = thread::scope(|scope| {
+            let lock = |txn, resource| {
+                let result = manager.lock(txn, resource, LockMode::Shared, &token);
+                if result.is_err() {
+                    manager.release_all(txn);
+                }
+                result
+            };
+            let a = scope.spawn(move || lock(a, "u"));
+            let b = scope.spawn(move || lock(b, "t"));
+            [a.join().unwrap(), b.join().unwrap()]
+        });
+        let deadlocks = results
+            .iter()
+            .filter(|result| **result == Err(ExecError::Deadlock))
+            .count();
+        assert_eq!(deadlocks, 1, "{results:?}");
+        assert!(results.contains(&Ok(())));
+    }
+}
@@ -0,0 +1,602 @@
+use std::cmp::Ordering;
+
+use rs_db_parser::{
+    ast::expr::{BinaryOperator, Expr, UnaryOperator},
+    eval::Evaluator,
+    value::Value,
+};
+
+use crate::{
+    batch::{Batch, Bitmap, Vector, VectorData},
+    cancel::CancellationToken,
+    error::ExecError,
+    exchange::{Exchange, Transform},
+    explain::Profile,
+    operator::{self, check_columns, matches, Context, Operator},
+    plan::{Layout, Plan, ProjectItem},
+    storage::RowIter,
+    Row,
+};
+
+/// A node of a running plan that produces its rows a [`Batch`] at a time, the vectorized
+/// counterpart of [`Operator`].
+///
+/// An operator that fails on a row returns the rows before it as a batch and the error on
+/// the next call, so the rows a consumer reads and the error it gets are the ones the row
+/// at a time operators give. The filters and projections run on several threads, see
+/// [`Exchange`].
+pub(crate) trait BatchOperator {
+    /// The next batch, never empty.
+    /// # Errors
+    /// Returns an error if the storage fails or an expression can't be evaluated for a row.
+    fn next_batch(&mut self) -> Result<Option<Batch>, ExecError>;
+}
+
+pub(crate) type BoxedBatchOperator<'p> = Box<dyn BatchOperator + 'p>;
+
+/// Whether [`build`] runs `plan`: filters and projections over a scan of a table or of an
+/// index. A scan alone is read a row at a time, there is nothing to run on its batches.
+pub(crate) fn supports(plan: &Plan<'_>) -> bool {
+    fn chain(plan: &Plan<'_>) -> bool {
+        match plan {
+            Plan::Scan { .. } | Plan::IndexScan { .. } | Plan::IndexRangeScan { .. } => true,
+            Plan::Filter { input, .. } | Plan::Project { input, .. } => chain(input),
+            _ => false,
+        }
+    }
+    matches!(plan, Plan::Filter { .. } | Plan::Project { .. }) && chain(plan)
+}
+
+/// The vectorized operators that run `plan`, one that [`supports`] accepts, in `context`.
+/// # Errors
+/// Returns an error if the table of the plan doesn't exist or an expression names a column
+/// its input doesn't have.
+pub(crate) fn build<'p>(
+    plan: &'p Plan<'_>,
+    context: Context<'p>,
+) -> Result<BoxedBatchOperator<'p>, ExecError> {
+    let evaluator = Evaluator::new(context.functions);
+    let workers = context.config.max_parallelism;
+    let node = context.profile.map(Profile::add_node);
+    let operator: BoxedBatchOperator<'p> = match plan {
+        Plan::Filter { input, predicate } => {
+            let layout = input.layout();
+            check_columns(predicate, &layout)?;
+            let filter = BatchFilter {
+                predicate,
+                layout,
+                evaluator,
+            };
+            Box::new(Exchange::new(build(input, context)?, filter, workers))
+        }
+        Plan::Project { input, items, .. } => {
+            let layout = input.layout();
+            let mut sources = Vec::with_capacity(items.len());
+            for item in items.iter() {
+                sources.push(match item {
+                    ProjectItem::Column(index) => Source::Column(*index),
+                    ProjectItem::Expr(expr) => {
+                        check_columns(expr, &layout)?;
+                        match expr {
+                            Expr::Column(column) => Source::Column(
+                                layout.index(column).expect("the columns were checked"),
+                            ),
+                            Expr::Literal((_, value)) => Source::Literal(value),
+                            expr => Source::Expr(expr),
+                        }
+                    }
+                });
+            }
+            let project = BatchProject {
+                sources,
+                layout,
+                evaluator,
+            };
+            Box::new(Exchange::new(build(input, context)?, project, workers))
+        }
+        Plan::Scan { .. } => Box::new(BatchScan {
+            rows: operator::scan(plan, context)?,
+            columns: None,
+            width: plan.layout().len(),
+            size: context.config.batch_size,
+            cancellation: context.cancellation,
+            error: None,
+        }),
+        Plan::IndexScan { columns, .. } | Plan::IndexRangeScan { columns, .. } => {
+            Box::new(BatchScan {
+                rows: operator::index_scan(plan, context)?,
+                columns: columns.as_deref(),
+                width: plan.layout().len(),
+                size: context.config.batch_size,
+                cancellation: context.cancellation,
+                error: None,
+            })
+        }
+        _ => return Err(ExecError::Unsupported("a vectorized plan of this node")),
+    };
+    Ok(match (context.profile, node) {
+        (Some(profile), Some(node)) => profile.instrument_batch(node, operator),
+        _ => operator,
+    })
+}
+
+/// Returns the rows of the batches of a vectorized operator one at a time, to the operators
+/// that take rows.
+pub(crate) struct Unbatch<'p> {
+    input: BoxedBatchOperator<'p>,
+    batch: Option<Batch>,
+    position: usize,
+}
+
+impl<'p> Unbatch<'p> {
+    pub(crate) fn new(input: BoxedBatchOperator<'p>) -> Self {
+        Self {
+            input,
+            batch: None,
+            position: 0,
+        }
+    }
+}
+
+impl Operator for Unbatch<'_> {
+    fn next(&mut self) -> Result<Option<Row>, ExecError> {
+        loop {
+            if let Some(batch) = &self.batch {
+                if self.position < batch.len() {
+                    self.position += 1;
+                    return Ok(Some(batch.row(self.position - 1)));
+                }
+            }
+            self.position = 0;
+            self.batch = self.input.next_batch()?;
+            if self.batch.is_none() {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+struct BatchScan<'p> {
+    rows: RowIter<'p>,
+    columns: Option<&'p [usize]>,
+    width: usize,
+    size: usize,
+    cancellation: &'p CancellationToken,
+    error: Option<ExecError>,
+}
+
+impl BatchOperator for BatchScan<'_> {
+    fn next_batch(&mut self) -> Result<Option<Batch>, ExecError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let mut rows = Vec::with_capacity(self.size);
+        while rows.len() < self.size {
+            let row = match self.cancellation.check() {
+                Ok(()) => self.rows.next(),
+                Err(error) => Some(Err(error)),
+            };
+            match row {
+                Some(Ok(row)) => rows.push(match self.columns {
+                    Some(columns) => columns.iter().map(|i| row[*i].clone()).collect(),
+                    None => row,
+                }),
+                Some(Err(error)) if rows.is_empty() => return Err(error),
+                Some(Err(error)) => {
+                    self.error = Some(error);
+                    break;
+                }
+                None => break,
+            }
+        }
+        Ok((!rows.is_empty()).then(|| Batch::from_rows(rows, self.width)))
+    }
+}
+
+struct BatchFilter<'p, 'a> {
+    predicate: &'p Expr<'a>,
+    layout: Layout,
+    evaluator: Evaluator<'p>,
+}
+
+impl Transform for BatchFilter<'_, '_> {
+    fn apply(&self, batch: Batch) -> (Batch, Option<ExecError>) {
+        let kernels = Kernels {
+            batch: &batch,
+            layout: &self.layout,
+            evaluator: self.evaluator,
+        };
+        let all = Bitmap::new(batch.len(), true);
+        if let Some(truth) = kernels.truth(self.predicate, &all) {
+            return (batch.filter(&truth.values), None);
+        }
+        // Row by row, to fail on the row and with the error the filter of rows does.
+        let mut selection = Bitmap::new(batch.len(), false);
+        for i in 0..batch.len() {
+            match matches(self.evaluator, self.predicate, &self.layout, &batch.row(i)) {
+                Ok(keep) => selection.set(i, keep),
+                Err(error) => return (batch.filter(&selection), Some(error)),
+            }
+        }
+        (batch.filter(&selection), None)
+    }
+}
+
+/// Where a [`BatchProject`] takes the values of an item.
+enum Source<'p, 'a> {
+    /// A column of the input, whose vector is reused.
+    Column(usize),
+    Literal(&'p Value),
+    /// An expression evaluated for each row.
+    Expr(&'p Expr<'a>),
+}
+
+struct BatchProject<'p, 'a> {
+    sources: Vec<Source<'p, 'a>>,
+    layout: Layout,
+    evaluator: Evaluator<'p>,
+}
+
+impl Transform for BatchProject<'_, '_> {
+    fn apply(&self, mut batch: Batch) -> (Batch, Option<ExecError>) {
+        let exprs = self
+            .sources
+            .iter()
+            .filter_map(|source| match source {
+                Source::Expr(expr) => Some(*expr),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut computed = vec![Vec::with_capacity(batch.len()); exprs.len()];
+        // The items of a row are evaluated before the next row, as the projection of rows
+        // does, so a failure is on the same row and item.
+        let mut len = batch.len();
+        let mut error = None;
+        'rows: for i in 0..batch.len() {
+            for (values, expr) in computed.iter_mut().zip(&exprs) {
+                match eval_at(self.evaluator, expr, &self.layout, &batch, i) {
+                    Ok(value) => values.push(value),
+                    Err(e) => {
+                        error = Some(e);
+                        len = i;
+                        break 'rows;
+                    }
+                }
+            }
+        }
+        if len < batch.len() {
+            batch = batch.prefix(len);
+            for values in &mut computed {
+                values.truncate(len);
+            }
+        }
+        let mut computed = computed.into_iter();
+        let columns = self
+            .sources
+            .iter()
+            .map(|source| match source {
+                Source::Column(index) => batch.columns[*index].clone(),
+                Source::Literal(value) => Vector::repeat(value, len),
+                Source::Expr(_) => Vector::from_values(computed.next().unwrap_or_default()),
+            })
+            .collect();
+        (Batch::new(columns, len), error)
+    }
+}
+
+/// Evaluates `expr` for the row at `index` of `batch`, whose columns are described by
+/// `layout`.
+fn eval_at<'a>(
+    evaluator: Evaluator<'_>,
+    expr: &Expr<'a>,
+    layout: &Layout,
+    batch: &Batch,
+    index: usize,
+) -> Result<Value, ExecError> {
+    let value = evaluator.eval(expr, &|column| {
+        layout
+            .index(column)
+            .map_or(Value::Null, |i| batch.columns[i].get(index))
+    })?;
+    Ok(value)
+}
+
+/// The truth of a predicate for some rows of a batch: the rows where it is TRUE and the
+/// ones where it is UNKNOWN, it is FALSE for the others.
+struct Truth {
+    values: Bitmap,
+    nulls: Bitmap,
+}
+
+impl Truth {
+    /// The rows of `mask` where the predicate is FALSE.
+    fn falses(&self, mask: &Bitmap) -> Bitmap {
+        mask.and_not(&self.values.or(&self.nulls))
+    }
+}
+
+/// A value a comparison kernel compares, of one of the types of [`VectorData`].
+#[derive(Clone, Copy)]
+enum Key<'v> {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(&'v str),
+}
+
+impl<'v> Key<'v> {
+    /// The key of `value`, `Some(None)` for `NULL` and `None` for a value of another type.
+    fn of(value: &'v Value) -> Option<Option<Self>> {
+        Some(Some(match value {
+            Value::Null => return Some(None),
+            Value::Bool(b) => Self::Bool(*b),
+            Value::I8(_)
+            | Value::I16(_)
+            | Value::I32(_)
+            | Value::I64(_)
+            | Value::U8(_)
+            | Value::U16(_)
+            | Value::U32(_) => Self::Int(i64::try_from(value.as_i128()?).ok()?),
+            Value::F32(_) | Value::F64(_) => Self::Float(value.as_f64()?),
+            Value::VarChar(s) => Self::Str(s),
+            _ => return None,
+        }))
+    }
+
+    /// The ordering [`Value::compare`] gives, `None` when it fails or the keys are not
+    /// compared that way.
+    #[allow(clippy::cast_precision_loss)]
+    fn compare(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => Some(a.cmp(&b)),
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(&b)),
+            (Self::Float(a), Self::Float(b)) => a.partial_cmp(&b),
+            (Self::Int(a), Self::Float(b)) => (a as f64).partial_cmp(&b),
+            (Self::Float(a), Self::Int(b)) => a.partial_cmp(&(b as f64)),
+            (Self::Str(a), Self::Str(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// An operand of a comparison kernel.
+enum Operand<'v> {
+    Vector(&'v Vector),
+    Scalar(Option<Key<'v>>),
+}
+
+impl<'v> Operand<'v> {
+    /// The key at `index`, `Some(None)` for `NULL` and `None` for a value of another type.
+    fn key(&self, index: usize) -> Option<Option<Key<'v>>> {
+        let vector = match self {
+            Self::Scalar(key) => return Some(*key),
+            Self::Vector(vector) => *vector,
+        };
+        if vector.nulls.get(index) {
+            return Some(None);
+        }
+        Some(Some(match &vector.data {
+            VectorData::Bool(values) => Key::Bool(values[index]),
+            VectorData::I32(values) => Key::Int(values[index].into()),
+            VectorData::I64(values) => Key::Int(values[index]),
+            VectorData::F64(values) => Key::Float(values[index]),
+            VectorData::VarChar(values) => Key::Str(&values[index]),
+            VectorData::Values(values) => return Key::of(&values[index]),
+        }))
+    }
+}
+
+/// Evaluates predicates over the rows of a batch. A kernel gives up, returning `None`, when
+/// the row at a time evaluation may fail, so the filter runs it instead and fails the same.
+struct Kernels<'b, 'p> {
+    batch: &'b Batch,
+    layout: &'b Layout,
+    evaluator: Evaluator<'p>,
+}
+
+impl<'b> Kernels<'b, '_> {
+    /// The truth of `expr` for the rows of `mask`, evaluating it only for them so `AND` and
+    /// `OR` skip the rows their left operand decides.
+    fn truth(&self, expr: &Expr<'_>, mask: &Bitmap) -> Option<Truth> {
+        match expr {
+            Expr::Nested((_, expr)) => self.truth(expr, mask),
+            Expr::Unary {
+                op: (_, UnaryOperator::Not),
+                expr,
+            } => {
+                let truth = self.truth(expr, mask)?;
+                Some(Truth {
+                    values: truth.falses(mask),
+                    nulls: truth.nulls,
+                })
+            }
+            Expr::Binary {
+                op: (_, BinaryOperator::And),
+                left,
+                right,
+            } => {
+                let left = self.truth(left, mask)?;
+                let undecided = left.values.or(&left.nulls);
+                let right = self.truth(right, &undecided)?;
+                let values = left.values.and(&right.values);
+                Some(Truth {
+                    nulls: undecided
+                        .and_not(&right.falses(&undecided))
+                        .and_not(&values),
+                    values,
+                })
+            }
+            Expr::Binary {
+                op: (_, BinaryOperator::Or),
+                left,
+                right,
+            } => {
+                let left = self.truth(left, mask)?;
+                let undecided = mask.and_not(&left.values);
+                let right = self.truth(right, &undecided)?;
+                Some(Truth {
+                    nulls: left.nulls.or(&right.nulls).and_not(&right.values),
+                    values: left.values.or(&right.values),
+                })
+            }
+            Expr::Binary {
+                op: (_, op),
+                left,
+                right,
+            } => match (ordering_test(*op), self.operand(left), self.operand(right)) {
+                (Some(test), Some(left), Some(right)) => compare(test, &left, &right, mask),
+                _ => self.row_by_row(expr, mask),
+            },
+            Expr::IsNull {
+                expr: operand,
+                negated,
+            } => match self.operand(operand) {
+                Some(Operand::Vector(vector)) => Some(Truth {
+                    values: if *negated {
+                        mask.and_not(&vector.nulls)
+                    } else {
+                        mask.and(&vector.nulls)
+                    },
+                    nulls: Bitmap::new(mask.len(), false),
+                }),
+                _ => self.row_by_row(expr, mask),
+            },
+            _ => self.row_by_row(expr, mask),
+        }
+    }
+
+    /// A column or a literal of a type the kernels compare.
+    fn operand(&self, expr: &'b Expr<'_>) -> Option<Operand<'b>> {
+        match expr {
+            Expr::Column(column) => {
+                let vector = &self.batch.columns[self.layout.index(column)?];
+                match vector.data {
+                    VectorData::Values(_) => None,
+                    _ => Some(Operand::Vector(vector)),
+                }
+            }
+            Expr::Literal((_, value)) => Key::of(value).map(Operand::Scalar),
+            _ => None,
+        }
+    }
+
+    /// Evaluates `expr` for each row of `mask`, giving up if it fails or is not a boolean.
+    fn row_by_row(&self, expr: &Expr<'_>, mask: &Bitmap) -> Option<Truth> {
+        let mut values = Bitmap::new(mask.len(), false);
+        let mut nulls = Bitmap::new(mask.len(), false);
+        for i in mask.iter_ones() {
+            let value = eval_at(self.evaluator, expr, self.layout, self.batch, i).ok()?;
+            match value.truth().ok()? {
+                Some(true) => values.set(i, true),
+                Some(false) => {}
+                None => nulls.set(i, true),
+            }
+        }
+        Some(Truth { values, nulls })
+    }
+}
+
+/// Whether an ordering satisfies a comparison operator.
+fn ordering_test(op: BinaryOperator) -> Option<fn(Ordering) -> bool> {
+    Some(match op {
+        BinaryOperator::Eq => Ordering::is_eq,
+        BinaryOperator::NotEq => Ordering::is_ne,
+        BinaryOperator::Lt => Ordering::is_lt,
+        BinaryOperator::LtEq => Ordering::is_le,
+        BinaryOperator::Gt => Ordering::is_gt,
+        BinaryOperator::GtEq => Ordering::is_ge,
+        _ => return None,
+    })
+}
+
+/// Compares `left` and `right` at each row of `mask`.
+fn compare(
+    test: fn(Ordering) -> bool,
+    left: &Operand<'_>,
+    right: &Operand<'_>,
+    mask: &Bitmap,
+) -> Option<Truth> {
+    let mut values = Bitmap::new(mask.len(), false);
+    let mut nulls = Bitmap::new(mask.len(), false);
+    for i in mask.iter_ones() {
+        match (left.key(i)?, right.key(i)?) {
+            (Some(left), Some(right)) => values.set(i, test(left.compare(right)?)),
+            _ => nulls.set(i, true),
+        }
+    }
+    Some(Truth { values, nulls })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use rs_db_parser::{ast::statement::Statement, parse::RawSpan};
+
+    use crate::{
+        executor::{execute, Config, Output},
+        storage::{testing::TestStorage, Storage},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_vectorized_execution() {
+        let (storage, table_map) =
+            TestStorage::new(&["CREATE TABLE t (a INT32, b VARCHAR(10), c FLOAT64, d INT64)"]);
+        for i in 0..20 {
+            let null_or = |value| if i % 7 == 3 { Value::Null } else { value };
+            storage
+                .insert(
+                    "public.t",
+                    vec![
+                        null_or(Value::I32(i)),
+                        null_or(Value::VarChar(format!("b{}", i % 4).into())),
+                        Value::F64(f64::from(i) / 2.0),
+                        Value::I64(i64::from(10 - i)),
+                    ],
+                )
+                .unwrap();
+        }
+        let run = |input: &str, batch_size| {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            let config = Config {
+                batch_size,
+                ..Config::default()
+            };
+            match execute(&statement, &table_map, &storage, &config) {
+                Ok(Output::Rows { rows, .. }) => Ok(rows),
+                Ok(output) => panic!("{output:?}"),
+                Err(error) => Err(error.to_string()),
+            }
+        };
+        for input in [
+            "SELECT a, b FROM t WHERE a > 4 AND b <> 'b1'",
+            "SELECT * FROM t WHERE NOT (a < 10 OR c >= 8) OR b IS NULL",
+            "SELECT a + d, 'x', b FROM t WHERE c = a OR d < 0",
+            "SELECT a FROM t WHERE a BETWEEN 3 AND 9 AND b LIKE 'b%'",
+            "SELECT a FROM t WHERE a IS NOT NULL AND 1 = 1",
+            // The rows before the failing one are returned, as a limit may stop before it.
+            "SELECT a FROM t WHERE d > 0 OR 10 / d > 0",
+            "SELECT a FROM t WHERE d > 0 OR 10 / d > 0 LIMIT 5",
+            "SELECT a, 10 / d FROM t LIMIT 10",
+            "SELECT a, 10 / d FROM t",
+            "SELECT a FROM t WHERE c > 3 AND a <> d",
+            "SELECT count(*) FROM t WHERE a % 2 = 0",
+        ] {
+            let rows = run(input, 0);
+            for batch_size in [1, 3, 1024] {
+                assert_eq!(run(input, batch_size), rows, "{input} {batch_size}");
+            }
+        }
+        assert!(run("SELECT a, 10 / d FROM t", 3).is_err());
+        assert_eq!(
+            run("SELECT a, 10 / d FROM t LIMIT 10", 3).unwrap().len(),
+            10
+        );
+        assert_eq!(
+            run("SELECT a FROM t WHERE a > 15 AND b = 'b2'", 3).unwrap(),
+            [[Value::I32(18)]]
+        );
+    }
+}
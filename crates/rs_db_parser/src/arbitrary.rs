@@ -0,0 +1,417 @@
+use std::sync::Arc;
+
+use proptest::{
+    arbitrary::Arbitrary,
+    collection::vec,
+    prop_oneof,
+    sample::{select, subsequence},
+    strategy::{BoxedStrategy, Just, Strategy, Union},
+};
+
+use crate::{
+    ast::{
+        commands::create::{Column, LengthUnit, SqlType},
+        format::{FormatOptions, FormatSql},
+    },
+    parse::TableMap,
+    value::Value,
+};
+
+impl Arbitrary for SqlType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// The types whose values [`Value::arbitrary_with`] generates.
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        prop_oneof![
+            Just(Self::Bool),
+            Just(Self::I8),
+            Just(Self::I16),
+            Just(Self::I32),
+            Just(Self::I64),
+            Just(Self::U8),
+            Just(Self::U16),
+            Just(Self::U32),
+            Just(Self::U64),
+            Just(Self::F64),
+            (1_usize..=32).prop_map(|size| Self::VarChar(size, LengthUnit::Chars)),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Value {
+    type Parameters = Option<SqlType>;
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A value of the type, one of the types [`SqlType::arbitrary`] generates, or `NULL`
+    /// for the others. Without a type, a value of any of them.
+    fn arbitrary_with(tp: Option<SqlType>) -> Self::Strategy {
+        use proptest::prelude::any;
+        let Some(tp) = tp else {
+            return SqlType::arbitrary()
+                .prop_flat_map(|tp| Self::arbitrary_with(Some(tp)))
+                .boxed();
+        };
+        match tp {
+            SqlType::Bool => any::<bool>().prop_map(Self::Bool).boxed(),
+            SqlType::I8 => any::<i8>().prop_map(Self::I8).boxed(),
+            SqlType::I16 => any::<i16>().prop_map(Self::I16).boxed(),
+            SqlType::I32 => any::<i32>().prop_map(Self::I32).boxed(),
+            SqlType::I64 => any::<i64>().prop_map(Self::I64).boxed(),
+            SqlType::U8 => any::<u8>().prop_map(Self::U8).boxed(),
+            SqlType::U16 => any::<u16>().prop_map(Self::U16).boxed(),
+            SqlType::U32 => any::<u32>().prop_map(Self::U32).boxed(),
+            SqlType::U64 => any::<u64>().prop_map(Self::U64).boxed(),
+            SqlType::F64 => proptest::num::f64::NORMAL.prop_map(Self::F64).boxed(),
+            SqlType::VarChar(size, _) => vec(text_char(), 0..=size.min(32))
+                .prop_map(|chars| Self::VarChar(chars.into_iter().collect()))
+                .boxed(),
+            _ => Just(Self::Null).boxed(),
+        }
+    }
+}
+
+fn text_char() -> impl Strategy<Value = char> {
+    prop_oneof![
+        8 => proptest::char::range('a', 'z'),
+        1 => Just(' '),
+        1 => Just('\''),
+    ]
+}
+
+/// A statement and a catalog it is valid for: the tables, columns, values and expression
+/// types of the statement match the catalog, so
+/// [`Statement::parse_with_table_map`](crate::ast::statement::Statement::parse_with_table_map)
+/// accepts it.
+///
+/// The catalog has one to three tables `t0`, `t1`, … in the default schema, with one to
+/// five columns `c0`, `c1`, … each. The statement is a `SELECT` of one table or a join of
+/// two, an `INSERT`, a `CREATE TABLE` of a new table or a `DROP TABLE`.
+#[derive(Debug, Clone)]
+pub struct GeneratedStatement {
+    pub table_map: TableMap,
+    pub sql: String,
+}
+
+impl Arbitrary for GeneratedStatement {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        vec(vec(SqlType::arbitrary(), 1..=5), 1..=3)
+            .prop_flat_map(|tables| {
+                let tables: Arc<[Vec<SqlType>]> = tables.into();
+                let table_map = tables
+                    .iter()
+                    .enumerate()
+                    .map(|(table, types)| {
+                        let columns = types
+                            .iter()
+                            .enumerate()
+                            .map(|(column, tp)| {
+                                let name = format!("c{column}");
+                                (name.clone().into(), Column::new(name, tp.clone()))
+                            })
+                            .collect();
+                        (format!("t{table}").into(), columns)
+                    })
+                    .collect::<TableMap>();
+                statement(&tables).prop_map(move |sql| Self {
+                    table_map: table_map.clone(),
+                    sql,
+                })
+            })
+            .boxed()
+    }
+}
+
+fn statement(tables: &Arc<[Vec<SqlType>]>) -> BoxedStrategy<String> {
+    let tables = tables.clone();
+    let count = tables.len();
+    let mut statements = vec![
+        (0..count)
+            .prop_flat_map({
+                let tables = tables.clone();
+                move |table| select_statement(&tables, &[table])
+            })
+            .boxed(),
+        (0..count)
+            .prop_flat_map({
+                let tables = tables.clone();
+                move |table| insert_statement(table, &tables[table])
+            })
+            .boxed(),
+        vec(SqlType::arbitrary(), 1..=5)
+            .prop_map(move |types| {
+                let columns = types
+                    .iter()
+                    .enumerate()
+                    .map(|(column, tp)| format!("c{column} {}", sql(tp)))
+                    .collect::<Vec<_>>();
+                format!("CREATE TABLE t{count} ({})", columns.join(", "))
+            })
+            .boxed(),
+        (0..count)
+            .prop_map(|table| format!("DROP TABLE t{table}"))
+            .boxed(),
+    ];
+    if count > 1 {
+        statements.push(
+            subsequence((0..count).collect::<Vec<_>>(), 2)
+                .prop_flat_map(move |joined| select_statement(&tables, &joined))
+                .boxed(),
+        );
+    }
+    Union::new(statements).boxed()
+}
+
+fn sql(node: &impl FormatSql) -> String {
+    node.format_sql(&FormatOptions::compact())
+}
+
+/// What the expressions of a generated statement compute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Integer,
+    Bool,
+    Text,
+}
+
+impl Kind {
+    fn of(tp: &SqlType) -> Option<Self> {
+        match tp {
+            SqlType::Bool => Some(Self::Bool),
+            SqlType::VarChar(..) => Some(Self::Text),
+            tp if tp.is_integer() => Some(Self::Integer),
+            _ => None,
+        }
+    }
+}
+
+/// The columns an expression can use, by name, `table.column` when more than one table is
+/// joined.
+#[derive(Debug, Clone)]
+struct Scope(Arc<[(String, Kind)]>);
+
+impl Scope {
+    fn new(tables: &[Vec<SqlType>], joined: &[usize]) -> Self {
+        let columns = joined
+            .iter()
+            .flat_map(|table| {
+                tables[*table]
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(column, tp)| {
+                        let name = if joined.len() > 1 {
+                            format!("t{table}.c{column}")
+                        } else {
+                            format!("c{column}")
+                        };
+                        Some((name, Kind::of(tp)?))
+                    })
+            })
+            .collect();
+        Self(columns)
+    }
+
+    /// A column of the kind, or a literal when there is none.
+    fn leaf(&self, kind: Kind) -> BoxedStrategy<String> {
+        let literal = match kind {
+            Kind::Integer => (0_i64..100).prop_map(|n| n.to_string()).boxed(),
+            Kind::Bool => select(vec!["TRUE", "FALSE"]).prop_map(String::from).boxed(),
+            Kind::Text => Value::arbitrary_with(Some(SqlType::VarChar(8, LengthUnit::Chars)))
+                .prop_map(|value| sql(&value))
+                .boxed(),
+        };
+        let columns = self
+            .0
+            .iter()
+            .filter(|(_, column_kind)| *column_kind == kind)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            literal
+        } else {
+            prop_oneof![literal, select(columns)].boxed()
+        }
+    }
+
+    /// An integer expression. Only small literals are multiplied, so folding the
+    /// constants never overflows.
+    fn integer(&self) -> BoxedStrategy<String> {
+        self.leaf(Kind::Integer)
+            .prop_recursive(3, 16, 2, |inner| {
+                (inner.clone(), select(vec!["+", "-", "*"]), inner)
+                    .prop_map(|(left, op, right)| format!("({left} {op} {right})"))
+            })
+            .boxed()
+    }
+
+    fn text(&self) -> BoxedStrategy<String> {
+        self.leaf(Kind::Text)
+    }
+
+    fn condition(&self) -> BoxedStrategy<String> {
+        let comparison = (
+            self.integer(),
+            select(vec!["=", "<>", "<", "<=", ">", ">="]),
+            self.integer(),
+        )
+            .prop_map(|(left, op, right)| format!("{left} {op} {right}"));
+        let leaf = prop_oneof![
+            self.leaf(Kind::Bool),
+            comparison,
+            (self.text(), self.text()).prop_map(|(left, right)| format!("{left} = {right}")),
+            (self.integer(), any_bool())
+                .prop_map(|(expr, negated)| format!("{expr} IS {}NULL", not(negated))),
+            (self.integer(), any_bool(), self.integer(), self.integer()).prop_map(
+                |(expr, negated, low, high)| format!(
+                    "{expr} {}BETWEEN {low} AND {high}",
+                    not(negated)
+                )
+            ),
+            (self.integer(), vec(self.integer(), 1..=3))
+                .prop_map(|(expr, list)| format!("{expr} IN ({})", list.join(", "))),
+        ];
+        leaf.prop_recursive(3, 16, 2, |inner| {
+            prop_oneof![
+                (inner.clone(), select(vec!["AND", "OR"]), inner.clone())
+                    .prop_map(|(left, op, right)| format!("({left} {op} {right})")),
+                inner.prop_map(|expr| format!("NOT ({expr})")),
+            ]
+        })
+        .boxed()
+    }
+}
+
+fn any_bool() -> impl Strategy<Value = bool> {
+    proptest::bool::ANY
+}
+
+const fn not(negated: bool) -> &'static str {
+    if negated {
+        "NOT "
+    } else {
+        ""
+    }
+}
+
+/// `SELECT` from the `joined` tables: `*`, some of their columns or expressions with an
+/// alias, an optional `WHERE`, `ORDER BY` and `LIMIT`.
+fn select_statement(tables: &Arc<[Vec<SqlType>]>, joined: &[usize]) -> BoxedStrategy<String> {
+    let scope = Scope::new(tables, joined);
+    let from = joined
+        .iter()
+        .map(|table| format!("t{table}"))
+        .collect::<Vec<_>>();
+    let columns = scope
+        .0
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    let column_count = columns.len();
+    let projection = prop_oneof![
+        Just("*".to_owned()),
+        (
+            subsequence(columns.clone(), 0..=column_count),
+            vec(scope.integer(), 0..=2)
+        )
+            .prop_map(|(columns, exprs)| {
+                let exprs = exprs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, expr)| format!("{expr} AS e{index}"));
+                let projection = columns.into_iter().chain(exprs).collect::<Vec<_>>();
+                if projection.is_empty() {
+                    "*".to_owned()
+                } else {
+                    projection.join(", ")
+                }
+            }),
+    ];
+    let on = if from.len() > 1 {
+        scope.condition().prop_map(Some).boxed()
+    } else {
+        Just(None).boxed()
+    };
+    let order_by = if columns.is_empty() {
+        Just(None).boxed()
+    } else {
+        proptest::option::of((select(columns), any_bool())).boxed()
+    };
+    (
+        projection,
+        on,
+        proptest::option::of(scope.condition()),
+        order_by,
+        proptest::option::of(0_u64..100),
+    )
+        .prop_map(move |(projection, on, selection, order_by, limit)| {
+            let mut sql = format!("SELECT {projection} FROM {}", from[0]);
+            if let Some(on) = on {
+                sql += &format!(" JOIN {} ON {on}", from[1]);
+            }
+            if let Some(selection) = selection {
+                sql += &format!(" WHERE {selection}");
+            }
+            if let Some((column, descending)) = order_by {
+                sql += &format!(
+                    " ORDER BY {column}{}",
+                    if descending { " DESC" } else { "" }
+                );
+            }
+            if let Some(limit) = limit {
+                sql += &format!(" LIMIT {limit}");
+            }
+            sql
+        })
+        .boxed()
+}
+
+/// `INSERT` of one to three rows with a value for every column of the table.
+fn insert_statement(table: usize, types: &[SqlType]) -> BoxedStrategy<String> {
+    let columns = (0..types.len())
+        .map(|column| format!("c{column}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let row = types
+        .iter()
+        .map(|tp| Value::arbitrary_with(Some(tp.clone())).prop_map(|value| sql(&value)))
+        .collect::<Vec<_>>()
+        .prop_map(|values| format!("({})", values.join(", ")));
+    vec(row, 1..=3)
+        .prop_map(move |rows| {
+            format!(
+                "INSERT INTO t{table} ({columns}) VALUES {}",
+                rows.join(", ")
+            )
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::{prelude::any, proptest};
+
+    use crate::{ast::statement::Statement, parse::parse_format_error};
+
+    use super::*;
+
+    proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(64))]
+
+        #[test]
+        fn test_generated_round_trip(generated in any::<GeneratedStatement>()) {
+            let table_map = &generated.table_map;
+            let parse = |input| {
+                parse_format_error(input, |i| Statement::parse_with_table_map(table_map, i))
+                    .map_err(|e| e.to_string())
+                    .map(|statement| sql(&statement))
+            };
+            let formatted = parse(&generated.sql).unwrap();
+            assert_eq!(parse(&formatted).unwrap(), formatted, "{}", generated.sql);
+        }
+
+    }
+}
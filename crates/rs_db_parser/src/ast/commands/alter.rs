@@ -0,0 +1,124 @@
+use nom::error::context;
+
+use crate::{
+    ast::{
+        commands::create::{OwnedRawColumn, RawColumn},
+        table::{OwnedTableName, TableName},
+    },
+    errors::ParseResult,
+    lexer::TokenCursor,
+    parse::{OwnedSpan, Parse, RawSpan},
+    parsers::identifier::identifier,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action<'a> {
+    AddColumn(RawColumn<'a>),
+    DropColumn(RawSpan<'a>),
+    RenameColumn { from: RawSpan<'a>, to: RawSpan<'a> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    pub table_name: TableName<'a>,
+    pub action: Action<'a>,
+}
+
+impl<'a> Parse<'a> for Action<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Alter Action", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let action = if tokens.opt_keyword("add").is_some() {
+                tokens.opt_keyword("column");
+                Self::AddColumn(tokens.parse(RawColumn::parse)?)
+            } else if tokens.opt_keyword("drop").is_some() {
+                tokens.opt_keyword("column");
+                Self::DropColumn(tokens.parse(context("Column Name", identifier))?)
+            } else if tokens.opt_keyword("rename").is_some() {
+                tokens.keyword("column")?;
+                let from = tokens.parse(context("Column Name", identifier))?;
+                tokens.keyword("to")?;
+                let to = tokens.parse(context("New Column Name", identifier))?;
+                Self::RenameColumn { from, to }
+            } else {
+                return Err(tokens.unexpected("ADD, DROP or RENAME"));
+            };
+            Ok((tokens.rest(), action))
+        })(input)
+    }
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Alter Table", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("alter")?;
+            tokens.keyword("table")?;
+            let table_name = tokens.parse(TableName::parse)?;
+            let action = tokens.parse(Action::parse)?;
+            Ok((tokens.rest(), Self { table_name, action }))
+        })(input)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedAction {
+    AddColumn(OwnedRawColumn),
+    DropColumn(OwnedSpan),
+    RenameColumn { from: OwnedSpan, to: OwnedSpan },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub table_name: OwnedTableName,
+    pub action: OwnedAction,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            table_name: self.table_name.to_owned(),
+            action: match &self.action {
+                Action::AddColumn(column) => OwnedAction::AddColumn(column.to_owned()),
+                Action::DropColumn(name) => OwnedAction::DropColumn((*name).into()),
+                Action::RenameColumn { from, to } => OwnedAction::RenameColumn {
+                    from: (*from).into(),
+                    to: (*to).into(),
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("add-column", "ALTER TABLE users ADD COLUMN age uint8");
+        test_case("add", "alter table users add name varchar(10)");
+        test_case("drop-column", "ALTER TABLE users DROP COLUMN age");
+        test_case("schema", "ALTER TABLE sales.orders DROP COLUMN note");
+        test_case(
+            "rename-column",
+            "ALTER TABLE users RENAME COLUMN name TO full_name",
+        );
+    }
+
+    #[test]
+    fn test_invalid_statement() {
+        assert!(Statement::parse("ALTER TABLE users RENAME name TO full_name".into()).is_err());
+        assert!(Statement::parse("ALTER TABLE users ADD COLUMN age".into()).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use nom::error::context;
+
+use crate::{
+    ast::table::{OwnedTableName, TableName},
+    errors::{custom_failure, ParseResult, RawParseError},
+    lexer::TokenCursor,
+    parse::{Parse, RawSpan, TableMap},
+};
+
+/// `ANALYZE [table]`, collects the statistics of a table, or of every table of the catalog
+/// without one, see [`TableStatistics`](crate::parse::TableStatistics).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    pub table_name: Option<TableName<'a>>,
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Analyze", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("analyze")?;
+            let table_name = tokens.opt_parse(TableName::parse)?;
+            Ok((tokens.rest(), Self { table_name }))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses an `ANALYZE`, checking the table exists.
+    /// # Errors
+    /// Returns an error if the input is not a valid `ANALYZE`.
+    /// Returns an error pointing at an unknown schema or table.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the table exists.
+    /// # Errors
+    /// Returns an error pointing at an unknown schema or table.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        if let Some(table_name) = &self.table_name {
+            table_name.lookup(table_map).map_err(|(span, error)| {
+                custom_failure(
+                    span,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+                )
+            })?;
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub table_name: Option<OwnedTableName>,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            table_name: self.table_name.as_ref().map(TableName::to_owned),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::{
+        ast::commands::create::{Column, SqlType},
+        errors::ParseError,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_with_table_map() {
+        let mut table_map = TableMap::new();
+        let column = Column::new("id", SqlType::I32);
+        table_map.insert("users".into(), [(column.name.clone(), column)].into());
+        let parse = |input: &'static str| {
+            Statement::parse_with_table_map(&table_map, input.into())
+                .map(|(_, statement)| statement.to_string())
+        };
+        assert_eq!(parse("analyze").unwrap(), "ANALYZE");
+        assert_eq!(
+            parse("ANALYZE  public.users").unwrap(),
+            "ANALYZE public.users"
+        );
+        let Err(nom::Err::Failure(error)) = parse("ANALYZE orders") else {
+            panic!("an unknown table");
+        };
+        assert_eq!(
+            &*crate::errors::ErrorReport::from(error).message,
+            ParseError::TableNotFound.to_string()
+        );
+    }
+}
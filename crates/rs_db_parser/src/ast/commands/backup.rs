@@ -0,0 +1,66 @@
+use nom::error::context;
+
+use crate::{
+    errors::ParseResult,
+    lexer::{cut, TokenCursor, TokenKind},
+    parse::{owned_with_span, OwnedWithSpan, Parse, RawSpan, WithSpan},
+    parsers::string::string_literal,
+};
+
+/// `BACKUP TO 'path'`, copies the tables of the catalog to the directory `path`, from
+/// which a storage can be opened again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    /// The path, with the span of its literal.
+    pub path: WithSpan<'a, Box<str>>,
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Backup", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("backup")?;
+            tokens.keyword("to")?;
+            let path = cut(tokens
+                .expect(TokenKind::String, "a string")
+                .and_then(|()| tokens.parse(string_literal)))?;
+            Ok((tokens.rest(), Self { path }))
+        })(input)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub path: OwnedWithSpan<Box<str>>,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            path: owned_with_span(&self.path, Clone::clone),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let parse = |input: &'static str| {
+            Statement::parse(input.into()).map(|(_, statement)| statement.to_string())
+        };
+        assert_eq!(
+            parse("backup  to '/var/backups/db'").unwrap(),
+            "BACKUP TO '/var/backups/db'"
+        );
+        assert_eq!(parse("BACKUP TO 'it''s'").unwrap(), "BACKUP TO 'it''s'");
+        assert!(matches!(
+            parse("BACKUP TO backups"),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(parse("BACKUP 'backups'").is_err());
+    }
+}
@@ -1,22 +1,43 @@
+use std::{cmp::Ordering, collections::HashSet, ops::Bound};
+
 use nom::{
     branch::alt,
-    character::complete::{char, multispace0, multispace1},
-    combinator::map,
+    character::complete::char,
+    combinator::{cut, map, opt, recognize, verify},
     error::context,
-    sequence::{delimited, preceded, separated_pair, tuple},
+    multi::many0,
+    sequence::{delimited, pair, preceded, tuple},
 };
 use nom_supreme::tag::complete::tag_no_case;
 
 use crate::{
-    errors::ParseResult,
-    parse::{Parse, RawSpan, WithSpan},
-    parsers::{comma_sep, identifier::identifier, parse_with_span},
+    ast::{
+        expr::{parenthesized, BinaryOperator, Expr, OwnedExpr},
+        table::{OwnedTableName, TableName},
+    },
+    decimal::{pow10, MAX_PRECISION},
+    errors::{custom_failure, ParseResult, RawParseError},
+    lexer::{self, TokenCursor},
+    parse::{
+        owned_with_span, ColumnMap, OwnedSpan, OwnedWithSpan, Parse, RawSpan, TableMap, WithSpan,
+    },
+    parsers::{
+        comma_sep, dialect::dialect_type, identifier::identifier, keyword, multispace0,
+        multispace1, parse_with_span, string::string_literal,
+    },
+    value::Value,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SqlType {
-    VarChar(usize),
+    /// A string of at most this length, counted in characters, or in bytes when declared as
+    /// `VARCHAR(n BYTES)`.
+    VarChar(usize, LengthUnit),
+    /// A string of exactly this length, padded with spaces, `CHARACTER` is a synonym and
+    /// the length defaults to 1. Trailing spaces are not significant.
+    Char(usize),
+    Bool,
     I8,
     I16,
     I32,
@@ -27,37 +48,925 @@ pub enum SqlType {
     U32,
     U64,
     U128,
+    F32,
+    F64,
+    /// An exact number of `precision` digits, `scale` of them after the point.
+    Decimal {
+        precision: u8,
+        scale: u8,
+    },
+    /// A calendar day, without time or time zone.
+    Date,
+    /// A time of day with microsecond precision, without time zone.
+    Time,
+    /// A date and time of day with microsecond precision, without time zone.
+    Timestamp,
+    /// A point in time with microsecond precision, read with a UTC offset and kept in UTC.
+    TimestampTz,
+    /// A length of time in months, days and microseconds.
+    Interval,
+    /// Binary data of any length, `BYTEA` is a synonym.
+    Blob,
+    /// A JSON document, validated when it is parsed.
+    Json,
+    /// A string that must be one of the labels, declared inline as `ENUM('a', 'b')` or with
+    /// `CREATE TYPE name AS ENUM ('a', 'b')`.
+    Enum(Box<[Box<str>]>),
+    /// A list of values of the element type, declared as `INT32[]`.
+    Array(Box<SqlType>),
+}
+
+/// What the length of a `VARCHAR` counts.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    /// Unicode scalar values, so `VARCHAR(5)` holds `'héllo'`.
+    #[default]
+    Chars,
+    /// Bytes of the UTF-8 encoding.
+    Bytes,
+}
+
+impl LengthUnit {
+    /// The length of `text` in this unit.
+    #[must_use]
+    pub fn len(self, text: &str) -> usize {
+        match self {
+            Self::Chars => text.chars().count(),
+            Self::Bytes => text.len(),
+        }
+    }
+
+    /// The longest start of `text` at most `size` long, cut between characters.
+    #[must_use]
+    pub fn truncate(self, text: &str, size: usize) -> &str {
+        let end = match self {
+            Self::Chars => text
+                .char_indices()
+                .nth(size)
+                .map_or(text.len(), |(end, _)| end),
+            Self::Bytes => (0..=size.min(text.len()))
+                .rev()
+                .find(|end| text.is_char_boundary(*end))
+                .unwrap_or(0),
+        };
+        &text[..end]
+    }
+}
+
+/// How the pages of a table are compressed in its file, set with
+/// `CREATE TABLE ... WITH (compression = 'zstd')`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// The pages are written as they are in memory.
+    #[default]
+    None,
+    /// LZ4, fast with a lower ratio.
+    Lz4,
+    /// Zstandard, slower with a higher ratio.
+    Zstd,
+}
+
+impl Compression {
+    /// The name of the compression in the `WITH` clause.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// The compression named `name`, in any case.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::None, Self::Lz4, Self::Zstd]
+            .into_iter()
+            .find(|compression| compression.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Where the rows of a table are kept, set with `CREATE TABLE ... WITH (engine = 'memory')`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Engine {
+    /// In a file of pages, which outlives the process.
+    #[default]
+    Disk,
+    /// In memory, lost when the process stops.
+    Memory,
+    /// In a file of column segments, each column of a segment encoded on its own: a scan
+    /// reads only the columns it needs, for analytic tables.
+    Columnar,
+}
+
+impl Engine {
+    /// The name of the engine in the `WITH` clause.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Disk => "disk",
+            Self::Memory => "memory",
+            Self::Columnar => "columnar",
+        }
+    }
+
+    /// The engine named `name`, in any case.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Disk, Self::Memory, Self::Columnar]
+            .into_iter()
+            .find(|engine| engine.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// How the rows of a table are split in partitions, each kept as a table of its own, set
+/// with `CREATE TABLE ... PARTITION BY RANGE (column) (...)` or
+/// `PARTITION BY HASH (column) PARTITIONS n`. The value of `column` in a row decides its
+/// partition, `NULL` is in the first one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Partitioning {
+    pub column: Box<str>,
+    pub scheme: PartitionScheme,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionScheme {
+    /// `PARTITION name VALUES LESS THAN (bound)`, sorted by their bound: a partition holds
+    /// the values below its bound and not below the one of the previous partition.
+    Range(Box<[RangePartition]>),
+    /// `n` partitions named `p0` to `p{n - 1}`, a value is in the one of its hash modulo `n`:
+    /// the 64-bit FNV-1a hash of its [`Value::encode_comparable`] bytes, which don't change
+    /// between versions, as the rows stay in their partition.
+    Hash(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct RangePartition {
+    pub name: Box<str>,
+    /// `None` for `MAXVALUE`, above every value.
+    pub less_than: Option<Value>,
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl Partitioning {
+    /// The names of the partitions, in their order.
+    #[must_use]
+    pub fn names(&self) -> Vec<Box<str>> {
+        match &self.scheme {
+            PartitionScheme::Range(partitions) => partitions
+                .iter()
+                .map(|partition| partition.name.clone())
+                .collect(),
+            PartitionScheme::Hash(count) => (0..*count).map(|i| format!("p{i}").into()).collect(),
+        }
+    }
+
+    /// The position of the partition that holds `value`, `None` if it is above the bound of
+    /// the last range partition. Equal values of different types, like `1` as an `INT32`
+    /// and as an `INT64`, are in the same partition.
+    #[must_use]
+    pub fn partition(&self, value: &Value) -> Option<usize> {
+        if value.is_null() {
+            return Some(0);
+        }
+        match &self.scheme {
+            PartitionScheme::Range(partitions) => {
+                partitions
+                    .iter()
+                    .position(|partition| match &partition.less_than {
+                        Some(bound) => value.compare(bound) == Ok(Some(Ordering::Less)),
+                        None => true,
+                    })
+            }
+            PartitionScheme::Hash(count) => {
+                let mut bytes = Vec::new();
+                value.encode_comparable(&mut bytes);
+                Some((fnv1a(&bytes) % u64::from(*count)) as usize)
+            }
+        }
+    }
+
+    /// The positions of the partitions that may hold values between `lower` and `upper`,
+    /// in their order. `NULL` is between no bounds, so the first partition is left out when
+    /// its other values are. A hash partition is only told apart for a single value.
+    #[must_use]
+    pub fn prune(&self, lower: Bound<&Value>, upper: Bound<&Value>) -> Vec<usize> {
+        // A partition is out of reach when a comparison says so, not when it fails.
+        let ordering = |value: &Value, bound: &Value| value.compare(bound).ok().flatten();
+        match &self.scheme {
+            PartitionScheme::Hash(count) => match (lower, upper) {
+                (Bound::Included(low), Bound::Included(high))
+                    if ordering(low, high) == Some(Ordering::Equal) =>
+                {
+                    self.partition(low).into_iter().collect()
+                }
+                _ => (0..*count as usize).collect(),
+            },
+            PartitionScheme::Range(partitions) => (0..partitions.len())
+                .filter(|i| {
+                    // The partition holds the values from the bound of the previous one
+                    // included to its bound excluded.
+                    let start = i
+                        .checked_sub(1)
+                        .and_then(|i| partitions[i].less_than.as_ref());
+                    let end = partitions[*i].less_than.as_ref();
+                    let above = match (lower, end) {
+                        (Bound::Included(low) | Bound::Excluded(low), Some(end)) => {
+                            ordering(low, end).is_some_and(Ordering::is_ge)
+                        }
+                        _ => false,
+                    };
+                    let below = match (upper, start) {
+                        (Bound::Included(high), Some(start)) => {
+                            ordering(high, start) == Some(Ordering::Less)
+                        }
+                        (Bound::Excluded(high), Some(start)) => {
+                            ordering(high, start).is_some_and(Ordering::is_le)
+                        }
+                        _ => false,
+                    };
+                    !above && !below
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How values of one type convert to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CastKind {
+    /// Every value converts without loss, the conversion happens on its own, as when
+    /// inserting an `INT8` into an `INT64` column.
+    Implicit,
+    /// The conversion may fail for some values and needs a `CAST`.
+    Explicit,
+    /// The conversion truncates values and needs a `CAST`.
+    Lossy,
+    /// The types can't be converted.
+    Forbidden,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ColumnConstraint<'a> {
+    NotNull,
+    Null,
+    Default(WithSpan<'a, Value>),
+    PrimaryKey,
+    Unique,
+    Check(Expr<'a>),
+    References(References<'a>),
+    /// `AUTO_INCREMENT`, also implied by the `SERIAL` family of types.
+    AutoIncrement,
+}
+
+/// The target of a foreign key, `REFERENCES table [(column, ...)]`. Without columns the
+/// primary key of the referenced table is used.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct References<'a> {
+    pub table_name: TableName<'a>,
+    pub columns: Box<[RawSpan<'a>]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TableConstraint<'a> {
+    PrimaryKey(Box<[RawSpan<'a>]>),
+    Unique(Box<[RawSpan<'a>]>),
+    Check(Expr<'a>),
+    ForeignKey {
+        columns: Box<[RawSpan<'a>]>,
+        references: References<'a>,
+    },
+}
+
+/// The catalog version of [`References`] for a single column.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ColumnReference {
+    /// `None` when the referenced table is in the default schema.
+    pub schema: Option<Box<str>>,
+    pub table_name: Box<str>,
+    /// `None` when the primary key of the referenced table is used.
+    pub column: Option<Box<str>>,
+}
+
+/// A constraint of a table as the catalog keeps it, which the executor checks the rows it
+/// writes against, see [`TableMap::constraints`]. The constraints can't be named in the
+/// statement, they are named like PostgreSQL names the ones it isn't given a name for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Constraint {
+    pub name: Box<str>,
+    pub kind: ConstraintKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintKind {
+    NotNull(Box<str>),
+    PrimaryKey(Vec<Box<str>>),
+    Unique(Vec<Box<str>>),
+    /// The condition as SQL text, parsed again when it is checked, like the query of a
+    /// [`View`](crate::parse::View).
+    Check(Box<str>),
+    /// The `columns` reference the `referenced` columns of a table, its primary key when
+    /// there are none.
+    ForeignKey {
+        columns: Vec<Box<str>>,
+        /// `None` when the referenced table is in the default schema.
+        schema: Option<Box<str>>,
+        table_name: Box<str>,
+        referenced: Vec<Box<str>>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RawColumn<'a> {
     pub name: RawSpan<'a>,
     pub tp: WithSpan<'a, SqlType>,
+    pub constraints: Box<[WithSpan<'a, ColumnConstraint<'a>>]>,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Column {
     pub name: Box<str>,
     pub tp: SqlType,
+    pub nullable: bool,
+    pub default: Option<Value>,
+    pub primary_key: bool,
+    pub unique: bool,
+    pub references: Option<ColumnReference>,
+    pub is_auto_increment: bool,
+}
+
+impl Column {
+    /// A nullable column without any constraint.
+    #[must_use]
+    pub fn new(name: impl Into<Box<str>>, tp: SqlType) -> Self {
+        Self {
+            name: name.into(),
+            tp,
+            nullable: true,
+            default: None,
+            primary_key: false,
+            unique: false,
+            references: None,
+            is_auto_increment: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Statement<'a> {
-    pub table_name: RawSpan<'a>,
+    pub if_not_exists: bool,
+    pub table_name: TableName<'a>,
     pub columns: Box<[RawColumn<'a>]>,
+    pub constraints: Box<[WithSpan<'a, TableConstraint<'a>>]>,
+    pub compression: Compression,
+    pub engine: Engine,
+    pub partitioning: Option<Partitioning>,
+    /// The columns with a bloom filter, set with `WITH (bloom_filter = 'a, b')`, in their
+    /// order there.
+    pub bloom_filters: Box<[Box<str>]>,
+}
+
+impl SqlType {
+    #[must_use]
+    pub const fn is_integer(&self) -> bool {
+        !matches!(
+            self,
+            Self::VarChar(..)
+                | Self::Char(_)
+                | Self::Bool
+                | Self::F32
+                | Self::F64
+                | Self::Decimal { .. }
+                | Self::Date
+                | Self::Time
+                | Self::Timestamp
+                | Self::TimestampTz
+                | Self::Interval
+                | Self::Blob
+                | Self::Json
+                | Self::Enum(_)
+                | Self::Array(_)
+        )
+    }
+
+    #[must_use]
+    pub const fn is_float(&self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    #[must_use]
+    pub const fn is_decimal(&self) -> bool {
+        matches!(self, Self::Decimal { .. })
+    }
+
+    /// Whether the type is a date, a time or an interval.
+    #[must_use]
+    pub const fn is_temporal(&self) -> bool {
+        matches!(
+            self,
+            Self::Date | Self::Time | Self::Timestamp | Self::TimestampTz | Self::Interval
+        )
+    }
+
+    /// Whether arithmetic applies to the type, integers, floats and decimals.
+    #[must_use]
+    pub const fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float() || self.is_decimal()
+    }
+
+    /// The precision and scale of an exact number, an integer has as many digits as its
+    /// largest value, capped to [`MAX_PRECISION`].
+    fn exact_digits(&self) -> Option<(u8, u8)> {
+        if let Self::Decimal { precision, scale } = *self {
+            return Some((precision, scale));
+        }
+        let (min, max) = self.integer_range()?;
+        let digits = max.max(min.unsigned_abs()).ilog10() + 1;
+        Some((
+            u8::try_from(digits).map_or(MAX_PRECISION, |d| d.min(MAX_PRECISION)),
+            0,
+        ))
+    }
+
+    /// The smallest and largest values of an integer type.
+    #[must_use]
+    pub const fn integer_range(&self) -> Option<(i128, u128)> {
+        Some(match self {
+            Self::VarChar(..)
+            | Self::Char(_)
+            | Self::Bool
+            | Self::F32
+            | Self::F64
+            | Self::Decimal { .. }
+            | Self::Date
+            | Self::Time
+            | Self::Timestamp
+            | Self::TimestampTz
+            | Self::Interval
+            | Self::Blob
+            | Self::Json
+            | Self::Enum(_)
+            | Self::Array(_) => return None,
+            Self::I8 => (i8::MIN as i128, i8::MAX as u128),
+            Self::I16 => (i16::MIN as i128, i16::MAX as u128),
+            Self::I32 => (i32::MIN as i128, i32::MAX as u128),
+            Self::I64 => (i64::MIN as i128, i64::MAX as u128),
+            Self::I128 => (i128::MIN, i128::MAX as u128),
+            Self::U8 => (0, u8::MAX as u128),
+            Self::U16 => (0, u16::MAX as u128),
+            Self::U32 => (0, u32::MAX as u128),
+            Self::U64 => (0, u64::MAX as u128),
+            Self::U128 => (0, u128::MAX),
+        })
+    }
+
+    /// The longest text a value of this type is written as, in `unit`. A character takes at
+    /// most four bytes.
+    fn max_text_len(&self, unit: LengthUnit) -> usize {
+        match *self {
+            Self::VarChar(size, LengthUnit::Chars) | Self::Char(size)
+                if unit == LengthUnit::Bytes =>
+            {
+                size.saturating_mul(4)
+            }
+            Self::VarChar(size, _) | Self::Char(size) => size,
+            Self::Bool => "false".len(),
+            Self::I8 => "-128".len(),
+            Self::I16 => "-32768".len(),
+            Self::I32 => "-2147483648".len(),
+            Self::I64 => "-9223372036854775808".len(),
+            Self::I128 => "-170141183460469231731687303715884105728".len(),
+            Self::U8 => "255".len(),
+            Self::U16 => "65535".len(),
+            Self::U32 => "4294967295".len(),
+            Self::U64 => "18446744073709551615".len(),
+            Self::U128 => "340282366920938463463374607431768211455".len(),
+            Self::F32 => "-1.1754944e-38".len(),
+            Self::F64 => "-2.2250738585072014e-308".len(),
+            // The sign, the digits and the point.
+            Self::Decimal { precision, scale } => precision as usize + 1 + (scale > 0) as usize,
+            Self::Date => "YYYY-MM-DD".len(),
+            Self::Time => "HH:MM:SS.ffffff".len(),
+            Self::Timestamp => "YYYY-MM-DD HH:MM:SS.ffffff".len(),
+            Self::TimestampTz => "YYYY-MM-DD HH:MM:SS.ffffff+00".len(),
+            Self::Interval => {
+                "-178956970 years -8 mons -2147483648 days -2562047788:00:54.775808".len()
+            }
+            Self::Blob | Self::Json | Self::Array(_) => usize::MAX,
+            Self::Enum(ref labels) => labels
+                .iter()
+                .map(|label| unit.len(label))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// The conversion matrix between types:
+    /// - integers widen implicitly and narrow explicitly, failing when a value is out of range;
+    /// - strings widen implicitly and narrow lossily, fixed-length strings included, a string
+    ///   sized in characters may be four times as long in bytes;
+    /// - anything converts to a string explicitly, or lossily if the string may be too short,
+    ///   and strings convert explicitly to numbers, booleans and dates by reading their text;
+    /// - integers and `F32` widen implicitly to floats, large integers are rounded to the
+    ///   nearest float, `F64` narrows lossily to `F32`, and
+    ///   floats are rounded lossily to integers;
+    /// - decimals widen implicitly when they keep as many digits before and after the point,
+    ///   narrow explicitly, failing when a value doesn't fit, and round lossily when digits
+    ///   after the point are dropped, integers are decimals without fraction;
+    /// - dates widen implicitly to timestamps at midnight, timestamps widen implicitly to
+    ///   timestamps with a time zone, taken as UTC, and back explicitly, and timestamps are
+    ///   cut lossily to their date or time;
+    /// - binary data converts explicitly from strings written as `\x` and hexadecimal digits;
+    /// - JSON converts explicitly from strings holding a JSON document, and enums from
+    ///   strings that are one of their labels;
+    /// - arrays convert to arrays as their elements do, and explicitly from strings written
+    ///   as `{1,2,3}`;
+    /// - booleans, dates and times, intervals, binary data, JSON, arrays and numbers don't
+    ///   convert to each other.
+    #[must_use]
+    pub fn cast_kind(&self, to: &Self) -> CastKind {
+        let text_fits = match to {
+            Self::VarChar(size, unit) => self.max_text_len(*unit) <= *size,
+            Self::Char(size) => self.max_text_len(LengthUnit::Chars) <= *size,
+            _ => false,
+        };
+        match (self, to) {
+            (from, to) if from == to => CastKind::Implicit,
+            (Self::Array(from), Self::Array(to)) => from.cast_kind(to),
+            (Self::VarChar(..) | Self::Char(_), Self::VarChar(..) | Self::Char(_)) => {
+                if text_fits {
+                    CastKind::Implicit
+                } else {
+                    CastKind::Lossy
+                }
+            }
+            (_, Self::VarChar(..) | Self::Char(_)) => {
+                if text_fits {
+                    CastKind::Explicit
+                } else {
+                    CastKind::Lossy
+                }
+            }
+            (Self::VarChar(..) | Self::Char(_), _) => CastKind::Explicit,
+            (Self::Date, Self::Timestamp | Self::TimestampTz)
+            | (Self::Timestamp, Self::TimestampTz) => CastKind::Implicit,
+            (Self::TimestampTz, Self::Timestamp) => CastKind::Explicit,
+            (Self::Timestamp | Self::TimestampTz, Self::Date | Self::Time) => CastKind::Lossy,
+            (Self::Bool | Self::Blob | Self::Json | Self::Array(_), _)
+            | (_, Self::Bool | Self::Blob | Self::Json | Self::Array(_)) => CastKind::Forbidden,
+            (from, to) if from.is_temporal() || to.is_temporal() => CastKind::Forbidden,
+            (Self::F64, Self::F32) => CastKind::Lossy,
+            (_, Self::F32 | Self::F64) => CastKind::Implicit,
+            (Self::F32 | Self::F64, _) => CastKind::Lossy,
+            (from, to) if from.is_decimal() || to.is_decimal() => {
+                match (from.exact_digits(), to.exact_digits()) {
+                    (Some((_, from_scale)), Some((_, to_scale))) if to_scale < from_scale => {
+                        CastKind::Lossy
+                    }
+                    // A `DECIMAL(3, 0)` holds 999, more than an `INT8`, count the digits of
+                    // integers from their actual range.
+                    (Some((precision, 0)), Some(_)) if to.integer_range().is_some() => {
+                        let limit = pow10(precision) - 1;
+                        let (min, max) = to.integer_range().unwrap_or_default();
+                        if min <= -limit && limit.unsigned_abs() <= max {
+                            CastKind::Implicit
+                        } else {
+                            CastKind::Explicit
+                        }
+                    }
+                    (Some(_), Some((precision, scale))) if from.integer_range().is_some() => {
+                        let limit = pow10(precision - scale) - 1;
+                        let (min, max) = from.integer_range().unwrap_or_default();
+                        if -limit <= min && max <= limit.unsigned_abs() {
+                            CastKind::Implicit
+                        } else {
+                            CastKind::Explicit
+                        }
+                    }
+                    (Some((from_precision, from_scale)), Some((to_precision, to_scale))) => {
+                        if from_precision - from_scale <= to_precision - to_scale {
+                            CastKind::Implicit
+                        } else {
+                            CastKind::Explicit
+                        }
+                    }
+                    _ => CastKind::Forbidden,
+                }
+            }
+            (from, to) => match (from.integer_range(), to.integer_range()) {
+                (Some((from_min, from_max)), Some((to_min, to_max))) => {
+                    if to_min <= from_min && from_max <= to_max {
+                        CastKind::Implicit
+                    } else {
+                        CastKind::Explicit
+                    }
+                }
+                _ => CastKind::Forbidden,
+            },
+        }
+    }
+
+    /// The type of `self op other` for an arithmetic operator:
+    /// - numbers meet at their [`SqlType::common`] type;
+    /// - days are added to or subtracted from a date, and two dates subtract to the number of
+    ///   days between them, an `INT32`;
+    /// - intervals are added to or subtracted from times and timestamps, dates become
+    ///   timestamps, and two times or timestamps subtract to the interval between them;
+    /// - intervals add, subtract and multiply by integers.
+    #[must_use]
+    pub fn arithmetic(&self, op: BinaryOperator, other: &Self) -> Option<Self> {
+        use BinaryOperator::{Minus, Multiply, Plus};
+        match (self, op, other) {
+            (Self::Date, Plus | Minus, days) | (days, Plus, Self::Date) if days.is_integer() => {
+                Some(Self::Date)
+            }
+            (Self::Date, Minus, Self::Date) => Some(Self::I32),
+            (Self::Date, Plus | Minus, Self::Interval) | (Self::Interval, Plus, Self::Date) => {
+                Some(Self::Timestamp)
+            }
+            (
+                tp @ (Self::Time | Self::Timestamp | Self::TimestampTz),
+                Plus | Minus,
+                Self::Interval,
+            )
+            | (Self::Interval, Plus, tp @ (Self::Time | Self::Timestamp | Self::TimestampTz)) => {
+                Some(tp.clone())
+            }
+            (Self::Time, Minus, Self::Time)
+            | (Self::Timestamp, Minus, Self::Timestamp)
+            | (Self::TimestampTz, Minus, Self::TimestampTz)
+            | (Self::Interval, Plus | Minus, Self::Interval) => Some(Self::Interval),
+            (Self::Interval, Multiply, factor) | (factor, Multiply, Self::Interval)
+                if factor.is_integer() =>
+            {
+                Some(Self::Interval)
+            }
+            (a, _, b) => a.common(b).filter(Self::is_numeric),
+        }
+    }
+
+    /// The type both `self` and `other` convert to, following the rules of
+    /// [`Value::checked_arithmetic`]: a float and another number meet at `F64`, a decimal
+    /// and an exact number at a decimal with the digits of both, distinct integer types at
+    /// `I128`, or `U128`. Dates and timestamps meet at the type both convert to implicitly,
+    /// fixed-length strings at the longest one, enums and strings of different kinds at a
+    /// string, sized in bytes only when both are, and arrays at an array of the common
+    /// element type.
+    #[must_use]
+    pub fn common(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (a, b) if a == b => Some(a.clone()),
+            (Self::Char(a), Self::Char(b)) => Some(Self::Char(*a.max(b))),
+            (Self::Array(a), Self::Array(b)) => a.common(b).map(|tp| Self::Array(Box::new(tp))),
+            (
+                Self::VarChar(..) | Self::Char(_),
+                Self::VarChar(..) | Self::Char(_) | Self::Enum(_),
+            )
+            | (Self::Enum(_), Self::VarChar(..) | Self::Char(_)) => {
+                let unit = match (self, other) {
+                    (Self::VarChar(_, LengthUnit::Bytes), Self::VarChar(_, LengthUnit::Bytes)) => {
+                        LengthUnit::Bytes
+                    }
+                    _ => LengthUnit::Chars,
+                };
+                Some(Self::VarChar(
+                    self.max_text_len(unit).max(other.max_text_len(unit)),
+                    unit,
+                ))
+            }
+            (a, b) if a.is_temporal() && b.is_temporal() => {
+                if a.cast_kind(b) == CastKind::Implicit {
+                    Some(b.clone())
+                } else if b.cast_kind(a) == CastKind::Implicit {
+                    Some(a.clone())
+                } else {
+                    None
+                }
+            }
+            (a, b) if (a.is_float() || b.is_float()) && a.is_numeric() && b.is_numeric() => {
+                Some(Self::F64)
+            }
+            (a, b) if a.is_decimal() || b.is_decimal() => {
+                let (a_precision, a_scale) = a.exact_digits()?;
+                let (b_precision, b_scale) = b.exact_digits()?;
+                let scale = a_scale.max(b_scale);
+                let integer = (a_precision - a_scale).max(b_precision - b_scale);
+                Some(Self::Decimal {
+                    precision: (integer + scale).min(MAX_PRECISION),
+                    scale,
+                })
+            }
+            (Self::U128, b) | (b, Self::U128) if b.is_integer() => Some(Self::U128),
+            (a, b) if a.is_integer() && b.is_integer() => Some(Self::I128),
+            _ => None,
+        }
+    }
+}
+
+/// Parses `ENUM('label', ...)`, pointing at the first label declared twice.
+pub(crate) fn enum_type(input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    let (input, labels) = preceded(
+        pair(keyword("enum"), multispace0),
+        cut(delimited(char('('), comma_sep(string_literal), char(')'))),
+    )(input)?;
+    let mut seen = HashSet::new();
+    if let Some((span, _)) = labels.iter().find(|(_, label)| !seen.insert(label)) {
+        return Err(custom_failure(
+            *span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(
+                crate::errors::ParseError::DuplicateEnumLabel,
+            )),
+        ));
+    }
+    Ok((
+        input,
+        SqlType::Enum(labels.into_iter().map(|(_, label)| label).collect()),
+    ))
+}
+
+/// Parses any number of `[]` after an element type, each one makes an array of the type
+/// before it.
+fn array_suffix(tp: SqlType, input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    let (input, dimensions) = many0(pair(char('['), char(']')))(input)?;
+    Ok((
+        input,
+        dimensions
+            .into_iter()
+            .fold(tp, |tp, _| SqlType::Array(Box::new(tp))),
+    ))
+}
+
+/// Parses the name of a type created with `CREATE TYPE`, looked up in `table_map`, or an
+/// array of it.
+fn named_type<'a>(table_map: Option<&TableMap>, input: RawSpan<'a>) -> ParseResult<'a, SqlType> {
+    let (input, name) = identifier(input)?;
+    let tp = table_map
+        .and_then(|table_map| table_map.get_type(name.fragment()))
+        .ok_or_else(|| {
+            custom_failure(
+                name,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    crate::errors::ParseError::TypeNotFound,
+                )),
+            )
+        })?;
+    array_suffix(tp.clone(), input)
+}
+
+/// Parses the date, time and interval types, also the start of their literals, like `DATE '2024-01-31'`.
+pub(crate) fn temporal_type(input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    let time_zone = |word| {
+        tuple((
+            multispace1,
+            keyword(word),
+            multispace1,
+            keyword("time"),
+            multispace1,
+            keyword("zone"),
+        ))
+    };
+    alt((
+        map(keyword("date"), |_| SqlType::Date),
+        map(
+            alt((
+                keyword("timestamptz"),
+                recognize(pair(keyword("timestamp"), time_zone("with"))),
+            )),
+            |_| SqlType::TimestampTz,
+        ),
+        map(
+            pair(keyword("timestamp"), opt(time_zone("without"))),
+            |_| SqlType::Timestamp,
+        ),
+        map(keyword("time"), |_| SqlType::Time),
+        map(keyword("interval"), |_| SqlType::Interval),
+    ))(input)
+}
+
+/// Parses `DECIMAL(precision[, scale])` or its `NUMERIC` synonym, the scale defaults to 0.
+fn decimal_type(input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    let (input, (span, (precision, scale))) = preceded(
+        pair(alt((keyword("decimal"), keyword("numeric"))), multispace0),
+        cut(|i| {
+            parse_with_span(i, |i| {
+                delimited(
+                    pair(char('('), multispace0),
+                    pair(
+                        u8::parse,
+                        opt(preceded(
+                            delimited(multispace0, char(','), multispace0),
+                            u8::parse,
+                        )),
+                    ),
+                    pair(multispace0, char(')')),
+                )(i)
+            })
+        }),
+    )(input)?;
+    let scale = scale.unwrap_or(0);
+    if !(1..=MAX_PRECISION).contains(&precision) || scale > precision {
+        return Err(custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(
+                crate::errors::ParseError::InvalidDecimalType,
+            )),
+        ));
+    }
+    Ok((input, SqlType::Decimal { precision, scale }))
+}
+
+/// Parses the standard names of the types, as other databases write them: `TINYINT`,
+/// `SMALLINT`, `INTEGER` or `INT`, `BIGINT`, `TEXT`, a string of any length, and
+/// `CHARACTER VARYING(n)` or `CHAR VARYING(n)`, a `VARCHAR(n)`. The names of the current
+/// [`Dialect`](crate::parsers::dialect::Dialect) come first.
+fn standard_type(input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    alt((
+        dialect_type,
+        map(keyword("tinyint"), |_| SqlType::I8),
+        map(keyword("smallint"), |_| SqlType::I16),
+        map(alt((keyword("integer"), keyword("int"))), |_| SqlType::I32),
+        map(keyword("bigint"), |_| SqlType::I64),
+        map(keyword("text"), |_| {
+            SqlType::VarChar(usize::MAX, LengthUnit::Chars)
+        }),
+        map(
+            preceded(
+                tuple((
+                    alt((keyword("character"), keyword("char"))),
+                    multispace1,
+                    keyword("varying"),
+                    multispace0,
+                )),
+                cut(delimited(
+                    pair(char('('), multispace0),
+                    usize::parse,
+                    pair(multispace0, char(')')),
+                )),
+            ),
+            |size| SqlType::VarChar(size, LengthUnit::Chars),
+        ),
+    ))(input)
+}
+
+/// Parses the `SERIAL` pseudo types, integers with an implicit `AUTO_INCREMENT`.
+fn serial_type(input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    alt((
+        map(keyword("smallserial"), |_| SqlType::I16),
+        map(keyword("serial"), |_| SqlType::I32),
+        map(keyword("bigserial"), |_| SqlType::I64),
+    ))(input)
 }
 
 impl<'a> Parse<'a> for SqlType {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
-        context(
+        let (input, tp) = context(
             "Column Type",
             alt((
+                standard_type,
                 map(
                     preceded(
                         tag_no_case("varchar"),
-                        delimited(char('('), usize::parse, char(')')),
+                        delimited(
+                            char('('),
+                            pair(
+                                usize::parse,
+                                opt(preceded(
+                                    multispace1,
+                                    alt((
+                                        map(keyword("chars"), |_| LengthUnit::Chars),
+                                        map(keyword("bytes"), |_| LengthUnit::Bytes),
+                                    )),
+                                )),
+                            ),
+                            char(')'),
+                        ),
+                    ),
+                    |(size, unit)| Self::VarChar(size, unit.unwrap_or_default()),
+                ),
+                map(
+                    preceded(
+                        alt((keyword("character"), keyword("char"))),
+                        opt(delimited(char('('), usize::parse, char(')'))),
                     ),
-                    Self::VarChar,
+                    |size| Self::Char(size.unwrap_or(1)),
                 ),
+                map(alt((keyword("boolean"), keyword("bool"))), |_| Self::Bool),
                 map(tag_no_case("int8"), |_| Self::I8),
                 map(tag_no_case("int16"), |_| Self::I16),
                 map(tag_no_case("int32"), |_| Self::I32),
@@ -68,68 +977,1044 @@ impl<'a> Parse<'a> for SqlType {
                 map(tag_no_case("uint32"), |_| Self::U32),
                 map(tag_no_case("uint64"), |_| Self::U64),
                 map(tag_no_case("uint128"), |_| Self::U128),
+                map(keyword("float32"), |_| Self::F32),
+                decimal_type,
+                map(keyword("float64"), |_| Self::F64),
+                temporal_type,
+                map(alt((keyword("blob"), keyword("bytea"))), |_| Self::Blob),
+                map(keyword("json"), |_| Self::Json),
+                enum_type,
             )),
-        )(input)
+        )(input)?;
+        array_suffix(tp, input)
+    }
+}
+
+impl<'a> ColumnConstraint<'a> {
+    /// Parses a column constraint, `DEFAULT` literals are checked against the column type.
+    /// # Errors
+    /// Returns an error if the input is not a valid column constraint.
+    pub fn parse_with_type(tp: SqlType, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Column Constraint", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let constraint = if tokens.opt_keyword("not").is_some() {
+                tokens.keyword("null")?;
+                Self::NotNull
+            } else if tokens.opt_keyword("null").is_some() {
+                Self::Null
+            } else if tokens.opt_keyword("default").is_some() {
+                Self::Default(lexer::cut(
+                    tokens.parse(|i| Value::parse_with_type(tp.clone(), i)),
+                )?)
+            } else if tokens.opt_keyword("primary").is_some() {
+                tokens.keyword("key")?;
+                Self::PrimaryKey
+            } else if tokens.opt_keyword("unique").is_some() {
+                Self::Unique
+            } else if tokens.is_next("check") {
+                Self::Check(check(&mut tokens)?)
+            } else if tokens.is_next("references") {
+                Self::References(tokens.parse(References::parse)?)
+            } else if tokens.opt_keyword("auto_increment").is_some()
+                || tokens.opt_keyword("autoincrement").is_some()
+            {
+                Self::AutoIncrement
+            } else {
+                return Err(tokens.unexpected("a column constraint"));
+            };
+            Ok((tokens.rest(), constraint))
+        })(input)
     }
 }
 
 impl<'a> Parse<'a> for RawColumn<'a> {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
-        context(
-            "Column",
-            map(
-                tuple((context("Column Name", identifier), char(' '), |i| {
-                    parse_with_span(i, SqlType::parse)
-                })),
-                |(name, _, tp)| Self { name, tp },
-            ),
-        )(input)
+        Self::parse_with_types(None, input)
+    }
+}
+
+impl<'a> RawColumn<'a> {
+    /// Parses a column definition, its type may also be one created with `CREATE TYPE`,
+    /// looked up in `table_map`.
+    fn parse_with_types(table_map: Option<&TableMap>, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let (column, serial) = tokens.context("Column", |tokens| {
+            let name = tokens.parse(context("Column Name", identifier))?;
+            let (tp_span, (tp, serial)) = tokens.parse(|i| {
+                parse_with_span(i, |i| {
+                    alt((
+                        map(serial_type, |tp| (tp, true)),
+                        map(SqlType::parse, |tp| (tp, false)),
+                        map(|i| named_type(table_map, i), |tp| (tp, false)),
+                    ))(i)
+                })
+            })?;
+            Ok((
+                Self {
+                    name,
+                    tp: (tp_span, tp),
+                    constraints: Box::new([]),
+                },
+                serial,
+            ))
+        })?;
+
+        let tp = column.tp.1.clone();
+        let mut constraints = tokens.context("Column", |tokens| {
+            let mut constraints = Vec::new();
+            while let Some(constraint) = tokens.opt_parse(|i| {
+                parse_with_span(i, |i| ColumnConstraint::parse_with_type(tp.clone(), i))
+            })? {
+                constraints.push(constraint);
+            }
+            Ok(constraints)
+        })?;
+        if serial {
+            constraints.insert(0, (column.tp.0, ColumnConstraint::AutoIncrement));
+        }
+        let column = Self {
+            constraints: constraints.into(),
+            ..column
+        };
+
+        if let Some((span, _)) = column
+            .constraints
+            .iter()
+            .find(|(_, constraint)| *constraint == ColumnConstraint::AutoIncrement)
+        {
+            if !tp.is_integer() {
+                return Err(custom_failure(
+                    *span,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::AutoIncrementNotInteger,
+                    )),
+                ));
+            }
+        }
+
+        if let Some((span, _)) = column.default().filter(|(_, value)| value.is_null()) {
+            if !column.nullable() {
+                return Err(custom_failure(
+                    *span,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::NullNotAllowed,
+                    )),
+                ));
+            }
+        }
+        Ok((tokens.rest(), column))
+    }
+
+    /// Whether the column accepts `NULL` values, columns are nullable unless declared
+    /// `NOT NULL`, `PRIMARY KEY` or `AUTO_INCREMENT`.
+    #[must_use]
+    pub fn nullable(&self) -> bool {
+        !self.constraints.iter().any(|(_, constraint)| {
+            matches!(
+                constraint,
+                ColumnConstraint::NotNull
+                    | ColumnConstraint::PrimaryKey
+                    | ColumnConstraint::AutoIncrement
+            )
+        })
+    }
+
+    #[must_use]
+    pub fn is_auto_increment(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|(_, constraint)| *constraint == ColumnConstraint::AutoIncrement)
+    }
+
+    #[must_use]
+    pub fn primary_key(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|(_, constraint)| *constraint == ColumnConstraint::PrimaryKey)
+    }
+
+    #[must_use]
+    pub fn references(&self) -> Option<&References<'a>> {
+        self.constraints
+            .iter()
+            .find_map(|(_, constraint)| match constraint {
+                ColumnConstraint::References(references) => Some(references),
+                _ => None,
+            })
+    }
+
+    /// Whether the values of the column must be unique, which is implied by `PRIMARY KEY`.
+    #[must_use]
+    pub fn unique(&self) -> bool {
+        self.constraints.iter().any(|(_, constraint)| {
+            matches!(
+                constraint,
+                ColumnConstraint::Unique | ColumnConstraint::PrimaryKey
+            )
+        })
+    }
+
+    /// The `DEFAULT` value of the column, if one was declared.
+    #[must_use]
+    pub fn default(&self) -> Option<&WithSpan<'a, Value>> {
+        self.constraints
+            .iter()
+            .find_map(|(_, constraint)| match constraint {
+                ColumnConstraint::Default(value) => Some(value),
+                _ => None,
+            })
+    }
+}
+
+/// Parses `CHECK (<predicate>)`.
+fn check<'a>(tokens: &mut TokenCursor<'a>) -> Result<Expr<'a>, nom::Err<RawParseError<'a>>> {
+    tokens.context("Check", |tokens| {
+        tokens.keyword("check")?;
+        lexer::cut(parenthesized(tokens))
+    })
+}
+
+impl<'a> TableConstraint<'a> {
+    /// The columns the constraint is declared on.
+    #[must_use]
+    pub fn columns(&self) -> &[RawSpan<'a>] {
+        match self {
+            Self::PrimaryKey(columns)
+            | Self::Unique(columns)
+            | Self::ForeignKey { columns, .. } => columns,
+            Self::Check(_) => &[],
+        }
+    }
+}
+
+impl<'a> Parse<'a> for References<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("References", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("references")?;
+            let table_name = lexer::cut(tokens.parse(TableName::parse))?;
+            let columns = if tokens.is_next_symbol("(") {
+                column_list(&mut tokens)?
+            } else {
+                Box::default()
+            };
+            Ok((
+                tokens.rest(),
+                Self {
+                    table_name,
+                    columns,
+                },
+            ))
+        })(input)
+    }
+}
+
+fn column_list<'a>(
+    tokens: &mut TokenCursor<'a>,
+) -> Result<Box<[RawSpan<'a>]>, nom::Err<RawParseError<'a>>> {
+    tokens.symbol("(")?;
+    let columns = lexer::cut(tokens.parse(context("Column Names", comma_sep(identifier))))?;
+    lexer::cut(tokens.symbol(")"))?;
+    Ok(columns.into())
+}
+
+impl<'a> Parse<'a> for TableConstraint<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Table Constraint", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let constraint = if tokens.opt_keyword("primary").is_some() {
+                tokens.keyword("key")?;
+                Self::PrimaryKey(column_list(&mut tokens)?)
+            } else if tokens.opt_keyword("unique").is_some() {
+                Self::Unique(column_list(&mut tokens)?)
+            } else if tokens.is_next("check") {
+                Self::Check(check(&mut tokens)?)
+            } else if tokens.opt_keyword("foreign").is_some() {
+                tokens.keyword("key")?;
+                let columns = lexer::cut(column_list(&mut tokens))?;
+                let references = lexer::cut(tokens.parse(References::parse))?;
+                Self::ForeignKey {
+                    columns,
+                    references,
+                }
+            } else {
+                return Err(tokens.unexpected("PRIMARY KEY, UNIQUE, CHECK or FOREIGN KEY"));
+            };
+            Ok((tokens.rest(), constraint))
+        })(input)
     }
 }
 
 impl<'a> Parse<'a> for Statement<'a> {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
-        context(
-            "Create Table",
-            map(
-                separated_pair(
-                    preceded(
-                        tuple((
-                            multispace0,
-                            tag_no_case("create"),
-                            multispace1,
-                            tag_no_case("table"),
-                            multispace1,
-                        )),
-                        context("Table Name", identifier),
-                    ),
-                    multispace1,
-                    column_definitions,
-                ),
-                |(table_name, columns)| Self {
-                    table_name: (*table_name.fragment()).into(),
+        Self::parse_with_types(None, input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses a `CREATE TABLE` statement, the types created with `CREATE TYPE` are looked
+    /// up in `table_map`.
+    fn parse_with_types(table_map: Option<&TableMap>, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = context("Create Table", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("create")?;
+            tokens.keyword("table")?;
+            let if_not_exists = if tokens.opt_keyword("if").is_some() {
+                tokens.keyword("not")?;
+                tokens.keyword("exists")?;
+                true
+            } else {
+                false
+            };
+            let table_name = tokens.parse(TableName::parse)?;
+            let (columns, constraints) = column_definitions(table_map, &mut tokens)?;
+            let partitioning = if tokens.is_next("partition") {
+                Some(partition_by(&columns, &mut tokens)?)
+            } else {
+                None
+            };
+            let options = if tokens.is_next("with") {
+                table_options(&columns, &mut tokens)?
+            } else {
+                TableOptions::default()
+            };
+            Ok((
+                tokens.rest(),
+                Self {
+                    if_not_exists,
+                    table_name,
                     columns,
+                    constraints,
+                    compression: options.compression,
+                    engine: options.engine,
+                    partitioning,
+                    bloom_filters: options.bloom_filters,
                 },
+            ))
+        })(input)?;
+        statement.validate()?;
+        Ok((input, statement))
+    }
+}
+
+/// Parses the `PARTITION BY RANGE (column) (PARTITION p0 VALUES LESS THAN (10), ...)` or
+/// `PARTITION BY HASH (column) PARTITIONS 4` clause of a `CREATE TABLE` with `columns`. The
+/// bounds are values of the type of the column, increasing, and `MAXVALUE` is the last one.
+fn partition_by<'a>(
+    columns: &[RawColumn<'a>],
+    tokens: &mut TokenCursor<'a>,
+) -> Result<Partitioning, nom::Err<RawParseError<'a>>> {
+    let (hash, column) = tokens.context("Partition By", |tokens| {
+        tokens.keyword("partition")?;
+        tokens.keyword("by")?;
+        let hash = if tokens.opt_keyword("hash").is_some() {
+            true
+        } else if tokens.opt_keyword("range").is_some() {
+            false
+        } else {
+            lexer::cut(Err(tokens.unexpected("RANGE or HASH")))?
+        };
+        lexer::cut(tokens.symbol("("))?;
+        let column = lexer::cut(tokens.parse(identifier))?;
+        lexer::cut(tokens.symbol(")"))?;
+        Ok((hash, column))
+    })?;
+    let failure = |span, error| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+        )
+    };
+    let tp = columns
+        .iter()
+        .find(|found| found.name.fragment() == column.fragment())
+        .map(|found| found.tp.1.clone())
+        .ok_or_else(|| failure(column, crate::errors::ParseError::ColumnNotFound))?;
+    let column = (*column.fragment()).into();
+    if hash {
+        let count = tokens.context("Partitions", |tokens| {
+            tokens.keyword("partitions")?;
+            lexer::cut(tokens.parse(verify(u32::parse, |count| *count > 0)))
+        })?;
+        let scheme = PartitionScheme::Hash(count);
+        return Ok(Partitioning { column, scheme });
+    }
+    let bound = |i: RawSpan<'a>| -> ParseResult<'a, WithSpan<'a, Option<Value>>> {
+        alt((
+            map(
+                |i| parse_with_span(i, keyword("maxvalue")),
+                |(span, _)| (span, None),
             ),
-        )(input)
+            map(
+                |i| Value::parse_with_type(tp.clone(), i),
+                |(span, value)| (span, Some(value)),
+            ),
+        ))(i)
+    };
+    let partitions = tokens.context("Range Partitions", |tokens| {
+        lexer::cut(tokens.symbol("("))?;
+        let partitions = lexer::cut(tokens.parse(comma_sep(|i| {
+            let mut tokens = TokenCursor::new(i);
+            tokens.keyword("partition")?;
+            let name = lexer::cut(tokens.parse(identifier))?;
+            lexer::cut(
+                tokens
+                    .keyword("values")
+                    .and_then(|_| tokens.keyword("less"))
+                    .and_then(|_| tokens.keyword("than"))
+                    .and_then(|_| tokens.symbol("(")),
+            )?;
+            let bound = lexer::cut(tokens.parse(bound))?;
+            lexer::cut(tokens.symbol(")"))?;
+            Ok((tokens.rest(), (name, bound)))
+        })))?;
+        lexer::cut(tokens.symbol(")"))?;
+        Ok(partitions)
+    })?;
+    let mut names = HashSet::new();
+    let mut previous: Option<Option<&Value>> = None;
+    for (name, (span, bound)) in &partitions {
+        if !names.insert(*name.fragment()) {
+            return Err(failure(
+                *name,
+                crate::errors::ParseError::DuplicatePartition,
+            ));
+        }
+        let increasing = match (previous, bound) {
+            (None, Some(bound)) => !bound.is_null(),
+            (None, None) => true,
+            (Some(None), _) => false,
+            (Some(Some(_)), None) => true,
+            (Some(Some(previous)), Some(bound)) => {
+                previous.compare(bound) == Ok(Some(Ordering::Less))
+            }
+        };
+        if !increasing {
+            return Err(failure(
+                *span,
+                crate::errors::ParseError::PartitionBoundOrder,
+            ));
+        }
+        previous = Some(bound.as_ref());
     }
+    let partitions = partitions
+        .into_iter()
+        .map(|(name, (_, less_than))| RangePartition {
+            name: (*name.fragment()).into(),
+            less_than,
+        })
+        .collect();
+    let scheme = PartitionScheme::Range(partitions);
+    Ok(Partitioning { column, scheme })
 }
 
-fn column_definitions(input: RawSpan<'_>) -> ParseResult<'_, Box<[RawColumn]>> {
-    context(
-        "Column Definitions",
-        map(
-            delimited(char('('), comma_sep(RawColumn::parse), char(')')),
-            |columns| columns.as_slice().into(),
-        ),
-    )(input)
+/// The options of the `WITH` clause of a `CREATE TABLE`.
+#[derive(Default)]
+struct TableOptions {
+    compression: Compression,
+    engine: Engine,
+    bloom_filters: Box<[Box<str>]>,
+}
+
+/// Parses the `WITH (compression = '...', engine = '...', bloom_filter = '...')` clause of
+/// a `CREATE TABLE` with `columns`, a later option overrides an earlier one. The bloom
+/// filter is a list of names of `columns` separated by commas.
+fn table_options<'a>(
+    columns: &[RawColumn<'a>],
+    tokens: &mut TokenCursor<'a>,
+) -> Result<TableOptions, nom::Err<RawParseError<'a>>> {
+    let options = tokens.context("Table Options", |tokens| {
+        tokens.keyword("with")?;
+        lexer::cut(tokens.symbol("("))?;
+        let options = lexer::cut(tokens.parse(comma_sep(|i| {
+            let mut tokens = TokenCursor::new(i);
+            let option = tokens
+                .opt_keyword("compression")
+                .or_else(|| tokens.opt_keyword("engine"))
+                .or_else(|| tokens.opt_keyword("bloom_filter"))
+                .ok_or_else(|| tokens.unexpected("COMPRESSION, ENGINE or BLOOM_FILTER"))?;
+            tokens.symbol("=")?;
+            let value = tokens.parse(string_literal)?;
+            Ok((tokens.rest(), (option, value)))
+        })))?;
+        lexer::cut(tokens.symbol(")"))?;
+        Ok(options)
+    })?;
+    let unknown = |span, error| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+        )
+    };
+    let mut table_options = TableOptions::default();
+    for (option, (span, name)) in options {
+        if option.fragment().eq_ignore_ascii_case("engine") {
+            table_options.engine = Engine::from_name(&name)
+                .ok_or_else(|| unknown(span, crate::errors::ParseError::UnknownEngine))?;
+        } else if option.fragment().eq_ignore_ascii_case("bloom_filter") {
+            let mut names = Vec::<Box<str>>::new();
+            for name in name
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                if !columns.iter().any(|column| *column.name.fragment() == name) {
+                    return Err(unknown(span, crate::errors::ParseError::ColumnNotFound));
+                }
+                if !names.iter().any(|found| **found == *name) {
+                    names.push(name.into());
+                }
+            }
+            table_options.bloom_filters = names.into();
+        } else {
+            table_options.compression = Compression::from_name(&name)
+                .ok_or_else(|| unknown(span, crate::errors::ParseError::UnknownCompression))?;
+        }
+    }
+    Ok(table_options)
+}
+
+enum Definition<'a> {
+    Column(RawColumn<'a>),
+    Constraint(WithSpan<'a, TableConstraint<'a>>),
+}
+
+type Definitions<'a> = (
+    Box<[RawColumn<'a>]>,
+    Box<[WithSpan<'a, TableConstraint<'a>>]>,
+);
+
+fn column_definitions<'a>(
+    table_map: Option<&TableMap>,
+    tokens: &mut TokenCursor<'a>,
+) -> Result<Definitions<'a>, nom::Err<RawParseError<'a>>> {
+    tokens.context("Column Definitions", |tokens| {
+        tokens.symbol("(")?;
+        let definitions = tokens.parse(comma_sep(alt((
+            map(
+                |i| parse_with_span(i, TableConstraint::parse),
+                Definition::Constraint,
+            ),
+            map(
+                |i| RawColumn::parse_with_types(table_map, i),
+                Definition::Column,
+            ),
+        ))))?;
+        tokens.symbol(")")?;
+        let mut columns = Vec::new();
+        let mut constraints = Vec::new();
+        for definition in definitions {
+            match definition {
+                Definition::Column(column) => columns.push(column),
+                Definition::Constraint(constraint) => constraints.push(constraint),
+            }
+        }
+        Ok((columns.into(), constraints.into()))
+    })
+}
+
+impl<'a> Statement<'a> {
+    /// Checks the table constraints against the declared columns.
+    fn validate(&self) -> Result<(), nom::Err<crate::errors::RawParseError<'a>>> {
+        let mut primary_keys = self
+            .columns
+            .iter()
+            .flat_map(|column| column.constraints.iter())
+            .filter(|(_, constraint)| *constraint == ColumnConstraint::PrimaryKey)
+            .map(|(span, _)| *span)
+            .chain(
+                self.constraints
+                    .iter()
+                    .filter(|(_, constraint)| matches!(constraint, TableConstraint::PrimaryKey(_)))
+                    .map(|(span, _)| *span),
+            );
+        if let Some(span) = primary_keys.nth(1) {
+            return Err(custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    crate::errors::ParseError::MultiplePrimaryKeys,
+                )),
+            ));
+        }
+
+        let checks = self
+            .columns
+            .iter()
+            .flat_map(|column| column.constraints.iter())
+            .filter_map(|(_, constraint)| match constraint {
+                ColumnConstraint::Check(expr) => Some(expr),
+                _ => None,
+            })
+            .chain(
+                self.constraints
+                    .iter()
+                    .filter_map(|(_, constraint)| match constraint {
+                        TableConstraint::Check(expr) => Some(expr),
+                        _ => None,
+                    }),
+            );
+        for column in checks.flat_map(Expr::columns) {
+            if let Some(qualifier) = column
+                .qualifier
+                .filter(|qualifier| qualifier.fragment() != self.table_name.name.fragment())
+            {
+                return Err(custom_failure(
+                    qualifier,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::TableNotFound,
+                    )),
+                ));
+            }
+            if self.column(column.name.fragment()).is_none() {
+                return Err(custom_failure(
+                    column.name,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::ColumnNotFound,
+                    )),
+                ));
+            }
+        }
+
+        for (_, constraint) in self.constraints.iter() {
+            if let TableConstraint::ForeignKey {
+                columns,
+                references,
+            } = constraint
+            {
+                if !references.columns.is_empty() && references.columns.len() != columns.len() {
+                    return Err(custom_failure(
+                        references.table_name.name,
+                        nom_supreme::error::BaseErrorKind::External(Box::new(
+                            crate::errors::ParseError::ForeignKeyColumnCount,
+                        )),
+                    ));
+                }
+            }
+            if let Some(name) = constraint
+                .columns()
+                .iter()
+                .find(|name| self.column(name.fragment()).is_none())
+            {
+                return Err(custom_failure(
+                    *name,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::ColumnNotFound,
+                    )),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a `CREATE TABLE` statement, checking that the tables and columns referenced by
+    /// foreign keys exist. A table may reference itself. Columns may use the types created
+    /// with `CREATE TYPE`.
+    /// # Errors
+    /// Returns an error if the input is not a valid `CREATE TABLE` statement.
+    /// Returns an error pointing at the first unknown type.
+    /// Returns an error if the schema of the table doesn't exist.
+    /// Returns an error if the table already exists, unless `IF NOT EXISTS` was given.
+    /// Returns an error pointing at the first unknown referenced schema, table or column.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse_with_types(Some(table_map), input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the table doesn't exist yet and the tables and columns of its foreign keys
+    /// exist. The types created with `CREATE TYPE` are resolved while parsing, see
+    /// [`Statement::parse_with_table_map`].
+    /// # Errors
+    /// Returns an error if the schema of the table doesn't exist.
+    /// Returns an error if the table already exists, unless `IF NOT EXISTS` was given.
+    /// Returns an error pointing at the first unknown referenced schema, table or column.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        match self.table_name.lookup(table_map) {
+            Ok(_) if !self.if_not_exists => {
+                return Err(custom_failure(
+                    self.table_name.name,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::TableAlreadyExists,
+                    )),
+                ));
+            }
+            Err((span, error @ crate::errors::ParseError::SchemaNotFound)) => {
+                return Err(custom_failure(
+                    span,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+                ));
+            }
+            _ => {}
+        }
+        for references in self.references() {
+            let own_table = references
+                .table_name
+                .same_table(&self.table_name, table_map);
+            let columns = match references.table_name.lookup(table_map) {
+                Ok(columns) => Some(columns),
+                Err(_) if own_table => None,
+                Err((span, error)) => {
+                    return Err(custom_failure(
+                        span,
+                        nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+                    ));
+                }
+            };
+            if let Some(name) = references.columns.iter().find(|name| {
+                if own_table {
+                    self.column(name.fragment()).is_none()
+                } else {
+                    columns.is_none_or(|columns| !columns.contains_key(*name.fragment()))
+                }
+            }) {
+                return Err(custom_failure(
+                    *name,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        crate::errors::ParseError::ColumnNotFound,
+                    )),
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Every foreign key target declared by the statement, on columns or on the table.
+    pub fn references(&self) -> impl Iterator<Item = &References<'a>> {
+        self.columns.iter().filter_map(RawColumn::references).chain(
+            self.constraints
+                .iter()
+                .filter_map(|(_, constraint)| match constraint {
+                    TableConstraint::ForeignKey { references, .. } => Some(references),
+                    _ => None,
+                }),
+        )
+    }
+
+    #[must_use]
+    pub fn column(&self, name: &str) -> Option<&RawColumn<'a>> {
+        self.columns
+            .iter()
+            .find(|column| *column.name.fragment() == name)
+    }
+
+    /// Builds the catalog entry of the table, applying the table level constraints to the
+    /// columns they reference. Multi-column `UNIQUE` and `CHECK` constraints only live on
+    /// the statement.
+    #[must_use]
+    pub fn column_map(&self) -> ColumnMap {
+        let mut columns: ColumnMap = self
+            .columns
+            .iter()
+            .cloned()
+            .map(|column| {
+                let column = Column::from(column);
+                (column.name.clone(), column)
+            })
+            .collect();
+        for (_, constraint) in self.constraints.iter() {
+            match constraint {
+                TableConstraint::PrimaryKey(names) => {
+                    for name in names.iter() {
+                        if let Some(column) = columns.get_mut(*name.fragment()) {
+                            column.primary_key = true;
+                            column.nullable = false;
+                        }
+                    }
+                    if let [name] = &**names {
+                        if let Some(column) = columns.get_mut(*name.fragment()) {
+                            column.unique = true;
+                        }
+                    }
+                }
+                TableConstraint::Unique(names) => {
+                    if let [name] = &**names {
+                        if let Some(column) = columns.get_mut(*name.fragment()) {
+                            column.unique = true;
+                        }
+                    }
+                }
+                TableConstraint::ForeignKey {
+                    columns: names,
+                    references,
+                } => {
+                    if let [name] = &**names {
+                        if let Some(column) = columns.get_mut(*name.fragment()) {
+                            column.references = Some(references.into());
+                        }
+                    }
+                }
+                TableConstraint::Check(_) => {}
+            }
+        }
+        columns
+    }
+
+    /// The constraints of the table for the catalog, in the order they are declared, the
+    /// `NOT NULL` ones first, see [`TableMap::set_constraints`].
+    #[must_use]
+    pub fn table_constraints(&self) -> Vec<Constraint> {
+        let table = *self.table_name.name.fragment();
+        let names = |columns: &[RawSpan<'_>]| {
+            columns
+                .iter()
+                .map(|column| (*column.fragment()).into())
+                .collect::<Vec<Box<str>>>()
+        };
+        let foreign_key =
+            |columns: Vec<Box<str>>, references: &References<'_>| ConstraintKind::ForeignKey {
+                columns,
+                schema: references.table_name.schema_name().map(Into::into),
+                table_name: (*references.table_name.name.fragment()).into(),
+                referenced: names(&references.columns),
+            };
+        let column_map = self.column_map();
+        let mut kinds = self
+            .columns
+            .iter()
+            .filter(|column| !column_map[*column.name.fragment()].nullable)
+            .map(|column| {
+                let name = *column.name.fragment();
+                (
+                    format!("{table}_{name}_not_null"),
+                    ConstraintKind::NotNull(name.into()),
+                )
+            })
+            .collect::<Vec<_>>();
+        for column in self.columns.iter() {
+            let name = *column.name.fragment();
+            for (_, constraint) in column.constraints.iter() {
+                kinds.push(match constraint {
+                    ColumnConstraint::PrimaryKey => (
+                        format!("{table}_pkey"),
+                        ConstraintKind::PrimaryKey(vec![name.into()]),
+                    ),
+                    ColumnConstraint::Unique => (
+                        format!("{table}_{name}_key"),
+                        ConstraintKind::Unique(vec![name.into()]),
+                    ),
+                    ColumnConstraint::Check(expr) => (
+                        format!("{table}_{name}_check"),
+                        ConstraintKind::Check(expr.to_string().into()),
+                    ),
+                    ColumnConstraint::References(references) => (
+                        format!("{table}_{name}_fkey"),
+                        foreign_key(vec![name.into()], references),
+                    ),
+                    _ => continue,
+                });
+            }
+        }
+        for (_, constraint) in self.constraints.iter() {
+            let joined = constraint
+                .columns()
+                .iter()
+                .map(|column| *column.fragment())
+                .collect::<Vec<_>>()
+                .join("_");
+            kinds.push(match constraint {
+                TableConstraint::PrimaryKey(columns) => (
+                    format!("{table}_pkey"),
+                    ConstraintKind::PrimaryKey(names(columns)),
+                ),
+                TableConstraint::Unique(columns) => (
+                    format!("{table}_{joined}_key"),
+                    ConstraintKind::Unique(names(columns)),
+                ),
+                TableConstraint::Check(expr) => (
+                    format!("{table}_check"),
+                    ConstraintKind::Check(expr.to_string().into()),
+                ),
+                TableConstraint::ForeignKey {
+                    columns,
+                    references,
+                } => (
+                    format!("{table}_{joined}_fkey"),
+                    foreign_key(names(columns), references),
+                ),
+            });
+        }
+        // A name taken by an earlier constraint gets the first number that makes it unique.
+        let mut taken = HashSet::new();
+        kinds
+            .into_iter()
+            .map(|(name, kind)| {
+                let name = (0..)
+                    .map(|n| {
+                        if n == 0 {
+                            name.clone()
+                        } else {
+                            format!("{name}{n}")
+                        }
+                    })
+                    .find(|name| !taken.contains(name))
+                    .unwrap_or(name);
+                taken.insert(name.clone());
+                Constraint {
+                    name: name.into(),
+                    kind,
+                }
+            })
+            .collect()
+    }
 }
 
 impl<'a> From<RawColumn<'a>> for Column {
     fn from(value: RawColumn<'a>) -> Self {
         Self {
             name: (*value.name.fragment()).into(),
-            tp: value.tp.1,
+            tp: value.tp.1.clone(),
+            nullable: value.nullable(),
+            default: value.default().map(|(_, default)| default.clone()),
+            primary_key: value.primary_key(),
+            unique: value.unique(),
+            references: value.references().map(Into::into),
+            is_auto_increment: value.is_auto_increment(),
+        }
+    }
+}
+
+impl<'a> From<&References<'a>> for ColumnReference {
+    fn from(value: &References<'a>) -> Self {
+        Self {
+            schema: value.table_name.schema_name().map(Into::into),
+            table_name: (*value.table_name.name.fragment()).into(),
+            column: value
+                .columns
+                .first()
+                .map(|column| (*column.fragment()).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedColumnConstraint {
+    NotNull,
+    Null,
+    Default(OwnedWithSpan<Value>),
+    PrimaryKey,
+    Unique,
+    Check(OwnedExpr),
+    References(OwnedReferences),
+    AutoIncrement,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedReferences {
+    pub table_name: OwnedTableName,
+    pub columns: Box<[OwnedSpan]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedTableConstraint {
+    PrimaryKey(Box<[OwnedSpan]>),
+    Unique(Box<[OwnedSpan]>),
+    Check(OwnedExpr),
+    ForeignKey {
+        columns: Box<[OwnedSpan]>,
+        references: OwnedReferences,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedRawColumn {
+    pub name: OwnedSpan,
+    pub tp: OwnedWithSpan<SqlType>,
+    pub constraints: Box<[OwnedWithSpan<OwnedColumnConstraint>]>,
+}
+
+/// A `CREATE TABLE` statement copied out of the input, see [`Statement::to_owned`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub if_not_exists: bool,
+    pub table_name: OwnedTableName,
+    pub columns: Box<[OwnedRawColumn]>,
+    pub constraints: Box<[OwnedWithSpan<OwnedTableConstraint>]>,
+    pub compression: Compression,
+    pub engine: Engine,
+    pub partitioning: Option<Partitioning>,
+    pub bloom_filters: Box<[Box<str>]>,
+}
+
+fn owned_names(names: &[RawSpan<'_>]) -> Box<[OwnedSpan]> {
+    names.iter().copied().map(OwnedSpan::from).collect()
+}
+
+impl References<'_> {
+    pub fn to_owned(&self) -> OwnedReferences {
+        OwnedReferences {
+            table_name: self.table_name.to_owned(),
+            columns: owned_names(&self.columns),
+        }
+    }
+}
+
+impl RawColumn<'_> {
+    pub fn to_owned(&self) -> OwnedRawColumn {
+        OwnedRawColumn {
+            name: self.name.into(),
+            tp: owned_with_span(&self.tp, SqlType::clone),
+            constraints: self
+                .constraints
+                .iter()
+                .map(|constraint| {
+                    owned_with_span(constraint, |constraint| match constraint {
+                        ColumnConstraint::NotNull => OwnedColumnConstraint::NotNull,
+                        ColumnConstraint::Null => OwnedColumnConstraint::Null,
+                        ColumnConstraint::Default(value) => {
+                            OwnedColumnConstraint::Default(owned_with_span(value, Value::clone))
+                        }
+                        ColumnConstraint::PrimaryKey => OwnedColumnConstraint::PrimaryKey,
+                        ColumnConstraint::Unique => OwnedColumnConstraint::Unique,
+                        ColumnConstraint::Check(expr) => {
+                            OwnedColumnConstraint::Check(expr.to_owned())
+                        }
+                        ColumnConstraint::References(references) => {
+                            OwnedColumnConstraint::References(references.to_owned())
+                        }
+                        ColumnConstraint::AutoIncrement => OwnedColumnConstraint::AutoIncrement,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Statement<'_> {
+    /// Copies the statement out of the input, keeping the positions of its spans.
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            if_not_exists: self.if_not_exists,
+            table_name: self.table_name.to_owned(),
+            columns: self.columns.iter().map(RawColumn::to_owned).collect(),
+            constraints: self
+                .constraints
+                .iter()
+                .map(|constraint| {
+                    owned_with_span(constraint, |constraint| match constraint {
+                        TableConstraint::PrimaryKey(columns) => {
+                            OwnedTableConstraint::PrimaryKey(owned_names(columns))
+                        }
+                        TableConstraint::Unique(columns) => {
+                            OwnedTableConstraint::Unique(owned_names(columns))
+                        }
+                        TableConstraint::Check(expr) => {
+                            OwnedTableConstraint::Check(expr.to_owned())
+                        }
+                        TableConstraint::ForeignKey {
+                            columns,
+                            references,
+                        } => OwnedTableConstraint::ForeignKey {
+                            columns: owned_names(columns),
+                            references: references.to_owned(),
+                        },
+                    })
+                })
+                .collect(),
+            compression: self.compression,
+            engine: self.engine,
+            partitioning: self.partitioning.clone(),
+            bloom_filters: self.bloom_filters.clone(),
         }
     }
 }
@@ -143,7 +2028,23 @@ mod tests {
     fn test_parse_sql_type() {
         assert_eq!(
             SqlType::parse("varchar(10)".into()).unwrap().1,
-            SqlType::VarChar(10)
+            SqlType::VarChar(10, LengthUnit::Chars)
+        );
+        assert_eq!(
+            SqlType::parse("VARCHAR(10 BYTES)".into()).unwrap().1,
+            SqlType::VarChar(10, LengthUnit::Bytes)
+        );
+        assert_eq!(
+            SqlType::parse("varchar(10 chars)".into()).unwrap().1,
+            SqlType::VarChar(10, LengthUnit::Chars)
+        );
+        assert_eq!(
+            SqlType::parse("CHAR(3)".into()).unwrap().1,
+            SqlType::Char(3)
+        );
+        assert_eq!(
+            SqlType::parse("character".into()).unwrap().1,
+            SqlType::Char(1)
         );
         assert_eq!(SqlType::parse("int8".into()).unwrap().1, SqlType::I8);
         assert_eq!(SqlType::parse("int16".into()).unwrap().1, SqlType::I16);
@@ -155,6 +2056,93 @@ mod tests {
         assert_eq!(SqlType::parse("uint32".into()).unwrap().1, SqlType::U32);
         assert_eq!(SqlType::parse("uint64".into()).unwrap().1, SqlType::U64);
         assert_eq!(SqlType::parse("uint128".into()).unwrap().1, SqlType::U128);
+        assert_eq!(SqlType::parse("FLOAT32".into()).unwrap().1, SqlType::F32);
+        assert_eq!(SqlType::parse("float64".into()).unwrap().1, SqlType::F64);
+        assert_eq!(
+            SqlType::parse("decimal(10, 2)".into()).unwrap().1,
+            SqlType::Decimal {
+                precision: 10,
+                scale: 2
+            }
+        );
+        assert_eq!(
+            SqlType::parse("NUMERIC(5)".into()).unwrap().1,
+            SqlType::Decimal {
+                precision: 5,
+                scale: 0
+            }
+        );
+        assert!(SqlType::parse("decimal(40)".into()).is_err());
+        assert!(SqlType::parse("decimal(4, 5)".into()).is_err());
+        assert!(SqlType::parse("decimal(0)".into()).is_err());
+        assert_eq!(SqlType::parse("DATE".into()).unwrap().1, SqlType::Date);
+        assert_eq!(SqlType::parse("time".into()).unwrap().1, SqlType::Time);
+        assert_eq!(
+            SqlType::parse("timestamp".into()).unwrap().1,
+            SqlType::Timestamp
+        );
+        assert_eq!(
+            SqlType::parse("TIMESTAMP WITHOUT TIME ZONE".into())
+                .unwrap()
+                .1,
+            SqlType::Timestamp
+        );
+        assert_eq!(
+            SqlType::parse("timestamp with time zone".into()).unwrap().1,
+            SqlType::TimestampTz
+        );
+        assert_eq!(
+            SqlType::parse("TIMESTAMPTZ".into()).unwrap().1,
+            SqlType::TimestampTz
+        );
+        assert_eq!(
+            SqlType::parse("interval".into()).unwrap().1,
+            SqlType::Interval
+        );
+        assert_eq!(SqlType::parse("BLOB".into()).unwrap().1, SqlType::Blob);
+        assert_eq!(SqlType::parse("bytea".into()).unwrap().1, SqlType::Blob);
+        assert_eq!(SqlType::parse("JSON".into()).unwrap().1, SqlType::Json);
+    }
+
+    #[test]
+    fn test_parse_standard_type_names() {
+        let cases = [
+            ("TINYINT", SqlType::I8),
+            ("smallint", SqlType::I16),
+            ("INTEGER", SqlType::I32),
+            ("int", SqlType::I32),
+            ("BIGINT", SqlType::I64),
+            ("text", SqlType::VarChar(usize::MAX, LengthUnit::Chars)),
+            (
+                "CHARACTER VARYING(20)",
+                SqlType::VarChar(20, LengthUnit::Chars),
+            ),
+            ("char varying (5)", SqlType::VarChar(5, LengthUnit::Chars)),
+            ("INTEGER[]", SqlType::Array(Box::new(SqlType::I32))),
+        ];
+        for (input, expected) in cases {
+            let (rest, tp) = SqlType::parse(input.into()).unwrap();
+            assert_eq!((*rest.fragment(), tp), ("", expected), "{input}");
+        }
+        assert_eq!(SqlType::parse("int8".into()).unwrap().1, SqlType::I8);
+        assert!(SqlType::parse("CHARACTER VARYING".into()).is_err());
+
+        let (_, statement) = Statement::parse(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, name CHARACTER VARYING(50), bio TEXT)".into(),
+        )
+        .unwrap();
+        assert_eq!(
+            statement
+                .columns
+                .iter()
+                .map(|column| column.tp.1.clone())
+                .collect::<Vec<_>>(),
+            [
+                SqlType::I32,
+                SqlType::VarChar(50, LengthUnit::Chars),
+                SqlType::VarChar(usize::MAX, LengthUnit::Chars)
+            ]
+        );
     }
 
     fn test_case_column_parse(suffix: &str, input: &str) {
@@ -172,10 +2160,693 @@ mod tests {
         settings.bind(|| insta::assert_debug_snapshot!(value));
     }
 
+    #[test]
+    fn test_cast_kind() {
+        let decimal = |precision, scale| SqlType::Decimal { precision, scale };
+        let cases = [
+            (SqlType::I8, SqlType::I8, CastKind::Implicit),
+            (SqlType::I8, SqlType::I64, CastKind::Implicit),
+            (SqlType::U32, SqlType::I64, CastKind::Implicit),
+            (SqlType::I64, SqlType::I32, CastKind::Explicit),
+            (SqlType::I8, SqlType::U128, CastKind::Explicit),
+            (
+                SqlType::VarChar(5, LengthUnit::Chars),
+                SqlType::VarChar(10, LengthUnit::Chars),
+                CastKind::Implicit,
+            ),
+            (
+                SqlType::VarChar(10, LengthUnit::Chars),
+                SqlType::VarChar(5, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (
+                SqlType::VarChar(5, LengthUnit::Chars),
+                SqlType::Char(5),
+                CastKind::Implicit,
+            ),
+            (
+                SqlType::Char(5),
+                SqlType::VarChar(4, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (
+                SqlType::VarChar(5, LengthUnit::Bytes),
+                SqlType::VarChar(5, LengthUnit::Chars),
+                CastKind::Implicit,
+            ),
+            (
+                SqlType::VarChar(5, LengthUnit::Chars),
+                SqlType::VarChar(5, LengthUnit::Bytes),
+                CastKind::Lossy,
+            ),
+            (
+                SqlType::VarChar(5, LengthUnit::Chars),
+                SqlType::VarChar(20, LengthUnit::Bytes),
+                CastKind::Implicit,
+            ),
+            (
+                SqlType::I8,
+                SqlType::VarChar(4, LengthUnit::Bytes),
+                CastKind::Explicit,
+            ),
+            (SqlType::I8, SqlType::Char(4), CastKind::Explicit),
+            (SqlType::Char(4), SqlType::I8, CastKind::Explicit),
+            (SqlType::Char(4), SqlType::Bool, CastKind::Explicit),
+            (
+                SqlType::I8,
+                SqlType::VarChar(4, LengthUnit::Chars),
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::I64,
+                SqlType::VarChar(4, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (
+                SqlType::VarChar(4, LengthUnit::Chars),
+                SqlType::I8,
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::VarChar(4, LengthUnit::Chars),
+                SqlType::Bool,
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::Bool,
+                SqlType::VarChar(5, LengthUnit::Chars),
+                CastKind::Explicit,
+            ),
+            (SqlType::Bool, SqlType::I8, CastKind::Forbidden),
+            (SqlType::U8, SqlType::Bool, CastKind::Forbidden),
+            (SqlType::I64, SqlType::F64, CastKind::Implicit),
+            (SqlType::F32, SqlType::F64, CastKind::Implicit),
+            (SqlType::F64, SqlType::F32, CastKind::Lossy),
+            (SqlType::F64, SqlType::I128, CastKind::Lossy),
+            (
+                SqlType::F32,
+                SqlType::VarChar(14, LengthUnit::Chars),
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::F64,
+                SqlType::VarChar(14, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (
+                SqlType::VarChar(4, LengthUnit::Chars),
+                SqlType::F32,
+                CastKind::Explicit,
+            ),
+            (SqlType::F64, SqlType::Bool, CastKind::Forbidden),
+            (decimal(10, 2), decimal(12, 2), CastKind::Implicit),
+            (decimal(10, 2), decimal(12, 4), CastKind::Implicit),
+            (decimal(10, 2), decimal(10, 4), CastKind::Explicit),
+            (decimal(10, 2), decimal(10, 1), CastKind::Lossy),
+            (decimal(10, 2), SqlType::I64, CastKind::Lossy),
+            (decimal(9, 0), SqlType::I32, CastKind::Implicit),
+            (decimal(10, 0), SqlType::I32, CastKind::Explicit),
+            (SqlType::I32, decimal(12, 2), CastKind::Implicit),
+            (SqlType::I32, decimal(10, 2), CastKind::Explicit),
+            (decimal(10, 2), SqlType::F64, CastKind::Implicit),
+            (SqlType::F64, decimal(10, 2), CastKind::Lossy),
+            (decimal(10, 2), SqlType::Bool, CastKind::Forbidden),
+            (
+                SqlType::Date,
+                SqlType::VarChar(10, LengthUnit::Chars),
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::Date,
+                SqlType::VarChar(8, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (
+                SqlType::VarChar(10, LengthUnit::Chars),
+                SqlType::Date,
+                CastKind::Explicit,
+            ),
+            (SqlType::Date, SqlType::I32, CastKind::Forbidden),
+            (SqlType::I32, SqlType::Date, CastKind::Forbidden),
+            (SqlType::Date, SqlType::Timestamp, CastKind::Implicit),
+            (SqlType::Timestamp, SqlType::TimestampTz, CastKind::Implicit),
+            (SqlType::TimestampTz, SqlType::Timestamp, CastKind::Explicit),
+            (SqlType::Timestamp, SqlType::Date, CastKind::Lossy),
+            (SqlType::TimestampTz, SqlType::Time, CastKind::Lossy),
+            (SqlType::Time, SqlType::Timestamp, CastKind::Forbidden),
+            (SqlType::Date, SqlType::Time, CastKind::Forbidden),
+            (
+                SqlType::VarChar(30, LengthUnit::Chars),
+                SqlType::TimestampTz,
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::Timestamp,
+                SqlType::VarChar(26, LengthUnit::Chars),
+                CastKind::Explicit,
+            ),
+            (SqlType::Time, SqlType::I64, CastKind::Forbidden),
+            (
+                SqlType::VarChar(20, LengthUnit::Chars),
+                SqlType::Interval,
+                CastKind::Explicit,
+            ),
+            (SqlType::Interval, SqlType::Time, CastKind::Forbidden),
+            (SqlType::Interval, SqlType::I64, CastKind::Forbidden),
+            (
+                SqlType::VarChar(10, LengthUnit::Chars),
+                SqlType::Blob,
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::Blob,
+                SqlType::VarChar(100, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (SqlType::Blob, SqlType::I8, CastKind::Forbidden),
+            (SqlType::U8, SqlType::Blob, CastKind::Forbidden),
+            (
+                SqlType::VarChar(10, LengthUnit::Chars),
+                SqlType::Json,
+                CastKind::Explicit,
+            ),
+            (
+                SqlType::Json,
+                SqlType::VarChar(100, LengthUnit::Chars),
+                CastKind::Lossy,
+            ),
+            (SqlType::Json, SqlType::Blob, CastKind::Forbidden),
+        ];
+        for (from, to, expected) in cases {
+            assert_eq!(from.cast_kind(&to), expected, "{from:?} -> {to:?}");
+        }
+    }
+
     #[test]
     fn test_parse_column() {
         test_case_column_parse("col-integer", "iD int8");
         test_case_column_parse("col-str", "column_name varchar(10)");
+        test_case_column_parse("col-not-null", "id int8 NOT NULL");
+        test_case_column_parse("col-null", "name varchar(10) null");
+        test_case_column_parse("col-default", "name varchar(10) NOT NULL DEFAULT 'anon'");
+    }
+
+    #[test]
+    fn test_parse_column_invalid_default() {
+        assert!(RawColumn::parse("id int8 DEFAULT 'a'".into()).is_err());
+        assert!(RawColumn::parse("id int8 DEFAULT 1000".into()).is_err());
+        assert!(RawColumn::parse("id int8 NOT NULL DEFAULT NULL".into()).is_err());
+        assert!(RawColumn::parse("id int8 DEFAULT NULL".into()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_primary_key() {
+        test_case_statement_parse(
+            "column-primary-key",
+            "CREATE TABLE t (id int32 PRIMARY KEY, name varchar(10))",
+        );
+        test_case_statement_parse(
+            "table-primary-key",
+            "CREATE TABLE t (a int32, b int32, PRIMARY KEY (a, b))",
+        );
+
+        let statement =
+            Statement::parse("CREATE TABLE t (a int32, b int32, PRIMARY KEY (b))".into())
+                .unwrap()
+                .1;
+        let columns = statement.column_map();
+        assert!(!columns["a"].primary_key);
+        assert!(columns["b"].primary_key);
+        assert!(!columns["b"].nullable);
+
+        assert!(Statement::parse(
+            "CREATE TABLE t (a int32 PRIMARY KEY, b int32, PRIMARY KEY (b))".into()
+        )
+        .is_err());
+        assert!(Statement::parse("CREATE TABLE t (a int32, PRIMARY KEY (c))".into()).is_err());
+    }
+
+    #[test]
+    fn test_parse_unique_and_check() {
+        test_case_statement_parse(
+            "unique-check",
+            "CREATE TABLE t (a int32 UNIQUE CHECK (a > 0), b varchar(10), UNIQUE (a, b), CHECK (b <> ')' AND (a < 10)))",
+        );
+
+        let statement = Statement::parse("CREATE TABLE t (a int32, b int32, UNIQUE (b))".into())
+            .unwrap()
+            .1;
+        let columns = statement.column_map();
+        assert!(!columns["a"].unique);
+        assert!(columns["b"].unique);
+
+        let statement = Statement::parse(
+            "CREATE TABLE t (a int32 PRIMARY KEY CHECK (a > 0), b int32 NOT NULL UNIQUE, UNIQUE (a, b), CHECK (b < a), CHECK (b > 1))".into(),
+        )
+        .unwrap()
+        .1;
+        let constraints = statement
+            .table_constraints()
+            .into_iter()
+            .map(|constraint| (constraint.name, constraint.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            constraints,
+            [
+                ("t_a_not_null".into(), ConstraintKind::NotNull("a".into())),
+                ("t_b_not_null".into(), ConstraintKind::NotNull("b".into())),
+                (
+                    "t_pkey".into(),
+                    ConstraintKind::PrimaryKey(vec!["a".into()])
+                ),
+                ("t_a_check".into(), ConstraintKind::Check("a > 0".into())),
+                ("t_b_key".into(), ConstraintKind::Unique(vec!["b".into()])),
+                (
+                    "t_a_b_key".into(),
+                    ConstraintKind::Unique(vec!["a".into(), "b".into()])
+                ),
+                ("t_check".into(), ConstraintKind::Check("b < a".into())),
+                ("t_check1".into(), ConstraintKind::Check("b > 1".into())),
+            ]
+        );
+
+        assert!(Statement::parse("CREATE TABLE t (a int32, UNIQUE (c))".into()).is_err());
+        assert!(Statement::parse("CREATE TABLE t (a int32 CHECK (a > 0)".into()).is_err());
+    }
+
+    #[test]
+    fn test_parse_foreign_key() {
+        test_case_statement_parse(
+            "foreign-key",
+            "CREATE TABLE t (a int32 REFERENCES users(id), b int32, c int32, FOREIGN KEY (b, c) REFERENCES other (x, y))",
+        );
+
+        let table_map: TableMap = [(
+            "users".into(),
+            [Column::new("id", SqlType::I32)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        )]
+        .into_iter()
+        .collect();
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let statement =
+            parse("CREATE TABLE t (a int32 REFERENCES users (id), b int32 REFERENCES users)")
+                .unwrap()
+                .1;
+        let columns = statement.column_map();
+        assert_eq!(
+            columns["a"].references,
+            Some(ColumnReference {
+                schema: None,
+                table_name: "users".into(),
+                column: Some("id".into()),
+            })
+        );
+        assert_eq!(
+            columns["b"].references,
+            Some(ColumnReference {
+                schema: None,
+                table_name: "users".into(),
+                column: None,
+            })
+        );
+        assert!(
+            parse("CREATE TABLE t (id int32 PRIMARY KEY, parent int32 REFERENCES t(id))").is_ok()
+        );
+
+        let Err(nom::Err::Failure(crate::errors::RawParseError::Base { location, .. })) =
+            parse("CREATE TABLE t (a int32 REFERENCES users (name))")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "name");
+        let Err(nom::Err::Failure(crate::errors::RawParseError::Base { location, .. })) =
+            parse("CREATE TABLE t (a int32, FOREIGN KEY (a) REFERENCES groups (id))")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "groups");
+        assert!(
+            parse("CREATE TABLE t (a int32, FOREIGN KEY (a) REFERENCES users (id, id))").is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_auto_increment() {
+        test_case_column_parse("col-serial", "id SERIAL PRIMARY KEY");
+        test_case_column_parse("col-auto-increment", "id int64 AUTO_INCREMENT");
+
+        let statement = Statement::parse("CREATE TABLE t (id bigserial, name varchar(10))".into())
+            .unwrap()
+            .1;
+        let columns = statement.column_map();
+        assert_eq!(columns["id"].tp, SqlType::I64);
+        assert!(columns["id"].is_auto_increment);
+        assert!(!columns["id"].nullable);
+        assert!(!columns["name"].is_auto_increment);
+
+        assert!(RawColumn::parse("id varchar(10) AUTO_INCREMENT".into()).is_err());
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        test_case_column_parse("col-enum", "mood ENUM('sad', 'ok') DEFAULT 'ok'");
+        assert!(RawColumn::parse("mood ENUM('sad', 'ok') DEFAULT 'meh'".into()).is_err());
+        assert!(RawColumn::parse("mood ENUM('sad', 'sad')".into()).is_err());
+        assert!(RawColumn::parse("mood mood".into()).is_err());
+
+        let mut table_map = TableMap::new();
+        let mood = SqlType::Enum(["sad".into(), "ok".into()].into());
+        table_map.create_type("mood", mood.clone());
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+        let statement = parse("CREATE TABLE people (name varchar(10), feeling mood)")
+            .unwrap()
+            .1;
+        assert_eq!(statement.column_map()["feeling"].tp, mood);
+        assert!(matches!(
+            parse("CREATE TABLE people (feeling moods)"),
+            Err(nom::Err::Failure(_))
+        ));
+
+        assert_eq!(
+            mood.cast_kind(&SqlType::VarChar(3, LengthUnit::Chars)),
+            CastKind::Explicit,
+            "labels fit"
+        );
+        assert_eq!(
+            SqlType::VarChar(1, LengthUnit::Chars).cast_kind(&mood),
+            CastKind::Explicit
+        );
+        assert_eq!(mood.cast_kind(&SqlType::I8), CastKind::Forbidden);
+        assert_eq!(
+            mood.common(&SqlType::VarChar(2, LengthUnit::Chars)),
+            Some(SqlType::VarChar(3, LengthUnit::Chars))
+        );
+    }
+
+    #[test]
+    fn test_parse_array() {
+        let ints = SqlType::Array(Box::new(SqlType::I32));
+        test_case_column_parse("col-array", "scores int32[] DEFAULT '{1,2,NULL}'");
+        assert_eq!(
+            SqlType::parse("varchar(3)[][]".into()).unwrap().1,
+            SqlType::Array(Box::new(SqlType::Array(Box::new(SqlType::VarChar(
+                3,
+                LengthUnit::Chars
+            )))))
+        );
+        assert!(RawColumn::parse("scores int32[] DEFAULT ARRAY['a']".into()).is_err());
+
+        let mut table_map = TableMap::new();
+        let mood = SqlType::Enum(["sad".into(), "ok".into()].into());
+        table_map.create_type("mood", mood.clone());
+        let statement =
+            Statement::parse_with_table_map(&table_map, "CREATE TABLE days (moods mood[])".into())
+                .unwrap()
+                .1;
+        assert_eq!(
+            statement.column_map()["moods"].tp,
+            SqlType::Array(Box::new(mood))
+        );
+
+        let bigints = SqlType::Array(Box::new(SqlType::I64));
+        assert_eq!(ints.cast_kind(&bigints), CastKind::Implicit);
+        assert_eq!(bigints.cast_kind(&ints), CastKind::Explicit);
+        assert_eq!(
+            SqlType::VarChar(7, LengthUnit::Chars).cast_kind(&ints),
+            CastKind::Explicit
+        );
+        assert_eq!(
+            ints.cast_kind(&SqlType::VarChar(7, LengthUnit::Chars)),
+            CastKind::Lossy
+        );
+        assert_eq!(ints.cast_kind(&SqlType::I32), CastKind::Forbidden);
+        assert_eq!(
+            ints.common(&SqlType::Array(Box::new(SqlType::I16))),
+            Some(SqlType::Array(Box::new(SqlType::I128)))
+        );
+        assert_eq!(ints.common(&SqlType::I32), None);
+    }
+
+    #[test]
+    fn test_parse_if_not_exists() {
+        test_case_statement_parse(
+            "if-not-exists",
+            "CREATE TABLE IF NOT EXISTS users (id int32)",
+        );
+
+        let table_map: TableMap = [("users".into(), ColumnMap::new())].into_iter().collect();
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+        assert!(parse("CREATE TABLE IF NOT EXISTS users (id int32)").is_ok());
+        assert!(parse("CREATE TABLE users (id int32)").is_err());
+        assert!(parse("CREATE TABLE if_users (id int32)").is_ok());
+    }
+
+    #[test]
+    fn test_parse_compression() {
+        let parse = |input: &'static str| Statement::parse(input.into()).map(|(_, s)| s);
+        assert_eq!(
+            parse("CREATE TABLE t (id int32)").unwrap().compression,
+            Compression::None
+        );
+        assert_eq!(
+            parse("CREATE TABLE t (id int32) WITH (compression = 'lz4')")
+                .unwrap()
+                .compression,
+            Compression::Lz4
+        );
+        assert_eq!(
+            parse("CREATE TABLE t (id int32)with(COMPRESSION='Zstd')")
+                .unwrap()
+                .compression,
+            Compression::Zstd
+        );
+        let Err(nom::Err::Failure(error)) =
+            parse("CREATE TABLE t (id int32) WITH (compression = 'gzip')")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(
+            &*crate::errors::ErrorReport::from(error).message,
+            crate::errors::ParseError::UnknownCompression.to_string()
+        );
+        assert!(parse("CREATE TABLE t (id int32) WITH (fillfactor = '70')").is_err());
+    }
+
+    #[test]
+    fn test_parse_engine() {
+        let parse = |input: &'static str| Statement::parse(input.into()).map(|(_, s)| s);
+        assert_eq!(
+            parse("CREATE TABLE t (id int32)").unwrap().engine,
+            Engine::Disk
+        );
+        let statement =
+            parse("CREATE TABLE t (id int32) WITH (engine = 'Memory', compression = 'lz4')")
+                .unwrap();
+        assert_eq!(
+            (statement.engine, statement.compression),
+            (Engine::Memory, Compression::Lz4)
+        );
+        assert_eq!(
+            parse("CREATE TABLE t (id int32) WITH (engine = 'columnar')")
+                .unwrap()
+                .engine,
+            Engine::Columnar
+        );
+        let Err(nom::Err::Failure(error)) =
+            parse("CREATE TABLE t (id int32) WITH (engine = 'rocksdb')")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(
+            &*crate::errors::ErrorReport::from(error).message,
+            crate::errors::ParseError::UnknownEngine.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_bloom_filter() {
+        let parse = |input: &'static str| Statement::parse(input.into()).map(|(_, s)| s);
+        assert!(parse("CREATE TABLE t (id int32)")
+            .unwrap()
+            .bloom_filters
+            .is_empty());
+        assert_eq!(
+            parse("CREATE TABLE t (id int32, n int64) WITH (bloom_filter = 'n, id,n')")
+                .unwrap()
+                .bloom_filters,
+            ["n".into(), "id".into()].into()
+        );
+        let Err(nom::Err::Failure(error)) =
+            parse("CREATE TABLE t (id int32) WITH (bloom_filter = 'id, name')")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(
+            &*crate::errors::ErrorReport::from(error).message,
+            crate::errors::ParseError::ColumnNotFound.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_partition_by() {
+        let parse = |input: &'static str| Statement::parse(input.into()).map(|(_, s)| s);
+        let statement = parse(
+            "CREATE TABLE t (id int32, n int64) PARTITION BY RANGE (n) (\
+             PARTITION small VALUES LESS THAN (10), \
+             PARTITION large VALUES LESS THAN (MAXVALUE)) WITH (engine = 'memory')",
+        )
+        .unwrap();
+        let partitioning = statement.partitioning.unwrap();
+        assert_eq!(
+            partitioning.scheme,
+            PartitionScheme::Range(
+                [
+                    RangePartition {
+                        name: "small".into(),
+                        less_than: Some(Value::I64(10)),
+                    },
+                    RangePartition {
+                        name: "large".into(),
+                        less_than: None,
+                    },
+                ]
+                .into()
+            )
+        );
+        assert_eq!(statement.engine, Engine::Memory);
+        assert_eq!(partitioning.partition(&Value::I32(9)), Some(0));
+        assert_eq!(partitioning.partition(&Value::I64(10)), Some(1));
+        assert_eq!(partitioning.partition(&Value::Null), Some(0));
+        assert_eq!(
+            partitioning.prune(Bound::Included(&Value::I32(10)), Bound::Unbounded),
+            [1]
+        );
+        assert_eq!(
+            partitioning.prune(Bound::Unbounded, Bound::Excluded(&Value::I32(10))),
+            [0]
+        );
+        assert_eq!(
+            partitioning.prune(Bound::Unbounded, Bound::Included(&Value::I32(10))),
+            [0, 1]
+        );
+
+        let hash = parse("CREATE TABLE t (id int32) PARTITION BY HASH (id) PARTITIONS 3")
+            .unwrap()
+            .partitioning
+            .unwrap();
+        assert_eq!(hash.names(), ["p0".into(), "p1".into(), "p2".into()]);
+        // Equal values of different types are in the same partition.
+        let partition = hash.partition(&Value::I32(7)).unwrap();
+        assert_eq!(hash.partition(&Value::I64(7)), Some(partition));
+        assert_eq!(
+            hash.prune(
+                Bound::Included(&Value::I64(7)),
+                Bound::Included(&Value::I64(7))
+            ),
+            [partition]
+        );
+        assert_eq!(hash.prune(Bound::Unbounded, Bound::Unbounded), [0, 1, 2]);
+        // The partitions don't change between versions, the rows stay where they are found.
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        let values = [
+            Value::I32(1),
+            Value::I32(2),
+            Value::I64(42),
+            Value::F64(2.5),
+            Value::VarChar("alice".into()),
+            Value::Char("alice  ".into()),
+            Value::Bool(true),
+            Value::I32(3),
+            Value::VarChar("bob".into()),
+        ];
+        assert_eq!(
+            values
+                .iter()
+                .map(|value| hash.partition(value).unwrap())
+                .collect::<Vec<_>>(),
+            [2, 2, 0, 0, 0, 0, 0, 2, 0]
+        );
+
+        let error = |input: &'static str| {
+            let Err(nom::Err::Failure(error)) = parse(input) else {
+                panic!("Expected error: {input}");
+            };
+            crate::errors::ErrorReport::from(error).message
+        };
+        assert_eq!(
+            &*error("CREATE TABLE t (id int32) PARTITION BY HASH (n) PARTITIONS 2"),
+            crate::errors::ParseError::ColumnNotFound.to_string()
+        );
+        assert_eq!(
+            &*error(
+                "CREATE TABLE t (id int32) PARTITION BY RANGE (id) \
+                 (PARTITION a VALUES LESS THAN (10), PARTITION b VALUES LESS THAN (5))"
+            ),
+            crate::errors::ParseError::PartitionBoundOrder.to_string()
+        );
+        assert_eq!(
+            &*error(
+                "CREATE TABLE t (id int32) PARTITION BY RANGE (id) \
+                 (PARTITION a VALUES LESS THAN (MAXVALUE), PARTITION b VALUES LESS THAN (5))"
+            ),
+            crate::errors::ParseError::PartitionBoundOrder.to_string()
+        );
+        assert_eq!(
+            &*error(
+                "CREATE TABLE t (id int32) PARTITION BY RANGE (id) \
+                 (PARTITION a VALUES LESS THAN (1), PARTITION a VALUES LESS THAN (5))"
+            ),
+            crate::errors::ParseError::DuplicatePartition.to_string()
+        );
+        assert!(parse("CREATE TABLE t (id int32) PARTITION BY HASH (id) PARTITIONS 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        test_case_statement_parse(
+            "schema",
+            "CREATE TABLE sales.orders (id int32, user_id int32 REFERENCES public.users (id))",
+        );
+
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [Column::new("id", SqlType::I32)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        );
+        table_map.create_schema("sales");
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+        let statement = parse("CREATE TABLE sales.users (id int32 REFERENCES public.users)")
+            .unwrap()
+            .1;
+        assert_eq!(
+            statement.column_map()["id"].references,
+            Some(ColumnReference {
+                schema: Some("public".into()),
+                table_name: "users".into(),
+                column: None,
+            })
+        );
+        assert!(parse("CREATE TABLE public.users (id int32)").is_err());
+        assert!(parse("CREATE TABLE sales.t (id int32 REFERENCES sales.users)").is_err());
+        assert!(
+            parse("CREATE TABLE sales.t (id int32 PRIMARY KEY, p int32 REFERENCES sales.t)")
+                .is_ok()
+        );
+
+        let Err(nom::Err::Failure(crate::errors::RawParseError::Base { location, .. })) =
+            parse("CREATE TABLE archive.users (id int32)")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "archive");
     }
 
     #[test]
@@ -190,5 +2861,9 @@ mod tests {
             age UINT8
             )"#,
         );
+        test_case_statement_parse(
+            "quoted",
+            r#"CREATE TABLE "Order Items" (`from` int8, "Unit ""Price""" int32)"#,
+        );
     }
 }
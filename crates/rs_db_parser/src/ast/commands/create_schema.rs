@@ -0,0 +1,117 @@
+use nom::error::context;
+
+use crate::{
+    errors::{custom_failure, ParseResult, RawParseError},
+    lexer::TokenCursor,
+    parse::{OwnedSpan, Parse, RawSpan, TableMap},
+    parsers::identifier::identifier,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    pub if_not_exists: bool,
+    pub schema_name: RawSpan<'a>,
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Create Schema", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("create")?;
+            tokens.keyword("schema")?;
+            let if_not_exists = if tokens.opt_keyword("if").is_some() {
+                tokens.keyword("not")?;
+                tokens.keyword("exists")?;
+                true
+            } else {
+                false
+            };
+            let schema_name = tokens.parse(context("Schema Name", identifier))?;
+            Ok((
+                tokens.rest(),
+                Self {
+                    if_not_exists,
+                    schema_name,
+                },
+            ))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses a `CREATE SCHEMA` statement.
+    /// # Errors
+    /// Returns an error if the input is not a valid `CREATE SCHEMA` statement.
+    /// Returns an error if the schema already exists, unless `IF NOT EXISTS` was given.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the schema doesn't exist yet, unless `IF NOT EXISTS` was given.
+    /// # Errors
+    /// Returns an error if the schema already exists.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        if !self.if_not_exists && table_map.contains_schema(self.schema_name.fragment()) {
+            return Err(custom_failure(
+                self.schema_name,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    crate::errors::ParseError::SchemaAlreadyExists,
+                )),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub if_not_exists: bool,
+    pub schema_name: OwnedSpan,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            if_not_exists: self.if_not_exists,
+            schema_name: self.schema_name.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("1", "CREATE SCHEMA sales");
+        test_case("if-not-exists", "create schema if not exists sales");
+    }
+
+    #[test]
+    fn test_schema_exists() {
+        let mut table_map = TableMap::new();
+        table_map.create_schema("sales");
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        assert!(parse("CREATE SCHEMA archive").is_ok());
+        assert!(parse("CREATE SCHEMA IF NOT EXISTS sales").is_ok());
+        assert!(parse("CREATE SCHEMA public").is_err());
+        let Err(nom::Err::Failure(crate::errors::RawParseError::Base { location, .. })) =
+            parse("CREATE SCHEMA sales")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "sales");
+    }
+}
@@ -0,0 +1,116 @@
+use nom::error::context;
+
+use crate::{
+    ast::commands::create::{enum_type, SqlType},
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    lexer::TokenCursor,
+    parse::{owned_with_span, OwnedSpan, OwnedWithSpan, Parse, RawSpan, TableMap, WithSpan},
+    parsers::{identifier::identifier, parse_with_span},
+};
+
+/// `CREATE TYPE name AS ENUM ('label', ...)`, a type columns can use by name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    pub type_name: RawSpan<'a>,
+    pub tp: WithSpan<'a, SqlType>,
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Create Type", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("create")?;
+            tokens.keyword("type")?;
+            let type_name = tokens.parse(context("Type Name", identifier))?;
+            tokens.keyword("as")?;
+            let tp = tokens.parse(|i| parse_with_span(i, enum_type))?;
+            Ok((tokens.rest(), Self { type_name, tp }))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses a `CREATE TYPE` statement.
+    /// # Errors
+    /// Returns an error if the input is not a valid `CREATE TYPE` statement.
+    /// Returns an error if the type already exists.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the type doesn't exist yet.
+    /// # Errors
+    /// Returns an error if the type already exists.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        if table_map.get_type(self.type_name.fragment()).is_some() {
+            return Err(custom_failure(
+                self.type_name,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    ParseError::TypeAlreadyExists,
+                )),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub type_name: OwnedSpan,
+    pub tp: OwnedWithSpan<SqlType>,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            type_name: self.type_name.into(),
+            tp: owned_with_span(&self.tp, SqlType::clone),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("1", "CREATE TYPE mood AS ENUM ('sad', 'ok', 'happy')");
+    }
+
+    #[test]
+    fn test_invalid_statement() {
+        let parse = |input: &'static str| Statement::parse(input.into());
+        assert!(parse("CREATE TYPE mood AS ENUM ()").is_err());
+        assert!(parse("CREATE TYPE mood AS INT8").is_err());
+        assert!(matches!(
+            parse("CREATE TYPE mood AS ENUM ('sad', 'ok', 'sad')"),
+            Err(nom::Err::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn test_type_exists() {
+        let mut table_map = TableMap::new();
+        table_map.create_type("mood", SqlType::Enum(["sad".into()].into()));
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        assert!(parse("CREATE TYPE color AS ENUM ('red')").is_ok());
+        let Err(nom::Err::Failure(crate::errors::RawParseError::Base { location, .. })) =
+            parse("CREATE TYPE mood AS ENUM ('ok')")
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "mood");
+    }
+}
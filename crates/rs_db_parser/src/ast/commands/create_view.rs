@@ -0,0 +1,234 @@
+use nom::error::context;
+
+use crate::{
+    ast::{
+        commands::select,
+        table::{OwnedTableName, TableName},
+    },
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    lexer::TokenCursor,
+    parse::{owned_with_span, OwnedWithSpan, Parse, RawSpan, TableMap, View, WithSpan},
+    parsers::parse_with_span,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    pub view_name: TableName<'a>,
+    pub query: WithSpan<'a, select::Statement<'a>>,
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Create View", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("create")?;
+            tokens.keyword("view")?;
+            let view_name = tokens.parse(context("View Name", TableName::parse))?;
+            tokens.keyword("as")?;
+            let query = tokens.parse(|i| parse_with_span(i, select::Statement::parse))?;
+            Ok((tokens.rest(), Self { view_name, query }))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses a `CREATE VIEW` statement, checking the query against `table_map`.
+    /// # Errors
+    /// Returns an error if the input is not a valid `CREATE VIEW` statement.
+    /// Returns an error if the name is already taken by a table.
+    /// Returns an error pointing at the first unknown table or column of the query.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the view against `table_map`.
+    /// # Errors
+    /// Returns an error if the name is already taken by a table.
+    /// Returns an error pointing at the first unknown table or column of the query.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        self.view(table_map)?;
+        Ok(self)
+    }
+
+    /// Builds the catalog entry of the view.
+    /// # Errors
+    /// Returns an error if the schema doesn't exist or the name is already taken by a table
+    /// or a view.
+    /// Returns an error pointing at the first unknown table or column of the query.
+    pub fn view(&self, table_map: &TableMap) -> Result<View, nom::Err<RawParseError<'a>>> {
+        match self.view_name.lookup(table_map) {
+            Ok(_) => {
+                return Err(custom_failure(
+                    self.view_name.name,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(
+                        ParseError::TableAlreadyExists,
+                    )),
+                ));
+            }
+            Err((span, error @ ParseError::SchemaNotFound)) => {
+                return Err(custom_failure(
+                    span,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+                ));
+            }
+            Err(_) => {}
+        }
+        if table_map
+            .view(self.view_name.schema_name(), self.view_name.name.fragment())
+            .is_some()
+        {
+            return Err(custom_failure(
+                self.view_name.name,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    ParseError::TableAlreadyExists,
+                )),
+            ));
+        }
+        let (query_span, query) = &self.query;
+        Ok(View {
+            query: query_span.fragment().trim().into(),
+            columns: query
+                .columns(table_map)?
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub view_name: OwnedTableName,
+    pub query: OwnedWithSpan<select::OwnedStatement>,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            view_name: self.view_name.to_owned(),
+            query: owned_with_span(&self.query, select::Statement::to_owned),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::ast::commands::create::{Column, LengthUnit, SqlType};
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        table_map
+    }
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("1", "CREATE VIEW user_names AS SELECT name FROM users");
+    }
+
+    #[test]
+    fn test_view() {
+        let table_map = get_table_map();
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            "create view user_names as select name from users".into(),
+        )
+        .unwrap();
+        let view = statement.view(&table_map).unwrap();
+        assert_eq!(&*view.query, "select name from users");
+        assert_eq!(view.columns.len(), 1);
+        assert_eq!(
+            view.columns["name"].tp,
+            SqlType::VarChar(10, LengthUnit::Chars)
+        );
+
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "CREATE VIEW v AS SELECT age FROM users".into()
+        )
+        .is_err());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "CREATE VIEW users AS SELECT id FROM users".into()
+        )
+        .is_err());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "CREATE VIEW sales.users AS SELECT id FROM public.users".into()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_select_from_view() {
+        let mut table_map = get_table_map();
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            "CREATE VIEW named AS SELECT id, name AS label FROM users".into(),
+        )
+        .unwrap();
+        let view = statement.view(&table_map).unwrap();
+        assert!(table_map.create_view(None, "named", view.clone()));
+        assert!(!table_map.create_view(None, "named", view.clone()));
+        assert!(!table_map.create_view(None, "users", view));
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "CREATE VIEW named AS SELECT id FROM users".into()
+        )
+        .is_err());
+
+        let (_, select) = select::Statement::parse_with_table_map(
+            &table_map,
+            "SELECT * FROM named AS n JOIN users ON n.id = users.id WHERE label = 'a'".into(),
+        )
+        .unwrap();
+        let columns = select
+            .columns(&table_map)
+            .unwrap()
+            .into_iter()
+            .map(|column| column.name)
+            .collect::<Vec<_>>();
+        assert_eq!(columns, ["id", "label", "id", "name"].map(Box::from));
+        assert!(select::Statement::parse_with_table_map(
+            &table_map,
+            "SELECT name FROM named".into()
+        )
+        .is_err());
+
+        // The query is expanded against the catalog as it is now.
+        table_map.insert(
+            "users".into(),
+            [Column::new("id", SqlType::I32)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        );
+        let Err(nom::Err::Failure(error)) =
+            select::Statement::parse_with_table_map(&table_map, "SELECT id FROM named".into())
+        else {
+            panic!("the view still binds");
+        };
+        assert!(error.to_string().contains("View query"), "{error}");
+    }
+}
@@ -0,0 +1,135 @@
+use nom::error::context;
+
+use crate::{
+    ast::table::{OwnedTableName, TableName},
+    errors::{custom_error, ParseResult, RawParseError},
+    lexer::TokenCursor,
+    parse::{Parse, RawSpan, TableMap},
+    parsers::comma_sep,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    pub if_exists: bool,
+    pub table_names: Box<[TableName<'a>]>,
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Drop Table", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("drop")?;
+            tokens.keyword("table")?;
+            let if_exists = if tokens.opt_keyword("if").is_some() {
+                tokens.keyword("exists")?;
+                true
+            } else {
+                false
+            };
+            let table_names = tokens.parse(context("Table Names", comma_sep(TableName::parse)))?;
+            Ok((
+                tokens.rest(),
+                Self {
+                    if_exists,
+                    table_names: table_names.into(),
+                },
+            ))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses a `DROP TABLE` statement and checks that every table exists.
+    /// # Errors
+    /// Returns an error if the input is not a valid `DROP TABLE` statement.
+    /// Returns an error pointing at the first unknown schema or table, unless `IF EXISTS`
+    /// was given.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks every table exists, unless `IF EXISTS` was given.
+    /// # Errors
+    /// Returns an error pointing at the first unknown schema or table.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        if !self.if_exists {
+            if let Some((span, error)) = self
+                .table_names
+                .iter()
+                .find_map(|name| name.lookup(table_map).err())
+            {
+                return Err(custom_error(
+                    span,
+                    nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+                ));
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub if_exists: bool,
+    pub table_names: Box<[OwnedTableName]>,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            if_exists: self.if_exists,
+            table_names: self.table_names.iter().map(TableName::to_owned).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("1", "DROP TABLE table_name");
+        test_case("if-exists", "drop table if exists t1, t2");
+        test_case("schema", "DROP TABLE sales.orders");
+    }
+
+    #[test]
+    fn test_table_not_found() {
+        let table_map: TableMap = [("t1".into(), crate::parse::ColumnMap::new())]
+            .into_iter()
+            .collect();
+
+        assert!(Statement::parse_with_table_map(&table_map, "DROP TABLE t1".into()).is_ok());
+        assert!(
+            Statement::parse_with_table_map(&table_map, "DROP TABLE IF EXISTS t1, t2".into())
+                .is_ok()
+        );
+
+        let Err(nom::Err::Error(crate::errors::RawParseError::Base { location, .. })) =
+            Statement::parse_with_table_map(&table_map, "DROP TABLE t1, t2".into())
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "t2");
+        assert_eq!(location.location_offset(), 15);
+
+        assert!(Statement::parse_with_table_map(&table_map, "DROP TABLE public.t1".into()).is_ok());
+        let Err(nom::Err::Error(crate::errors::RawParseError::Base { location, .. })) =
+            Statement::parse_with_table_map(&table_map, "DROP TABLE sales.t1".into())
+        else {
+            panic!("Expected error");
+        };
+        assert_eq!(*location.fragment(), "sales");
+    }
+}
@@ -0,0 +1,166 @@
+use nom::error::context;
+
+use crate::{
+    ast::statement,
+    errors::{ParseResult, RawParseError},
+    lexer::{cut, TokenCursor},
+    parse::{Parse, RawSpan, TableMap},
+    parsers::limits::DepthGuard,
+};
+
+/// How [`Statement`] renders the plan.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplainFormat {
+    /// An indented tree, a line per node.
+    #[default]
+    Text,
+    Json,
+}
+
+/// `EXPLAIN [ANALYZE] [FORMAT {TEXT | JSON}] statement`, describes the plan the statement
+/// runs with instead of running it. With `ANALYZE` the statement runs, and each node of the
+/// plan tells the rows it produced and the time it took.
+#[derive(Debug, Clone)]
+pub struct Statement<'a> {
+    pub analyze: bool,
+    pub format: ExplainFormat,
+    pub statement: Box<statement::Statement<'a>>,
+}
+
+/// The `EXPLAIN` keyword and the options that follow it.
+fn options<'a>(
+    tokens: &mut TokenCursor<'a>,
+) -> Result<(bool, ExplainFormat), nom::Err<RawParseError<'a>>> {
+    tokens.keyword("explain")?;
+    let analyze = tokens.opt_keyword("analyze").is_some();
+    let format = if tokens.opt_keyword("format").is_some() {
+        cut(tokens.context("Explain Format", |tokens| {
+            if tokens.opt_keyword("text").is_some() {
+                Ok(ExplainFormat::Text)
+            } else if tokens.opt_keyword("json").is_some() {
+                Ok(ExplainFormat::Json)
+            } else {
+                Err(tokens.unexpected("TEXT or JSON"))
+            }
+        }))?
+    } else {
+        ExplainFormat::default()
+    };
+    Ok((analyze, format))
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    /// Parses an `EXPLAIN` without a catalog, see [`statement::Statement::parse`].
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        // The statement explained may be an `EXPLAIN` too, each one nests a level deeper.
+        let _depth = DepthGuard::enter(input)?;
+        context("Explain", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let (analyze, format) = options(&mut tokens)?;
+            let statement = tokens.parse(statement::Statement::parse)?;
+            Ok((
+                tokens.rest(),
+                Self {
+                    analyze,
+                    format,
+                    statement: Box::new(statement),
+                },
+            ))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses an `EXPLAIN`, resolving the statement it explains against `table_map`.
+    /// # Errors
+    /// Returns an error if the input is not a valid `EXPLAIN`, or the error
+    /// [`statement::Statement::parse_with_table_map`] returns for the statement.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let _depth = DepthGuard::enter(input)?;
+        context("Explain", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let (analyze, format) = options(&mut tokens)?;
+            let statement =
+                tokens.parse(|i| statement::Statement::parse_with_table_map(table_map, i))?;
+            Ok((
+                tokens.rest(),
+                Self {
+                    analyze,
+                    format,
+                    statement: Box::new(statement),
+                },
+            ))
+        })(input)
+    }
+
+    /// Resolves the statement it explains, see [`statement::Statement::bind`].
+    /// # Errors
+    /// Returns the error [`statement::Statement::bind`] returns.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        Ok(Self {
+            statement: Box::new(self.statement.bind(table_map)?),
+            ..self
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub analyze: bool,
+    pub format: ExplainFormat,
+    pub statement: Box<statement::OwnedStatement>,
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            analyze: self.analyze,
+            format: self.format,
+            statement: Box::new(statement::Statement::to_owned(&self.statement)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::ast::commands::create::{Column, SqlType};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_with_table_map() {
+        let mut table_map = TableMap::new();
+        let column = Column::new("id", SqlType::I32);
+        table_map.insert("users".into(), [(column.name.clone(), column)].into());
+        let parse = |input: &'static str| {
+            Statement::parse_with_table_map(&table_map, input.into())
+                .map(|(_, statement)| (statement.analyze, statement.format, statement.to_string()))
+        };
+        assert_eq!(
+            parse("explain select id from users").unwrap(),
+            (
+                false,
+                ExplainFormat::Text,
+                "EXPLAIN SELECT id FROM users".into()
+            )
+        );
+        assert_eq!(
+            parse("EXPLAIN ANALYZE FORMAT json\n  SELECT * FROM users").unwrap(),
+            (
+                true,
+                ExplainFormat::Json,
+                "EXPLAIN ANALYZE FORMAT JSON SELECT * FROM users".into()
+            )
+        );
+        assert!(matches!(
+            parse("EXPLAIN FORMAT yaml SELECT * FROM users"),
+            Err(nom::Err::Failure(_))
+        ));
+        // The statement is bound like any other.
+        assert!(parse("EXPLAIN SELECT missing FROM users").is_err());
+    }
+}
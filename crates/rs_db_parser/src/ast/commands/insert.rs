@@ -1,26 +1,88 @@
-use nom::{
-    character::complete::{char, multispace0, multispace1},
-    combinator::map_opt,
-    error::context,
-    sequence::{delimited, preceded, terminated, tuple},
-};
-use nom_supreme::tag::complete::tag_no_case;
+use std::collections::HashSet;
+
+use nom::{combinator::map_opt, error::context};
 
 use crate::{
-    errors::{custom_error, ParseResult},
-    parse::{ColumnMap, RawSpan, TableMap, WithSpan},
-    parsers::row::RowParser,
-    parsers::{comma_sep, identifier::identifier},
+    ast::{
+        commands::{
+            create::{CastKind, Column},
+            select::{projection_columns, OwnedProjection, Projection},
+        },
+        expr::{parameter_indexes, ColumnRef, Expr, OwnedColumnRef, OwnedExpr},
+        table::{push_table, OwnedTableName, Scope, TableName},
+    },
+    errors::{custom_error, custom_failure, ParseError, ParseResult, RawParseError},
+    functions::FunctionRegistry,
+    lexer::{cut, TokenCursor},
+    parse::{
+        owned_with_span, ColumnMap, OwnedSpan, OwnedWithSpan, Parse, RawSpan, Span, TableMap,
+        WithSpan,
+    },
+    parsers::row::{bind_value, parse_row_value, OwnedRowValue, RowParser, RowValue},
+    parsers::{comma_sep, identifier::identifier, limits::check_insert_values, parse_with_span},
     value::Value,
 };
 
 #[derive(Debug, Clone, Hash)]
-pub struct Statement<'a> {
-    pub table_name: RawSpan<'a>,
+pub struct Row<'a> {
+    pub span: RawSpan<'a>,
+    /// The name of each column with its value, in the order of [`Statement::columns`].
     pub values: Box<[(RawSpan<'a>, WithSpan<'a, Value>)]>,
 }
 
-impl<'a> Statement<'a> {
+/// `column = expr` in `ON CONFLICT ... DO UPDATE SET`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Assignment<'a> {
+    pub column: RawSpan<'a>,
+    pub value: Expr<'a>,
+}
+
+/// What to do with a row that conflicts with an existing one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConflictAction<'a> {
+    /// `DO NOTHING`, the row is skipped.
+    Nothing,
+    /// `DO UPDATE SET ...`, the existing row is updated instead. The values of the row that
+    /// was skipped are read through the `excluded` table.
+    Update(Box<[Assignment<'a>]>),
+}
+
+/// `ON CONFLICT [(column, ...)] DO ...`, the clause that turns an `INSERT` into an upsert.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnConflict<'a> {
+    /// The columns whose values conflict, empty when any conflict is handled.
+    pub target: Box<[RawSpan<'a>]>,
+    pub action: ConflictAction<'a>,
+}
+
+/// The values of a row, in the order of the columns, with the span of the row.
+pub type UnboundRow<'a> = WithSpan<'a, Box<[WithSpan<'a, RowValue<'a>>]>>;
+
+/// An `INSERT` parsed without the catalog, its values are not yet given to the columns of
+/// the table, see [`UnboundStatement::bind`].
+#[derive(Debug, Clone, Hash)]
+pub struct UnboundStatement<'a> {
+    pub table_name: TableName<'a>,
+    /// The columns given values, with the span of the list.
+    pub columns: WithSpan<'a, Vec<ColumnRef<'a>>>,
+    pub rows: Box<[UnboundRow<'a>]>,
+    pub on_conflict: Option<WithSpan<'a, OnConflict<'a>>>,
+    pub returning: Box<[Projection<'a>]>,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct Statement<'a> {
+    pub table_name: TableName<'a>,
+    /// The columns given values, in the order they are listed.
+    pub columns: Box<[RawSpan<'a>]>,
+    pub rows: Box<[Row<'a>]>,
+    pub on_conflict: Option<OnConflict<'a>>,
+    /// The `RETURNING` list, the values of the inserted rows reported back. Empty when the
+    /// clause is missing.
+    pub returning: Box<[Projection<'a>]>,
+}
+
+impl<'a> Row<'a> {
     #[must_use]
     pub fn len(&self) -> usize {
         self.values.iter().map(|(_k, (_, v))| v.len()).sum()
@@ -30,33 +92,241 @@ impl<'a> Statement<'a> {
     pub fn is_empty(&self) -> bool {
         self.values.is_empty()
     }
+
+    /// The values with the index of their column in [`Statement::columns`].
+    pub fn column_values(&self) -> impl Iterator<Item = (usize, &Value)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(index, (_, (_, value)))| (index, value))
+    }
 }
 
-fn parse_values<'a>(
-    columns: &'a ColumnMap,
-    input: RawSpan<'a>,
-) -> ParseResult<'a, Vec<(RawSpan<'a>, WithSpan<'a, Value>)>> {
-    let (input1, value_names): (RawSpan, Vec<RawSpan>) = context(
-        "Column Definitions",
-        delimited(
-            multispace1,
-            delimited(
-                char('('),
-                delimited(
-                    multispace0,
-                    context("Column Names", comma_sep(identifier)),
-                    multispace0,
-                ),
-                char(')'),
-            ),
-            delimited(multispace1, tag_no_case("values"), multispace1),
-        ),
-    )(input)?;
+impl<'a> Statement<'a> {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.rows.iter().map(Row::len).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl<'a> Parse<'a> for Assignment<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Assignment", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let column = tokens.parse(context("Column Name", identifier))?;
+            tokens.symbol("=")?;
+            let value = cut(tokens.parse(Expr::parse))?;
+            Ok((tokens.rest(), Self { column, value }))
+        })(input)
+    }
+}
+
+impl<'a> Parse<'a> for OnConflict<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("On Conflict", |input| {
+            let mut tokens = TokenCursor::new(input);
+            tokens.keyword("on")?;
+            tokens.keyword("conflict")?;
+            let target = if tokens.opt_symbol("(").is_some() {
+                let target = cut(tokens.parse(context("Conflict Target", comma_sep(identifier))))?;
+                cut(tokens.symbol(")"))?;
+                target
+            } else {
+                Vec::new()
+            };
+            cut(tokens.keyword("do"))?;
+            let action = if tokens.opt_keyword("nothing").is_some() {
+                ConflictAction::Nothing
+            } else if tokens.opt_keyword("update").is_some() {
+                cut(tokens.keyword("set"))?;
+                let assignments = cut(tokens.parse(comma_sep(Assignment::parse)))?;
+                ConflictAction::Update(assignments.into())
+            } else {
+                cut(Err(tokens.unexpected("NOTHING or UPDATE")))?
+            };
+            Ok((
+                tokens.rest(),
+                Self {
+                    target: target.into(),
+                    action,
+                },
+            ))
+        })(input)
+    }
+}
+
+impl<'a> OnConflict<'a> {
+    /// Checks the clause against the columns of the table the rows are inserted into.
+    /// # Errors
+    /// Returns an error pointing at the clause if `DO UPDATE` has no conflict target.
+    /// Returns an error pointing at an unknown column, or at the target if it is not the
+    /// primary key or a unique column.
+    /// Returns an error pointing at an assigned value that doesn't resolve or doesn't fit
+    /// its column.
+    fn check(
+        &self,
+        span: RawSpan<'a>,
+        table_name: TableName<'a>,
+        columns: &ColumnMap,
+    ) -> Result<(), nom::Err<RawParseError<'a>>> {
+        let failure = |span, error| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        let column = |name: &RawSpan<'a>| {
+            columns
+                .get(*name.fragment())
+                .ok_or_else(|| failure(*name, ParseError::ColumnNotFound))
+        };
+        for name in self.target.iter() {
+            column(name)?;
+        }
+        if let Some(first) = self.target.first() {
+            // Only single column `UNIQUE` constraints live in the catalog.
+            let primary_key = columns
+                .values()
+                .filter(|column| column.primary_key)
+                .map(|column| &*column.name)
+                .collect::<HashSet<_>>();
+            let target = self
+                .target
+                .iter()
+                .map(|name| *name.fragment())
+                .collect::<HashSet<_>>();
+            let unique = match &*self.target {
+                [name] => column(name)?.unique,
+                _ => false,
+            };
+            if !unique && (primary_key.is_empty() || target != primary_key) {
+                return Err(failure(*first, ParseError::ConflictTargetNotUnique));
+            }
+        }
+
+        let ConflictAction::Update(assignments) = &self.action else {
+            return Ok(());
+        };
+        if self.target.is_empty() {
+            return Err(failure(span, ParseError::ConflictTargetRequired));
+        }
+        let mut tables = vec![];
+        push_table(&mut tables, table_name.name, columns)?;
+        push_table(&mut tables, RawSpan::new("excluded"), columns)?;
+        let scope = Scope::new(tables, &HashSet::new());
+        for Assignment {
+            column: name,
+            value,
+        } in assignments.iter()
+        {
+            let column = column(name)?;
+            for column_ref in value.columns() {
+                scope.resolve(&column_ref)?;
+            }
+            if let Some(tp) = value.result_type(&scope.columns, FunctionRegistry::builtins())? {
+                if tp.cast_kind(&column.tp) != CastKind::Implicit {
+                    return Err(failure(value.span(), ParseError::AssignmentType));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a single `( ... )` value tuple, checking it against the declared columns.
+fn parse_row<'a>(row_parser: &RowParser<'a, '_>, input: RawSpan<'a>) -> ParseResult<'a, Row<'a>> {
+    let mut row_parser = row_parser.clone();
+    let mut tokens = TokenCursor::new(input);
+    let (span, values) = tokens.with_span(|tokens| {
+        tokens.symbol("(")?;
+        let values = cut(tokens.parse(context("Row Values", comma_sep(|i| row_parser.parse(i)))))?;
+        cut(tokens.symbol(")"))?;
+        Ok(values)
+    })?;
+    let input = tokens.rest();
+
+    row_parser.next_column().map_or_else(
+        || {
+            Ok((
+                input,
+                Row {
+                    span,
+                    values: values.into(),
+                },
+            ))
+        },
+        |(name, _)| {
+            Err(custom_failure(
+                name,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    crate::errors::ParseError::ColumnNotUsed,
+                )),
+            ))
+        },
+    )
+}
+
+/// Parses the list of columns given values and the `VALUES` keyword.
+fn column_list<'a>(
+    tokens: &mut TokenCursor<'a>,
+) -> Result<WithSpan<'a, Vec<ColumnRef<'a>>>, nom::Err<RawParseError<'a>>> {
+    tokens.context("Column Definitions", |tokens| {
+        let columns = tokens.with_span(|tokens| {
+            tokens.symbol("(")?;
+            let columns = tokens.parse(context("Column Names", comma_sep(ColumnRef::parse)))?;
+            tokens.symbol(")")?;
+            Ok(columns)
+        })?;
+        tokens.keyword("values")?;
+        Ok(columns)
+    })
+}
+
+/// Parses the `RETURNING` clause, if any.
+fn returning<'a>(
+    tokens: &mut TokenCursor<'a>,
+) -> Result<Vec<Projection<'a>>, nom::Err<RawParseError<'a>>> {
+    if tokens.opt_keyword("returning").is_some() {
+        cut(tokens.parse(context("Returning", comma_sep(Projection::parse))))
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Looks up the columns given values in `columns`, the columns of the table.
+/// # Errors
+/// Returns an error pointing at a column qualified with another table, or at an unknown
+/// column.
+/// Returns an error pointing at the column list if a `NOT NULL` column without a default
+/// is not given a value.
+fn resolve_columns<'a, 'c>(
+    table_name: TableName<'a>,
+    columns: &'c ColumnMap,
+    (names_span, column_refs): &WithSpan<'a, Vec<ColumnRef<'a>>>,
+) -> Result<Vec<(RawSpan<'a>, &'c Column)>, nom::Err<RawParseError<'a>>> {
+    // The columns may be qualified with the name of the table, `INSERT INTO t (t.a)`.
+    if let Some(qualifier) = column_refs
+        .iter()
+        .filter_map(|column| column.qualifier)
+        .find(|qualifier| qualifier.fragment() != table_name.name.fragment())
+    {
+        return Err(custom_error(
+            qualifier,
+            nom_supreme::error::BaseErrorKind::External(Box::new(
+                crate::errors::ParseError::TableNotFound,
+            )),
+        ));
+    }
 
     let mut columns_found = vec![];
-    for name in &value_names {
+    for ColumnRef { name, .. } in column_refs {
         if let Some(column) = columns.get(*name.fragment()) {
-            columns_found.insert(0, (*name, column));
+            columns_found.push((*name, column));
         } else {
             return Err(custom_error(
                 *name,
@@ -67,79 +337,402 @@ fn parse_values<'a>(
         }
     }
 
-    let mut row_parser = RowParser::new(columns_found);
+    if let Some(column) = columns.values().find(|column| {
+        !column.nullable
+            && column.default.is_none()
+            && !column.is_auto_increment
+            && !column_refs
+                .iter()
+                .any(|column_ref| *column_ref.name.fragment() == &*column.name)
+    }) {
+        return Err(custom_error(
+            *names_span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(
+                crate::errors::ParseError::MissingNotNullColumn(column.name.clone()),
+            )),
+        ));
+    }
+    Ok(columns_found)
+}
 
-    let (input2, values) = context(
+fn parse_values<'a>(
+    table_name: TableName<'a>,
+    columns: &ColumnMap,
+    tokens: &mut TokenCursor<'a>,
+) -> Result<(Vec<RawSpan<'a>>, Vec<Row<'a>>), nom::Err<RawParseError<'a>>> {
+    let column_list = column_list(tokens)?;
+    let columns_found = resolve_columns(table_name, columns, &column_list)?;
+    let names = columns_found.iter().map(|(name, _)| *name).collect();
+    let row_parser = RowParser::new(columns_found);
+
+    let mut values = 0;
+    let rows = tokens.parse(context(
         "Column Values",
-        terminated(
-            delimited(
-                char('('),
-                delimited(
-                    multispace0,
-                    context("Column Names", comma_sep(|i| row_parser.parse(i))),
-                    multispace0,
-                ),
-                char(')'),
-            ),
-            multispace0,
-        ),
-    )(input1)?;
-
-    row_parser.pop().map_or_else(
-        || Ok((input2, values)),
-        |(name, _)| {
-            Err(custom_error(
-                name,
-                nom_supreme::error::BaseErrorKind::External(Box::new(
-                    crate::errors::ParseError::ColumnNotUsed,
-                )),
-            ))
-        },
-    )
+        comma_sep(|i| {
+            let (rest, row) = parse_row(&row_parser, i)?;
+            values += row.values.len();
+            check_insert_values(row.span, values)?;
+            Ok((rest, row))
+        }),
+    ))?;
+    Ok((names, rows))
 }
 
 impl<'a> Statement<'a> {
+    /// Replaces the parameter placeholders of the rows with `values`, converted to the types
+    /// of their columns.
+    /// # Errors
+    /// Returns an error pointing at a placeholder without a value, or at the first one when
+    /// `?` and `$n` are mixed, or at the table when there are more values than placeholders.
+    /// Returns an error pointing at the first value that doesn't fit its column.
+    pub fn bind_parameters(
+        &self,
+        table_map: &TableMap,
+        values: &[Value],
+    ) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        let failure = |span, error: Box<dyn std::error::Error + Send + Sync>| {
+            custom_failure(span, nom_supreme::error::BaseErrorKind::External(error))
+        };
+        let columns = self
+            .table_name
+            .lookup(table_map)
+            .map_err(|(span, error)| failure(span, Box::new(error)))?;
+        let mut statement = self.clone();
+        let mut parameters = statement
+            .rows
+            .iter_mut()
+            .flat_map(|row| row.values.iter_mut())
+            .filter(|(_, (_, value))| matches!(value, Value::Parameter(_)))
+            .collect::<Vec<_>>();
+        parameters.sort_by_key(|(_, (span, _))| span.location_offset());
+        let placeholders = parameters
+            .iter()
+            .filter_map(|(_, (span, value))| match value {
+                Value::Parameter(n) => Some((*span, *n)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let indexes = parameter_indexes(&placeholders, values.len(), self.table_name.name)?;
+        for ((name, (span, value)), index) in parameters.into_iter().zip(indexes) {
+            let column = columns
+                .get(*name.fragment())
+                .ok_or_else(|| failure(*name, Box::new(ParseError::ColumnNotFound)))?;
+            let bound = values[index]
+                .coerce(column.tp.clone())
+                .map_err(|error| failure(*span, Box::new(error)))?;
+            if bound.is_null() && !column.nullable {
+                return Err(failure(*span, Box::new(ParseError::NullNotAllowed)));
+            }
+            *value = bound;
+        }
+        Ok(statement)
+    }
+
+    /// The columns of the rows reported by `RETURNING`, resolved against the table.
+    /// # Errors
+    /// Returns an error pointing at an unknown table or column, or at an aggregate, which
+    /// can't be computed for each inserted row.
+    pub fn returning_columns(
+        &self,
+        table_map: &TableMap,
+    ) -> Result<Vec<Column>, nom::Err<RawParseError<'a>>> {
+        let failure = |span, error| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        let columns = self
+            .table_name
+            .lookup(table_map)
+            .map_err(|(span, error)| failure(span, error))?;
+        if let Some(aggregate) = self
+            .returning
+            .iter()
+            .filter_map(|projection| match projection {
+                Projection::Expr {
+                    expr: (_, expr), ..
+                } => expr.aggregates().first().copied(),
+                Projection::Wildcard(_) | Projection::Column { .. } => None,
+            })
+            .next()
+        {
+            return Err(failure(aggregate.span(), ParseError::AggregateNotAllowed));
+        }
+        let mut tables = vec![];
+        push_table(&mut tables, self.table_name.name, columns)?;
+        projection_columns(&self.returning, &Scope::new(tables, &HashSet::new()))
+    }
+
     /// Parses an `INSERT` statement.
     /// # Errors
     /// Returns an error if the input is not a valid `INSERT` statement.
-    pub fn parse_with_table_map(
-        table_map: &'a TableMap,
-        input: RawSpan<'a>,
-    ) -> ParseResult<'a, Self> {
-        let (input, (_, _, (table_name, columns))) = context(
-            "Insert Statement",
-            tuple((
-                tag_no_case("insert"),
-                preceded(multispace1, tag_no_case("into")),
-                preceded(
-                    multispace1,
-                    map_opt(context("Table Name", identifier), |table_name| {
-                        let columns = table_map.get(*table_name.fragment())?;
-                        Some((table_name, columns))
-                    }),
-                ),
-            )),
-        )(input)?;
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let (table_name, columns, names, rows) = tokens.context("Insert Statement", |tokens| {
+            tokens.keyword("insert")?;
+            tokens.keyword("into")?;
+            let (table_name, columns) = tokens.parse(map_opt(TableName::parse, |table_name| {
+                let columns = table_name.lookup(table_map).ok()?;
+                Some((table_name, columns))
+            }))?;
+            let (names, rows) = parse_values(table_name, columns, tokens)?;
+            Ok((table_name, columns, names, rows))
+        })?;
+        let on_conflict = tokens.opt_parse(|i| parse_with_span(i, OnConflict::parse))?;
+        if let Some((span, on_conflict)) = &on_conflict {
+            on_conflict.check(*span, table_name, columns)?;
+        }
+        let returning = returning(&mut tokens)?;
 
-        let (input, values) = context("Insert Statement", |i| parse_values(columns, i))(input)?;
+        let statement = Self {
+            table_name,
+            columns: names.into(),
+            rows: rows.into(),
+            on_conflict: on_conflict.map(|(_, on_conflict)| on_conflict),
+            returning: returning.into(),
+        };
+        statement.returning_columns(table_map)?;
+        Ok((tokens.rest(), statement))
+    }
+}
 
+impl<'a> Parse<'a> for UnboundStatement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let (table_name, columns, rows) = tokens.context("Insert Statement", |tokens| {
+            tokens.keyword("insert")?;
+            tokens.keyword("into")?;
+            let table_name = tokens.parse(TableName::parse)?;
+            let columns = column_list(tokens)?;
+            let mut values = 0;
+            let rows = tokens.parse(context(
+                "Column Values",
+                comma_sep(|i| {
+                    let mut tokens = TokenCursor::new(i);
+                    let (span, row) = tokens.with_span(|tokens| {
+                        tokens.symbol("(")?;
+                        let row =
+                            cut(tokens.parse(context("Row Values", comma_sep(parse_row_value))))?;
+                        cut(tokens.symbol(")"))?;
+                        Ok(row)
+                    })?;
+                    values += row.len();
+                    check_insert_values(span, values)?;
+                    Ok((tokens.rest(), (span, row)))
+                }),
+            ))?;
+            Ok((table_name, columns, rows))
+        })?;
+        let on_conflict = tokens.opt_parse(|i| parse_with_span(i, OnConflict::parse))?;
+        let returning = returning(&mut tokens)?;
         Ok((
-            input,
+            tokens.rest(),
             Self {
                 table_name,
-                values: values.into(),
+                columns,
+                rows: rows
+                    .into_iter()
+                    .map(|(span, values)| (span, values.into()))
+                    .collect(),
+                on_conflict,
+                returning: returning.into(),
             },
         ))
     }
 }
 
+impl<'a> UnboundStatement<'a> {
+    /// Resolves the table and columns against `table_map` and gives each value to its
+    /// column, producing the statement [`Statement::parse_with_table_map`] parses.
+    /// # Errors
+    /// Returns an error pointing at an unknown table or column.
+    /// Returns an error pointing at a row with too many or too few values, or at a value
+    /// that doesn't fit its column.
+    /// Returns an error pointing at an invalid `ON CONFLICT` or `RETURNING` clause.
+    pub fn bind(&self, table_map: &TableMap) -> Result<Statement<'a>, nom::Err<RawParseError<'a>>> {
+        let failure = |span, error| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        let columns = self
+            .table_name
+            .lookup(table_map)
+            .map_err(|(span, error)| failure(span, error))?;
+        let columns_found = resolve_columns(self.table_name, columns, &self.columns)?;
+        let rows = self
+            .rows
+            .iter()
+            .map(|(span, values)| {
+                if let Some((extra, _)) = values.get(columns_found.len()) {
+                    return Err(custom_failure(
+                        *extra,
+                        nom_supreme::error::BaseErrorKind::Expected(
+                            nom_supreme::error::Expectation::Char(')'),
+                        ),
+                    ));
+                }
+                if let Some((name, _)) = columns_found.get(values.len()) {
+                    return Err(failure(*name, ParseError::ColumnNotUsed));
+                }
+                let values = columns_found
+                    .iter()
+                    .zip(values.iter())
+                    .map(|((name, column), value)| Ok((*name, bind_value(column, value)?)))
+                    .collect::<Result<_, _>>()?;
+                Ok(Row {
+                    span: *span,
+                    values,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        if let Some((span, on_conflict)) = &self.on_conflict {
+            on_conflict.check(*span, self.table_name, columns)?;
+        }
+        let statement = Statement {
+            table_name: self.table_name,
+            columns: columns_found.iter().map(|(name, _)| *name).collect(),
+            rows,
+            on_conflict: self
+                .on_conflict
+                .as_ref()
+                .map(|(_, on_conflict)| on_conflict.clone()),
+            returning: self.returning.clone(),
+        };
+        statement.returning_columns(table_map)?;
+        Ok(statement)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedRow {
+    pub span: Span,
+    pub values: Box<[(OwnedSpan, OwnedWithSpan<Value>)]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedAssignment {
+    pub column: OwnedSpan,
+    pub value: OwnedExpr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedConflictAction {
+    Nothing,
+    Update(Box<[OwnedAssignment]>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedOnConflict {
+    pub target: Box<[OwnedSpan]>,
+    pub action: OwnedConflictAction,
+}
+
+pub type OwnedUnboundRow = OwnedWithSpan<Box<[OwnedWithSpan<OwnedRowValue>]>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedUnboundStatement {
+    pub table_name: OwnedTableName,
+    pub columns: OwnedWithSpan<Vec<OwnedColumnRef>>,
+    pub rows: Box<[OwnedUnboundRow]>,
+    pub on_conflict: Option<OwnedWithSpan<OwnedOnConflict>>,
+    pub returning: Box<[OwnedProjection]>,
+}
+
+/// An `INSERT` copied out of the input, see [`Statement::to_owned`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub table_name: OwnedTableName,
+    pub columns: Box<[OwnedSpan]>,
+    pub rows: Box<[OwnedRow]>,
+    pub on_conflict: Option<OwnedOnConflict>,
+    pub returning: Box<[OwnedProjection]>,
+}
+
+impl Row<'_> {
+    pub fn to_owned(&self) -> OwnedRow {
+        OwnedRow {
+            span: self.span.into(),
+            values: self
+                .values
+                .iter()
+                .map(|(name, value)| ((*name).into(), owned_with_span(value, Value::clone)))
+                .collect(),
+        }
+    }
+}
+
+impl OnConflict<'_> {
+    pub fn to_owned(&self) -> OwnedOnConflict {
+        OwnedOnConflict {
+            target: self.target.iter().copied().map(OwnedSpan::from).collect(),
+            action: match &self.action {
+                ConflictAction::Nothing => OwnedConflictAction::Nothing,
+                ConflictAction::Update(assignments) => OwnedConflictAction::Update(
+                    assignments
+                        .iter()
+                        .map(|assignment| OwnedAssignment {
+                            column: assignment.column.into(),
+                            value: assignment.value.to_owned(),
+                        })
+                        .collect(),
+                ),
+            },
+        }
+    }
+}
+
+impl UnboundStatement<'_> {
+    pub fn to_owned(&self) -> OwnedUnboundStatement {
+        OwnedUnboundStatement {
+            table_name: self.table_name.to_owned(),
+            columns: owned_with_span(&self.columns, |columns| {
+                columns.iter().map(ColumnRef::to_owned).collect()
+            }),
+            rows: self
+                .rows
+                .iter()
+                .map(|row| {
+                    owned_with_span(row, |values| {
+                        values
+                            .iter()
+                            .map(|value| owned_with_span(value, RowValue::to_owned))
+                            .collect()
+                    })
+                })
+                .collect(),
+            on_conflict: self
+                .on_conflict
+                .as_ref()
+                .map(|on_conflict| owned_with_span(on_conflict, OnConflict::to_owned)),
+            returning: self.returning.iter().map(Projection::to_owned).collect(),
+        }
+    }
+}
+
+impl Statement<'_> {
+    /// Copies the statement out of the input, keeping the positions of its spans.
+    pub fn to_owned(&self) -> OwnedStatement {
+        OwnedStatement {
+            table_name: self.table_name.to_owned(),
+            columns: self.columns.iter().copied().map(OwnedSpan::from).collect(),
+            rows: self.rows.iter().map(Row::to_owned).collect(),
+            on_conflict: self.on_conflict.as_ref().map(OnConflict::to_owned),
+            returning: self.returning.iter().map(Projection::to_owned).collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
     use miette::GraphicalTheme;
 
     use crate::{
-        ast::commands::create::{Column, SqlType},
+        ast::commands::create::{Column, LengthUnit, SqlType},
         parse::parse_format_error,
     };
 
@@ -151,12 +744,21 @@ mod tests {
             "test_table".into(),
             [
                 Column {
-                    name: "id".into(),
-                    tp: SqlType::I32,
+                    nullable: false,
+                    primary_key: true,
+                    ..Column::new("id", SqlType::I32)
                 },
+                Column::new("name", SqlType::VarChar(255, LengthUnit::Chars)),
                 Column {
-                    name: "name".into(),
-                    tp: SqlType::VarChar(255),
+                    nullable: false,
+                    is_auto_increment: true,
+                    unique: true,
+                    ..Column::new("seq", SqlType::I64)
+                },
+                Column {
+                    nullable: false,
+                    default: Some(Value::VarChar("active".into())),
+                    ..Column::new("status", SqlType::VarChar(10, LengthUnit::Chars))
                 },
             ]
             .into_iter()
@@ -207,7 +809,12 @@ mod tests {
         let table_map = get_table_map();
         let column_map = table_map.get("test_table").unwrap();
         let input = RawSpan::new(" (id, name) VALUES ( 1, 'test' ) ");
-        let (_, values) = parse_values(column_map, input).unwrap();
+        let values = parse_values(
+            TableName::parse_from_raw("test_table").unwrap().1,
+            column_map,
+            &mut TokenCursor::new(input),
+        )
+        .unwrap();
         let mut settings = insta::Settings::new();
         settings.set_description(format!("Input: {input}",));
         settings.bind(|| {
@@ -223,6 +830,240 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_null_value() {
+        test_case(
+            "null",
+            r#"INSERT INTO test_table (id, name) VALUES (1, NULL)"#,
+        );
+    }
+
+    #[test]
+    fn test_default_value() {
+        test_case(
+            "default",
+            r#"INSERT INTO test_table (id, name, status) VALUES (1, DEFAULT, DEFAULT)"#,
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_value() {
+        test_case(
+            "arithmetic",
+            r#"INSERT INTO test_table (id, name) VALUES ((1 + 2) * -3, 'a')"#,
+        );
+    }
+
+    #[test]
+    fn test_qualified_columns() {
+        test_case(
+            "qualified",
+            r#"INSERT INTO test_table (test_table.id, name) VALUES (1, 'a')"#,
+        );
+    }
+
+    #[test]
+    fn test_schema_table() {
+        test_case("schema", r#"INSERT INTO public.test_table (id) VALUES (1)"#);
+    }
+
+    #[test]
+    fn test_bind_parameters() {
+        let table_map = get_table_map();
+        test_case(
+            "parameters",
+            r#"INSERT INTO test_table (id, name) VALUES ($1, $2), ($1, NULL)"#,
+        );
+
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            "INSERT INTO test_table (id, name) VALUES (?, ?), (3, ?)".into(),
+        )
+        .unwrap();
+        let bound = statement
+            .bind_parameters(
+                &table_map,
+                &[Value::I64(1), Value::VarChar("a".into()), Value::Null],
+            )
+            .unwrap();
+        let values = bound
+            .rows
+            .iter()
+            .flat_map(|row| row.values.iter().map(|(_, (_, value))| value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                Value::I32(1),
+                Value::VarChar("a".into()),
+                Value::I32(3),
+                Value::Null
+            ]
+        );
+
+        let bind = |values: &[Value]| statement.bind_parameters(&table_map, values);
+        assert!(bind(&[Value::Null, Value::Null, Value::Null]).is_err());
+        assert!(bind(&[Value::VarChar("a".into()), Value::Null, Value::Null]).is_err());
+        assert!(bind(&[Value::I64(1), Value::VarChar("a".into())]).is_err());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "INSERT INTO test_table (id) VALUES ($1 + 1)".into(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_on_conflict() {
+        test_case(
+            "on-conflict-nothing",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') ON CONFLICT DO NOTHING"#,
+        );
+        test_case(
+            "on-conflict-update",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') ON CONFLICT (id) DO UPDATE SET name = excluded.name, seq = test_table.seq + 1"#,
+        );
+
+        let table_map = get_table_map();
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+        assert!(
+            parse("INSERT INTO test_table (id) VALUES (1) ON CONFLICT (id) DO NOTHING").is_ok()
+        );
+        assert!(parse("INSERT INTO test_table (id) VALUES (1) on conflict(seq)do nothing").is_ok());
+        assert!(
+            parse("INSERT INTO test_table (id) VALUES (1) ON CONFLICT (age) DO NOTHING").is_err()
+        );
+        assert!(parse("INSERT INTO test_table (id) VALUES (1) ON CONFLICT DO").is_err());
+        assert!(parse(
+            "INSERT INTO test_table (id) VALUES (1) ON CONFLICT (id) DO UPDATE SET age = 1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_returning() {
+        test_case(
+            "returning",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') RETURNING seq, id * 2 AS double"#,
+        );
+
+        let table_map = get_table_map();
+        let returning = |input: &'static str| {
+            Statement::parse_with_table_map(&table_map, input.into())
+                .map(|(_, statement)| statement.returning_columns(&table_map).unwrap())
+        };
+        assert!(returning("INSERT INTO test_table (id) VALUES (1)")
+            .unwrap()
+            .is_empty());
+        let columns = returning(
+            "INSERT INTO test_table (id) VALUES (1) ON CONFLICT DO NOTHING RETURNING *, test_table.id",
+        )
+        .unwrap();
+        assert_eq!(
+            columns
+                .iter()
+                .map(|column| &*column.name)
+                .collect::<Vec<_>>(),
+            ["id", "name", "seq", "status", "id"]
+        );
+        assert!(returning("INSERT INTO test_table (id) VALUES (1) RETURNING age").is_err());
+        assert!(returning("INSERT INTO test_table (id) VALUES (1) RETURNING count(id)").is_err());
+        assert!(returning("INSERT INTO test_table (id) VALUES (1) RETURNING").is_err());
+    }
+
+    #[test]
+    fn test_multi_row_statement() {
+        test_case(
+            "multi-row",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a'), (2, 'b'),(3, 'c')"#,
+        );
+    }
+
+    #[test]
+    fn test_column_order() {
+        let table_map = get_table_map();
+        let statement = Statement::parse_with_table_map(
+            &table_map,
+            "INSERT INTO test_table (name, status, id) VALUES ('a', DEFAULT, 1)".into(),
+        )
+        .unwrap()
+        .1;
+        let columns = statement
+            .columns
+            .iter()
+            .map(|name| *name.fragment())
+            .collect::<Vec<_>>();
+        assert_eq!(columns, ["name", "status", "id"]);
+        let values = statement.rows[0]
+            .column_values()
+            .map(|(index, value)| (columns[index], value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("name", Value::VarChar("a".into())),
+                ("status", Value::VarChar("active".into())),
+                ("id", Value::I32(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbound_statement() {
+        let table_map = get_table_map();
+        let bind = |input: &'static str| {
+            let (rest, statement) = UnboundStatement::parse(input.into()).unwrap();
+            assert!(rest.is_empty());
+            statement.bind(&table_map)
+        };
+        for input in [
+            "INSERT INTO test_table (name, status, id) VALUES ('a', DEFAULT, 1)",
+            "INSERT INTO test_table (id, name) VALUES (1 + 1, NULL), ($1, 'b') RETURNING id",
+            "INSERT INTO test_table (id) VALUES (1) ON CONFLICT (id) DO NOTHING",
+        ] {
+            let (_, bound) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            assert_eq!(
+                format!("{:?}", bind(input).unwrap()),
+                format!("{bound:?}"),
+                "{input}"
+            );
+        }
+
+        assert!(UnboundStatement::parse("INSERT INTO missing (a) VALUES (x + 1)".into()).is_ok());
+        for input in [
+            "INSERT INTO missing (id) VALUES (1)",
+            "INSERT INTO test_table (id, missing) VALUES (1, 2)",
+            "INSERT INTO test_table (id, name) VALUES (1)",
+            "INSERT INTO test_table (id) VALUES (1, 'a')",
+            "INSERT INTO test_table (id) VALUES ('a')",
+            "INSERT INTO test_table (id) VALUES (NULL)",
+            "INSERT INTO test_table (name) VALUES ('a')",
+        ] {
+            assert!(bind(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_enum_value() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "people".into(),
+            [Column::new(
+                "mood",
+                SqlType::Enum(["sad".into(), "happy".into()].into()),
+            )]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+        assert!(parse("INSERT INTO people (mood) VALUES ('happy'), (NULL)").is_ok());
+        assert!(matches!(
+            parse("INSERT INTO people (mood) VALUES ('sad'), ('meh')"),
+            Err(nom::Err::Failure(_))
+        ));
+    }
+
     #[test]
     fn test_invalid_statement() {
         test_case_err(
@@ -233,9 +1074,58 @@ mod tests {
             "less-values",
             r#"INSERT INTO test_table (id, name) VALUES ( 2) "#,
         );
+        test_case_err(
+            "less-values-second-row",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a'), (2) "#,
+        );
+        test_case_err(
+            "wrong-type-second-row",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a'), ('b', 2) "#,
+        );
+        test_case_err(
+            "null-not-allowed",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a'), (NULL, 'b') "#,
+        );
+        test_case_err(
+            "missing-not-null",
+            r#"INSERT INTO test_table (name) VALUES ('a') "#,
+        );
+        test_case_err("reserved-table", r#"INSERT INTO values (id) VALUES (1) "#);
+        test_case_err(
+            "out-of-range",
+            r#"INSERT INTO test_table (id, name) VALUES (2147483647 + 1, 'a') "#,
+        );
+        test_case_err(
+            "overflow",
+            r#"INSERT INTO test_table (id, name) VALUES (9223372036854775807 + 1, 'a') "#,
+        );
+        test_case_err(
+            "not-constant",
+            r#"INSERT INTO test_table (id, name) VALUES (id + 1, 'a') "#,
+        );
         test_case_err(
             "wrong-column",
             r#"INSERT INTO test_table (id, age) VALUES ( 2, 3) "#,
         );
+        test_case_err(
+            "wrong-qualifier",
+            r#"INSERT INTO test_table (other.id, name) VALUES (2, 'a') "#,
+        );
+        test_case_err(
+            "conflict-not-unique",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') ON CONFLICT (name) DO NOTHING"#,
+        );
+        test_case_err(
+            "conflict-without-target",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') ON CONFLICT DO UPDATE SET name = 'b'"#,
+        );
+        test_case_err(
+            "conflict-ambiguous",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') ON CONFLICT (id) DO UPDATE SET name = name"#,
+        );
+        test_case_err(
+            "conflict-wrong-type",
+            r#"INSERT INTO test_table (id, name) VALUES (1, 'a') ON CONFLICT (id) DO UPDATE SET id = excluded.name"#,
+        );
     }
 }
@@ -1,3 +1,14 @@
+pub mod alter;
+pub mod analyze;
+pub mod backup;
 pub mod create;
+pub mod create_schema;
+pub mod create_type;
+pub mod create_view;
+pub mod drop;
+pub mod explain;
 pub mod insert;
 pub mod select;
+pub mod show;
+pub mod transaction;
+pub mod vacuum;
@@ -0,0 +1,1430 @@
+use std::borrow::Cow;
+
+use nom::error::context;
+
+use crate::{
+    ast::{
+        commands::create::{Column, LengthUnit, SqlType},
+        expr::{parameter_indexes, ColumnRef, Expr, OwnedColumnRef, OwnedExpr},
+        table::{OwnedTableExpr, Scope, TableExpr},
+    },
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    functions::FunctionRegistry,
+    lexer::{cut, TokenCursor},
+    parse::{owned_with_span, OwnedSpan, OwnedWithSpan, Parse, RawSpan, TableMap, WithSpan},
+    parsers::{
+        comma_sep, dialect::dialect, identifier::identifier, limits::DepthGuard, parse_with_span,
+    },
+    value::Value,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Projection<'a> {
+    Wildcard(RawSpan<'a>),
+    /// `column [AS alias]`.
+    Column {
+        column: ColumnRef<'a>,
+        alias: Option<RawSpan<'a>>,
+    },
+    /// `expr [AS alias]`, for any expression that is not a plain column. The span is the
+    /// source of the expression.
+    Expr {
+        expr: WithSpan<'a, Expr<'a>>,
+        alias: Option<RawSpan<'a>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+/// A sort key of `ORDER BY`: `expr [ASC | DESC] [NULLS FIRST | NULLS LAST]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OrderByItem<'a> {
+    /// The sort expression, the span is its source.
+    pub expr: WithSpan<'a, Expr<'a>>,
+    pub order: SortOrder,
+    /// Where `NULL`s go, `None` when not written, see [`OrderByItem::nulls_first`].
+    pub nulls: Option<NullsOrder>,
+}
+
+/// A common table expression of the `WITH` clause: `name [(column, ...)] AS (query)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommonTableExpr<'a> {
+    pub name: RawSpan<'a>,
+    /// Renames the columns of the query, empty to keep their names.
+    pub columns: Box<[RawSpan<'a>]>,
+    /// The query, the span is its source. For a recursive expression this is the anchor,
+    /// evaluated once.
+    pub query: WithSpan<'a, Box<Statement<'a>>>,
+    /// With `WITH RECURSIVE`, the term after `UNION [ALL]`.
+    pub recursive: Option<RecursiveTerm<'a>>,
+}
+
+/// The recursive term of `anchor UNION [ALL] term`. Its reference to the expression is a
+/// [`TableExpr::SelfReference`], it is evaluated again on the rows of the previous
+/// iteration until it produces no new rows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecursiveTerm<'a> {
+    /// `UNION ALL` keeps duplicate rows, `UNION` discards them.
+    pub all: bool,
+    /// The query, the span is its source.
+    pub query: WithSpan<'a, Box<Statement<'a>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement<'a> {
+    /// The common table expressions of the `WITH` clause, each one visible to the ones
+    /// after it and to the statement.
+    pub with: Box<[CommonTableExpr<'a>]>,
+    pub projection: Box<[Projection<'a>]>,
+    /// The `FROM` clause.
+    pub from: TableExpr<'a>,
+    /// The `WHERE` predicate.
+    pub selection: Option<Expr<'a>>,
+    /// The `GROUP BY` expressions, the span is their source.
+    pub group_by: Box<[WithSpan<'a, Expr<'a>>]>,
+    /// The `HAVING` predicate, applied to each group.
+    pub having: Option<Expr<'a>>,
+    /// The `ORDER BY` sort keys, from the most to the least significant.
+    pub order_by: Box<[OrderByItem<'a>]>,
+    /// The maximum number of rows returned.
+    pub limit: Option<WithSpan<'a, u64>>,
+    /// The number of rows skipped before the first returned one.
+    pub offset: Option<WithSpan<'a, u64>>,
+}
+
+/// The `LIMIT` count and the `OFFSET`, if any.
+type Limit<'a> = (WithSpan<'a, u64>, Option<WithSpan<'a, u64>>);
+
+impl<'a> Parse<'a> for Projection<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Projection", |input| {
+            let mut tokens = TokenCursor::new(input);
+            if let Some(span) = tokens.opt_symbol("*") {
+                return Ok((tokens.rest(), Self::Wildcard(span)));
+            }
+            let (span, expr) = tokens.parse(|i| parse_with_span(i, Expr::parse))?;
+            let alias = if tokens.opt_keyword("as").is_some() {
+                Some(cut(tokens.parse(context("Alias", identifier)))?)
+            } else {
+                None
+            };
+            let projection = match expr {
+                Expr::Column(column) => Self::Column { column, alias },
+                expr => Self::Expr {
+                    expr: (span, expr),
+                    alias,
+                },
+            };
+            Ok((tokens.rest(), projection))
+        })(input)
+    }
+}
+
+impl<'a> Projection<'a> {
+    #[must_use]
+    pub const fn alias(&self) -> Option<RawSpan<'a>> {
+        match self {
+            Self::Wildcard(_) => None,
+            Self::Column { alias, .. } | Self::Expr { alias, .. } => *alias,
+        }
+    }
+
+    /// The span of the projected expression.
+    #[must_use]
+    pub const fn span(&self) -> RawSpan<'a> {
+        match self {
+            Self::Wildcard(span)
+            | Self::Column {
+                column: ColumnRef { name: span, .. },
+                ..
+            }
+            | Self::Expr {
+                expr: (span, _), ..
+            } => *span,
+        }
+    }
+}
+
+impl<'a> Parse<'a> for OrderByItem<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Order By Item", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let expr = tokens.parse(|i| parse_with_span(i, Expr::parse))?;
+            let order = if tokens.opt_keyword("desc").is_some() {
+                SortOrder::Desc
+            } else {
+                tokens.opt_keyword("asc");
+                SortOrder::Asc
+            };
+            let nulls = if tokens.opt_keyword("nulls").is_some() {
+                Some(if tokens.opt_keyword("first").is_some() {
+                    NullsOrder::First
+                } else if tokens.opt_keyword("last").is_some() {
+                    NullsOrder::Last
+                } else {
+                    cut(Err(tokens.unexpected("FIRST or LAST")))?
+                })
+            } else {
+                None
+            };
+            Ok((tokens.rest(), Self { expr, order, nulls }))
+        })(input)
+    }
+}
+
+impl OrderByItem<'_> {
+    /// Whether `NULL`s sort before the other values. When not written, `NULL` sorts as the
+    /// largest value: last in ascending order and first in descending order.
+    #[must_use]
+    pub fn nulls_first(&self) -> bool {
+        match self.nulls {
+            Some(nulls) => nulls == NullsOrder::First,
+            None => self.order == SortOrder::Desc,
+        }
+    }
+}
+
+impl<'a> Parse<'a> for CommonTableExpr<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        Self::parse_recursive(false, input)
+    }
+}
+
+impl<'a> CommonTableExpr<'a> {
+    /// Parses a common table expression. When `recursive`, the query may be the
+    /// `UNION [ALL]` of an anchor and a recursive term, the anchor must not reference the
+    /// expression and the term may reference it once.
+    fn parse_recursive(recursive: bool, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let query = |i| parse_with_span(i, Statement::parse);
+        let mut tokens = TokenCursor::new(input);
+        let (name, columns, (span, query), term) =
+            tokens.context("Common Table Expression", |tokens| {
+                let name = tokens.parse(context("Name", identifier))?;
+                let columns = if tokens.opt_symbol("(").is_some() {
+                    let columns =
+                        cut(tokens.parse(context("Column Names", comma_sep(identifier))))?;
+                    cut(tokens.symbol(")"))?;
+                    columns
+                } else {
+                    Vec::new()
+                };
+                tokens.keyword("as")?;
+                tokens.symbol("(")?;
+                let anchor = cut(tokens.parse(query))?;
+                let term = if recursive && tokens.opt_keyword("union").is_some() {
+                    let all = tokens.opt_keyword("all").is_some();
+                    Some((all, cut(tokens.parse(query))?))
+                } else {
+                    None
+                };
+                cut(tokens.symbol(")"))?;
+                Ok((name, columns, anchor, term))
+            })?;
+
+        let failure = |span| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    ParseError::RecursiveReference,
+                )),
+            )
+        };
+        let mut query = Box::new(query);
+        let recursive = match term {
+            Some((all, (term_span, term))) => {
+                if let Some(span) = query.from.mark_self_references(name.fragment()).first() {
+                    return Err(failure(*span));
+                }
+                let mut term = Box::new(term);
+                if let Some(span) = term.from.mark_self_references(name.fragment()).get(1) {
+                    return Err(failure(*span));
+                }
+                Some(RecursiveTerm {
+                    all,
+                    query: (term_span, term),
+                })
+            }
+            None => None,
+        };
+        Ok((
+            tokens.rest(),
+            Self {
+                name,
+                columns: columns.into(),
+                query: (span, query),
+                recursive,
+            },
+        ))
+    }
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let _depth = DepthGuard::enter(input)?;
+        context("Select", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let with = Self::parse_with(&mut tokens)?;
+            tokens.keyword("select")?;
+            let projection = tokens.parse(comma_sep(Projection::parse))?;
+            tokens.keyword("from")?;
+            let from = tokens.parse(TableExpr::parse)?;
+            let selection = if tokens.opt_keyword("where").is_some() {
+                Some(cut(tokens.parse(context("Where", Expr::parse)))?)
+            } else {
+                None
+            };
+            let group_by = if tokens.opt_keyword("group").is_some() {
+                tokens.keyword("by")?;
+                cut(tokens.parse(context(
+                    "Group By",
+                    comma_sep(|i| parse_with_span(i, Expr::parse)),
+                )))?
+            } else {
+                Vec::new()
+            };
+            let having = if tokens.opt_keyword("having").is_some() {
+                Some(cut(tokens.parse(context("Having", Expr::parse)))?)
+            } else {
+                None
+            };
+            let order_by = if tokens.opt_keyword("order").is_some() {
+                tokens.keyword("by")?;
+                cut(tokens.parse(context("Order By", comma_sep(OrderByItem::parse))))?
+            } else {
+                Vec::new()
+            };
+            let (limit, offset) = if tokens.opt_keyword("limit").is_some() {
+                let (limit, offset) = cut(tokens.context("Limit", Self::parse_limit))?;
+                (Some(limit), offset)
+            } else {
+                (None, None)
+            };
+            Ok((
+                tokens.rest(),
+                Self {
+                    with: with.into(),
+                    projection: projection.into(),
+                    from,
+                    selection,
+                    group_by: group_by.into(),
+                    having,
+                    order_by: order_by.into(),
+                    limit,
+                    offset,
+                },
+            ))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses the `WITH [RECURSIVE]` clause, if any.
+    fn parse_with(
+        tokens: &mut TokenCursor<'a>,
+    ) -> Result<Vec<CommonTableExpr<'a>>, nom::Err<RawParseError<'a>>> {
+        if tokens.opt_keyword("with").is_none() {
+            return Ok(Vec::new());
+        }
+        let recursive = tokens.opt_keyword("recursive").is_some();
+        cut(tokens.parse(context(
+            "With",
+            comma_sep(move |i| CommonTableExpr::parse_recursive(recursive, i)),
+        )))
+    }
+
+    /// Parses what follows `LIMIT`: `count [OFFSET skip]`, or with the `mysql` feature or a
+    /// [`Dialect`](crate::parsers::dialect::Dialect) that accepts it also `skip, count`.
+    fn parse_limit(tokens: &mut TokenCursor<'a>) -> Result<Limit<'a>, nom::Err<RawParseError<'a>>> {
+        let number = |i| parse_with_span(i, u64::parse);
+        let limit = tokens.parse(number)?;
+        if (cfg!(feature = "mysql") || dialect().accepts_comma_limit())
+            && tokens.opt_symbol(",").is_some()
+        {
+            return Ok((tokens.parse(number)?, Some(limit)));
+        }
+        let offset = if tokens.opt_keyword("offset").is_some() {
+            Some(cut(tokens.parse(number))?)
+        } else {
+            None
+        };
+        Ok((limit, offset))
+    }
+
+    /// Parses a `SELECT` statement, checking the tables and the projected and filtered
+    /// columns exist, and that a grouped statement only projects grouped columns.
+    /// # Errors
+    /// Returns an error if the input is not a valid `SELECT` statement.
+    /// Returns an error pointing at the first unknown table or unknown or ambiguous column,
+    /// or at the first column that is neither grouped nor aggregated.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the tables and columns against `table_map`.
+    /// # Errors
+    /// Returns an error pointing at the first unknown table or unknown or ambiguous column,
+    /// or at the first column that is neither grouped nor aggregated.
+    pub fn bind(mut self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        self.check(table_map)?;
+        self.bind_function_types(table_map)?;
+        Ok(self)
+    }
+
+    fn check(&self, table_map: &TableMap) -> Result<(), nom::Err<RawParseError<'a>>> {
+        let table_map = self.bind_ctes(table_map)?;
+        let scope = self.from.scope(&table_map)?;
+        self.resolve_columns(&scope)?;
+        self.check_selection(&scope)?;
+        self.check_grouping(&scope)
+    }
+
+    /// Replaces the parameter placeholders with `values` and checks the statement again
+    /// against `table_map`, now that the types of the parameters are known.
+    /// # Errors
+    /// Returns an error pointing at a placeholder without a value, or at the first one when
+    /// `?` and `$n` are mixed, or at the projection when there are more values than
+    /// placeholders.
+    /// Returns an error pointing at the first value that doesn't fit where it is used.
+    pub fn bind_parameters(
+        &self,
+        table_map: &TableMap,
+        values: &[Value],
+    ) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        let mut statement = self.clone();
+        let parameters = statement.parameters_mut();
+        let placeholders = parameters
+            .iter()
+            .filter_map(|expr| match &**expr {
+                Expr::Parameter(placeholder) => Some(*placeholder),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let indexes = parameter_indexes(&placeholders, values.len(), self.projection[0].span())?;
+        for (expr, index) in parameters.into_iter().zip(indexes) {
+            *expr = Expr::Literal((expr.span(), values[index].clone()));
+        }
+        statement.check(table_map)?;
+        statement.bind_function_types(table_map)?;
+        Ok(statement)
+    }
+
+    /// Records the types of the function calls of the statement and of its common table
+    /// expressions, see [`Expr::bind_function_types`]. The statement is checked already.
+    fn bind_function_types(
+        &mut self,
+        table_map: &TableMap,
+    ) -> Result<(), nom::Err<RawParseError<'a>>> {
+        let table_map = self.bind_ctes(table_map)?;
+        for cte in self.with.iter_mut() {
+            cte.query.1.bind_function_types(&table_map)?;
+            if let Some(term) = &mut cte.recursive {
+                term.query.1.bind_function_types(&table_map)?;
+            }
+        }
+        let scope = self.from.scope(&table_map)?;
+        let aliases = self
+            .order_by
+            .iter()
+            .map(|item| self.is_output_alias(&item.expr.1))
+            .collect::<Vec<_>>();
+        let projection = self
+            .projection
+            .iter_mut()
+            .filter_map(|projection| match projection {
+                Projection::Expr {
+                    expr: (_, expr), ..
+                } => Some(expr),
+                Projection::Wildcard(_) | Projection::Column { .. } => None,
+            });
+        let order_by = self
+            .order_by
+            .iter_mut()
+            .zip(aliases)
+            .filter(|(_, alias)| !alias)
+            .map(|(item, _)| &mut item.expr.1);
+        let exprs = projection
+            .chain(self.selection.iter_mut())
+            .chain(self.group_by.iter_mut().map(|(_, expr)| expr))
+            .chain(self.having.iter_mut())
+            .chain(order_by);
+        for expr in exprs {
+            expr.bind_function_types(&scope.columns, FunctionRegistry::builtins())?;
+        }
+        Ok(())
+    }
+
+    /// The parameter placeholders of the statement and its common table expressions, in
+    /// source order.
+    fn parameters_mut(&mut self) -> Vec<&mut Expr<'a>> {
+        let mut parameters = Vec::new();
+        for cte in self.with.iter_mut() {
+            parameters.extend(cte.query.1.parameters_mut());
+            if let Some(term) = &mut cte.recursive {
+                parameters.extend(term.query.1.parameters_mut());
+            }
+        }
+        for projection in self.projection.iter_mut() {
+            if let Projection::Expr {
+                expr: (_, expr), ..
+            } = projection
+            {
+                expr.collect_parameters_mut(&mut parameters);
+            }
+        }
+        self.from.collect_parameters_mut(&mut parameters);
+        let exprs = self
+            .selection
+            .iter_mut()
+            .chain(self.group_by.iter_mut().map(|(_, expr)| expr))
+            .chain(self.having.iter_mut())
+            .chain(self.order_by.iter_mut().map(|item| &mut item.expr.1));
+        for expr in exprs {
+            expr.collect_parameters_mut(&mut parameters);
+        }
+        parameters.sort_by_key(|expr| expr.span().location_offset());
+        parameters
+    }
+
+    /// The catalog the statement is resolved against. The common table expressions of the
+    /// `WITH` clause are added to the default schema, shadowing the tables with the same
+    /// name, and each one is resolved against the ones before it.
+    /// # Errors
+    /// Returns an error pointing at a name used by two common table expressions, at one
+    /// whose column list doesn't match its query, or at the first error of a query.
+    pub fn bind_ctes<'m>(
+        &self,
+        table_map: &'m TableMap,
+    ) -> Result<Cow<'m, TableMap>, nom::Err<RawParseError<'a>>> {
+        if self.with.is_empty() {
+            return Ok(Cow::Borrowed(table_map));
+        }
+        let failure = |span, error| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        let mut bound = table_map.clone();
+        for (index, cte) in self.with.iter().enumerate() {
+            if self.with[..index]
+                .iter()
+                .any(|other| other.name.fragment() == cte.name.fragment())
+            {
+                return Err(failure(cte.name, ParseError::DuplicateCteName));
+            }
+            let (_, query) = &cte.query;
+            query.check(&bound)?;
+            let mut columns = query.columns(&bound)?;
+            if !cte.columns.is_empty() {
+                if cte.columns.len() != columns.len() {
+                    return Err(failure(cte.name, ParseError::CteColumnCount));
+                }
+                for (column, name) in columns.iter_mut().zip(cte.columns.iter()) {
+                    column.name = (*name.fragment()).into();
+                }
+            }
+            let column_map = |columns: &[Column]| {
+                columns
+                    .iter()
+                    .map(|column| (column.name.clone(), column.clone()))
+                    .collect()
+            };
+
+            // The recursive term sees the expression with the columns of the anchor, and
+            // must produce rows of the same shape.
+            if let Some(RecursiveTerm {
+                query: (term_span, term),
+                ..
+            }) = &cte.recursive
+            {
+                if let Some(aggregate) = term.aggregates().first() {
+                    return Err(failure(aggregate.span(), ParseError::AggregateNotAllowed));
+                }
+                let mut with_self = bound.clone();
+                with_self.insert((*cte.name.fragment()).into(), column_map(&columns));
+                term.check(&with_self)?;
+                let term_columns = term.columns(&with_self)?;
+                if term_columns.len() != columns.len() {
+                    return Err(failure(*term_span, ParseError::CteColumnCount));
+                }
+                for (column, term_column) in columns.iter_mut().zip(term_columns) {
+                    if column.tp.common(&term_column.tp).is_none() {
+                        return Err(failure(*term_span, ParseError::IncompatibleTypes));
+                    }
+                    column.nullable |= term_column.nullable;
+                }
+            }
+            bound.insert((*cte.name.fragment()).into(), column_map(&columns));
+        }
+        Ok(Cow::Owned(bound))
+    }
+
+    /// Resolves the columns produced by the statement, in projection order.
+    /// `*` expands to the columns of every table, in `FROM` order and each sorted by name.
+    /// Expressions become nullable columns named after their source, with the inferred type
+    /// (`VARCHAR(0)` when the expression is always `NULL`). Function calls resolve to the
+    /// built-in functions.
+    /// # Errors
+    /// Returns an error pointing at the first unknown table or unknown or ambiguous column,
+    /// or at an expression whose type can't be inferred.
+    pub fn columns(
+        &self,
+        table_map: &TableMap,
+    ) -> Result<Vec<Column>, nom::Err<RawParseError<'a>>> {
+        let table_map = self.bind_ctes(table_map)?;
+        self.resolve_columns(&self.from.scope(&table_map)?)
+    }
+
+    fn resolve_columns(&self, scope: &Scope) -> Result<Vec<Column>, nom::Err<RawParseError<'a>>> {
+        projection_columns(&self.projection, scope)
+    }
+
+    /// The outermost aggregate calls of the projection and of `HAVING`, in source order.
+    /// A statement with aggregates produces one row per group instead of one row per
+    /// table row.
+    #[must_use]
+    pub fn aggregates(&self) -> Vec<&Expr<'a>> {
+        self.projection
+            .iter()
+            .flat_map(|projection| match projection {
+                Projection::Expr {
+                    expr: (_, expr), ..
+                } => expr.aggregates(),
+                Projection::Wildcard(_) | Projection::Column { .. } => vec![],
+            })
+            .chain(self.having.iter().flat_map(Expr::aggregates))
+            .collect()
+    }
+
+    /// Whether the statement produces one row per group, because it has a `GROUP BY`, a
+    /// `HAVING` or an aggregate.
+    #[must_use]
+    pub fn is_grouped(&self) -> bool {
+        !self.group_by.is_empty() || self.having.is_some() || !self.aggregates().is_empty()
+    }
+
+    /// Checks the `GROUP BY` expressions exist and don't use aggregates, and that every
+    /// column the projection, `HAVING` and `ORDER BY` use outside of an aggregate is grouped.
+    /// An expression is also accepted as is when it is written exactly like a `GROUP BY`
+    /// expression, so `SELECT a + 1 ... GROUP BY a + 1` is valid.
+    fn check_grouping(&self, scope: &Scope) -> Result<(), nom::Err<RawParseError<'a>>> {
+        let failure = |span, error| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        for (_, expr) in self.group_by.iter() {
+            if let Some(aggregate) = expr.aggregates().first() {
+                return Err(failure(
+                    aggregate.span(),
+                    crate::errors::ParseError::AggregateNotAllowed,
+                ));
+            }
+            scope.check(expr)?;
+            expr.result_type(&scope.columns, FunctionRegistry::builtins())?;
+        }
+        let order_by = self
+            .order_by
+            .iter()
+            .map(|item| &item.expr.1)
+            .filter(|expr| !self.is_output_alias(expr));
+        for expr in self.having.iter().chain(order_by) {
+            scope.check(expr)?;
+            expr.result_type(&scope.columns, FunctionRegistry::builtins())?;
+        }
+        if !self.is_grouped() {
+            return Ok(());
+        }
+
+        let grouped_columns = self
+            .group_by
+            .iter()
+            .filter_map(|(_, expr)| match expr {
+                Expr::Column(column) => scope.qualified_name(column),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let check = |expr: &Expr<'a>, source: Option<RawSpan<'a>>| {
+            let written_as_grouped = source.is_some_and(|source| {
+                self.group_by
+                    .iter()
+                    .any(|(group, _)| group.fragment().trim() == source.fragment().trim())
+            });
+            if written_as_grouped {
+                return Ok(());
+            }
+            expr.columns_outside_aggregates()
+                .into_iter()
+                .find(|column| {
+                    scope
+                        .qualified_name(column)
+                        .is_none_or(|name| !grouped_columns.contains(&name))
+                })
+                .map_or(Ok(()), |column| {
+                    Err(failure(
+                        column.name,
+                        crate::errors::ParseError::ColumnNotGrouped,
+                    ))
+                })
+        };
+        for projection in self.projection.iter() {
+            match projection {
+                Projection::Wildcard(span) => {
+                    return Err(failure(*span, crate::errors::ParseError::ColumnNotGrouped))
+                }
+                Projection::Column { column, .. } => check(&Expr::Column(*column), None)?,
+                Projection::Expr {
+                    expr: (span, expr), ..
+                } => check(expr, Some(*span))?,
+            }
+        }
+        if let Some(having) = &self.having {
+            check(having, None)?;
+        }
+        for OrderByItem {
+            expr: (span, expr), ..
+        } in self.order_by.iter()
+        {
+            if !self.is_output_alias(expr) {
+                check(expr, Some(*span))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `expr` is a bare name given to a projection with `AS`, `ORDER BY` can sort on
+    /// the output columns by their alias.
+    fn is_output_alias(&self, expr: &Expr) -> bool {
+        let Expr::Column(ColumnRef {
+            qualifier: None,
+            name,
+        }) = expr
+        else {
+            return false;
+        };
+        self.projection
+            .iter()
+            .filter_map(Projection::alias)
+            .any(|alias| alias.fragment() == name.fragment())
+    }
+
+    /// Checks every column used by the `WHERE` predicate can be resolved, and that it
+    /// doesn't use aggregates, which are computed after the rows are filtered.
+    fn check_selection(&self, scope: &Scope) -> Result<(), nom::Err<RawParseError<'a>>> {
+        if let Some(aggregate) = self.selection.iter().flat_map(Expr::aggregates).next() {
+            return Err(custom_failure(
+                aggregate.span(),
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    crate::errors::ParseError::AggregateNotAllowed,
+                )),
+            ));
+        }
+        if let Some(selection) = &self.selection {
+            scope.check(selection)?;
+            selection.result_type(&scope.columns, FunctionRegistry::builtins())?;
+        }
+        Ok(())
+    }
+}
+
+/// The output columns of a projection list, resolving columns in `scope`: `*` expands to
+/// every column of the scope and expressions become nullable columns named after their
+/// source.
+/// # Errors
+/// Returns an error pointing at the first unknown or ambiguous column, or at an expression
+/// whose type can't be inferred.
+pub(crate) fn projection_columns<'a>(
+    projection: &[Projection<'a>],
+    scope: &Scope,
+) -> Result<Vec<Column>, nom::Err<RawParseError<'a>>> {
+    let mut resolved = Vec::new();
+    for projection in projection {
+        match projection {
+            Projection::Wildcard(_) => {
+                resolved.extend(scope.tables.iter().flat_map(|(_, columns)| columns.clone()));
+            }
+            Projection::Column { column, alias } => {
+                let mut column = scope.resolve(column)?.clone();
+                if let Some(alias) = alias {
+                    column.name = (*alias.fragment()).into();
+                }
+                resolved.push(column);
+            }
+            Projection::Expr {
+                expr: (span, expr),
+                alias,
+            } => {
+                scope.check(expr)?;
+                let tp = expr
+                    .result_type(&scope.columns, FunctionRegistry::builtins())?
+                    .unwrap_or(SqlType::VarChar(0, LengthUnit::Chars));
+                let name = alias.map_or_else(|| span.fragment().trim(), |alias| *alias.fragment());
+                resolved.push(Column::new(name, tp));
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedProjection {
+    Wildcard(OwnedSpan),
+    Column {
+        column: OwnedColumnRef,
+        alias: Option<OwnedSpan>,
+    },
+    Expr {
+        expr: OwnedWithSpan<OwnedExpr>,
+        alias: Option<OwnedSpan>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedOrderByItem {
+    pub expr: OwnedWithSpan<OwnedExpr>,
+    pub order: SortOrder,
+    pub nulls: Option<NullsOrder>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedCommonTableExpr {
+    pub name: OwnedSpan,
+    pub columns: Box<[OwnedSpan]>,
+    pub query: OwnedWithSpan<Box<OwnedStatement>>,
+    pub recursive: Option<OwnedRecursiveTerm>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedRecursiveTerm {
+    pub all: bool,
+    pub query: OwnedWithSpan<Box<OwnedStatement>>,
+}
+
+/// A `SELECT` statement copied out of the input, see [`Statement::to_owned`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedStatement {
+    pub with: Box<[OwnedCommonTableExpr]>,
+    pub projection: Box<[OwnedProjection]>,
+    pub from: OwnedTableExpr,
+    pub selection: Option<OwnedExpr>,
+    pub group_by: Box<[OwnedWithSpan<OwnedExpr>]>,
+    pub having: Option<OwnedExpr>,
+    pub order_by: Box<[OwnedOrderByItem]>,
+    pub limit: Option<OwnedWithSpan<u64>>,
+    pub offset: Option<OwnedWithSpan<u64>>,
+}
+
+impl Projection<'_> {
+    pub fn to_owned(&self) -> OwnedProjection {
+        match self {
+            Self::Wildcard(span) => OwnedProjection::Wildcard((*span).into()),
+            Self::Column { column, alias } => OwnedProjection::Column {
+                column: column.to_owned(),
+                alias: alias.map(OwnedSpan::from),
+            },
+            Self::Expr { expr, alias } => OwnedProjection::Expr {
+                expr: owned_with_span(expr, Expr::to_owned),
+                alias: alias.map(OwnedSpan::from),
+            },
+        }
+    }
+}
+
+impl OrderByItem<'_> {
+    pub fn to_owned(&self) -> OwnedOrderByItem {
+        OwnedOrderByItem {
+            expr: owned_with_span(&self.expr, Expr::to_owned),
+            order: self.order,
+            nulls: self.nulls,
+        }
+    }
+}
+
+impl CommonTableExpr<'_> {
+    pub fn to_owned(&self) -> OwnedCommonTableExpr {
+        OwnedCommonTableExpr {
+            name: self.name.into(),
+            columns: self.columns.iter().copied().map(OwnedSpan::from).collect(),
+            query: owned_with_span(&self.query, |query| Box::new(Statement::to_owned(query))),
+            recursive: self.recursive.as_ref().map(|recursive| OwnedRecursiveTerm {
+                all: recursive.all,
+                query: owned_with_span(&recursive.query, |query| {
+                    Box::new(Statement::to_owned(query))
+                }),
+            }),
+        }
+    }
+}
+
+impl Statement<'_> {
+    /// Copies the statement out of the input, keeping the positions of its spans.
+    pub fn to_owned(&self) -> OwnedStatement {
+        let exprs = |exprs: &[WithSpan<'_, Expr<'_>>]| {
+            exprs
+                .iter()
+                .map(|expr| owned_with_span(expr, Expr::to_owned))
+                .collect()
+        };
+        OwnedStatement {
+            with: self.with.iter().map(CommonTableExpr::to_owned).collect(),
+            projection: self.projection.iter().map(Projection::to_owned).collect(),
+            from: self.from.to_owned(),
+            selection: self.selection.as_ref().map(Expr::to_owned),
+            group_by: exprs(&self.group_by),
+            having: self.having.as_ref().map(Expr::to_owned),
+            order_by: self.order_by.iter().map(OrderByItem::to_owned).collect(),
+            limit: self.limit.map(|(span, limit)| (span.into(), limit)),
+            offset: self.offset.map(|(span, offset)| (span.into(), offset)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("wildcard", "SELECT * FROM users");
+        test_case("columns", "select id, name from users");
+        test_case(
+            "case",
+            "SELECT id, CASE WHEN id > 10 THEN 'big' ELSE 'small' END FROM users",
+        );
+        test_case(
+            "where",
+            "SELECT id FROM users WHERE id > 10 AND (name = 'bob' OR name = 'alice')",
+        );
+        test_case(
+            "aggregates",
+            "SELECT count(*), COUNT(DISTINCT name), max(id) - min(id) FROM users",
+        );
+        test_case(
+            "order-by",
+            "SELECT id FROM users WHERE id > 1 ORDER BY name DESC NULLS LAST, id, age + 1 asc",
+        );
+        test_case(
+            "join",
+            "SELECT u.name, o.id FROM users AS u LEFT JOIN orders o ON u.id = o.user_id",
+        );
+        test_case(
+            "alias",
+            "SELECT name AS n, id + 1 AS next FROM users AS u ORDER BY next",
+        );
+        test_case(
+            "limit",
+            "SELECT id FROM users ORDER BY id LIMIT 10 OFFSET 20",
+        );
+        test_case(
+            "group-by",
+            "SELECT name, count(*) FROM users WHERE id > 1 GROUP BY name HAVING count(*) > 2",
+        );
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [Column::new("id", SqlType::I32)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        );
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            "SELECT count(*), sum(id) + 1 FROM users".into(),
+        )
+        .unwrap();
+        assert_eq!(statement.aggregates().len(), 2);
+        let columns = statement.columns(&table_map).unwrap();
+        assert_eq!(columns[0].tp, SqlType::I64);
+        assert_eq!(columns[1].tp, SqlType::I128);
+
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "SELECT id FROM users WHERE count(*) > 1".into()
+        )
+        .is_err());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "SELECT sum(count(id)) FROM users".into()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+                Column::new("age", SqlType::U8),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) =
+            parse("SELECT name, count(*) FROM users GROUP BY name HAVING max(age) > 18").unwrap();
+        assert!(statement.is_grouped());
+        assert_eq!(statement.group_by.len(), 1);
+        assert_eq!(statement.aggregates().len(), 2);
+        assert!(parse("SELECT age + 1, sum(id) FROM users GROUP BY age + 1, name").is_ok());
+        assert!(parse("SELECT name, age FROM users GROUP BY name, age").is_ok());
+        assert!(parse("SELECT count(*) FROM users HAVING count(*) > 1").is_ok());
+        assert!(parse("SELECT upper(name) FROM users GROUP BY name").is_ok());
+        assert!(parse("SELECT users.name, u.age FROM users u GROUP BY name, u.age").is_err());
+        assert!(parse("SELECT u.name, age FROM users u GROUP BY name, u.age").is_ok());
+
+        assert!(parse("SELECT name, age FROM users GROUP BY name").is_err());
+        assert!(parse("SELECT id, count(*) FROM users").is_err());
+        assert!(parse("SELECT * FROM users GROUP BY id").is_err());
+        assert!(parse("SELECT name FROM users GROUP BY name HAVING age > 1").is_err());
+        assert!(parse("SELECT name FROM users GROUP BY unknown").is_err());
+        assert!(parse("SELECT count(*) FROM users GROUP BY count(*)").is_err());
+        assert!(!parse("SELECT name FROM users").unwrap().1.is_grouped());
+    }
+
+    #[test]
+    fn test_order_by() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) =
+            parse("SELECT id FROM users ORDER BY id DESC, name NULLS FIRST, id * 2 ASC").unwrap();
+        let keys = statement
+            .order_by
+            .iter()
+            .map(|item| {
+                (
+                    item.expr.0.fragment().trim(),
+                    item.order,
+                    item.nulls_first(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                ("id", SortOrder::Desc, true),
+                ("name", SortOrder::Asc, true),
+                ("id * 2", SortOrder::Asc, false),
+            ]
+        );
+        assert!(
+            parse("SELECT name, count(*) FROM users GROUP BY name ORDER BY count(*) DESC").is_ok()
+        );
+
+        assert!(parse("SELECT id FROM users ORDER BY age").is_err());
+        assert!(parse("SELECT id FROM users ORDER BY id NULLS LATER").is_err());
+        assert!(parse("SELECT name FROM users GROUP BY name ORDER BY id").is_err());
+    }
+
+    #[test]
+    fn test_limit() {
+        let parse = |input: &'static str| {
+            let (rest, statement) = Statement::parse(input.into()).unwrap();
+            assert!(rest.fragment().is_empty(), "Unparsed input: {rest}");
+            (
+                statement.limit.map(|(_, limit)| limit),
+                statement.offset.map(|(_, offset)| offset),
+            )
+        };
+        assert_eq!(parse("SELECT id FROM users LIMIT 5"), (Some(5), None));
+        assert_eq!(
+            parse("SELECT id FROM users limit 5 offset 0"),
+            (Some(5), Some(0))
+        );
+        assert_eq!(
+            parse("SELECT id FROM users WHERE id > 1 LIMIT 18446744073709551615"),
+            (Some(u64::MAX), None)
+        );
+        assert_eq!(parse("SELECT id FROM users"), (None, None));
+
+        assert!(
+            Statement::parse("SELECT id FROM users LIMIT 18446744073709551616".into()).is_err()
+        );
+        assert!(Statement::parse("SELECT id FROM users LIMIT -1".into()).is_err());
+        assert!(Statement::parse("SELECT id FROM users LIMIT 1 OFFSET a".into()).is_err());
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn test_limit_mysql() {
+        let (_, statement) = Statement::parse("SELECT id FROM users LIMIT 20, 10".into()).unwrap();
+        assert_eq!(statement.limit.unwrap().1, 10);
+        assert_eq!(statement.offset.unwrap().1, 20);
+    }
+
+    #[test]
+    fn test_join() {
+        let mut table_map = TableMap::new();
+        for (table, columns) in [
+            (
+                "users",
+                [
+                    ("id", SqlType::I32),
+                    ("name", SqlType::VarChar(10, LengthUnit::Chars)),
+                ],
+            ),
+            ("orders", [("id", SqlType::I64), ("user_id", SqlType::I32)]),
+        ] {
+            let columns = columns
+                .into_iter()
+                .map(|(name, tp)| {
+                    let mut column = Column::new(name, tp);
+                    column.nullable = false;
+                    (column.name.clone(), column)
+                })
+                .collect();
+            table_map.insert(table.into(), columns);
+        }
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) =
+            parse("SELECT * FROM users u RIGHT JOIN orders ON u.id = user_id").unwrap();
+        let columns = statement.columns(&table_map).unwrap();
+        let columns = columns
+            .iter()
+            .map(|column| (&*column.name, column.nullable))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            [
+                ("id", true),
+                ("name", true),
+                ("id", false),
+                ("user_id", false)
+            ]
+        );
+        let (_, statement) =
+            parse("SELECT orders.id, name FROM users JOIN orders USING (id) WHERE id > 1").unwrap();
+        let columns = statement.columns(&table_map).unwrap();
+        assert_eq!(columns[0].tp, SqlType::I64);
+        assert!(
+            parse("SELECT u.name, count(*) FROM users u CROSS JOIN orders GROUP BY u.name").is_ok()
+        );
+
+        assert!(parse("SELECT id FROM users JOIN orders ON users.id = orders.user_id").is_err());
+        assert!(
+            parse("SELECT name FROM users u JOIN orders o ON u.id = o.id WHERE users.id = 1")
+                .is_err()
+        );
+        assert!(parse("SELECT o.total FROM users JOIN orders o ON true").is_err());
+    }
+
+    #[test]
+    fn test_alias() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) =
+            parse("SELECT u.name AS n, count(*) AS total FROM users AS u GROUP BY u.name ORDER BY total DESC")
+                .unwrap();
+        let columns = statement.columns(&table_map).unwrap();
+        let names = columns.iter().map(|c| &*c.name).collect::<Vec<_>>();
+        assert_eq!(names, ["n", "total"]);
+        assert_eq!(columns[0].tp, SqlType::VarChar(10, LengthUnit::Chars));
+        assert_eq!(
+            statement.projection[1]
+                .alias()
+                .map(|alias| alias.location_offset()),
+            Some(32)
+        );
+
+        assert!(parse("SELECT users.id FROM users AS u").is_err());
+        assert!(parse("SELECT id AS i FROM users WHERE i > 1").is_err());
+        assert!(parse("SELECT id AS FROM users").is_err());
+    }
+
+    #[test]
+    fn test_quoted_identifiers() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "My Table".into(),
+            [
+                Column::new("select", SqlType::I32),
+                Column::new("Say \"\"hi\"\"", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) =
+            parse(r#"SELECT t."select", `Say ""hi""` AS "Greeting" FROM "My Table" AS t"#).unwrap();
+        let columns = statement.columns(&table_map).unwrap();
+        let names = columns.iter().map(|c| &*c.name).collect::<Vec<_>>();
+        assert_eq!(names, ["select", "Greeting"]);
+
+        assert!(parse(r#"SELECT "Select" FROM "My Table""#).is_err());
+        assert!(parse(r#"SELECT "" FROM "My Table""#).is_err());
+        assert!(parse(r#"SELECT "select FROM "My Table""#).is_err());
+    }
+
+    #[test]
+    fn test_cte() {
+        test_case(
+            "cte",
+            "WITH named (n) AS (SELECT name FROM users) SELECT n FROM named",
+        );
+
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) = parse(
+            "WITH big AS (SELECT id FROM users WHERE id > 10), \
+             ids (x) AS (SELECT id FROM big) \
+             SELECT x, u.name FROM ids JOIN users u ON x = u.id",
+        )
+        .unwrap();
+        assert_eq!(statement.with.len(), 2);
+        let columns = statement.columns(&table_map).unwrap();
+        let names = columns.iter().map(|c| &*c.name).collect::<Vec<_>>();
+        assert_eq!(names, ["x", "name"]);
+        assert_eq!(columns[0].tp, SqlType::I32);
+
+        // A common table expression shadows the table with the same name.
+        let (_, statement) =
+            parse("WITH users AS (SELECT name FROM users) SELECT * FROM users").unwrap();
+        let columns = statement.columns(&table_map).unwrap();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(table_map.get("users").unwrap().len(), 2);
+
+        assert!(parse("WITH a AS (SELECT id FROM users) SELECT name FROM a").is_err());
+        assert!(
+            parse("WITH a AS (SELECT id FROM b), b AS (SELECT id FROM users) SELECT * FROM a")
+                .is_err()
+        );
+        assert!(parse("WITH a AS (SELECT age FROM users) SELECT * FROM a").is_err());
+        assert!(parse("WITH a (x, y) AS (SELECT id FROM users) SELECT * FROM a").is_err());
+        assert!(parse(
+            "WITH a AS (SELECT id FROM users), a AS (SELECT id FROM users) SELECT * FROM a"
+        )
+        .is_err());
+        assert!(parse("WITH a AS SELECT id FROM users SELECT * FROM a").is_err());
+    }
+
+    #[test]
+    fn test_recursive_cte() {
+        test_case(
+            "cte-recursive",
+            "WITH RECURSIVE chain (id) AS (SELECT id FROM users WHERE id = 1 UNION ALL SELECT u.id FROM chain c JOIN users u ON u.id = c.id + 1) SELECT id FROM chain",
+        );
+
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| Statement::parse_with_table_map(&table_map, input.into());
+
+        let (_, statement) = parse(
+            "WITH RECURSIVE t (n) AS (SELECT id FROM users UNION SELECT n + 1 FROM t WHERE n < 10) \
+             SELECT n FROM t",
+        )
+        .unwrap();
+        let cte = &statement.with[0];
+        let term = cte.recursive.as_ref().unwrap();
+        assert!(!term.all);
+        assert!(matches!(term.query.1.from, TableExpr::SelfReference { .. }));
+        assert!(matches!(cte.query.1.from, TableExpr::Table { .. }));
+        let columns = statement.columns(&table_map).unwrap();
+        assert_eq!(columns[0].tp, SqlType::I32);
+        assert!(columns[0].nullable);
+
+        // Without RECURSIVE there is no UNION.
+        assert!(
+            parse("WITH t AS (SELECT id FROM users UNION SELECT id FROM t) SELECT * FROM t")
+                .is_err()
+        );
+        // The anchor can't reference the expression, the term only once.
+        assert!(parse(
+            "WITH RECURSIVE t AS (SELECT id FROM t UNION SELECT id FROM users) SELECT * FROM t"
+        )
+        .is_err());
+        assert!(parse("WITH RECURSIVE t AS (SELECT id FROM users UNION SELECT t.id FROM t JOIN t AS t2 ON true) SELECT * FROM t").is_err());
+        // The term must match the anchor.
+        assert!(parse(
+            "WITH RECURSIVE t AS (SELECT id FROM users UNION SELECT id, id FROM t) SELECT * FROM t"
+        )
+        .is_err());
+        assert!(parse("WITH RECURSIVE t AS (SELECT id FROM users UNION SELECT name FROM users) SELECT * FROM t").is_err());
+        assert!(parse("WITH RECURSIVE t AS (SELECT id FROM users UNION SELECT count(*) FROM t) SELECT * FROM t").is_err());
+        // A term without a self-reference and plain expressions are accepted.
+        assert!(parse("WITH RECURSIVE a AS (SELECT id FROM users), t AS (SELECT id FROM users UNION ALL SELECT id FROM a) SELECT * FROM t").is_ok());
+    }
+
+    #[test]
+    fn test_bind_parameters() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let parse = |input: &'static str| {
+            Statement::parse_with_table_map(&table_map, input.into())
+                .unwrap()
+                .1
+        };
+
+        let statement = parse("SELECT id, $2 FROM users WHERE name = $1 AND id > $2");
+        let bound = statement
+            .bind_parameters(&table_map, &[Value::VarChar("a".into()), Value::I64(3)])
+            .unwrap();
+        let Some(Expr::Binary { right, .. }) = &bound.selection else {
+            panic!("Expected a binary expression");
+        };
+        assert!(matches!(
+            &**right,
+            Expr::Binary { right, .. } if matches!(&**right, Expr::Literal((_, Value::I64(3))))
+        ));
+        assert!(matches!(
+            &bound.projection[1],
+            Projection::Expr {
+                expr: (_, Expr::Literal((_, Value::I64(3)))),
+                ..
+            }
+        ));
+
+        let statement = parse("SELECT id FROM users WHERE id = ? OR name = ?");
+        assert!(statement
+            .bind_parameters(&table_map, &[Value::I64(1), Value::VarChar("a".into())])
+            .is_ok());
+        // The values must fit where the parameters are used.
+        assert!(statement
+            .bind_parameters(&table_map, &[Value::VarChar("a".into()), Value::I64(1)])
+            .is_err());
+        assert!(statement
+            .bind_parameters(&table_map, &[Value::I64(1)])
+            .is_err());
+        assert!(statement
+            .bind_parameters(&table_map, &[Value::I64(1), Value::Null, Value::Null])
+            .is_err());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "SELECT id FROM users WHERE id = ? OR id = $1".into()
+        )
+        .unwrap()
+        .1
+        .bind_parameters(&table_map, &[Value::I64(1)])
+        .is_err());
+    }
+
+    #[test]
+    fn test_where_unknown_column() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [Column::new(
+                "id",
+                crate::ast::commands::create::SqlType::I32,
+            )]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "SELECT id FROM users WHERE id = 1".into()
+        )
+        .is_ok());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "SELECT id FROM users WHERE age = 1".into()
+        )
+        .is_err());
+        assert!(Statement::parse_with_table_map(
+            &table_map,
+            "SELECT CASE WHEN id > 1 THEN age END FROM users".into()
+        )
+        .is_err());
+
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            "SELECT CASE id WHEN 1 THEN 'one' WHEN 2 THEN 'two' END FROM users".into(),
+        )
+        .unwrap();
+        let columns = statement.columns(&table_map).unwrap();
+        assert_eq!(
+            *columns[0].name,
+            *"CASE id WHEN 1 THEN 'one' WHEN 2 THEN 'two' END"
+        );
+        assert_eq!(
+            columns[0].tp,
+            crate::ast::commands::create::SqlType::VarChar(3, LengthUnit::Chars)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use nom::error::context;
+
+use crate::{
+    ast::{
+        commands::create::Column,
+        table::{OwnedTableName, TableName},
+    },
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    lexer::{cut, TokenCursor},
+    parse::{OwnedSpan, Parse, RawSpan, TableMap},
+    parsers::identifier::identifier,
+};
+
+/// An introspection statement, answered from the catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Statement<'a> {
+    /// `SHOW TABLES [FROM schema]`, without a schema the default one is listed.
+    Tables { schema: Option<RawSpan<'a>> },
+    /// `SHOW COLUMNS FROM table` or `DESCRIBE table`.
+    Columns(TableName<'a>),
+}
+
+/// The answer to an introspection statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShowResult {
+    /// The table names, sorted.
+    Tables(Vec<Box<str>>),
+    /// The columns of the table, sorted by name.
+    Columns(Vec<Column>),
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Show", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let statement = if tokens.opt_keyword("show").is_some() {
+                if tokens.opt_keyword("tables").is_some() {
+                    let schema = if tokens.opt_keyword("from").is_some() {
+                        Some(cut(tokens.parse(context("Schema Name", identifier)))?)
+                    } else {
+                        None
+                    };
+                    Self::Tables { schema }
+                } else if tokens.opt_keyword("columns").is_some() {
+                    tokens.keyword("from")?;
+                    Self::Columns(cut(tokens.parse(TableName::parse))?)
+                } else {
+                    return Err(tokens.unexpected("TABLES or COLUMNS"));
+                }
+            } else if tokens.opt_keyword("describe").is_some()
+                || tokens.opt_keyword("desc").is_some()
+            {
+                Self::Columns(cut(tokens.parse(TableName::parse))?)
+            } else {
+                return Err(tokens.unexpected("SHOW or DESCRIBE"));
+            };
+            Ok((tokens.rest(), statement))
+        })(input)
+    }
+}
+
+impl<'a> Statement<'a> {
+    /// Parses an introspection statement, checking the schema or table exists.
+    /// # Errors
+    /// Returns an error if the input is not a valid introspection statement.
+    /// Returns an error pointing at an unknown schema or table.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let (input, statement) = Self::parse(input)?;
+        Ok((input, statement.bind(table_map)?))
+    }
+
+    /// Checks the schema or table exists.
+    /// # Errors
+    /// Returns an error pointing at an unknown schema or table.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        self.result(table_map)?;
+        Ok(self)
+    }
+
+    /// Answers the statement from `table_map`.
+    /// # Errors
+    /// Returns an error pointing at an unknown schema or table.
+    pub fn result(&self, table_map: &TableMap) -> Result<ShowResult, nom::Err<RawParseError<'a>>> {
+        let failure = |span, error| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        match self {
+            Self::Tables { schema } => {
+                let tables = match schema {
+                    Some(schema) => table_map
+                        .schema(schema.fragment())
+                        .ok_or_else(|| failure(*schema, ParseError::SchemaNotFound))?,
+                    None => table_map
+                        .schema(table_map.default_schema())
+                        .ok_or_else(|| failure(RawSpan::new(""), ParseError::SchemaNotFound))?,
+                };
+                let mut names = tables.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                Ok(ShowResult::Tables(names))
+            }
+            Self::Columns(table_name) => {
+                let columns = table_name
+                    .lookup(table_map)
+                    .map_err(|(span, error)| failure(span, error))?;
+                let mut columns = columns.values().cloned().collect::<Vec<_>>();
+                columns.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(ShowResult::Columns(columns))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedStatement {
+    Tables { schema: Option<OwnedSpan> },
+    Columns(OwnedTableName),
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        match self {
+            Self::Tables { schema } => OwnedStatement::Tables {
+                schema: schema.map(OwnedSpan::from),
+            },
+            Self::Columns(table_name) => OwnedStatement::Columns(table_name.to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::ast::commands::create::{LengthUnit, SqlType};
+
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("tables", "SHOW TABLES FROM sales");
+        test_case("columns", "show columns from sales.orders");
+        test_case("describe", "DESCRIBE users");
+    }
+
+    #[test]
+    fn test_result() {
+        let mut table_map = TableMap::new();
+        for table in ["users", "accounts"] {
+            table_map.insert(
+                table.into(),
+                [
+                    Column::new("name", SqlType::VarChar(10, LengthUnit::Chars)),
+                    Column::new("id", SqlType::I32),
+                ]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+            );
+        }
+        table_map.create_schema("sales");
+        let result = |input: &'static str| {
+            Statement::parse_with_table_map(&table_map, input.into())
+                .map(|(_, statement)| statement.result(&table_map).unwrap())
+        };
+
+        assert_eq!(
+            result("SHOW TABLES").unwrap(),
+            ShowResult::Tables(vec!["accounts".into(), "users".into()])
+        );
+        assert_eq!(
+            result("SHOW TABLES FROM sales").unwrap(),
+            ShowResult::Tables(vec![])
+        );
+        let ShowResult::Columns(columns) = result("DESC public.users").unwrap() else {
+            panic!("Expected columns");
+        };
+        assert_eq!(
+            columns.iter().map(|c| &*c.name).collect::<Vec<_>>(),
+            ["id", "name"]
+        );
+        assert_eq!(
+            result("SHOW COLUMNS FROM users").unwrap(),
+            result("DESCRIBE users").unwrap()
+        );
+
+        assert!(result("SHOW TABLES FROM archive").is_err());
+        assert!(result("DESCRIBE orders").is_err());
+        assert!(result("SHOW COLUMNS users").is_err());
+    }
+}
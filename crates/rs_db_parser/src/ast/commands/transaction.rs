@@ -0,0 +1,196 @@
+use std::fmt;
+
+use nom::error::context;
+
+use crate::{
+    errors::ParseResult,
+    lexer::{cut, TokenCursor},
+    parse::{OwnedSpan, Parse, RawSpan},
+    parsers::identifier::identifier,
+};
+
+/// A transaction control statement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Statement<'a> {
+    /// `BEGIN [TRANSACTION | WORK]`.
+    Begin,
+    /// `COMMIT [TRANSACTION | WORK]`.
+    Commit,
+    /// `ROLLBACK [TRANSACTION | WORK] [TO [SAVEPOINT] name]`, without a savepoint the whole
+    /// transaction is rolled back.
+    Rollback { savepoint: Option<RawSpan<'a>> },
+    /// `SAVEPOINT name`.
+    Savepoint(RawSpan<'a>),
+    /// `SET TRANSACTION ISOLATION LEVEL level`, the level of the transactions the session
+    /// begins next.
+    SetIsolationLevel(IsolationLevel),
+}
+
+/// How much a transaction sees of the transactions running beside it.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum IsolationLevel {
+    /// Each statement sees the rows committed before it started.
+    #[default]
+    ReadCommitted,
+    /// The transaction runs as if no other one ran beside it.
+    Serializable,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ReadCommitted => "READ COMMITTED",
+            Self::Serializable => "SERIALIZABLE",
+        })
+    }
+}
+
+impl<'a> Parse<'a> for IsolationLevel {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Isolation Level", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let level = if tokens.opt_keyword("read").is_some() {
+                tokens.keyword("committed")?;
+                Self::ReadCommitted
+            } else if tokens.opt_keyword("serializable").is_some() {
+                Self::Serializable
+            } else {
+                return Err(tokens.unexpected("READ COMMITTED or SERIALIZABLE"));
+            };
+            Ok((tokens.rest(), level))
+        })(input)
+    }
+}
+
+/// Reads the optional `TRANSACTION` or `WORK` keyword that may follow `BEGIN`, `COMMIT`
+/// and `ROLLBACK`.
+fn opt_transaction_keyword(tokens: &mut TokenCursor<'_>) {
+    if tokens.opt_keyword("transaction").is_none() {
+        tokens.opt_keyword("work");
+    }
+}
+
+fn savepoint_name(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    context("Savepoint Name", identifier)(input)
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Transaction", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let statement = if tokens.opt_keyword("begin").is_some() {
+                opt_transaction_keyword(&mut tokens);
+                Self::Begin
+            } else if tokens.opt_keyword("commit").is_some() {
+                opt_transaction_keyword(&mut tokens);
+                Self::Commit
+            } else if tokens.opt_keyword("rollback").is_some() {
+                opt_transaction_keyword(&mut tokens);
+                let savepoint = if tokens.opt_keyword("to").is_some() {
+                    tokens.opt_keyword("savepoint");
+                    Some(cut(tokens.parse(savepoint_name))?)
+                } else {
+                    None
+                };
+                Self::Rollback { savepoint }
+            } else if tokens.opt_keyword("savepoint").is_some() {
+                Self::Savepoint(cut(tokens.parse(savepoint_name))?)
+            } else if tokens.opt_keyword("set").is_some() {
+                tokens.keyword("transaction")?;
+                cut(tokens
+                    .keyword("isolation")
+                    .and_then(|_| tokens.keyword("level"))
+                    .and_then(|_| tokens.parse(IsolationLevel::parse))
+                    .map(Self::SetIsolationLevel))?
+            } else {
+                return Err(tokens.unexpected("BEGIN, COMMIT, ROLLBACK, SAVEPOINT or SET"));
+            };
+            Ok((tokens.rest(), statement))
+        })(input)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedStatement {
+    Begin,
+    Commit,
+    Rollback { savepoint: Option<OwnedSpan> },
+    Savepoint(OwnedSpan),
+    SetIsolationLevel(IsolationLevel),
+}
+
+impl Statement<'_> {
+    pub fn to_owned(&self) -> OwnedStatement {
+        match self {
+            Self::Begin => OwnedStatement::Begin,
+            Self::Commit => OwnedStatement::Commit,
+            Self::Rollback { savepoint } => OwnedStatement::Rollback {
+                savepoint: savepoint.map(OwnedSpan::from),
+            },
+            Self::Savepoint(name) => OwnedStatement::Savepoint((*name).into()),
+            Self::SetIsolationLevel(level) => OwnedStatement::SetIsolationLevel(*level),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn test_case(suffix: &str, input: &str) {
+        let value = Statement::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(value));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        test_case("rollback-to", "ROLLBACK TO SAVEPOINT before_update");
+        test_case("savepoint", "savepoint sp1");
+    }
+
+    #[test]
+    fn test_parse_keywords() {
+        let parse =
+            |input: &'static str| Statement::parse(input.into()).map(|(_, statement)| statement);
+        for input in ["BEGIN", "begin transaction", "BEGIN WORK"] {
+            assert_eq!(parse(input).unwrap(), Statement::Begin);
+        }
+        for input in ["COMMIT", "commit work", "COMMIT TRANSACTION"] {
+            assert_eq!(parse(input).unwrap(), Statement::Commit);
+        }
+        assert_eq!(
+            parse("ROLLBACK WORK").unwrap(),
+            Statement::Rollback { savepoint: None }
+        );
+        let Statement::Rollback {
+            savepoint: Some(savepoint),
+        } = parse("rollback transaction to sp1").unwrap()
+        else {
+            panic!("Expected a savepoint");
+        };
+        assert_eq!(*savepoint.fragment(), "sp1");
+
+        assert!(parse("ROLLBACK TO").is_err());
+        assert!(parse("SAVEPOINT").is_err());
+        assert!(parse("BEGINNING").is_err());
+
+        assert_eq!(
+            parse("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE").unwrap(),
+            Statement::SetIsolationLevel(IsolationLevel::Serializable)
+        );
+        assert_eq!(
+            parse("set transaction isolation level read  committed").unwrap(),
+            Statement::SetIsolationLevel(IsolationLevel::ReadCommitted)
+        );
+        assert!(parse("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ").is_err());
+        assert!(parse("SET TRANSACTION").is_err());
+    }
+}
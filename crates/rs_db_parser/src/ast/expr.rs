@@ -0,0 +1,2093 @@
+use std::{borrow::Cow, cmp::Ordering};
+
+use nom::{
+    branch::alt,
+    character::complete::char,
+    combinator::{cut, map, opt, verify},
+    error::context,
+    sequence::{pair, preceded, terminated},
+};
+
+use crate::{
+    ast::commands::create::{LengthUnit, SqlType},
+    decimal::{DIVISION_SCALE, MAX_PRECISION},
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    functions::{FunctionRegistry, ReturnType},
+    lexer::{self, Token, TokenCursor, TokenKind},
+    like::LikePattern,
+    parse::{owned_with_span, ColumnMap, OwnedSpan, OwnedWithSpan, Parse, RawSpan, Span, WithSpan},
+    parsers::{
+        comma_sep, identifier::identifier, limits::DepthGuard, parse_with_span,
+        string::string_literal,
+    },
+    value::Value,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryOperator {
+    Not,
+    Minus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOperator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    /// `json -> key`, the field of an object or the element of an array, as JSON.
+    JsonGet,
+    /// `json ->> key`, like [`JsonGet`](Self::JsonGet) but as text.
+    JsonGetText,
+}
+
+/// A reference to a column, `column` or `table.column`. The qualifier is the name or
+/// alias of a table of the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColumnRef<'a> {
+    pub qualifier: Option<RawSpan<'a>>,
+    pub name: RawSpan<'a>,
+}
+
+/// A function that folds the values of a column over many rows into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr<'a> {
+    Literal(WithSpan<'a, Value>),
+    /// A prepared statement placeholder, `$n` or `?`. `?` is stored as `0`, the
+    /// placeholders are numbered in source order when the parameters are bound.
+    Parameter(WithSpan<'a, u16>),
+    Column(ColumnRef<'a>),
+    Unary {
+        op: WithSpan<'a, UnaryOperator>,
+        expr: Box<Expr<'a>>,
+    },
+    Binary {
+        op: WithSpan<'a, BinaryOperator>,
+        left: Box<Expr<'a>>,
+        right: Box<Expr<'a>>,
+    },
+    /// An expression between parentheses, the span includes the parentheses.
+    Nested(WithSpan<'a, Box<Expr<'a>>>),
+    /// `expr [NOT] BETWEEN low AND high`, both bounds are inclusive.
+    Between {
+        expr: Box<Expr<'a>>,
+        negated: bool,
+        low: Box<Expr<'a>>,
+        high: Box<Expr<'a>>,
+    },
+    /// `expr [NOT] IN (value, ...)`.
+    InList {
+        expr: Box<Expr<'a>>,
+        negated: bool,
+        list: Box<[Expr<'a>]>,
+    },
+    /// `expr [NOT] LIKE 'pattern'`, the span points at the pattern literal.
+    Like {
+        expr: Box<Expr<'a>>,
+        negated: bool,
+        pattern: WithSpan<'a, LikePattern>,
+    },
+    /// `expr IS [NOT] NULL`, the only predicate that is never UNKNOWN.
+    IsNull {
+        expr: Box<Expr<'a>>,
+        negated: bool,
+    },
+    /// `CASE [operand] WHEN ... THEN ... [ELSE ...] END`, the span points at `CASE`.
+    /// With an operand each `WHEN` is compared to it, otherwise each `WHEN` is a condition.
+    Case {
+        span: RawSpan<'a>,
+        operand: Option<Box<Expr<'a>>>,
+        branches: Box<[(Expr<'a>, Expr<'a>)]>,
+        else_result: Option<Box<Expr<'a>>>,
+    },
+    /// `name(arg, ...)`, a call to a function of the
+    /// [`FunctionRegistry`](crate::functions::FunctionRegistry).
+    Function {
+        name: RawSpan<'a>,
+        args: Box<[Expr<'a>]>,
+        /// The type the result is converted to, for a function returning the common type of
+        /// its arguments. Known once the statement is bound, see
+        /// [`Expr::bind_function_types`].
+        tp: Option<SqlType>,
+    },
+    /// `COUNT(*)` or `function([DISTINCT] arg)`, `arg` is `None` for `COUNT(*)`.
+    /// Kept apart from [`Expr::Function`] as it is computed over a group of rows.
+    Aggregate {
+        function: WithSpan<'a, AggregateFunction>,
+        distinct: bool,
+        arg: Option<Box<Expr<'a>>>,
+    },
+    /// `CAST(expr AS type)`, the span points at `CAST`.
+    Cast {
+        span: RawSpan<'a>,
+        expr: Box<Expr<'a>>,
+        tp: WithSpan<'a, SqlType>,
+    },
+    /// `array[index]`, the element at `index` counting from 1. The span of the index
+    /// includes the brackets.
+    Subscript {
+        expr: Box<Expr<'a>>,
+        index: WithSpan<'a, Box<Expr<'a>>>,
+    },
+}
+
+/// The part of a `BETWEEN`, `IN`, `LIKE` or `IS NULL` predicate that follows its operand.
+enum Predicate<'a> {
+    Between { low: Expr<'a>, high: Expr<'a> },
+    InList(Box<[Expr<'a>]>),
+    Like(WithSpan<'a, LikePattern>),
+    IsNull,
+}
+
+/// Binding power of `NOT`, it binds looser than comparisons: `NOT a = b` is `NOT (a = b)`.
+pub(crate) const NOT_BINDING_POWER: u8 = 5;
+
+/// Left binding power of `BETWEEN`, `IN`, `LIKE` and `IS NULL`, the same as comparisons.
+pub(crate) const PREDICATE_BINDING_POWER: u8 = 7;
+
+/// Binding power of the `BETWEEN` bounds, tight enough to stop before its own `AND`.
+pub(crate) const BETWEEN_BOUND_BINDING_POWER: u8 = 9;
+
+/// Binding power of unary `-`, it binds tighter than every binary operator.
+pub(crate) const MINUS_BINDING_POWER: u8 = 13;
+
+impl BinaryOperator {
+    /// Left and right binding powers, a higher power binds tighter.
+    /// Comparisons are non-associative, `a = b = c` is rejected.
+    pub(crate) const fn binding_power(self) -> (u8, u8) {
+        match self {
+            Self::Or => (1, 2),
+            Self::And => (3, 4),
+            Self::Eq | Self::NotEq | Self::Lt | Self::LtEq | Self::Gt | Self::GtEq => (7, 8),
+            Self::Plus | Self::Minus => (9, 10),
+            Self::Multiply | Self::Divide | Self::Modulo => (11, 12),
+            Self::JsonGet | Self::JsonGetText => (13, 14),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_arithmetic(self) -> bool {
+        matches!(
+            self,
+            Self::Plus | Self::Minus | Self::Multiply | Self::Divide | Self::Modulo
+        )
+    }
+
+    #[must_use]
+    pub const fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Self::Eq | Self::NotEq | Self::Lt | Self::LtEq | Self::Gt | Self::GtEq
+        )
+    }
+
+    #[must_use]
+    pub const fn is_json(self) -> bool {
+        matches!(self, Self::JsonGet | Self::JsonGetText)
+    }
+}
+
+impl BinaryOperator {
+    /// The operator `token` is, if any.
+    fn from_token(token: Token<'_>) -> Option<Self> {
+        match token.kind {
+            TokenKind::Operator => Some(match *token.span.fragment() {
+                "<=" => Self::LtEq,
+                ">=" => Self::GtEq,
+                "<>" | "!=" => Self::NotEq,
+                "=" => Self::Eq,
+                "<" => Self::Lt,
+                ">" => Self::Gt,
+                "+" => Self::Plus,
+                "->>" => Self::JsonGetText,
+                "->" => Self::JsonGet,
+                "-" => Self::Minus,
+                "*" => Self::Multiply,
+                "/" => Self::Divide,
+                "%" => Self::Modulo,
+                _ => return None,
+            }),
+            TokenKind::Keyword | TokenKind::Identifier => {
+                let word = token.span.fragment();
+                if word.eq_ignore_ascii_case("and") {
+                    Some(Self::And)
+                } else if word.eq_ignore_ascii_case("or") {
+                    Some(Self::Or)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Parse<'a> for BinaryOperator {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let op = tokens
+            .peek()
+            .and_then(Self::from_token)
+            .ok_or_else(|| tokens.unexpected("an operator"))?;
+        tokens.next();
+        Ok((tokens.rest(), op))
+    }
+}
+
+impl<'a> Parse<'a> for ColumnRef<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Column Name",
+            map(
+                pair(opt(terminated(identifier, char('.'))), identifier),
+                |(qualifier, name)| Self { qualifier, name },
+            ),
+        )(input)
+    }
+}
+
+impl ColumnRef<'_> {
+    /// The reference as written, `column` or `table.column`.
+    #[must_use]
+    pub fn key(&self) -> Cow<'_, str> {
+        match self.qualifier {
+            Some(qualifier) => Cow::Owned(format!("{}.{}", qualifier.fragment(), self.name)),
+            None => Cow::Borrowed(self.name.fragment()),
+        }
+    }
+}
+
+impl<'a> Parse<'a> for AggregateFunction {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let function = if tokens.opt_keyword("count").is_some() {
+            Self::Count
+        } else if tokens.opt_keyword("sum").is_some() {
+            Self::Sum
+        } else if tokens.opt_keyword("avg").is_some() {
+            Self::Avg
+        } else if tokens.opt_keyword("min").is_some() {
+            Self::Min
+        } else if tokens.opt_keyword("max").is_some() {
+            Self::Max
+        } else {
+            return Err(tokens.unexpected("COUNT, SUM, AVG, MIN or MAX"));
+        };
+        Ok((tokens.rest(), function))
+    }
+}
+
+impl<'a> Parse<'a> for Expr<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context("Expression", |input| {
+            let mut tokens = TokenCursor::new(input);
+            let expr = Self::parse_binding_power(&mut tokens, 0)?;
+            Ok((tokens.rest(), expr))
+        })(input)
+    }
+}
+
+impl<'a> Expr<'a> {
+    /// Precedence climbing: parses a prefix expression, then keeps folding binary operators
+    /// into it while they bind at least as tight as `min_power`.
+    fn parse_binding_power(
+        tokens: &mut TokenCursor<'a>,
+        min_power: u8,
+    ) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        let _depth = DepthGuard::enter(tokens.rest())?;
+        let mut left = Self::parse_prefix(tokens)?;
+        let mut last_comparison = false;
+
+        loop {
+            // Subscripts bind tighter than every operator, `-a[1]` is `-(a[1])`.
+            if tokens.is_next_symbol("[") {
+                let index = tokens.with_span(|tokens| {
+                    tokens.symbol("[")?;
+                    let index = lexer::cut(tokens.parse(Self::parse))?;
+                    lexer::cut(tokens.symbol("]"))?;
+                    Ok(Box::new(index))
+                })?;
+                left = Self::Subscript {
+                    expr: Box::new(left),
+                    index,
+                };
+                continue;
+            }
+
+            if PREDICATE_BINDING_POWER >= min_power {
+                match tokens.opt(|tokens| tokens.with_span(Self::parse_predicate))? {
+                    Some((span, _)) if last_comparison => return Err(chained_comparison(span)),
+                    Some((_, (negated, predicate))) => {
+                        left = predicate.apply(left, negated);
+                        last_comparison = true;
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+
+            let Some(token) = tokens.peek() else {
+                break;
+            };
+            let Some(op) = BinaryOperator::from_token(token) else {
+                break;
+            };
+            let (left_power, right_power) = op.binding_power();
+            if left_power < min_power {
+                break;
+            }
+            if last_comparison && op.is_comparison() {
+                return Err(chained_comparison(token.span));
+            }
+            tokens.next();
+            let right = lexer::cut(Self::parse_binding_power(tokens, right_power))?;
+            last_comparison = op.is_comparison();
+            left = Self::Binary {
+                op: (token.span, op),
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parses `[NOT] BETWEEN ...`, `[NOT] IN (...)`, `[NOT] LIKE ...` or `IS [NOT] NULL`,
+    /// without the operand.
+    fn parse_predicate(
+        tokens: &mut TokenCursor<'a>,
+    ) -> Result<(bool, Predicate<'a>), nom::Err<RawParseError<'a>>> {
+        if tokens.opt_keyword("is").is_some() {
+            let negated = tokens.opt_keyword("not").is_some();
+            lexer::cut(tokens.keyword("null"))?;
+            return Ok((negated, Predicate::IsNull));
+        }
+        let negated = tokens.opt_keyword("not").is_some();
+        let predicate = if tokens.opt_keyword("between").is_some() {
+            let low = lexer::cut(Self::parse_binding_power(
+                tokens,
+                BETWEEN_BOUND_BINDING_POWER,
+            ))?;
+            lexer::cut(tokens.keyword("and"))?;
+            let high = lexer::cut(Self::parse_binding_power(
+                tokens,
+                BETWEEN_BOUND_BINDING_POWER,
+            ))?;
+            Predicate::Between { low, high }
+        } else if tokens.opt_keyword("in").is_some() {
+            lexer::cut(tokens.symbol("("))?;
+            let list = lexer::cut(tokens.parse(comma_sep(Self::parse)))?;
+            lexer::cut(tokens.symbol(")"))?;
+            Predicate::InList(list.into())
+        } else if tokens.opt_keyword("like").is_some() {
+            Predicate::Like(lexer::cut(tokens.parse(|i| {
+                parse_with_span(i, map(string_literal, |(_, text)| LikePattern::new(&text)))
+            }))?)
+        } else {
+            return Err(tokens.unexpected("BETWEEN, IN or LIKE"));
+        };
+        Ok((negated, predicate))
+    }
+
+    /// Parses `$n`, numbered from 1, or `?`.
+    fn parse_parameter(input: RawSpan<'a>) -> ParseResult<'a, u16> {
+        context(
+            "Parameter",
+            alt((
+                map(char('?'), |_| 0),
+                preceded(char('$'), cut(verify(u16::parse, |n| *n > 0))),
+            )),
+        )(input)
+    }
+
+    fn parse_prefix(tokens: &mut TokenCursor<'a>) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        if let Some(span) = tokens.opt_keyword("not") {
+            let expr = Self::parse_binding_power(tokens, NOT_BINDING_POWER)?;
+            return Ok(Self::Unary {
+                op: (span, UnaryOperator::Not),
+                expr: Box::new(expr),
+            });
+        }
+        if tokens.is_next_symbol("(") {
+            let nested = tokens.with_span(|tokens| {
+                tokens.symbol("(")?;
+                let expr = lexer::cut(tokens.parse(Self::parse))?;
+                lexer::cut(tokens.symbol(")"))?;
+                Ok(Box::new(expr))
+            })?;
+            return Ok(Self::Nested(nested));
+        }
+        if tokens.is_next("case") {
+            return Self::parse_case(tokens);
+        }
+        if let Some(cast) = tokens.opt(Self::parse_cast)? {
+            return Ok(cast);
+        }
+        if let Some(aggregate) = tokens.opt(Self::parse_aggregate)? {
+            return Ok(aggregate);
+        }
+        if let Some(literal) = tokens.opt_parse(|i| parse_with_span(i, Value::parse))? {
+            return Ok(Self::Literal(literal));
+        }
+        if let Some(parameter) = tokens.opt_parse(|i| parse_with_span(i, Self::parse_parameter))? {
+            return Ok(Self::Parameter(parameter));
+        }
+        if let Some(minus) = tokens.opt(|tokens| {
+            let span = tokens.symbol("-")?;
+            let expr = Self::parse_binding_power(tokens, MINUS_BINDING_POWER)?;
+            Ok(Self::Unary {
+                op: (span, UnaryOperator::Minus),
+                expr: Box::new(expr),
+            })
+        })? {
+            return Ok(minus);
+        }
+        if let Some(name) = tokens.opt(|tokens| {
+            let name = tokens.parse(identifier)?;
+            tokens.symbol("(")?;
+            Ok(name)
+        })? {
+            let args = if tokens.is_next_symbol(")") {
+                Vec::new()
+            } else {
+                lexer::cut(tokens.parse(comma_sep(Self::parse)))?
+            };
+            lexer::cut(tokens.symbol(")"))?;
+            return Ok(Self::Function {
+                name,
+                args: args.into(),
+                tp: None,
+            });
+        }
+        Ok(Self::Column(tokens.parse(ColumnRef::parse)?))
+    }
+
+    fn parse_case(tokens: &mut TokenCursor<'a>) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        tokens.context("Case", |tokens| {
+            let span = tokens.keyword("case")?;
+            let operand = if tokens.is_next("when") {
+                None
+            } else {
+                Some(Box::new(lexer::cut(tokens.parse(Self::parse))?))
+            };
+            let mut branches = Vec::new();
+            while tokens.opt_keyword("when").is_some() {
+                let condition = lexer::cut(tokens.parse(Self::parse))?;
+                lexer::cut(tokens.keyword("then"))?;
+                let result = lexer::cut(tokens.parse(Self::parse))?;
+                branches.push((condition, result));
+            }
+            if branches.is_empty() {
+                return lexer::cut(Err(tokens.unexpected("WHEN")));
+            }
+            let else_result = if tokens.opt_keyword("else").is_some() {
+                Some(Box::new(lexer::cut(tokens.parse(Self::parse))?))
+            } else {
+                None
+            };
+            lexer::cut(tokens.keyword("end"))?;
+            Ok(Self::Case {
+                span,
+                operand,
+                branches: branches.into(),
+                else_result,
+            })
+        })
+    }
+
+    fn parse_cast(tokens: &mut TokenCursor<'a>) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        tokens.context("Cast", |tokens| {
+            let span = tokens.keyword("cast")?;
+            tokens.symbol("(")?;
+            let expr = Box::new(lexer::cut(tokens.parse(Self::parse))?);
+            lexer::cut(tokens.keyword("as"))?;
+            let tp = lexer::cut(tokens.parse(|i| parse_with_span(i, SqlType::parse)))?;
+            lexer::cut(tokens.symbol(")"))?;
+            Ok(Self::Cast { span, expr, tp })
+        })
+    }
+
+    /// Parses `COUNT(*)` or `function([DISTINCT] arg)`, only `COUNT` accepts `*`.
+    fn parse_aggregate(tokens: &mut TokenCursor<'a>) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        let (function, distinct, arg) = tokens.context("Aggregate", |tokens| {
+            let function = tokens.parse(|i| parse_with_span(i, AggregateFunction::parse))?;
+            tokens.symbol("(")?;
+            let (distinct, arg) = if tokens.opt_symbol("*").is_some() {
+                (false, None)
+            } else {
+                let distinct = tokens.opt_keyword("distinct").is_some();
+                (
+                    distinct,
+                    Some(Box::new(lexer::cut(tokens.parse(Self::parse))?)),
+                )
+            };
+            lexer::cut(tokens.symbol(")"))?;
+            Ok((function, distinct, arg))
+        })?;
+        if arg.is_none() && function.1 != AggregateFunction::Count {
+            return Err(custom_failure(
+                function.0,
+                nom_supreme::error::BaseErrorKind::External(Box::new(
+                    ParseError::AggregateWildcard,
+                )),
+            ));
+        }
+        Ok(Self::Aggregate {
+            function,
+            distinct,
+            arg,
+        })
+    }
+
+    /// Evaluates an expression made only of literals. Comparisons and predicates follow SQL
+    /// three-valued logic, UNKNOWN is represented as `NULL`. Returns `None` if the expression
+    /// depends on a column or an aggregate.
+    /// # Errors
+    /// Returns an error pointing at the operator that overflows, divides by zero or is given
+    /// operands of the wrong type, or at the function that is unknown or fails.
+    pub fn fold(
+        &self,
+        functions: &FunctionRegistry,
+    ) -> Result<Option<Value>, nom::Err<RawParseError<'a>>> {
+        let to_failure = |span: RawSpan<'a>, error: ParseError| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        let value = match self {
+            Self::Literal((_, value)) => value.clone(),
+            Self::Column(_) | Self::Parameter(_) | Self::Aggregate { .. } => return Ok(None),
+            Self::Nested((_, expr)) => return expr.fold(functions),
+            Self::Unary {
+                op: (span, op),
+                expr,
+            } => {
+                let Some(value) = expr.fold(functions)? else {
+                    return Ok(None);
+                };
+                match op {
+                    UnaryOperator::Minus => value.checked_neg(),
+                    UnaryOperator::Not => value.truth().map(|truth| truth.map(|t| !t).into()),
+                }
+                .map_err(|e| to_failure(*span, e))?
+            }
+            Self::Binary {
+                op: (span, op),
+                left,
+                right,
+            } => {
+                let (Some(left), Some(right)) = (left.fold(functions)?, right.fold(functions)?)
+                else {
+                    return Ok(None);
+                };
+                evaluate_binary(*op, &left, &right).map_err(|e| to_failure(*span, e))?
+            }
+            Self::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let (Some(value), Some(low), Some(high)) = (
+                    expr.fold(functions)?,
+                    low.fold(functions)?,
+                    high.fold(functions)?,
+                ) else {
+                    return Ok(None);
+                };
+                let in_range = value
+                    .compare(&low)
+                    .and_then(|above| {
+                        let below = value.compare(&high)?;
+                        Ok(and(above.map(Ordering::is_ge), below.map(Ordering::is_le)))
+                    })
+                    .map_err(|e| to_failure(expr.span(), e))?;
+                in_range.map(|b| b != *negated).into()
+            }
+            Self::InList {
+                expr,
+                negated,
+                list,
+            } => {
+                let Some(value) = expr.fold(functions)? else {
+                    return Ok(None);
+                };
+                // `x IN (a, b)` is `x = a OR x = b`, so a `NULL` makes a miss UNKNOWN.
+                let mut found = Some(false);
+                for item in list.iter() {
+                    let Some(item) = item.fold(functions)? else {
+                        return Ok(None);
+                    };
+                    let equal = value
+                        .compare(&item)
+                        .map_err(|e| to_failure(expr.span(), e))?
+                        .map(Ordering::is_eq);
+                    found = or(found, equal);
+                }
+                found.map(|b| b != *negated).into()
+            }
+            Self::Like {
+                expr,
+                negated,
+                pattern: (span, pattern),
+            } => {
+                let Some(value) = expr.fold(functions)? else {
+                    return Ok(None);
+                };
+                match value {
+                    Value::Null => Value::Null,
+                    Value::VarChar(s) => Value::Bool(pattern.matches(&s) != *negated),
+                    Value::Char(s) => {
+                        Value::Bool(pattern.matches(s.trim_end_matches(' ')) != *negated)
+                    }
+                    _ => return Err(to_failure(*span, ParseError::InvalidOperand)),
+                }
+            }
+            Self::IsNull { expr, negated } => {
+                let Some(value) = expr.fold(functions)? else {
+                    return Ok(None);
+                };
+                Value::Bool(value.is_null() != *negated)
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => {
+                let operand = match operand {
+                    Some(operand) => match operand.fold(functions)? {
+                        Some(value) => Some(value),
+                        None => return Ok(None),
+                    },
+                    None => None,
+                };
+                for (condition, result) in branches.iter() {
+                    let Some(condition_value) = condition.fold(functions)? else {
+                        return Ok(None);
+                    };
+                    let matched = match &operand {
+                        Some(operand) => operand
+                            .compare(&condition_value)
+                            .map(|ordering| ordering.is_some_and(Ordering::is_eq)),
+                        None => condition_value.truth().map(|truth| truth == Some(true)),
+                    }
+                    .map_err(|e| to_failure(condition.span(), e))?;
+                    if matched {
+                        return result.fold(functions);
+                    }
+                }
+                match else_result {
+                    Some(result) => return result.fold(functions),
+                    None => Value::Null,
+                }
+            }
+            Self::Function { name, args, tp } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args.iter() {
+                    let Some(value) = arg.fold(functions)? else {
+                        return Ok(None);
+                    };
+                    values.push(value);
+                }
+                let function = functions
+                    .get(name.fragment())
+                    .ok_or_else(|| to_failure(*name, ParseError::UnknownFunction))?;
+                let value = function.call(&values).map_err(|e| {
+                    custom_failure(*name, nom_supreme::error::BaseErrorKind::External(e))
+                })?;
+                match tp {
+                    Some(tp) => value.cast(tp.clone()).map_err(|e| {
+                        custom_failure(*name, nom_supreme::error::BaseErrorKind::External(e.into()))
+                    })?,
+                    None => value,
+                }
+            }
+            Self::Cast {
+                expr,
+                tp: (span, tp),
+                ..
+            } => {
+                let Some(value) = expr.fold(functions)? else {
+                    return Ok(None);
+                };
+                value.cast(tp.clone()).map_err(|e| {
+                    custom_failure(
+                        *span,
+                        nom_supreme::error::BaseErrorKind::External(Box::new(e)),
+                    )
+                })?
+            }
+            Self::Subscript {
+                expr,
+                index: (span, index),
+            } => {
+                let (Some(value), Some(index)) = (expr.fold(functions)?, index.fold(functions)?)
+                else {
+                    return Ok(None);
+                };
+                value.subscript(&index).map_err(|e| to_failure(*span, e))?
+            }
+        };
+        Ok(Some(value))
+    }
+
+    /// Records the type of each call to a function that returns the common type of its
+    /// arguments, so its result is converted to it: the argument it returns may be of
+    /// another type, and the type of a `NULL` one is only known here.
+    /// # Errors
+    /// Returns the error [`Expr::result_type`] returns.
+    pub(crate) fn bind_function_types(
+        &mut self,
+        columns: &ColumnMap,
+        functions: &FunctionRegistry,
+    ) -> Result<(), nom::Err<RawParseError<'a>>> {
+        for child in self.children_mut() {
+            child.bind_function_types(columns, functions)?;
+        }
+        let Self::Function { name, .. } = self else {
+            return Ok(());
+        };
+        let common = functions
+            .get(name.fragment())
+            .is_some_and(|function| function.return_type == ReturnType::Common);
+        if common {
+            let resolved = self.result_type(columns, functions)?;
+            if let Self::Function { tp, .. } = self {
+                *tp = resolved;
+            }
+        }
+        Ok(())
+    }
+
+    /// Infers the type of the values the expression produces, resolving columns in `columns`
+    /// and function calls in `functions`.
+    /// Returns `None` for expressions that are always `NULL`.
+    /// # Errors
+    /// Returns an error pointing at an unknown column or function, at an operation,
+    /// function call or `CASE` branch whose operands have incompatible types, or at an
+    /// aggregate nested in another one.
+    pub fn result_type(
+        &self,
+        columns: &ColumnMap,
+        functions: &FunctionRegistry,
+    ) -> Result<Option<SqlType>, nom::Err<RawParseError<'a>>> {
+        let to_failure = |span: RawSpan<'a>, error: ParseError| {
+            custom_failure(
+                span,
+                nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+            )
+        };
+        let tp = match self {
+            Self::Literal((_, value)) => value.sql_type(),
+            // The type of a parameter is only known once it is bound, like `NULL` it is
+            // accepted anywhere.
+            Self::Parameter(_) => None,
+            Self::Column(column) => Some(
+                columns
+                    .get(&*column.key())
+                    .ok_or_else(|| to_failure(column.name, ParseError::ColumnNotFound))?
+                    .tp
+                    .clone(),
+            ),
+            Self::Nested((_, expr)) => expr.result_type(columns, functions)?,
+            Self::Unary {
+                op: (_, UnaryOperator::Minus),
+                expr,
+            } => expr.result_type(columns, functions)?.map(|tp| match tp {
+                SqlType::U8 | SqlType::U16 | SqlType::U32 | SqlType::U64 | SqlType::U128 => {
+                    SqlType::I128
+                }
+                tp => tp,
+            }),
+            Self::Binary {
+                op: (span, op),
+                left,
+                right,
+            } if op.is_arithmetic() => {
+                match (
+                    left.result_type(columns, functions)?,
+                    right.result_type(columns, functions)?,
+                ) {
+                    (Some(left), Some(right)) => Some(
+                        left.arithmetic(*op, &right)
+                            .ok_or_else(|| to_failure(*span, ParseError::InvalidOperand))?,
+                    ),
+                    (tp, None) | (None, tp) => tp,
+                }
+            }
+            Self::Binary {
+                op: (span, op),
+                left,
+                right,
+            } if op.is_comparison() => {
+                if let (Some(left), Some(right)) = (
+                    left.result_type(columns, functions)?,
+                    right.result_type(columns, functions)?,
+                ) {
+                    SqlType::common(&left, &right)
+                        .ok_or_else(|| to_failure(*span, ParseError::IncompatibleTypes))?;
+                }
+                Some(SqlType::Bool)
+            }
+            // The key is a field name or an array index, `->>` gives the text of the JSON.
+            Self::Binary {
+                op: (span, op),
+                left,
+                right,
+            } if op.is_json() => {
+                let left = left.result_type(columns, functions)?;
+                let right = right.result_type(columns, functions)?;
+                if !left.is_none_or(|tp| tp == SqlType::Json)
+                    || !right.is_none_or(|tp| tp.is_integer() || matches!(tp, SqlType::VarChar(..)))
+                {
+                    return Err(to_failure(*span, ParseError::InvalidOperand));
+                }
+                Some(if *op == BinaryOperator::JsonGet {
+                    SqlType::Json
+                } else {
+                    SqlType::VarChar(usize::MAX, LengthUnit::Chars)
+                })
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => {
+                if let Some(operand) = operand {
+                    operand.result_type(columns, functions)?;
+                }
+                let mut result_type: Option<SqlType> = None;
+                for (condition, result) in branches
+                    .iter()
+                    .map(|(condition, result)| (Some(condition), result))
+                    .chain(else_result.iter().map(|result| (None, &**result)))
+                {
+                    if let Some(condition) = condition {
+                        condition.result_type(columns, functions)?;
+                    }
+                    if let Some(tp) = result.result_type(columns, functions)? {
+                        result_type = Some(match result_type {
+                            Some(previous) => SqlType::common(&previous, &tp).ok_or_else(|| {
+                                to_failure(result.span(), ParseError::IncompatibleTypes)
+                            })?,
+                            None => tp,
+                        });
+                    }
+                }
+                result_type
+            }
+            Self::Cast { expr, tp, .. } => {
+                expr.result_type(columns, functions)?;
+                Some(tp.1.clone())
+            }
+            Self::Subscript {
+                expr,
+                index: (span, index),
+            } => {
+                let index_type = index.result_type(columns, functions)?;
+                if !index_type.is_none_or(|tp| tp.is_integer()) {
+                    return Err(to_failure(*span, ParseError::InvalidOperand));
+                }
+                match expr.result_type(columns, functions)? {
+                    Some(SqlType::Array(element)) => Some(*element),
+                    None => None,
+                    Some(_) => return Err(to_failure(*span, ParseError::InvalidOperand)),
+                }
+            }
+            Self::Aggregate {
+                function: (span, function),
+                arg,
+                ..
+            } => {
+                let tp = match arg {
+                    Some(arg) => {
+                        if let Some(nested) = arg.aggregates().first() {
+                            return Err(to_failure(nested.span(), ParseError::NestedAggregate));
+                        }
+                        arg.result_type(columns, functions)?
+                    }
+                    None => None,
+                };
+                match function {
+                    AggregateFunction::Count => Some(SqlType::I64),
+                    AggregateFunction::Min | AggregateFunction::Max => tp,
+                    AggregateFunction::Sum | AggregateFunction::Avg => match tp {
+                        Some(tp) if !tp.is_numeric() => {
+                            return Err(to_failure(*span, ParseError::InvalidOperand))
+                        }
+                        Some(tp) if tp.is_float() && *function == AggregateFunction::Sum => {
+                            Some(SqlType::F64)
+                        }
+                        Some(SqlType::Decimal { scale, .. })
+                            if *function == AggregateFunction::Sum =>
+                        {
+                            Some(SqlType::Decimal {
+                                precision: MAX_PRECISION,
+                                scale,
+                            })
+                        }
+                        // A sum can overflow any column type, it is computed on 128 bits.
+                        Some(tp) if *function == AggregateFunction::Sum => {
+                            Some(if tp.integer_range().is_some_and(|(min, _)| min >= 0) {
+                                SqlType::U128
+                            } else {
+                                SqlType::I128
+                            })
+                        }
+                        // The average of integers keeps its fraction, as a decimal quotient.
+                        Some(tp) if tp.is_integer() => Some(SqlType::Decimal {
+                            precision: MAX_PRECISION,
+                            scale: DIVISION_SCALE,
+                        }),
+                        tp => tp,
+                    },
+                }
+            }
+            Self::Function { name, args, .. } => {
+                let types = args
+                    .iter()
+                    .map(|arg| arg.result_type(columns, functions))
+                    .collect::<Result<Vec<_>, _>>()?;
+                functions
+                    .get(name.fragment())
+                    .ok_or(ParseError::UnknownFunction)
+                    .and_then(|function| function.result_type(&types))
+                    .map_err(|e| to_failure(*name, e))?
+            }
+            Self::Unary { .. }
+            | Self::Binary { .. }
+            | Self::Between { .. }
+            | Self::InList { .. }
+            | Self::Like { .. }
+            | Self::IsNull { .. } => {
+                self.children()
+                    .try_for_each(|child| child.result_type(columns, functions).map(|_| ()))?;
+                Some(SqlType::Bool)
+            }
+        };
+        Ok(tp)
+    }
+
+    /// The span errors about the expression point at: the literal or column, or the operator
+    /// or keyword for operations.
+    #[must_use]
+    pub fn span(&self) -> RawSpan<'a> {
+        match self {
+            Self::Literal((span, _))
+            | Self::Parameter((span, _))
+            | Self::Column(ColumnRef { name: span, .. })
+            | Self::Function { name: span, .. }
+            | Self::Nested((span, _))
+            | Self::Unary { op: (span, _), .. }
+            | Self::Binary { op: (span, _), .. }
+            | Self::Like {
+                pattern: (span, _), ..
+            }
+            | Self::Case { span, .. }
+            | Self::Aggregate {
+                function: (span, _),
+                ..
+            }
+            | Self::Cast { span, .. }
+            | Self::Subscript {
+                index: (span, _), ..
+            } => *span,
+            Self::Between { expr, .. } | Self::InList { expr, .. } | Self::IsNull { expr, .. } => {
+                expr.span()
+            }
+        }
+    }
+
+    /// Every column referenced by the expression, in source order.
+    #[must_use]
+    pub fn columns(&self) -> Vec<ColumnRef<'a>> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    /// The columns referenced outside of aggregate calls, in source order. In a grouped
+    /// query they must be grouped, as they need a single value per group.
+    #[must_use]
+    pub fn columns_outside_aggregates(&self) -> Vec<ColumnRef<'a>> {
+        match self {
+            Self::Column(column) => vec![*column],
+            Self::Aggregate { .. } => vec![],
+            _ => self
+                .children()
+                .flat_map(Self::columns_outside_aggregates)
+                .collect(),
+        }
+    }
+
+    /// The outermost aggregate calls of the expression, in source order. Empty when the
+    /// expression can be computed from a single row.
+    #[must_use]
+    pub fn aggregates(&self) -> Vec<&Self> {
+        let mut aggregates = Vec::new();
+        self.collect_aggregates(&mut aggregates);
+        aggregates
+    }
+
+    fn collect_aggregates<'e>(&'e self, aggregates: &mut Vec<&'e Self>) {
+        if let Self::Aggregate { .. } = self {
+            aggregates.push(self);
+            return;
+        }
+        for child in self.children() {
+            child.collect_aggregates(aggregates);
+        }
+    }
+
+    fn collect_columns(&self, columns: &mut Vec<ColumnRef<'a>>) {
+        if let Self::Column(column) = self {
+            columns.push(*column);
+        }
+        for child in self.children() {
+            child.collect_columns(columns);
+        }
+    }
+
+    /// Collects the parameter placeholders of the expression, so they can be replaced by
+    /// their values.
+    pub(crate) fn collect_parameters_mut<'e>(&'e mut self, parameters: &mut Vec<&'e mut Self>) {
+        if let Self::Parameter(_) = self {
+            parameters.push(self);
+            return;
+        }
+        for child in self.children_mut() {
+            child.collect_parameters_mut(parameters);
+        }
+    }
+
+    /// The direct sub-expressions, in source order.
+    pub(crate) fn children_mut(&mut self) -> impl Iterator<Item = &mut Self> {
+        let children: Vec<&mut Self> = match self {
+            Self::Literal(_) | Self::Parameter(_) | Self::Column(_) => vec![],
+            Self::Unary { expr, .. }
+            | Self::Nested((_, expr))
+            | Self::Like { expr, .. }
+            | Self::IsNull { expr, .. }
+            | Self::Cast { expr, .. } => vec![expr],
+            Self::Binary { left, right, .. }
+            | Self::Subscript {
+                expr: left,
+                index: (_, right),
+            } => vec![left, right],
+            Self::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Self::InList { expr, list, .. } => std::iter::once(&mut **expr)
+                .chain(list.iter_mut())
+                .collect(),
+            Self::Function { args, .. } => args.iter_mut().collect(),
+            Self::Aggregate { arg, .. } => arg.as_deref_mut().into_iter().collect(),
+            Self::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => operand
+                .as_deref_mut()
+                .into_iter()
+                .chain(
+                    branches
+                        .iter_mut()
+                        .flat_map(|(condition, result)| [condition, result]),
+                )
+                .chain(else_result.as_deref_mut())
+                .collect(),
+        };
+        children.into_iter()
+    }
+
+    /// The direct sub-expressions, in source order.
+    pub(crate) fn children(&self) -> impl Iterator<Item = &Self> {
+        let children: Vec<&Self> = match self {
+            Self::Literal(_) | Self::Parameter(_) | Self::Column(_) => vec![],
+            Self::Unary { expr, .. }
+            | Self::Nested((_, expr))
+            | Self::Like { expr, .. }
+            | Self::IsNull { expr, .. }
+            | Self::Cast { expr, .. } => vec![expr],
+            Self::Binary { left, right, .. }
+            | Self::Subscript {
+                expr: left,
+                index: (_, right),
+            } => vec![left, right],
+            Self::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Self::InList { expr, list, .. } => {
+                std::iter::once(&**expr).chain(list.iter()).collect()
+            }
+            Self::Function { args, .. } => args.iter().collect(),
+            Self::Aggregate { arg, .. } => arg.as_deref().into_iter().collect(),
+            Self::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => operand
+                .as_deref()
+                .into_iter()
+                .chain(
+                    branches
+                        .iter()
+                        .flat_map(|(condition, result)| [condition, result]),
+                )
+                .chain(else_result.as_deref())
+                .collect(),
+        };
+        children.into_iter()
+    }
+}
+
+impl<'a> Predicate<'a> {
+    fn apply(self, expr: Expr<'a>, negated: bool) -> Expr<'a> {
+        let expr = Box::new(expr);
+        match self {
+            Self::Between { low, high } => Expr::Between {
+                expr,
+                negated,
+                low: Box::new(low),
+                high: Box::new(high),
+            },
+            Self::InList(list) => Expr::InList {
+                expr,
+                negated,
+                list,
+            },
+            Self::Like(pattern) => Expr::Like {
+                expr,
+                negated,
+                pattern,
+            },
+            Self::IsNull => Expr::IsNull { expr, negated },
+        }
+    }
+}
+
+/// Comparisons are non-associative, `a = b = c` must be written with parentheses.
+fn chained_comparison(span: RawSpan<'_>) -> nom::Err<RawParseError<'_>> {
+    custom_failure(
+        span,
+        nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::ChainedComparison)),
+    )
+}
+
+/// Three-valued `AND`: FALSE wins over UNKNOWN.
+pub(crate) const fn and(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+/// Three-valued `OR`: TRUE wins over UNKNOWN.
+pub(crate) const fn or(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+pub(crate) fn evaluate_binary(
+    op: BinaryOperator,
+    left: &Value,
+    right: &Value,
+) -> Result<Value, ParseError> {
+    let compare = |f: fn(Ordering) -> bool| Ok(left.compare(right)?.map(f).into());
+    match op {
+        BinaryOperator::And => Ok(and(left.truth()?, right.truth()?).into()),
+        BinaryOperator::Or => Ok(or(left.truth()?, right.truth()?).into()),
+        BinaryOperator::Eq => compare(Ordering::is_eq),
+        BinaryOperator::NotEq => compare(Ordering::is_ne),
+        BinaryOperator::Lt => compare(Ordering::is_lt),
+        BinaryOperator::LtEq => compare(Ordering::is_le),
+        BinaryOperator::Gt => compare(Ordering::is_gt),
+        BinaryOperator::GtEq => compare(Ordering::is_ge),
+        BinaryOperator::Plus
+        | BinaryOperator::Minus
+        | BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::Modulo => left.checked_arithmetic(op, right),
+        BinaryOperator::JsonGet => left.json_get(right, false),
+        BinaryOperator::JsonGetText => left.json_get(right, true),
+    }
+}
+
+/// Binds values to parameter placeholders, given in source order: `?` placeholders, stored
+/// as `0`, take the values in order and `$n` takes the n-th value. Returns the index of the
+/// value of each placeholder.
+/// # Errors
+/// Returns an error pointing at the first placeholder when `?` and `$n` are mixed, at a
+/// placeholder without a value, or at `span` when there are more values than placeholders.
+pub(crate) fn parameter_indexes<'a>(
+    placeholders: &[WithSpan<'a, u16>],
+    count: usize,
+    span: RawSpan<'a>,
+) -> Result<Vec<usize>, nom::Err<RawParseError<'a>>> {
+    let failure = |span, error| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+        )
+    };
+    let anonymous = placeholders.iter().filter(|(_, n)| *n == 0).count();
+    if anonymous != 0 && anonymous != placeholders.len() {
+        return Err(failure(placeholders[0].0, ParseError::MixedParameters));
+    }
+    let indexes = placeholders
+        .iter()
+        .enumerate()
+        .map(|(position, (_, n))| {
+            if *n == 0 {
+                position
+            } else {
+                usize::from(*n) - 1
+            }
+        })
+        .collect::<Vec<_>>();
+    if let Some(((span, _), _)) = placeholders
+        .iter()
+        .zip(&indexes)
+        .find(|(_, index)| **index >= count)
+    {
+        return Err(failure(*span, ParseError::ParameterCount));
+    }
+    if indexes.iter().max().map_or(0, |index| index + 1) < count {
+        return Err(failure(span, ParseError::ParameterCount));
+    }
+    Ok(indexes)
+}
+
+/// Parses an expression between parentheses, as used by `CHECK (...)`.
+pub(crate) fn parenthesized<'a>(
+    tokens: &mut TokenCursor<'a>,
+) -> Result<Expr<'a>, nom::Err<RawParseError<'a>>> {
+    tokens.symbol("(")?;
+    let expr = lexer::cut(tokens.parse(Expr::parse))?;
+    lexer::cut(tokens.symbol(")"))?;
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedColumnRef {
+    pub qualifier: Option<OwnedSpan>,
+    pub name: OwnedSpan,
+}
+
+/// An [`Expr`] copied out of the input, see [`Expr::to_owned`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedExpr {
+    Literal(OwnedWithSpan<Value>),
+    Parameter(OwnedWithSpan<u16>),
+    Column(OwnedColumnRef),
+    Unary {
+        op: OwnedWithSpan<UnaryOperator>,
+        expr: Box<OwnedExpr>,
+    },
+    Binary {
+        op: OwnedWithSpan<BinaryOperator>,
+        left: Box<OwnedExpr>,
+        right: Box<OwnedExpr>,
+    },
+    Nested(OwnedWithSpan<Box<OwnedExpr>>),
+    Between {
+        expr: Box<OwnedExpr>,
+        negated: bool,
+        low: Box<OwnedExpr>,
+        high: Box<OwnedExpr>,
+    },
+    InList {
+        expr: Box<OwnedExpr>,
+        negated: bool,
+        list: Box<[OwnedExpr]>,
+    },
+    Like {
+        expr: Box<OwnedExpr>,
+        negated: bool,
+        pattern: OwnedWithSpan<LikePattern>,
+    },
+    IsNull {
+        expr: Box<OwnedExpr>,
+        negated: bool,
+    },
+    Case {
+        span: Span,
+        operand: Option<Box<OwnedExpr>>,
+        branches: Box<[(OwnedExpr, OwnedExpr)]>,
+        else_result: Option<Box<OwnedExpr>>,
+    },
+    Function {
+        name: OwnedSpan,
+        args: Box<[OwnedExpr]>,
+    },
+    Aggregate {
+        function: OwnedWithSpan<AggregateFunction>,
+        distinct: bool,
+        arg: Option<Box<OwnedExpr>>,
+    },
+    Cast {
+        span: Span,
+        expr: Box<OwnedExpr>,
+        tp: OwnedWithSpan<SqlType>,
+    },
+    Subscript {
+        expr: Box<OwnedExpr>,
+        index: OwnedWithSpan<Box<OwnedExpr>>,
+    },
+}
+
+impl ColumnRef<'_> {
+    pub fn to_owned(&self) -> OwnedColumnRef {
+        OwnedColumnRef {
+            qualifier: self.qualifier.map(OwnedSpan::from),
+            name: self.name.into(),
+        }
+    }
+}
+
+impl Expr<'_> {
+    /// Copies the expression out of the input, so it can outlive it.
+    pub fn to_owned(&self) -> OwnedExpr {
+        fn boxed(expr: &Expr<'_>) -> Box<OwnedExpr> {
+            Box::new(expr.to_owned())
+        }
+        fn list(exprs: &[Expr<'_>]) -> Box<[OwnedExpr]> {
+            exprs.iter().map(Expr::to_owned).collect()
+        }
+        match self {
+            Self::Literal(value) => OwnedExpr::Literal(owned_with_span(value, Value::clone)),
+            Self::Parameter(index) => OwnedExpr::Parameter(owned_with_span(index, |index| *index)),
+            Self::Column(column) => OwnedExpr::Column(column.to_owned()),
+            Self::Unary { op, expr } => OwnedExpr::Unary {
+                op: owned_with_span(op, |op| *op),
+                expr: boxed(expr),
+            },
+            Self::Binary { op, left, right } => OwnedExpr::Binary {
+                op: owned_with_span(op, |op| *op),
+                left: boxed(left),
+                right: boxed(right),
+            },
+            Self::Nested(expr) => OwnedExpr::Nested(owned_with_span(expr, |expr| boxed(expr))),
+            Self::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => OwnedExpr::Between {
+                expr: boxed(expr),
+                negated: *negated,
+                low: boxed(low),
+                high: boxed(high),
+            },
+            Self::InList {
+                expr,
+                negated,
+                list: exprs,
+            } => OwnedExpr::InList {
+                expr: boxed(expr),
+                negated: *negated,
+                list: list(exprs),
+            },
+            Self::Like {
+                expr,
+                negated,
+                pattern,
+            } => OwnedExpr::Like {
+                expr: boxed(expr),
+                negated: *negated,
+                pattern: owned_with_span(pattern, LikePattern::clone),
+            },
+            Self::IsNull { expr, negated } => OwnedExpr::IsNull {
+                expr: boxed(expr),
+                negated: *negated,
+            },
+            Self::Case {
+                span,
+                operand,
+                branches,
+                else_result,
+            } => OwnedExpr::Case {
+                span: (*span).into(),
+                operand: operand.as_deref().map(boxed),
+                branches: branches
+                    .iter()
+                    .map(|(when, then)| (when.to_owned(), then.to_owned()))
+                    .collect(),
+                else_result: else_result.as_deref().map(boxed),
+            },
+            Self::Function { name, args, .. } => OwnedExpr::Function {
+                name: (*name).into(),
+                args: list(args),
+            },
+            Self::Aggregate {
+                function,
+                distinct,
+                arg,
+            } => OwnedExpr::Aggregate {
+                function: owned_with_span(function, |function| *function),
+                distinct: *distinct,
+                arg: arg.as_deref().map(boxed),
+            },
+            Self::Cast { span, expr, tp } => OwnedExpr::Cast {
+                span: (*span).into(),
+                expr: boxed(expr),
+                tp: owned_with_span(tp, SqlType::clone),
+            },
+            Self::Subscript { expr, index } => OwnedExpr::Subscript {
+                expr: boxed(expr),
+                index: owned_with_span(index, |index| boxed(index)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    /// Renders the tree with explicit parentheses, to check precedence at a glance.
+    fn render(expr: &Expr) -> String {
+        match expr {
+            Expr::Literal((span, _)) | Expr::Parameter((span, _)) => span.fragment().to_string(),
+            Expr::Column(column) => column.key().into_owned(),
+            Expr::Unary { op, expr } => format!("({:?} {})", op.1, render(expr)),
+            Expr::Binary { op, left, right } => {
+                format!("({} {:?} {})", render(left), op.1, render(right))
+            }
+            Expr::Nested((_, expr)) => render(expr),
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => format!(
+                "({} {}Between {} {})",
+                render(expr),
+                if *negated { "Not" } else { "" },
+                render(low),
+                render(high)
+            ),
+            Expr::InList {
+                expr,
+                negated,
+                list,
+            } => format!(
+                "({} {}In [{}])",
+                render(expr),
+                if *negated { "Not" } else { "" },
+                list.iter().map(render).collect::<Vec<_>>().join(", ")
+            ),
+            Expr::Like {
+                expr,
+                negated,
+                pattern,
+            } => format!(
+                "({} {}Like {})",
+                render(expr),
+                if *negated { "Not" } else { "" },
+                pattern.0.fragment()
+            ),
+            Expr::IsNull { expr, negated } => format!(
+                "({} Is{}Null)",
+                render(expr),
+                if *negated { "Not" } else { "" }
+            ),
+            Expr::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => {
+                let mut s = String::from("(Case");
+                if let Some(operand) = operand {
+                    s += &format!(" {}", render(operand));
+                }
+                for (condition, result) in branches.iter() {
+                    s += &format!(" When {} Then {}", render(condition), render(result));
+                }
+                if let Some(result) = else_result {
+                    s += &format!(" Else {}", render(result));
+                }
+                s + ")"
+            }
+            Expr::Function { name, args, .. } => format!(
+                "{}({})",
+                name.fragment(),
+                args.iter().map(render).collect::<Vec<_>>().join(", ")
+            ),
+            Expr::Aggregate {
+                function,
+                distinct,
+                arg,
+            } => format!(
+                "{:?}({}{})",
+                function.1,
+                if *distinct { "Distinct " } else { "" },
+                arg.as_deref().map_or_else(|| "*".to_string(), render)
+            ),
+            Expr::Cast { expr, tp, .. } => format!("(Cast {} As {:?})", render(expr), tp.1),
+            Expr::Subscript { expr, index } => format!("{}[{}]", render(expr), render(&index.1)),
+        }
+    }
+
+    fn parse(input: &str) -> String {
+        let (rest, expr) = Expr::parse(input.into()).unwrap();
+        assert!(rest.fragment().is_empty(), "Unparsed input: {rest}");
+        render(&expr)
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(parse("a = 1"), "(a Eq 1)");
+        assert_eq!(
+            parse("a = 1 AND b <> 'x' OR c >= 3"),
+            "(((a Eq 1) And (b NotEq 'x')) Or (c GtEq 3))"
+        );
+        assert_eq!(
+            parse("a = 1 OR b = 2 AND c = 3"),
+            "((a Eq 1) Or ((b Eq 2) And (c Eq 3)))"
+        );
+        assert_eq!(parse("NOT a = 1 AND b"), "((Not (a Eq 1)) And b)");
+        assert_eq!(
+            parse("(a = 1 OR b = 2) AND c != 3"),
+            "(((a Eq 1) Or (b Eq 2)) And (c NotEq 3))"
+        );
+        assert_eq!(parse("a<=-1"), "(a LtEq -1)");
+        assert_eq!(parse("a OR b OR c"), "((a Or b) Or c)");
+        assert_eq!(parse("nothing AND orders"), "(nothing And orders)");
+        assert_eq!(parse("1 + 2 * 3"), "(1 Plus (2 Multiply 3))");
+        assert_eq!(
+            parse("a - 1 - 2 > b % 3"),
+            "(((a Minus 1) Minus 2) Gt (b Modulo 3))"
+        );
+        assert_eq!(parse("-a * (b+1)"), "((Minus a) Multiply (b Plus 1))");
+        assert_eq!(parse("a-1"), "(a Minus 1)");
+    }
+
+    #[test]
+    fn test_predicates() {
+        assert_eq!(
+            parse("a BETWEEN 1 AND 2 + 3 AND b"),
+            "((a Between 1 (2 Plus 3)) And b)"
+        );
+        assert_eq!(parse("a NOT IN (1, 2,3)"), "(a NotIn [1, 2, 3])");
+        assert_eq!(
+            parse("NOT name LIKE 'a%' OR name not like '_b'"),
+            "((Not (name Like 'a%')) Or (name NotLike '_b'))"
+        );
+        assert_eq!(parse("a + 1 in (b)"), "((a Plus 1) In [b])");
+        assert_eq!(
+            parse("a IS NOT NULL AND b is null"),
+            "((a IsNotNull) And (b IsNull))"
+        );
+        assert!(Expr::parse("a IS NOT NULL AND b is null = c".into()).is_err());
+        assert!(Expr::parse("a BETWEEN 1".into()).is_err());
+        assert!(Expr::parse("a IS 1".into()).is_err());
+        assert!(Expr::parse("a IN ()".into()).is_err());
+        assert!(Expr::parse("a LIKE b".into()).is_err());
+        assert!(Expr::parse("a LIKE 'x' = b".into()).is_err());
+        assert!(Expr::parse("c AND a = b LIKE 'x'".into()).is_err());
+    }
+
+    fn fold(input: &str) -> Result<Option<Value>, ()> {
+        Expr::parse(input.into())
+            .unwrap()
+            .1
+            .fold(FunctionRegistry::builtins())
+            .map_err(|_| ())
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("1 + 2 * 3"), Ok(Some(Value::I64(7))));
+        assert_eq!(fold("(1 + 2) * -3"), Ok(Some(Value::I64(-9))));
+        assert_eq!(fold("- (7 % 4)"), Ok(Some(Value::I64(-3))));
+        assert_eq!(fold("1 + NULL"), Ok(Some(Value::Null)));
+        assert_eq!(fold("a + 1"), Ok(None));
+        assert_eq!(fold("a = 1 AND FALSE"), Ok(None));
+        assert_eq!(fold("9223372036854775807 + 1"), Err(()));
+        assert_eq!(fold("1 / (2 - 2)"), Err(()));
+        assert_eq!(fold("'a' * 2"), Err(()));
+        assert_eq!(
+            fold("CAST('ab' AS char(4)) = 'ab' AND CAST('ab' AS char(4)) LIKE '%b'"),
+            Ok(Some(Value::Bool(true)))
+        );
+    }
+
+    #[test]
+    fn test_fold_float() {
+        assert_eq!(fold("1.5e0 * 2"), Ok(Some(Value::F64(3.0))));
+        assert_eq!(fold("-.5 + 1e1"), Ok(Some(Value::F64(9.5))));
+        assert_eq!(fold("1 / 2.0 > 0.25"), Ok(Some(Value::Bool(true))));
+        assert_eq!(fold("abs(-2.5E-1)"), Ok(Some(Value::F64(0.25))));
+        assert_eq!(fold("CAST(2.5 AS int32)"), Ok(Some(Value::I32(3))));
+        assert_eq!(fold("CAST('0.5' AS float32)"), Ok(Some(Value::F32(0.5))));
+        assert_eq!(fold("1e308 * 10"), Err(()));
+        assert_eq!(fold("1.0 / 0"), Err(()));
+    }
+
+    #[test]
+    fn test_fold_dates() {
+        let date = |s: &str| Ok(Some(Value::Date(s.parse().unwrap())));
+        assert_eq!(fold("DATE '2024-02-28' + 1"), date("2024-02-29"));
+        assert_eq!(fold("30 + DATE '2024-02-01'"), date("2024-03-02"));
+        assert_eq!(fold("DATE '2024-01-01' - 1"), date("2023-12-31"));
+        assert_eq!(
+            fold("DATE '2024-03-01' - DATE '2023-03-01'"),
+            Ok(Some(Value::I32(366)))
+        );
+        assert_eq!(
+            fold("TIMESTAMP '2024-01-31 10:00' > DATE '2024-01-31'"),
+            Ok(Some(Value::Bool(true)))
+        );
+        assert_eq!(fold("DATE '9999-12-31' + 1"), Err(()));
+        assert_eq!(fold("1 - DATE '2024-01-01'"), Err(()));
+        assert_eq!(fold("DATE '2024-01-01' * 2"), Err(()));
+        assert_eq!(fold("TIME '10:00' + 1"), Err(()));
+    }
+
+    #[test]
+    fn test_fold_intervals() {
+        let timestamp = |s: &str| Ok(Some(Value::Timestamp(s.parse().unwrap())));
+        let interval = |s: &str| Ok(Some(Value::Interval(s.parse().unwrap())));
+        assert_eq!(
+            fold("TIMESTAMP '2024-01-31 10:00' + INTERVAL '1 month'"),
+            timestamp("2024-02-29 10:00")
+        );
+        assert_eq!(
+            fold("DATE '2024-01-01' - INTERVAL '1 day 2 hours'"),
+            timestamp("2023-12-30 22:00")
+        );
+        assert_eq!(
+            fold("INTERVAL '1 year' + DATE '2024-02-29'"),
+            timestamp("2025-02-28")
+        );
+        assert_eq!(
+            fold("TIMESTAMP '2024-01-02' - TIMESTAMP '2023-12-31 22:00'"),
+            interval("1 day 2 hours")
+        );
+        assert_eq!(
+            fold("TIMESTAMPTZ '2024-01-31 10:00+02' - TIMESTAMPTZ '2024-01-31 10:00Z'"),
+            interval("-2 hours")
+        );
+        assert_eq!(
+            fold("TIME '23:00' + INTERVAL '2 hours'"),
+            Ok(Some(Value::Time("01:00".parse().unwrap())))
+        );
+        assert_eq!(
+            fold("TIME '10:30' - TIME '08:00'"),
+            interval("2 hours 30 minutes")
+        );
+        assert_eq!(
+            fold("INTERVAL '1 hour' * 3 - INTERVAL '30 min'"),
+            interval("150 min")
+        );
+        assert_eq!(fold("-INTERVAL '1 day'"), interval("-1 day"));
+        assert_eq!(
+            fold("INTERVAL '1 day' = INTERVAL '24 hours'"),
+            Ok(Some(Value::Bool(true)))
+        );
+        assert_eq!(fold("DATE '9999-12-31' + INTERVAL '1 day'"), Err(()));
+        assert_eq!(fold("INTERVAL '1 day' - DATE '2024-01-01'"), Err(()));
+        assert_eq!(fold("INTERVAL '1 day' / 2"), Err(()));
+    }
+
+    #[test]
+    fn test_fold_json() {
+        let document = r#"CAST('{"a": [1, "two", {"b": null}], "c": 1.5}' AS json)"#;
+        let fold_with = |expr: &str| fold(&format!("{document} {expr}"));
+        let text = |s: &str| Ok(Some(Value::VarChar(s.into())));
+        assert_eq!(fold_with("->> 'c'"), text("1.5"));
+        assert_eq!(fold_with("-> 'a' ->> 1"), text("two"));
+        assert_eq!(fold_with("-> 'a' ->> -3"), text("1"));
+        assert_eq!(
+            fold_with("-> 'a' -> 2"),
+            Ok(Some(Value::Json(serde_json::json!({"b": null}))))
+        );
+        assert_eq!(fold_with("-> 'a' -> 2 ->> 'b'"), Ok(Some(Value::Null)));
+        assert_eq!(fold_with("-> 'missing'"), Ok(Some(Value::Null)));
+        assert_eq!(fold_with("-> 'a' -> 3"), Ok(Some(Value::Null)));
+        assert_eq!(fold_with("-> 'c' -> 0"), Ok(Some(Value::Null)));
+        assert_eq!(fold_with("->> 'c' = '1.5'"), Ok(Some(Value::Bool(true))));
+        assert_eq!(fold_with("-> 1.5"), Err(()));
+        assert_eq!(fold("'{}' -> 'a'"), Err(()));
+    }
+
+    #[test]
+    fn test_fold_array() {
+        assert_eq!(
+            parse("-a[1] + b[i + 1][2]"),
+            "((Minus a[1]) Plus b[(i Plus 1)][2])"
+        );
+        assert_eq!(fold("ARRAY[10, 20, 30][2]"), Ok(Some(Value::I64(20))));
+        assert_eq!(fold("ARRAY[10, 20][3]"), Ok(Some(Value::Null)));
+        assert_eq!(fold("ARRAY[10, 20][NULL]"), Ok(Some(Value::Null)));
+        assert_eq!(
+            fold("ARRAY[ARRAY['a', 'b'], ARRAY['c']][1][2]"),
+            Ok(Some(Value::VarChar("b".into())))
+        );
+        assert_eq!(
+            fold("CAST('{1,2}' AS int8[])[1] + 1"),
+            Ok(Some(Value::I128(2)))
+        );
+        assert_eq!(fold("ARRAY[1] = ARRAY[1]"), Ok(Some(Value::Bool(true))));
+        assert_eq!(fold("ARRAY[10, 20]['a']"), Err(()));
+        assert_eq!(fold("10[1]"), Err(()));
+        assert!(Expr::parse("a[1".into()).is_err());
+    }
+
+    #[test]
+    fn test_fold_decimal() {
+        let decimal = |s: &str| Ok(Some(Value::Decimal(s.parse().unwrap())));
+        assert_eq!(fold("19.99 * 3"), decimal("59.97"));
+        assert_eq!(fold("0.1 + 0.2 = 0.3"), Ok(Some(Value::Bool(true))));
+        assert_eq!(fold("1.00 / 3"), decimal("0.333333"));
+        assert_eq!(fold("-2.50 + 1"), decimal("-1.50"));
+        assert_eq!(fold("abs(-0.05)"), decimal("0.05"));
+        assert_eq!(fold("CAST(2.345 AS decimal(4, 2))"), decimal("2.35"));
+        assert_eq!(fold("CAST(123.4 AS decimal(3, 1))"), Err(()));
+        assert_eq!(fold("0.5 + 0.5e0"), Ok(Some(Value::F64(1.0))));
+    }
+
+    #[test]
+    fn test_case_expression() {
+        assert_eq!(
+            parse("CASE WHEN a > 1 THEN 'big' WHEN a IS NULL THEN NULL ELSE 'small' END"),
+            "(Case When (a Gt 1) Then 'big' When (a IsNull) Then NULL Else 'small')"
+        );
+        assert_eq!(
+            parse("case a + 1 when 1 then b when 2 then c end = d"),
+            "((Case (a Plus 1) When 1 Then b When 2 Then c) Eq d)"
+        );
+        assert!(Expr::parse("CASE END".into()).is_err());
+        assert!(Expr::parse("CASE WHEN a THEN b".into()).is_err());
+
+        assert_eq!(
+            fold("CASE WHEN 1 > 2 THEN 1 WHEN NULL THEN 2 ELSE 3 END"),
+            Ok(Some(Value::I64(3)))
+        );
+        assert_eq!(
+            fold("CASE 2 WHEN 1 THEN 'a' WHEN 2 THEN 'b' END"),
+            Ok(Some(Value::VarChar("b".into())))
+        );
+        assert_eq!(
+            fold("CASE NULL WHEN NULL THEN 1 END"),
+            Ok(Some(Value::Null))
+        );
+        assert_eq!(fold("CASE WHEN a THEN 1 END"), Ok(None));
+    }
+
+    #[test]
+    fn test_cast() {
+        assert_eq!(
+            parse("CAST(a + 1 AS int8) * 2"),
+            "((Cast (a Plus 1) As I8) Multiply 2)"
+        );
+        assert_eq!(
+            parse("cast ( a as varchar(3) )"),
+            "(Cast a As VarChar(3, Chars))"
+        );
+        assert!(Expr::parse("CAST(a int8)".into()).is_err());
+        assert!(Expr::parse("CAST(a AS int7)".into()).is_err());
+
+        assert_eq!(fold("CAST('12' AS uint8) + 1"), Ok(Some(Value::I128(13))));
+        assert_eq!(
+            fold("CAST(1234 AS varchar(2))"),
+            Ok(Some(Value::VarChar("12".into())))
+        );
+        assert_eq!(fold("CAST(300 AS int8)"), Err(()));
+        assert_eq!(fold("CAST(TRUE AS int8)"), Err(()));
+    }
+
+    #[test]
+    fn test_function() {
+        assert_eq!(
+            parse("upper(name) = 'A' OR length (b, c+1) > 2"),
+            "((upper(name) Eq 'A') Or (length(b, (c Plus 1)) Gt 2))"
+        );
+        assert_eq!(parse("now()"), "now()");
+        assert!(Expr::parse("abs(1".into()).is_err());
+
+        assert_eq!(fold("abs(-3) + length('abc')"), Ok(Some(Value::I64(6))));
+        assert_eq!(
+            fold("COALESCE(NULL, upper('a'), 'b')"),
+            Ok(Some(Value::VarChar("A".into())))
+        );
+        assert_eq!(fold("lower(a)"), Ok(None));
+        assert_eq!(fold("nope(1)"), Err(()));
+        assert_eq!(fold("abs('a')"), Err(()));
+    }
+
+    #[test]
+    fn test_parameter() {
+        assert_eq!(parse("a = $1 AND b > ?"), "((a Eq $1) And (b Gt ?))");
+        let (_, expr) = Expr::parse("$12".into()).unwrap();
+        assert!(matches!(expr, Expr::Parameter((_, 12))));
+        let (_, expr) = Expr::parse("?".into()).unwrap();
+        assert!(matches!(expr, Expr::Parameter((_, 0))));
+        assert!(Expr::parse("$0".into()).is_err());
+        assert!(Expr::parse("$a".into()).is_err());
+        assert_eq!(fold("$1 + 1"), Ok(None));
+
+        let span = RawSpan::new("");
+        let placeholders = |numbers: &[u16]| numbers.iter().map(|n| (span, *n)).collect::<Vec<_>>();
+        assert_eq!(
+            parameter_indexes(&placeholders(&[0, 0, 0]), 3, span).ok(),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(
+            parameter_indexes(&placeholders(&[2, 1, 2]), 2, span).ok(),
+            Some(vec![1, 0, 1])
+        );
+        assert!(parameter_indexes(&placeholders(&[1, 0]), 1, span).is_err());
+        assert!(parameter_indexes(&placeholders(&[0, 0]), 1, span).is_err());
+        assert!(parameter_indexes(&placeholders(&[1]), 2, span).is_err());
+        assert!(parameter_indexes(&[], 0, span).is_ok());
+    }
+
+    #[test]
+    fn test_aggregate() {
+        assert_eq!(parse("count(*)"), "Count(*)");
+        assert_eq!(
+            parse("COUNT ( DISTINCT a ) + max(b * 2)"),
+            "(Count(Distinct a) Plus Max((b Multiply 2)))"
+        );
+        assert_eq!(parse("sum(a) > 1"), "(Sum(a) Gt 1)");
+        assert_eq!(parse("counter"), "counter");
+        assert_eq!(parse("avg(distinctive)"), "Avg(distinctive)");
+        assert!(Expr::parse("sum(*)".into()).is_err());
+        assert!(Expr::parse("count()".into()).is_err());
+        assert_eq!(fold("count(*)"), Ok(None));
+
+        let (_, expr) = Expr::parse("1 + sum(a) * min(b + count(c))".into()).unwrap();
+        let aggregates = expr
+            .aggregates()
+            .into_iter()
+            .map(render)
+            .collect::<Vec<_>>();
+        assert_eq!(aggregates, ["Sum(a)", "Min((b Plus Count(c)))"]);
+        assert!(Expr::parse("a + 1".into())
+            .unwrap()
+            .1
+            .aggregates()
+            .is_empty());
+        assert!(expr.columns_outside_aggregates().is_empty());
+        let (_, expr) = Expr::parse("a + sum(b) - c".into()).unwrap();
+        let columns = expr.columns_outside_aggregates();
+        assert_eq!(
+            columns.iter().map(ColumnRef::key).collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+        let (_, expr) = Expr::parse("t.a = b".into()).unwrap();
+        let columns = expr.columns();
+        assert_eq!(columns[0].qualifier.map(|q| *q.fragment()), Some("t"));
+        assert_eq!(*columns[0].name.fragment(), "a");
+        assert_eq!(columns[1].qualifier, None);
+    }
+
+    #[test]
+    fn test_result_type() {
+        let columns: ColumnMap = [
+            crate::ast::commands::create::Column::new("a", SqlType::I32),
+            crate::ast::commands::create::Column::new("b", SqlType::VarChar(10, LengthUnit::Chars)),
+        ]
+        .into_iter()
+        .map(|column| (column.name.clone(), column))
+        .collect();
+        let result_type = |input: &str| {
+            Expr::parse(input.into())
+                .unwrap()
+                .1
+                .result_type(&columns, FunctionRegistry::builtins())
+                .map_err(|_| ())
+        };
+        assert_eq!(result_type("a + 1"), Ok(Some(SqlType::I128)));
+        assert_eq!(result_type("a * a"), Ok(Some(SqlType::I32)));
+        assert_eq!(result_type("a > 1 AND b LIKE 'x'"), Ok(Some(SqlType::Bool)));
+        assert_eq!(result_type("NULL"), Ok(None));
+        assert_eq!(
+            result_type("CASE WHEN a > 1 THEN b WHEN a < 0 THEN NULL ELSE 'a long string' END"),
+            Ok(Some(SqlType::VarChar(13, LengthUnit::Chars)))
+        );
+        assert_eq!(result_type("CASE WHEN a > 1 THEN b ELSE a END"), Err(()));
+        assert_eq!(result_type("CASE WHEN c THEN 1 END"), Err(()));
+        assert_eq!(result_type("b + 1"), Err(()));
+        assert_eq!(result_type("a = b"), Err(()));
+        assert_eq!(result_type("a = $1 AND b < ?"), Ok(Some(SqlType::Bool)));
+        assert_eq!(result_type("CAST(b AS uint16)"), Ok(Some(SqlType::U16)));
+        assert_eq!(result_type("length(b)"), Ok(Some(SqlType::I64)));
+        assert_eq!(result_type("coalesce(a, 1)"), Ok(Some(SqlType::I128)));
+        assert_eq!(
+            result_type("DATE '2024-01-31' + a"),
+            Ok(Some(SqlType::Date))
+        );
+        assert_eq!(
+            result_type("DATE '2024-01-31' - DATE '2024-01-01'"),
+            Ok(Some(SqlType::I32))
+        );
+        assert_eq!(
+            result_type("TIMESTAMP '2024-01-31' < DATE '2024-01-31'"),
+            Ok(Some(SqlType::Bool))
+        );
+        assert_eq!(result_type("DATE '2024-01-31' + b"), Err(()));
+        assert_eq!(
+            result_type("DATE '2024-01-31' + INTERVAL '1 hour'"),
+            Ok(Some(SqlType::Timestamp))
+        );
+        assert_eq!(
+            result_type("TIME '10:00' - TIME '09:00'"),
+            Ok(Some(SqlType::Interval))
+        );
+        assert_eq!(
+            result_type("a * INTERVAL '1 day'"),
+            Ok(Some(SqlType::Interval))
+        );
+        assert_eq!(result_type("INTERVAL '1 day' - a"), Err(()));
+        assert_eq!(
+            result_type("CAST(b AS json) -> 'k' -> a"),
+            Ok(Some(SqlType::Json))
+        );
+        assert_eq!(
+            result_type("CAST(b AS json) ->> b"),
+            Ok(Some(SqlType::VarChar(usize::MAX, LengthUnit::Chars)))
+        );
+        assert_eq!(result_type("b -> 'k'"), Err(()));
+        assert_eq!(result_type("CAST(b AS json) -> DATE '2024-01-31'"), Err(()));
+        assert_eq!(result_type("CAST(b AS int16[])[a]"), Ok(Some(SqlType::I16)));
+        assert_eq!(result_type("CAST(b AS int16[])[b]"), Err(()));
+        assert_eq!(result_type("a[1]"), Err(()));
+        assert_eq!(result_type("NULL[1]"), Ok(None));
+        assert_eq!(result_type("upper(a)"), Err(()));
+        assert_eq!(result_type("nope(a)"), Err(()));
+        assert_eq!(result_type("count(*)"), Ok(Some(SqlType::I64)));
+        assert_eq!(result_type("sum(a)"), Ok(Some(SqlType::I128)));
+        assert_eq!(
+            result_type("sum(CAST(a AS uint8))"),
+            Ok(Some(SqlType::U128))
+        );
+        assert_eq!(
+            result_type("avg(a)"),
+            Ok(Some(SqlType::Decimal {
+                precision: 38,
+                scale: 6
+            }))
+        );
+        assert_eq!(
+            result_type("avg(CAST(a AS float32))"),
+            Ok(Some(SqlType::F32))
+        );
+        assert_eq!(
+            result_type("max(b)"),
+            Ok(Some(SqlType::VarChar(10, LengthUnit::Chars)))
+        );
+        assert_eq!(result_type("sum(b)"), Err(()));
+        assert_eq!(result_type("max(count(a))"), Err(()));
+    }
+
+    #[test]
+    fn test_three_valued_logic() {
+        let cases = [
+            ("1 < 2", Value::Bool(true)),
+            ("'b' <= 'a'", Value::Bool(false)),
+            ("NULL = NULL", Value::Null),
+            ("NOT NULL", Value::Null),
+            ("NULL AND FALSE", Value::Bool(false)),
+            ("NULL AND TRUE", Value::Null),
+            ("NULL OR TRUE", Value::Bool(true)),
+            ("NULL OR FALSE", Value::Null),
+            ("NULL IS NULL", Value::Bool(true)),
+            ("1 IS NULL", Value::Bool(false)),
+            ("1 + NULL IS NOT NULL", Value::Bool(false)),
+            ("2 BETWEEN 1 AND 3", Value::Bool(true)),
+            ("2 NOT BETWEEN 1 AND 3", Value::Bool(false)),
+            ("2 BETWEEN NULL AND 1", Value::Bool(false)),
+            ("2 BETWEEN NULL AND 3", Value::Null),
+            ("1 IN (2, 1)", Value::Bool(true)),
+            ("1 IN (2, NULL)", Value::Null),
+            ("1 NOT IN (2, 3)", Value::Bool(true)),
+            ("NULL IN (1)", Value::Null),
+            ("'abc' LIKE 'a%'", Value::Bool(true)),
+            ("'abc' NOT LIKE 'a%'", Value::Bool(false)),
+            ("NULL LIKE 'a%'", Value::Null),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(fold(input), Ok(Some(expected)), "{input}");
+        }
+        assert_eq!(fold("1 AND TRUE"), Err(()));
+        assert_eq!(fold("'a' < 1"), Err(()));
+        assert_eq!(fold("1 LIKE 'a'"), Err(()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Expr::parse("".into()).is_err());
+        assert!(Expr::parse("(a = 1".into()).is_err());
+        assert!(Expr::parse("a = ".into()).is_err());
+        assert!(Expr::parse("a = b = c".into()).is_err());
+    }
+
+    fn test_case(suffix: &str, input: &str) {
+        let expr = Expr::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(expr));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let input = "NOT (id > 10) AND name = 'bob'";
+        let expr = Expr::parse(input.into()).unwrap().1;
+        let mut settings = insta::Settings::new();
+        settings.set_description(format!("Input: {input}"));
+        settings.bind(|| insta::assert_debug_snapshot!(expr));
+    }
+
+    #[test]
+    fn test_snapshot_predicates() {
+        test_case("like", "name NOT LIKE 'b_b%'");
+        test_case("between-in", "id BETWEEN 1 AND 10 OR id IN (20, 30)");
+    }
+
+    #[test]
+    fn test_snapshot_json() {
+        test_case("json", "doc -> 'items' ->> 0 = 'x'");
+    }
+
+    #[test]
+    fn test_snapshot_subscript() {
+        test_case("subscript", "tags[1] = 'x'");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{
+    ast::{
+        commands::insert,
+        expr::{ColumnRef, Expr},
+        format::{FormatOptions, FormatSql},
+        statement::Statement,
+        visit::{walk_expr, walk_expr_mut, Visitor, VisitorMut},
+    },
+    parsers::row::RowValue,
+    value::Value,
+};
+
+/// The shape of a statement: the statement with its literals replaced by placeholders,
+/// see [`Statement::fingerprint`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    /// The statement as compact canonical SQL, with `$n` placeholders instead of literals.
+    pub sql: String,
+    /// The literals that were replaced, `params[i]` is the value of the `i`-th placeholder
+    /// added, which follows the placeholders the statement already had.
+    pub params: Vec<Value>,
+    /// A hash of [`Fingerprint::sql`], equal for statements that only differ in their
+    /// literals, spacing, case of keywords or redundant parentheses. It is stable across
+    /// builds and platforms, so it can be stored.
+    pub hash: u64,
+}
+
+impl Statement<'_> {
+    /// Replaces every literal of the expressions of the statement, and every value of an
+    /// `INSERT`, with a placeholder, so statements that only differ in their values share
+    /// the same [`Fingerprint::sql`] and [`Fingerprint::hash`].
+    ///
+    /// The `?` placeholders of the statement become `$1`, `$2`, … in source order, which
+    /// binds the same values, and the literals get the numbers after the highest
+    /// placeholder. Column defaults of `CREATE TABLE`, `LIKE` patterns, `LIMIT` and
+    /// `OFFSET` are part of the shape and stay as written. A literal that would need a
+    /// placeholder above `$65535` stays as well.
+    #[must_use]
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut statement = match self {
+            Self::Insert(insert) => Self::UnboundInsert(unbind(insert)),
+            statement => statement.clone(),
+        };
+        let mut placeholders = Placeholders::default();
+        placeholders.visit_statement(&statement);
+        let mut parameterize = Parameterize {
+            anonymous: 0,
+            next: placeholders.highest.max(placeholders.anonymous),
+            params: Vec::new(),
+        };
+        parameterize.visit_statement_mut(&mut statement);
+        let sql = statement.format_sql(&FormatOptions::compact());
+        Fingerprint {
+            hash: fnv1a(sql.as_bytes()),
+            sql,
+            params: parameterize.params,
+        }
+    }
+}
+
+/// An `INSERT` bound to the catalog with its values as literal expressions again.
+fn unbind<'a>(insert: &insert::Statement<'a>) -> insert::UnboundStatement<'a> {
+    let span = insert.table_name.name;
+    insert::UnboundStatement {
+        table_name: insert.table_name,
+        columns: (
+            span,
+            insert
+                .columns
+                .iter()
+                .map(|name| ColumnRef {
+                    qualifier: None,
+                    name: *name,
+                })
+                .collect(),
+        ),
+        rows: insert
+            .rows
+            .iter()
+            .map(|row| {
+                let values = row
+                    .values
+                    .iter()
+                    .map(|(_, value)| (value.0, RowValue::Expr(Expr::Literal(value.clone()))))
+                    .collect();
+                (row.span, values)
+            })
+            .collect(),
+        on_conflict: insert
+            .on_conflict
+            .clone()
+            .map(|on_conflict| (span, on_conflict)),
+        returning: insert.returning.clone(),
+    }
+}
+
+/// Counts the placeholders a statement already has.
+#[derive(Default)]
+struct Placeholders {
+    /// The highest `$n`.
+    highest: u16,
+    /// How many `?` there are.
+    anonymous: u16,
+}
+
+impl<'a> Visitor<'a> for Placeholders {
+    fn visit_expr(&mut self, expr: &Expr<'a>) {
+        match expr {
+            Expr::Parameter((_, 0)) => self.anonymous = self.anonymous.saturating_add(1),
+            Expr::Parameter((_, n)) => self.highest = self.highest.max(*n),
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+/// Numbers the `?` placeholders, replaces the literals with the next placeholders and drops
+/// the parentheses written around expressions.
+struct Parameterize {
+    /// How many `?` were numbered so far.
+    anonymous: u16,
+    /// The number of the last placeholder.
+    next: u16,
+    params: Vec<Value>,
+}
+
+impl<'a> VisitorMut<'a> for Parameterize {
+    fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+        match expr {
+            Expr::Parameter((_, n)) if *n == 0 => {
+                self.anonymous += 1;
+                *n = self.anonymous;
+            }
+            Expr::Literal((span, value)) => {
+                let Some(next) = self.next.checked_add(1) else {
+                    return;
+                };
+                self.next = next;
+                self.params.push(value.clone());
+                *expr = Expr::Parameter((*span, next));
+            }
+            // The formatter writes the parentheses the operators need.
+            Expr::Nested((span, nested)) => {
+                let placeholder = Expr::Parameter((*span, 0));
+                *expr = std::mem::replace(&mut **nested, placeholder);
+                self.visit_expr_mut(expr);
+            }
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::{
+        ast::commands::create::{Column, SqlType},
+        parse::{Parse, RawSpan, TableMap},
+    };
+
+    use super::*;
+
+    fn fingerprint(input: &str) -> Fingerprint {
+        let (_, statement) = Statement::parse(RawSpan::new(input)).unwrap();
+        statement.fingerprint()
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let first = fingerprint("SELECT id FROM users WHERE name = 'a' AND (age) > 18 LIMIT 5");
+        let second = fingerprint("select id from users where name='b' and age>21 limit 5");
+        assert_eq!(
+            first.sql,
+            "SELECT id FROM users WHERE name = $1 AND age > $2 LIMIT 5"
+        );
+        assert_eq!(first.params, [Value::VarChar("a".into()), Value::I64(18)]);
+        assert_eq!(second.params, [Value::VarChar("b".into()), Value::I64(21)]);
+        assert_eq!(first.hash, second.hash);
+        assert_ne!(
+            first.hash,
+            fingerprint("SELECT id FROM users WHERE name = 'a' LIMIT 5").hash
+        );
+
+        let placeholders = fingerprint("SELECT id FROM users WHERE a = ? AND b = 1 AND c = ?");
+        assert_eq!(
+            placeholders.sql,
+            "SELECT id FROM users WHERE a = $1 AND b = $3 AND c = $2"
+        );
+        assert_eq!(placeholders.params, [Value::I64(1)]);
+        assert_eq!(
+            fingerprint("SELECT id FROM users WHERE a = $4 OR b = 'x'").sql,
+            "SELECT id FROM users WHERE a = $4 OR b = $5"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_insert() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "t".into(),
+            [
+                ("id".into(), Column::new("id", SqlType::I32)),
+                ("n".into(), Column::new("n", SqlType::I64)),
+            ]
+            .into(),
+        );
+        let input = "INSERT INTO t (id, n) VALUES (1, 2), (3, 4)";
+        let (_, bound) = Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+        let bound = bound.fingerprint();
+        let unbound = fingerprint(input);
+        assert_eq!(bound.sql, "INSERT INTO t (id, n) VALUES ($1, $2), ($3, $4)");
+        assert_eq!(bound.sql, unbound.sql);
+        assert_eq!(bound.hash, unbound.hash);
+        assert_eq!(
+            bound.params,
+            [Value::I32(1), Value::I64(2), Value::I32(3), Value::I64(4)]
+        );
+    }
+}
@@ -0,0 +1,1464 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    ast::{
+        commands::{
+            alter::{self, Action},
+            analyze, backup,
+            create::{
+                self, ColumnConstraint, Compression, Engine, LengthUnit, PartitionScheme,
+                Partitioning, RawColumn, References, SqlType, TableConstraint,
+            },
+            create_schema, create_type, create_view, drop, explain,
+            insert::{self, ConflictAction, OnConflict},
+            select::{self, CommonTableExpr, NullsOrder, OrderByItem, Projection, SortOrder},
+            show, transaction, vacuum,
+        },
+        expr::{
+            AggregateFunction, BinaryOperator, ColumnRef, Expr, UnaryOperator,
+            BETWEEN_BOUND_BINDING_POWER, MINUS_BINDING_POWER, NOT_BINDING_POWER,
+            PREDICATE_BINDING_POWER,
+        },
+        statement::Statement,
+        table::{JoinConstraint, JoinKind, TableExpr, TableName},
+    },
+    like::{LikePattern, LikeToken},
+    parse::RawSpan,
+    parsers::{identifier::is_reserved, row::RowValue},
+    value::Value,
+};
+
+/// How [`FormatSql`] lays out a statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FormatOptions {
+    /// Starts each clause on its own line and indents column definitions, rows and nested
+    /// queries by this many spaces. `None` writes the whole statement on one line.
+    pub indent: Option<usize>,
+}
+
+impl FormatOptions {
+    /// Every statement on a single line, as [`std::fmt::Display`] writes it.
+    #[must_use]
+    pub const fn compact() -> Self {
+        Self { indent: None }
+    }
+
+    /// Every clause on its own line, nested parts indented by `indent` spaces.
+    #[must_use]
+    pub const fn indented(indent: usize) -> Self {
+        Self {
+            indent: Some(indent),
+        }
+    }
+}
+
+/// The output of [`FormatSql`], keeping track of the depth of the nested parts.
+pub struct SqlWriter<'w> {
+    out: &'w mut dyn Write,
+    options: FormatOptions,
+    depth: usize,
+}
+
+impl Write for SqlWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.write_str(s)
+    }
+}
+
+impl<'w> SqlWriter<'w> {
+    #[must_use]
+    pub fn new(out: &'w mut dyn Write, options: FormatOptions) -> Self {
+        Self {
+            out,
+            options,
+            depth: 0,
+        }
+    }
+
+    /// Starts a new line at the current depth, or writes a space on a single line.
+    /// # Errors
+    /// Returns an error if the output fails.
+    pub fn line(&mut self) -> fmt::Result {
+        match self.options.indent {
+            Some(indent) => write!(self.out, "\n{:width$}", "", width = indent * self.depth),
+            None => self.out.write_char(' '),
+        }
+    }
+
+    /// Writes with `f` one level deeper.
+    /// # Errors
+    /// Returns an error if `f` fails.
+    pub fn indented(&mut self, f: impl FnOnce(&mut Self) -> fmt::Result) -> fmt::Result {
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Writes `items` between parentheses, one per indented line, or separated by `, ` on a
+    /// single line.
+    fn block<T>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+        mut f: impl FnMut(&mut Self, T) -> fmt::Result,
+    ) -> fmt::Result {
+        self.write_char('(')?;
+        let indent = self.options.indent.is_some();
+        self.indented(|w| {
+            for (index, item) in items.into_iter().enumerate() {
+                match (index, indent) {
+                    (0, false) => {}
+                    (_, false) => w.write_str(", ")?,
+                    (0, true) => w.line()?,
+                    (_, true) => {
+                        w.write_char(',')?;
+                        w.line()?;
+                    }
+                }
+                f(w, item)?;
+            }
+            Ok(())
+        })?;
+        if indent {
+            self.line()?;
+        }
+        self.write_char(')')
+    }
+}
+
+/// Writes a node of the AST back as canonical SQL: keywords in uppercase, names quoted only
+/// when they must be, literals written from their value and parentheses only where the
+/// operators need them. Parsing the output gives back the same tree. String literals are
+/// written for [`EscapeMode::Standard`](crate::parsers::string::EscapeMode::Standard).
+pub trait FormatSql {
+    /// Writes the node to `w`.
+    /// # Errors
+    /// Returns an error if the output fails.
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result;
+
+    /// The node as SQL laid out with `options`.
+    #[must_use]
+    fn format_sql(&self, options: &FormatOptions) -> String
+    where
+        Self: Sized,
+    {
+        Formatted(self, *options).to_string()
+    }
+}
+
+struct Formatted<'n, T>(&'n T, FormatOptions);
+
+impl<T: FormatSql> fmt::Display for Formatted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_sql(&mut SqlWriter::new(f, self.1))
+    }
+}
+
+macro_rules! display_as_sql {
+    ($($tp:ty),* $(,)?) => {
+        $(
+            impl fmt::Display for $tp {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    self.write_sql(&mut SqlWriter::new(f, FormatOptions::compact()))
+                }
+            }
+        )*
+    };
+}
+
+display_as_sql!(
+    Statement<'_>,
+    create::Statement<'_>,
+    create_schema::Statement<'_>,
+    create_type::Statement<'_>,
+    create_view::Statement<'_>,
+    drop::Statement<'_>,
+    alter::Statement<'_>,
+    insert::Statement<'_>,
+    insert::UnboundStatement<'_>,
+    select::Statement<'_>,
+    transaction::Statement<'_>,
+    show::Statement<'_>,
+    analyze::Statement<'_>,
+    vacuum::Statement<'_>,
+    backup::Statement<'_>,
+    explain::Statement<'_>,
+    Expr<'_>,
+    TableExpr<'_>,
+    SqlType,
+);
+
+/// Displays a value as the SQL literal [`FormatSql`] writes, see [`Value::sql_literal`].
+#[derive(Debug, Clone, Copy)]
+pub struct SqlLiteral<'v>(pub &'v Value);
+
+impl fmt::Display for SqlLiteral<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0
+            .write_sql(&mut SqlWriter::new(f, FormatOptions::compact()))
+    }
+}
+
+impl Value {
+    /// The value as a SQL literal, `'it''s'` for the text `it's`, while [`std::fmt::Display`]
+    /// writes the text a `CAST` to `VARCHAR` gives, `it's`.
+    #[must_use]
+    pub const fn sql_literal(&self) -> SqlLiteral<'_> {
+        SqlLiteral(self)
+    }
+}
+
+/// Writes `items` separated by `, `.
+fn comma_separated<T>(
+    w: &mut SqlWriter<'_>,
+    items: impl IntoIterator<Item = T>,
+    mut f: impl FnMut(&mut SqlWriter<'_>, T) -> fmt::Result,
+) -> fmt::Result {
+    for (index, item) in items.into_iter().enumerate() {
+        if index > 0 {
+            w.write_str(", ")?;
+        }
+        f(w, item)?;
+    }
+    Ok(())
+}
+
+/// Writes a name, between double quotes when it is a reserved keyword or not a plain word.
+/// A quoted name keeps its doubled quotes, so it is quoted with the quote it was written
+/// with.
+fn write_name(w: &mut SqlWriter<'_>, name: &str) -> fmt::Result {
+    let plain = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !is_reserved(name);
+    if plain {
+        return w.write_str(name);
+    }
+    let quote = if name.contains('`') || name.replace("\"\"", "").contains('"') {
+        '`'
+    } else {
+        '"'
+    };
+    write!(w, "{quote}{name}{quote}")
+}
+
+fn write_span(w: &mut SqlWriter<'_>, name: &RawSpan<'_>) -> fmt::Result {
+    write_name(w, name.fragment())
+}
+
+fn write_names(w: &mut SqlWriter<'_>, names: &[RawSpan<'_>]) -> fmt::Result {
+    w.write_char('(')?;
+    comma_separated(w, names, write_span)?;
+    w.write_char(')')
+}
+
+fn write_string(w: &mut SqlWriter<'_>, text: &str) -> fmt::Result {
+    write!(w, "'{}'", text.replace('\'', "''"))
+}
+
+impl FormatSql for Value {
+    /// Writes the value as a literal that reads back as the same value, both in an
+    /// expression and as the value of a column of its type.
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => w.write_str("NULL"),
+            Self::VarChar(s) => write_string(w, s),
+            Self::Char(s) => write_string(w, s.trim_end_matches(' ')),
+            Self::Bool(true) => w.write_str("TRUE"),
+            Self::Bool(false) => w.write_str("FALSE"),
+            // An exponent keeps floats apart from the exact decimals.
+            Self::F32(v) => write!(w, "{v:e}"),
+            Self::F64(v) => write!(w, "{v:e}"),
+            Self::I8(_)
+            | Self::I16(_)
+            | Self::I32(_)
+            | Self::I64(_)
+            | Self::I128(_)
+            | Self::U8(_)
+            | Self::U16(_)
+            | Self::U32(_)
+            | Self::U64(_)
+            | Self::U128(_)
+            | Self::Decimal(_)
+            | Self::Parameter(_) => write!(w, "{self}"),
+            Self::Date(v) => write!(w, "DATE '{v}'"),
+            Self::Time(v) => write!(w, "TIME '{v}'"),
+            Self::Timestamp(v) => write!(w, "TIMESTAMP '{v}'"),
+            Self::TimestampTz(v) => write!(w, "TIMESTAMPTZ '{v}+00'"),
+            Self::Interval(v) => write!(w, "INTERVAL '{v}'"),
+            Self::Blob(v) => {
+                w.write_str("X'")?;
+                v.iter().try_for_each(|byte| write!(w, "{byte:02X}"))?;
+                w.write_char('\'')
+            }
+            Self::Json(v) => write_string(w, &v.to_string()),
+            // `ARRAY[]` needs an element to know its type, the empty array is only written
+            // as text.
+            Self::Array(items) if items.is_empty() => w.write_str("'{}'"),
+            Self::Array(items) => {
+                w.write_str("ARRAY[")?;
+                comma_separated(w, items.iter(), |w, item| item.write_sql(w))?;
+                w.write_char(']')
+            }
+        }
+    }
+}
+
+impl FormatSql for SqlType {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::VarChar(size, LengthUnit::Chars) => write!(w, "VARCHAR({size})"),
+            Self::VarChar(size, LengthUnit::Bytes) => write!(w, "VARCHAR({size} BYTES)"),
+            Self::Char(size) => write!(w, "CHAR({size})"),
+            Self::Bool => w.write_str("BOOL"),
+            Self::I8 => w.write_str("INT8"),
+            Self::I16 => w.write_str("INT16"),
+            Self::I32 => w.write_str("INT32"),
+            Self::I64 => w.write_str("INT64"),
+            Self::I128 => w.write_str("INT128"),
+            Self::U8 => w.write_str("UINT8"),
+            Self::U16 => w.write_str("UINT16"),
+            Self::U32 => w.write_str("UINT32"),
+            Self::U64 => w.write_str("UINT64"),
+            Self::U128 => w.write_str("UINT128"),
+            Self::F32 => w.write_str("FLOAT32"),
+            Self::F64 => w.write_str("FLOAT64"),
+            Self::Decimal { precision, scale } => write!(w, "DECIMAL({precision}, {scale})"),
+            Self::Date => w.write_str("DATE"),
+            Self::Time => w.write_str("TIME"),
+            Self::Timestamp => w.write_str("TIMESTAMP"),
+            Self::TimestampTz => w.write_str("TIMESTAMPTZ"),
+            Self::Interval => w.write_str("INTERVAL"),
+            Self::Blob => w.write_str("BLOB"),
+            Self::Json => w.write_str("JSON"),
+            Self::Enum(labels) => {
+                w.write_str("ENUM(")?;
+                comma_separated(w, labels.iter(), |w, label| write_string(w, label))?;
+                w.write_char(')')
+            }
+            Self::Array(element) => {
+                element.write_sql(w)?;
+                w.write_str("[]")
+            }
+        }
+    }
+}
+
+impl FormatSql for Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::Create(statement) => statement.write_sql(w),
+            Self::CreateSchema(statement) => statement.write_sql(w),
+            Self::CreateType(statement) => statement.write_sql(w),
+            Self::CreateView(statement) => statement.write_sql(w),
+            Self::Drop(statement) => statement.write_sql(w),
+            Self::Alter(statement) => statement.write_sql(w),
+            Self::Insert(statement) => statement.write_sql(w),
+            Self::UnboundInsert(statement) => statement.write_sql(w),
+            Self::Select(statement) => statement.write_sql(w),
+            Self::Transaction(statement) => statement.write_sql(w),
+            Self::Show(statement) => statement.write_sql(w),
+            Self::Analyze(statement) => statement.write_sql(w),
+            Self::Vacuum(statement) => statement.write_sql(w),
+            Self::Backup(statement) => statement.write_sql(w),
+            Self::Explain(statement) => statement.write_sql(w),
+        }
+    }
+}
+
+impl FormatSql for TableName<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        if let Some(schema) = &self.schema {
+            write_span(w, schema)?;
+            w.write_char('.')?;
+        }
+        write_span(w, &self.name)
+    }
+}
+
+impl FormatSql for ColumnRef<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        if let Some(qualifier) = &self.qualifier {
+            write_span(w, qualifier)?;
+            w.write_char('.')?;
+        }
+        write_span(w, &self.name)
+    }
+}
+
+impl FormatSql for create::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("CREATE TABLE ")?;
+        if self.if_not_exists {
+            w.write_str("IF NOT EXISTS ")?;
+        }
+        self.table_name.write_sql(w)?;
+        w.write_char(' ')?;
+        let columns = self.columns.iter().map(Ok);
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|(_, constraint)| Err(constraint));
+        w.block(
+            columns.chain(constraints),
+            |w, definition| match definition {
+                Ok(column) => column.write_sql(w),
+                Err(constraint) => constraint.write_sql(w),
+            },
+        )?;
+        if let Some(partitioning) = &self.partitioning {
+            partitioning.write_sql(w)?;
+        }
+        let mut options = Vec::new();
+        if self.compression != Compression::None {
+            options.push(format!("compression = '{}'", self.compression.name()));
+        }
+        if self.engine != Engine::Disk {
+            options.push(format!("engine = '{}'", self.engine.name()));
+        }
+        if !self.bloom_filters.is_empty() {
+            options.push(format!(
+                "bloom_filter = '{}'",
+                self.bloom_filters.join(", ")
+            ));
+        }
+        if !options.is_empty() {
+            write!(w, " WITH ({})", options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FormatSql for Partitioning {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str(" PARTITION BY ")?;
+        match &self.scheme {
+            PartitionScheme::Range(partitions) => {
+                w.write_str("RANGE (")?;
+                write_name(w, &self.column)?;
+                w.write_str(") (")?;
+                comma_separated(w, partitions.iter(), |w, partition| {
+                    w.write_str("PARTITION ")?;
+                    write_name(w, &partition.name)?;
+                    w.write_str(" VALUES LESS THAN (")?;
+                    match &partition.less_than {
+                        Some(bound) => bound.write_sql(w)?,
+                        None => w.write_str("MAXVALUE")?,
+                    }
+                    w.write_char(')')
+                })?;
+                w.write_char(')')
+            }
+            PartitionScheme::Hash(count) => {
+                w.write_str("HASH (")?;
+                write_name(w, &self.column)?;
+                write!(w, ") PARTITIONS {count}")
+            }
+        }
+    }
+}
+
+impl FormatSql for RawColumn<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        write_span(w, &self.name)?;
+        w.write_char(' ')?;
+        self.tp.1.write_sql(w)?;
+        for (_, constraint) in self.constraints.iter() {
+            w.write_char(' ')?;
+            constraint.write_sql(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl FormatSql for ColumnConstraint<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::NotNull => w.write_str("NOT NULL"),
+            Self::Null => w.write_str("NULL"),
+            Self::Default((_, value)) => {
+                w.write_str("DEFAULT ")?;
+                value.write_sql(w)
+            }
+            Self::PrimaryKey => w.write_str("PRIMARY KEY"),
+            Self::Unique => w.write_str("UNIQUE"),
+            Self::Check(expr) => write_check(w, expr),
+            Self::References(references) => references.write_sql(w),
+            Self::AutoIncrement => w.write_str("AUTO_INCREMENT"),
+        }
+    }
+}
+
+fn write_check(w: &mut SqlWriter<'_>, expr: &Expr<'_>) -> fmt::Result {
+    w.write_str("CHECK (")?;
+    expr.write_sql(w)?;
+    w.write_char(')')
+}
+
+impl FormatSql for References<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("REFERENCES ")?;
+        self.table_name.write_sql(w)?;
+        if !self.columns.is_empty() {
+            w.write_char(' ')?;
+            write_names(w, &self.columns)?;
+        }
+        Ok(())
+    }
+}
+
+impl FormatSql for TableConstraint<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::PrimaryKey(columns) => {
+                w.write_str("PRIMARY KEY ")?;
+                write_names(w, columns)
+            }
+            Self::Unique(columns) => {
+                w.write_str("UNIQUE ")?;
+                write_names(w, columns)
+            }
+            Self::Check(expr) => write_check(w, expr),
+            Self::ForeignKey {
+                columns,
+                references,
+            } => {
+                w.write_str("FOREIGN KEY ")?;
+                write_names(w, columns)?;
+                w.write_char(' ')?;
+                references.write_sql(w)
+            }
+        }
+    }
+}
+
+impl FormatSql for create_schema::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("CREATE SCHEMA ")?;
+        if self.if_not_exists {
+            w.write_str("IF NOT EXISTS ")?;
+        }
+        write_span(w, &self.schema_name)
+    }
+}
+
+impl FormatSql for create_type::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("CREATE TYPE ")?;
+        write_span(w, &self.type_name)?;
+        w.write_str(" AS ")?;
+        self.tp.1.write_sql(w)
+    }
+}
+
+impl FormatSql for create_view::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("CREATE VIEW ")?;
+        self.view_name.write_sql(w)?;
+        w.write_str(" AS")?;
+        w.line()?;
+        self.query.1.write_sql(w)
+    }
+}
+
+impl FormatSql for drop::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("DROP TABLE ")?;
+        if self.if_exists {
+            w.write_str("IF EXISTS ")?;
+        }
+        comma_separated(w, self.table_names.iter(), |w, name| name.write_sql(w))
+    }
+}
+
+impl FormatSql for alter::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("ALTER TABLE ")?;
+        self.table_name.write_sql(w)?;
+        match &self.action {
+            Action::AddColumn(column) => {
+                w.write_str(" ADD COLUMN ")?;
+                column.write_sql(w)
+            }
+            Action::DropColumn(name) => {
+                w.write_str(" DROP COLUMN ")?;
+                write_span(w, name)
+            }
+            Action::RenameColumn { from, to } => {
+                w.write_str(" RENAME COLUMN ")?;
+                write_span(w, from)?;
+                w.write_str(" TO ")?;
+                write_span(w, to)
+            }
+        }
+    }
+}
+
+/// Writes the `VALUES` rows, one per indented line.
+fn write_rows<R>(
+    w: &mut SqlWriter<'_>,
+    rows: impl IntoIterator<Item = R>,
+    mut f: impl FnMut(&mut SqlWriter<'_>, R) -> fmt::Result,
+) -> fmt::Result {
+    w.line()?;
+    w.write_str("VALUES")?;
+    w.indented(|w| {
+        for (index, row) in rows.into_iter().enumerate() {
+            if index > 0 {
+                w.write_char(',')?;
+            }
+            w.line()?;
+            w.write_char('(')?;
+            f(w, row)?;
+            w.write_char(')')?;
+        }
+        Ok(())
+    })
+}
+
+fn write_on_conflict(w: &mut SqlWriter<'_>, on_conflict: &OnConflict<'_>) -> fmt::Result {
+    w.line()?;
+    w.write_str("ON CONFLICT ")?;
+    if !on_conflict.target.is_empty() {
+        write_names(w, &on_conflict.target)?;
+        w.write_char(' ')?;
+    }
+    match &on_conflict.action {
+        ConflictAction::Nothing => w.write_str("DO NOTHING"),
+        ConflictAction::Update(assignments) => {
+            w.write_str("DO UPDATE SET ")?;
+            comma_separated(w, assignments.iter(), |w, assignment| {
+                write_span(w, &assignment.column)?;
+                w.write_str(" = ")?;
+                assignment.value.write_sql(w)
+            })
+        }
+    }
+}
+
+fn write_returning(w: &mut SqlWriter<'_>, returning: &[Projection<'_>]) -> fmt::Result {
+    if returning.is_empty() {
+        return Ok(());
+    }
+    w.line()?;
+    w.write_str("RETURNING ")?;
+    comma_separated(w, returning, |w, projection| projection.write_sql(w))
+}
+
+impl FormatSql for insert::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("INSERT INTO ")?;
+        self.table_name.write_sql(w)?;
+        w.write_char(' ')?;
+        write_names(w, &self.columns)?;
+        write_rows(w, self.rows.iter(), |w, row| {
+            comma_separated(w, row.values.iter(), |w, (_, (_, value))| {
+                value.write_sql(w)
+            })
+        })?;
+        if let Some(on_conflict) = &self.on_conflict {
+            write_on_conflict(w, on_conflict)?;
+        }
+        write_returning(w, &self.returning)
+    }
+}
+
+impl FormatSql for insert::UnboundStatement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("INSERT INTO ")?;
+        self.table_name.write_sql(w)?;
+        w.write_str(" (")?;
+        comma_separated(w, self.columns.1.iter(), |w, column| column.write_sql(w))?;
+        w.write_char(')')?;
+        write_rows(w, self.rows.iter(), |w, (_, values)| {
+            comma_separated(w, values.iter(), |w, (_, value)| match value {
+                RowValue::Default => w.write_str("DEFAULT"),
+                RowValue::Expr(expr) => expr.write_sql(w),
+            })
+        })?;
+        if let Some((_, on_conflict)) = &self.on_conflict {
+            write_on_conflict(w, on_conflict)?;
+        }
+        write_returning(w, &self.returning)
+    }
+}
+
+impl FormatSql for select::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        if !self.with.is_empty() {
+            w.write_str("WITH ")?;
+            if self.with.iter().any(|cte| cte.recursive.is_some()) {
+                w.write_str("RECURSIVE ")?;
+            }
+            comma_separated(w, self.with.iter(), |w, cte| cte.write_sql(w))?;
+            w.line()?;
+        }
+        w.write_str("SELECT ")?;
+        comma_separated(w, self.projection.iter(), |w, projection| {
+            projection.write_sql(w)
+        })?;
+        w.line()?;
+        w.write_str("FROM ")?;
+        self.from.write_sql(w)?;
+        if let Some(selection) = &self.selection {
+            w.line()?;
+            w.write_str("WHERE ")?;
+            selection.write_sql(w)?;
+        }
+        if !self.group_by.is_empty() {
+            w.line()?;
+            w.write_str("GROUP BY ")?;
+            comma_separated(w, self.group_by.iter(), |w, (_, expr)| expr.write_sql(w))?;
+        }
+        if let Some(having) = &self.having {
+            w.line()?;
+            w.write_str("HAVING ")?;
+            having.write_sql(w)?;
+        }
+        if !self.order_by.is_empty() {
+            w.line()?;
+            w.write_str("ORDER BY ")?;
+            comma_separated(w, self.order_by.iter(), |w, item| item.write_sql(w))?;
+        }
+        if let Some((_, limit)) = self.limit {
+            w.line()?;
+            write!(w, "LIMIT {limit}")?;
+            if let Some((_, offset)) = self.offset {
+                write!(w, " OFFSET {offset}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FormatSql for CommonTableExpr<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        write_span(w, &self.name)?;
+        if !self.columns.is_empty() {
+            write_names(w, &self.columns)?;
+        }
+        w.write_str(" AS ")?;
+        w.block([()], |w, ()| {
+            self.query.1.write_sql(w)?;
+            if let Some(term) = &self.recursive {
+                w.line()?;
+                w.write_str(if term.all { "UNION ALL" } else { "UNION" })?;
+                w.line()?;
+                term.query.1.write_sql(w)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl FormatSql for Projection<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        let alias = match self {
+            Self::Wildcard(_) => return w.write_char('*'),
+            Self::Column { column, alias } => {
+                column.write_sql(w)?;
+                alias
+            }
+            Self::Expr {
+                expr: (_, expr),
+                alias,
+            } => {
+                expr.write_sql(w)?;
+                alias
+            }
+        };
+        if let Some(alias) = alias {
+            w.write_str(" AS ")?;
+            write_span(w, alias)?;
+        }
+        Ok(())
+    }
+}
+
+impl FormatSql for OrderByItem<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        self.expr.1.write_sql(w)?;
+        if self.order == SortOrder::Desc {
+            w.write_str(" DESC")?;
+        }
+        match self.nulls {
+            Some(NullsOrder::First) => w.write_str(" NULLS FIRST"),
+            Some(NullsOrder::Last) => w.write_str(" NULLS LAST"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FormatSql for TableExpr<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        let alias = match self {
+            Self::Table { name, alias } => {
+                name.write_sql(w)?;
+                alias
+            }
+            Self::SelfReference { name, alias } => {
+                write_span(w, name)?;
+                alias
+            }
+            Self::Join {
+                kind: (_, kind),
+                left,
+                right,
+                constraint,
+            } => {
+                left.write_sql(w)?;
+                return w.indented(|w| {
+                    w.line()?;
+                    w.write_str(match kind {
+                        JoinKind::Inner => "JOIN ",
+                        JoinKind::Left => "LEFT JOIN ",
+                        JoinKind::Right => "RIGHT JOIN ",
+                        JoinKind::Full => "FULL JOIN ",
+                        JoinKind::Cross => "CROSS JOIN ",
+                    })?;
+                    // Joins are left-associative, a join on the right is nested.
+                    if matches!(**right, Self::Join { .. }) {
+                        w.write_char('(')?;
+                        right.write_sql(w)?;
+                        w.write_char(')')?;
+                    } else {
+                        right.write_sql(w)?;
+                    }
+                    match constraint {
+                        JoinConstraint::On(expr) => {
+                            w.write_str(" ON ")?;
+                            expr.write_sql(w)
+                        }
+                        JoinConstraint::Using(columns) => {
+                            w.write_str(" USING ")?;
+                            write_names(w, columns)
+                        }
+                        JoinConstraint::None => Ok(()),
+                    }
+                });
+            }
+        };
+        if let Some(alias) = alias {
+            w.write_str(" AS ")?;
+            write_span(w, alias)?;
+        }
+        Ok(())
+    }
+}
+
+impl FormatSql for transaction::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::Begin => w.write_str("BEGIN"),
+            Self::Commit => w.write_str("COMMIT"),
+            Self::Rollback { savepoint: None } => w.write_str("ROLLBACK"),
+            Self::Rollback {
+                savepoint: Some(savepoint),
+            } => {
+                w.write_str("ROLLBACK TO SAVEPOINT ")?;
+                write_span(w, savepoint)
+            }
+            Self::Savepoint(name) => {
+                w.write_str("SAVEPOINT ")?;
+                write_span(w, name)
+            }
+            Self::SetIsolationLevel(level) => {
+                write!(w, "SET TRANSACTION ISOLATION LEVEL {level}")
+            }
+        }
+    }
+}
+
+impl FormatSql for show::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::Tables { schema: None } => w.write_str("SHOW TABLES"),
+            Self::Tables {
+                schema: Some(schema),
+            } => {
+                w.write_str("SHOW TABLES FROM ")?;
+                write_span(w, schema)
+            }
+            Self::Columns(name) => {
+                w.write_str("SHOW COLUMNS FROM ")?;
+                name.write_sql(w)
+            }
+        }
+    }
+}
+
+impl FormatSql for analyze::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("ANALYZE")?;
+        match &self.table_name {
+            Some(name) => {
+                w.write_char(' ')?;
+                name.write_sql(w)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl FormatSql for vacuum::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("VACUUM")?;
+        match &self.table_name {
+            Some(name) => {
+                w.write_char(' ')?;
+                name.write_sql(w)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl FormatSql for backup::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("BACKUP TO ")?;
+        write_string(w, &self.path.1)
+    }
+}
+
+impl FormatSql for explain::Statement<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str("EXPLAIN ")?;
+        if self.analyze {
+            w.write_str("ANALYZE ")?;
+        }
+        if self.format == explain::ExplainFormat::Json {
+            w.write_str("FORMAT JSON ")?;
+        }
+        self.statement.write_sql(w)
+    }
+}
+
+/// The left and right binding powers of the operator at the top of `expr`, `None` when it
+/// is a single operand, like a literal or a call, that never needs parentheses.
+fn binding_power(expr: &Expr<'_>) -> Option<(u8, u8)> {
+    match expr {
+        Expr::Binary { op: (_, op), .. } => Some(op.binding_power()),
+        Expr::Between { .. } | Expr::InList { .. } | Expr::Like { .. } | Expr::IsNull { .. } => {
+            Some((PREDICATE_BINDING_POWER, PREDICATE_BINDING_POWER + 1))
+        }
+        Expr::Unary {
+            op: (_, UnaryOperator::Not),
+            ..
+        } => Some((NOT_BINDING_POWER, NOT_BINDING_POWER)),
+        Expr::Unary {
+            op: (_, UnaryOperator::Minus),
+            ..
+        } => Some((MINUS_BINDING_POWER, MINUS_BINDING_POWER)),
+        _ => None,
+    }
+}
+
+/// Whether `expr` is a comparison or a predicate, they can't be chained without
+/// parentheses.
+fn is_comparison(expr: &Expr<'_>) -> bool {
+    match expr {
+        Expr::Binary { op: (_, op), .. } => op.is_comparison(),
+        Expr::Between { .. } | Expr::InList { .. } | Expr::Like { .. } | Expr::IsNull { .. } => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Writes `expr`, between parentheses when `parenthesize` says so.
+fn write_operand(w: &mut SqlWriter<'_>, expr: &Expr<'_>, parenthesize: bool) -> fmt::Result {
+    if parenthesize {
+        w.write_char('(')?;
+        expr.write_sql(w)?;
+        w.write_char(')')
+    } else {
+        expr.write_sql(w)
+    }
+}
+
+/// Writes the operand on the left of an operator with the left binding power `power`. It is
+/// parenthesized when the operator would otherwise take its right operand, or when both
+/// are comparisons.
+fn write_left(w: &mut SqlWriter<'_>, expr: &Expr<'_>, power: u8, comparison: bool) -> fmt::Result {
+    let parenthesize = binding_power(expr).is_some_and(|(_, right)| right <= power)
+        || (comparison && is_comparison(expr));
+    write_operand(w, expr, parenthesize)
+}
+
+/// Writes the operand on the right of an operator with the right binding power `power`.
+fn write_right(w: &mut SqlWriter<'_>, expr: &Expr<'_>, power: u8) -> fmt::Result {
+    let parenthesize = binding_power(expr).is_some_and(|(left, _)| left < power);
+    write_operand(w, expr, parenthesize)
+}
+
+/// Writes the operand of a predicate, followed by ` NOT` when it is negated.
+fn write_predicate_operand(w: &mut SqlWriter<'_>, expr: &Expr<'_>, negated: bool) -> fmt::Result {
+    write_left(w, expr, PREDICATE_BINDING_POWER, true)?;
+    w.write_str(if negated { " NOT" } else { "" })
+}
+
+fn write_like_pattern(w: &mut SqlWriter<'_>, pattern: &LikePattern) -> fmt::Result {
+    let mut text = String::new();
+    for token in pattern.tokens() {
+        match token {
+            LikeToken::Char(c @ ('%' | '_' | '\\')) => {
+                text.push('\\');
+                text.push(*c);
+            }
+            LikeToken::Char(c) => text.push(*c),
+            LikeToken::AnyChar => text.push('_'),
+            LikeToken::AnyString => text.push('%'),
+        }
+    }
+    write_string(w, &text)
+}
+
+impl FormatSql for BinaryOperator {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        w.write_str(match self {
+            Self::Eq => "=",
+            Self::NotEq => "<>",
+            Self::Lt => "<",
+            Self::LtEq => "<=",
+            Self::Gt => ">",
+            Self::GtEq => ">=",
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Modulo => "%",
+            Self::JsonGet => "->",
+            Self::JsonGetText => "->>",
+        })
+    }
+}
+
+impl FormatSql for Expr<'_> {
+    fn write_sql(&self, w: &mut SqlWriter<'_>) -> fmt::Result {
+        match self {
+            Self::Literal((_, value)) => value.write_sql(w),
+            Self::Parameter((_, 0)) => w.write_char('?'),
+            Self::Parameter((_, n)) => write!(w, "${n}"),
+            Self::Column(column) => column.write_sql(w),
+            Self::Unary {
+                op: (_, UnaryOperator::Not),
+                expr,
+            } => {
+                w.write_str("NOT ")?;
+                write_right(w, expr, NOT_BINDING_POWER)
+            }
+            Self::Unary {
+                op: (_, UnaryOperator::Minus),
+                expr,
+            } => {
+                w.write_char('-')?;
+                // `--` would start a comment.
+                let operand = Formatted(&**expr, FormatOptions::compact()).to_string();
+                let parenthesize = operand.starts_with('-')
+                    || binding_power(expr).is_some_and(|(left, _)| left < MINUS_BINDING_POWER);
+                if parenthesize {
+                    write!(w, "({operand})")
+                } else {
+                    w.write_str(&operand)
+                }
+            }
+            Self::Binary {
+                op: (_, op),
+                left,
+                right,
+            } => {
+                let (left_power, right_power) = op.binding_power();
+                write_left(w, left, left_power, op.is_comparison())?;
+                w.write_char(' ')?;
+                op.write_sql(w)?;
+                w.write_char(' ')?;
+                write_right(w, right, right_power)
+            }
+            Self::Nested((_, expr)) => write_operand(w, expr, true),
+            Self::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                write_predicate_operand(w, expr, *negated)?;
+                w.write_str(" BETWEEN ")?;
+                write_right(w, low, BETWEEN_BOUND_BINDING_POWER)?;
+                w.write_str(" AND ")?;
+                write_right(w, high, BETWEEN_BOUND_BINDING_POWER)
+            }
+            Self::InList {
+                expr,
+                negated,
+                list,
+            } => {
+                write_predicate_operand(w, expr, *negated)?;
+                w.write_str(" IN (")?;
+                comma_separated(w, list.iter(), |w, item| item.write_sql(w))?;
+                w.write_char(')')
+            }
+            Self::Like {
+                expr,
+                negated,
+                pattern: (_, pattern),
+            } => {
+                write_predicate_operand(w, expr, *negated)?;
+                w.write_str(" LIKE ")?;
+                write_like_pattern(w, pattern)
+            }
+            Self::IsNull { expr, negated } => {
+                write_left(w, expr, PREDICATE_BINDING_POWER, true)?;
+                w.write_str(if *negated { " IS NOT NULL" } else { " IS NULL" })
+            }
+            Self::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => {
+                w.write_str("CASE")?;
+                if let Some(operand) = operand {
+                    w.write_char(' ')?;
+                    operand.write_sql(w)?;
+                }
+                for (condition, result) in branches.iter() {
+                    w.write_str(" WHEN ")?;
+                    condition.write_sql(w)?;
+                    w.write_str(" THEN ")?;
+                    result.write_sql(w)?;
+                }
+                if let Some(else_result) = else_result {
+                    w.write_str(" ELSE ")?;
+                    else_result.write_sql(w)?;
+                }
+                w.write_str(" END")
+            }
+            Self::Function { name, args, .. } => {
+                write_span(w, name)?;
+                w.write_char('(')?;
+                comma_separated(w, args.iter(), |w, arg| arg.write_sql(w))?;
+                w.write_char(')')
+            }
+            Self::Aggregate {
+                function: (_, function),
+                distinct,
+                arg,
+            } => {
+                w.write_str(match function {
+                    AggregateFunction::Count => "COUNT(",
+                    AggregateFunction::Sum => "SUM(",
+                    AggregateFunction::Avg => "AVG(",
+                    AggregateFunction::Min => "MIN(",
+                    AggregateFunction::Max => "MAX(",
+                })?;
+                if *distinct {
+                    w.write_str("DISTINCT ")?;
+                }
+                match arg {
+                    Some(arg) => arg.write_sql(w)?,
+                    None => w.write_char('*')?,
+                }
+                w.write_char(')')
+            }
+            Self::Cast {
+                expr, tp: (_, tp), ..
+            } => {
+                w.write_str("CAST(")?;
+                expr.write_sql(w)?;
+                w.write_str(" AS ")?;
+                tp.write_sql(w)?;
+                w.write_char(')')
+            }
+            Self::Subscript {
+                expr,
+                index: (_, index),
+            } => {
+                write_operand(w, expr, binding_power(expr).is_some())?;
+                w.write_char('[')?;
+                index.write_sql(w)?;
+                w.write_char(']')
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use nom::combinator::all_consuming;
+
+    use crate::{
+        ast::{commands::create::Column, visit::VisitorMut},
+        parse::{Parse, RawSpan, TableMap},
+    };
+
+    use super::*;
+
+    /// Formats `input` with `options`, checking the output parses back to a statement that
+    /// is formatted the same way.
+    fn round_trip(input: &str, options: &FormatOptions) -> String {
+        let (_, statement) = all_consuming(Statement::parse)(RawSpan::new(input)).unwrap();
+        let formatted = statement.format_sql(options);
+        let (_, reparsed) = all_consuming(Statement::parse)(RawSpan::new(&formatted))
+            .unwrap_or_else(|err| panic!("{formatted} doesn't parse: {err:?}"));
+        assert_eq!(reparsed.format_sql(options), formatted, "{input}");
+        formatted
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cases = [
+            (
+                "select  a+b*2 , t.c as x from t where not x = 1 and y is not null",
+                "SELECT a + b * 2, t.c AS x FROM t WHERE NOT x = 1 AND y IS NOT NULL",
+            ),
+            (
+                "SELECT * FROM users u LEFT OUTER JOIN orders o ON o.user_id = u.id cross join z",
+                "SELECT * FROM users AS u LEFT JOIN orders AS o ON o.user_id = u.id CROSS JOIN z",
+            ),
+            (
+                "SELECT * FROM a JOIN (b JOIN c USING (id)) ON a.id = b.id",
+                "SELECT * FROM a JOIN (b JOIN c USING (id)) ON a.id = b.id",
+            ),
+            (
+                "select \"select\", `a b`, \"a\"\"b\" from \"t\"",
+                "SELECT \"select\", \"a b\", \"a\"\"b\" FROM t",
+            ),
+            (
+                "SELECT count(*), sum(DISTINCT x) FROM t GROUP BY y HAVING count(*) > 1 \
+                 ORDER BY y desc nulls last, z LIMIT 10 OFFSET 5",
+                "SELECT COUNT(*), SUM(DISTINCT x) FROM t GROUP BY y HAVING COUNT(*) > 1 \
+                 ORDER BY y DESC NULLS LAST, z LIMIT 10 OFFSET 5",
+            ),
+            (
+                "SELECT name FROM t WHERE name LIKE 'it''s\\_%%' AND id NOT IN (1,2) \
+                 AND x not between 1 and 2+3",
+                "SELECT name FROM t WHERE name LIKE 'it''s\\_%' AND id NOT IN (1, 2) \
+                 AND x NOT BETWEEN 1 AND 2 + 3",
+            ),
+            (
+                "SELECT CASE WHEN a THEN 'x' ELSE 'y' END, case a when 1 then 2 end, \
+                 CAST(a AS varchar(10)), a[1], j->>'k', upper(b) FROM t",
+                "SELECT CASE WHEN a THEN 'x' ELSE 'y' END, CASE a WHEN 1 THEN 2 END, \
+                 CAST(a AS VARCHAR(10)), a[1], j ->> 'k', upper(b) FROM t",
+            ),
+            (
+                "SELECT DATE '2024-01-31', x'ff', 1.50, 1e3, -5, $1, ?, null, true, \
+                 ARRAY[1, 2], interval '1 day' FROM t",
+                "SELECT DATE '2024-01-31', X'FF', 1.50, 1e3, -5, $1, ?, NULL, TRUE, \
+                 ARRAY[1, 2], INTERVAL '1 day' FROM t",
+            ),
+            (
+                "WITH RECURSIVE r(n) AS (SELECT n FROM s UNION ALL SELECT n FROM r) \
+                 SELECT n FROM r",
+                "WITH RECURSIVE r(n) AS (SELECT n FROM s UNION ALL SELECT n FROM r) \
+                 SELECT n FROM r",
+            ),
+            (
+                "create table if not exists s.t (id serial primary key, \
+                 name varchar(10 bytes) not null default 'a', tags int32[], \
+                 mood enum('a','b'), price decimal(10,2) check (price > 0), \
+                 owner int64 references users (id), unique (name), \
+                 foreign key (owner) references users)",
+                "CREATE TABLE IF NOT EXISTS s.t (id INT32 AUTO_INCREMENT PRIMARY KEY, \
+                 name VARCHAR(10 BYTES) NOT NULL DEFAULT 'a', tags INT32[], \
+                 mood ENUM('a', 'b'), price DECIMAL(10, 2) CHECK (price > 0), \
+                 owner INT64 REFERENCES users (id), UNIQUE (name), \
+                 FOREIGN KEY (owner) REFERENCES users)",
+            ),
+            (
+                "create table t (id int32) with (compression='none', compression = 'ZSTD')",
+                "CREATE TABLE t (id INT32) WITH (compression = 'zstd')",
+            ),
+            (
+                "create table t (id int32) with (engine='memory', compression='lz4')",
+                "CREATE TABLE t (id INT32) WITH (compression = 'lz4', engine = 'memory')",
+            ),
+            (
+                "create table t (id int32, n int64) with (bloom_filter='n,id')",
+                "CREATE TABLE t (id INT32, n INT64) WITH (bloom_filter = 'n, id')",
+            ),
+            (
+                "create table t (id int32, day date) partition by range(day) \
+                 (partition old values less than ('2024-01-01'), \
+                 partition new values less than (maxvalue)) with (engine = 'memory')",
+                "CREATE TABLE t (id INT32, day DATE) PARTITION BY RANGE (day) \
+                 (PARTITION old VALUES LESS THAN (DATE '2024-01-01'), \
+                 PARTITION new VALUES LESS THAN (MAXVALUE)) WITH (engine = 'memory')",
+            ),
+            (
+                "create table t (id int32) partition by hash (id) partitions 4",
+                "CREATE TABLE t (id INT32) PARTITION BY HASH (id) PARTITIONS 4",
+            ),
+            (
+                "INSERT INTO t (a, b) VALUES (1, DEFAULT), (2 + 3, 'x') \
+                 ON CONFLICT (a) DO UPDATE SET b = excluded.b RETURNING a AS x",
+                "INSERT INTO t (a, b) VALUES (1, DEFAULT), (2 + 3, 'x') \
+                 ON CONFLICT (a) DO UPDATE SET b = excluded.b RETURNING a AS x",
+            ),
+            (
+                "ALTER TABLE t ADD c int8 NOT NULL",
+                "ALTER TABLE t ADD COLUMN c INT8 NOT NULL",
+            ),
+            (
+                "alter table t rename column a to b",
+                "ALTER TABLE t RENAME COLUMN a TO b",
+            ),
+            ("drop table if exists a, s.b", "DROP TABLE IF EXISTS a, s.b"),
+            (
+                "create schema if not exists s",
+                "CREATE SCHEMA IF NOT EXISTS s",
+            ),
+            (
+                "CREATE TYPE mood AS ENUM ('sad', 'happy')",
+                "CREATE TYPE mood AS ENUM('sad', 'happy')",
+            ),
+            (
+                "create view v as select id from users",
+                "CREATE VIEW v AS SELECT id FROM users",
+            ),
+            ("BEGIN WORK", "BEGIN"),
+            ("rollback to sp", "ROLLBACK TO SAVEPOINT sp"),
+            (
+                "set transaction isolation level read committed",
+                "SET TRANSACTION ISOLATION LEVEL READ COMMITTED",
+            ),
+            ("DESCRIBE users", "SHOW COLUMNS FROM users"),
+            ("show tables from s", "SHOW TABLES FROM s"),
+            ("vacuum  users", "VACUUM users"),
+            ("backup to '/tmp/db'", "BACKUP TO '/tmp/db'"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(round_trip(input, &FormatOptions::compact()), expected);
+            round_trip(input, &FormatOptions::indented(2));
+        }
+    }
+
+    #[test]
+    fn test_literals_and_types() {
+        let literals = [
+            (Value::VarChar("it's".into()), "'it''s'"),
+            (Value::Char("ab  ".into()), "'ab'"),
+            (Value::Null, "NULL"),
+            (Value::Bool(false), "FALSE"),
+            (Value::I32(-7), "-7"),
+            (Value::F64(1.5), "1.5e0"),
+            (
+                Value::Date("2024-02-29".parse().unwrap()),
+                "DATE '2024-02-29'",
+            ),
+            (Value::Blob([0xab, 0x01].into()), "X'AB01'"),
+            (
+                Value::Array([Value::VarChar("a".into()), Value::Null].into()),
+                "ARRAY['a', NULL]",
+            ),
+        ];
+        for (value, literal) in literals {
+            assert_eq!(value.sql_literal().to_string(), literal);
+        }
+        assert_eq!(Value::VarChar("it's".into()).to_string(), "it's");
+
+        let types = [
+            (SqlType::VarChar(10, LengthUnit::Bytes), "VARCHAR(10 BYTES)"),
+            (
+                SqlType::Decimal {
+                    precision: 10,
+                    scale: 2,
+                },
+                "DECIMAL(10, 2)",
+            ),
+            (SqlType::Enum(["a'b".into()].into()), "ENUM('a''b')"),
+            (SqlType::Array(Box::new(SqlType::U8)), "UINT8[]"),
+        ];
+        for (tp, ddl) in types {
+            assert_eq!(tp.to_string(), ddl);
+        }
+    }
+
+    #[test]
+    fn test_bound_insert() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                Column::new("id", SqlType::I32),
+                Column::new("at", SqlType::Date),
+            ]
+            .into_iter()
+            .map(|column| (column.name.clone(), column))
+            .collect(),
+        );
+        let (_, statement) = Statement::parse_with_table_map(
+            &table_map,
+            "INSERT INTO users (id, at) VALUES (1 + 1, '2024-01-31')".into(),
+        )
+        .unwrap();
+        let formatted = statement.to_string();
+        assert_eq!(
+            formatted,
+            "INSERT INTO users (id, at) VALUES (2, DATE '2024-01-31')"
+        );
+        assert!(Statement::parse_with_table_map(&table_map, RawSpan::new(&formatted)).is_ok());
+    }
+
+    /// Removes the parentheses written in the input, leaving the formatter to add the ones
+    /// the operators need.
+    struct Unnest;
+
+    impl<'a> VisitorMut<'a> for Unnest {
+        fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+            while let Expr::Nested((_, nested)) = expr {
+                *expr = std::mem::replace(&mut **nested, Expr::Parameter((RawSpan::new(""), 0)));
+            }
+            crate::ast::visit::walk_expr_mut(self, expr);
+        }
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let cases = [
+            ("(a + b) * c", "(a + b) * c"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("(a - b) - c", "a - b - c"),
+            ("-(a + b)", "-(a + b)"),
+            ("-(-1)", "-(-1)"),
+            ("NOT (a OR b)", "NOT (a OR b)"),
+            ("(NOT a) = b", "(NOT a) = b"),
+            ("(a OR b) AND c", "(a OR b) AND c"),
+            ("a OR (b AND c)", "a OR b AND c"),
+            ("(a = b) = c", "(a = b) = c"),
+            ("(a AND b) IS NULL", "(a AND b) IS NULL"),
+            ("(a + b)[1]", "(a + b)[1]"),
+            (
+                "x BETWEEN (1 + 2) AND (a OR b)",
+                "x BETWEEN 1 + 2 AND (a OR b)",
+            ),
+            ("(a -> 'b') ->> 'c'", "a -> 'b' ->> 'c'"),
+        ];
+        for (input, expected) in cases {
+            let (_, mut expr) = all_consuming(Expr::parse)(RawSpan::new(input)).unwrap();
+            Unnest.visit_expr_mut(&mut expr);
+            let formatted = expr.to_string();
+            assert_eq!(formatted, expected);
+            let (_, mut reparsed) = all_consuming(Expr::parse)(RawSpan::new(&formatted)).unwrap();
+            Unnest.visit_expr_mut(&mut reparsed);
+            assert_eq!(reparsed.to_string(), formatted);
+        }
+    }
+
+    #[test]
+    fn test_indented() {
+        let output = [
+            "WITH u AS (SELECT id FROM users WHERE age > 18) \
+             SELECT u.id, o.total FROM u JOIN orders o ON o.user_id = u.id \
+             WHERE o.total > 10 ORDER BY o.total DESC LIMIT 5",
+            "CREATE TABLE t (id int32 PRIMARY KEY, name varchar(10), UNIQUE (name))",
+            "INSERT INTO t (id, name) VALUES (1, 'a'), (2, 'b') RETURNING id",
+            "CREATE VIEW v AS SELECT id FROM t",
+        ]
+        .map(|input| round_trip(input, &FormatOptions::indented(2)))
+        .join("\n\n");
+        insta::assert_snapshot!(output);
+    }
+}
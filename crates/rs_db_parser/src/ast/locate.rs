@@ -0,0 +1,499 @@
+use std::borrow::Cow;
+
+use crate::{
+    ast::{
+        commands::{
+            alter::{self, Action},
+            analyze,
+            create::{self, ColumnConstraint, RawColumn, SqlType, TableConstraint},
+            insert::{self, ConflictAction, OnConflict},
+            select::{self, Projection},
+            show, vacuum,
+        },
+        expr::{ColumnRef, Expr},
+        statement::Statement,
+        table::{JoinConstraint, TableExpr, TableName},
+    },
+    functions::FunctionRegistry,
+    parse::{RawSpan, TableMap, WithSpan},
+    parsers::row::RowValue,
+    value::Value,
+};
+
+/// A node of a statement, borrowed from it. The nodes are the ones a
+/// [`Visitor`](crate::ast::visit::Visitor) visits.
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'s, 'a> {
+    Statement(&'s Statement<'a>),
+    Select(&'s select::Statement<'a>),
+    Insert(&'s insert::Statement<'a>),
+    UnboundInsert(&'s insert::UnboundStatement<'a>),
+    Create(&'s create::Statement<'a>),
+    Alter(&'s alter::Statement<'a>),
+    RawColumn(&'s RawColumn<'a>),
+    Projection(&'s Projection<'a>),
+    TableExpr(&'s TableExpr<'a>),
+    TableName(&'s TableName<'a>),
+    Expr(&'s Expr<'a>),
+    ColumnRef(&'s ColumnRef<'a>),
+    /// A literal of an expression, a value of an `INSERT` or a column default.
+    Literal(&'s WithSpan<'a, Value>),
+}
+
+/// The node found at an offset by [`node_at_offset`].
+#[derive(Debug, Clone)]
+pub struct NodeAt<'s, 'a> {
+    pub node: Node<'s, 'a>,
+    /// The nodes containing [`NodeAt::node`], from the statement down to its parent.
+    pub parents: Vec<Node<'s, 'a>>,
+}
+
+/// Finds the innermost node of `statement` whose source contains the byte `offset` of the
+/// input. A node spans from the start of its first token to the end of its last one, so
+/// keywords between the children of a node, like `FROM`, belong to the node.
+/// Returns `None` if the offset is outside the statement.
+#[must_use]
+pub fn node_at_offset<'s, 'a>(
+    statement: &'s Statement<'a>,
+    offset: usize,
+) -> Option<NodeAt<'s, 'a>> {
+    let mut parents = Vec::new();
+    let mut found = None;
+    locate(Node::Statement(statement), offset, &mut parents, &mut found);
+    found.map(|(_, node_at)| node_at)
+}
+
+/// Computes the extent of `node` from its own spans and the extents of its children,
+/// keeping the deepest node containing `offset` in `found` along with its depth.
+fn locate<'s, 'a>(
+    node: Node<'s, 'a>,
+    offset: usize,
+    parents: &mut Vec<Node<'s, 'a>>,
+    found: &mut Option<(usize, NodeAt<'s, 'a>)>,
+) -> Option<(usize, usize)> {
+    let mut extent = None;
+    let mut extend = |(start, end): (usize, usize)| {
+        extent = Some(extent.map_or((start, end), |(s, e): (usize, usize)| {
+            (s.min(start), e.max(end))
+        }));
+    };
+    for span in node.spans() {
+        let start = span.location_offset();
+        extend((start, start + span.fragment().len()));
+    }
+    parents.push(node);
+    for child in node.children() {
+        if let Some(child_extent) = locate(child, offset, parents, found) {
+            extend(child_extent);
+        }
+    }
+    parents.pop();
+
+    let depth = parents.len();
+    if extent.is_some_and(|(start, end)| start <= offset && offset < end)
+        && found
+            .as_ref()
+            .is_none_or(|(found_depth, _)| *found_depth < depth)
+    {
+        *found = Some((
+            depth,
+            NodeAt {
+                node,
+                parents: parents.clone(),
+            },
+        ));
+    }
+    extent
+}
+
+impl<'s, 'a> Node<'s, 'a> {
+    /// The spans of the node itself, not covered by its children.
+    fn spans(self) -> Vec<RawSpan<'a>> {
+        match self {
+            Self::Select(select) => select
+                .with
+                .iter()
+                .map(|cte| cte.name)
+                .chain(select.group_by.iter().map(|(span, _)| *span))
+                .chain(select.order_by.iter().map(|item| item.expr.0))
+                .chain(
+                    select
+                        .limit
+                        .iter()
+                        .chain(select.offset.iter())
+                        .map(|(span, _)| *span),
+                )
+                .collect(),
+            Self::Insert(insert) => insert
+                .columns
+                .iter()
+                .copied()
+                .chain(insert.rows.iter().map(|row| row.span))
+                .collect(),
+            Self::UnboundInsert(insert) => [insert.columns.0]
+                .into_iter()
+                .chain(insert.rows.iter().map(|(span, _)| *span))
+                .collect(),
+            Self::Alter(alter) => match &alter.action {
+                Action::AddColumn(_) => vec![],
+                Action::DropColumn(column) => vec![*column],
+                Action::RenameColumn { from, to } => vec![*from, *to],
+            },
+            Self::Create(create) => create.constraints.iter().map(|(span, _)| *span).collect(),
+            Self::RawColumn(column) => [column.name, column.tp.0]
+                .into_iter()
+                .chain(column.constraints.iter().map(|(span, _)| *span))
+                .collect(),
+            Self::Projection(projection) => match projection {
+                Projection::Wildcard(span) => vec![*span],
+                Projection::Column { alias, .. } => alias.iter().copied().collect(),
+                Projection::Expr {
+                    expr: (span, _),
+                    alias,
+                } => [*span].into_iter().chain(alias.iter().copied()).collect(),
+            },
+            Self::TableExpr(table) => match table {
+                TableExpr::Table { alias, .. } => alias.iter().copied().collect(),
+                TableExpr::SelfReference { name, alias } => {
+                    [*name].into_iter().chain(alias.iter().copied()).collect()
+                }
+                TableExpr::Join {
+                    kind: (span, _),
+                    constraint,
+                    ..
+                } => {
+                    let mut spans = vec![*span];
+                    if let JoinConstraint::Using(columns) = constraint {
+                        spans.extend(columns.iter().copied());
+                    }
+                    spans
+                }
+            },
+            Self::TableName(name) => name.schema.into_iter().chain([name.name]).collect(),
+            Self::Expr(expr) => vec![expr.span()],
+            Self::ColumnRef(column) => column.qualifier.into_iter().chain([column.name]).collect(),
+            Self::Literal((span, _)) => vec![*span],
+            Self::Statement(_) => vec![],
+        }
+    }
+
+    /// The nodes directly below this one, in source order.
+    #[must_use]
+    pub fn children(self) -> Vec<Self> {
+        match self {
+            Self::Statement(statement) => match statement {
+                Statement::Create(create) => vec![Self::Create(create)],
+                Statement::CreateView(create_view) => vec![
+                    Self::TableName(&create_view.view_name),
+                    Self::Select(&create_view.query.1),
+                ],
+                Statement::Drop(drop) => drop.table_names.iter().map(Self::TableName).collect(),
+                Statement::Alter(alter) => vec![Self::Alter(alter)],
+                Statement::Insert(insert) => vec![Self::Insert(insert)],
+                Statement::UnboundInsert(insert) => vec![Self::UnboundInsert(insert)],
+                Statement::Select(select) => vec![Self::Select(select)],
+                Statement::Explain(explain) => vec![Self::Statement(&explain.statement)],
+                Statement::Show(show::Statement::Columns(name))
+                | Statement::Analyze(analyze::Statement {
+                    table_name: Some(name),
+                })
+                | Statement::Vacuum(vacuum::Statement {
+                    table_name: Some(name),
+                }) => vec![Self::TableName(name)],
+                Statement::CreateSchema(_)
+                | Statement::CreateType(_)
+                | Statement::Transaction(_)
+                | Statement::Show(_)
+                | Statement::Analyze(_)
+                | Statement::Vacuum(_)
+                | Statement::Backup(_) => vec![],
+            },
+            Self::Select(select) => {
+                let mut children = Vec::new();
+                for cte in select.with.iter() {
+                    children.push(Self::Select(&cte.query.1));
+                    if let Some(term) = &cte.recursive {
+                        children.push(Self::Select(&term.query.1));
+                    }
+                }
+                children.extend(select.projection.iter().map(Self::Projection));
+                children.push(Self::TableExpr(&select.from));
+                let exprs = select
+                    .selection
+                    .iter()
+                    .chain(select.group_by.iter().map(|(_, expr)| expr))
+                    .chain(select.having.iter())
+                    .chain(select.order_by.iter().map(|item| &item.expr.1));
+                children.extend(exprs.map(Self::Expr));
+                children
+            }
+            Self::Insert(insert) => {
+                let mut children = vec![Self::TableName(&insert.table_name)];
+                for row in insert.rows.iter() {
+                    children.extend(row.values.iter().map(|(_, value)| Self::Literal(value)));
+                }
+                children.extend(on_conflict_children(insert.on_conflict.as_ref()));
+                children.extend(insert.returning.iter().map(Self::Projection));
+                children
+            }
+            Self::UnboundInsert(insert) => {
+                let mut children = vec![Self::TableName(&insert.table_name)];
+                children.extend(insert.columns.1.iter().map(Self::ColumnRef));
+                for (_, values) in insert.rows.iter() {
+                    children.extend(values.iter().filter_map(|(_, value)| match value {
+                        RowValue::Expr(expr) => Some(Self::Expr(expr)),
+                        RowValue::Default => None,
+                    }));
+                }
+                children.extend(on_conflict_children(
+                    insert
+                        .on_conflict
+                        .as_ref()
+                        .map(|(_, on_conflict)| on_conflict),
+                ));
+                children.extend(insert.returning.iter().map(Self::Projection));
+                children
+            }
+            Self::Create(create) => {
+                let mut children = vec![Self::TableName(&create.table_name)];
+                children.extend(create.columns.iter().map(Self::RawColumn));
+                for (_, constraint) in create.constraints.iter() {
+                    match constraint {
+                        TableConstraint::Check(expr) => children.push(Self::Expr(expr)),
+                        TableConstraint::ForeignKey { references, .. } => {
+                            children.push(Self::TableName(&references.table_name));
+                        }
+                        TableConstraint::PrimaryKey(_) | TableConstraint::Unique(_) => {}
+                    }
+                }
+                children
+            }
+            Self::Alter(alter) => {
+                let mut children = vec![Self::TableName(&alter.table_name)];
+                if let Action::AddColumn(column) = &alter.action {
+                    children.push(Self::RawColumn(column));
+                }
+                children
+            }
+            Self::RawColumn(column) => column
+                .constraints
+                .iter()
+                .filter_map(|(_, constraint)| match constraint {
+                    ColumnConstraint::Default(value) => Some(Self::Literal(value)),
+                    ColumnConstraint::Check(expr) => Some(Self::Expr(expr)),
+                    ColumnConstraint::References(references) => {
+                        Some(Self::TableName(&references.table_name))
+                    }
+                    ColumnConstraint::NotNull
+                    | ColumnConstraint::Null
+                    | ColumnConstraint::PrimaryKey
+                    | ColumnConstraint::Unique
+                    | ColumnConstraint::AutoIncrement => None,
+                })
+                .collect(),
+            Self::Projection(projection) => match projection {
+                Projection::Wildcard(_) => vec![],
+                Projection::Column { column, .. } => vec![Self::ColumnRef(column)],
+                Projection::Expr {
+                    expr: (_, expr), ..
+                } => vec![Self::Expr(expr)],
+            },
+            Self::TableExpr(table) => match table {
+                TableExpr::Table { name, .. } => vec![Self::TableName(name)],
+                TableExpr::SelfReference { .. } => vec![],
+                TableExpr::Join {
+                    left,
+                    right,
+                    constraint,
+                    ..
+                } => {
+                    let mut children = vec![Self::TableExpr(left), Self::TableExpr(right)];
+                    if let JoinConstraint::On(expr) = constraint {
+                        children.push(Self::Expr(expr));
+                    }
+                    children
+                }
+            },
+            Self::Expr(expr) => {
+                let mut children = match expr {
+                    Expr::Literal(value) => vec![Self::Literal(value)],
+                    Expr::Column(column) => vec![Self::ColumnRef(column)],
+                    _ => vec![],
+                };
+                children.extend(expr.children().map(Self::Expr));
+                children
+            }
+            Self::TableName(_) | Self::ColumnRef(_) | Self::Literal(_) => vec![],
+        }
+    }
+}
+
+fn on_conflict_children<'s, 'a>(on_conflict: Option<&'s OnConflict<'a>>) -> Vec<Node<'s, 'a>> {
+    match on_conflict.map(|on_conflict| &on_conflict.action) {
+        Some(ConflictAction::Update(assignments)) => assignments
+            .iter()
+            .map(|assignment| Node::Expr(&assignment.value))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+impl NodeAt<'_, '_> {
+    /// The type of the node: the type an expression produces, the type of the column a
+    /// reference resolves to, of a literal, or of a column definition.
+    /// Expressions and column references resolve against the tables of the enclosing query
+    /// or `INSERT`, and the common table expressions of the enclosing queries.
+    /// Returns `None` for other nodes, for expressions that are always `NULL` and when the
+    /// node can't be resolved against `table_map`.
+    #[must_use]
+    pub fn sql_type(&self, table_map: &TableMap, functions: &FunctionRegistry) -> Option<SqlType> {
+        match self.node {
+            Node::Literal((_, value)) => return value.sql_type(),
+            Node::RawColumn(column) => return Some(column.tp.1.clone()),
+            Node::Expr(_) | Node::ColumnRef(_) => {}
+            _ => return None,
+        }
+
+        let mut table_map = Cow::Borrowed(table_map);
+        let mut from = None;
+        for parent in &self.parents {
+            match parent {
+                Node::Select(select) => {
+                    table_map = Cow::Owned(select.bind_ctes(&table_map).ok()?.into_owned());
+                    from = Some(Cow::Borrowed(&select.from));
+                }
+                Node::Insert(insert) => from = Some(Cow::Owned(table(insert.table_name))),
+                Node::UnboundInsert(insert) => from = Some(Cow::Owned(table(insert.table_name))),
+                _ => {}
+            }
+        }
+        let scope = from?.scope(&table_map).ok()?;
+        match self.node {
+            Node::Expr(expr) => expr.result_type(&scope.columns, functions).ok()?,
+            Node::ColumnRef(column) => Some(scope.resolve(column).ok()?.tp.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn table(name: TableName<'_>) -> TableExpr<'_> {
+    TableExpr::Table { name, alias: None }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::ast::commands::create::{Column, LengthUnit};
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [
+                ("id".into(), Column::new("id", SqlType::I32)),
+                (
+                    "name".into(),
+                    Column::new("name", SqlType::VarChar(20, LengthUnit::Chars)),
+                ),
+            ]
+            .into(),
+        );
+        table_map
+    }
+
+    /// Describes the node at the first occurrence of `at` in `input`, the kinds of the node
+    /// and its parents, and the type of the node.
+    fn describe(input: &str, at: &str) -> Option<(Vec<&'static str>, Option<SqlType>)> {
+        let table_map = get_table_map();
+        let (_, statement) =
+            Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+        let node_at = node_at_offset(&statement, input.find(at)?)?;
+        let kinds = node_at
+            .parents
+            .iter()
+            .chain([&node_at.node])
+            .map(|node| match node {
+                Node::Statement(_) => "statement",
+                Node::Select(_) => "select",
+                Node::Insert(_) | Node::UnboundInsert(_) => "insert",
+                Node::Create(_) => "create",
+                Node::Alter(_) => "alter",
+                Node::RawColumn(_) => "raw_column",
+                Node::Projection(_) => "projection",
+                Node::TableExpr(_) => "table_expr",
+                Node::TableName(_) => "table_name",
+                Node::Expr(_) => "expr",
+                Node::ColumnRef(_) => "column_ref",
+                Node::Literal(_) => "literal",
+            })
+            .collect();
+        Some((
+            kinds,
+            node_at.sql_type(&table_map, FunctionRegistry::builtins()),
+        ))
+    }
+
+    #[test]
+    fn test_node_at_offset() {
+        let input = "SELECT id, upper(name) FROM users WHERE id + 1 > 2 LIMIT 5";
+        assert_eq!(
+            describe(input, "name"),
+            Some((
+                vec![
+                    "statement",
+                    "select",
+                    "projection",
+                    "expr",
+                    "expr",
+                    "column_ref"
+                ],
+                Some(SqlType::VarChar(20, LengthUnit::Chars))
+            ))
+        );
+        assert_eq!(
+            describe(input, "upper"),
+            Some((
+                vec!["statement", "select", "projection", "expr"],
+                Some(SqlType::VarChar(20, LengthUnit::Chars))
+            ))
+        );
+        assert_eq!(
+            describe(input, "users"),
+            Some((
+                vec!["statement", "select", "table_expr", "table_name"],
+                None
+            ))
+        );
+        assert_eq!(
+            describe(input, ">"),
+            Some((vec!["statement", "select", "expr"], Some(SqlType::Bool)))
+        );
+        assert_eq!(
+            describe(input, "FROM"),
+            Some((vec!["statement", "select"], None))
+        );
+        assert_eq!(describe(input, "SELECT"), None);
+
+        assert_eq!(
+            describe(
+                "WITH u AS (SELECT id AS uid FROM users) SELECT uid + 1 FROM u",
+                "uid +"
+            )
+            .map(|(_, tp)| tp),
+            Some(Some(SqlType::I32))
+        );
+        assert_eq!(
+            describe("INSERT INTO users (id, name) VALUES (1, 'a')", "'a'"),
+            Some((
+                vec!["statement", "insert", "literal"],
+                Some(SqlType::VarChar(1, LengthUnit::Chars))
+            ))
+        );
+        assert_eq!(
+            describe("CREATE TABLE t (id int32 DEFAULT 0, age uint8)", "uint8"),
+            Some((vec!["statement", "create", "raw_column"], Some(SqlType::U8)))
+        );
+    }
+}
@@ -1 +1,8 @@
 pub mod commands;
+pub mod expr;
+pub mod fingerprint;
+pub mod format;
+pub mod locate;
+pub mod statement;
+pub mod table;
+pub mod visit;
@@ -0,0 +1,401 @@
+use nom::combinator::map;
+
+use crate::{
+    ast::commands::{
+        alter, analyze, backup, create, create_schema, create_type, create_view, drop, explain,
+        insert, select, show, transaction, vacuum,
+    },
+    errors::{custom_error, ParseError, ParseResult, RawParseError},
+    lexer::{TokenCursor, TokenKind},
+    parse::{Parse, RawSpan, TableMap},
+};
+
+#[derive(Debug, Clone)]
+pub enum Statement<'a> {
+    Create(create::Statement<'a>),
+    CreateSchema(create_schema::Statement<'a>),
+    CreateType(create_type::Statement<'a>),
+    CreateView(create_view::Statement<'a>),
+    Drop(drop::Statement<'a>),
+    Alter(alter::Statement<'a>),
+    Insert(insert::Statement<'a>),
+    /// An `INSERT` parsed without a catalog, see [`Statement::bind`].
+    UnboundInsert(insert::UnboundStatement<'a>),
+    Select(select::Statement<'a>),
+    Transaction(transaction::Statement<'a>),
+    Show(show::Statement<'a>),
+    Analyze(analyze::Statement<'a>),
+    Vacuum(vacuum::Statement<'a>),
+    Backup(backup::Statement<'a>),
+    Explain(explain::Statement<'a>),
+}
+
+impl<'a> Statement<'a> {
+    /// Parses any statement, resolving tables and columns against `table_map`.
+    /// # Errors
+    /// Returns an error if the input is not a valid statement.
+    pub fn parse_with_table_map(table_map: &TableMap, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        Self::dispatch(Some(table_map), input)
+    }
+
+    /// Looks at the leading keyword and hands the input to the matching command parser,
+    /// so the resulting error tree only contains the errors of that command.
+    fn dispatch(table_map: Option<&TableMap>, input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let keyword = tokens.context("Statement", |tokens| match tokens.peek() {
+            Some(token) if matches!(token.kind, TokenKind::Keyword | TokenKind::Identifier) => {
+                tokens.next();
+                Ok(token.span)
+            }
+            _ => Err(tokens.unexpected("a statement")),
+        })?;
+
+        match keyword.fragment().to_ascii_lowercase().as_str() {
+            "create" if tokens.is_next("view") => match table_map {
+                Some(table_map) => map(
+                    |i| create_view::Statement::parse_with_table_map(table_map, i),
+                    Self::CreateView,
+                )(input),
+                None => map(create_view::Statement::parse, Self::CreateView)(input),
+            },
+            "create" if tokens.is_next("schema") => match table_map {
+                Some(table_map) => map(
+                    |i| create_schema::Statement::parse_with_table_map(table_map, i),
+                    Self::CreateSchema,
+                )(input),
+                None => map(create_schema::Statement::parse, Self::CreateSchema)(input),
+            },
+            "create" if tokens.is_next("type") => match table_map {
+                Some(table_map) => map(
+                    |i| create_type::Statement::parse_with_table_map(table_map, i),
+                    Self::CreateType,
+                )(input),
+                None => map(create_type::Statement::parse, Self::CreateType)(input),
+            },
+            "create" => match table_map {
+                Some(table_map) => map(
+                    |i| create::Statement::parse_with_table_map(table_map, i),
+                    Self::Create,
+                )(input),
+                None => map(create::Statement::parse, Self::Create)(input),
+            },
+            "drop" => match table_map {
+                Some(table_map) => map(
+                    |i| drop::Statement::parse_with_table_map(table_map, i),
+                    Self::Drop,
+                )(input),
+                None => map(drop::Statement::parse, Self::Drop)(input),
+            },
+            "alter" => map(alter::Statement::parse, Self::Alter)(input),
+            "insert" => match table_map {
+                Some(table_map) => map(
+                    |i| insert::Statement::parse_with_table_map(table_map, i),
+                    Self::Insert,
+                )(input),
+                None => map(insert::UnboundStatement::parse, Self::UnboundInsert)(input),
+            },
+            "select" | "with" => match table_map {
+                Some(table_map) => map(
+                    |i| select::Statement::parse_with_table_map(table_map, i),
+                    Self::Select,
+                )(input),
+                None => map(select::Statement::parse, Self::Select)(input),
+            },
+            "begin" | "commit" | "rollback" | "savepoint" | "set" => {
+                map(transaction::Statement::parse, Self::Transaction)(input)
+            }
+            "show" | "describe" | "desc" => match table_map {
+                Some(table_map) => map(
+                    |i| show::Statement::parse_with_table_map(table_map, i),
+                    Self::Show,
+                )(input),
+                None => map(show::Statement::parse, Self::Show)(input),
+            },
+            "analyze" => match table_map {
+                Some(table_map) => map(
+                    |i| analyze::Statement::parse_with_table_map(table_map, i),
+                    Self::Analyze,
+                )(input),
+                None => map(analyze::Statement::parse, Self::Analyze)(input),
+            },
+            "vacuum" => match table_map {
+                Some(table_map) => map(
+                    |i| vacuum::Statement::parse_with_table_map(table_map, i),
+                    Self::Vacuum,
+                )(input),
+                None => map(vacuum::Statement::parse, Self::Vacuum)(input),
+            },
+            "backup" => map(backup::Statement::parse, Self::Backup)(input),
+            "explain" => match table_map {
+                Some(table_map) => map(
+                    |i| explain::Statement::parse_with_table_map(table_map, i),
+                    Self::Explain,
+                )(input),
+                None => map(explain::Statement::parse, Self::Explain)(input),
+            },
+            _ => Err(custom_error(
+                keyword,
+                nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::UnknownStatement)),
+            )),
+        }
+    }
+
+    /// Resolves the tables, columns and types of a statement parsed with
+    /// [`Statement::parse`] against `table_map`, giving the statement
+    /// [`Statement::parse_with_table_map`] parses.
+    /// # Errors
+    /// Returns the error [`Statement::parse_with_table_map`] returns for an unknown or
+    /// conflicting table, column or type.
+    pub fn bind(self, table_map: &TableMap) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        Ok(match self {
+            Self::Create(statement) => Self::Create(statement.bind(table_map)?),
+            Self::CreateSchema(statement) => Self::CreateSchema(statement.bind(table_map)?),
+            Self::CreateType(statement) => Self::CreateType(statement.bind(table_map)?),
+            Self::CreateView(statement) => Self::CreateView(statement.bind(table_map)?),
+            Self::Drop(statement) => Self::Drop(statement.bind(table_map)?),
+            Self::Insert(statement) => Self::Insert(statement),
+            Self::UnboundInsert(statement) => Self::Insert(statement.bind(table_map)?),
+            Self::Select(statement) => Self::Select(statement.bind(table_map)?),
+            Self::Show(statement) => Self::Show(statement.bind(table_map)?),
+            Self::Analyze(statement) => Self::Analyze(statement.bind(table_map)?),
+            Self::Vacuum(statement) => Self::Vacuum(statement.bind(table_map)?),
+            Self::Explain(statement) => Self::Explain(statement.bind(table_map)?),
+            statement @ (Self::Alter(_) | Self::Transaction(_) | Self::Backup(_)) => statement,
+        })
+    }
+}
+
+impl<'a> Parse<'a> for Statement<'a> {
+    /// Parses any statement without a catalog, only checking its syntax. An `INSERT` is
+    /// parsed as [`Statement::UnboundInsert`], use [`Statement::bind`] to resolve it.
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        Self::dispatch(None, input)
+    }
+}
+
+/// A [`Statement`] that doesn't borrow the input, so it can be stored after the input is
+/// dropped. Names keep their text and every span keeps its position, see
+/// [`crate::parse::Span`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedStatement {
+    Create(create::OwnedStatement),
+    CreateSchema(create_schema::OwnedStatement),
+    CreateType(create_type::OwnedStatement),
+    CreateView(create_view::OwnedStatement),
+    Drop(drop::OwnedStatement),
+    Alter(alter::OwnedStatement),
+    Insert(insert::OwnedStatement),
+    UnboundInsert(insert::OwnedUnboundStatement),
+    Select(select::OwnedStatement),
+    Transaction(transaction::OwnedStatement),
+    Show(show::OwnedStatement),
+    Analyze(analyze::OwnedStatement),
+    Vacuum(vacuum::OwnedStatement),
+    Backup(backup::OwnedStatement),
+    Explain(explain::OwnedStatement),
+}
+
+impl Statement<'_> {
+    /// Copies the statement out of the input, see [`OwnedStatement`].
+    pub fn to_owned(&self) -> OwnedStatement {
+        match self {
+            Self::Create(statement) => OwnedStatement::Create(statement.to_owned()),
+            Self::CreateSchema(statement) => OwnedStatement::CreateSchema(statement.to_owned()),
+            Self::CreateType(statement) => OwnedStatement::CreateType(statement.to_owned()),
+            Self::CreateView(statement) => OwnedStatement::CreateView(statement.to_owned()),
+            Self::Drop(statement) => OwnedStatement::Drop(statement.to_owned()),
+            Self::Alter(statement) => OwnedStatement::Alter(statement.to_owned()),
+            Self::Insert(statement) => OwnedStatement::Insert(statement.to_owned()),
+            Self::UnboundInsert(statement) => OwnedStatement::UnboundInsert(statement.to_owned()),
+            Self::Select(statement) => OwnedStatement::Select(statement.to_owned()),
+            Self::Transaction(statement) => OwnedStatement::Transaction(statement.to_owned()),
+            Self::Show(statement) => OwnedStatement::Show(statement.to_owned()),
+            Self::Analyze(statement) => OwnedStatement::Analyze(statement.to_owned()),
+            Self::Vacuum(statement) => OwnedStatement::Vacuum(statement.to_owned()),
+            Self::Backup(statement) => OwnedStatement::Backup(statement.to_owned()),
+            Self::Explain(statement) => OwnedStatement::Explain(statement.to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use miette::GraphicalTheme;
+
+    use crate::{
+        ast::{
+            commands::create::{Column, SqlType},
+            expr::{BinaryOperator, OwnedExpr},
+            table::OwnedTableExpr,
+        },
+        parse::{parse_format_error, Span},
+    };
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [Column::new("id", SqlType::I32)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        );
+        table_map
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let table_map = get_table_map();
+        let cases = [
+            ("CREATE TABLE t (id int8)", "Create"),
+            ("  drop table users", "Drop"),
+            ("ALTER TABLE users DROP COLUMN id", "Alter"),
+            ("INSERT INTO users (id) VALUES (1)", "Insert"),
+            ("SELECT * FROM users", "Select"),
+            ("CREATE VIEW v AS SELECT id FROM users", "CreateView"),
+            ("CREATE SCHEMA sales", "CreateSchema"),
+            ("CREATE TYPE mood AS ENUM ('sad', 'happy')", "CreateType"),
+            ("BEGIN", "Transaction"),
+            ("ROLLBACK TO SAVEPOINT sp", "Transaction"),
+            (
+                "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE",
+                "Transaction",
+            ),
+            ("SHOW TABLES", "Show"),
+            ("VACUUM users", "Vacuum"),
+            ("BACKUP TO 'backups'", "Backup"),
+            ("EXPLAIN ANALYZE SELECT * FROM users", "Explain"),
+            ("-- list\nSHOW /* all */ TABLES", "Show"),
+            ("SELECT id /* the key */ FROM users -- done", "Select"),
+            (
+                "CREATE TABLE t (\n  id int8, -- key\n  name /* nested /* */ */ varchar(10)\n)",
+                "Create",
+            ),
+            ("DESCRIBE users", "Show"),
+            ("SELECT * FROM public.users", "Select"),
+            (
+                "WITH u AS (SELECT id FROM users) SELECT id FROM u",
+                "Select",
+            ),
+        ];
+        for (input, expected) in cases {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            assert!(
+                format!("{statement:?}").starts_with(expected),
+                "{input} parsed as {statement:?}"
+            );
+            assert!(format!("{:?}", statement.to_owned()).starts_with(expected));
+        }
+    }
+
+    #[test]
+    fn test_to_owned() {
+        let table_map = get_table_map();
+        let owned = {
+            let input = String::from("SELECT id FROM users\n  WHERE id > 1");
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(&input)).unwrap();
+            statement.to_owned()
+        };
+        let OwnedStatement::Select(select) = &owned else {
+            panic!("Expected a select, got {owned:?}");
+        };
+        let OwnedTableExpr::Table { name, alias: None } = &select.from else {
+            panic!("Expected a table, got {:?}", select.from);
+        };
+        assert_eq!(&*name.name.fragment, "users");
+        assert_eq!(
+            name.name.span,
+            Span {
+                offset: 15,
+                len: 5,
+                line: 1
+            }
+        );
+        let Some(OwnedExpr::Binary {
+            op: (op_span, BinaryOperator::Gt),
+            ..
+        }) = &select.selection
+        else {
+            panic!("Expected a comparison, got {:?}", select.selection);
+        };
+        assert_eq!((op_span.offset, op_span.line), (32, 2));
+    }
+
+    #[test]
+    fn test_parse_without_table_map() {
+        let table_map = get_table_map();
+        assert!(Statement::parse("SELECT * FROM users".into()).is_ok());
+        let (_, statement) = Statement::parse("INSERT INTO users (id) VALUES (1)".into()).unwrap();
+        assert!(matches!(statement, Statement::UnboundInsert(_)));
+        assert!(matches!(
+            statement.bind(&table_map).unwrap(),
+            Statement::Insert(_)
+        ));
+
+        let (_, statement) = Statement::parse("SELECT missing FROM users".into()).unwrap();
+        assert!(statement.bind(&table_map).is_err());
+        let (_, statement) = Statement::parse("DROP TABLE missing".into()).unwrap();
+        assert!(statement.bind(&table_map).is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let table_map = get_table_map();
+        for input in [
+            "CREATE TABLE t (id int8 PRIMARY KEY, name varchar(10) DEFAULT 'a')",
+            "INSERT INTO users (id) VALUES (1) RETURNING id",
+            "SELECT id, count(*) FROM users WHERE id LIKE 'a%' GROUP BY id ORDER BY id LIMIT 1",
+            "WITH u AS (SELECT id FROM users) SELECT CAST(id AS int8) FROM u",
+            "ROLLBACK TO SAVEPOINT sp",
+        ] {
+            let (_, statement) =
+                Statement::parse_with_table_map(&table_map, RawSpan::new(input)).unwrap();
+            let owned = statement.to_owned();
+            let json = serde_json::to_string(&owned).unwrap();
+            assert_eq!(
+                serde_json::from_str::<OwnedStatement>(&json).unwrap(),
+                owned,
+                "{input}"
+            );
+        }
+
+        let (_, statement) =
+            Statement::parse_with_table_map(&table_map, "SELECT id FROM users WHERE id > 1".into())
+                .unwrap();
+        insta::assert_json_snapshot!(statement.to_owned());
+    }
+
+    fn test_case_err(suffix: &str, input: &str) {
+        let table_map = get_table_map();
+        let Err(err) =
+            parse_format_error(input, |i| Statement::parse_with_table_map(&table_map, i))
+        else {
+            panic!("Expected error");
+        };
+        let mut s = String::new();
+        miette::GraphicalReportHandler::new()
+            .with_cause_chain()
+            .with_theme(GraphicalTheme::unicode_nocolor())
+            .with_context_lines(10)
+            .render_report(&mut s, &err)
+            .unwrap();
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_suffix(suffix);
+        settings.set_description(format!("Input: {input}",));
+        settings.bind(|| {
+            insta::assert_snapshot!(s);
+        });
+    }
+
+    #[test]
+    fn test_invalid_statement() {
+        test_case_err("unknown-keyword", "UPSERT INTO users (id) VALUES (1)");
+        test_case_err("invalid-create", "CREATE TABLE t (id int7)");
+    }
+}
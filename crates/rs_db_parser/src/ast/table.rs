@@ -0,0 +1,748 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use nom::{
+    branch::alt,
+    character::complete::char,
+    combinator::{map, opt, verify},
+    error::context,
+    sequence::{pair, terminated},
+};
+
+use crate::{
+    ast::{
+        commands::create::Column,
+        expr::{ColumnRef, Expr, OwnedExpr},
+    },
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    functions::FunctionRegistry,
+    lexer::{cut, TokenCursor},
+    parse::{
+        owned_with_span, ColumnMap, OwnedSpan, OwnedWithSpan, Parse, RawSpan, TableMap, WithSpan,
+    },
+    parsers::{
+        comma_sep,
+        identifier::{identifier, quoted_identifier},
+        limits::DepthGuard,
+        parse_with_span,
+    },
+};
+
+/// Words that end a table reference, so they are never read as an alias unless quoted, even
+/// when reserved keywords are accepted as names.
+const RESERVED: &[&str] = &[
+    "where", "group", "having", "order", "limit", "offset", "join", "inner", "left", "right",
+    "full", "outer", "cross", "on", "using", "as", "union",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JoinConstraint<'a> {
+    /// `ON predicate`.
+    On(Expr<'a>),
+    /// `USING (column, ...)`, the columns must exist on both sides.
+    Using(Box<[RawSpan<'a>]>),
+    /// `CROSS JOIN` has no constraint.
+    None,
+}
+
+/// A table name, optionally qualified with its schema, `schema.table`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TableName<'a> {
+    pub schema: Option<RawSpan<'a>>,
+    pub name: RawSpan<'a>,
+}
+
+/// The `FROM` clause: a table, or a tree of joins between tables.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TableExpr<'a> {
+    Table {
+        name: TableName<'a>,
+        alias: Option<RawSpan<'a>>,
+    },
+    /// The reference of a recursive common table expression to itself, in its recursive
+    /// term. The rows it yields are the ones produced by the previous iteration.
+    SelfReference {
+        name: RawSpan<'a>,
+        alias: Option<RawSpan<'a>>,
+    },
+    /// A join, the span points at the join keywords.
+    Join {
+        kind: WithSpan<'a, JoinKind>,
+        left: Box<TableExpr<'a>>,
+        right: Box<TableExpr<'a>>,
+        constraint: JoinConstraint<'a>,
+    },
+}
+
+/// The columns visible to the expressions of a query, built from its `FROM` clause.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scope {
+    /// Each table with the name it is referenced by, in source order. The columns are
+    /// sorted by name, and nullable when the table is on the outer side of a join.
+    pub tables: Vec<(Box<str>, Vec<Column>)>,
+    /// The columns by `table.column`, and by bare name when only one table has it.
+    pub columns: ColumnMap,
+    /// Bare names shared by more than one table, they must be qualified.
+    ambiguous: HashSet<Box<str>>,
+}
+
+impl<'a> Parse<'a> for TableName<'a> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Table Name",
+            map(
+                pair(opt(terminated(identifier, char('.'))), identifier),
+                |(schema, name)| Self { schema, name },
+            ),
+        )(input)
+    }
+}
+
+impl<'a> TableName<'a> {
+    #[must_use]
+    pub fn schema_name(&self) -> Option<&'a str> {
+        self.schema.map(|schema| *schema.fragment())
+    }
+
+    /// Whether both names point at the same table, an unqualified name is in the default
+    /// schema.
+    #[must_use]
+    pub fn same_table(&self, other: &TableName<'_>, table_map: &TableMap) -> bool {
+        let schema = |name: &TableName<'_>| {
+            name.schema
+                .map_or(table_map.default_schema(), |schema| schema.fragment())
+                .to_owned()
+        };
+        *self.name.fragment() == *other.name.fragment() && schema(self) == schema(other)
+    }
+
+    /// The columns of the table.
+    /// # Errors
+    /// Returns the span of the unknown schema or table with the matching error.
+    pub fn lookup<'m>(
+        &self,
+        table_map: &'m TableMap,
+    ) -> Result<&'m ColumnMap, WithSpan<'a, ParseError>> {
+        if let Some(schema) = self.schema {
+            if !table_map.contains_schema(schema.fragment()) {
+                return Err((schema, ParseError::SchemaNotFound));
+            }
+        }
+        table_map
+            .get_qualified(self.schema_name(), self.name.fragment())
+            .ok_or((self.name, ParseError::TableNotFound))
+    }
+}
+
+impl<'a> Parse<'a> for JoinKind {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let mut tokens = TokenCursor::new(input);
+        let kind = if tokens.is_next("join") || tokens.opt_keyword("inner").is_some() {
+            Self::Inner
+        } else if tokens.opt_keyword("cross").is_some() {
+            Self::Cross
+        } else {
+            let kind = if tokens.opt_keyword("left").is_some() {
+                Self::Left
+            } else if tokens.opt_keyword("right").is_some() {
+                Self::Right
+            } else if tokens.opt_keyword("full").is_some() {
+                Self::Full
+            } else {
+                return Err(tokens.unexpected("JOIN"));
+            };
+            tokens.opt_keyword("outer");
+            kind
+        };
+        tokens.keyword("join")?;
+        Ok((tokens.rest(), kind))
+    }
+}
+
+impl<'a> Parse<'a> for TableExpr<'a> {
+    /// Parses a table reference followed by any number of joins, joins are left-associative.
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        let _depth = DepthGuard::enter(input)?;
+        let mut tokens = TokenCursor::new(input);
+        let mut left = tokens.context("Table", Self::parse_factor)?;
+        while let Some(kind) = tokens.opt_parse(|i| parse_with_span(i, JoinKind::parse))? {
+            let right = cut(Self::parse_factor(&mut tokens))?;
+            let constraint = if kind.1 == JoinKind::Cross {
+                JoinConstraint::None
+            } else {
+                cut(Self::parse_constraint(&mut tokens))?
+            };
+            left = Self::Join {
+                kind,
+                left: Box::new(left),
+                right: Box::new(right),
+                constraint,
+            };
+        }
+        Ok((tokens.rest(), left))
+    }
+}
+
+impl<'a> TableExpr<'a> {
+    /// Parses `name [[AS] alias]` or a parenthesized join.
+    fn parse_factor(tokens: &mut TokenCursor<'a>) -> Result<Self, nom::Err<RawParseError<'a>>> {
+        if tokens.opt_symbol("(").is_some() {
+            let table = cut(tokens.parse(Self::parse))?;
+            cut(tokens.symbol(")"))?;
+            return Ok(table);
+        }
+        let name = tokens.parse(TableName::parse)?;
+        let mut ahead = tokens.clone();
+        ahead.opt_keyword("as");
+        let alias = ahead.opt_parse(context(
+            "Alias",
+            alt((
+                quoted_identifier,
+                verify(identifier, |alias: &RawSpan| {
+                    !RESERVED.contains(&alias.fragment().to_ascii_lowercase().as_str())
+                }),
+            )),
+        ))?;
+        if alias.is_some() {
+            *tokens = ahead;
+        }
+        Ok(Self::Table { name, alias })
+    }
+
+    fn parse_constraint(
+        tokens: &mut TokenCursor<'a>,
+    ) -> Result<JoinConstraint<'a>, nom::Err<RawParseError<'a>>> {
+        tokens.context("Join Constraint", |tokens| {
+            if tokens.opt_keyword("on").is_some() {
+                Ok(JoinConstraint::On(cut(tokens.parse(Expr::parse))?))
+            } else if tokens.opt_keyword("using").is_some() {
+                cut(tokens.symbol("("))?;
+                let columns = cut(tokens.parse(comma_sep(identifier)))?;
+                cut(tokens.symbol(")"))?;
+                Ok(JoinConstraint::Using(columns.into()))
+            } else {
+                Err(tokens.unexpected("ON or USING"))
+            }
+        })
+    }
+
+    /// Turns the unqualified references to the table `name` into
+    /// [`TableExpr::SelfReference`], returning their spans in source order.
+    pub fn mark_self_references(&mut self, name: &str) -> Vec<RawSpan<'a>> {
+        match self {
+            Self::Table {
+                name:
+                    TableName {
+                        schema: None,
+                        name: table,
+                    },
+                alias,
+            } if *table.fragment() == name => {
+                let (table, alias) = (*table, *alias);
+                *self = Self::SelfReference { name: table, alias };
+                vec![table]
+            }
+            Self::Table { .. } | Self::SelfReference { .. } => vec![],
+            Self::Join { left, right, .. } => {
+                let mut spans = left.mark_self_references(name);
+                spans.extend(right.mark_self_references(name));
+                spans
+            }
+        }
+    }
+
+    /// Collects the parameter placeholders of the join constraints.
+    pub(crate) fn collect_parameters_mut<'e>(&'e mut self, parameters: &mut Vec<&'e mut Expr<'a>>) {
+        if let Self::Join {
+            left,
+            right,
+            constraint,
+            ..
+        } = self
+        {
+            left.collect_parameters_mut(parameters);
+            right.collect_parameters_mut(parameters);
+            if let JoinConstraint::On(expr) = constraint {
+                expr.collect_parameters_mut(parameters);
+            }
+        }
+    }
+
+    /// Resolves the tables against `table_map` and checks the join constraints.
+    /// # Errors
+    /// Returns an error pointing at an unknown table, a name used by two tables, or an
+    /// unknown or ambiguous column of a join constraint.
+    pub fn scope(&self, table_map: &TableMap) -> Result<Scope, nom::Err<RawParseError<'a>>> {
+        let mut tables = Vec::new();
+        let mut merged = HashSet::new();
+        self.collect_tables(table_map, &mut tables, &mut merged)?;
+        Ok(Scope::new(tables, &merged))
+    }
+
+    fn collect_tables(
+        &self,
+        table_map: &TableMap,
+        tables: &mut Vec<(Box<str>, Vec<Column>)>,
+        merged: &mut HashSet<Box<str>>,
+    ) -> Result<(), nom::Err<RawParseError<'a>>> {
+        match self {
+            Self::Table { name, alias } => {
+                // A view is expanded to its query, whose columns it has.
+                let columns = match name.lookup(table_map) {
+                    Ok(columns) => Cow::Borrowed(columns),
+                    Err((span, ParseError::TableNotFound)) => {
+                        let view = table_map
+                            .view(name.schema_name(), name.name.fragment())
+                            .ok_or_else(|| failure(span, ParseError::TableNotFound))?;
+                        Cow::Owned(
+                            view.expand(table_map)
+                                .ok_or_else(|| failure(name.name, ParseError::InvalidView))?,
+                        )
+                    }
+                    Err((span, error)) => return Err(failure(span, error)),
+                };
+                push_table(tables, alias.unwrap_or(name.name), &columns)?;
+            }
+            Self::SelfReference { name, alias } => {
+                let columns = table_map
+                    .get(name.fragment())
+                    .ok_or_else(|| failure(*name, ParseError::TableNotFound))?;
+                push_table(tables, alias.unwrap_or(*name), columns)?;
+            }
+            Self::Join {
+                kind: (_, kind),
+                left,
+                right,
+                constraint,
+            } => {
+                let start = tables.len();
+                left.collect_tables(table_map, tables, merged)?;
+                let split = tables.len();
+                right.collect_tables(table_map, tables, merged)?;
+
+                // The constraint only sees the tables of this join, not the enclosing ones.
+                let (left_tables, right_tables) = tables[start..].split_at(split - start);
+                match constraint {
+                    JoinConstraint::On(expr) => {
+                        let scope = Scope::new(tables[start..].to_vec(), merged);
+                        scope.check(expr)?;
+                        expr.result_type(&scope.columns, FunctionRegistry::builtins())?;
+                    }
+                    JoinConstraint::Using(columns) => {
+                        for column in columns.iter() {
+                            let count = |tables: &[(Box<str>, Vec<Column>)]| {
+                                tables
+                                    .iter()
+                                    .flat_map(|(_, columns)| columns)
+                                    .filter(|c| *c.name == **column.fragment())
+                                    .count()
+                            };
+                            match (count(left_tables), count(right_tables)) {
+                                (1, 1) => {}
+                                (0, _) | (_, 0) => {
+                                    return Err(failure(*column, ParseError::ColumnNotFound))
+                                }
+                                _ => return Err(failure(*column, ParseError::AmbiguousColumn)),
+                            }
+                            merged.insert((*column.fragment()).into());
+                        }
+                    }
+                    JoinConstraint::None => {}
+                }
+
+                let nullable = match kind {
+                    JoinKind::Left => split..tables.len(),
+                    JoinKind::Right => start..split,
+                    JoinKind::Full => start..tables.len(),
+                    JoinKind::Inner | JoinKind::Cross => 0..0,
+                };
+                for (_, columns) in &mut tables[nullable] {
+                    for column in columns {
+                        column.nullable = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Scope {
+    /// Indexes the columns of `tables`, the bare names in `merged` come from `USING` and
+    /// are not ambiguous: they refer to the first table that has them.
+    pub(crate) fn new(tables: Vec<(Box<str>, Vec<Column>)>, merged: &HashSet<Box<str>>) -> Self {
+        let mut columns = HashMap::new();
+        let mut ambiguous = HashSet::new();
+        for (table, table_columns) in &tables {
+            for column in table_columns {
+                columns.insert(format!("{table}.{}", column.name).into(), column.clone());
+                if columns.contains_key(&column.name) {
+                    if !merged.contains(&column.name) {
+                        ambiguous.insert(column.name.clone());
+                    }
+                } else {
+                    columns.insert(column.name.clone(), column.clone());
+                }
+            }
+        }
+        for name in &ambiguous {
+            columns.remove(name);
+        }
+        Self {
+            tables,
+            columns,
+            ambiguous,
+        }
+    }
+
+    /// Finds the column a reference points at.
+    /// # Errors
+    /// Returns an error pointing at the qualifier if it is not a table of the scope, or at
+    /// the name if the column doesn't exist, or exists in more than one table and is not
+    /// qualified.
+    pub fn resolve<'a>(
+        &self,
+        column: &ColumnRef<'a>,
+    ) -> Result<&Column, nom::Err<RawParseError<'a>>> {
+        match column.qualifier {
+            Some(qualifier)
+                if !self
+                    .tables
+                    .iter()
+                    .any(|(table, _)| **table == **qualifier.fragment()) =>
+            {
+                return Err(failure(qualifier, ParseError::TableNotFound));
+            }
+            None if self.ambiguous.contains(*column.name.fragment()) => {
+                return Err(failure(column.name, ParseError::AmbiguousColumn));
+            }
+            _ => {}
+        }
+        self.columns
+            .get(&*column.key())
+            .ok_or_else(|| failure(column.name, ParseError::ColumnNotFound))
+    }
+
+    /// The `table.column` name of a reference, so references written with and without the
+    /// qualifier can be compared. `None` if the column doesn't exist.
+    #[must_use]
+    pub fn qualified_name(&self, column: &ColumnRef) -> Option<Box<str>> {
+        let table = match column.qualifier {
+            Some(qualifier) => self
+                .tables
+                .iter()
+                .find(|(table, _)| **table == **qualifier.fragment())?,
+            None => self
+                .tables
+                .iter()
+                .find(|(_, columns)| columns.iter().any(|c| *c.name == **column.name.fragment()))?,
+        };
+        table
+            .1
+            .iter()
+            .any(|c| *c.name == **column.name.fragment())
+            .then(|| format!("{}.{}", table.0, column.name.fragment()).into())
+    }
+
+    /// Checks every column used by `expr` can be resolved.
+    /// # Errors
+    /// Returns an error pointing at the first unknown or ambiguous column.
+    pub fn check<'a>(&self, expr: &Expr<'a>) -> Result<(), nom::Err<RawParseError<'a>>> {
+        expr.columns()
+            .iter()
+            .try_for_each(|column| self.resolve(column).map(|_| ()))
+    }
+}
+
+/// Adds a table to the scope under the name it is referenced by.
+pub(crate) fn push_table<'a>(
+    tables: &mut Vec<(Box<str>, Vec<Column>)>,
+    visible: RawSpan<'a>,
+    columns: &ColumnMap,
+) -> Result<(), nom::Err<RawParseError<'a>>> {
+    if tables
+        .iter()
+        .any(|(table, _)| **table == **visible.fragment())
+    {
+        return Err(failure(visible, ParseError::DuplicateTableName));
+    }
+    let mut columns = columns.values().cloned().collect::<Vec<_>>();
+    columns.sort_by(|a, b| a.name.cmp(&b.name));
+    tables.push(((*visible.fragment()).into(), columns));
+    Ok(())
+}
+
+fn failure(span: RawSpan<'_>, error: ParseError) -> nom::Err<RawParseError<'_>> {
+    custom_failure(
+        span,
+        nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedJoinConstraint {
+    On(OwnedExpr),
+    Using(Box<[OwnedSpan]>),
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedTableName {
+    pub schema: Option<OwnedSpan>,
+    pub name: OwnedSpan,
+}
+
+/// A [`TableExpr`] that doesn't borrow the input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedTableExpr {
+    Table {
+        name: OwnedTableName,
+        alias: Option<OwnedSpan>,
+    },
+    SelfReference {
+        name: OwnedSpan,
+        alias: Option<OwnedSpan>,
+    },
+    Join {
+        kind: OwnedWithSpan<JoinKind>,
+        left: Box<OwnedTableExpr>,
+        right: Box<OwnedTableExpr>,
+        constraint: OwnedJoinConstraint,
+    },
+}
+
+impl JoinConstraint<'_> {
+    pub fn to_owned(&self) -> OwnedJoinConstraint {
+        match self {
+            Self::On(expr) => OwnedJoinConstraint::On(expr.to_owned()),
+            Self::Using(columns) => {
+                OwnedJoinConstraint::Using(columns.iter().copied().map(OwnedSpan::from).collect())
+            }
+            Self::None => OwnedJoinConstraint::None,
+        }
+    }
+}
+
+impl TableName<'_> {
+    pub fn to_owned(&self) -> OwnedTableName {
+        OwnedTableName {
+            schema: self.schema.map(OwnedSpan::from),
+            name: self.name.into(),
+        }
+    }
+}
+
+impl TableExpr<'_> {
+    pub fn to_owned(&self) -> OwnedTableExpr {
+        match self {
+            Self::Table { name, alias } => OwnedTableExpr::Table {
+                name: name.to_owned(),
+                alias: alias.map(OwnedSpan::from),
+            },
+            Self::SelfReference { name, alias } => OwnedTableExpr::SelfReference {
+                name: (*name).into(),
+                alias: alias.map(OwnedSpan::from),
+            },
+            Self::Join {
+                kind,
+                left,
+                right,
+                constraint,
+            } => OwnedTableExpr::Join {
+                kind: owned_with_span(kind, |kind| *kind),
+                left: Box::new(TableExpr::to_owned(left)),
+                right: Box::new(TableExpr::to_owned(right)),
+                constraint: constraint.to_owned(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::ast::commands::create::{LengthUnit, SqlType};
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        for (table, columns) in [
+            (
+                "users",
+                vec![
+                    ("id", SqlType::I32),
+                    ("name", SqlType::VarChar(10, LengthUnit::Chars)),
+                ],
+            ),
+            (
+                "orders",
+                vec![("id", SqlType::I32), ("user_id", SqlType::I32)],
+            ),
+        ] {
+            let columns = columns
+                .into_iter()
+                .map(|(name, tp)| {
+                    let mut column = Column::new(name, tp);
+                    column.nullable = false;
+                    (column.name.clone(), column)
+                })
+                .collect();
+            table_map.insert(table.into(), columns);
+        }
+        table_map
+    }
+
+    /// Renders the tree with explicit parentheses, to check associativity at a glance.
+    fn render(table: &TableExpr) -> String {
+        match table {
+            TableExpr::SelfReference { name, alias } => match alias {
+                Some(alias) => format!("@{} {}", name.fragment(), alias.fragment()),
+                None => format!("@{}", name.fragment()),
+            },
+            TableExpr::Table { name, alias } => {
+                let name = match name.schema {
+                    Some(schema) => format!("{}.{}", schema.fragment(), name.name.fragment()),
+                    None => name.name.fragment().to_string(),
+                };
+                match alias {
+                    Some(alias) => format!("{name} {}", alias.fragment()),
+                    None => name,
+                }
+            }
+            TableExpr::Join {
+                kind,
+                left,
+                right,
+                constraint,
+            } => {
+                let constraint = match constraint {
+                    JoinConstraint::On(_) => " On".to_string(),
+                    JoinConstraint::Using(columns) => format!(
+                        " Using {}",
+                        columns
+                            .iter()
+                            .map(|c| *c.fragment())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ),
+                    JoinConstraint::None => String::new(),
+                };
+                format!(
+                    "({} {:?} {}{constraint})",
+                    render(left),
+                    kind.1,
+                    render(right)
+                )
+            }
+        }
+    }
+
+    fn parse(input: &str) -> String {
+        let (rest, table) = TableExpr::parse(input.into()).unwrap();
+        assert!(rest.fragment().is_empty(), "Unparsed input: {rest}");
+        render(&table)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("users"), "users");
+        assert_eq!(parse("users AS u"), "users u");
+        assert_eq!(parse("public.users u"), "public.users u");
+        assert_eq!(
+            parse("users u JOIN orders o ON u = o"),
+            "(users u Inner orders o On)"
+        );
+        assert_eq!(
+            parse("a LEFT OUTER JOIN b USING (id, name) cross join c"),
+            "((a Left b Using id,name) Cross c)"
+        );
+        assert_eq!(
+            parse("a full join (b right join c using (id)) using (id)"),
+            "(a Full (b Right c Using id) Using id)"
+        );
+        assert_eq!(parse("a inner join b on true"), "(a Inner b On)");
+        assert!(TableExpr::parse("a JOIN b".into()).is_err());
+        assert!(TableExpr::parse("a JOIN b USING ()".into()).is_err());
+
+        let (rest, _) = TableExpr::parse("users where".into()).unwrap();
+        assert_eq!(*rest.fragment(), " where");
+
+        let (_, mut table) =
+            TableExpr::parse("t a JOIN (u JOIN t ON true) USING (id)".into()).unwrap();
+        let spans = table.mark_self_references("t");
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.location_offset())
+                .collect::<Vec<_>>(),
+            [0, 17]
+        );
+        assert_eq!(render(&table), "(@t a Inner (u Inner @t On) Using id)");
+    }
+
+    fn build_scope(input: &'static str) -> Result<Scope, ()> {
+        let table_map = get_table_map();
+        let (_, table) = TableExpr::parse(input.into()).unwrap();
+        table.scope(&table_map).map_err(|_| ())
+    }
+
+    #[test]
+    fn test_scope() {
+        let scope = build_scope("users u LEFT JOIN orders ON u.id = user_id").unwrap();
+        assert_eq!(
+            scope
+                .tables
+                .iter()
+                .map(|(name, _)| &**name)
+                .collect::<Vec<_>>(),
+            ["u", "orders"]
+        );
+        assert!(!scope.columns["u.id"].nullable);
+        assert!(scope.columns["orders.id"].nullable);
+        assert!(scope.columns["user_id"].nullable);
+        assert!(!scope.columns.contains_key("id"));
+        let column = |input: &'static str| ColumnRef::parse(input.into()).unwrap().1;
+        assert!(scope.resolve(&column("id")).is_err());
+        assert!(scope.resolve(&column("users.id")).is_err());
+        assert!(scope.resolve(&column("name")).is_ok());
+        assert_eq!(
+            scope.qualified_name(&column("user_id")).as_deref(),
+            Some("orders.user_id")
+        );
+        assert_eq!(scope.qualified_name(&column("u.age")), None);
+
+        let scope = build_scope("users JOIN orders USING (id)").unwrap();
+        assert!(scope.resolve(&column("id")).is_ok());
+        assert_eq!(
+            scope.qualified_name(&column("id")).as_deref(),
+            Some("users.id")
+        );
+
+        assert!(build_scope("users JOIN orders ON id = user_id").is_err());
+        assert!(build_scope("users JOIN orders ON users.age = 1").is_err());
+        assert!(build_scope("users JOIN orders USING (name)").is_err());
+        assert!(build_scope("users JOIN users ON true").is_err());
+        assert!(build_scope("users JOIN users AS u2 ON true").is_ok());
+        assert!(build_scope("products").is_err());
+
+        let scope = build_scope("public.users JOIN orders USING (id)").unwrap();
+        assert!(scope.resolve(&column("users.name")).is_ok());
+        assert!(build_scope("sales.users").is_err());
+        assert!(build_scope("public.products").is_err());
+    }
+}
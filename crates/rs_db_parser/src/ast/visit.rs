@@ -0,0 +1,622 @@
+use crate::{
+    ast::{
+        commands::{
+            alter::{self, Action},
+            analyze,
+            create::{self, ColumnConstraint, RawColumn, References, TableConstraint},
+            insert::{self, ConflictAction, OnConflict},
+            select::{self, Projection},
+            show, vacuum,
+        },
+        expr::{ColumnRef, Expr},
+        statement::Statement,
+        table::{JoinConstraint, TableExpr, TableName},
+    },
+    parse::WithSpan,
+    parsers::row::RowValue,
+    value::Value,
+};
+
+/// Walks the nodes of a statement in source order. Every method visits the children of its
+/// node with the matching `walk_*` function, so an implementation only overrides the nodes
+/// it cares about, and calls the `walk_*` function itself to keep going deeper.
+pub trait Visitor<'a> {
+    fn visit_statement(&mut self, statement: &Statement<'a>) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_select(&mut self, select: &select::Statement<'a>) {
+        walk_select(self, select);
+    }
+
+    fn visit_insert(&mut self, insert: &insert::Statement<'a>) {
+        walk_insert(self, insert);
+    }
+
+    fn visit_unbound_insert(&mut self, insert: &insert::UnboundStatement<'a>) {
+        walk_unbound_insert(self, insert);
+    }
+
+    fn visit_create(&mut self, create: &create::Statement<'a>) {
+        walk_create(self, create);
+    }
+
+    fn visit_alter(&mut self, alter: &alter::Statement<'a>) {
+        walk_alter(self, alter);
+    }
+
+    fn visit_raw_column(&mut self, column: &RawColumn<'a>) {
+        walk_raw_column(self, column);
+    }
+
+    fn visit_projection(&mut self, projection: &Projection<'a>) {
+        walk_projection(self, projection);
+    }
+
+    fn visit_table_expr(&mut self, table: &TableExpr<'a>) {
+        walk_table_expr(self, table);
+    }
+
+    fn visit_table_name(&mut self, _name: &TableName<'a>) {}
+
+    fn visit_expr(&mut self, expr: &Expr<'a>) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_column_ref(&mut self, _column: &ColumnRef<'a>) {}
+
+    /// Visits a literal of an expression, a value of an `INSERT` or a column default.
+    fn visit_literal(&mut self, _value: &WithSpan<'a, Value>) {}
+}
+
+pub fn walk_statement<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, statement: &Statement<'a>) {
+    match statement {
+        Statement::Create(create) => visitor.visit_create(create),
+        Statement::CreateView(create_view) => {
+            visitor.visit_table_name(&create_view.view_name);
+            visitor.visit_select(&create_view.query.1);
+        }
+        Statement::Drop(drop) => {
+            for name in drop.table_names.iter() {
+                visitor.visit_table_name(name);
+            }
+        }
+        Statement::Alter(alter) => visitor.visit_alter(alter),
+        Statement::Insert(insert) => visitor.visit_insert(insert),
+        Statement::UnboundInsert(insert) => visitor.visit_unbound_insert(insert),
+        Statement::Select(select) => visitor.visit_select(select),
+        Statement::Explain(explain) => visitor.visit_statement(&explain.statement),
+        Statement::Show(show::Statement::Columns(name))
+        | Statement::Analyze(analyze::Statement {
+            table_name: Some(name),
+        })
+        | Statement::Vacuum(vacuum::Statement {
+            table_name: Some(name),
+        }) => {
+            visitor.visit_table_name(name);
+        }
+        Statement::CreateSchema(_)
+        | Statement::CreateType(_)
+        | Statement::Transaction(_)
+        | Statement::Show(_)
+        | Statement::Analyze(_)
+        | Statement::Vacuum(_)
+        | Statement::Backup(_) => {}
+    }
+}
+
+pub fn walk_select<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, select: &select::Statement<'a>) {
+    for cte in select.with.iter() {
+        visitor.visit_select(&cte.query.1);
+        if let Some(term) = &cte.recursive {
+            visitor.visit_select(&term.query.1);
+        }
+    }
+    for projection in select.projection.iter() {
+        visitor.visit_projection(projection);
+    }
+    visitor.visit_table_expr(&select.from);
+    let exprs = select
+        .selection
+        .iter()
+        .chain(select.group_by.iter().map(|(_, expr)| expr))
+        .chain(select.having.iter())
+        .chain(select.order_by.iter().map(|item| &item.expr.1));
+    for expr in exprs {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_insert<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, insert: &insert::Statement<'a>) {
+    visitor.visit_table_name(&insert.table_name);
+    for row in insert.rows.iter() {
+        for (_, value) in row.values.iter() {
+            visitor.visit_literal(value);
+        }
+    }
+    if let Some(on_conflict) = &insert.on_conflict {
+        walk_on_conflict(visitor, on_conflict);
+    }
+    for projection in insert.returning.iter() {
+        visitor.visit_projection(projection);
+    }
+}
+
+pub fn walk_unbound_insert<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    insert: &insert::UnboundStatement<'a>,
+) {
+    visitor.visit_table_name(&insert.table_name);
+    for column in &insert.columns.1 {
+        visitor.visit_column_ref(column);
+    }
+    for (_, values) in insert.rows.iter() {
+        for (_, value) in values.iter() {
+            if let RowValue::Expr(expr) = value {
+                visitor.visit_expr(expr);
+            }
+        }
+    }
+    if let Some((_, on_conflict)) = &insert.on_conflict {
+        walk_on_conflict(visitor, on_conflict);
+    }
+    for projection in insert.returning.iter() {
+        visitor.visit_projection(projection);
+    }
+}
+
+fn walk_on_conflict<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, on_conflict: &OnConflict<'a>) {
+    if let ConflictAction::Update(assignments) = &on_conflict.action {
+        for assignment in assignments.iter() {
+            visitor.visit_expr(&assignment.value);
+        }
+    }
+}
+
+pub fn walk_create<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, create: &create::Statement<'a>) {
+    visitor.visit_table_name(&create.table_name);
+    for column in create.columns.iter() {
+        visitor.visit_raw_column(column);
+    }
+    for (_, constraint) in create.constraints.iter() {
+        match constraint {
+            TableConstraint::Check(expr) => visitor.visit_expr(expr),
+            TableConstraint::ForeignKey { references, .. } => {
+                walk_references(visitor, references);
+            }
+            TableConstraint::PrimaryKey(_) | TableConstraint::Unique(_) => {}
+        }
+    }
+}
+
+pub fn walk_alter<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, alter: &alter::Statement<'a>) {
+    visitor.visit_table_name(&alter.table_name);
+    if let Action::AddColumn(column) = &alter.action {
+        visitor.visit_raw_column(column);
+    }
+}
+
+pub fn walk_raw_column<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, column: &RawColumn<'a>) {
+    for (_, constraint) in column.constraints.iter() {
+        match constraint {
+            ColumnConstraint::Default(value) => visitor.visit_literal(value),
+            ColumnConstraint::Check(expr) => visitor.visit_expr(expr),
+            ColumnConstraint::References(references) => walk_references(visitor, references),
+            ColumnConstraint::NotNull
+            | ColumnConstraint::Null
+            | ColumnConstraint::PrimaryKey
+            | ColumnConstraint::Unique
+            | ColumnConstraint::AutoIncrement => {}
+        }
+    }
+}
+
+fn walk_references<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, references: &References<'a>) {
+    visitor.visit_table_name(&references.table_name);
+}
+
+pub fn walk_projection<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, projection: &Projection<'a>) {
+    match projection {
+        Projection::Wildcard(_) => {}
+        Projection::Column { column, .. } => visitor.visit_column_ref(column),
+        Projection::Expr {
+            expr: (_, expr), ..
+        } => visitor.visit_expr(expr),
+    }
+}
+
+pub fn walk_table_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, table: &TableExpr<'a>) {
+    match table {
+        TableExpr::Table { name, .. } => visitor.visit_table_name(name),
+        TableExpr::SelfReference { .. } => {}
+        TableExpr::Join {
+            left,
+            right,
+            constraint,
+            ..
+        } => {
+            visitor.visit_table_expr(left);
+            visitor.visit_table_expr(right);
+            if let JoinConstraint::On(expr) = constraint {
+                visitor.visit_expr(expr);
+            }
+        }
+    }
+}
+
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &Expr<'a>) {
+    match expr {
+        Expr::Literal(value) => visitor.visit_literal(value),
+        Expr::Column(column) => visitor.visit_column_ref(column),
+        _ => {}
+    }
+    for child in expr.children() {
+        visitor.visit_expr(child);
+    }
+}
+
+/// Like [`Visitor`], with mutable access to the nodes so they can be rewritten in place.
+pub trait VisitorMut<'a> {
+    fn visit_statement_mut(&mut self, statement: &mut Statement<'a>) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_select_mut(&mut self, select: &mut select::Statement<'a>) {
+        walk_select_mut(self, select);
+    }
+
+    fn visit_insert_mut(&mut self, insert: &mut insert::Statement<'a>) {
+        walk_insert_mut(self, insert);
+    }
+
+    fn visit_unbound_insert_mut(&mut self, insert: &mut insert::UnboundStatement<'a>) {
+        walk_unbound_insert_mut(self, insert);
+    }
+
+    fn visit_create_mut(&mut self, create: &mut create::Statement<'a>) {
+        walk_create_mut(self, create);
+    }
+
+    fn visit_alter_mut(&mut self, alter: &mut alter::Statement<'a>) {
+        walk_alter_mut(self, alter);
+    }
+
+    fn visit_raw_column_mut(&mut self, column: &mut RawColumn<'a>) {
+        walk_raw_column_mut(self, column);
+    }
+
+    fn visit_projection_mut(&mut self, projection: &mut Projection<'a>) {
+        walk_projection_mut(self, projection);
+    }
+
+    fn visit_table_expr_mut(&mut self, table: &mut TableExpr<'a>) {
+        walk_table_expr_mut(self, table);
+    }
+
+    fn visit_table_name_mut(&mut self, _name: &mut TableName<'a>) {}
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_column_ref_mut(&mut self, _column: &mut ColumnRef<'a>) {}
+
+    fn visit_literal_mut(&mut self, _value: &mut WithSpan<'a, Value>) {}
+}
+
+pub fn walk_statement_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    statement: &mut Statement<'a>,
+) {
+    match statement {
+        Statement::Create(create) => visitor.visit_create_mut(create),
+        Statement::CreateView(create_view) => {
+            visitor.visit_table_name_mut(&mut create_view.view_name);
+            visitor.visit_select_mut(&mut create_view.query.1);
+        }
+        Statement::Drop(drop) => {
+            for name in drop.table_names.iter_mut() {
+                visitor.visit_table_name_mut(name);
+            }
+        }
+        Statement::Alter(alter) => visitor.visit_alter_mut(alter),
+        Statement::Insert(insert) => visitor.visit_insert_mut(insert),
+        Statement::UnboundInsert(insert) => visitor.visit_unbound_insert_mut(insert),
+        Statement::Select(select) => visitor.visit_select_mut(select),
+        Statement::Explain(explain) => visitor.visit_statement_mut(&mut explain.statement),
+        Statement::Show(show::Statement::Columns(name))
+        | Statement::Analyze(analyze::Statement {
+            table_name: Some(name),
+        })
+        | Statement::Vacuum(vacuum::Statement {
+            table_name: Some(name),
+        }) => {
+            visitor.visit_table_name_mut(name);
+        }
+        Statement::CreateSchema(_)
+        | Statement::CreateType(_)
+        | Statement::Transaction(_)
+        | Statement::Show(_)
+        | Statement::Analyze(_)
+        | Statement::Vacuum(_)
+        | Statement::Backup(_) => {}
+    }
+}
+
+pub fn walk_select_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    select: &mut select::Statement<'a>,
+) {
+    for cte in select.with.iter_mut() {
+        visitor.visit_select_mut(&mut cte.query.1);
+        if let Some(term) = &mut cte.recursive {
+            visitor.visit_select_mut(&mut term.query.1);
+        }
+    }
+    for projection in select.projection.iter_mut() {
+        visitor.visit_projection_mut(projection);
+    }
+    visitor.visit_table_expr_mut(&mut select.from);
+    let exprs = select
+        .selection
+        .iter_mut()
+        .chain(select.group_by.iter_mut().map(|(_, expr)| expr))
+        .chain(select.having.iter_mut())
+        .chain(select.order_by.iter_mut().map(|item| &mut item.expr.1));
+    for expr in exprs {
+        visitor.visit_expr_mut(expr);
+    }
+}
+
+pub fn walk_insert_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    insert: &mut insert::Statement<'a>,
+) {
+    visitor.visit_table_name_mut(&mut insert.table_name);
+    for row in insert.rows.iter_mut() {
+        for (_, value) in row.values.iter_mut() {
+            visitor.visit_literal_mut(value);
+        }
+    }
+    if let Some(on_conflict) = &mut insert.on_conflict {
+        walk_on_conflict_mut(visitor, on_conflict);
+    }
+    for projection in insert.returning.iter_mut() {
+        visitor.visit_projection_mut(projection);
+    }
+}
+
+pub fn walk_unbound_insert_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    insert: &mut insert::UnboundStatement<'a>,
+) {
+    visitor.visit_table_name_mut(&mut insert.table_name);
+    for column in &mut insert.columns.1 {
+        visitor.visit_column_ref_mut(column);
+    }
+    for (_, values) in insert.rows.iter_mut() {
+        for (_, value) in values.iter_mut() {
+            if let RowValue::Expr(expr) = value {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+    }
+    if let Some((_, on_conflict)) = &mut insert.on_conflict {
+        walk_on_conflict_mut(visitor, on_conflict);
+    }
+    for projection in insert.returning.iter_mut() {
+        visitor.visit_projection_mut(projection);
+    }
+}
+
+fn walk_on_conflict_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    on_conflict: &mut OnConflict<'a>,
+) {
+    if let ConflictAction::Update(assignments) = &mut on_conflict.action {
+        for assignment in assignments.iter_mut() {
+            visitor.visit_expr_mut(&mut assignment.value);
+        }
+    }
+}
+
+pub fn walk_create_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    create: &mut create::Statement<'a>,
+) {
+    visitor.visit_table_name_mut(&mut create.table_name);
+    for column in create.columns.iter_mut() {
+        visitor.visit_raw_column_mut(column);
+    }
+    for (_, constraint) in create.constraints.iter_mut() {
+        match constraint {
+            TableConstraint::Check(expr) => visitor.visit_expr_mut(expr),
+            TableConstraint::ForeignKey { references, .. } => {
+                walk_references_mut(visitor, references);
+            }
+            TableConstraint::PrimaryKey(_) | TableConstraint::Unique(_) => {}
+        }
+    }
+}
+
+pub fn walk_alter_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    alter: &mut alter::Statement<'a>,
+) {
+    visitor.visit_table_name_mut(&mut alter.table_name);
+    if let Action::AddColumn(column) = &mut alter.action {
+        visitor.visit_raw_column_mut(column);
+    }
+}
+
+pub fn walk_raw_column_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    column: &mut RawColumn<'a>,
+) {
+    for (_, constraint) in column.constraints.iter_mut() {
+        match constraint {
+            ColumnConstraint::Default(value) => visitor.visit_literal_mut(value),
+            ColumnConstraint::Check(expr) => visitor.visit_expr_mut(expr),
+            ColumnConstraint::References(references) => {
+                walk_references_mut(visitor, references);
+            }
+            ColumnConstraint::NotNull
+            | ColumnConstraint::Null
+            | ColumnConstraint::PrimaryKey
+            | ColumnConstraint::Unique
+            | ColumnConstraint::AutoIncrement => {}
+        }
+    }
+}
+
+fn walk_references_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    references: &mut References<'a>,
+) {
+    visitor.visit_table_name_mut(&mut references.table_name);
+}
+
+pub fn walk_projection_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    projection: &mut Projection<'a>,
+) {
+    match projection {
+        Projection::Wildcard(_) => {}
+        Projection::Column { column, .. } => visitor.visit_column_ref_mut(column),
+        Projection::Expr {
+            expr: (_, expr), ..
+        } => visitor.visit_expr_mut(expr),
+    }
+}
+
+pub fn walk_table_expr_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    table: &mut TableExpr<'a>,
+) {
+    match table {
+        TableExpr::Table { name, .. } => visitor.visit_table_name_mut(name),
+        TableExpr::SelfReference { .. } => {}
+        TableExpr::Join {
+            left,
+            right,
+            constraint,
+            ..
+        } => {
+            visitor.visit_table_expr_mut(left);
+            visitor.visit_table_expr_mut(right);
+            if let JoinConstraint::On(expr) = constraint {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+    }
+}
+
+/// Visits the literal or column of `expr`, then its sub-expressions. A visitor replacing
+/// `expr` itself does so in [`VisitorMut::visit_expr_mut`], before or instead of walking it.
+pub fn walk_expr_mut<'a, V: VisitorMut<'a> + ?Sized>(visitor: &mut V, expr: &mut Expr<'a>) {
+    match expr {
+        Expr::Literal(value) => visitor.visit_literal_mut(value),
+        Expr::Column(column) => visitor.visit_column_ref_mut(column),
+        _ => {}
+    }
+    for child in expr.children_mut() {
+        visitor.visit_expr_mut(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::parse::{Parse, RawSpan};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Names {
+        tables: Vec<String>,
+        columns: Vec<String>,
+        literals: usize,
+    }
+
+    impl<'a> Visitor<'a> for Names {
+        fn visit_table_name(&mut self, name: &TableName<'a>) {
+            self.tables.push(name.name.fragment().to_string());
+        }
+
+        fn visit_column_ref(&mut self, column: &ColumnRef<'a>) {
+            self.columns.push(column.name.fragment().to_string());
+        }
+
+        fn visit_literal(&mut self, _value: &WithSpan<'a, Value>) {
+            self.literals += 1;
+        }
+    }
+
+    /// Replaces every literal of an expression with the next parameter.
+    struct Parameterize(Vec<Value>);
+
+    impl<'a> VisitorMut<'a> for Parameterize {
+        fn visit_expr_mut(&mut self, expr: &mut Expr<'a>) {
+            if let Expr::Literal((span, value)) = expr {
+                self.0.push(value.clone());
+                #[allow(clippy::cast_possible_truncation)]
+                let index = self.0.len() as u16;
+                *expr = Expr::Parameter((*span, index));
+            } else {
+                walk_expr_mut(self, expr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let (_, statement) = Statement::parse(RawSpan::new(
+            "WITH u AS (SELECT id FROM users WHERE age > 18) \
+             SELECT u.id, upper(o.name) FROM u JOIN orders o ON o.user_id = u.id \
+             WHERE o.total BETWEEN 1 AND 10 ORDER BY o.total",
+        ))
+        .unwrap();
+        let mut names = Names::default();
+        names.visit_statement(&statement);
+        assert_eq!(names.tables, ["users", "u", "orders"]);
+        assert_eq!(
+            names.columns,
+            ["id", "age", "id", "name", "user_id", "id", "total", "total"]
+        );
+        assert_eq!(names.literals, 3);
+
+        let (_, statement) = Statement::parse(RawSpan::new(
+            "INSERT INTO t (a, b) VALUES (1, DEFAULT) ON CONFLICT (a) DO UPDATE SET b = 2",
+        ))
+        .unwrap();
+        let mut names = Names::default();
+        names.visit_statement(&statement);
+        assert_eq!(
+            (names.tables, names.columns),
+            (vec!["t".into()], vec!["a".into(), "b".into()])
+        );
+        assert_eq!(names.literals, 2);
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let (_, mut statement) = Statement::parse(RawSpan::new(
+            "SELECT id FROM users WHERE name = 'a' AND age IN (1, 2 + 3)",
+        ))
+        .unwrap();
+        let mut parameterize = Parameterize(vec![]);
+        parameterize.visit_statement_mut(&mut statement);
+        assert_eq!(
+            parameterize.0,
+            [
+                Value::VarChar("a".into()),
+                Value::I64(1),
+                Value::I64(2),
+                Value::I64(3)
+            ]
+        );
+        let mut names = Names::default();
+        names.visit_statement(&statement);
+        assert_eq!(names.literals, 0);
+    }
+}
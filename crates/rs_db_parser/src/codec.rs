@@ -0,0 +1,390 @@
+use crate::{
+    ast::commands::create::SqlType,
+    date::{Date, Interval, Time, Timestamp},
+    decimal::Decimal,
+    errors::DecodeError,
+    value::{f64_bits, Value},
+};
+
+/// The version of the binary format of values and rows, the first byte of every row.
+///
+/// A row is written as:
+///
+/// 1. The format version, one byte.
+/// 2. A null bitmap of `ceil(columns / 8)` bytes, the bit `i % 8` of byte `i / 8` is set
+///    when column `i` is `NULL`.
+/// 3. The value of each column that is not `NULL`, in column order, as [`Value::encode`]
+///    writes it.
+///
+/// A value is written without its type, which is given back to [`Value::decode`]. All
+/// numbers are little-endian:
+///
+/// | Type | Bytes |
+/// |------|-------|
+/// | `BOOL` | one byte, `0` or `1` |
+/// | integers | fixed width, 1 to 16 bytes |
+/// | `FLOAT32`, `FLOAT64` | the IEEE 754 bits, 4 or 8 bytes |
+/// | `DECIMAL` | the mantissa as 16 bytes, then the scale as one byte |
+/// | `DATE` | the days since 1970-01-01, 4 bytes |
+/// | `TIME` | the microseconds since midnight, 8 bytes |
+/// | `TIMESTAMP`, `TIMESTAMPTZ` | the microseconds since 1970-01-01 00:00 UTC, 8 bytes |
+/// | `INTERVAL` | months and days, 4 bytes each, then microseconds, 8 bytes |
+/// | `VARCHAR`, `CHAR`, `JSON`, `ENUM` | the length as 4 bytes, then the UTF-8 text, the label for an enum |
+/// | `BLOB` | the length as 4 bytes, then the bytes |
+/// | arrays | the element count as 4 bytes, a null bitmap of the elements, then the elements that are not `NULL` |
+pub const ROW_FORMAT_VERSION: u8 = 1;
+
+impl Value {
+    /// Appends the value to `out` in the format described at [`ROW_FORMAT_VERSION`].
+    /// `NULL`, which the null bitmap of the row records, writes nothing, and so does a
+    /// parameter placeholder, which is not a stored value.
+    ///
+    /// The value is written as its own type, cast it to the type of its column first.
+    ///
+    /// # Panics
+    /// Panics if a text, blob or array is longer than `u32::MAX`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Null | Self::Parameter(_) => {}
+            Self::Bool(v) => out.push(u8::from(*v)),
+            Self::I8(v) => out.extend(v.to_le_bytes()),
+            Self::I16(v) => out.extend(v.to_le_bytes()),
+            Self::I32(v) => out.extend(v.to_le_bytes()),
+            Self::I64(v) => out.extend(v.to_le_bytes()),
+            Self::I128(v) => out.extend(v.to_le_bytes()),
+            Self::U8(v) => out.push(*v),
+            Self::U16(v) => out.extend(v.to_le_bytes()),
+            Self::U32(v) => out.extend(v.to_le_bytes()),
+            Self::U64(v) => out.extend(v.to_le_bytes()),
+            Self::U128(v) => out.extend(v.to_le_bytes()),
+            Self::F32(v) => out.extend(v.to_le_bytes()),
+            Self::F64(v) => out.extend(v.to_le_bytes()),
+            Self::Decimal(v) => {
+                out.extend(v.mantissa().to_le_bytes());
+                out.push(v.scale());
+            }
+            Self::Date(v) => out.extend(v.days().to_le_bytes()),
+            Self::Time(v) => out.extend(v.micros().to_le_bytes()),
+            Self::Timestamp(v) | Self::TimestampTz(v) => out.extend(v.micros().to_le_bytes()),
+            Self::Interval(v) => {
+                out.extend(v.months().to_le_bytes());
+                out.extend(v.days().to_le_bytes());
+                out.extend(v.micros().to_le_bytes());
+            }
+            Self::VarChar(s) | Self::Char(s) => encode_bytes(s.as_bytes(), out),
+            Self::Json(v) => encode_bytes(v.to_string().as_bytes(), out),
+            Self::Blob(v) => encode_bytes(v, out),
+            Self::Array(items) => {
+                out.extend(length(items.len()).to_le_bytes());
+                encode_values(items, out);
+            }
+        }
+    }
+
+    /// Appends bytes that are the same for the values [`Value::compare`] finds equal, like
+    /// `1` as an `I32` and `1.0` as an `F64`, the way [`Value::hash_comparable`] hashes them.
+    /// Unlike a [`Hash`](std::hash::Hash), the bytes don't change between versions of Rust
+    /// or platforms, so what is stored by them, like the partition of a row, stays where
+    /// it is found.
+    ///
+    /// The first byte is the kind of the value: the strings write their text without its
+    /// trailing spaces, the intervals their length in microseconds, 16 bytes, the dates and
+    /// timestamps their timestamp, the numbers the bits of their `f64`, 8 bytes, and the
+    /// others their [`Value::encode`] bytes. An array writes its length, 8 bytes, then its
+    /// elements.
+    pub fn encode_comparable(&self, out: &mut Vec<u8>) {
+        out.push(self.sort_rank().0);
+        match self {
+            Self::VarChar(s) | Self::Char(s) => {
+                encode_bytes(s.trim_end_matches(' ').as_bytes(), out)
+            }
+            Self::Array(values) => {
+                out.extend((values.len() as u64).to_le_bytes());
+                for value in values.iter() {
+                    value.encode_comparable(out);
+                }
+            }
+            Self::Interval(v) => out.extend(v.length_micros().to_le_bytes()),
+            _ if self.is_temporal() => match self.as_timestamp() {
+                Some(timestamp) => Self::Timestamp(timestamp).encode(out),
+                None => self.encode(out),
+            },
+            _ => match self.as_f64() {
+                Some(v) => out.extend(f64_bits(v).to_le_bytes()),
+                None => self.encode(out),
+            },
+        }
+    }
+
+    /// Reads a value of type `tp` written by [`Value::encode`] from the start of `bytes`,
+    /// returning it with the bytes after it.
+    /// # Errors
+    /// Returns an error if `bytes` end before the value does, or don't hold a valid value
+    /// of the type.
+    pub fn decode<'b>(tp: &SqlType, bytes: &'b [u8]) -> Result<(Self, &'b [u8]), DecodeError> {
+        let mut reader = Reader(bytes);
+        let value = reader.value(tp)?;
+        Ok((value, reader.0))
+    }
+}
+
+/// Appends a row of `values` to `out`, in the format described at [`ROW_FORMAT_VERSION`].
+///
+/// # Panics
+/// Panics if a text, blob or array is longer than `u32::MAX`.
+pub fn encode_row(values: &[Value], out: &mut Vec<u8>) {
+    out.push(ROW_FORMAT_VERSION);
+    encode_values(values, out);
+}
+
+/// Reads a row written by [`encode_row`], with a value of each of `types`.
+/// # Errors
+/// Returns an error if the row has another version, is shorter or longer than the values
+/// of `types`, or holds an invalid value.
+pub fn decode_row(types: &[SqlType], bytes: &[u8]) -> Result<Vec<Value>, DecodeError> {
+    let mut reader = Reader(bytes);
+    let [version] = reader.array()?;
+    if version != ROW_FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    let values = reader.values(types.len(), |_| types.iter())?;
+    match reader.0.len() {
+        0 => Ok(values),
+        len => Err(DecodeError::TrailingBytes(len)),
+    }
+}
+
+/// Writes the null bitmap of `values`, then the values that are not `NULL`.
+fn encode_values(values: &[Value], out: &mut Vec<u8>) {
+    let start = out.len();
+    out.resize(start + values.len().div_ceil(8), 0);
+    for (index, value) in values.iter().enumerate() {
+        if matches!(value, Value::Null | Value::Parameter(_)) {
+            out[start + index / 8] |= 1 << (index % 8);
+        }
+    }
+    for value in values {
+        value.encode(out);
+    }
+}
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend(length(bytes.len()).to_le_bytes());
+    out.extend(bytes);
+}
+
+#[allow(clippy::expect_used)]
+fn length(len: usize) -> u32 {
+    u32::try_from(len).expect("values are at most u32::MAX bytes or elements long")
+}
+
+/// The bytes left to decode.
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8], DecodeError> {
+        if self.0.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    /// A length-prefixed run of bytes.
+    fn bytes(&mut self) -> Result<&'b [u8], DecodeError> {
+        let len = u32::from_le_bytes(self.array()?);
+        self.take(len as usize)
+    }
+
+    fn text(&mut self, tp: &SqlType) -> Result<&'b str, DecodeError> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| DecodeError::InvalidValue(tp.clone()))
+    }
+
+    /// A null bitmap of `count` values, then the values that are not `NULL`, the types
+    /// are given by `types` once the count is known.
+    fn values<'t>(
+        &mut self,
+        count: usize,
+        types: impl FnOnce(usize) -> std::slice::Iter<'t, SqlType>,
+    ) -> Result<Vec<Value>, DecodeError> {
+        let bitmap = self.take(count.div_ceil(8))?;
+        types(count)
+            .enumerate()
+            .map(|(index, tp)| {
+                if bitmap[index / 8] & (1 << (index % 8)) != 0 {
+                    Ok(Value::Null)
+                } else {
+                    self.value(tp)
+                }
+            })
+            .collect()
+    }
+
+    fn value(&mut self, tp: &SqlType) -> Result<Value, DecodeError> {
+        let invalid = || DecodeError::InvalidValue(tp.clone());
+        let value = match tp {
+            SqlType::Bool => match self.array()? {
+                [0] => Value::Bool(false),
+                [1] => Value::Bool(true),
+                _ => return Err(invalid()),
+            },
+            SqlType::I8 => Value::I8(i8::from_le_bytes(self.array()?)),
+            SqlType::I16 => Value::I16(i16::from_le_bytes(self.array()?)),
+            SqlType::I32 => Value::I32(i32::from_le_bytes(self.array()?)),
+            SqlType::I64 => Value::I64(i64::from_le_bytes(self.array()?)),
+            SqlType::I128 => Value::I128(i128::from_le_bytes(self.array()?)),
+            SqlType::U8 => Value::U8(u8::from_le_bytes(self.array()?)),
+            SqlType::U16 => Value::U16(u16::from_le_bytes(self.array()?)),
+            SqlType::U32 => Value::U32(u32::from_le_bytes(self.array()?)),
+            SqlType::U64 => Value::U64(u64::from_le_bytes(self.array()?)),
+            SqlType::U128 => Value::U128(u128::from_le_bytes(self.array()?)),
+            SqlType::F32 => Value::F32(f32::from_le_bytes(self.array()?)),
+            SqlType::F64 => Value::F64(f64::from_le_bytes(self.array()?)),
+            SqlType::Decimal { .. } => {
+                let mantissa = i128::from_le_bytes(self.array()?);
+                let [scale] = self.array()?;
+                Value::Decimal(Decimal::new(mantissa, scale).ok_or_else(invalid)?)
+            }
+            SqlType::Date => {
+                let days = i32::from_le_bytes(self.array()?);
+                Value::Date(Date::from_days(days).ok_or_else(invalid)?)
+            }
+            SqlType::Time => {
+                let micros = i64::from_le_bytes(self.array()?);
+                Value::Time(Time::from_micros(micros).ok_or_else(invalid)?)
+            }
+            SqlType::Timestamp | SqlType::TimestampTz => {
+                let micros = i64::from_le_bytes(self.array()?);
+                let timestamp = Timestamp::from_micros(micros).ok_or_else(invalid)?;
+                if *tp == SqlType::Timestamp {
+                    Value::Timestamp(timestamp)
+                } else {
+                    Value::TimestampTz(timestamp)
+                }
+            }
+            SqlType::Interval => {
+                let months = i32::from_le_bytes(self.array()?);
+                let days = i32::from_le_bytes(self.array()?);
+                let micros = i64::from_le_bytes(self.array()?);
+                Value::Interval(Interval::new(months, days, micros))
+            }
+            SqlType::VarChar(..) => Value::VarChar(self.text(tp)?.into()),
+            SqlType::Char(_) => Value::Char(self.text(tp)?.into()),
+            SqlType::Enum(labels) => {
+                let label = self.text(tp)?;
+                if !labels.iter().any(|l| **l == *label) {
+                    return Err(invalid());
+                }
+                Value::VarChar(label.into())
+            }
+            SqlType::Json => {
+                Value::Json(serde_json::from_str(self.text(tp)?).map_err(|_| invalid())?)
+            }
+            SqlType::Blob => Value::Blob(self.bytes()?.into()),
+            SqlType::Array(element) => {
+                let count = u32::from_le_bytes(self.array()?) as usize;
+                let types = vec![(**element).clone(); count];
+                Value::Array(self.values(count, |_| types.iter())?.into())
+            }
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::ast::commands::create::LengthUnit;
+
+    use super::*;
+
+    #[test]
+    fn test_row_round_trip() {
+        let row = [
+            (SqlType::Bool, Value::Bool(true)),
+            (SqlType::I16, Value::I16(-2)),
+            (SqlType::U64, Value::U64(u64::MAX)),
+            (SqlType::I128, Value::Null),
+            (SqlType::F64, Value::F64(1.5)),
+            (
+                SqlType::Decimal {
+                    precision: 10,
+                    scale: 2,
+                },
+                Value::Decimal(Decimal::new(-12345, 2).unwrap()),
+            ),
+            (
+                SqlType::VarChar(10, LengthUnit::Chars),
+                Value::VarChar("héllo".into()),
+            ),
+            (SqlType::Char(3), Value::Char("ab ".into())),
+            (SqlType::Date, Value::Date("2024-02-29".parse().unwrap())),
+            (
+                SqlType::TimestampTz,
+                Value::TimestampTz(Timestamp::from_micros(1_700_000_000_000_000).unwrap()),
+            ),
+            (SqlType::Interval, Value::Interval(Interval::new(1, -2, 3))),
+            (SqlType::Blob, Value::Blob([0xde, 0xad].into())),
+            (
+                SqlType::Json,
+                Value::Json(serde_json::json!({"a": [1, null]})),
+            ),
+            (
+                SqlType::Enum(["on".into(), "off".into()].into()),
+                Value::VarChar("off".into()),
+            ),
+            (
+                SqlType::Array(Box::new(SqlType::I32)),
+                Value::Array([Value::I32(1), Value::Null, Value::I32(3)].into()),
+            ),
+        ];
+        let (types, values): (Vec<_>, Vec<_>) = row.into_iter().unzip();
+        let mut bytes = Vec::new();
+        encode_row(&values, &mut bytes);
+        assert_eq!(bytes[..3], [ROW_FORMAT_VERSION, 0b0000_1000, 0]);
+        assert_eq!(decode_row(&types, &bytes).unwrap(), values);
+
+        let mut bytes = Vec::new();
+        Value::VarChar("ab".into()).encode(&mut bytes);
+        assert_eq!(bytes, [2, 0, 0, 0, b'a', b'b']);
+        bytes.push(7);
+        assert_eq!(
+            Value::decode(&SqlType::VarChar(2, LengthUnit::Chars), &bytes).unwrap(),
+            (Value::VarChar("ab".into()), &[7][..])
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let types = [SqlType::I32, SqlType::Bool];
+        let mut bytes = Vec::new();
+        encode_row(&[Value::I32(7), Value::Bool(false)], &mut bytes);
+        assert_eq!(
+            decode_row(&types, &bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        assert_eq!(
+            decode_row(&types, &[bytes.as_slice(), &[0]].concat()),
+            Err(DecodeError::TrailingBytes(1))
+        );
+        assert_eq!(
+            decode_row(&types, &[&[2], &bytes[1..]].concat()),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            decode_row(&types, &[&bytes[..bytes.len() - 1], &[2]].concat()),
+            Err(DecodeError::InvalidValue(SqlType::Bool))
+        );
+        assert_eq!(
+            Value::decode(&SqlType::Blob, &[5, 0, 0, 0, 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+}
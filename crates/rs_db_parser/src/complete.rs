@@ -0,0 +1,518 @@
+use crate::{
+    lexer::{tokenize, Token, TokenKind},
+    parse::TableMap,
+};
+
+/// What a [`Completion`] inserts.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionKind {
+    Column,
+    Table,
+    Keyword,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    /// The text to insert, keywords are uppercase.
+    pub text: Box<str>,
+}
+
+/// The candidates for the word at a cursor, see [`complete`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Completions {
+    /// The byte offset where the word being typed starts, a candidate replaces the input
+    /// from there up to the cursor.
+    pub start: usize,
+    /// Columns first, then tables and keywords, each sorted by text.
+    pub candidates: Vec<Completion>,
+}
+
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "ALTER",
+    "ANALYZE",
+    "BACKUP",
+    "BEGIN",
+    "COMMIT",
+    "CREATE",
+    "DESCRIBE",
+    "DROP",
+    "EXPLAIN",
+    "INSERT",
+    "ROLLBACK",
+    "SAVEPOINT",
+    "SELECT",
+    "SET",
+    "SHOW",
+    "VACUUM",
+    "WITH",
+];
+
+/// The keywords that can start an expression, besides a column.
+const EXPRESSION_KEYWORDS: &[&str] = &["CASE", "CAST", "FALSE", "NOT", "NULL", "TRUE"];
+
+/// The keywords that can follow a table of the `FROM` clause.
+const AFTER_TABLE_KEYWORDS: &[&str] = &[
+    "AS", "CROSS", "FULL", "GROUP", "INNER", "JOIN", "LEFT", "LIMIT", "ON", "ORDER", "RIGHT",
+    "USING", "WHERE",
+];
+
+/// The keywords that can follow a condition of `WHERE`, `ON` or `HAVING`.
+const AFTER_CONDITION_KEYWORDS: &[&str] = &[
+    "AND", "BETWEEN", "GROUP", "IN", "IS", "LIKE", "LIMIT", "NOT", "OR", "ORDER",
+];
+
+/// Lists the keywords, tables and columns that can be written at the byte offset `cursor`
+/// of `input`, starting with the word the cursor is in or right after, in any case.
+///
+/// The input is read as tokens rather than parsed, so the statement being typed, which is
+/// rarely valid, still gets candidates. Tables come from the default schema of
+/// `table_map`, or the schema before a `.`, and columns from the tables the statement
+/// names after `FROM`, `JOIN` and `INTO`, anywhere in the statement. Inside string
+/// literals, quoted names and comments there are no candidates.
+#[must_use]
+pub fn complete(table_map: &TableMap, input: &str, cursor: usize) -> Completions {
+    let cursor = (0..=cursor.min(input.len()))
+        .rev()
+        .find(|cursor| input.is_char_boundary(*cursor))
+        .unwrap_or(0);
+
+    // The tokens of the statement the cursor is in.
+    let mut statement = Vec::new();
+    for token in tokenize(input) {
+        if is_punctuation(&token, ";") {
+            if token.span.location_offset() >= cursor {
+                break;
+            }
+            statement.clear();
+        } else {
+            statement.push(token);
+        }
+    }
+
+    let mut completions = Completions {
+        start: cursor,
+        candidates: Vec::new(),
+    };
+    if let Some(token) = statement.iter().find(|token| contains(token, cursor)) {
+        match token.kind {
+            TokenKind::Identifier | TokenKind::Keyword => {
+                completions.start = token.span.location_offset();
+            }
+            TokenKind::Whitespace
+            | TokenKind::Number
+            | TokenKind::Parameter
+            | TokenKind::Operator
+            | TokenKind::Punctuation => {}
+            TokenKind::Comment
+            | TokenKind::QuotedIdentifier
+            | TokenKind::String
+            | TokenKind::Blob
+            | TokenKind::Unknown => return completions,
+        }
+    }
+    let prefix = &input[completions.start..cursor];
+    statement.retain(|token| !token.is_trivia());
+    let before = statement
+        .iter()
+        .take_while(|token| {
+            token.span.location_offset() + token.span.fragment().len() <= completions.start
+        })
+        .count();
+    let context = Context {
+        table_map,
+        before: &statement[..before],
+        tables: tables_in(&statement),
+    };
+    context.candidates(&mut completions.candidates);
+
+    completions
+        .candidates
+        .retain(|candidate| starts_with_ignore_case(&candidate.text, prefix));
+    completions.candidates.sort();
+    completions.candidates.dedup();
+    completions
+}
+
+/// Whether the cursor is inside the token, or at its end.
+fn contains(token: &Token<'_>, cursor: usize) -> bool {
+    let start = token.span.location_offset();
+    start < cursor && cursor <= start + token.span.fragment().len()
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// A table named by the statement, with the name it is referenced by.
+struct TableRef {
+    schema: Option<String>,
+    name: String,
+    alias: Option<String>,
+}
+
+/// The name a token stands for, without the quotes of a quoted name.
+fn name(token: &Token<'_>) -> Option<String> {
+    let text = token.span.fragment();
+    match token.kind {
+        TokenKind::Identifier => Some((*text).to_owned()),
+        TokenKind::QuotedIdentifier => Some(text[1..text.len() - 1].to_owned()),
+        _ => None,
+    }
+}
+
+fn is_punctuation(token: &Token<'_>, punctuation: &str) -> bool {
+    token.kind == TokenKind::Punctuation && *token.span.fragment() == punctuation
+}
+
+/// The tables after `FROM`, `JOIN` and `INTO`, with their aliases.
+fn tables_in(statement: &[Token<'_>]) -> Vec<TableRef> {
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < statement.len() {
+        let token = &statement[i];
+        i += 1;
+        if !["from", "join", "into"]
+            .iter()
+            .any(|keyword| token.is_keyword(keyword))
+        {
+            continue;
+        }
+        while let Some(mut table_name) = statement.get(i).and_then(name) {
+            i += 1;
+            let mut schema = None;
+            if statement.get(i).is_some_and(|t| is_punctuation(t, ".")) {
+                if let Some(table) = statement.get(i + 1).and_then(name) {
+                    schema = Some(table_name);
+                    table_name = table;
+                    i += 2;
+                }
+            }
+            if statement.get(i).is_some_and(|t| t.is_keyword("as")) {
+                i += 1;
+            }
+            let alias = statement.get(i).and_then(name);
+            if alias.is_some() {
+                i += 1;
+            }
+            tables.push(TableRef {
+                schema,
+                name: table_name,
+                alias,
+            });
+            if !(token.is_keyword("from")
+                && statement.get(i).is_some_and(|t| is_punctuation(t, ",")))
+            {
+                break;
+            }
+            i += 1;
+        }
+    }
+    tables
+}
+
+struct Context<'c, 't> {
+    table_map: &'c TableMap,
+    /// The tokens of the statement before the word being typed, without trivia.
+    before: &'c [Token<'t>],
+    tables: Vec<TableRef>,
+}
+
+impl Context<'_, '_> {
+    /// The clause the cursor is in, the last clause keyword before it, lowercase.
+    fn clause(&self) -> Option<String> {
+        const CLAUSES: &[&str] = &[
+            "select",
+            "from",
+            "join",
+            "on",
+            "using",
+            "where",
+            "group",
+            "having",
+            "order",
+            "limit",
+            "offset",
+            "into",
+            "values",
+            "set",
+            "returning",
+            "table",
+        ];
+        self.before.iter().rev().find_map(|token| {
+            let word = token.span.fragment().to_ascii_lowercase();
+            CLAUSES.contains(&word.as_str()).then_some(word)
+        })
+    }
+
+    fn first_word(&self) -> Option<String> {
+        self.before
+            .first()
+            .map(|token| token.span.fragment().to_ascii_lowercase())
+    }
+
+    fn candidates(&self, candidates: &mut Vec<Completion>) {
+        let keywords = |candidates: &mut Vec<Completion>, keywords: &[&str]| {
+            candidates.extend(keywords.iter().map(|keyword| Completion {
+                kind: CompletionKind::Keyword,
+                text: (*keyword).into(),
+            }));
+        };
+        let Some(previous) = self.before.last() else {
+            return keywords(candidates, STATEMENT_KEYWORDS);
+        };
+        let clause = self.clause();
+        let clause = clause.as_deref();
+
+        if is_punctuation(previous, ".") {
+            let Some(qualifier) = self.before.iter().rev().nth(1).and_then(name) else {
+                return;
+            };
+            if matches!(clause, Some("from" | "join" | "into" | "table"))
+                && self.table_map.contains_schema(&qualifier)
+            {
+                self.tables(candidates, Some(&qualifier));
+            } else {
+                self.columns(candidates, Some(&qualifier));
+            }
+            return;
+        }
+
+        let word = previous.span.fragment().to_ascii_lowercase();
+        let is_word = matches!(previous.kind, TokenKind::Keyword | TokenKind::Identifier);
+        match word.as_str() {
+            "explain" if is_word => {
+                return keywords(
+                    candidates,
+                    &["ANALYZE", "FORMAT", "INSERT", "SELECT", "WITH"],
+                )
+            }
+            "analyze" if is_word && self.first_word().as_deref() == Some("explain") => {
+                return keywords(candidates, &["FORMAT", "INSERT", "SELECT", "WITH"])
+            }
+            "format" if is_word => return keywords(candidates, &["JSON", "TEXT"]),
+            "backup" if is_word => return keywords(candidates, &["TO"]),
+            "json" | "text" if is_word => {
+                return keywords(candidates, &["INSERT", "SELECT", "WITH"])
+            }
+            "from" | "join" | "into" | "references" | "describe" | "analyze" | "vacuum"
+                if is_word =>
+            {
+                return self.tables(candidates, None)
+            }
+            "table" | "exists" if is_word => {
+                if self.first_word().as_deref() != Some("create") {
+                    self.tables(candidates, None);
+                }
+                return;
+            }
+            "create" if is_word => {
+                return keywords(candidates, &["SCHEMA", "TABLE", "TYPE", "VIEW"])
+            }
+            "drop" | "alter" if is_word => return keywords(candidates, &["TABLE"]),
+            "show" if is_word => return keywords(candidates, &["COLUMNS", "TABLES"]),
+            "columns" if is_word => return keywords(candidates, &["FROM"]),
+            "insert" if is_word => return keywords(candidates, &["INTO"]),
+            "group" | "order" if is_word => return keywords(candidates, &["BY"]),
+            _ => {}
+        }
+
+        let starts_expression = match previous.kind {
+            TokenKind::Keyword => [
+                "select",
+                "where",
+                "on",
+                "by",
+                "having",
+                "and",
+                "or",
+                "not",
+                "when",
+                "then",
+                "else",
+                "distinct",
+                "set",
+                "returning",
+                "between",
+                "case",
+            ]
+            .contains(&word.as_str()),
+            TokenKind::Operator => true,
+            TokenKind::Punctuation => ["(", ",", "["].contains(&word.as_str()),
+            _ => false,
+        };
+        if starts_expression {
+            match clause {
+                Some("from") if word == "," => self.tables(candidates, None),
+                Some("into") => {
+                    if let Some(table) = self.tables.last() {
+                        self.table_columns(candidates, table);
+                    }
+                }
+                Some("values") => {
+                    keywords(candidates, EXPRESSION_KEYWORDS);
+                    keywords(candidates, &["DEFAULT"]);
+                }
+                _ => {
+                    self.columns(candidates, None);
+                    keywords(candidates, EXPRESSION_KEYWORDS);
+                    if word == "select" {
+                        keywords(candidates, &["DISTINCT"]);
+                    }
+                }
+            }
+            return;
+        }
+
+        // The cursor follows a name, a value or a closing parenthesis.
+        match clause {
+            Some("select") => keywords(candidates, &["AS", "FROM"]),
+            Some("from" | "join") => keywords(candidates, AFTER_TABLE_KEYWORDS),
+            Some("on") => {
+                keywords(candidates, AFTER_CONDITION_KEYWORDS);
+                keywords(candidates, AFTER_TABLE_KEYWORDS);
+            }
+            Some("where") => keywords(candidates, AFTER_CONDITION_KEYWORDS),
+            Some("having") => keywords(candidates, &["AND", "LIMIT", "OR", "ORDER"]),
+            Some("group") => keywords(candidates, &["HAVING", "LIMIT", "ORDER"]),
+            Some("order") => keywords(candidates, &["ASC", "DESC", "LIMIT", "NULLS", "OFFSET"]),
+            Some("limit") => keywords(candidates, &["OFFSET"]),
+            Some("into") => keywords(candidates, &["SELECT", "VALUES"]),
+            _ => {}
+        }
+    }
+
+    fn tables(&self, candidates: &mut Vec<Completion>, schema: Option<&str>) {
+        let schema = schema.unwrap_or(self.table_map.default_schema());
+        let tables = self
+            .table_map
+            .schema(schema)
+            .into_iter()
+            .flat_map(|tables| {
+                tables.keys().map(|table| Completion {
+                    kind: CompletionKind::Table,
+                    text: table.clone(),
+                })
+            });
+        candidates.extend(tables);
+    }
+
+    /// The columns of the tables of the statement, or of the table `qualifier` names.
+    fn columns(&self, candidates: &mut Vec<Completion>, qualifier: Option<&str>) {
+        for table in &self.tables {
+            let name = table.alias.as_ref().unwrap_or(&table.name);
+            if qualifier.is_none_or(|qualifier| qualifier == name || qualifier == table.name) {
+                self.table_columns(candidates, table);
+            }
+        }
+    }
+
+    fn table_columns(&self, candidates: &mut Vec<Completion>, table: &TableRef) {
+        let Some(columns) = self
+            .table_map
+            .get_qualified(table.schema.as_deref(), &table.name)
+        else {
+            return;
+        };
+        candidates.extend(columns.keys().map(|column| Completion {
+            kind: CompletionKind::Column,
+            text: column.clone(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::commands::create::{Column, SqlType};
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        for (table, columns) in [("users", ["id", "name"]), ("orders", ["id", "total"])] {
+            table_map.insert(
+                table.into(),
+                columns
+                    .into_iter()
+                    .map(|column| (column.into(), Column::new(column, SqlType::I32)))
+                    .collect(),
+            );
+        }
+        table_map.insert_qualified(Some("archive"), "logs", [].into());
+        table_map
+    }
+
+    /// The candidates at the `|` of `input`.
+    fn candidates(input: &str) -> Vec<String> {
+        let cursor = input.find('|').unwrap_or(input.len());
+        let input = input.replacen('|', "", 1);
+        complete(&get_table_map(), &input, cursor)
+            .candidates
+            .into_iter()
+            .map(|candidate| candidate.text.into())
+            .collect()
+    }
+
+    #[test]
+    fn test_complete() {
+        let cases: &[(&str, &[&str])] = &[
+            ("|", STATEMENT_KEYWORDS),
+            ("sel|", &["SELECT"]),
+            ("SELECT 1; dr|", &["DROP"]),
+            ("SELECT * FROM |", &["orders", "users"]),
+            ("SELECT * FROM us|", &["users"]),
+            ("SELECT * FROM archive.|", &["logs"]),
+            (
+                "SELECT | FROM users",
+                &[
+                    "id", "name", "CASE", "CAST", "DISTINCT", "FALSE", "NOT", "NULL", "TRUE",
+                ],
+            ),
+            ("SELECT n| FROM users", &["name", "NOT", "NULL"]),
+            (
+                "SELECT u.| FROM users u JOIN orders o ON o.id = u.id",
+                &["id", "name"],
+            ),
+            (
+                "SELECT * FROM users u JOIN orders o ON o.id = u.id WHERE o.t|",
+                &["total"],
+            ),
+            (
+                "SELECT * FROM users WHERE id = 1 |",
+                AFTER_CONDITION_KEYWORDS,
+            ),
+            ("SELECT * FROM users |", AFTER_TABLE_KEYWORDS),
+            ("SELECT * FROM users GROUP |", &["BY"]),
+            ("INSERT INTO orders (id, |", &["id", "total"]),
+            ("INSERT |", &["INTO"]),
+            ("EXPLAIN ANALYZE |", &["FORMAT", "INSERT", "SELECT", "WITH"]),
+            ("ANALYZE |", &["orders", "users"]),
+            ("DROP TABLE |", &["orders", "users"]),
+            ("CREATE TABLE |", &[]),
+            ("SELECT 'us|", &[]),
+            ("SELECT * FROM users -- |", &[]),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(candidates(input), *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_complete_start() {
+        let completions = complete(&get_table_map(), "SELECT * FROM us WHERE", 16);
+        assert_eq!(completions.start, 14);
+        assert_eq!(
+            completions.candidates,
+            [Completion {
+                kind: CompletionKind::Table,
+                text: "users".into()
+            }]
+        );
+    }
+}
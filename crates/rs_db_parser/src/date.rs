@@ -0,0 +1,739 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use crate::errors::ParseError;
+
+/// A calendar date of the proleptic Gregorian calendar, stored as the number of days since
+/// 1970-01-01. Years go from 1 to 9999, the range of `YYYY-MM-DD`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct Date(i32);
+
+/// A time of day with microsecond precision, stored as the number of microseconds since
+/// midnight.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct Time(i64);
+
+/// A date and time of day with microsecond precision, stored as the number of microseconds
+/// since 1970-01-01 00:00:00. A timestamp with a time zone is stored in UTC.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct Timestamp(i64);
+
+/// A length of time kept as months, days and microseconds, since months and days don't have
+/// a fixed length: one month after January 31st is the last day of February, and one day
+/// may last 23 hours across a change of daylight saving time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Interval {
+    months: i32,
+    days: i32,
+    micros: i64,
+}
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+
+pub const fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub const fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    /// `None` if the date doesn't exist, like `2023-02-29`, or the year is out of range.
+    #[must_use]
+    pub const fn from_ymd(year: i32, month: u8, day: u8) -> Option<Self> {
+        if year < 1 || year > 9999 || month < 1 || month > 12 {
+            return None;
+        }
+        if day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        // Counts from March so that the leap day ends the year.
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month = month as i32;
+        let day_of_year =
+            (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i32 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Some(Self(era * 146_097 + day_of_era - 719_468))
+    }
+
+    /// `None` if the date is outside the years 1 to 9999.
+    #[must_use]
+    pub const fn from_days(days: i32) -> Option<Self> {
+        let date = Self(days);
+        let (year, _, _) = date.ymd();
+        if year < 1 || year > 9999 {
+            None
+        } else {
+            Some(date)
+        }
+    }
+
+    /// The number of days since 1970-01-01.
+    #[must_use]
+    pub const fn days(&self) -> i32 {
+        self.0
+    }
+
+    /// The year, month and day.
+    #[must_use]
+    pub const fn ymd(&self) -> (i32, u8, u8) {
+        let days = self.0 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        (year, month as u8, day as u8)
+    }
+}
+
+impl Time {
+    pub const MIDNIGHT: Self = Self(0);
+
+    /// `None` if a field is out of range, there are no leap seconds.
+    #[must_use]
+    pub const fn from_hms_micro(hour: u8, minute: u8, second: u8, micro: u32) -> Option<Self> {
+        if hour > 23 || minute > 59 || second > 59 || micro >= MICROS_PER_SECOND as u32 {
+            return None;
+        }
+        let seconds = hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+        Some(Self(seconds * MICROS_PER_SECOND + micro as i64))
+    }
+
+    /// `None` if the time is not within a day.
+    #[must_use]
+    pub const fn from_micros(micros: i64) -> Option<Self> {
+        if micros < 0 || micros >= MICROS_PER_DAY {
+            None
+        } else {
+            Some(Self(micros))
+        }
+    }
+
+    /// The number of microseconds since midnight.
+    #[must_use]
+    pub const fn micros(&self) -> i64 {
+        self.0
+    }
+
+    /// Moves the time by the time part of `interval`, wrapping around midnight. The months
+    /// and days of the interval don't change a time of day.
+    #[must_use]
+    pub const fn wrapping_add(&self, interval: &Interval) -> Self {
+        Self((self.0 + interval.micros % MICROS_PER_DAY).rem_euclid(MICROS_PER_DAY))
+    }
+
+    /// The hour, minute, second and microsecond.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub const fn hms_micro(&self) -> (u8, u8, u8, u32) {
+        let seconds = self.0 / MICROS_PER_SECOND;
+        (
+            (seconds / 3600) as u8,
+            (seconds / 60 % 60) as u8,
+            (seconds % 60) as u8,
+            (self.0 % MICROS_PER_SECOND) as u32,
+        )
+    }
+}
+
+impl Timestamp {
+    #[must_use]
+    pub const fn new(date: Date, time: Time) -> Self {
+        Self(date.0 as i64 * MICROS_PER_DAY + time.0)
+    }
+
+    /// `None` if the date is outside the years 1 to 9999.
+    #[must_use]
+    pub const fn from_micros(micros: i64) -> Option<Self> {
+        match Date::from_days(micros.div_euclid(MICROS_PER_DAY) as i32) {
+            Some(_) => Some(Self(micros)),
+            None => None,
+        }
+    }
+
+    /// The number of microseconds since 1970-01-01 00:00:00.
+    #[must_use]
+    pub const fn micros(&self) -> i64 {
+        self.0
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn date(&self) -> Date {
+        Date(self.0.div_euclid(MICROS_PER_DAY) as i32)
+    }
+
+    #[must_use]
+    pub const fn time(&self) -> Time {
+        Time(self.0.rem_euclid(MICROS_PER_DAY))
+    }
+
+    /// Adds the months, then the days, then the time of `interval`. A day of the month that
+    /// doesn't exist in the new month becomes its last day. `None` if the result is outside
+    /// the years 1 to 9999.
+    #[must_use]
+    pub fn checked_add_interval(&self, interval: &Interval) -> Option<Self> {
+        let (year, month, day) = self.date().ymd();
+        let months = i64::from(year) * 12 + i64::from(month) - 1 + i64::from(interval.months);
+        let year = i32::try_from(months.div_euclid(12)).ok()?;
+        let month = u8::try_from(months.rem_euclid(12) + 1).ok()?;
+        let date = Date::from_ymd(year, month, day.min(days_in_month(year, month)))?;
+        let micros = Self::new(date, self.time())
+            .0
+            .checked_add(i64::from(interval.days).checked_mul(MICROS_PER_DAY)?)?
+            .checked_add(interval.micros)?;
+        Self::from_micros(micros)
+    }
+
+    /// The time from `other` to `self`, as days and microseconds.
+    #[must_use]
+    pub const fn since(&self, other: &Self) -> Interval {
+        Interval::from_micros(self.0 - other.0)
+    }
+
+    /// Reads a timestamp with an optional UTC offset, `+HH`, `+HH:MM`, `-HHMM` or `Z`, and
+    /// converts it to UTC. Without an offset the timestamp is taken as UTC.
+    /// # Errors
+    /// Returns an error if the text is not a valid timestamp or offset.
+    pub fn parse_with_offset(s: &str) -> Result<Self, ParseError> {
+        // The offset follows the time, after the date and its dashes.
+        let (timestamp, offset) = match s
+            .get(10..)
+            .and_then(|time| time.rfind(['+', '-', 'Z', 'z']))
+        {
+            Some(index) => s.split_at(index + 10),
+            None => (s, ""),
+        };
+        let timestamp: Self = timestamp.parse()?;
+        let offset = parse_offset(offset).ok_or(ParseError::InvalidTimestamp)?;
+        Self::from_micros(timestamp.0 - offset * 60 * MICROS_PER_SECOND)
+            .ok_or(ParseError::InvalidTimestamp)
+    }
+}
+
+impl Interval {
+    #[must_use]
+    pub const fn new(months: i32, days: i32, micros: i64) -> Self {
+        Self {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    /// Whole days of `micros` become days, the rest stays as microseconds.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn from_micros(micros: i64) -> Self {
+        // `i64::MAX` microseconds are about 107 million days, within an `i32`.
+        Self::new(0, (micros / MICROS_PER_DAY) as i32, micros % MICROS_PER_DAY)
+    }
+
+    #[must_use]
+    pub const fn months(&self) -> i32 {
+        self.months
+    }
+
+    #[must_use]
+    pub const fn days(&self) -> i32 {
+        self.days
+    }
+
+    #[must_use]
+    pub const fn micros(&self) -> i64 {
+        self.micros
+    }
+
+    #[must_use]
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Self::new(
+            self.months.checked_add(other.months)?,
+            self.days.checked_add(other.days)?,
+            self.micros.checked_add(other.micros)?,
+        ))
+    }
+
+    #[must_use]
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    #[must_use]
+    pub fn checked_neg(&self) -> Option<Self> {
+        Some(Self::new(
+            self.months.checked_neg()?,
+            self.days.checked_neg()?,
+            self.micros.checked_neg()?,
+        ))
+    }
+
+    /// Multiplies each part of the interval by `factor`.
+    #[must_use]
+    pub fn checked_mul(&self, factor: i128) -> Option<Self> {
+        Some(Self::new(
+            i32::try_from(i128::from(self.months).checked_mul(factor)?).ok()?,
+            i32::try_from(i128::from(self.days).checked_mul(factor)?).ok()?,
+            i64::try_from(i128::from(self.micros).checked_mul(factor)?).ok()?,
+        ))
+    }
+
+    /// Compares the lengths of two intervals, counting 30 days in a month and 24 hours in a
+    /// day as SQL does, so `1 day` and `24 hours` are as long.
+    #[must_use]
+    pub fn cmp_length(&self, other: &Self) -> Ordering {
+        self.length_micros().cmp(&other.length_micros())
+    }
+
+    /// The length of the interval in microseconds, see [`Interval::cmp_length`].
+    #[must_use]
+    pub fn length_micros(&self) -> i128 {
+        (i128::from(self.months) * 30 + i128::from(self.days)) * i128::from(MICROS_PER_DAY)
+            + i128::from(self.micros)
+    }
+}
+
+/// Reads the fields of `text` separated by `separator`, each of exactly `len` digits, or
+/// `None` if the text doesn't have `N` such fields.
+fn fixed_fields<const N: usize>(text: &str, separator: char, len: usize) -> Option<[u32; N]> {
+    let mut fields = [0; N];
+    let mut parts = text.split(separator);
+    for field in &mut fields {
+        *field = parts
+            .next()
+            .filter(|part| part.len() == len && part.chars().all(|c| c.is_ascii_digit()))?
+            .parse()
+            .ok()?;
+    }
+    parts.next().is_none().then_some(fields)
+}
+
+/// Reads a UTC offset in minutes, an empty text is UTC.
+fn parse_offset(text: &str) -> Option<i64> {
+    let (sign, digits) = match text.chars().next() {
+        None | Some('Z' | 'z') if text.len() <= 1 => return Some(0),
+        Some('+') => (1, &text[1..]),
+        Some('-') => (-1, &text[1..]),
+        _ => return None,
+    };
+    if !digits.is_ascii() {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits, "00"),
+        4 => digits.split_at(2),
+        5 if digits.as_bytes()[2] == b':' => (&digits[..2], &digits[3..]),
+        _ => return None,
+    };
+    let [hours, minutes] = fixed_fields(&format!("{hours}:{minutes}"), ':', 2)?;
+    (hours <= 15 && minutes <= 59).then(|| sign * i64::from(hours * 60 + minutes))
+}
+
+impl FromStr for Time {
+    type Err = ParseError;
+
+    /// Reads `HH:MM[:SS[.ffffff]]`, with up to six digits of fraction.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (clock, fraction) = s
+            .split_once('.')
+            .map_or((s, None), |(clock, fraction)| (clock, Some(fraction)));
+        let [hour, minute, second] = match (fixed_fields(clock, ':', 2), fraction) {
+            (Some(fields), _) => fields,
+            // The seconds can only be left out without a fraction.
+            (None, None) => {
+                let [hour, minute] = fixed_fields(clock, ':', 2).ok_or(ParseError::InvalidTime)?;
+                [hour, minute, 0]
+            }
+            (None, Some(_)) => return Err(ParseError::InvalidTime),
+        };
+        let micro = match fraction {
+            None => 0,
+            Some(fraction)
+                if (1..=6).contains(&fraction.len())
+                    && fraction.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                format!("{fraction:0<6}")
+                    .parse()
+                    .map_err(|_| ParseError::InvalidTime)?
+            }
+            Some(_) => return Err(ParseError::InvalidTime),
+        };
+        let field = |value: u32| u8::try_from(value).map_err(|_| ParseError::InvalidTime);
+        Self::from_hms_micro(field(hour)?, field(minute)?, field(second)?, micro)
+            .ok_or(ParseError::InvalidTime)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ParseError;
+
+    /// Reads `YYYY-MM-DD[ HH:MM[:SS[.ffffff]]]`, a `T` may separate the date and the time.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, time) = s
+            .split_once([' ', 'T', 't'])
+            .map_or((s, None), |(date, time)| (date, Some(time)));
+        let date = date.parse().map_err(|_| ParseError::InvalidTimestamp)?;
+        let time = time.map_or(Ok(Time::MIDNIGHT), |time| {
+            time.trim_start()
+                .parse()
+                .map_err(|_| ParseError::InvalidTimestamp)
+        })?;
+        Ok(Self::new(date, time))
+    }
+}
+
+impl FromStr for Interval {
+    type Err = ParseError;
+
+    /// Reads quantities followed by their unit, like `1 year 2 months -3 days 4 hours`, and
+    /// an optional `[-]HH:MM[:SS[.ffffff]]` time, the units are years, months, weeks, days,
+    /// hours, minutes, seconds, milliseconds and microseconds, singular or plural.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut interval = Self::new(0, 0, 0);
+        let mut tokens = s.split_whitespace().peekable();
+        if tokens.peek().is_none() {
+            return Err(ParseError::InvalidInterval);
+        }
+        while let Some(token) = tokens.next() {
+            let part = if token.contains(':') {
+                let (negative, clock) = token
+                    .strip_prefix('-')
+                    .map_or((false, token), |clock| (true, clock));
+                let (hours, rest) = clock.split_once(':').ok_or(ParseError::InvalidInterval)?;
+                let hours: i64 = hours
+                    .parse()
+                    .ok()
+                    .filter(|_| hours.chars().all(|c| c.is_ascii_digit()))
+                    .ok_or(ParseError::InvalidInterval)?;
+                // The minutes and seconds read like a time during the first hour.
+                let rest: Time = format!("00:{rest}")
+                    .parse()
+                    .map_err(|_| ParseError::InvalidInterval)?;
+                let micros = hours
+                    .checked_mul(3600 * MICROS_PER_SECOND)
+                    .and_then(|micros| micros.checked_add(rest.0))
+                    .ok_or(ParseError::InvalidInterval)?;
+                Self::new(0, 0, if negative { -micros } else { micros })
+            } else {
+                let quantity: i32 = token.parse().map_err(|_| ParseError::InvalidInterval)?;
+                let unit = tokens
+                    .next()
+                    .ok_or(ParseError::InvalidInterval)?
+                    .to_ascii_lowercase();
+                let unit = unit.strip_suffix('s').unwrap_or(&unit);
+                let (months, days, micros) = match unit {
+                    "year" => (12, 0, 0),
+                    "month" | "mon" => (1, 0, 0),
+                    "week" => (0, 7, 0),
+                    "day" => (0, 1, 0),
+                    "hour" => (0, 0, 3600 * MICROS_PER_SECOND),
+                    "minute" | "min" => (0, 0, 60 * MICROS_PER_SECOND),
+                    "second" | "sec" => (0, 0, MICROS_PER_SECOND),
+                    "millisecond" => (0, 0, 1000),
+                    "microsecond" => (0, 0, 1),
+                    _ => return Err(ParseError::InvalidInterval),
+                };
+                Self::new(months, days, micros)
+                    .checked_mul(i128::from(quantity))
+                    .ok_or(ParseError::InvalidInterval)?
+            };
+            interval = interval
+                .checked_add(&part)
+                .ok_or(ParseError::InvalidInterval)?;
+        }
+        Ok(interval)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ParseError;
+
+    /// Reads `YYYY-MM-DD`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('-');
+        let mut part = |len: usize| {
+            parts
+                .next()
+                .filter(|part| part.len() == len && part.chars().all(|c| c.is_ascii_digit()))
+                .and_then(|part| part.parse().ok())
+        };
+        let (Some(year), Some(month), Some(day)) = (part(4), part(2), part(2)) else {
+            return Err(ParseError::InvalidDate);
+        };
+        if parts.next().is_some() {
+            return Err(ParseError::InvalidDate);
+        }
+        let month = u8::try_from(month).map_err(|_| ParseError::InvalidDate)?;
+        let day = u8::try_from(day).map_err(|_| ParseError::InvalidDate)?;
+        Self::from_ymd(year, month, day).ok_or(ParseError::InvalidDate)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl std::fmt::Display for Time {
+    /// Writes `HH:MM:SS`, followed by the fraction without trailing zeros when there is one.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (hour, minute, second, micro) = self.hms_micro();
+        write!(f, "{hour:02}:{minute:02}:{second:02}")?;
+        if micro > 0 {
+            let fraction = format!("{micro:06}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Interval {
+    /// Writes the years, months and days that aren't zero, then the time when it isn't zero
+    /// or nothing else was written, like `1 year 2 mons 3 days 04:05:06`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: i32| if n.abs() == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        let (years, months) = (self.months / 12, self.months % 12);
+        if years != 0 {
+            parts.push(format!("{years} year{}", plural(years)));
+        }
+        if months != 0 {
+            parts.push(format!("{months} mon{}", plural(months)));
+        }
+        if self.days != 0 {
+            parts.push(format!("{} day{}", self.days, plural(self.days)));
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            let micros = self.micros.unsigned_abs();
+            let hours = micros / 3_600_000_000;
+            let rest = Time((micros % 3_600_000_000).cast_signed());
+            // The time of the rest starts with `00:`, the hours take its place.
+            parts.push(format!("{sign}{hours:02}{}", &rest.to_string()[2..]));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.date(), self.time())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(date("1970-01-01").days(), 0);
+        assert_eq!(date("1969-12-31").days(), -1);
+        assert_eq!(date("2000-03-01").days(), 11_017);
+        for input in [
+            "2024-01-31",
+            "2024-02-29",
+            "2000-02-29",
+            "0001-01-01",
+            "9999-12-31",
+        ] {
+            assert_eq!(date(input).to_string(), input);
+        }
+        for input in [
+            "2023-02-29",
+            "1900-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "0000-01-01",
+            "2024-1-31",
+            "2024-01-31-",
+            "+024-01-31",
+            "2024/01/31",
+            "",
+        ] {
+            assert_eq!(
+                input.parse::<Date>(),
+                Err(ParseError::InvalidDate),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_time() {
+        let time = |s: &str| s.parse::<Time>().unwrap();
+        assert_eq!(time("00:00").micros(), 0);
+        assert_eq!(time("10:20:30.5").hms_micro(), (10, 20, 30, 500_000));
+        for input in [
+            "10:20:30",
+            "23:59:59.999999",
+            "00:00:00.000001",
+            "12:00:00.25",
+        ] {
+            assert_eq!(time(input).to_string(), input);
+        }
+        for input in [
+            "24:00:00",
+            "10:60:00",
+            "10:20:60",
+            "10:20:30.1234567",
+            "10:20:30.",
+            "10:20.5",
+            "1:20:30",
+            "10:20:30:40",
+            "",
+        ] {
+            assert_eq!(
+                input.parse::<Time>(),
+                Err(ParseError::InvalidTime),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp = |s: &str| s.parse::<Timestamp>().unwrap();
+        assert_eq!(timestamp("1970-01-01").micros(), 0);
+        assert_eq!(timestamp("1969-12-31 23:59:59.999999").micros(), -1);
+        assert_eq!(
+            timestamp("2024-01-31T10:20:30.123").to_string(),
+            "2024-01-31 10:20:30.123"
+        );
+        assert_eq!(timestamp("2024-02-29 10:20").date(), date("2024-02-29"));
+        assert!("2023-02-29 10:20:30".parse::<Timestamp>().is_err());
+        assert!("2024-01-31 10:20:30+02".parse::<Timestamp>().is_err());
+
+        let utc = |s: &str| Timestamp::parse_with_offset(s).unwrap().to_string();
+        assert_eq!(utc("2024-01-31 10:20:30.123+02"), "2024-01-31 08:20:30.123");
+        assert_eq!(utc("2024-01-31 23:30:00-05:30"), "2024-02-01 05:00:00");
+        assert_eq!(utc("2024-01-31 10:20:30-0130"), "2024-01-31 11:50:30");
+        assert_eq!(utc("2024-01-31 10:20:30Z"), "2024-01-31 10:20:30");
+        assert_eq!(utc("2024-01-31"), "2024-01-31 00:00:00");
+        assert!(Timestamp::parse_with_offset("2024-01-31 10:20:30+16").is_err());
+        assert!(Timestamp::parse_with_offset("2024-01-31 10:20:30+2").is_err());
+        assert!(Timestamp::parse_with_offset("0001-01-01 00:00:00+01").is_err());
+    }
+
+    #[test]
+    fn test_interval() {
+        let interval = |s: &str| s.parse::<Interval>().unwrap();
+        assert_eq!(
+            interval("1 day 2 hours"),
+            Interval::new(0, 1, 2 * 3600 * MICROS_PER_SECOND)
+        );
+        assert_eq!(interval("1 YEAR -2 Mons 1 week"), Interval::new(10, 7, 0));
+        assert_eq!(
+            interval("-01:30"),
+            Interval::new(0, 0, -90 * 60 * MICROS_PER_SECOND)
+        );
+        for input in [
+            "1 year 2 mons 3 days 04:05:06",
+            "1 day",
+            "-2 days 100:00:00.5",
+            "00:00:00",
+            "-00:00:01",
+        ] {
+            assert_eq!(interval(input).to_string(), input);
+        }
+        assert_eq!(interval("90 minutes").to_string(), "01:30:00");
+        for input in [
+            "",
+            "1",
+            "day",
+            "1 fortnight",
+            "1.5 days",
+            "1:60",
+            "2147483648 days",
+        ] {
+            assert_eq!(
+                input.parse::<Interval>(),
+                Err(ParseError::InvalidInterval),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            interval("1 day").cmp_length(&interval("24 hours")),
+            Ordering::Equal
+        );
+        assert_eq!(
+            interval("1 mon").cmp_length(&interval("31 days")),
+            Ordering::Less
+        );
+
+        let timestamp = |s: &str| s.parse::<Timestamp>().unwrap();
+        let add = |t: &str, i: &str| {
+            timestamp(t)
+                .checked_add_interval(&interval(i))
+                .map(|t| t.to_string())
+        };
+        assert_eq!(
+            add("2024-01-31 10:00", "1 month").as_deref(),
+            Some("2024-02-29 10:00:00")
+        );
+        assert_eq!(
+            add("2024-03-31", "-1 mon 1 day").as_deref(),
+            Some("2024-03-01 00:00:00")
+        );
+        assert_eq!(
+            add("2024-01-01", "-26 hours").as_deref(),
+            Some("2023-12-30 22:00:00")
+        );
+        assert_eq!(add("9999-12-01", "1 mon"), None);
+        assert_eq!(
+            timestamp("2024-01-02").since(&timestamp("2023-12-31 22:00")),
+            interval("1 day 2 hours")
+        );
+        assert_eq!(
+            "23:00"
+                .parse::<Time>()
+                .unwrap()
+                .wrapping_add(&interval("2 hours")),
+            "01:00".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_days() {
+        let first = date("0001-01-01").days();
+        let last = date("9999-12-31").days();
+        let mut previous = Date::from_days(first).unwrap().ymd();
+        for days in first + 1..=last {
+            let (year, month, day) = Date::from_days(days).unwrap().ymd();
+            assert_eq!(Date::from_ymd(year, month, day), Some(Date(days)));
+            assert!((year, month, day) > previous);
+            previous = (year, month, day);
+        }
+        assert!(Date::from_days(first - 1).is_none());
+        assert!(Date::from_days(last + 1).is_none());
+        assert!(is_leap_year(2024));
+        assert!(!is_leap_year(2100));
+    }
+}
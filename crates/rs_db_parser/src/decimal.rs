@@ -0,0 +1,401 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::errors::ParseError;
+
+/// The most digits a [`Decimal`] holds, the most that fit an `i128`.
+pub const MAX_PRECISION: u8 = 38;
+
+/// The smallest scale of a quotient, so `1 / 3` keeps some digits.
+pub const DIVISION_SCALE: u8 = 6;
+
+/// An exact decimal number, `mantissa * 10^-scale`, with at most [`MAX_PRECISION`] digits.
+/// Values are compared by number, `1.50` equals `1.5`.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+/// `10^exponent`, up to `10^38`.
+pub(crate) const fn pow10(exponent: u8) -> i128 {
+    10_i128.pow(exponent as u32)
+}
+
+impl Decimal {
+    /// `None` if the mantissa has more than [`MAX_PRECISION`] digits or the scale is larger
+    /// than it.
+    #[must_use]
+    pub fn new(mantissa: i128, scale: u8) -> Option<Self> {
+        (scale <= MAX_PRECISION && mantissa.unsigned_abs() < pow10(MAX_PRECISION).unsigned_abs())
+            .then_some(Self { mantissa, scale })
+    }
+
+    /// An integer as a decimal without fraction, `None` if it has too many digits.
+    #[must_use]
+    pub fn from_integer(value: i128) -> Option<Self> {
+        Self::new(value, 0)
+    }
+
+    /// Reads a float at `scale` digits after the point, rounding half away from zero like
+    /// [`Decimal::rescale`]. The float is read from its shortest representation, so `0.125`
+    /// is a tie even though its binary value isn't.
+    #[must_use]
+    pub fn from_f64(value: f64, scale: u8) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let mut text = value.to_string();
+        if text
+            .split_once('.')
+            .is_some_and(|(_, fraction)| fraction.len() > usize::from(MAX_PRECISION))
+        {
+            text = format!("{value:.*}", usize::from(MAX_PRECISION));
+        }
+        text.parse::<Self>().ok()?.rescale(scale)
+    }
+
+    #[must_use]
+    pub const fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    #[must_use]
+    pub const fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// The number of digits, at least the scale, so `0.05` has a precision of 2.
+    #[must_use]
+    pub fn precision(&self) -> u8 {
+        let digits = self
+            .mantissa
+            .unsigned_abs()
+            .checked_ilog10()
+            .map_or(1, |log| log + 1);
+        u8::try_from(digits).map_or(MAX_PRECISION, |digits| digits.max(self.scale))
+    }
+
+    /// Changes the number of digits after the point, rounding half away from zero when
+    /// digits are dropped. `None` if the result has too many digits.
+    #[must_use]
+    pub fn rescale(&self, scale: u8) -> Option<Self> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Some(*self),
+            Ordering::Greater => Self::new(
+                self.mantissa
+                    .checked_mul(10_i128.checked_pow(u32::from(scale - self.scale))?)?,
+                scale,
+            ),
+            Ordering::Less => {
+                let divisor = pow10(self.scale - scale);
+                Self::new(divide_rounded(self.mantissa, divisor), scale)
+            }
+        }
+    }
+
+    /// The nearest integer, rounding half away from zero.
+    #[must_use]
+    pub const fn round(&self) -> i128 {
+        divide_rounded(self.mantissa, pow10(self.scale))
+    }
+
+    /// The nearest float.
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Whether the value fits `DECIMAL(precision, scale)` without rounding.
+    #[must_use]
+    pub fn fits(&self, precision: u8, scale: u8) -> bool {
+        self.scale <= scale
+            && self.rescale(scale).is_some_and(|value| {
+                value.mantissa.unsigned_abs() < pow10(precision).unsigned_abs()
+            })
+    }
+
+    /// Both values at the larger scale.
+    fn align(&self, other: &Self) -> Option<(i128, i128, u8)> {
+        let scale = self.scale.max(other.scale);
+        Some((
+            self.rescale(scale)?.mantissa,
+            other.rescale(scale)?.mantissa,
+            scale,
+        ))
+    }
+
+    #[must_use]
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Self::new(a.checked_add(b)?, scale)
+    }
+
+    #[must_use]
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Self::new(a.checked_sub(b)?, scale)
+    }
+
+    /// The product keeps every digit of the fractions, up to [`MAX_PRECISION`] of them.
+    #[must_use]
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let mantissa = self.mantissa.checked_mul(other.mantissa)?;
+        let scale = self.scale + other.scale;
+        if scale > MAX_PRECISION {
+            let divisor = 10_i128.checked_pow(u32::from(scale - MAX_PRECISION))?;
+            return Self::new(divide_rounded(mantissa, divisor), MAX_PRECISION);
+        }
+        Self::new(mantissa, scale)
+    }
+
+    /// The quotient is rounded to the larger scale of the operands, and at least
+    /// [`DIVISION_SCALE`] digits. `None` when dividing by zero.
+    #[must_use]
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.mantissa == 0 {
+            return None;
+        }
+        let scale = self.scale.max(other.scale).max(DIVISION_SCALE);
+        let exponent = scale + other.scale - self.scale;
+        let dividend = self
+            .mantissa
+            .checked_mul(10_i128.checked_pow(u32::from(exponent))?)?;
+        Self::new(divide_rounded(dividend, other.mantissa), scale)
+    }
+
+    /// The remainder has the sign of the dividend. `None` when dividing by zero.
+    #[must_use]
+    pub fn checked_rem(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Self::new(a.checked_rem(b)?, scale)
+    }
+
+    #[must_use]
+    pub fn checked_neg(&self) -> Option<Self> {
+        Self::new(-self.mantissa, self.scale)
+    }
+
+    #[must_use]
+    pub fn abs(&self) -> Self {
+        Self {
+            mantissa: self.mantissa.abs(),
+            scale: self.scale,
+        }
+    }
+
+    /// The same number without trailing zeros after the point.
+    fn normalized(&self) -> Self {
+        let mut value = *self;
+        while value.scale > 0 && value.mantissa % 10 == 0 {
+            value.mantissa /= 10;
+            value.scale -= 1;
+        }
+        value
+    }
+}
+
+/// Divides rounding half away from zero.
+const fn divide_rounded(dividend: i128, divisor: i128) -> i128 {
+    let quotient = dividend / divisor;
+    let remainder = dividend % divisor;
+    if remainder.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+        if (dividend < 0) == (divisor < 0) {
+            quotient + 1
+        } else {
+            quotient - 1
+        }
+    } else {
+        quotient
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    /// Compares the integer parts, then the fractions at the larger scale. Unlike aligning
+    /// the mantissas this can't overflow.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let parts = |value: &Self| {
+            let unit = pow10(value.scale);
+            (value.mantissa / unit, value.mantissa % unit)
+        };
+        let (a_integer, a_fraction) = parts(self);
+        let (b_integer, b_fraction) = parts(other);
+        let scale = self.scale.max(other.scale);
+        a_integer.cmp(&b_integer).then_with(|| {
+            (a_fraction * pow10(scale - self.scale)).cmp(&(b_fraction * pow10(scale - other.scale)))
+        })
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseError;
+
+    /// Reads `[-]digits[.digits]`, the scale is the number of digits after the point.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = s.strip_prefix('-').map_or((false, s), |rest| (true, rest));
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if (integer.is_empty() && fraction.is_empty())
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(ParseError::InvalidDecimal);
+        }
+        let scale = u8::try_from(fraction.len())
+            .ok()
+            .filter(|scale| *scale <= MAX_PRECISION)
+            .ok_or(ParseError::ArithmeticOverflow)?;
+        let digits = format!("{integer}{fraction}");
+        let digits = digits.trim_start_matches('0');
+        if digits.len() > usize::from(MAX_PRECISION) {
+            return Err(ParseError::ArithmeticOverflow);
+        }
+        let mantissa = if digits.is_empty() {
+            0
+        } else {
+            digits.parse().map_err(|_| ParseError::InvalidDecimal)?
+        };
+        Self::new(if negative { -mantissa } else { mantissa }, scale)
+            .ok_or(ParseError::ArithmeticOverflow)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = usize::from(self.scale);
+        if scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{sign}{integer}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for (input, expected) in [
+            ("12.50", "12.50"),
+            ("-0.05", "-0.05"),
+            (".5", "0.5"),
+            ("7.", "7"),
+            ("007", "7"),
+        ] {
+            assert_eq!(decimal(input).to_string(), expected);
+        }
+        assert_eq!(decimal("-0.05").precision(), 2);
+        assert_eq!(decimal("123.4").precision(), 4);
+        assert!("".parse::<Decimal>().is_err());
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert_eq!(
+            "1234567890123456789012345678901234567890".parse::<Decimal>(),
+            Err(ParseError::ArithmeticOverflow)
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(decimal("1.50"), decimal("1.5"));
+        assert!(decimal("-1.5") < decimal("-1.25"));
+        assert!(decimal("-0.5") < decimal("0.3"));
+        assert!(decimal("99999999999999999999999999999999999999") > decimal("0.1"));
+        let hash = |value: Decimal| {
+            use std::collections::hash_map::DefaultHasher;
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(decimal("2.000")), hash(decimal("2")));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(
+            decimal("0.1")
+                .checked_add(&decimal("0.2"))
+                .unwrap()
+                .to_string(),
+            "0.3"
+        );
+        assert_eq!(
+            decimal("19.99")
+                .checked_mul(&decimal("3"))
+                .unwrap()
+                .to_string(),
+            "59.97"
+        );
+        assert_eq!(
+            decimal("1").checked_div(&decimal("3")).unwrap().to_string(),
+            "0.333333"
+        );
+        assert_eq!(
+            decimal("-2")
+                .checked_div(&decimal("3"))
+                .unwrap()
+                .to_string(),
+            "-0.666667"
+        );
+        assert_eq!(
+            decimal("5.5")
+                .checked_rem(&decimal("2"))
+                .unwrap()
+                .to_string(),
+            "1.5"
+        );
+        assert!(decimal("1").checked_div(&decimal("0.0")).is_none());
+        let max = decimal("99999999999999999999999999999999999999");
+        assert!(max.checked_add(&decimal("1")).is_none());
+    }
+
+    #[test]
+    fn test_rescale() {
+        assert_eq!(decimal("2.345").rescale(2).unwrap().to_string(), "2.35");
+        assert_eq!(decimal("-2.345").rescale(2).unwrap().to_string(), "-2.35");
+        assert_eq!(decimal("2.5").round(), 3);
+        assert_eq!(decimal("-2.4").round(), -2);
+        assert!(decimal("123.45").fits(5, 2));
+        assert!(!decimal("123.45").fits(4, 2));
+        assert!(!decimal("1.234").fits(10, 2));
+        assert_eq!(Decimal::from_f64(0.1, 3).unwrap().to_string(), "0.100");
+        assert!(Decimal::from_f64(1e300, 0).is_none());
+        assert_eq!(Decimal::from_f64(-0.125, 2).unwrap().to_string(), "-0.13");
+        assert_eq!(Decimal::from_f64(1e-50, 2).unwrap().to_string(), "0.00");
+    }
+}
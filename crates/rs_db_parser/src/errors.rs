@@ -2,7 +2,9 @@ use nom::IResult;
 use nom_locate::LocatedSpan;
 use nom_supreme::error::{BaseErrorKind, ErrorTree, StackContext};
 
-use crate::parse::RawSpan;
+use crate::{
+    ast::commands::create::SqlType, lexer::Tokens, parse::RawSpan, parsers::limits::Limit,
+};
 
 pub type RawParseError<'a> = ErrorTree<RawSpan<'a>>;
 pub type CustomParseError<T> = ErrorTree<LocatedSpan<T>>;
@@ -19,13 +21,342 @@ pub(crate) fn custom_error<'a, T: 'a>(
     })
 }
 
-#[derive(Debug, thiserror::Error)]
+/// Like [`custom_error`], but the error is not recoverable: enclosing `alt`s and lists
+/// won't backtrack and try something else.
+pub(crate) fn custom_failure<'a, T: 'a>(
+    input: LocatedSpan<T>,
+    kind: BaseErrorKind<&'static str, Box<dyn std::error::Error + Send + Sync + 'static>>,
+) -> nom::Err<CustomParseError<T>> {
+    nom::Err::Failure(CustomParseError::Base {
+        location: input,
+        kind,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum ParseError {
     #[error("Column not found")]
     ColumnNotFound,
 
     #[error("Column declared, but not used")]
     ColumnNotUsed,
+
+    #[error("Column does not accept NULL values")]
+    NullNotAllowed,
+
+    #[error("Column `{0}` does not accept NULL values and must be given a value")]
+    MissingNotNullColumn(Box<str>),
+
+    #[error("Table declares more than one primary key")]
+    MultiplePrimaryKeys,
+
+    #[error("Foreign key column count does not match the referenced columns")]
+    ForeignKeyColumnCount,
+
+    #[error("Decimal precision must be between 1 and 38, and the scale at most the precision")]
+    InvalidDecimalType,
+
+    #[error("Invalid decimal number")]
+    InvalidDecimal,
+
+    #[error("Invalid date, expected an existing day as YYYY-MM-DD")]
+    InvalidDate,
+
+    #[error("Invalid time, expected HH:MM:SS with up to six digits of fraction")]
+    InvalidTime,
+
+    #[error(
+        "Invalid timestamp, expected YYYY-MM-DD HH:MM:SS with an optional fraction and UTC offset"
+    )]
+    InvalidTimestamp,
+
+    #[error("Invalid binary literal, expected pairs of hexadecimal digits")]
+    InvalidBlob,
+
+    #[error("Invalid escape sequence")]
+    InvalidEscape,
+
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(Box<str>),
+
+    #[error("Invalid array, expected elements between braces, like '{{1,2,3}}'")]
+    InvalidArray,
+
+    #[error("Array elements must share a type")]
+    ArrayElementType,
+
+    #[error("Invalid interval, expected quantities with their unit, like '1 day 2 hours'")]
+    InvalidInterval,
+
+    #[error("Only integer columns can be auto-incremented")]
+    AutoIncrementNotInteger,
+
+    #[error("Unexpected {found}, expected {expected}")]
+    UnexpectedToken { found: Box<str>, expected: Box<str> },
+
+    #[error("Table not found")]
+    TableNotFound,
+
+    #[error("Table already exists")]
+    TableAlreadyExists,
+
+    #[error("View query no longer matches the catalog")]
+    InvalidView,
+
+    #[error("Schema not found")]
+    SchemaNotFound,
+
+    #[error("Schema already exists")]
+    SchemaAlreadyExists,
+
+    #[error("Type not found")]
+    TypeNotFound,
+
+    #[error("Type already exists")]
+    TypeAlreadyExists,
+
+    #[error("Enum labels must be unique")]
+    DuplicateEnumLabel,
+
+    #[error("Value is not one of the labels of the enum")]
+    InvalidEnumLabel,
+
+    #[error("Common table expression name used more than once")]
+    DuplicateCteName,
+
+    #[error("Column list does not match the columns of the query")]
+    CteColumnCount,
+
+    #[error("A recursive common table expression must reference itself at most once, in its recursive term")]
+    RecursiveReference,
+
+    #[error("Parameters can't mix `?` and `$n` placeholders")]
+    MixedParameters,
+
+    #[error("Number of values does not match the parameters")]
+    ParameterCount,
+
+    #[error("Conflict target must be the primary key or a unique column")]
+    ConflictTargetNotUnique,
+
+    #[error("ON CONFLICT DO UPDATE requires a conflict target")]
+    ConflictTargetRequired,
+
+    #[error("Value type does not convert implicitly to the column type")]
+    AssignmentType,
+
+    #[error("Table name used more than once, give it an alias")]
+    DuplicateTableName,
+
+    #[error("Column name exists in more than one table, qualify it with the table name")]
+    AmbiguousColumn,
+
+    #[error("Reserved keyword, quote it to use it as a name")]
+    ReservedKeyword,
+
+    #[error("Unknown statement")]
+    UnknownStatement,
+
+    #[error("String literal is not terminated, expected a closing quote")]
+    UnterminatedString,
+
+    #[error("Input exceeds the maximum {limit} of {max}")]
+    LimitExceeded { limit: Limit, max: usize },
+
+    #[error("Arithmetic overflow")]
+    ArithmeticOverflow,
+
+    #[error("Division by zero")]
+    DivisionByZero,
+
+    #[error("Operator cannot be applied to these values")]
+    InvalidOperand,
+
+    #[error("Expected a constant expression")]
+    NotConstant,
+
+    #[error("Comparisons cannot be chained, use parentheses")]
+    ChainedComparison,
+
+    #[error("Type is not compatible with the previous branches")]
+    IncompatibleTypes,
+
+    #[error("Function not found")]
+    UnknownFunction,
+
+    #[error("Wrong number of arguments")]
+    ArgumentCount,
+
+    #[error("Argument type not accepted by the function")]
+    ArgumentType,
+
+    #[error("Only COUNT accepts `*` as argument")]
+    AggregateWildcard,
+
+    #[error("Aggregate functions cannot be nested")]
+    NestedAggregate,
+
+    #[error("Aggregate functions are not allowed here")]
+    AggregateNotAllowed,
+
+    #[error("Column must appear in the GROUP BY clause or be used in an aggregate function")]
+    ColumnNotGrouped,
+
+    #[error("Unknown compression, expected 'none', 'lz4' or 'zstd'")]
+    UnknownCompression,
+
+    #[error("Unknown engine, expected 'disk', 'memory' or 'columnar'")]
+    UnknownEngine,
+
+    #[error("Partition bounds must increase, with MAXVALUE last")]
+    PartitionBoundOrder,
+
+    #[error("Partition name used more than once")]
+    DuplicatePartition,
+}
+
+impl ParseError {
+    /// A code that identifies the error, it doesn't change between versions, unlike the
+    /// message. Codes are grouped by hundreds: `E00xx` for syntax, `E01xx` for names,
+    /// `E02xx` for literals, `E03xx` for definitions and `E04xx` for types and expressions.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownStatement => "E0001",
+            Self::ReservedKeyword => "E0002",
+            Self::InvalidEscape => "E0003",
+            Self::ChainedComparison => "E0004",
+            Self::MixedParameters => "E0005",
+            Self::UnterminatedString => "E0006",
+            Self::LimitExceeded { .. } => "E0007",
+            Self::UnexpectedToken { .. } => "E0008",
+            Self::TableNotFound => "E0101",
+            Self::ColumnNotFound => "E0102",
+            Self::SchemaNotFound => "E0103",
+            Self::TypeNotFound => "E0104",
+            Self::UnknownFunction => "E0105",
+            Self::TableAlreadyExists => "E0111",
+            Self::SchemaAlreadyExists => "E0112",
+            Self::TypeAlreadyExists => "E0113",
+            Self::AmbiguousColumn => "E0121",
+            Self::DuplicateTableName => "E0122",
+            Self::DuplicateCteName => "E0123",
+            Self::DuplicateEnumLabel => "E0124",
+            Self::InvalidDecimal => "E0201",
+            Self::InvalidDate => "E0202",
+            Self::InvalidTime => "E0203",
+            Self::InvalidTimestamp => "E0204",
+            Self::InvalidInterval => "E0205",
+            Self::InvalidBlob => "E0206",
+            Self::InvalidJson(_) => "E0207",
+            Self::InvalidArray => "E0208",
+            Self::ArrayElementType => "E0209",
+            Self::InvalidEnumLabel => "E0210",
+            Self::ColumnNotUsed => "E0301",
+            Self::NullNotAllowed => "E0302",
+            Self::MissingNotNullColumn(_) => "E0303",
+            Self::MultiplePrimaryKeys => "E0304",
+            Self::ForeignKeyColumnCount => "E0305",
+            Self::InvalidDecimalType => "E0306",
+            Self::AutoIncrementNotInteger => "E0307",
+            Self::CteColumnCount => "E0308",
+            Self::RecursiveReference => "E0309",
+            Self::ConflictTargetNotUnique => "E0310",
+            Self::ConflictTargetRequired => "E0311",
+            Self::ParameterCount => "E0312",
+            Self::UnknownCompression => "E0313",
+            Self::UnknownEngine => "E0314",
+            Self::PartitionBoundOrder => "E0315",
+            Self::DuplicatePartition => "E0316",
+            Self::InvalidView => "E0317",
+            Self::AssignmentType => "E0401",
+            Self::ArithmeticOverflow => "E0402",
+            Self::DivisionByZero => "E0403",
+            Self::InvalidOperand => "E0404",
+            Self::NotConstant => "E0405",
+            Self::IncompatibleTypes => "E0406",
+            Self::ArgumentCount => "E0407",
+            Self::ArgumentType => "E0408",
+            Self::AggregateWildcard => "E0409",
+            Self::NestedAggregate => "E0410",
+            Self::AggregateNotAllowed => "E0411",
+            Self::ColumnNotGrouped => "E0412",
+        }
+    }
+}
+
+/// Why a [`Value`](crate::value::Value) could not be converted to another type.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CastError {
+    #[error("Cannot convert {from:?} to {to:?}")]
+    Forbidden { from: SqlType, to: SqlType },
+
+    #[error("Converting {from:?} to {to:?} must be explicit, use CAST")]
+    NotImplicit { from: SqlType, to: SqlType },
+
+    #[error("Converting {from:?} to {to:?} loses data, use CAST")]
+    Lossy { from: SqlType, to: SqlType },
+
+    #[error("Value is out of range for {0:?}")]
+    OutOfRange(SqlType),
+
+    #[error("Text is not a valid {0:?}")]
+    InvalidText(SqlType),
+
+    #[error("NULL can only be converted to an Option")]
+    Null,
+}
+
+impl CastError {
+    /// A stable code for the error, see [`ParseError::code`], in the `E05xx` group.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::Forbidden { .. } => "E0501",
+            Self::NotImplicit { .. } => "E0502",
+            Self::Lossy { .. } => "E0503",
+            Self::OutOfRange(_) => "E0504",
+            Self::InvalidText(_) => "E0505",
+            Self::Null => "E0506",
+        }
+    }
+}
+
+/// Why bytes could not be read back as a [`Value`](crate::value::Value) or a row, see
+/// [`ROW_FORMAT_VERSION`](crate::codec::ROW_FORMAT_VERSION).
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("Row format version {0} is not supported")]
+    UnsupportedVersion(u8),
+
+    #[error("Input ends in the middle of a value")]
+    UnexpectedEnd,
+
+    #[error("{0} bytes are left after the last value")]
+    TrailingBytes(usize),
+
+    #[error("Bytes are not a valid {0:?}")]
+    InvalidValue(SqlType),
+}
+
+/// The code of an error that is not a [`ParseError`] or a [`CastError`], like an expected
+/// keyword or character.
+pub const SYNTAX_ERROR_CODE: &str = "E0000";
+
+/// The stable code of an error kind, see [`ParseError::code`].
+fn error_code(
+    kind: &BaseErrorKind<&str, Box<dyn std::error::Error + Send + Sync + 'static>>,
+) -> &'static str {
+    let BaseErrorKind::External(err) = kind else {
+        return SYNTAX_ERROR_CODE;
+    };
+    if let Some(err) = err.downcast_ref::<ParseError>() {
+        err.code()
+    } else if let Some(err) = err.downcast_ref::<CastError>() {
+        err.code()
+    } else {
+        SYNTAX_ERROR_CODE
+    }
 }
 
 #[derive(Debug, thiserror::Error, miette::Diagnostic)]
@@ -43,6 +374,79 @@ pub struct FormattedError<'b> {
     others: Vec<FormattedErrorContext<'b>>,
 }
 
+impl FormattedError<'_> {
+    /// The byte offset of the error in the input.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.span.offset()
+    }
+
+    /// The stable code of the error, see [`ParseError::code`].
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        error_code(&self.kind)
+    }
+
+    /// A copy of the error that can be serialized, for consumers that don't render it with
+    /// miette, like editors.
+    #[must_use]
+    pub fn to_report(&self) -> ErrorReport {
+        ErrorReport {
+            offset: self.span.offset(),
+            len: self.span.len(),
+            code: self.code().into(),
+            message: match &self.kind {
+                BaseErrorKind::External(err) => err.to_string(),
+                kind => kind.to_string(),
+            }
+            .into(),
+            contexts: self
+                .others
+                .iter()
+                .map(|context| ContextReport {
+                    offset: context.span.offset(),
+                    len: context.span.len(),
+                    context: match context.context {
+                        StackContext::Context(context) => context.into(),
+                        context => context.to_string().into(),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A [`FormattedError`] that doesn't borrow the input, with its message rendered, to be
+/// serialized as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ErrorReport {
+    /// Byte offset of the error from the start of the input.
+    pub offset: usize,
+    /// Length in bytes of the fragment the error points at.
+    pub len: usize,
+    /// The stable code of the error, see [`ParseError::code`].
+    pub code: Box<str>,
+    pub message: Box<str>,
+    /// The sections being parsed when the error happened, like `Column`, innermost first.
+    pub contexts: Box<[ContextReport]>,
+}
+
+impl From<RawParseError<'_>> for ErrorReport {
+    /// Reports an error of a method of the AST, like [`Expr::fold`](crate::ast::expr::Expr::fold),
+    /// without the input it points into.
+    fn from(err: RawParseError<'_>) -> Self {
+        format_spanned_error("", span_locations(err)).to_report()
+    }
+}
+
+/// A section of the input being parsed when an [`ErrorReport`] happened.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ContextReport {
+    pub offset: usize,
+    pub len: usize,
+    pub context: Box<str>,
+}
+
 #[derive(Debug, thiserror::Error, miette::Diagnostic)]
 #[error("Parse Error Context")]
 pub struct FormattedErrorContext<'b> {
@@ -55,32 +459,151 @@ pub struct FormattedErrorContext<'b> {
     context: StackContext<&'b str>,
 }
 
+/// An error whose locations are the spans of the input they point at, so it outlives the
+/// input.
+pub(crate) type SpannedParseError = ErrorTree<miette::SourceSpan>;
+
+/// Replaces the locations of `err` with the fragments they point at: the span given to an
+/// external error, like a [`ParseError`], is its own fragment, other errors and their
+/// contexts point at the next token, or at the end of the input.
+pub(crate) fn span_locations(err: RawParseError<'_>) -> SpannedParseError {
+    match err {
+        RawParseError::Base { location, kind } => {
+            let len = match kind {
+                BaseErrorKind::External(_) => location.fragment().len(),
+                _ => token_len(location),
+            };
+            ErrorTree::Base {
+                location: miette::SourceSpan::new(location.location_offset().into(), len.into()),
+                kind,
+            }
+        }
+        RawParseError::Stack { base, contexts } => ErrorTree::Stack {
+            base: Box::new(span_locations(*base)),
+            contexts: contexts
+                .into_iter()
+                .map(|(location, context)| {
+                    (
+                        miette::SourceSpan::new(
+                            location.location_offset().into(),
+                            token_len(location).into(),
+                        ),
+                        context,
+                    )
+                })
+                .collect(),
+        },
+        RawParseError::Alt(alt_errors) => {
+            ErrorTree::Alt(alt_errors.into_iter().map(span_locations).collect())
+        }
+    }
+}
+
+/// The length of the token `location` starts with, 0 if it starts with whitespace or a
+/// comment. An unterminated string or comment spans the rest of the input.
+fn token_len(location: RawSpan<'_>) -> usize {
+    Tokens::new(location)
+        .next()
+        .filter(|token| !token.is_trivia())
+        .map_or(0, |token| token.span.fragment().len())
+}
+
 #[must_use]
 pub fn format_parse_error<'a>(input: &'a str, err: RawParseError<'a>) -> FormattedError<'a> {
+    format_spanned_error(input, span_locations(err))
+}
+
+/// Like [`format_parse_error`], for an error whose locations are spans of `input`.
+pub(crate) fn format_spanned_error(input: &str, err: SpannedParseError) -> FormattedError<'_> {
     match err {
-        RawParseError::Base { location, kind } => FormattedError {
+        ErrorTree::Base { location, kind } => FormattedError {
             src: input,
-            span: miette::SourceSpan::new(location.location_offset().into(), 0.into()),
+            span: location,
             kind,
             others: Vec::new(),
         },
-        RawParseError::Stack { base, contexts } => {
-            let mut base = format_parse_error(input, *base);
+        ErrorTree::Stack { base, contexts } => {
+            let mut base = format_spanned_error(input, *base);
             let mut contexts = contexts
                 .into_iter()
                 .map(|(location, context)| FormattedErrorContext {
                     src: input,
-                    span: miette::SourceSpan::new(location.location_offset().into(), 0.into()),
+                    span: location,
                     context,
                 })
                 .collect::<Vec<_>>();
             base.others.append(&mut contexts);
             base
         }
-        RawParseError::Alt(alt_errors) => alt_errors
+        ErrorTree::Alt(alt_errors) => alt_errors
             .into_iter()
-            .map(|e| format_parse_error(input, e))
+            .map(|e| format_spanned_error(input, e))
             .max_by_key(|e| e.others.len())
             .expect("alt errors should not be empty"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::{
+        ast::{commands::create::Column, statement::Statement},
+        parse::{parse_format_error, TableMap},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_error_report() {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [("id".into(), Column::new("id", SqlType::I32))].into(),
+        );
+        let report = |input| {
+            parse_format_error(input, |i| Statement::parse_with_table_map(&table_map, i))
+                .unwrap_err()
+                .to_report()
+        };
+        assert_eq!(report("SELECT missing FROM users").code.as_ref(), "E0102");
+        assert_eq!(report("UPSERT INTO users").code.as_ref(), "E0001");
+        assert_eq!(
+            report("INSERT INTO users (id) VALUES ('a')").code.as_ref(),
+            "E0502"
+        );
+        assert_eq!(
+            report("SELECT id FROM users WHERE").code.as_ref(),
+            SYNTAX_ERROR_CODE
+        );
+
+        let report = report("CREATE TABLE t (id int7)");
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<ErrorReport>(&json).unwrap(), report);
+        insta::assert_json_snapshot!(report);
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let table_map = TableMap::new();
+        let span = |input| {
+            let report =
+                parse_format_error(input, |i| Statement::parse_with_table_map(&table_map, i))
+                    .unwrap_err()
+                    .to_report();
+            (report.offset, report.len, report.code)
+        };
+        assert_eq!(span("DROP TABLE missing"), (11, 7, "E0101".into()));
+        assert_eq!(
+            span("SELECT 1 FROM t WHERE x = 'abc\ndef"),
+            (26, 8, "E0006".into())
+        );
+        assert_eq!(
+            span("CREATE TABLE t (id int8) garbage"),
+            (24, 0, SYNTAX_ERROR_CODE.into())
+        );
+        crate::parsers::string::with_escape_mode(
+            crate::parsers::string::EscapeMode::Backslash,
+            || assert_eq!(span("SELECT 'a\\qb' FROM t"), (9, 2, "E0003".into())),
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use std::cmp::Ordering;
+
+use nom_supreme::error::BaseErrorKind;
+
+use crate::{
+    ast::expr::{and, evaluate_binary, or, BinaryOperator, ColumnRef, Expr, UnaryOperator},
+    errors::{custom_failure, ParseError, RawParseError},
+    functions::FunctionRegistry,
+    parse::RawSpan,
+    value::Value,
+};
+
+/// The values of the columns of the row an expression is evaluated against.
+pub type Row<'r, 'a> = &'r dyn Fn(&ColumnRef<'a>) -> Value;
+
+/// Evaluates expressions against rows, calling the functions of a registry.
+#[derive(Clone, Copy)]
+pub struct Evaluator<'f> {
+    functions: &'f FunctionRegistry,
+}
+
+impl<'f> Evaluator<'f> {
+    #[must_use]
+    pub const fn new(functions: &'f FunctionRegistry) -> Self {
+        Self { functions }
+    }
+
+    /// Evaluates `expr` with the values `row` gives to its columns. Operators and
+    /// predicates follow SQL three-valued logic, UNKNOWN is `NULL`, and `AND`, `OR` and
+    /// `CASE` only evaluate the operands they need, so `FALSE AND 1 / 0 = 1` is `FALSE`.
+    /// Parameters must be bound and aggregates computed, as columns, before.
+    /// # Errors
+    /// Returns an error pointing at the operator that overflows, divides by zero or is given
+    /// operands of the wrong type, at the function that is unknown or fails, or at a
+    /// parameter or an aggregate.
+    pub fn eval<'a>(
+        &self,
+        expr: &Expr<'a>,
+        row: Row<'_, 'a>,
+    ) -> Result<Value, nom::Err<RawParseError<'a>>> {
+        let eval = |expr: &Expr<'a>| self.eval(expr, row);
+        let value = match expr {
+            Expr::Literal((_, value)) => value.clone(),
+            Expr::Column(column) => row(column),
+            Expr::Parameter((span, _)) => return Err(failure(*span, ParseError::ParameterCount)),
+            Expr::Aggregate {
+                function: (span, _),
+                ..
+            } => return Err(failure(*span, ParseError::AggregateNotAllowed)),
+            Expr::Nested((_, expr)) => eval(expr)?,
+            Expr::Unary {
+                op: (span, op),
+                expr,
+            } => {
+                let value = eval(expr)?;
+                match op {
+                    UnaryOperator::Minus => value.checked_neg(),
+                    UnaryOperator::Not => value.truth().map(|truth| truth.map(|t| !t).into()),
+                }
+                .map_err(|e| failure(*span, e))?
+            }
+            Expr::Binary {
+                op: (span, op @ (BinaryOperator::And | BinaryOperator::Or)),
+                left,
+                right,
+            } => {
+                let left = eval(left)?.truth().map_err(|e| failure(*span, e))?;
+                // FALSE decides `AND` and TRUE decides `OR` whatever the right operand is.
+                if left == Some(*op == BinaryOperator::Or) {
+                    return Ok(left.into());
+                }
+                let right = eval(right)?.truth().map_err(|e| failure(*span, e))?;
+                match op {
+                    BinaryOperator::And => and(left, right).into(),
+                    _ => or(left, right).into(),
+                }
+            }
+            Expr::Binary {
+                op: (span, op),
+                left,
+                right,
+            } => {
+                let (left, right) = (eval(left)?, eval(right)?);
+                evaluate_binary(*op, &left, &right).map_err(|e| failure(*span, e))?
+            }
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let (value, low, high) = (eval(expr)?, eval(low)?, eval(high)?);
+                let in_range = value
+                    .compare(&low)
+                    .and_then(|above| {
+                        let below = value.compare(&high)?;
+                        Ok(and(above.map(Ordering::is_ge), below.map(Ordering::is_le)))
+                    })
+                    .map_err(|e| failure(expr.span(), e))?;
+                in_range.map(|b| b != *negated).into()
+            }
+            Expr::InList {
+                expr,
+                negated,
+                list,
+            } => {
+                let value = eval(expr)?;
+                // `x IN (a, b)` is `x = a OR x = b`, so a `NULL` makes a miss UNKNOWN and the
+                // first match decides.
+                let mut found = Some(false);
+                for item in list.iter() {
+                    let equal = value
+                        .compare(&eval(item)?)
+                        .map_err(|e| failure(expr.span(), e))?
+                        .map(Ordering::is_eq);
+                    found = or(found, equal);
+                    if found == Some(true) {
+                        break;
+                    }
+                }
+                found.map(|b| b != *negated).into()
+            }
+            Expr::Like {
+                expr,
+                negated,
+                pattern: (span, pattern),
+            } => match eval(expr)? {
+                Value::Null => Value::Null,
+                Value::VarChar(s) => Value::Bool(pattern.matches(&s) != *negated),
+                Value::Char(s) => Value::Bool(pattern.matches(s.trim_end_matches(' ')) != *negated),
+                _ => return Err(failure(*span, ParseError::InvalidOperand)),
+            },
+            Expr::IsNull { expr, negated } => Value::Bool(eval(expr)?.is_null() != *negated),
+            Expr::Case {
+                operand,
+                branches,
+                else_result,
+                ..
+            } => {
+                let operand = operand.as_deref().map(eval).transpose()?;
+                for (condition, result) in branches.iter() {
+                    let condition_value = eval(condition)?;
+                    let matched = match &operand {
+                        Some(operand) => operand
+                            .compare(&condition_value)
+                            .map(|ordering| ordering.is_some_and(Ordering::is_eq)),
+                        None => condition_value.truth().map(|truth| truth == Some(true)),
+                    }
+                    .map_err(|e| failure(condition.span(), e))?;
+                    if matched {
+                        return eval(result);
+                    }
+                }
+                match else_result {
+                    Some(result) => eval(result)?,
+                    None => Value::Null,
+                }
+            }
+            Expr::Function { name, args, tp } => {
+                let values = args.iter().map(eval).collect::<Result<Vec<_>, _>>()?;
+                let function = self
+                    .functions
+                    .get(name.fragment())
+                    .ok_or_else(|| failure(*name, ParseError::UnknownFunction))?;
+                let value = function
+                    .call(&values)
+                    .map_err(|e| custom_failure(*name, BaseErrorKind::External(e)))?;
+                match tp {
+                    Some(tp) => value
+                        .cast(tp.clone())
+                        .map_err(|e| custom_failure(*name, BaseErrorKind::External(Box::new(e))))?,
+                    None => value,
+                }
+            }
+            Expr::Cast {
+                expr,
+                tp: (span, tp),
+                ..
+            } => eval(expr)?
+                .cast(tp.clone())
+                .map_err(|e| custom_failure(*span, BaseErrorKind::External(Box::new(e))))?,
+            Expr::Subscript {
+                expr,
+                index: (span, index),
+            } => eval(expr)?
+                .subscript(&eval(index)?)
+                .map_err(|e| failure(*span, e))?,
+        };
+        Ok(value)
+    }
+
+    /// Whether `expr` is TRUE for `row`, as `WHERE` and `HAVING` keep a row: FALSE and
+    /// UNKNOWN both reject it.
+    /// # Errors
+    /// Returns an error if the expression fails, see [`Evaluator::eval`], or is not a
+    /// boolean.
+    pub fn matches<'a>(
+        &self,
+        expr: &Expr<'a>,
+        row: Row<'_, 'a>,
+    ) -> Result<bool, nom::Err<RawParseError<'a>>> {
+        self.eval(expr, row)?
+            .truth()
+            .map(|truth| truth == Some(true))
+            .map_err(|e| failure(expr.span(), e))
+    }
+}
+
+fn failure(span: RawSpan<'_>, error: ParseError) -> nom::Err<RawParseError<'_>> {
+    custom_failure(span, BaseErrorKind::External(Box::new(error)))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::collections::HashMap;
+
+    use crate::parse::Parse;
+
+    use super::*;
+
+    fn eval(input: &str, row: &[(&str, Value)]) -> Result<Value, ParseError> {
+        let columns = row.iter().cloned().collect::<HashMap<_, _>>();
+        let (_, expr) = Expr::parse(RawSpan::new(input)).unwrap();
+        Evaluator::new(FunctionRegistry::builtins())
+            .eval(&expr, &|column| columns[column.key().as_ref()].clone())
+            .map_err(|e| match e {
+                nom::Err::Failure(RawParseError::Base {
+                    kind: BaseErrorKind::External(e),
+                    ..
+                }) => *e.downcast::<ParseError>().unwrap(),
+                e => panic!("{e:?}"),
+            })
+    }
+
+    #[test]
+    fn test_eval() {
+        let row = [
+            ("a", Value::I32(3)),
+            ("b", Value::I64(4)),
+            ("name", Value::VarChar("Ana".into())),
+            ("missing", Value::Null),
+        ];
+        assert_eq!(eval("a * b + 1", &row), Ok(Value::I128(13)));
+        assert_eq!(eval("-a", &row), Ok(Value::I32(-3)));
+        assert_eq!(
+            eval("a < b AND name LIKE 'A%'", &row),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(eval("a BETWEEN 1 AND b", &row), Ok(Value::Bool(true)));
+        assert_eq!(eval("b NOT IN (1, 2, a)", &row), Ok(Value::Bool(true)));
+        assert_eq!(eval("a IN (missing, 3)", &row), Ok(Value::Bool(true)));
+        assert_eq!(eval("a IN (missing, 2)", &row), Ok(Value::Null));
+        assert_eq!(eval("missing + 1", &row), Ok(Value::Null));
+        assert_eq!(eval("missing = missing", &row), Ok(Value::Null));
+        assert_eq!(eval("missing IS NULL", &row), Ok(Value::Bool(true)));
+        assert_eq!(
+            eval("CASE WHEN a > b THEN 'big' ELSE name END", &row),
+            Ok(Value::VarChar("Ana".into()))
+        );
+        assert_eq!(eval("upper(name)", &row), Ok(Value::VarChar("ANA".into())));
+        assert_eq!(eval("CAST(a AS INT8)", &row), Ok(Value::I8(3)));
+    }
+
+    #[test]
+    fn test_eval_short_circuit() {
+        let row = [("a", Value::I32(0)), ("missing", Value::Null)];
+        assert_eq!(eval("a = 1 AND 1 / a = 1", &row), Ok(Value::Bool(false)));
+        assert_eq!(eval("a = 0 OR 1 / a = 1", &row), Ok(Value::Bool(true)));
+        assert_eq!(
+            eval("CASE WHEN a = 0 THEN 0 ELSE 1 / a END", &row),
+            Ok(Value::I64(0))
+        );
+        assert_eq!(eval("a IN (0, 1 / a)", &row), Ok(Value::Bool(true)));
+        assert_eq!(
+            eval("a = 0 AND 1 / a = 1", &row),
+            Err(ParseError::DivisionByZero)
+        );
+        assert_eq!(
+            eval("missing AND 1 / a = 1", &row),
+            Err(ParseError::DivisionByZero)
+        );
+        assert_eq!(eval("a AND TRUE", &row), Err(ParseError::InvalidOperand));
+        assert_eq!(eval("count(a)", &row), Err(ParseError::AggregateNotAllowed));
+    }
+
+    #[test]
+    fn test_matches() {
+        let (_, expr) = Expr::parse(RawSpan::new("a > 1")).unwrap();
+        let evaluator = Evaluator::new(FunctionRegistry::builtins());
+        assert!(evaluator.matches(&expr, &|_| Value::I8(2)).unwrap());
+        assert!(!evaluator.matches(&expr, &|_| Value::I8(1)).unwrap());
+        assert!(!evaluator.matches(&expr, &|_| Value::Null).unwrap());
+    }
+}
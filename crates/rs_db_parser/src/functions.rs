@@ -0,0 +1,343 @@
+use std::{collections::HashMap, sync::Arc, sync::OnceLock};
+
+use crate::{ast::commands::create::SqlType, errors::ParseError, value::Value};
+
+pub type FunctionError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+pub type FunctionImpl = Arc<dyn Fn(&[Value]) -> Result<Value, FunctionError> + Send + Sync>;
+
+/// The values a function argument accepts, `NULL` is accepted by all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArgType {
+    Any,
+    Integer,
+    /// Integers, floats and decimals.
+    Numeric,
+    /// Strings, of variable or fixed length.
+    Text,
+    /// Text or binary data, the values with a length.
+    TextOrBlob,
+    Bool,
+}
+
+/// How many arguments a function takes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Arguments {
+    Fixed(Box<[ArgType]>),
+    /// At least `min` arguments, all of the same kind.
+    Variadic {
+        tp: ArgType,
+        min: usize,
+    },
+}
+
+/// The type of the value a function returns.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReturnType {
+    Fixed(SqlType),
+    /// The type of the argument at this position.
+    Argument(usize),
+    /// The type all arguments convert to, see [`SqlType::common`].
+    Common,
+}
+
+#[derive(Clone)]
+pub struct Function {
+    pub name: Box<str>,
+    pub arguments: Arguments,
+    pub return_type: ReturnType,
+    implementation: FunctionImpl,
+}
+
+/// The scalar functions expressions can call, looked up by case-insensitive name.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<Box<str>, Function>,
+}
+
+impl ArgType {
+    #[must_use]
+    pub const fn accepts(self, tp: &SqlType) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Integer => tp.is_integer(),
+            Self::Numeric => tp.is_numeric(),
+            Self::Text => matches!(tp, SqlType::VarChar(..) | SqlType::Char(_)),
+            Self::TextOrBlob => {
+                matches!(tp, SqlType::VarChar(..) | SqlType::Char(_) | SqlType::Blob)
+            }
+            Self::Bool => matches!(tp, SqlType::Bool),
+        }
+    }
+}
+
+impl Arguments {
+    /// The kind of the argument at `index`, `None` past the last argument.
+    fn get(&self, index: usize) -> Option<ArgType> {
+        match self {
+            Self::Fixed(args) => args.get(index).copied(),
+            Self::Variadic { tp, .. } => Some(*tp),
+        }
+    }
+
+    const fn accepts_count(&self, count: usize) -> bool {
+        match self {
+            Self::Fixed(args) => args.len() == count,
+            Self::Variadic { min, .. } => count >= *min,
+        }
+    }
+
+    /// Checks the number and types of the arguments, `None` stands for `NULL`.
+    fn check(&self, types: &[Option<SqlType>]) -> Result<(), ParseError> {
+        if !self.accepts_count(types.len()) {
+            return Err(ParseError::ArgumentCount);
+        }
+        let accepted = types.iter().enumerate().all(|(index, tp)| {
+            tp.as_ref()
+                .is_none_or(|tp| self.get(index).is_some_and(|arg| arg.accepts(tp)))
+        });
+        if accepted {
+            Ok(())
+        } else {
+            Err(ParseError::ArgumentType)
+        }
+    }
+}
+
+impl Function {
+    /// The type returned for arguments of the given types, `None` stands for `NULL`.
+    /// # Errors
+    /// Returns an error if the arguments don't match the function signature.
+    pub fn result_type(&self, types: &[Option<SqlType>]) -> Result<Option<SqlType>, ParseError> {
+        self.arguments.check(types)?;
+        match &self.return_type {
+            ReturnType::Fixed(tp) => Ok(Some(tp.clone())),
+            ReturnType::Argument(index) => Ok(types.get(*index).cloned().flatten()),
+            ReturnType::Common => types.iter().flatten().try_fold(None, |common, tp| {
+                common
+                    .map_or(Some(tp.clone()), |common: SqlType| common.common(tp))
+                    .map(Some)
+                    .ok_or(ParseError::IncompatibleTypes)
+            }),
+        }
+    }
+
+    /// Calls the function, after checking the arguments match its signature.
+    /// # Errors
+    /// Returns an error if the arguments don't match the signature, or the error of the
+    /// implementation.
+    pub fn call(&self, args: &[Value]) -> Result<Value, FunctionError> {
+        let types = args.iter().map(Value::sql_type).collect::<Vec<_>>();
+        self.arguments.check(&types)?;
+        (self.implementation)(args)
+    }
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("arguments", &self.arguments)
+            .field("return_type", &self.return_type)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionRegistry {
+    /// An empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the built-in functions: `length`, `upper`, `lower`, `abs` and
+    /// `coalesce`.
+    #[must_use]
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            "length",
+            Arguments::Fixed([ArgType::TextOrBlob].into()),
+            ReturnType::Fixed(SqlType::I64),
+            |args| match &args[0] {
+                Value::VarChar(s) => Ok(Value::I64(s.chars().count().try_into()?)),
+                // The padding of a fixed-length string doesn't count.
+                Value::Char(s) => Ok(Value::I64(
+                    s.trim_end_matches(' ').chars().count().try_into()?,
+                )),
+                Value::Blob(bytes) => Ok(Value::I64(bytes.len().try_into()?)),
+                _ => Ok(Value::Null),
+            },
+        );
+        registry.register(
+            "upper",
+            Arguments::Fixed([ArgType::Text].into()),
+            ReturnType::Argument(0),
+            |args| match &args[0] {
+                Value::VarChar(s) => Ok(Value::VarChar(s.to_uppercase().into())),
+                Value::Char(s) => Ok(Value::Char(s.to_uppercase().into())),
+                _ => Ok(Value::Null),
+            },
+        );
+        registry.register(
+            "lower",
+            Arguments::Fixed([ArgType::Text].into()),
+            ReturnType::Argument(0),
+            |args| match &args[0] {
+                Value::VarChar(s) => Ok(Value::VarChar(s.to_lowercase().into())),
+                Value::Char(s) => Ok(Value::Char(s.to_lowercase().into())),
+                _ => Ok(Value::Null),
+            },
+        );
+        registry.register(
+            "abs",
+            Arguments::Fixed([ArgType::Numeric].into()),
+            ReturnType::Argument(0),
+            |args| {
+                match &args[0] {
+                    Value::F32(v) => Some(Value::F32(v.abs())),
+                    Value::F64(v) => Some(Value::F64(v.abs())),
+                    Value::Decimal(v) => Some(Value::Decimal(v.abs())),
+                    Value::I8(v) => v.checked_abs().map(Value::I8),
+                    Value::I16(v) => v.checked_abs().map(Value::I16),
+                    Value::I32(v) => v.checked_abs().map(Value::I32),
+                    Value::I64(v) => v.checked_abs().map(Value::I64),
+                    Value::I128(v) => v.checked_abs().map(Value::I128),
+                    value => Some(value.clone()),
+                }
+                .ok_or_else(|| ParseError::ArithmeticOverflow.into())
+            },
+        );
+        registry.register(
+            "coalesce",
+            Arguments::Variadic {
+                tp: ArgType::Any,
+                min: 1,
+            },
+            ReturnType::Common,
+            |args| {
+                Ok(args
+                    .iter()
+                    .find(|value| !value.is_null())
+                    .cloned()
+                    .unwrap_or(Value::Null))
+            },
+        );
+        registry
+    }
+
+    /// The built-in functions, shared by the statement parsers.
+    #[must_use]
+    pub fn builtins() -> &'static Self {
+        static BUILTINS: OnceLock<FunctionRegistry> = OnceLock::new();
+        BUILTINS.get_or_init(Self::with_builtins)
+    }
+
+    /// Registers a function, replacing and returning any function with the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        arguments: Arguments,
+        return_type: ReturnType,
+        implementation: impl Fn(&[Value]) -> Result<Value, FunctionError> + Send + Sync + 'static,
+    ) -> Option<Function> {
+        let name: Box<str> = name.to_ascii_lowercase().into();
+        self.functions.insert(
+            name.clone(),
+            Function {
+                name,
+                arguments,
+                return_type,
+                implementation: Arc::new(implementation),
+            },
+        )
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        self.functions.get(&*name.to_ascii_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::ast::commands::create::LengthUnit;
+
+    #[test]
+    fn test_builtins() {
+        let registry = FunctionRegistry::builtins();
+        let call = |name: &str, args: &[Value]| registry.get(name).unwrap().call(args).ok();
+        assert_eq!(
+            call("LENGTH", &[Value::VarChar("héllo".into())]),
+            Some(Value::I64(5))
+        );
+        assert_eq!(
+            call("length", &[Value::Char("ab  ".into())]),
+            Some(Value::I64(2))
+        );
+        assert_eq!(
+            call("upper", &[Value::VarChar("abc".into())]),
+            Some(Value::VarChar("ABC".into()))
+        );
+        assert_eq!(
+            call("lower", &[Value::VarChar("ABC".into())]),
+            Some(Value::VarChar("abc".into()))
+        );
+        assert_eq!(call("abs", &[Value::I8(-3)]), Some(Value::I8(3)));
+        assert_eq!(call("abs", &[Value::I8(i8::MIN)]), None);
+        assert_eq!(call("abs", &[Value::Null]), Some(Value::Null));
+        assert_eq!(
+            call("coalesce", &[Value::Null, Value::I64(1), Value::I64(2)]),
+            Some(Value::I64(1))
+        );
+        assert_eq!(call("length", &[Value::I64(1)]), None);
+        assert_eq!(
+            call("length", &[Value::Blob([0xde, 0xad, 0xbe].into())]),
+            Some(Value::I64(3))
+        );
+        assert_eq!(call("coalesce", &[]), None);
+    }
+
+    #[test]
+    fn test_result_type() {
+        let registry = FunctionRegistry::builtins();
+        let coalesce = registry.get("coalesce").unwrap();
+        assert_eq!(
+            coalesce.result_type(&[None, Some(SqlType::I8), Some(SqlType::I16)]),
+            Ok(Some(SqlType::I128))
+        );
+        assert_eq!(
+            coalesce.result_type(&[Some(SqlType::I8), Some(SqlType::Bool)]),
+            Err(ParseError::IncompatibleTypes)
+        );
+        assert_eq!(
+            registry
+                .get("upper")
+                .unwrap()
+                .result_type(&[Some(SqlType::VarChar(3, LengthUnit::Chars))]),
+            Ok(Some(SqlType::VarChar(3, LengthUnit::Chars)))
+        );
+        assert_eq!(
+            registry.get("abs").unwrap().result_type(&[]),
+            Err(ParseError::ArgumentCount)
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = FunctionRegistry::new();
+        assert!(registry.get("double").is_none());
+        registry.register(
+            "Double",
+            Arguments::Fixed([ArgType::Integer].into()),
+            ReturnType::Argument(0),
+            |args| Ok(args[0].add(&args[0])?),
+        );
+        assert_eq!(
+            registry.get("DOUBLE").unwrap().call(&[Value::I64(21)]).ok(),
+            Some(Value::I64(42))
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till, take_while1},
+    character::complete::{char, digit1, one_of},
+    combinator::{map, not, peek, recognize},
+    error::ContextError,
+    sequence::{pair, preceded, terminated},
+    Slice,
+};
+
+use crate::{
+    errors::{custom_error, ParseError, ParseResult, RawParseError},
+    parse::{RawSpan, WithSpan},
+    parsers::{
+        comment,
+        identifier::{is_reserved, quoted_identifier},
+        number::{decimal, integer},
+        string::string_literal,
+        truncate_raw_span,
+    },
+};
+
+/// What a [`Token`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Whitespace,
+    /// `-- ...` up to the end of the line, or `/* ... */`.
+    Comment,
+    /// One of the [`RESERVED_KEYWORDS`](crate::parsers::identifier::RESERVED_KEYWORDS), in
+    /// any case. The other words, like type and function names, are identifiers.
+    Keyword,
+    Identifier,
+    /// A name between double quotes or backticks, the span includes the quotes.
+    QuotedIdentifier,
+    /// A string literal, the span includes the quotes.
+    String,
+    /// A binary literal, `x'DEADBEEF'`.
+    Blob,
+    /// An integer or decimal number, without its sign.
+    Number,
+    /// A placeholder, `$n` or `?`.
+    Parameter,
+    /// An arithmetic, comparison or JSON operator.
+    Operator,
+    /// `(`, `)`, `[`, `]`, `,`, `.` or `;`.
+    Punctuation,
+    /// A character that starts no token, or an unterminated string, name or comment up to
+    /// the end of the input.
+    Unknown,
+}
+
+/// A token of the input, with its span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub span: RawSpan<'a>,
+}
+
+impl Token<'_> {
+    /// Whether the token is the keyword `word`, in any case.
+    #[must_use]
+    pub fn is_keyword(&self, word: &str) -> bool {
+        self.kind == TokenKind::Keyword && self.span.fragment().eq_ignore_ascii_case(word)
+    }
+
+    /// Whether the token only separates the others.
+    #[must_use]
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+/// The tokens of an input, in order. Every character of the input is part of exactly one
+/// token, so the tokens can be used to highlight it as written. String literals are read
+/// with the current [`EscapeMode`](crate::parsers::string::EscapeMode).
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    rest: RawSpan<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    /// The tokens of `input`, keeping its position so the spans point into the whole input.
+    #[must_use]
+    pub const fn new(input: RawSpan<'a>) -> Self {
+        Self { rest: input }
+    }
+
+    /// The input after the tokens read so far.
+    #[must_use]
+    pub const fn rest(&self) -> RawSpan<'a> {
+        self.rest
+    }
+}
+
+/// The tokens of `input`, see [`Tokens`].
+#[must_use]
+pub fn tokenize(input: &str) -> Tokens<'_> {
+    Tokens::new(RawSpan::new(input))
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.fragment().is_empty() {
+            return None;
+        }
+        let (rest, token) = match token(self.rest) {
+            Ok(result) => result,
+            // An unterminated string, name or comment swallows the rest of the input.
+            Err(nom::Err::Failure(_)) => {
+                let end = self.rest.slice(self.rest.fragment().len()..);
+                (end, (TokenKind::Unknown, self.rest))
+            }
+            Err(_) => {
+                let len = self
+                    .rest
+                    .fragment()
+                    .chars()
+                    .next()
+                    .map_or(0, char::len_utf8);
+                (
+                    self.rest.slice(len..),
+                    (TokenKind::Unknown, self.rest.slice(..len)),
+                )
+            }
+        };
+        self.rest = rest;
+        let (kind, span) = token;
+        Some(Token { kind, span })
+    }
+}
+
+/// A cursor over the [`Tokens`] of an input that skips the trivia, for the parsers of the
+/// commands that read tokens instead of characters. Its errors point at the token that
+/// doesn't match and name it, like "Unexpected keyword VALUES, expected TO".
+#[derive(Debug, Clone)]
+pub(crate) struct TokenCursor<'a> {
+    tokens: Tokens<'a>,
+    /// The input after the last token read.
+    rest: RawSpan<'a>,
+}
+
+impl<'a> TokenCursor<'a> {
+    pub(crate) const fn new(input: RawSpan<'a>) -> Self {
+        Self {
+            tokens: Tokens::new(input),
+            rest: input,
+        }
+    }
+
+    /// The input after the last token read, the trivia after it included.
+    pub(crate) const fn rest(&self) -> RawSpan<'a> {
+        self.rest
+    }
+
+    /// The next token that isn't trivia, without reading it.
+    pub(crate) fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.clone().find(|token| !token.is_trivia())
+    }
+
+    /// Reads the trivia before the next token, so [`Tokens::rest`] starts with it.
+    fn skip_trivia(&mut self) {
+        let mut ahead = self.tokens.clone();
+        while ahead.next().is_some_and(|token| token.is_trivia()) {
+            self.tokens = ahead.clone();
+        }
+    }
+
+    /// Whether the next token is the word `word`, in any case. The words that aren't
+    /// reserved, like `BACKUP`, are identifiers.
+    pub(crate) fn is_next(&self, word: &str) -> bool {
+        self.peek().is_some_and(|token| {
+            matches!(token.kind, TokenKind::Keyword | TokenKind::Identifier)
+                && token.span.fragment().eq_ignore_ascii_case(word)
+        })
+    }
+
+    /// Reads the keyword `word` if it is next.
+    pub(crate) fn opt_keyword(&mut self, word: &str) -> Option<RawSpan<'a>> {
+        if self.is_next(word) {
+            self.next().map(|token| token.span)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the keyword `word`.
+    /// # Errors
+    /// Returns an error at the next token if it is another one.
+    pub(crate) fn keyword(
+        &mut self,
+        word: &str,
+    ) -> Result<RawSpan<'a>, nom::Err<RawParseError<'a>>> {
+        self.opt_keyword(word)
+            .ok_or_else(|| self.unexpected(&word.to_ascii_uppercase()))
+    }
+
+    /// Whether the next token is the punctuation or operator `symbol`, like `(` or `=`.
+    pub(crate) fn is_next_symbol(&self, symbol: &str) -> bool {
+        self.peek().is_some_and(|token| {
+            matches!(token.kind, TokenKind::Punctuation | TokenKind::Operator)
+                && *token.span.fragment() == symbol
+        })
+    }
+
+    /// Reads the punctuation or operator `symbol` if it is next.
+    pub(crate) fn opt_symbol(&mut self, symbol: &str) -> Option<RawSpan<'a>> {
+        if self.is_next_symbol(symbol) {
+            self.next().map(|token| token.span)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the punctuation or operator `symbol`.
+    /// # Errors
+    /// Returns an error at the next token if it is another one.
+    pub(crate) fn symbol(
+        &mut self,
+        symbol: &str,
+    ) -> Result<RawSpan<'a>, nom::Err<RawParseError<'a>>> {
+        self.opt_symbol(symbol)
+            .ok_or_else(|| self.unexpected(&format!("`{symbol}`")))
+    }
+
+    /// Runs `f`, adding the context `name` at the next token to its error, like
+    /// [`nom::error::context`].
+    /// # Errors
+    /// Returns the error of `f`.
+    pub(crate) fn context<T>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> Result<T, nom::Err<RawParseError<'a>>>,
+    ) -> Result<T, nom::Err<RawParseError<'a>>> {
+        self.skip_trivia();
+        let input = self.tokens.rest();
+        f(self).map_err(|err| err.map(|err| RawParseError::add_context(input, name, err)))
+    }
+
+    /// Runs `f`, giving the span from the next token to the last token it read, empty when
+    /// it read none.
+    /// # Errors
+    /// Returns the error of `f`.
+    pub(crate) fn with_span<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, nom::Err<RawParseError<'a>>>,
+    ) -> Result<WithSpan<'a, T>, nom::Err<RawParseError<'a>>> {
+        self.skip_trivia();
+        let start = self.tokens.rest();
+        let value = f(self)?;
+        let end = if self.rest.location_offset() < start.location_offset() {
+            start
+        } else {
+            self.rest
+        };
+        Ok((truncate_raw_span(&start, &end), value))
+    }
+
+    /// Runs the parser of characters `parser` from the next token, like a string literal or a
+    /// table name, and reads the tokens it read.
+    /// # Errors
+    /// Returns the error of the parser.
+    pub(crate) fn parse<T>(
+        &mut self,
+        mut parser: impl FnMut(RawSpan<'a>) -> ParseResult<'a, T>,
+    ) -> Result<T, nom::Err<RawParseError<'a>>> {
+        self.skip_trivia();
+        let (rest, value) = parser(self.tokens.rest())?;
+        self.tokens = Tokens::new(rest);
+        self.rest = rest;
+        Ok(value)
+    }
+
+    /// Like [`TokenCursor::parse`], `None` when the parser doesn't match, reading no token.
+    /// # Errors
+    /// Returns the failure of the parser.
+    pub(crate) fn opt_parse<T>(
+        &mut self,
+        parser: impl FnMut(RawSpan<'a>) -> ParseResult<'a, T>,
+    ) -> Result<Option<T>, nom::Err<RawParseError<'a>>> {
+        self.opt(|tokens| tokens.parse(parser))
+    }
+
+    /// Runs `f`, `None` when it doesn't match, reading no token, like
+    /// [`nom::combinator::opt`].
+    /// # Errors
+    /// Returns the failure of `f`.
+    pub(crate) fn opt<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, nom::Err<RawParseError<'a>>>,
+    ) -> Result<Option<T>, nom::Err<RawParseError<'a>>> {
+        let mut ahead = self.clone();
+        match f(&mut ahead) {
+            Ok(value) => {
+                *self = ahead;
+                Ok(Some(value))
+            }
+            Err(nom::Err::Error(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks the next token is a `kind`, like a string literal.
+    /// # Errors
+    /// Returns an error at the next token if it is another kind, `expected` naming the
+    /// token.
+    pub(crate) fn expect(
+        &self,
+        kind: TokenKind,
+        expected: &str,
+    ) -> Result<(), nom::Err<RawParseError<'a>>> {
+        match self.peek() {
+            Some(token) if token.kind == kind => Ok(()),
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// An [`ParseError::UnexpectedToken`] at the next token, `expected` being what should
+    /// have been there.
+    pub(crate) fn unexpected(&self, expected: &str) -> nom::Err<RawParseError<'a>> {
+        let mut rest = self.clone();
+        rest.skip_trivia();
+        let (location, found) = match rest.peek() {
+            Some(token) if token.kind == TokenKind::Keyword => (
+                token.span,
+                format!("keyword {}", token.span.fragment().to_ascii_uppercase()),
+            ),
+            Some(token) => (token.span, format!("`{}`", token.span.fragment())),
+            None => (rest.tokens.rest(), "end of input".to_owned()),
+        };
+        custom_error(
+            location,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::UnexpectedToken {
+                found: found.into(),
+                expected: expected.into(),
+            })),
+        )
+    }
+}
+
+impl<'a> Iterator for TokenCursor<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.find(|token| !token.is_trivia())?;
+        self.rest = self.tokens.rest();
+        Some(token)
+    }
+}
+
+/// Turns the recoverable error of a [`TokenCursor`] into a failure, once the input can
+/// only be the statement being parsed, like [`nom::combinator::cut`].
+pub(crate) fn cut<'a, T>(
+    result: Result<T, nom::Err<RawParseError<'a>>>,
+) -> Result<T, nom::Err<RawParseError<'a>>> {
+    result.map_err(|err| match err {
+        nom::Err::Error(err) => nom::Err::Failure(err),
+        err => err,
+    })
+}
+
+fn token(input: RawSpan<'_>) -> ParseResult<'_, (TokenKind, RawSpan<'_>)> {
+    alt((
+        kind(
+            TokenKind::Whitespace,
+            take_while1(|c: char| c.is_whitespace()),
+        ),
+        kind(TokenKind::Comment, comment),
+        kind(
+            TokenKind::Blob,
+            recognize(preceded(
+                pair(one_of("xX"), char('\'')),
+                terminated(take_till(|c| c == '\''), char('\'')),
+            )),
+        ),
+        kind(TokenKind::Number, number),
+        map(word, |word: RawSpan| {
+            if is_reserved(word.fragment()) {
+                (TokenKind::Keyword, word)
+            } else {
+                (TokenKind::Identifier, word)
+            }
+        }),
+        kind(TokenKind::QuotedIdentifier, recognize(quoted_identifier)),
+        kind(TokenKind::String, recognize(string_literal)),
+        kind(
+            TokenKind::Parameter,
+            alt((tag("?"), recognize(pair(char('$'), digit1)))),
+        ),
+        kind(
+            TokenKind::Operator,
+            alt((
+                tag("->>"),
+                tag("->"),
+                tag("<="),
+                tag(">="),
+                tag("<>"),
+                tag("!="),
+                recognize(one_of("=<>+-*/%")),
+            )),
+        ),
+        kind(TokenKind::Punctuation, recognize(one_of("()[],.;"))),
+    ))(input)
+}
+
+fn kind<'a>(
+    kind: TokenKind,
+    parser: impl FnMut(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>>,
+) -> impl FnMut(RawSpan<'a>) -> ParseResult<'a, (TokenKind, RawSpan<'a>)> {
+    map(parser, move |span| (kind, span))
+}
+
+fn word(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')(input)
+}
+
+/// Recognizes an unsigned number, `42`, `0xFF`, `1_000`, `1.5`, `.5` or `2e-3`. A leading
+/// sign is an operator of its own.
+fn number(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    terminated(
+        preceded(
+            peek(one_of("0123456789.")),
+            alt((
+                recognize(terminated(integer, not(peek(one_of(".eE"))))),
+                decimal,
+            )),
+        ),
+        not(word),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<(TokenKind, String)> {
+        tokenize(input)
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.span.fragment().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use TokenKind::*;
+        let expected = [
+            (Keyword, "SELECT"),
+            (Identifier, "a"),
+            (Punctuation, "."),
+            (QuotedIdentifier, "\"b c\""),
+            (Punctuation, ","),
+            (Operator, "-"),
+            (Number, "1.5e3"),
+            (Operator, "->>"),
+            (String, "'it''s'"),
+            (Comment, "/* x; */"),
+            (Keyword, "from"),
+            (Identifier, "t"),
+            (Keyword, "WHERE"),
+            (Identifier, "id"),
+            (Operator, "<>"),
+            (Parameter, "$1"),
+            (Keyword, "AND"),
+            (Identifier, "data"),
+            (Operator, "="),
+            (Blob, "x'ff'"),
+            (Keyword, "OR"),
+            (Identifier, "n"),
+            (Keyword, "IN"),
+            (Punctuation, "("),
+            (Number, "0xFF"),
+            (Punctuation, ","),
+            (Parameter, "?"),
+            (Punctuation, ")"),
+            (Punctuation, ";"),
+            (Comment, "-- done"),
+        ]
+        .map(|(kind, text)| (kind, text.to_string()));
+        assert_eq!(
+            kinds(
+                "SELECT a.\"b c\", -1.5e3->>'it''s' /* x; */ from t \
+                 WHERE id <> $1 AND data = x'ff' OR n IN (0xFF, ?); -- done"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_tokenize_is_lossless() {
+        let input = "SELECT 'a' # \"b\" FROM t /* open";
+        let tokens = tokenize(input).collect::<Vec<_>>();
+        let text = tokens
+            .iter()
+            .map(|token| *token.span.fragment())
+            .collect::<String>();
+        assert_eq!(text, input);
+        assert_eq!(
+            tokens
+                .iter()
+                .filter(|token| token.kind == TokenKind::Unknown)
+                .map(|token| *token.span.fragment())
+                .collect::<Vec<_>>(),
+            ["#", "/* open"]
+        );
+        assert!(tokens[0].is_keyword("select"));
+        assert_eq!(tokens.iter().filter(|token| token.is_trivia()).count(), 6);
+        assert_eq!(
+            tokens.last().map(|token| token.span.location_offset()),
+            Some(24)
+        );
+    }
+
+    #[test]
+    fn test_token_cursor() {
+        use crate::{
+            ast::commands::{backup, insert, select, transaction},
+            errors::ErrorReport,
+            parse::Parse,
+        };
+
+        fn message<T>(result: ParseResult<'_, T>) -> Box<str> {
+            let Err(nom::Err::Error(error) | nom::Err::Failure(error)) = result else {
+                panic!("parsed");
+            };
+            ErrorReport::from(error).message
+        }
+        assert_eq!(
+            &*message(backup::Statement::parse("BACKUP VALUES ('x')".into())),
+            "Unexpected keyword VALUES, expected TO"
+        );
+        assert_eq!(
+            &*message(backup::Statement::parse("BACKUP TO /* the path */".into())),
+            "Unexpected end of input, expected a string"
+        );
+        assert_eq!(
+            &*message(transaction::Statement::parse(
+                "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ".into()
+            )),
+            "Unexpected `REPEATABLE`, expected READ COMMITTED or SERIALIZABLE"
+        );
+        assert_eq!(
+            &*message(insert::UnboundStatement::parse(
+                "INSERT INTO users VALUES (1)".into()
+            )),
+            "Unexpected keyword VALUES, expected `(`"
+        );
+        assert_eq!(
+            &*message(select::Statement::parse(
+                "SELECT id FROM users ORDER id".into()
+            )),
+            "Unexpected `id`, expected BY"
+        );
+
+        // The input after the last token read is left to the caller.
+        let mut tokens = TokenCursor::new("begin -- now\n;".into());
+        assert_eq!(tokens.opt_keyword("commit"), None);
+        assert!(tokens.keyword("BEGIN").is_ok());
+        assert_eq!(*tokens.rest().fragment(), " -- now\n;");
+        assert_eq!(
+            tokens.next().map(|token| token.kind),
+            Some(TokenKind::Punctuation)
+        );
+        assert!(tokens.next().is_none());
+    }
+}
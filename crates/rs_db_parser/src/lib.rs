@@ -1,5 +1,15 @@
+#[cfg(any(test, feature = "proptest"))]
+pub mod arbitrary;
 pub mod ast;
+pub mod codec;
+pub mod complete;
+pub mod date;
+pub mod decimal;
 pub mod errors;
+pub mod eval;
+pub mod functions;
+pub mod lexer;
+pub mod like;
 pub mod parse;
 pub mod parsers;
 pub mod value;
@@ -0,0 +1,135 @@
+/// A single element of a compiled `LIKE` pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LikeToken {
+    /// Matches exactly this character.
+    Char(char),
+    /// `_`, matches any single character.
+    AnyChar,
+    /// `%`, matches any sequence of characters, including an empty one.
+    AnyString,
+}
+
+/// A `LIKE` pattern compiled into tokens, so it can be matched many times without
+/// re-reading the escapes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct LikePattern {
+    tokens: Box<[LikeToken]>,
+}
+
+impl LikePattern {
+    /// Compiles a pattern, `\` escapes the next character so `\%` matches a literal `%`.
+    /// Consecutive `%` are merged, they match the same strings as a single one.
+    #[must_use]
+    pub fn new(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '\\' => LikeToken::Char(chars.next().unwrap_or('\\')),
+                '_' => LikeToken::AnyChar,
+                '%' => LikeToken::AnyString,
+                c => LikeToken::Char(c),
+            };
+            if token == LikeToken::AnyString && tokens.last() == Some(&LikeToken::AnyString) {
+                continue;
+            }
+            tokens.push(token);
+        }
+        Self {
+            tokens: tokens.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn tokens(&self) -> &[LikeToken] {
+        &self.tokens
+    }
+
+    /// Checks whether the whole of `input` matches the pattern.
+    #[must_use]
+    pub fn matches(&self, input: &str) -> bool {
+        let input = input.chars().collect::<Vec<_>>();
+        let (mut token, mut position) = (0, 0);
+        // The last `%` seen and the input position it is currently expanded up to, so a
+        // mismatch can backtrack by letting the `%` swallow one more character.
+        let mut backtrack = None;
+
+        while position < input.len() {
+            match self.tokens.get(token) {
+                Some(LikeToken::AnyString) => {
+                    backtrack = Some((token, position));
+                    token += 1;
+                }
+                Some(LikeToken::AnyChar) => {
+                    token += 1;
+                    position += 1;
+                }
+                Some(LikeToken::Char(c)) if *c == input[position] => {
+                    token += 1;
+                    position += 1;
+                }
+                _ => match backtrack {
+                    Some((star, star_position)) => {
+                        backtrack = Some((star, star_position + 1));
+                        token = star + 1;
+                        position = star_position + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        self.tokens[token..]
+            .iter()
+            .all(|token| *token == LikeToken::AnyString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        assert_eq!(
+            LikePattern::new(r"a%%_\%").tokens(),
+            &[
+                LikeToken::Char('a'),
+                LikeToken::AnyString,
+                LikeToken::AnyChar,
+                LikeToken::Char('%'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            ("abc", "abc", true),
+            ("abc", "abd", false),
+            ("a%", "a", true),
+            ("a%", "abc", true),
+            ("a%", "ba", false),
+            ("%c", "abc", true),
+            ("%b%", "abc", true),
+            ("%b%", "ac", false),
+            ("a_c", "abc", true),
+            ("a_c", "ac", false),
+            ("%a%b%c%", "xxaxxbxxcxx", true),
+            ("%ab", "aab", true),
+            ("%", "", true),
+            ("_", "", false),
+            (r"100\%", "100%", true),
+            (r"100\%", "1000", false),
+            ("é_", "éa", true),
+        ];
+        for (pattern, input, expected) in cases {
+            assert_eq!(
+                LikePattern::new(pattern).matches(input),
+                expected,
+                "{input} LIKE {pattern}"
+            );
+        }
+    }
+}
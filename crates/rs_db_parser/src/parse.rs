@@ -1,18 +1,542 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use nom::Finish;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::char,
+    combinator::{eof, map},
+    multi::many0_count,
+    sequence::{preceded, terminated},
+    Finish, Slice,
+};
 use nom_locate::LocatedSpan;
 
 use crate::{
-    ast::commands::create::Column,
-    errors::{FormattedError, ParseResult, RawParseError},
+    ast::{
+        commands::{
+            create::{Column, Constraint, Partitioning, SqlType},
+            select,
+        },
+        statement::Statement,
+    },
+    errors::{
+        format_parse_error, format_spanned_error, FormattedError, ParseResult, RawParseError,
+    },
+    lexer::{TokenKind, Tokens},
+    parsers::{
+        limits::{self, input_limit, limit_exceeded, Limit},
+        multispace0, multispace1, parse_with_span,
+        recovery::with_recovery,
+    },
+    value::Value,
 };
 
-pub type TableMap = HashMap<Box<str>, ColumnMap>;
 pub type ColumnMap = HashMap<Box<str>, Column>;
+/// The tables of one schema.
+pub type SchemaMap = HashMap<Box<str>, ColumnMap>;
+pub type ViewMap = HashMap<Box<str>, View>;
+
+/// A view stored in the catalog. The query is kept as SQL text and re-parsed when the view
+/// is expanded, the columns are resolved once when the view is created so the view can be
+/// used like a table.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct View {
+    pub query: Box<str>,
+    pub columns: ColumnMap,
+}
+
+impl View {
+    /// The columns of the query of the view, resolved against `table_map`, so a table the
+    /// query reads that changed since the view was created is taken into account. `None`
+    /// if the query is no longer valid, like when a column it reads was dropped.
+    #[must_use]
+    pub fn expand(&self, table_map: &TableMap) -> Option<ColumnMap> {
+        let (rest, query) =
+            select::Statement::parse_with_table_map(table_map, RawSpan::new(&self.query)).ok()?;
+        if !rest.fragment().trim().is_empty() {
+            return None;
+        }
+        let columns = query.columns(table_map).ok()?;
+        Some(
+            columns
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        )
+    }
+}
+
+/// What `ANALYZE` found of the rows of a table, which the planner uses to estimate the
+/// rows of a query. They describe the table when it was analyzed, not as it is now.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TableStatistics {
+    pub row_count: u64,
+    /// By column name.
+    pub columns: BTreeMap<Box<str>, ColumnStatistics>,
+}
+
+/// What `ANALYZE` found of the values of a column.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ColumnStatistics {
+    /// The number of `NULL`s.
+    pub nulls: u64,
+    /// An estimate of the number of distinct values, `NULL` aside.
+    pub distinct: u64,
+    /// The smallest and the largest values, `None` if all the values are `NULL` or they
+    /// can't be ordered, like documents.
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+}
+
+impl ColumnStatistics {
+    /// The fraction of the `row_count` rows whose value is `NULL`.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn null_fraction(&self, row_count: u64) -> f64 {
+        if row_count == 0 {
+            0.0
+        } else {
+            self.nulls as f64 / row_count as f64
+        }
+    }
+}
+
+/// The definitions of a [`TableMap`] as [`TableMap::to_json`] writes them. The statistics
+/// aren't kept, `ANALYZE` collects them again, and the versions start again.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Catalog {
+    default_schema: Box<str>,
+    schemas: HashMap<Box<str>, SchemaMap>,
+    types: HashMap<Box<str>, SqlType>,
+    constraints: HashMap<Box<str>, HashMap<Box<str>, Vec<Constraint>>>,
+    partitionings: HashMap<Box<str>, HashMap<Box<str>, Partitioning>>,
+    views: HashMap<Box<str>, ViewMap>,
+}
+
+/// The schema unqualified table names resolve in, unless another one is configured.
+pub const DEFAULT_SCHEMA: &str = "public";
+
+/// The catalog: schemas, their tables and the columns of each table, and the types created
+/// with `CREATE TYPE`, which are shared by all schemas.
+///
+/// Table names without a schema resolve in the default schema, the map-like methods
+/// (`get`, `insert`, `contains_key`) work on it so a single-schema catalog is used like a
+/// plain map of tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableMap {
+    schemas: HashMap<Box<str>, SchemaMap>,
+    types: HashMap<Box<str>, SqlType>,
+    /// By schema, then by table.
+    statistics: HashMap<Box<str>, HashMap<Box<str>, TableStatistics>>,
+    /// By schema, then by table.
+    constraints: HashMap<Box<str>, HashMap<Box<str>, Vec<Constraint>>>,
+    /// By schema, then by table.
+    partitionings: HashMap<Box<str>, HashMap<Box<str>, Partitioning>>,
+    /// By schema, then by view.
+    views: HashMap<Box<str>, ViewMap>,
+    /// The [version](Self::version) of each table, by schema, then by table.
+    versions: HashMap<Box<str>, HashMap<Box<str>, u64>>,
+    /// The number of times a table was added or replaced.
+    changes: u64,
+    default_schema: Box<str>,
+}
+
+impl Default for TableMap {
+    fn default() -> Self {
+        Self::with_default_schema(DEFAULT_SCHEMA)
+    }
+}
+
+impl TableMap {
+    /// An empty catalog with the [`DEFAULT_SCHEMA`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty catalog whose unqualified names resolve in `schema`.
+    #[must_use]
+    pub fn with_default_schema(schema: impl Into<Box<str>>) -> Self {
+        let default_schema = schema.into();
+        Self {
+            schemas: [(default_schema.clone(), SchemaMap::new())].into(),
+            types: HashMap::new(),
+            statistics: HashMap::new(),
+            constraints: HashMap::new(),
+            partitionings: HashMap::new(),
+            views: HashMap::new(),
+            versions: HashMap::new(),
+            changes: 0,
+            default_schema,
+        }
+    }
+
+    #[must_use]
+    pub fn default_schema(&self) -> &str {
+        &self.default_schema
+    }
+
+    /// Changes the schema unqualified names resolve in, creating it when missing.
+    pub fn set_default_schema(&mut self, schema: impl Into<Box<str>>) {
+        let schema = schema.into();
+        self.schemas.entry(schema.clone()).or_default();
+        self.default_schema = schema;
+    }
+
+    /// Creates an empty schema, returns `false` if it already exists.
+    pub fn create_schema(&mut self, schema: impl Into<Box<str>>) -> bool {
+        match self.schemas.entry(schema.into()) {
+            std::collections::hash_map::Entry::Occupied(_) => false,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(SchemaMap::new());
+                true
+            }
+        }
+    }
+
+    /// Adds a type created with `CREATE TYPE`, returns `false` if the name is already taken.
+    pub fn create_type(&mut self, name: impl Into<Box<str>>, tp: SqlType) -> bool {
+        match self.types.entry(name.into()) {
+            std::collections::hash_map::Entry::Occupied(_) => false,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(tp);
+                true
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get_type(&self, name: &str) -> Option<&SqlType> {
+        self.types.get(name)
+    }
+
+    /// The schemas and their tables, in no particular order.
+    pub fn schemas(&self) -> impl Iterator<Item = (&str, &SchemaMap)> {
+        self.schemas
+            .iter()
+            .map(|(name, tables)| (name.as_ref(), tables))
+    }
+
+    #[must_use]
+    pub fn schema(&self, schema: &str) -> Option<&SchemaMap> {
+        self.schemas.get(schema)
+    }
+
+    #[must_use]
+    pub fn contains_schema(&self, schema: &str) -> bool {
+        self.schemas.contains_key(schema)
+    }
+
+    /// The columns of a table, `schema` defaults to the default schema.
+    #[must_use]
+    pub fn get_qualified(&self, schema: Option<&str>, table: &str) -> Option<&ColumnMap> {
+        self.schema(schema.unwrap_or(&self.default_schema))?
+            .get(table)
+    }
+
+    /// Adds a table to `schema`, creating the schema when missing. Returns the columns of
+    /// the table it replaces, whose statistics, constraints and partitioning are dropped.
+    pub fn insert_qualified(
+        &mut self,
+        schema: Option<&str>,
+        table: impl Into<Box<str>>,
+        columns: ColumnMap,
+    ) -> Option<ColumnMap> {
+        let schema = schema.unwrap_or(&self.default_schema);
+        let table = table.into();
+        if let Some(statistics) = self.statistics.get_mut(schema) {
+            statistics.remove(&table);
+        }
+        if let Some(constraints) = self.constraints.get_mut(schema) {
+            constraints.remove(&table);
+        }
+        if let Some(partitionings) = self.partitionings.get_mut(schema) {
+            partitionings.remove(&table);
+        }
+        self.changes += 1;
+        self.versions
+            .entry(schema.into())
+            .or_default()
+            .insert(table.clone(), self.changes);
+        self.schemas
+            .entry(schema.into())
+            .or_default()
+            .insert(table, columns)
+    }
+
+    /// The version of the definition of a table, `schema` defaults to the default schema.
+    /// It changes each time the table, its constraints or its partitioning are replaced,
+    /// and no two tables of the catalog share one, so what was resolved against a table can
+    /// be checked to still be valid.
+    #[must_use]
+    pub fn version(&self, schema: Option<&str>, table: &str) -> Option<u64> {
+        self.versions
+            .get(schema.unwrap_or(&self.default_schema))?
+            .get(table)
+            .copied()
+    }
+
+    /// The statistics `ANALYZE` collected for a table, `schema` defaults to the default
+    /// schema.
+    #[must_use]
+    pub fn statistics(&self, schema: Option<&str>, table: &str) -> Option<&TableStatistics> {
+        self.statistics
+            .get(schema.unwrap_or(&self.default_schema))?
+            .get(table)
+    }
+
+    /// Stores the statistics of a table, replacing the previous ones. Returns `false` if the
+    /// table doesn't exist.
+    pub fn set_statistics(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+        statistics: TableStatistics,
+    ) -> bool {
+        let schema = schema.unwrap_or(&self.default_schema);
+        if self.get_qualified(Some(schema), table).is_none() {
+            return false;
+        }
+        self.statistics
+            .entry(schema.into())
+            .or_default()
+            .insert(table.into(), statistics);
+        true
+    }
+
+    /// The constraints of a table, see [`create::Statement::table_constraints`], `schema`
+    /// defaults to the default schema. A table without any has none.
+    ///
+    /// [`create::Statement::table_constraints`]: crate::ast::commands::create::Statement::table_constraints
+    #[must_use]
+    pub fn constraints(&self, schema: Option<&str>, table: &str) -> &[Constraint] {
+        self.constraints
+            .get(schema.unwrap_or(&self.default_schema))
+            .and_then(|constraints| constraints.get(table))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Stores the constraints of a table, replacing the previous ones, which changes its
+    /// [version](Self::version). Returns `false` if the table doesn't exist.
+    pub fn set_constraints(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+        constraints: Vec<Constraint>,
+    ) -> bool {
+        let schema = schema.unwrap_or(&self.default_schema);
+        if self.get_qualified(Some(schema), table).is_none() {
+            return false;
+        }
+        self.constraints
+            .entry(schema.into())
+            .or_default()
+            .insert(table.into(), constraints);
+        // What was checked against the old constraints must be prepared again.
+        self.changes += 1;
+        self.versions
+            .entry(schema.into())
+            .or_default()
+            .insert(table.into(), self.changes);
+        true
+    }
+
+    /// How the rows of a table are split in partitions, `None` if it isn't partitioned,
+    /// `schema` defaults to the default schema.
+    #[must_use]
+    pub fn partitioning(&self, schema: Option<&str>, table: &str) -> Option<&Partitioning> {
+        self.partitionings
+            .get(schema.unwrap_or(&self.default_schema))?
+            .get(table)
+    }
+
+    /// Stores how the rows of a table are split in partitions, replacing the previous
+    /// partitioning, which changes its [version](Self::version). Returns `false` if the
+    /// table doesn't exist.
+    pub fn set_partitioning(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+        partitioning: Partitioning,
+    ) -> bool {
+        let schema = schema.unwrap_or(&self.default_schema);
+        if self.get_qualified(Some(schema), table).is_none() {
+            return false;
+        }
+        self.partitionings
+            .entry(schema.into())
+            .or_default()
+            .insert(table.into(), partitioning);
+        // What was planned against the old partitions must be prepared again.
+        self.changes += 1;
+        self.versions
+            .entry(schema.into())
+            .or_default()
+            .insert(table.into(), self.changes);
+        true
+    }
+
+    /// A view created with `CREATE VIEW`, `schema` defaults to the default schema.
+    #[must_use]
+    pub fn view(&self, schema: Option<&str>, name: &str) -> Option<&View> {
+        self.views
+            .get(schema.unwrap_or(&self.default_schema))?
+            .get(name)
+    }
+
+    /// Adds a view to `schema`, see [`create_view::Statement::view`]. Returns `false` if
+    /// the schema doesn't exist or the name is already taken by a table or a view.
+    ///
+    /// [`create_view::Statement::view`]: crate::ast::commands::create_view::Statement::view
+    pub fn create_view(
+        &mut self,
+        schema: Option<&str>,
+        name: impl Into<Box<str>>,
+        view: View,
+    ) -> bool {
+        let schema = schema.unwrap_or(&self.default_schema);
+        let name = name.into();
+        if !self.contains_schema(schema)
+            || self.get_qualified(Some(schema), &name).is_some()
+            || self.view(Some(schema), &name).is_some()
+        {
+            return false;
+        }
+        self.views
+            .entry(schema.into())
+            .or_default()
+            .insert(name, view);
+        true
+    }
+
+    /// The catalog as JSON: its schemas, tables, types, constraints, partitionings and
+    /// views, for a backup, read back by [`TableMap::from_json`]. The statistics aren't
+    /// written.
+    /// # Errors
+    /// Returns an error if a default value of a column can't be written, like a `NaN`.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&Catalog {
+            default_schema: self.default_schema.clone(),
+            schemas: self.schemas.clone(),
+            types: self.types.clone(),
+            constraints: self.constraints.clone(),
+            partitionings: self.partitionings.clone(),
+            views: self.views.clone(),
+        })
+    }
+
+    /// Reads a catalog written by [`TableMap::to_json`]. Its tables get new
+    /// [versions](Self::version).
+    /// # Errors
+    /// Returns an error if `json` isn't a catalog.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let catalog = serde_json::from_str::<Catalog>(json)?;
+        let mut table_map = Self::with_default_schema(catalog.default_schema);
+        table_map.types = catalog.types;
+        for (schema, tables) in catalog.schemas {
+            table_map.create_schema(schema.clone());
+            for (table, columns) in tables {
+                table_map.insert_qualified(Some(&schema), table, columns);
+            }
+        }
+        for (schema, tables) in catalog.constraints {
+            for (table, constraints) in tables {
+                table_map.set_constraints(Some(&schema), &table, constraints);
+            }
+        }
+        for (schema, tables) in catalog.partitionings {
+            for (table, partitioning) in tables {
+                table_map.set_partitioning(Some(&schema), &table, partitioning);
+            }
+        }
+        for (schema, views) in catalog.views {
+            for (name, view) in views {
+                table_map.create_view(Some(&schema), name, view);
+            }
+        }
+        Ok(table_map)
+    }
+
+    /// The columns of a table of the default schema.
+    #[must_use]
+    pub fn get(&self, table: &str) -> Option<&ColumnMap> {
+        self.get_qualified(None, table)
+    }
+
+    #[must_use]
+    pub fn contains_key(&self, table: &str) -> bool {
+        self.get(table).is_some()
+    }
+
+    /// Adds a table to the default schema.
+    pub fn insert(&mut self, table: Box<str>, columns: ColumnMap) -> Option<ColumnMap> {
+        self.insert_qualified(None, table, columns)
+    }
+}
+
+impl FromIterator<(Box<str>, ColumnMap)> for TableMap {
+    /// Builds a catalog with the tables in the default schema.
+    fn from_iter<T: IntoIterator<Item = (Box<str>, ColumnMap)>>(iter: T) -> Self {
+        let mut table_map = Self::new();
+        for (table, columns) in iter {
+            table_map.insert(table, columns);
+        }
+        table_map
+    }
+}
+
 pub type RawSpan<'a> = LocatedSpan<&'a str>;
 pub type WithSpan<'a, T> = (RawSpan<'a>, T);
 
+/// The position of a fragment of the input, without borrowing it.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct Span {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    /// Length in bytes.
+    pub len: usize,
+    /// Line of the first byte, starting at 1.
+    pub line: u32,
+}
+
+impl From<RawSpan<'_>> for Span {
+    fn from(span: RawSpan<'_>) -> Self {
+        Self {
+            offset: span.location_offset(),
+            len: span.fragment().len(),
+            line: span.location_line(),
+        }
+    }
+}
+
+/// A copy of a [`RawSpan`], for the names kept by the owned AST.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct OwnedSpan {
+    pub span: Span,
+    pub fragment: Box<str>,
+}
+
+impl From<RawSpan<'_>> for OwnedSpan {
+    fn from(span: RawSpan<'_>) -> Self {
+        Self {
+            span: span.into(),
+            fragment: (*span.fragment()).into(),
+        }
+    }
+}
+
+pub type OwnedWithSpan<T> = (Span, T);
+
+/// Copies the value of a [`WithSpan`] with `f`, keeping the position of its span.
+pub(crate) fn owned_with_span<T, U>(
+    (span, value): &WithSpan<'_, T>,
+    f: impl FnOnce(&T) -> U,
+) -> OwnedWithSpan<U> {
+    ((*span).into(), f(value))
+}
+
 pub trait Parse<'a>: Sized {
     /// Parse the input and return the result.
     /// # Errors
@@ -32,6 +556,11 @@ pub trait Parse<'a>: Sized {
     fn parse_format_error(input: &'a str) -> Result<Self, FormattedError<'a>> {
         parse_format_error(input, Self::parse)
     }
+
+    /// Parse the input, recovering from invalid list items, see [`parse_recovering`].
+    fn parse_recovering(input: &'a str) -> (Option<Self>, Vec<FormattedError<'a>>) {
+        parse_recovering(input, Self::parse)
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -42,8 +571,390 @@ pub fn parse_format_error<'a, F, T>(input: &'a str, f: F) -> Result<T, Formatted
 where
     F: nom::Parser<RawSpan<'a>, T, RawParseError<'a>>,
 {
-    match nom::combinator::all_consuming(f)(RawSpan::new(input)).finish() {
+    match nom::combinator::all_consuming(preceded(input_limit, f))(RawSpan::new(input)).finish() {
         Ok((_, result)) => Ok(result),
-        Err(err) => Err(crate::errors::format_parse_error(input, err)),
+        Err(err) => Err(format_parse_error(input, err)),
+    }
+}
+
+/// Parse the input like [`parse_format_error`], but without stopping at the first invalid
+/// item of a list: the item is reported and skipped up to the next `,`, `)` or `;`, and the
+/// list goes on with the following items.
+///
+/// Returns the value parsed without the skipped items, or `None` if the input still couldn't
+/// be parsed, and every error found, in the order of the input, so all the problems of a
+/// statement can be shown at once.
+pub fn parse_recovering<'a, F, T>(input: &'a str, f: F) -> (Option<T>, Vec<FormattedError<'a>>)
+where
+    F: nom::Parser<RawSpan<'a>, T, RawParseError<'a>>,
+{
+    let (result, recovered) = with_recovery(|| {
+        nom::combinator::all_consuming(preceded(input_limit, f))(RawSpan::new(input)).finish()
+    });
+    let mut errors = recovered
+        .into_iter()
+        .map(|err| format_spanned_error(input, err))
+        .collect::<Vec<_>>();
+    let value = match result {
+        Ok((_, value)) => Some(value),
+        Err(err) => {
+            errors.push(format_parse_error(input, err));
+            None
+        }
+    };
+    // A list tried again by another branch reports its items twice.
+    errors.sort_by_key(FormattedError::offset);
+    errors.dedup_by_key(|err| err.offset());
+    (value, errors)
+}
+
+/// Parse a script made of statements separated by `;`.
+///
+/// Every statement is parsed on its own and returned with its span. When a statement is
+/// invalid its error is reported and parsing resumes after the next `;`, so a single
+/// mistake doesn't hide the problems of the following statements. An input over the
+/// [`ParserConfig`](crate::parsers::limits::ParserConfig) limits ends the script with an
+/// error.
+#[must_use]
+pub fn parse_script<'a>(
+    table_map: &TableMap,
+    input: &'a str,
+) -> Vec<Result<WithSpan<'a, Statement<'a>>, FormattedError<'a>>> {
+    let mut results = Vec::new();
+    let mut rest = RawSpan::new(input);
+    if let Err(nom::Err::Error(err) | nom::Err::Failure(err)) = input_limit(rest) {
+        results.push(Err(format_parse_error(input, err)));
+        return results;
+    }
+    let max_statements = limits::config().max_statements;
+    loop {
+        rest = skip_separators(rest);
+        if rest.fragment().is_empty() {
+            break;
+        }
+        if results.len() == max_statements {
+            if let nom::Err::Error(err) | nom::Err::Failure(err) =
+                limit_exceeded(rest.slice(..0), Limit::Statements, max_statements)
+            {
+                results.push(Err(format_parse_error(input, err)));
+            }
+            break;
+        }
+        match script_statement(table_map, rest).finish() {
+            Ok((next, statement)) => {
+                results.push(Ok(statement));
+                rest = next;
+            }
+            Err(err) => {
+                results.push(Err(format_parse_error(input, err)));
+                rest = skip_statement(rest);
+            }
+        }
+    }
+    results
+}
+
+fn script_statement<'a>(
+    table_map: &TableMap,
+    input: RawSpan<'a>,
+) -> ParseResult<'a, WithSpan<'a, Statement<'a>>> {
+    terminated(
+        |i| parse_with_span(i, |i| Statement::parse_with_table_map(table_map, i)),
+        preceded(multispace0, alt((map(char(';'), |_| ()), map(eof, |_| ())))),
+    )(input)
+}
+
+fn skip_separators(input: RawSpan<'_>) -> RawSpan<'_> {
+    many0_count(alt((multispace1, tag(";"))))(input).map_or(input, |(rest, _)| rest)
+}
+
+/// Skips everything up to and including the next `;` that is not inside a string literal, a
+/// quoted name or a comment.
+fn skip_statement(input: RawSpan<'_>) -> RawSpan<'_> {
+    let mut tokens = Tokens::new(input);
+    tokens
+        .by_ref()
+        .any(|token| token.kind == TokenKind::Punctuation && *token.span.fragment() == ";");
+    tokens.rest()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::commands::create::{ConstraintKind, PartitionScheme, SqlType};
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [Column::new("id", SqlType::I32)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        );
+        table_map
+    }
+
+    #[test]
+    fn test_table_map_schemas() {
+        let mut table_map = get_table_map();
+        assert_eq!(table_map.default_schema(), DEFAULT_SCHEMA);
+        assert!(table_map.get_qualified(Some("public"), "users").is_some());
+        assert!(!table_map.create_schema("public"));
+        assert!(table_map.create_schema("sales"));
+        table_map.insert_qualified(Some("sales"), "orders", ColumnMap::new());
+        assert!(!table_map.contains_key("orders"));
+
+        table_map.set_default_schema("sales");
+        assert!(table_map.contains_key("orders"));
+        assert!(!table_map.contains_key("users"));
+        assert!(table_map.get_qualified(Some("public"), "users").is_some());
+
+        let table_map = TableMap::with_default_schema("main");
+        assert!(table_map.contains_schema("main"));
+        assert!(!table_map.contains_schema(DEFAULT_SCHEMA));
+    }
+
+    #[test]
+    fn test_table_map_statistics() {
+        let mut table_map = get_table_map();
+        let statistics = TableStatistics {
+            row_count: 4,
+            columns: [(
+                "id".into(),
+                ColumnStatistics {
+                    nulls: 1,
+                    distinct: 3,
+                    min: Some(Value::I32(1)),
+                    max: Some(Value::I32(3)),
+                },
+            )]
+            .into(),
+        };
+        assert!(!table_map.set_statistics(None, "orders", statistics.clone()));
+        assert!(table_map.set_statistics(None, "users", statistics.clone()));
+        assert_eq!(
+            table_map.statistics(Some("public"), "users"),
+            Some(&statistics)
+        );
+        assert!((statistics.columns["id"].null_fraction(4) - 0.25).abs() < f64::EPSILON);
+        // New columns make the statistics stale.
+        table_map.insert("users".into(), ColumnMap::new());
+        assert_eq!(table_map.statistics(None, "users"), None);
+    }
+
+    #[test]
+    fn test_table_map_constraints() {
+        let mut table_map = get_table_map();
+        let constraints = vec![Constraint {
+            name: "users_id_not_null".into(),
+            kind: ConstraintKind::NotNull("id".into()),
+        }];
+        assert!(table_map.constraints(None, "users").is_empty());
+        assert!(!table_map.set_constraints(None, "orders", constraints.clone()));
+        assert!(table_map.set_constraints(Some("public"), "users", constraints.clone()));
+        assert_eq!(table_map.constraints(None, "users"), constraints);
+        // They are the ones of the table they were set for.
+        table_map.insert("users".into(), ColumnMap::new());
+        assert!(table_map.constraints(None, "users").is_empty());
+    }
+
+    #[test]
+    fn test_table_map_partitioning() {
+        let mut table_map = get_table_map();
+        let partitioning = Partitioning {
+            column: "id".into(),
+            scheme: PartitionScheme::Hash(4),
+        };
+        assert_eq!(table_map.partitioning(None, "users"), None);
+        assert!(!table_map.set_partitioning(None, "orders", partitioning.clone()));
+        assert!(table_map.set_partitioning(None, "users", partitioning.clone()));
+        assert_eq!(
+            table_map.partitioning(Some("public"), "users"),
+            Some(&partitioning)
+        );
+        table_map.insert("users".into(), ColumnMap::new());
+        assert_eq!(table_map.partitioning(None, "users"), None);
+    }
+
+    #[test]
+    fn test_table_map_json() {
+        let mut table_map = get_table_map();
+        table_map.set_default_schema("sales");
+        table_map.create_schema("empty");
+        table_map.insert_qualified(
+            Some("sales"),
+            "orders",
+            [Column::new("total", SqlType::I64)]
+                .into_iter()
+                .map(|column| (column.name.clone(), column))
+                .collect(),
+        );
+        table_map.create_type("cents", SqlType::I64);
+        let constraints = vec![Constraint {
+            name: "users_id_not_null".into(),
+            kind: ConstraintKind::NotNull("id".into()),
+        }];
+        table_map.set_constraints(Some("public"), "users", constraints.clone());
+        let partitioning = Partitioning {
+            column: "total".into(),
+            scheme: PartitionScheme::Hash(2),
+        };
+        table_map.set_partitioning(None, "orders", partitioning.clone());
+        let view = View {
+            query: "SELECT id FROM public.users".into(),
+            columns: table_map
+                .get_qualified(Some("public"), "users")
+                .unwrap()
+                .clone(),
+        };
+        assert!(table_map.create_view(None, "ids", view.clone()));
+        table_map.set_statistics(None, "orders", TableStatistics::default());
+
+        let read = TableMap::from_json(&table_map.to_json().unwrap()).unwrap();
+        assert_eq!(read.default_schema(), "sales");
+        assert!(read.contains_schema("empty"));
+        for (schema, tables) in table_map.schemas() {
+            assert_eq!(read.schema(schema), Some(tables));
+        }
+        assert_eq!(read.get_type("cents"), Some(&SqlType::I64));
+        assert_eq!(read.constraints(Some("public"), "users"), constraints);
+        assert_eq!(read.partitioning(None, "orders"), Some(&partitioning));
+        assert_eq!(read.view(None, "ids"), Some(&view));
+        assert_eq!(read.statistics(None, "orders"), None);
+        assert!(read.version(None, "orders").is_some());
+        assert!(TableMap::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_table_map_versions() {
+        let mut table_map = get_table_map();
+        let users = table_map.version(None, "users").unwrap();
+        assert_eq!(table_map.version(Some("public"), "users"), Some(users));
+        assert_eq!(table_map.version(None, "orders"), None);
+        table_map.insert_qualified(Some("sales"), "users", ColumnMap::new());
+        let sales = table_map.version(Some("sales"), "users").unwrap();
+        assert_ne!(sales, users);
+        assert_eq!(table_map.version(None, "users"), Some(users));
+        // Statistics don't change the definition of the table, replacing it does.
+        table_map.set_statistics(None, "users", TableStatistics::default());
+        assert_eq!(table_map.version(None, "users"), Some(users));
+        table_map.insert("users".into(), ColumnMap::new());
+        let replaced = table_map.version(None, "users").unwrap();
+        assert!(replaced != users && replaced != sales);
+        assert!(table_map.set_constraints(None, "users", Vec::new()));
+        let constrained = table_map.version(None, "users").unwrap();
+        assert!(constrained > replaced);
+        table_map.set_partitioning(
+            None,
+            "users",
+            Partitioning {
+                column: "id".into(),
+                scheme: PartitionScheme::Hash(2),
+            },
+        );
+        assert!(table_map.version(None, "users").unwrap() > constrained);
+        assert!(!table_map.set_constraints(None, "orders", Vec::new()));
+        assert_eq!(table_map.version(None, "orders"), None);
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let table_map = get_table_map();
+        let input = "
+            CREATE TABLE t (id int8);
+            INSERT INTO users (id) VALUES (1);;
+            SELECT * FROM users
+        ";
+        let results = parse_script(&table_map, input);
+        assert_eq!(results.len(), 3);
+        let spans = results
+            .iter()
+            .map(|result| result.as_ref().unwrap().0.fragment().trim())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                "CREATE TABLE t (id int8)",
+                "INSERT INTO users (id) VALUES (1)",
+                "SELECT * FROM users"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_script_recovers() {
+        let table_map = get_table_map();
+        let input = "INSERT INTO users (id) VALUES ('a;b'); DROP TABLE users; SELEC * FROM \"a;b\"";
+        let results = parse_script(&table_map, input);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_err());
+        assert!(matches!(results[1], Ok((_, Statement::Drop(_)))));
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_parse_script_comments() {
+        let table_map = get_table_map();
+        let input = "
+            -- create a table; not a statement
+            CREATE TABLE t (id int8); /* a ; b */
+            INSERT INTO users (id) /* nested /* comment; */ ; */ VALUES ('a;b') -- trailing
+            ;
+            SELEC 1 /* ; */ -- ;
+            ; SELECT * FROM users
+        ";
+        let results = parse_script(&table_map, input);
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[0], Ok((_, Statement::Create(_)))));
+        assert!(results[1].is_err());
+        assert!(results[2].is_err());
+        assert!(matches!(results[3], Ok((_, Statement::Select(_)))));
+        assert!(parse_script(&table_map, "SELECT 1 /* unterminated")[0].is_err());
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let table_map = get_table_map();
+        let input = "CREATE TABLE t (id int7, name varchar(10), age int9, PRIMARY KEY (name))";
+        let (statement, errors) =
+            parse_recovering(input, |i| Statement::parse_with_table_map(&table_map, i));
+        assert_eq!(
+            errors
+                .iter()
+                .map(FormattedError::offset)
+                .collect::<Vec<_>>(),
+            [19, 47]
+        );
+        let Some(Statement::Create(create)) = statement else {
+            panic!("Expected a partial statement, got {statement:?}");
+        };
+        assert_eq!(create.columns.len(), 1);
+        assert_eq!(*create.columns[0].name.fragment(), "name");
+        assert_eq!(create.constraints.len(), 1);
+
+        let (statement, errors) =
+            parse_recovering("INSERT INTO users (id) VALUES (1), ('a'), (3)", |i| {
+                Statement::parse_with_table_map(&table_map, i)
+            });
+        let Some(Statement::Insert(insert)) = statement else {
+            panic!("Expected a partial statement, got {statement:?}");
+        };
+        assert_eq!(insert.rows.len(), 2);
+        assert_eq!(
+            errors
+                .iter()
+                .map(FormattedError::offset)
+                .collect::<Vec<_>>(),
+            [36]
+        );
+
+        let (statement, errors) = parse_recovering("SELECT id FROM users", |i| {
+            Statement::parse_with_table_map(&table_map, i)
+        });
+        assert!(statement.is_some());
+        assert!(errors.is_empty());
+        assert!(Statement::parse_format_error("CREATE TABLE t (id int7, a int8)").is_err());
     }
 }
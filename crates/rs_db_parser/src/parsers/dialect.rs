@@ -0,0 +1,239 @@
+use std::cell::Cell;
+
+use nom::{
+    branch::alt,
+    combinator::{map, opt},
+    sequence::{pair, preceded, tuple},
+};
+
+use crate::{
+    ast::commands::create::SqlType,
+    errors::{custom_error, ParseResult},
+    parse::RawSpan,
+    parsers::{
+        keyword, multispace1,
+        string::{with_escape_mode, EscapeMode},
+    },
+};
+
+/// The SQL engine the input is written for. It decides the type names, how names are
+/// quoted, the `LIMIT` syntax and how string literals are escaped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// The types of this crate, like `INT32` and `UINT8`, names quoted with double quotes or
+    /// backticks, and standard strings.
+    #[default]
+    Generic,
+    /// `INT2`, `INT4` and `INT8` are 16, 32 and 64 bits wide, names are quoted with double
+    /// quotes.
+    Postgres,
+    /// `TINYINT` to `BIGINT`, optionally `UNSIGNED`, and `DATETIME`, names are quoted with
+    /// backticks, strings have backslash escapes and `LIMIT skip, count` is accepted.
+    MySql,
+    /// `INTEGER` is 64 bits wide and `REAL` is a double, names are quoted with double quotes
+    /// or backticks and `LIMIT skip, count` is accepted.
+    Sqlite,
+    /// The standard type names, names are quoted with double quotes.
+    Ansi,
+}
+
+impl Dialect {
+    /// The characters names can be quoted with.
+    #[must_use]
+    pub const fn identifier_quotes(self) -> &'static [char] {
+        match self {
+            Self::Generic | Self::Sqlite => &['"', '`'],
+            Self::Postgres | Self::Ansi => &['"'],
+            Self::MySql => &['`'],
+        }
+    }
+
+    /// How backslashes in string literals are read.
+    #[must_use]
+    pub const fn escape_mode(self) -> EscapeMode {
+        match self {
+            Self::MySql => EscapeMode::Backslash,
+            Self::Generic | Self::Postgres | Self::Sqlite | Self::Ansi => EscapeMode::Standard,
+        }
+    }
+
+    /// Whether `LIMIT skip, count` is accepted besides `LIMIT count OFFSET skip`.
+    #[must_use]
+    pub const fn accepts_comma_limit(self) -> bool {
+        matches!(self, Self::MySql | Self::Sqlite)
+    }
+}
+
+thread_local! {
+    static DIALECT: Cell<Dialect> = const { Cell::new(Dialect::Generic) };
+}
+
+/// Restores the previous dialect when dropped, even if the parser panics.
+struct DialectGuard(Dialect);
+
+impl Drop for DialectGuard {
+    fn drop(&mut self) {
+        DIALECT.set(self.0);
+    }
+}
+
+/// Runs `f` with the input parsed as written for `dialect` on the current thread, the
+/// dialect is [`Dialect::Generic`] otherwise. String literals are read with the
+/// [`Dialect::escape_mode`], unless `f` sets another one.
+pub fn with_dialect<T>(dialect: Dialect, f: impl FnOnce() -> T) -> T {
+    let _guard = DialectGuard(DIALECT.replace(dialect));
+    with_escape_mode(dialect.escape_mode(), f)
+}
+
+#[must_use]
+pub fn dialect() -> Dialect {
+    DIALECT.get()
+}
+
+/// Parses the type names of the current dialect that the standard names of
+/// [`SqlType::parse`] don't cover, or that mean another type there.
+pub(crate) fn dialect_type(input: RawSpan<'_>) -> ParseResult<'_, SqlType> {
+    let double_precision = || {
+        map(
+            tuple((keyword("double"), multispace1, keyword("precision"))),
+            |(double, _, _)| double,
+        )
+    };
+    match dialect() {
+        Dialect::Generic => Err(custom_error(
+            input,
+            nom_supreme::error::BaseErrorKind::Kind(nom::error::ErrorKind::Alt),
+        )),
+        Dialect::Postgres => alt((
+            map(keyword("int2"), |_| SqlType::I16),
+            map(keyword("int4"), |_| SqlType::I32),
+            map(keyword("int8"), |_| SqlType::I64),
+            map(alt((keyword("float4"), keyword("real"))), |_| SqlType::F32),
+            map(alt((keyword("float8"), double_precision())), |_| {
+                SqlType::F64
+            }),
+        ))(input),
+        Dialect::MySql => {
+            let unsigned = || opt(preceded(multispace1, keyword("unsigned")));
+            let integer = |word, signed: SqlType, unsigned_tp: SqlType| {
+                map(pair(keyword(word), unsigned()), move |(_, unsigned)| {
+                    if unsigned.is_some() {
+                        unsigned_tp.clone()
+                    } else {
+                        signed.clone()
+                    }
+                })
+            };
+            alt((
+                integer("tinyint", SqlType::I8, SqlType::U8),
+                integer("smallint", SqlType::I16, SqlType::U16),
+                integer("mediumint", SqlType::I32, SqlType::U32),
+                integer("integer", SqlType::I32, SqlType::U32),
+                integer("int", SqlType::I32, SqlType::U32),
+                integer("bigint", SqlType::I64, SqlType::U64),
+                map(keyword("float"), |_| SqlType::F32),
+                map(keyword("double"), |_| SqlType::F64),
+                map(keyword("datetime"), |_| SqlType::Timestamp),
+            ))(input)
+        }
+        Dialect::Sqlite => alt((
+            map(keyword("integer"), |_| SqlType::I64),
+            map(keyword("real"), |_| SqlType::F64),
+        ))(input),
+        Dialect::Ansi => alt((
+            map(keyword("real"), |_| SqlType::F32),
+            map(alt((keyword("float"), double_precision())), |_| {
+                SqlType::F64
+            }),
+        ))(input),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::{
+        ast::{
+            commands::{create::LengthUnit, select},
+            statement::Statement,
+        },
+        parse::{Parse, RawSpan},
+        value::Value,
+    };
+
+    use super::*;
+
+    fn parse_type(dialect: Dialect, input: &str) -> Option<SqlType> {
+        with_dialect(dialect, || SqlType::parse(RawSpan::new(input)).ok()).map(|(_, tp)| tp)
+    }
+
+    #[test]
+    fn test_dialect_types() {
+        let cases = [
+            (Dialect::Generic, "int8", Some(SqlType::I8)),
+            (Dialect::Postgres, "int8", Some(SqlType::I64)),
+            (Dialect::Postgres, "double precision", Some(SqlType::F64)),
+            (Dialect::Postgres, "uint8", Some(SqlType::U8)),
+            (Dialect::MySql, "TINYINT UNSIGNED", Some(SqlType::U8)),
+            (Dialect::MySql, "bigint", Some(SqlType::I64)),
+            (Dialect::MySql, "datetime", Some(SqlType::Timestamp)),
+            (Dialect::Sqlite, "INTEGER", Some(SqlType::I64)),
+            (Dialect::Sqlite, "real", Some(SqlType::F64)),
+            (Dialect::Ansi, "integer", Some(SqlType::I32)),
+            (Dialect::Ansi, "REAL", Some(SqlType::F32)),
+            (Dialect::Ansi, "datetime", None),
+        ];
+        for (dialect, input, expected) in cases {
+            assert_eq!(parse_type(dialect, input), expected, "{dialect:?} {input}");
+        }
+        assert_eq!(
+            parse_type(Dialect::Postgres, "text"),
+            Some(SqlType::VarChar(usize::MAX, LengthUnit::Chars))
+        );
+    }
+
+    #[test]
+    fn test_dialect_syntax() {
+        let parses = |dialect, input| {
+            with_dialect(dialect, || Statement::parse(RawSpan::new(input)).is_ok())
+        };
+        assert!(parses(Dialect::Generic, "SELECT \"a\", `b` FROM t"));
+        assert!(!parses(Dialect::Postgres, "SELECT `b` FROM t"));
+        assert!(!parses(Dialect::MySql, "SELECT \"a\" FROM t"));
+        assert!(parses(Dialect::MySql, "SELECT `b` FROM t"));
+
+        let limit = |dialect| {
+            with_dialect(dialect, || {
+                select::Statement::parse(RawSpan::new("SELECT a FROM t LIMIT 5, 10")).ok()
+            })
+            .map(|(_, statement)| {
+                (
+                    statement.limit.map(|(_, n)| n),
+                    statement.offset.map(|(_, n)| n),
+                )
+            })
+        };
+        assert_eq!(limit(Dialect::Sqlite), Some((Some(10), Some(5))));
+        assert_eq!(limit(Dialect::MySql), Some((Some(10), Some(5))));
+        if !cfg!(feature = "mysql") {
+            assert_eq!(
+                limit(Dialect::Postgres).and_then(|(limit, _)| limit),
+                Some(5)
+            );
+        }
+
+        let string = |dialect| {
+            with_dialect(dialect, || Value::parse(RawSpan::new(r"'a\'b'")).ok())
+                .map(|(rest, value)| (*rest.fragment(), value))
+        };
+        assert_eq!(
+            string(Dialect::MySql),
+            Some(("", Value::VarChar("a'b".into())))
+        );
+        assert_eq!(
+            string(Dialect::Ansi),
+            Some(("b'", Value::VarChar(r"a\".into())))
+        );
+        assert_eq!(dialect(), Dialect::Generic, "the dialect is restored");
+    }
+}
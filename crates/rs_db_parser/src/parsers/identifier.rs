@@ -1,10 +1,192 @@
-use nom::{bytes::complete::take_while_m_n, combinator::map};
+use std::cell::Cell;
 
-use crate::{errors::ParseResult, parse::RawSpan};
+use nom::{
+    branch::alt,
+    bytes::complete::{take_while1, take_while_m_n},
+    character::complete::char,
+    combinator::{cut, recognize, verify},
+    multi::many0,
+    sequence::{delimited, pair},
+};
 
-pub(crate) fn identifier(input: RawSpan<'_>) -> ParseResult<RawSpan<'_>> {
-    map(
-        take_while_m_n(0, 128, |c: char| c.is_ascii_alphanumeric() || c == '_'),
-        |s: RawSpan| s,
-    )(input)
+use crate::{
+    errors::{custom_error, ParseError, ParseResult},
+    parse::RawSpan,
+    parsers::dialect::dialect,
+};
+
+/// The longest name, in bytes.
+const MAX_LENGTH: usize = 128;
+
+/// Words that start or separate clauses, they can only be used as names when quoted. Sorted,
+/// lowercase.
+pub const RESERVED_KEYWORDS: &[&str] = &[
+    "all",
+    "and",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "case",
+    "cast",
+    "check",
+    "constraint",
+    "create",
+    "cross",
+    "default",
+    "desc",
+    "distinct",
+    "drop",
+    "else",
+    "end",
+    "exists",
+    "false",
+    "foreign",
+    "from",
+    "full",
+    "group",
+    "having",
+    "in",
+    "inner",
+    "insert",
+    "into",
+    "is",
+    "join",
+    "left",
+    "like",
+    "limit",
+    "not",
+    "null",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "outer",
+    "primary",
+    "references",
+    "right",
+    "select",
+    "table",
+    "then",
+    "true",
+    "union",
+    "unique",
+    "using",
+    "values",
+    "when",
+    "where",
+    "with",
+];
+
+/// How unquoted names that are [`RESERVED_KEYWORDS`] are treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KeywordMode {
+    /// Reserved keywords are rejected, they must be quoted to be used as names.
+    #[default]
+    Strict,
+    /// Any word is a name where a name is expected, like before keywords were reserved.
+    Lenient,
+}
+
+thread_local! {
+    static KEYWORD_MODE: Cell<KeywordMode> = const { Cell::new(KeywordMode::Strict) };
+}
+
+/// Restores the previous mode when dropped, even if the parser panics.
+struct ModeGuard(KeywordMode);
+
+impl Drop for ModeGuard {
+    fn drop(&mut self) {
+        KEYWORD_MODE.set(self.0);
+    }
+}
+
+/// Runs `f` with names parsed in `mode` on the current thread, the mode is
+/// [`KeywordMode::Strict`] otherwise.
+pub fn with_keyword_mode<T>(mode: KeywordMode, f: impl FnOnce() -> T) -> T {
+    let _guard = ModeGuard(KEYWORD_MODE.replace(mode));
+    f()
+}
+
+/// Whether `word` is one of the [`RESERVED_KEYWORDS`], in any case.
+#[must_use]
+pub fn is_reserved(word: &str) -> bool {
+    RESERVED_KEYWORDS
+        .binary_search(&word.to_ascii_lowercase().as_str())
+        .is_ok()
+}
+
+/// Parses a name: ASCII letters, digits and `_`, or any text between the quotes of the
+/// current [`Dialect`](crate::parsers::dialect::Dialect), double quotes or backticks by default, where the quote is written twice to be part of the name. The span of a quoted
+/// name excludes the quotes and keeps doubled quotes as written, so it still points into the
+/// input and `"users"` names the same table as `users`. Unquoted reserved keywords are
+/// rejected, see [`KeywordMode`].
+pub(crate) fn identifier(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    alt((quoted_identifier, unquoted_identifier))(input)
+}
+
+/// Parses a name between the quotes of the current
+/// [`Dialect`](crate::parsers::dialect::Dialect).
+pub(crate) fn quoted_identifier(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    let quotes = dialect().identifier_quotes();
+    let quote = quotes
+        .iter()
+        .copied()
+        .find(|quote| input.fragment().starts_with(*quote))
+        .unwrap_or(quotes[0]);
+    quoted(quote)(input)
+}
+
+fn unquoted_identifier(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    let (rest, name) = take_while_m_n(1, MAX_LENGTH, |c: char| {
+        c.is_ascii_alphanumeric() || c == '_'
+    })(input)?;
+    if KEYWORD_MODE.get() == KeywordMode::Strict && is_reserved(name.fragment()) {
+        return Err(custom_error(
+            name,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::ReservedKeyword)),
+        ));
+    }
+    Ok((rest, name))
+}
+
+/// Parses a name between `quote`s, it can't be empty.
+fn quoted<'a>(quote: char) -> impl FnMut(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>> {
+    delimited(
+        char(quote),
+        cut(verify(
+            recognize(many0(alt((
+                take_while1(move |c| c != quote),
+                recognize(pair(char(quote), char(quote))),
+            )))),
+            |name: &RawSpan| (1..=MAX_LENGTH).contains(&name.len()),
+        )),
+        cut(char(quote)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_keywords() {
+        assert!(RESERVED_KEYWORDS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(is_reserved("VALUES"));
+        assert!(!is_reserved("users"));
+
+        let parse = |input: &'static str| {
+            identifier(input.into()).map(|(_, name)| name.fragment().to_string())
+        };
+        assert!(parse("values").is_err());
+        assert_eq!(parse("\"values\"").ok().as_deref(), Some("values"));
+        assert_eq!(parse("values_1").ok().as_deref(), Some("values_1"));
+        assert_eq!(
+            with_keyword_mode(KeywordMode::Lenient, || parse("values"))
+                .ok()
+                .as_deref(),
+            Some("values")
+        );
+        assert!(parse("values").is_err(), "the mode is restored");
+    }
 }
@@ -0,0 +1,257 @@
+use std::cell::Cell;
+
+use nom::Slice;
+
+use crate::{
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    parse::RawSpan,
+};
+
+/// Bounds on the input the parsers accept, so hostile input is rejected with
+/// [`ParseError::LimitExceeded`] instead of overflowing the stack or allocating without end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParserConfig {
+    /// How deeply expressions can nest, counting parentheses, operators and function calls.
+    pub max_depth: usize,
+    /// The longest input, in bytes.
+    pub max_input_len: usize,
+    /// The most values an `INSERT` can hold, over all its rows.
+    pub max_insert_values: usize,
+    /// The most statements a script can hold.
+    pub max_statements: usize,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_input_len: 16 * 1024 * 1024,
+            max_insert_values: 1_000_000,
+            max_statements: 10_000,
+        }
+    }
+}
+
+/// Which bound of the [`ParserConfig`] the input exceeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Depth,
+    InputLength,
+    InsertValues,
+    Statements,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Depth => "nesting depth",
+            Self::InputLength => "input length",
+            Self::InsertValues => "values per insert",
+            Self::Statements => "statement count",
+        })
+    }
+}
+
+thread_local! {
+    static CONFIG: Cell<ParserConfig> = Cell::new(ParserConfig::default());
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Restores the previous configuration when dropped, even if the parser panics.
+struct ConfigGuard(ParserConfig);
+
+impl Drop for ConfigGuard {
+    fn drop(&mut self) {
+        CONFIG.set(self.0);
+    }
+}
+
+/// Runs `f` with the input bounded by `config` on the current thread, the bounds are
+/// [`ParserConfig::default`] otherwise.
+pub fn with_parser_config<T>(config: ParserConfig, f: impl FnOnce() -> T) -> T {
+    let _guard = ConfigGuard(CONFIG.replace(config));
+    f()
+}
+
+pub(crate) fn config() -> ParserConfig {
+    CONFIG.get()
+}
+
+/// A [`ParseError::LimitExceeded`] pointing at `span`.
+pub(crate) fn limit_exceeded(
+    span: RawSpan<'_>,
+    limit: Limit,
+    max: usize,
+) -> nom::Err<RawParseError<'_>> {
+    custom_failure(
+        span,
+        nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::LimitExceeded {
+            limit,
+            max,
+        })),
+    )
+}
+
+/// Checks the length of a whole input without consuming it, pointing at the bytes past the
+/// limit.
+pub(crate) fn input_limit(input: RawSpan<'_>) -> ParseResult<'_, ()> {
+    let max = config().max_input_len;
+    let text = input.fragment();
+    if text.len() <= max {
+        return Ok((input, ()));
+    }
+    let end = (max..text.len())
+        .find(|end| text.is_char_boundary(*end))
+        .unwrap_or(text.len());
+    Err(limit_exceeded(input.slice(end..), Limit::InputLength, max))
+}
+
+/// Counts a level of nesting while alive, see [`DepthGuard::enter`].
+pub(crate) struct DepthGuard(());
+
+impl DepthGuard {
+    /// Enters one more level of nesting at `input`, failing once
+    /// [`ParserConfig::max_depth`] levels are already entered.
+    pub(crate) fn enter(input: RawSpan<'_>) -> Result<Self, nom::Err<RawParseError<'_>>> {
+        let max = config().max_depth;
+        let depth = DEPTH.get();
+        if depth >= max {
+            return Err(limit_exceeded(input.slice(..0), Limit::Depth, max));
+        }
+        DEPTH.set(depth + 1);
+        Ok(Self(()))
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.set(DEPTH.get() - 1);
+    }
+}
+
+/// Checks the number of values of an `INSERT` after a row of `span`.
+pub(crate) fn check_insert_values(
+    span: RawSpan<'_>,
+    values: usize,
+) -> Result<(), nom::Err<RawParseError<'_>>> {
+    let max = config().max_insert_values;
+    if values > max {
+        return Err(limit_exceeded(span, Limit::InsertValues, max));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use crate::{
+        ast::{commands::create::Column, commands::create::SqlType, statement::Statement},
+        parse::{parse_format_error, parse_script, TableMap},
+    };
+
+    use super::*;
+
+    fn get_table_map() -> TableMap {
+        let mut table_map = TableMap::new();
+        table_map.insert(
+            "users".into(),
+            [("id".into(), Column::new("id", SqlType::I32))].into(),
+        );
+        table_map
+    }
+
+    fn limit(input: &str) -> Option<Limit> {
+        let table_map = get_table_map();
+        let report = parse_format_error(input, |i| Statement::parse_with_table_map(&table_map, i))
+            .err()?
+            .to_report();
+        Limit::from_message(&report.message)
+    }
+
+    impl Limit {
+        fn from_message(message: &str) -> Option<Self> {
+            [
+                Self::Depth,
+                Self::InputLength,
+                Self::InsertValues,
+                Self::Statements,
+            ]
+            .into_iter()
+            .find(|limit| message.contains(&limit.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_depth() {
+        let nested = |depth| {
+            format!(
+                "SELECT {}1{} FROM users",
+                "(".repeat(depth),
+                ")".repeat(depth)
+            )
+        };
+        assert_eq!(limit(&nested(60)), None);
+        assert_eq!(limit(&nested(10_000)), Some(Limit::Depth));
+        assert_eq!(
+            limit(&format!("SELECT {}1 FROM users", "- ".repeat(10_000))),
+            Some(Limit::Depth)
+        );
+        assert_eq!(
+            limit(&format!("SELECT 1 FROM {}users", "(".repeat(10_000))),
+            Some(Limit::Depth)
+        );
+        assert_eq!(limit(&"EXPLAIN ".repeat(5000)), Some(Limit::Depth));
+        assert_eq!(limit("EXPLAIN EXPLAIN SELECT 1 FROM users"), None);
+        let config = ParserConfig {
+            max_depth: 8,
+            ..ParserConfig::default()
+        };
+        with_parser_config(config, || {
+            assert_eq!(limit(&nested(10)), Some(Limit::Depth));
+        });
+        assert_eq!(limit(&nested(10)), None, "the config is restored");
+    }
+
+    #[test]
+    fn test_input_and_insert_limits() {
+        let config = ParserConfig {
+            max_input_len: 40,
+            max_insert_values: 2,
+            max_statements: 2,
+            ..ParserConfig::default()
+        };
+        with_parser_config(config, || {
+            assert_eq!(
+                limit("SELECT id FROM users WHERE id = 1 OR id = 2"),
+                Some(Limit::InputLength)
+            );
+            assert_eq!(limit("INSERT INTO users (id) VALUES (1), (2)"), None);
+            assert_eq!(
+                limit("INSERT INTO users (id) VALUES (1), (2), (3)"),
+                Some(Limit::InputLength)
+            );
+        });
+        let config = ParserConfig {
+            max_insert_values: 2,
+            max_statements: 2,
+            ..ParserConfig::default()
+        };
+        with_parser_config(config, || {
+            assert_eq!(
+                limit("INSERT INTO users (id) VALUES (1), (2), (3)"),
+                Some(Limit::InsertValues)
+            );
+            let table_map = get_table_map();
+            let results = parse_script(
+                &table_map,
+                "SHOW TABLES; SHOW TABLES; SHOW TABLES; SHOW TABLES",
+            );
+            assert_eq!(results.len(), 3);
+            assert!(results[..2].iter().all(Result::is_ok));
+            assert_eq!(
+                Limit::from_message(&results[2].as_ref().unwrap_err().to_report().message),
+                Some(Limit::Statements)
+            );
+        });
+    }
+}
@@ -1,46 +1,130 @@
 use nom::{
-    character::complete::{char, multispace0},
-    multi::separated_list1,
-    sequence::delimited,
-    IResult,
+    branch::alt,
+    bytes::complete::{is_not, tag, take_till},
+    character::complete::{anychar, char, satisfy},
+    combinator::{cut, not, peek, recognize},
+    multi::{many0_count, many1_count, separated_list1},
+    sequence::{delimited, pair, terminated},
 };
 use nom_locate::LocatedSpan;
+use nom_supreme::tag::complete::tag_no_case;
 
 use crate::{
-    errors::ParseResult,
+    errors::{ParseResult, RawParseError},
     parse::{RawSpan, WithSpan},
 };
 
+pub mod dialect;
 pub mod identifier;
+pub mod limits;
 pub mod number;
+pub(crate) mod recovery;
 pub mod row;
+pub mod string;
 
-pub(crate) fn comma_sep<'a, O, E, F>(
-    f: F,
-) -> impl FnMut(RawSpan<'a>) -> IResult<RawSpan<'a>, Vec<O>, E>
+/// Parses a comment, `-- ...` up to the end of the line or `/* ... */`. Block comments nest,
+/// an unterminated one is an error.
+pub(crate) fn comment(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    alt((
+        recognize(pair(tag("--"), take_till(|c| c == '\n'))),
+        block_comment,
+    ))(input)
+}
+
+fn block_comment(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    recognize(delimited(
+        tag("/*"),
+        many0_count(alt((
+            block_comment,
+            is_not("*/"),
+            recognize(pair(not(tag("*/")), anychar)),
+        ))),
+        cut(tag("*/")),
+    ))(input)
+}
+
+/// Skips whitespace and comments, like [`nom::character::complete::multispace0`].
+pub(crate) fn multispace0(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    recognize(many0_count(alt((
+        nom::character::complete::multispace1,
+        comment,
+    ))))(input)
+}
+
+/// Skips whitespace and comments, at least one of them, like
+/// [`nom::character::complete::multispace1`].
+pub(crate) fn multispace1(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    recognize(many1_count(alt((
+        nom::character::complete::multispace1,
+        comment,
+    ))))(input)
+}
+
+/// Parses a list of `f` separated by commas. While recovering, see
+/// [`crate::parse::parse_recovering`], an invalid item is reported and skipped up to the next
+/// `,`, `)` or `;`, so the list keeps the valid items.
+pub(crate) fn comma_sep<'a, O, F>(mut f: F) -> impl FnMut(RawSpan<'a>) -> ParseResult<'a, Vec<O>>
 where
-    F: nom::Parser<RawSpan<'a>, O, E>,
-    E: nom::error::ParseError<RawSpan<'a>>,
+    F: nom::Parser<RawSpan<'a>, O, RawParseError<'a>>,
 {
-    delimited(
-        multispace0,
-        separated_list1(delimited(multispace0, char(','), multispace0), f),
-        multispace0,
-    )
+    move |input| {
+        if !recovery::is_recovering() {
+            return delimited(
+                multispace0,
+                separated_list1(delimited(multispace0, char(','), multispace0), |i| {
+                    f.parse(i)
+                }),
+                multispace0,
+            )(input);
+        }
+        let (mut input, _) = multispace0(input)?;
+        let mut items = Vec::new();
+        let mut after_comma = false;
+        loop {
+            let recorded = recovery::recorded();
+            match f.parse(input) {
+                Ok((rest, item)) => {
+                    items.push(item);
+                    input = rest;
+                }
+                // Without a comma before it the first item may just be missing, like the
+                // arguments of `count()`, let the caller decide.
+                Err(nom::Err::Error(err)) if !after_comma => {
+                    return Err(nom::Err::Error(err));
+                }
+                Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+                    // An item whose own lists were recovered fails because of them, its error
+                    // would only repeat theirs.
+                    if recovery::recorded() == recorded {
+                        recovery::record(err);
+                    }
+                    input = recovery::skip_item(input);
+                }
+                Err(err @ nom::Err::Incomplete(_)) => return Err(err),
+            }
+            match delimited(multispace0, char::<_, RawParseError<'a>>(','), multispace0)(input) {
+                Ok((rest, _)) => {
+                    input = rest;
+                    after_comma = true;
+                }
+                Err(_) => break,
+            }
+        }
+        multispace0(input).map(|(input, _)| (input, items))
+    }
 }
 
-pub(crate) fn map_raw_span<'a, T: 'a>(
-    span: RawSpan<'a>,
-    f: impl FnOnce(&'a str) -> T,
-) -> LocatedSpan<T> {
-    unsafe {
-        LocatedSpan::new_from_raw_offset(
-            span.location_offset(),
-            span.location_line(),
-            f(span.fragment()),
-            (),
-        )
-    }
+/// Matches `word` case-insensitively, making sure it is not just the prefix of a longer
+/// identifier (`null` must not match the start of `nullable`).
+pub(crate) fn keyword<'a>(
+    word: &'static str,
+) -> impl FnMut(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>> {
+    terminated(
+        tag_no_case(word),
+        not(peek(satisfy(|c: char| {
+            c.is_ascii_alphanumeric() || c == '_'
+        }))),
+    )
 }
 
 pub(crate) fn truncate_raw_span<'a>(first: &RawSpan<'a>, second: &RawSpan<'a>) -> RawSpan<'a> {
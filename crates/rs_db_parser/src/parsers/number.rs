@@ -1,6 +1,10 @@
 use nom::{
+    branch::alt,
     bytes::complete::take_while1,
-    combinator::{map, map_res},
+    character::complete::{char, digit0, digit1, hex_digit1, oct_digit1, one_of},
+    combinator::{map_opt, map_res, opt, recognize, success, value, verify},
+    multi::many0_count,
+    sequence::{pair, preceded, tuple},
 };
 
 use crate::{
@@ -13,7 +17,7 @@ macro_rules! impl_parse_number {
         $(
             impl<'a> Parse<'a> for $ty {
                 fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
-                    map_res(parse_int, |s| s.parse())(input)
+                    map_opt(integer, Integer::get)(input)
                 }
             }
         )*
@@ -25,9 +29,130 @@ macro_rules! impl_parse_number {
 
 impl_parse_number!(usize, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
 
-fn parse_int(input: RawSpan) -> ParseResult<&str> {
-    map(
-        take_while1(|c: char| c.is_ascii_digit() || c == '-'),
-        |s: RawSpan| *s.fragment(),
+macro_rules! impl_parse_float {
+    ($($ty:ty),*) => {
+        $(
+            impl<'a> Parse<'a> for $ty {
+                fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+                    verify(map_res(decimal, |s| s.parse()), |v: &$ty| v.is_finite())(input)
+                }
+            }
+        )*
+    };
+}
+
+impl_parse_float!(f32, f64);
+
+/// Recognizes a decimal number with an optional fraction and exponent, like `-12`, `1.5`,
+/// `.5` or `2e-3`.
+pub(crate) fn decimal(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    recognize(tuple((
+        opt(char('-')),
+        alt((
+            recognize(pair(digit1, opt(pair(char('.'), digit0)))),
+            recognize(pair(char('.'), digit1)),
+        )),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(input)
+}
+
+/// An integer literal as its sign and magnitude, so it can be read as any integer type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Integer {
+    pub negative: bool,
+    pub magnitude: u128,
+}
+
+impl Integer {
+    /// The value as a `T`, `None` when it is out of range.
+    pub(crate) fn get<T: TryFrom<i128> + TryFrom<u128>>(self) -> Option<T> {
+        if self.negative {
+            T::try_from(0i128.checked_sub_unsigned(self.magnitude)?).ok()
+        } else {
+            T::try_from(self.magnitude).ok()
+        }
+    }
+}
+
+/// Parses an integer literal with an optional `+` or `-` sign, decimal or prefixed by its
+/// base: hexadecimal `0xFF`, octal `0o77` or binary `0b1010`. Digits may be separated by
+/// single underscores, like `1_000_000`.
+pub(crate) fn integer(input: RawSpan<'_>) -> ParseResult<'_, Integer> {
+    map_opt(
+        pair(
+            opt(one_of("+-")),
+            alt((
+                preceded(
+                    char('0'),
+                    alt((
+                        pair(value(16, one_of("xX")), separated_digits(hex_digit1)),
+                        pair(value(8, one_of("oO")), separated_digits(oct_digit1)),
+                        pair(value(2, one_of("bB")), separated_digits(binary_digit1)),
+                    )),
+                ),
+                pair(success(10), separated_digits(digit1)),
+            )),
+        ),
+        |(sign, (radix, digits)): (_, (_, RawSpan))| {
+            Some(Integer {
+                negative: sign == Some('-'),
+                magnitude: u128::from_str_radix(&digits.replace('_', ""), radix).ok()?,
+            })
+        },
     )(input)
 }
+
+/// Recognizes runs of `digit` separated by single underscores.
+fn separated_digits<'a, F>(digit: F) -> impl FnMut(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>>
+where
+    F: FnMut(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>> + Copy,
+{
+    recognize(pair(digit, many0_count(pair(char('_'), digit))))
+}
+
+fn binary_digit1(input: RawSpan<'_>) -> ParseResult<'_, RawSpan<'_>> {
+    take_while1(|c: char| c == '0' || c == '1')(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radix_integers() {
+        let parse = |input: &'static str| i16::parse(input.into()).ok().map(|(_, v)| v);
+        assert_eq!(parse("0xFF"), Some(255));
+        assert_eq!(parse("0o77"), Some(63));
+        assert_eq!(parse("0b1010"), Some(10));
+        assert_eq!(parse("-0x8000"), Some(i16::MIN));
+        assert_eq!(parse("0x8000"), None);
+        assert_eq!(parse("42"), Some(42));
+        assert_eq!(u8::parse("-0x1".into()).ok(), None);
+        assert_eq!(
+            i128::parse("-0x80000000000000000000000000000000".into())
+                .ok()
+                .map(|(_, v)| v),
+            Some(i128::MIN)
+        );
+    }
+
+    #[test]
+    fn test_integer_signs_and_separators() {
+        let parse = |input: &'static str| {
+            i32::parse(input.into())
+                .ok()
+                .map(|(rest, v)| (v, *rest.fragment()))
+        };
+        assert_eq!(parse("1-2-3"), Some((1, "-2-3")));
+        assert_eq!(parse("-12"), Some((-12, "")));
+        assert_eq!(parse("+12"), Some((12, "")));
+        assert_eq!(parse("1_000_000"), Some((1_000_000, "")));
+        assert_eq!(parse("0xFF_FF"), Some((0xFFFF, "")));
+        assert_eq!(parse("1__0"), Some((1, "__0")));
+        assert_eq!(parse("1_"), Some((1, "_")));
+        assert_eq!(parse("_1"), None);
+        assert_eq!(parse("--1"), None);
+        assert_eq!(parse("+-1"), None);
+        assert_eq!(u32::parse("-0".into()).ok().map(|(_, v)| v), Some(0));
+    }
+}
@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+
+use crate::{
+    errors::{span_locations, RawParseError, SpannedParseError},
+    lexer::{TokenKind, Tokens},
+    parse::RawSpan,
+};
+
+thread_local! {
+    /// The errors recovered from so far, `None` when parsers stop at the first error.
+    static RECOVERED: RefCell<Option<Vec<SpannedParseError>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous errors when dropped, even if the parser panics.
+struct RecoveryGuard(Option<Vec<SpannedParseError>>);
+
+impl Drop for RecoveryGuard {
+    fn drop(&mut self) {
+        RECOVERED.set(self.0.take());
+    }
+}
+
+/// Runs `f` with lists recovering from invalid items on the current thread, returning what
+/// `f` returns and the errors of the items that were skipped, in the order they were found.
+pub(crate) fn with_recovery<T>(f: impl FnOnce() -> T) -> (T, Vec<SpannedParseError>) {
+    let _guard = RecoveryGuard(RECOVERED.replace(Some(Vec::new())));
+    let result = f();
+    (result, RECOVERED.take().unwrap_or_default())
+}
+
+#[must_use]
+pub(crate) fn is_recovering() -> bool {
+    RECOVERED.with_borrow(Option::is_some)
+}
+
+/// How many errors were recovered from so far.
+#[must_use]
+pub(crate) fn recorded() -> usize {
+    RECOVERED.with_borrow(|errors| errors.as_ref().map_or(0, Vec::len))
+}
+
+/// Keeps `err` to be reported once parsing ends.
+pub(crate) fn record(err: RawParseError<'_>) {
+    RECOVERED.with_borrow_mut(|errors| {
+        if let Some(errors) = errors {
+            errors.push(span_locations(err));
+        }
+    });
+}
+
+/// Skips an invalid list item, up to the next `,`, `)`, `]` or `;` that is not nested in
+/// parentheses or brackets, or inside a string literal, a quoted name or a comment. The
+/// separator is not consumed.
+pub(crate) fn skip_item(input: RawSpan<'_>) -> RawSpan<'_> {
+    let mut tokens = Tokens::new(input);
+    let mut depth = 0_usize;
+    loop {
+        let rest = tokens.rest();
+        let Some(token) = tokens.next() else {
+            return rest;
+        };
+        if token.kind != TokenKind::Punctuation {
+            continue;
+        }
+        match *token.span.fragment() {
+            "(" | "[" => depth += 1,
+            ")" | "]" if depth > 0 => depth -= 1,
+            ")" | "]" | "," | ";" if depth == 0 => return rest,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_item() {
+        let cases = [
+            ("a b, c", ", c"),
+            ("f(1, 2) + 'x,y', c", ", c"),
+            ("a[1, 2]) d", ") d"),
+            ("\"a)\" /* ; */ ; b", "; b"),
+            ("a b", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(*skip_item(RawSpan::new(input)).fragment(), expected);
+        }
+    }
+}
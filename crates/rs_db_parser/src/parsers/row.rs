@@ -1,31 +1,97 @@
+use nom::{branch::alt, combinator::map, error::context};
+
 use crate::{
-    ast::commands::create::Column,
-    errors::{custom_error, ParseResult},
-    parse::{RawSpan, WithSpan},
+    ast::{
+        commands::create::Column,
+        expr::{Expr, OwnedExpr},
+    },
+    errors::{custom_error, custom_failure, ParseError, ParseResult, RawParseError},
+    functions::FunctionRegistry,
+    parse::{Parse, RawSpan, WithSpan},
+    parsers::{keyword, parse_with_span},
     value::Value,
 };
 
+/// A value of a row, before it is given to its column.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RowValue<'a> {
+    /// `DEFAULT`, the default value of the column.
+    Default,
+    Expr(Expr<'a>),
+}
+
+/// Parses a value of a row, `DEFAULT` or an expression.
+/// # Errors
+/// Returns an error if the input is neither.
+pub fn parse_row_value(input: RawSpan<'_>) -> ParseResult<'_, WithSpan<'_, RowValue<'_>>> {
+    alt((
+        map(keyword("default"), |span| (span, RowValue::Default)),
+        context("Value", |i| {
+            parse_with_span(i, map(Expr::parse, RowValue::Expr))
+        }),
+    ))(input)
+}
+
+/// Gives a value of a row to `column`. The `DEFAULT` keyword is replaced by the default
+/// value of the column, and constant expressions like `1 + 2` or `upper('a')` are folded,
+/// calling the built-in functions. A parameter placeholder is kept as a
+/// [`Value::Parameter`].
+/// # Errors
+/// Returns an error pointing at the value if it is not constant or does not fit the column
+/// type, or if it is a `NULL` given to a `NOT NULL` column.
+pub fn bind_value<'a>(
+    column: &Column,
+    (span, value): &WithSpan<'a, RowValue<'a>>,
+) -> Result<WithSpan<'a, Value>, nom::Err<RawParseError<'a>>> {
+    let to_failure = |error: Box<dyn std::error::Error + Send + Sync>| {
+        custom_failure(*span, nom_supreme::error::BaseErrorKind::External(error))
+    };
+    let value = match value {
+        RowValue::Default => column.default.clone().unwrap_or(Value::Null),
+        RowValue::Expr(Expr::Parameter((_, n))) => Value::Parameter(*n),
+        RowValue::Expr(expr) => expr
+            .fold(FunctionRegistry::builtins())?
+            .ok_or_else(|| to_failure(Box::new(ParseError::NotConstant)))?
+            .coerce(column.tp.clone())
+            .map_err(|e| to_failure(Box::new(e)))?,
+    };
+    if value.is_null() && !column.nullable {
+        return Err(to_failure(Box::new(ParseError::NullNotAllowed)));
+    }
+    Ok((*span, value))
+}
+
+/// Binds the values of a row to the columns, the first value to the first column and so on.
 #[allow(clippy::module_name_repetitions)]
 #[derive(Clone, Debug)]
-pub struct RowParser<'a> {
-    columns: Vec<(RawSpan<'a>, &'a Column)>,
+pub struct RowParser<'a, 'c> {
+    columns: Vec<(RawSpan<'a>, &'c Column)>,
+    /// The index of the column the next value is given to.
+    position: usize,
 }
 
-impl<'a> RowParser<'a> {
+impl<'a, 'c> RowParser<'a, 'c> {
+    /// A parser for rows with a value for each of `columns`, in the order they are given.
     #[must_use]
-    pub fn new(columns: Vec<(RawSpan<'a>, &'a Column)>) -> Self {
-        Self { columns }
+    pub fn new(columns: Vec<(RawSpan<'a>, &'c Column)>) -> Self {
+        Self {
+            columns,
+            position: 0,
+        }
     }
 
-    /// Parses a row of values.
+    /// Parses the value of the next column, returning it along with the name of the column,
+    /// see [`bind_value`].
     /// # Errors
     /// Returns an error if the input is not a valid row of values.
     /// Returns an error if the number of values does not match the number of columns.
+    /// Returns an error if a `NULL` is given to a `NOT NULL` column.
+    /// Returns an error if a value is not constant or does not fit the column type.
     pub fn parse(
         &mut self,
         input: RawSpan<'a>,
     ) -> ParseResult<'a, (RawSpan<'a>, WithSpan<'a, Value>)> {
-        self.columns.pop().map_or_else(
+        self.next_column().map_or_else(
             || {
                 Err(custom_error(
                     input,
@@ -35,24 +101,44 @@ impl<'a> RowParser<'a> {
                 ))
             },
             |(name_span, column)| {
-                Value::parse_with_type(column.tp, input)
-                    .map(|(input, value)| (input, (name_span, value)))
+                let (input, value) = parse_row_value(input)?;
+                let value = bind_value(column, &value)?;
+                self.position += 1;
+                Ok((input, (name_span, value)))
             },
         )
     }
 
+    /// The number of columns still without a value.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.columns.len()
+        self.columns.len() - self.position
     }
 
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.columns.is_empty()
+        self.len() == 0
     }
 
+    /// The column the next value is given to.
     #[must_use]
-    pub fn pop(&mut self) -> Option<(RawSpan<'a>, &'a Column)> {
-        self.columns.pop()
+    pub fn next_column(&self) -> Option<(RawSpan<'a>, &'c Column)> {
+        self.columns.get(self.position).copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnedRowValue {
+    Default,
+    Expr(OwnedExpr),
+}
+
+impl RowValue<'_> {
+    pub fn to_owned(&self) -> OwnedRowValue {
+        match self {
+            Self::Default => OwnedRowValue::Default,
+            Self::Expr(expr) => OwnedRowValue::Expr(expr.to_owned()),
+        }
     }
 }
@@ -0,0 +1,170 @@
+use std::cell::Cell;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag},
+    character::complete::{anychar, char},
+    combinator::recognize,
+    multi::many0_count,
+    sequence::{pair, preceded},
+    Slice,
+};
+
+use crate::{
+    errors::{custom_failure, ParseError, ParseResult, RawParseError},
+    parse::{RawSpan, WithSpan},
+};
+
+/// How backslashes in string literals are read. A quote is always written twice to be part of
+/// the string, `'it''s'`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EscapeMode {
+    /// Backslashes are ordinary characters, as in standard SQL.
+    #[default]
+    Standard,
+    /// Backslashes start C-style escapes: `\n`, `\t`, `\r`, `\0`, `\\`, `\'`, `\"` and
+    /// `\u{...}`. `\%` and `\_` are kept as written, so they still escape `LIKE` wildcards.
+    Backslash,
+}
+
+thread_local! {
+    static ESCAPE_MODE: Cell<EscapeMode> = const { Cell::new(EscapeMode::Standard) };
+}
+
+/// Restores the previous mode when dropped, even if the parser panics.
+struct ModeGuard(EscapeMode);
+
+impl Drop for ModeGuard {
+    fn drop(&mut self) {
+        ESCAPE_MODE.set(self.0);
+    }
+}
+
+/// Runs `f` with string literals parsed in `mode` on the current thread, the mode is
+/// [`EscapeMode::Standard`] otherwise.
+pub fn with_escape_mode<T>(mode: EscapeMode, f: impl FnOnce() -> T) -> T {
+    let _guard = ModeGuard(ESCAPE_MODE.replace(mode));
+    f()
+}
+
+/// Parses a single quoted string literal, returning the span of its content, as written, and
+/// the content with its escapes read, see [`EscapeMode`]. An invalid escape is an error
+/// pointing at it, an unterminated literal is an error spanning the rest of the input.
+pub(crate) fn string_literal(input: RawSpan<'_>) -> ParseResult<'_, WithSpan<'_, Box<str>>> {
+    let mode = ESCAPE_MODE.get();
+    let (rest, raw) = preceded(
+        char('\''),
+        recognize(many0_count(alt((
+            is_not(if mode == EscapeMode::Backslash {
+                "\\'"
+            } else {
+                "'"
+            }),
+            tag("''"),
+            recognize(pair(char('\\'), anychar)),
+        )))),
+    )(input)?;
+    // Point at the whole literal, up to the end of the input, not just where the quote is
+    // missing.
+    let (rest, _) = char('\'')(rest).map_err(|_: nom::Err<RawParseError<'_>>| {
+        custom_failure(
+            input,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::UnterminatedString)),
+        )
+    })?;
+    let text = match mode {
+        EscapeMode::Standard => raw.replace("''", "'").into(),
+        EscapeMode::Backslash => unescape(raw)?,
+    };
+    Ok((rest, (raw, text)))
+}
+
+/// Reads the doubled quotes and backslash escapes of `raw`.
+fn unescape(raw: RawSpan<'_>) -> Result<Box<str>, nom::Err<RawParseError<'_>>> {
+    // The backslash and the character after it.
+    let invalid = |index: usize| {
+        let end = raw.fragment()[index + 1..]
+            .chars()
+            .next()
+            .map_or(raw.fragment().len(), |c| index + 1 + c.len_utf8());
+        custom_failure(
+            raw.slice(index..end),
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::InvalidEscape)),
+        )
+    };
+    let mut text = String::with_capacity(raw.len());
+    let mut chars = raw.fragment().char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\'' => {
+                chars.next();
+                text.push('\'');
+            }
+            '\\' => match chars.next().ok_or_else(|| invalid(index))?.1 {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                'r' => text.push('\r'),
+                '0' => text.push('\0'),
+                c @ ('\\' | '\'' | '"') => text.push(c),
+                c @ ('%' | '_') => {
+                    text.push('\\');
+                    text.push(c);
+                }
+                'u' => {
+                    let rest = &raw.fragment()[index + 2..];
+                    let code = rest
+                        .strip_prefix('{')
+                        .and_then(|rest| rest.split_once('}'))
+                        .map(|(digits, _)| digits)
+                        .filter(|digits| (1..=6).contains(&digits.len()))
+                        .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| invalid(index))?;
+                    text.push(code);
+                    while chars.next_if(|&(_, c)| c != '}').is_some() {}
+                    chars.next();
+                }
+                _ => return Err(invalid(index)),
+            },
+            c => text.push(c),
+        }
+    }
+    Ok(text.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_literal() {
+        let parse = |input: &'static str| {
+            string_literal(input.into()).map(|(rest, (raw, text))| {
+                (
+                    rest.fragment().to_string(),
+                    raw.fragment().to_string(),
+                    text,
+                )
+            })
+        };
+        let (rest, raw, text) = parse("'it''s' x").unwrap();
+        assert_eq!(
+            (rest.as_str(), raw.as_str(), &*text),
+            (" x", "it''s", "it's")
+        );
+        assert_eq!(&*parse(r"'a\nb'").unwrap().2, r"a\nb");
+        assert_eq!(parse(r"'a\'b'").unwrap().0, "b'");
+
+        with_escape_mode(EscapeMode::Backslash, || {
+            let text = |input| parse(input).unwrap().2;
+            assert_eq!(&*text(r"'a\nb\t\\'"), "a\nb\t\\");
+            assert_eq!(&*text(r"'it\'s ''ok'''"), "it's 'ok'");
+            assert_eq!(&*text(r"'\u{1F600}\u{e9}!'"), "\u{1F600}é!");
+            assert_eq!(&*text(r"'100\%'"), r"100\%");
+            assert!(matches!(parse(r"'\q'"), Err(nom::Err::Failure(_))));
+            assert!(matches!(parse(r"'\u{110000}'"), Err(nom::Err::Failure(_))));
+            assert!(matches!(parse(r"'\u{}'"), Err(nom::Err::Failure(_))));
+        });
+        assert_eq!(&*parse(r"'\q'").unwrap().2, r"\q", "the mode is restored");
+    }
+}
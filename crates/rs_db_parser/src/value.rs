@@ -1,21 +1,48 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
 use nom::{
-    bytes::complete::escaped,
-    character::complete::{char, none_of, one_of},
-    combinator::{cut, map, map_res},
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    character::complete::{char, one_of},
+    combinator::{cut, map, map_opt, map_res, not, opt, peek},
     error::context,
-    sequence::{preceded, terminated},
+    sequence::{delimited, pair, preceded, terminated},
 };
 
 use crate::{
-    ast::commands::create::SqlType,
-    errors::ParseResult,
+    ast::{
+        commands::{
+            create::{temporal_type, CastKind, LengthUnit, SqlType},
+            select::SortOrder,
+        },
+        expr::BinaryOperator,
+    },
+    date::{Date, Interval, Time, Timestamp},
+    decimal::{Decimal, MAX_PRECISION},
+    errors::{custom_failure, CastError, ParseError, ParseResult},
     parse::{Parse, RawSpan, WithSpan},
-    parsers::parse_with_span,
+    parsers::{
+        comma_sep, keyword,
+        limits::DepthGuard,
+        multispace0,
+        number::{decimal, integer, Integer},
+        parse_with_span,
+        string::string_literal,
+    },
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+/// Values are compared and hashed by content, floats by their bits, see [`f64_bits`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Value {
+    Null,
     VarChar(Box<str>),
+    /// A fixed-length string, padded with spaces. The padding is not significant, it is
+    /// ignored when comparing and hashing.
+    Char(Box<str>),
+    Bool(bool),
     I8(i8),
     I16(i16),
     I32(i32),
@@ -26,64 +53,1651 @@ pub enum Value {
     U32(u32),
     U64(u64),
     U128(u128),
+    F32(f32),
+    F64(f64),
+    Decimal(Decimal),
+    Date(Date),
+    Time(Time),
+    Timestamp(Timestamp),
+    /// A timestamp in UTC.
+    TimestampTz(Timestamp),
+    Interval(Interval),
+    Blob(Box<[u8]>),
+    Json(serde_json::Value),
+    Array(Box<[Value]>),
+    /// A placeholder of a prepared `INSERT` row, `$n`, or `?` stored as `0` until it is
+    /// numbered. Replaced by its value when the parameters are bound.
+    Parameter(u16),
+}
+
+/// The bits of a float with every `NaN` and both zeros folded into a single pattern, so
+/// equal values are equal and hash the same.
+pub(crate) fn f64_bits(v: f64) -> u64 {
+    if v.is_nan() {
+        f64::NAN.to_bits()
+    } else if v == 0.0 {
+        0
+    } else {
+        v.to_bits()
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::VarChar(a), Self::VarChar(b)) => a == b,
+            (Self::Char(a), Self::Char(b)) => a.trim_end_matches(' ') == b.trim_end_matches(' '),
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::I8(a), Self::I8(b)) => a == b,
+            (Self::I16(a), Self::I16(b)) => a == b,
+            (Self::I32(a), Self::I32(b)) => a == b,
+            (Self::I64(a), Self::I64(b)) => a == b,
+            (Self::I128(a), Self::I128(b)) => a == b,
+            (Self::U8(a), Self::U8(b)) => a == b,
+            (Self::U16(a), Self::U16(b)) => a == b,
+            (Self::U32(a), Self::U32(b)) => a == b,
+            (Self::U64(a), Self::U64(b)) => a == b,
+            (Self::U128(a), Self::U128(b)) => a == b,
+            (Self::F32(a), Self::F32(b)) => f64_bits(f64::from(*a)) == f64_bits(f64::from(*b)),
+            (Self::F64(a), Self::F64(b)) => f64_bits(*a) == f64_bits(*b),
+            (Self::Decimal(a), Self::Decimal(b)) => a == b,
+            (Self::Date(a), Self::Date(b)) => a == b,
+            (Self::Time(a), Self::Time(b)) => a == b,
+            (Self::Interval(a), Self::Interval(b)) => a == b,
+            (Self::Blob(a), Self::Blob(b)) => a == b,
+            (Self::Json(a), Self::Json(b)) => a == b,
+            (Self::Array(a), Self::Array(b)) => a == b,
+            (Self::Timestamp(a), Self::Timestamp(b))
+            | (Self::TimestampTz(a), Self::TimestampTz(b)) => a == b,
+            (Self::Parameter(a), Self::Parameter(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Null => {}
+            Self::VarChar(v) => v.hash(state),
+            Self::Char(v) => v.trim_end_matches(' ').hash(state),
+            Self::Bool(v) => v.hash(state),
+            Self::I8(v) => v.hash(state),
+            Self::I16(v) => v.hash(state),
+            Self::I32(v) => v.hash(state),
+            Self::I64(v) => v.hash(state),
+            Self::I128(v) => v.hash(state),
+            Self::U8(v) => v.hash(state),
+            Self::U16(v) => v.hash(state),
+            Self::U32(v) => v.hash(state),
+            Self::U64(v) => v.hash(state),
+            Self::U128(v) => v.hash(state),
+            Self::F32(v) => f64_bits(f64::from(*v)).hash(state),
+            Self::F64(v) => f64_bits(*v).hash(state),
+            Self::Decimal(v) => v.hash(state),
+            Self::Date(v) => v.hash(state),
+            Self::Time(v) => v.hash(state),
+            Self::Interval(v) => v.hash(state),
+            Self::Blob(v) => v.hash(state),
+            Self::Json(v) => v.to_string().hash(state),
+            Self::Array(v) => v.hash(state),
+            Self::Timestamp(v) | Self::TimestampTz(v) => v.hash(state),
+            Self::Parameter(v) => v.hash(state),
+        }
+    }
+}
+
+/// A total order for sorting and ordered collections, consistent with [`PartialEq`]: values
+/// are grouped by kind, booleans, numbers, strings, blobs, dates and timestamps, times,
+/// intervals, JSON, arrays and placeholders, with `NULL` last. Values of a kind are ordered
+/// as [`Value::sql_cmp`] orders them, `NaN` after the other numbers, and values that
+/// compare equal but are not, like `1` as an `I32` and as an `I64`, by their variant.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (kind, variant) = self.sort_rank();
+        let (other_kind, other_variant) = other.sort_rank();
+        kind.cmp(&other_kind)
+            .then_with(|| match (self, other) {
+                (Self::Array(a), Self::Array(b)) => a.cmp(b),
+                (Self::Interval(a), Self::Interval(b)) => a.cmp_length(b).then_with(|| {
+                    (a.months(), a.days(), a.micros()).cmp(&(b.months(), b.days(), b.micros()))
+                }),
+                (Self::Parameter(a), Self::Parameter(b)) => a.cmp(b),
+                _ => self
+                    .sql_cmp(other, SortOrder::Asc, false, Collation::Binary)
+                    .unwrap_or(Ordering::Equal),
+            })
+            .then_with(|| variant.cmp(&other_variant))
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// How strings are compared by [`Value::sql_cmp`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Collation {
+    /// By Unicode code points, the order of Rust strings.
+    #[default]
+    Binary,
+    /// By code points of the lowercase strings, `'a'` equals `'A'` and sorts before `'B'`.
+    CaseInsensitive,
+}
+
+impl Collation {
+    #[must_use]
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            Self::Binary => a.cmp(b),
+            Self::CaseInsensitive => a
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase)),
+        }
+    }
+}
+
+/// Parses a string literal holding a date, time or interval of type `tp`, pointing at the string when
+/// its text is not valid.
+fn temporal_string<'a>(tp: &SqlType, input: RawSpan<'a>) -> ParseResult<'a, Value> {
+    let (input, (span, text)) = string_literal(input)?;
+    let value = Value::read_temporal(tp, &text).map_err(|error| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(error)),
+        )
+    })?;
+    Ok((input, value))
+}
+
+/// Parses a date, time or interval literal prefixed by its type, like `DATE '2024-01-31'`,
+/// `TIMESTAMPTZ '2024-01-31 10:20:30+02'` or `INTERVAL '1 day 2 hours'`.
+fn temporal_literal(input: RawSpan<'_>) -> ParseResult<'_, Value> {
+    let (input, tp) = terminated(temporal_type, multispace0)(input)?;
+    temporal_string(&tp, input)
+}
+
+/// Parses a string literal holding a JSON document, pointing at the string when it is not
+/// valid JSON.
+fn json_string(input: RawSpan<'_>) -> ParseResult<'_, Value> {
+    let (input, (span, text)) = string_literal(input)?;
+    let json = serde_json::from_str(&text).map_err(|error| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::InvalidJson(
+                error.to_string().into(),
+            ))),
+        )
+    })?;
+    Ok((input, Value::Json(json)))
+}
+
+/// Reads pairs of hexadecimal digits as bytes.
+fn decode_hex(digits: &str) -> Option<Box<[u8]>> {
+    if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// Parses a binary literal, `x'DEADBEEF'` or `'\xDEADBEEF'`, pointing at the digits when
+/// they are not pairs of hexadecimal digits.
+fn blob_literal(input: RawSpan<'_>) -> ParseResult<'_, Box<[u8]>> {
+    let digits = || cut(terminated(take_till(|c| c == '\''), char('\'')));
+    let (input, digits) = alt((
+        preceded(pair(one_of("xX"), char('\'')), digits()),
+        preceded(tag("'\\x"), digits()),
+    ))(input)?;
+    let bytes = decode_hex(digits.fragment()).ok_or_else(|| {
+        custom_failure(
+            digits,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::InvalidBlob)),
+        )
+    })?;
+    Ok((input, bytes))
+}
+
+/// Parses `ARRAY[value, ...]` without a known type. The elements must share a type and are
+/// converted to it, pointing at the first element that doesn't.
+fn array_literal(input: RawSpan<'_>) -> ParseResult<'_, Value> {
+    let _depth = DepthGuard::enter(input)?;
+    let (input, items) = preceded(
+        pair(keyword("array"), multispace0),
+        cut(delimited(
+            char('['),
+            comma_sep(|i| parse_with_span(i, Value::parse)),
+            char(']'),
+        )),
+    )(input)?;
+    let mismatch = |span| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::ArrayElementType)),
+        )
+    };
+    let mut element_type: Option<SqlType> = None;
+    for (span, item) in &items {
+        if let Some(tp) = item.sql_type() {
+            element_type = Some(match element_type {
+                Some(previous) => previous.common(&tp).ok_or_else(|| mismatch(*span))?,
+                None => tp,
+            });
+        }
+    }
+    let items = items
+        .iter()
+        .map(|(span, item)| match &element_type {
+            Some(tp) => item.coerce(tp.clone()).map_err(|_| mismatch(*span)),
+            None => Ok(item.clone()),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((input, Value::Array(items)))
+}
+
+/// Parses a string literal holding the text of an array of `element`, like `'{1,2,3}'`,
+/// pointing at the string when it is not valid.
+fn array_string<'a>(element: &SqlType, input: RawSpan<'a>) -> ParseResult<'a, Value> {
+    let (input, (span, text)) = string_literal(input)?;
+    let items = read_array(element, &text).ok_or_else(|| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(ParseError::InvalidArray)),
+        )
+    })?;
+    Ok((input, Value::Array(items)))
+}
+
+/// Reads the text of an array, `{1,2,3}`, converting each element to `element` as `CAST`
+/// does. Elements may be double quoted, an unquoted `NULL` is `NULL`, and the elements of
+/// an array of arrays are written between nested braces.
+fn read_array(element: &SqlType, text: &str) -> Option<Box<[Value]>> {
+    let (items, rest) = read_array_prefix(element, text)?;
+    rest.trim().is_empty().then_some(items)
+}
+
+/// Like [`read_array`], returning the text after the closing brace.
+fn read_array_prefix<'t>(element: &SqlType, text: &'t str) -> Option<(Box<[Value]>, &'t str)> {
+    let mut rest = text.trim_start().strip_prefix('{')?.trim_start();
+    let mut items = Vec::new();
+    if let Some(rest) = rest.strip_prefix('}') {
+        return Some((items.into(), rest));
+    }
+    loop {
+        let item = match (element, rest.strip_prefix('"')) {
+            (SqlType::Array(inner), None) if rest.starts_with('{') => {
+                let (nested, after) = read_array_prefix(inner, rest)?;
+                rest = after;
+                Value::Array(nested)
+            }
+            (_, Some(quoted)) => {
+                let mut content = String::new();
+                let mut chars = quoted.char_indices();
+                loop {
+                    match chars.next()? {
+                        (index, '"') => {
+                            rest = &quoted[index + 1..];
+                            break;
+                        }
+                        (_, '\\') => content.push(chars.next()?.1),
+                        (_, c) => content.push(c),
+                    }
+                }
+                Value::read_element(element, &content)?
+            }
+            (_, None) => {
+                let end = rest.find([',', '}'])?;
+                let word = rest[..end].trim();
+                rest = &rest[end..];
+                if word.eq_ignore_ascii_case("null") {
+                    Value::Null
+                } else if word.is_empty() {
+                    return None;
+                } else {
+                    Value::read_element(element, word)?
+                }
+            }
+        };
+        items.push(item);
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after.trim_start();
+        } else {
+            return Some((items.into(), rest.strip_prefix('}')?));
+        }
+    }
+}
+
+/// Parses an integer literal of type `tp`, pointing at it when it is out of range.
+fn integer_literal<'a, T: TryFrom<i128> + TryFrom<u128>>(
+    tp: &SqlType,
+    input: RawSpan<'a>,
+) -> ParseResult<'a, T> {
+    let (input, (span, integer)) = parse_with_span(input, integer)?;
+    let value = integer.get().ok_or_else(|| {
+        custom_failure(
+            span,
+            nom_supreme::error::BaseErrorKind::External(Box::new(CastError::OutOfRange(
+                tp.clone(),
+            ))),
+        )
+    })?;
+    Ok((input, value))
+}
+
+fn bool_literal(input: RawSpan<'_>) -> ParseResult<'_, bool> {
+    alt((
+        map(keyword("true"), |_| true),
+        map(keyword("false"), |_| false),
+    ))(input)
+}
+
+impl<'a> Parse<'a> for Value {
+    /// Parses a literal without a known type: strings are `VarChar`s, `x'...'` and `'\x...'`
+    /// are `Blob`s, `DATE '...'` and the other prefixed literals are dates, times and
+    /// intervals, integers, also written with `_` separators or in hexadecimal `0xFF`, octal
+    /// `0o77` or binary `0b1010`, get the narrowest of `I64`, `I128` and `U128` that fits them,
+    /// numbers with a fraction are exact `Decimal`s and numbers with an exponent, or too many
+    /// digits, are `F64`s.
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Literal",
+            alt((
+                map(keyword("null"), |_| Self::Null),
+                map(bool_literal, Self::Bool),
+                temporal_literal,
+                array_literal,
+                map(blob_literal, Self::Blob),
+                map(string_literal, |(_, text)| Self::VarChar(text)),
+                map_opt(
+                    terminated(integer, not(peek(one_of(".eE")))),
+                    |integer: Integer| {
+                        integer
+                            .get()
+                            .map(Self::I64)
+                            .or_else(|| integer.get().map(Self::I128))
+                            .or_else(|| integer.get().map(Self::U128))
+                    },
+                ),
+                map_opt(decimal, |s: RawSpan| {
+                    if s.contains('.') && !s.contains(['e', 'E']) {
+                        if let Ok(v) = s.parse() {
+                            return Some(Self::Decimal(v));
+                        }
+                    }
+                    if s.contains(['.', 'e', 'E']) {
+                        return s
+                            .parse()
+                            .ok()
+                            .filter(|v: &f64| v.is_finite())
+                            .map(Self::F64);
+                    }
+                    s.parse()
+                        .map(Self::I64)
+                        .or_else(|_| s.parse().map(Self::I128))
+                        .or_else(|_| s.parse().map(Self::U128))
+                        .ok()
+                }),
+            )),
+        )(input)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "NULL"),
+            Self::VarChar(s) | Self::Char(s) => write!(f, "{s}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::I8(v) => write!(f, "{v}"),
+            Self::I16(v) => write!(f, "{v}"),
+            Self::I32(v) => write!(f, "{v}"),
+            Self::I64(v) => write!(f, "{v}"),
+            Self::I128(v) => write!(f, "{v}"),
+            Self::U8(v) => write!(f, "{v}"),
+            Self::U16(v) => write!(f, "{v}"),
+            Self::U32(v) => write!(f, "{v}"),
+            Self::U64(v) => write!(f, "{v}"),
+            Self::U128(v) => write!(f, "{v}"),
+            // The debug format keeps the fraction of whole numbers, `1.0`, and switches to
+            // an exponent for very large or small ones.
+            Self::F32(v) => write!(f, "{v:?}"),
+            Self::F64(v) => write!(f, "{v:?}"),
+            Self::Decimal(v) => write!(f, "{v}"),
+            Self::Date(v) => write!(f, "{v}"),
+            Self::Time(v) => write!(f, "{v}"),
+            Self::Timestamp(v) => write!(f, "{v}"),
+            Self::TimestampTz(v) => write!(f, "{v}+00"),
+            Self::Interval(v) => write!(f, "{v}"),
+            Self::Blob(v) => {
+                write!(f, "\\x")?;
+                v.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+            Self::Json(v) => write!(f, "{v}"),
+            Self::Array(items) => {
+                write!(f, "{{")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    match item {
+                        Self::Null | Self::Array(_) => write!(f, "{item}")?,
+                        _ => write_array_element(f, &item.to_string())?,
+                    }
+                }
+                write!(f, "}}")
+            }
+            Self::Parameter(0) => write!(f, "?"),
+            Self::Parameter(n) => write!(f, "${n}"),
+        }
+    }
+}
+
+/// Writes the text of an array element, quoted when it would otherwise be read back
+/// differently, like an empty string, the string `NULL` or one with a comma.
+fn write_array_element(f: &mut std::fmt::Formatter<'_>, text: &str) -> std::fmt::Result {
+    let special = |c: char| c.is_whitespace() || "{},\"\\".contains(c);
+    if !text.is_empty() && !text.eq_ignore_ascii_case("null") && !text.contains(special) {
+        return write!(f, "{text}");
+    }
+    write!(f, "\"")?;
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            write!(f, "\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    write!(f, "\"")
+}
+
+impl<T: Into<Self>> From<Option<T>> for Value {
+    /// `None` becomes `NULL`, like the UNKNOWN truth value.
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// Converts `value` as an assignment to a column of type `tp` does, see [`Value::coerce`],
+/// and takes the result out with `take`. A value `take` accepts as it is is not converted,
+/// `NULL` and parameters are not converted at all.
+fn take_coerced<T>(
+    value: &Value,
+    tp: SqlType,
+    take: impl Fn(&Value) -> Option<T>,
+) -> Result<T, CastError> {
+    if let Some(taken) = take(value) {
+        return Ok(taken);
+    }
+    let Some(from) = value.sql_type() else {
+        return Err(CastError::Null);
+    };
+    take(&value.coerce(tp.clone())?).ok_or(CastError::Forbidden { from, to: tp })
+}
+
+/// Conversions from a Rust type into its variant and back, through [`take_coerced`], so
+/// narrower integers widen and wider ones convert when they fit. `NULL` converts to `None`
+/// of an `Option`.
+macro_rules! conversions {
+    ($($rust:ty => $variant:ident, $tp:expr, |$v:ident| $take:expr;)*) => {
+        $(
+            impl From<$rust> for Value {
+                fn from(value: $rust) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+
+            impl TryFrom<Value> for $rust {
+                type Error = CastError;
+
+                fn try_from(value: Value) -> Result<Self, CastError> {
+                    take_coerced(&value, $tp, |value| match value {
+                        Value::$variant($v) => Some($take),
+                        _ => None,
+                    })
+                }
+            }
+
+            impl TryFrom<Value> for Option<$rust> {
+                type Error = CastError;
+
+                fn try_from(value: Value) -> Result<Self, CastError> {
+                    match value {
+                        Value::Null => Ok(None),
+                        value => value.try_into().map(Some),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+conversions! {
+    bool => Bool, SqlType::Bool, |v| *v;
+    i8 => I8, SqlType::I8, |v| *v;
+    i16 => I16, SqlType::I16, |v| *v;
+    i32 => I32, SqlType::I32, |v| *v;
+    i64 => I64, SqlType::I64, |v| *v;
+    i128 => I128, SqlType::I128, |v| *v;
+    u8 => U8, SqlType::U8, |v| *v;
+    u16 => U16, SqlType::U16, |v| *v;
+    u32 => U32, SqlType::U32, |v| *v;
+    u64 => U64, SqlType::U64, |v| *v;
+    u128 => U128, SqlType::U128, |v| *v;
+    f32 => F32, SqlType::F32, |v| *v;
+    f64 => F64, SqlType::F64, |v| *v;
+    Decimal => Decimal, SqlType::Decimal { precision: MAX_PRECISION, scale: 0 }, |v| *v;
+    Date => Date, SqlType::Date, |v| *v;
+    Time => Time, SqlType::Time, |v| *v;
+    Timestamp => Timestamp, SqlType::Timestamp, |v| *v;
+    Interval => Interval, SqlType::Interval, |v| *v;
+    String => VarChar, SqlType::VarChar(usize::MAX, LengthUnit::Chars), |v| v.to_string();
+    Box<str> => VarChar, SqlType::VarChar(usize::MAX, LengthUnit::Chars), |v| v.clone();
+    Vec<u8> => Blob, SqlType::Blob, |v| v.to_vec();
+    Box<[u8]> => Blob, SqlType::Blob, |v| v.clone();
+    serde_json::Value => Json, SqlType::Json, |v| v.clone();
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::VarChar(value.into())
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Self::Blob(value.into())
+    }
+}
+
+impl From<Vec<Self>> for Value {
+    fn from(value: Vec<Self>) -> Self {
+        Self::Array(value.into())
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = CastError;
+
+    fn try_from(value: Value) -> Result<Self, CastError> {
+        match value {
+            Value::Array(items) => Ok(items.into_vec()),
+            value => Err(value
+                .sql_type()
+                .map_or(CastError::Null, |from| CastError::Forbidden {
+                    to: SqlType::Array(Box::new(from.clone())),
+                    from,
+                })),
+        }
+    }
+}
+
+impl TryFrom<Value> for Option<Vec<Value>> {
+    type Error = CastError;
+
+    fn try_from(value: Value) -> Result<Self, CastError> {
+        match value {
+            Value::Null => Ok(None),
+            value => value.try_into().map(Some),
+        }
+    }
 }
 
 impl Value {
-    fn parse_inner(tp: SqlType, input: RawSpan<'_>) -> ParseResult<'_, Self> {
+    fn parse_inner<'a>(tp: &SqlType, input: RawSpan<'a>) -> ParseResult<'a, Self> {
         match tp {
-            SqlType::VarChar(size) => map(
-                preceded(
-                    char('\''),
-                    cut(map_res(
-                        terminated(escaped(none_of("\\'"), '\\', one_of("'\\")), char('\'')),
-                        |s: RawSpan| {
-                            if s.len() > size {
-                                Err("Value too long")
-                            } else {
-                                Ok(s)
-                            }
-                        },
-                    )),
+            SqlType::VarChar(size, unit) => map_res(string_literal, |(_, text)| {
+                if unit.len(&text) > *size {
+                    Err("Value too long")
+                } else {
+                    Ok(Self::VarChar(text))
+                }
+            })(input),
+            SqlType::Char(size) => map_res(string_literal, |(_, text)| {
+                if text.trim_end_matches(' ').chars().count() > *size {
+                    Err("Value too long")
+                } else {
+                    Ok(Self::padded(&text, *size))
+                }
+            })(input),
+            SqlType::Bool => map(bool_literal, Self::Bool)(input),
+            SqlType::Blob => map(blob_literal, Self::Blob)(input),
+            SqlType::Json => json_string(input),
+            SqlType::Array(element) => alt((
+                map(
+                    preceded(
+                        pair(keyword("array"), multispace0),
+                        cut(delimited(
+                            char('['),
+                            opt(comma_sep(|i| {
+                                alt((map(keyword("null"), |_| Self::Null), |i| {
+                                    Self::parse_inner(element, i)
+                                }))(i)
+                            })),
+                            pair(multispace0, char(']')),
+                        )),
+                    ),
+                    |items| Self::Array(items.unwrap_or_default().into()),
                 ),
-                |s: RawSpan| Self::VarChar((*s).into()),
-            )(input),
-            SqlType::I8 => map(i8::parse, Self::I8)(input),
-            SqlType::I16 => map(i16::parse, Self::I16)(input),
-            SqlType::I32 => map(i32::parse, Self::I32)(input),
-            SqlType::I64 => map(i64::parse, Self::I64)(input),
-            SqlType::I128 => map(i128::parse, Self::I128)(input),
-            SqlType::U8 => map(u8::parse, Self::U8)(input),
-            SqlType::U16 => map(u16::parse, Self::U16)(input),
-            SqlType::U32 => map(u32::parse, Self::U32)(input),
-            SqlType::U64 => map(u64::parse, Self::U64)(input),
-            SqlType::U128 => map(u128::parse, Self::U128)(input),
-        }
-    }
-
-    /// Parse a value with the given type.
+                |i| array_string(element, i),
+            ))(input),
+            SqlType::Enum(labels) => {
+                let (input, (span, label)) = string_literal(input)?;
+                if !labels.contains(&label) {
+                    return Err(custom_failure(
+                        span,
+                        nom_supreme::error::BaseErrorKind::External(Box::new(
+                            ParseError::InvalidEnumLabel,
+                        )),
+                    ));
+                }
+                Ok((input, Self::VarChar(label)))
+            }
+            SqlType::I8 => map(|i| integer_literal(tp, i), Self::I8)(input),
+            SqlType::I16 => map(|i| integer_literal(tp, i), Self::I16)(input),
+            SqlType::I32 => map(|i| integer_literal(tp, i), Self::I32)(input),
+            SqlType::I64 => map(|i| integer_literal(tp, i), Self::I64)(input),
+            SqlType::I128 => map(|i| integer_literal(tp, i), Self::I128)(input),
+            SqlType::U8 => map(|i| integer_literal(tp, i), Self::U8)(input),
+            SqlType::U16 => map(|i| integer_literal(tp, i), Self::U16)(input),
+            SqlType::U32 => map(|i| integer_literal(tp, i), Self::U32)(input),
+            SqlType::U64 => map(|i| integer_literal(tp, i), Self::U64)(input),
+            SqlType::U128 => map(|i| integer_literal(tp, i), Self::U128)(input),
+            SqlType::F32 => map(f32::parse, Self::F32)(input),
+            SqlType::F64 => map(f64::parse, Self::F64)(input),
+            SqlType::Decimal { .. } => map_opt(decimal, |s: RawSpan| {
+                Self::Decimal(s.parse().ok()?).coerce(tp.clone()).ok()
+            })(input),
+            // A date, time or interval column also takes the plain string.
+            SqlType::Date
+            | SqlType::Time
+            | SqlType::Timestamp
+            | SqlType::TimestampTz
+            | SqlType::Interval => alt((
+                map_opt(temporal_literal, |value| value.coerce(tp.clone()).ok()),
+                |i| temporal_string(tp, i),
+            ))(input),
+        }
+    }
+
+    /// Parse a value with the given type, `NULL` is accepted for every type.
     /// # Errors
     /// If the type is `VarChar` and the value is not the correct length.
     pub fn parse_with_type(tp: SqlType, input: RawSpan<'_>) -> ParseResult<'_, WithSpan<'_, Self>> {
         context("Value", |i| {
-            parse_with_span(i, |i| Self::parse_inner(tp, i))
+            parse_with_span(i, |i| {
+                alt((map(keyword("null"), |_| Self::Null), |i| {
+                    Self::parse_inner(&tp, i)
+                }))(i)
+            })
         })(input)
     }
 
+    /// The type of the value, `None` for `NULL` and parameters. Strings are as wide as their
+    /// content.
+    #[must_use]
+    pub fn sql_type(&self) -> Option<SqlType> {
+        Some(match self {
+            Self::Null | Self::Parameter(_) => return None,
+            Self::VarChar(s) => SqlType::VarChar(s.chars().count(), LengthUnit::Chars),
+            Self::Char(s) => SqlType::Char(s.chars().count()),
+            Self::Bool(_) => SqlType::Bool,
+            Self::I8(_) => SqlType::I8,
+            Self::I16(_) => SqlType::I16,
+            Self::I32(_) => SqlType::I32,
+            Self::I64(_) => SqlType::I64,
+            Self::I128(_) => SqlType::I128,
+            Self::U8(_) => SqlType::U8,
+            Self::U16(_) => SqlType::U16,
+            Self::U32(_) => SqlType::U32,
+            Self::U64(_) => SqlType::U64,
+            Self::U128(_) => SqlType::U128,
+            Self::F32(_) => SqlType::F32,
+            Self::F64(_) => SqlType::F64,
+            Self::Decimal(v) => SqlType::Decimal {
+                precision: v.precision(),
+                scale: v.scale(),
+            },
+            Self::Date(_) => SqlType::Date,
+            Self::Time(_) => SqlType::Time,
+            Self::Timestamp(_) => SqlType::Timestamp,
+            Self::TimestampTz(_) => SqlType::TimestampTz,
+            Self::Interval(_) => SqlType::Interval,
+            Self::Blob(_) => SqlType::Blob,
+            Self::Json(_) => SqlType::Json,
+            // The common type of the elements, an array of `NULL`s has none.
+            Self::Array(items) => {
+                let mut types = items.iter().filter_map(Self::sql_type);
+                let first = types.next()?;
+                SqlType::Array(Box::new(
+                    types.try_fold(first, |tp, other| tp.common(&other))?,
+                ))
+            }
+        })
+    }
+
+    /// Reads the text of an array element as `element`, a string must fit its length.
+    fn read_element(element: &SqlType, text: &str) -> Option<Self> {
+        match element {
+            SqlType::VarChar(size, unit) if unit.len(text) > *size => return None,
+            SqlType::Char(size) if text.trim_end_matches(' ').chars().count() > *size => {
+                return None
+            }
+            _ => {}
+        }
+        Self::VarChar(text.into()).cast(element.clone()).ok()
+    }
+
+    /// `text` as a `CHAR(size)`, padded with spaces. Its trailing spaces don't count toward
+    /// the size.
+    fn padded(text: &str, size: usize) -> Self {
+        let text = text.trim_end_matches(' ');
+        let padding = size.saturating_sub(text.chars().count());
+        Self::Char(format!("{text}{}", " ".repeat(padding)).into())
+    }
+
+    /// The value as an `i128`, if it is an integer that fits.
+    #[must_use]
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Self::Null
+            | Self::VarChar(_)
+            | Self::Char(_)
+            | Self::Bool(_)
+            | Self::F32(_)
+            | Self::F64(_)
+            | Self::Decimal(_)
+            | Self::Date(_)
+            | Self::Time(_)
+            | Self::Timestamp(_)
+            | Self::TimestampTz(_)
+            | Self::Interval(_)
+            | Self::Blob(_)
+            | Self::Json(_)
+            | Self::Array(_)
+            | Self::Parameter(_) => None,
+            Self::I8(v) => Some(i128::from(*v)),
+            Self::I16(v) => Some(i128::from(*v)),
+            Self::I32(v) => Some(i128::from(*v)),
+            Self::I64(v) => Some(i128::from(*v)),
+            Self::I128(v) => Some(*v),
+            Self::U8(v) => Some(i128::from(*v)),
+            Self::U16(v) => Some(i128::from(*v)),
+            Self::U32(v) => Some(i128::from(*v)),
+            Self::U64(v) => Some(i128::from(*v)),
+            Self::U128(v) => i128::try_from(*v).ok(),
+        }
+    }
+
+    /// The value as an `u128`, if it is an integer that fits.
+    #[must_use]
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Self::U128(v) => Some(*v),
+            _ => self.as_i128().and_then(|v| u128::try_from(v).ok()),
+        }
+    }
+
+    /// The value as an `f64`, if it is a number. Integers beyond 2^53 are rounded.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::F32(v) => Some(f64::from(*v)),
+            Self::F64(v) => Some(*v),
+            Self::Decimal(v) => Some(v.to_f64()),
+            Self::U128(v) => Some(*v as f64),
+            _ => self.as_i128().map(|v| v as f64),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_integer(&self) -> bool {
+        !matches!(
+            self,
+            Self::Null
+                | Self::VarChar(_)
+                | Self::Bool(_)
+                | Self::F32(_)
+                | Self::F64(_)
+                | Self::Decimal(_)
+                | Self::Date(_)
+                | Self::Time(_)
+                | Self::Timestamp(_)
+                | Self::TimestampTz(_)
+                | Self::Interval(_)
+                | Self::Blob(_)
+                | Self::Json(_)
+                | Self::Parameter(_)
+        )
+    }
+
+    /// Whether the value is a date, a time or an interval.
+    #[must_use]
+    pub const fn is_temporal(&self) -> bool {
+        matches!(
+            self,
+            Self::Date(_)
+                | Self::Time(_)
+                | Self::Timestamp(_)
+                | Self::TimestampTz(_)
+                | Self::Interval(_)
+        )
+    }
+
+    /// The value as a timestamp, if it is a date, at midnight, or a timestamp.
+    #[must_use]
+    pub const fn as_timestamp(&self) -> Option<Timestamp> {
+        match self {
+            Self::Date(v) => Some(Timestamp::new(*v, Time::MIDNIGHT)),
+            Self::Timestamp(v) | Self::TimestampTz(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Reads the text of a date, time or interval of type `tp`.
+    fn read_temporal(tp: &SqlType, text: &str) -> Result<Self, ParseError> {
+        match tp {
+            SqlType::Date => text.parse().map(Self::Date),
+            SqlType::Time => text.parse().map(Self::Time),
+            SqlType::Timestamp => text.parse().map(Self::Timestamp),
+            SqlType::TimestampTz => Timestamp::parse_with_offset(text).map(Self::TimestampTz),
+            SqlType::Interval => text.parse().map(Self::Interval),
+            _ => Err(ParseError::IncompatibleTypes),
+        }
+    }
+
+    /// Converts a date, time or interval to the type `tp`, `None` if the types don't
+    /// convert.
+    fn to_temporal(&self, tp: &SqlType) -> Option<Self> {
+        if matches!(self, Self::Time(_) | Self::Interval(_)) {
+            return (self.sql_type().as_ref() == Some(tp)).then(|| self.clone());
+        }
+        let timestamp = self.as_timestamp()?;
+        match tp {
+            SqlType::Date => Some(Self::Date(timestamp.date())),
+            SqlType::Time => Some(Self::Time(timestamp.time())),
+            SqlType::Timestamp => Some(Self::Timestamp(timestamp)),
+            SqlType::TimestampTz => Some(Self::TimestampTz(timestamp)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_float(&self) -> bool {
+        matches!(self, Self::F32(_) | Self::F64(_))
+    }
+
     #[must_use]
-    pub const fn len(&self) -> usize {
+    pub const fn is_decimal(&self) -> bool {
+        matches!(self, Self::Decimal(_))
+    }
+
+    /// The value as a `Decimal`, if it is a decimal or an integer of at most 38 digits.
+    #[must_use]
+    pub fn as_decimal(&self) -> Option<Decimal> {
         match self {
-            Self::VarChar(s) => s.len(),
-            Self::I8(_) | Self::U8(_) => 1,
+            Self::Decimal(v) => Some(*v),
+            _ => Decimal::from_integer(self.as_i128()?),
+        }
+    }
+
+    /// Converts a number to `DECIMAL(precision, scale)`, rounding floats and decimals to the
+    /// scale.
+    fn to_decimal(&self, precision: u8, scale: u8) -> Result<Self, CastError> {
+        match self {
+            Self::F32(_) | Self::F64(_) => self.as_f64().and_then(|v| Decimal::from_f64(v, scale)),
+            _ => self.as_decimal().and_then(|v| v.rescale(scale)),
+        }
+        .filter(|v| v.fits(precision, scale))
+        .map(Self::Decimal)
+        .ok_or(CastError::OutOfRange(SqlType::Decimal { precision, scale }))
+    }
+
+    /// Converts a number to the float type `tp`, `None` if it is too large for an `F32`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_float(&self, tp: &SqlType) -> Option<Self> {
+        let v = self.as_f64()?;
+        match tp {
+            SqlType::F32 => Some(v as f32).filter(|v| v.is_finite()).map(Self::F32),
+            SqlType::F64 => Some(Self::F64(v)),
+            _ => None,
+        }
+    }
+
+    /// Rounds a float to the nearest integer, `None` if it doesn't fit an `i128` or `u128`.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn round_float(&self) -> Option<Self> {
+        let v = self.as_f64()?.round();
+        if (i128::MIN as f64..i128::MAX as f64).contains(&v) {
+            Some(Self::I128(v as i128))
+        } else if (0.0..u128::MAX as f64).contains(&v) {
+            Some(Self::U128(v as u128))
+        } else {
+            None
+        }
+    }
+
+    /// Converts an integer to the integer type `tp`, if it fits.
+    fn to_integer(&self, tp: &SqlType) -> Option<Self> {
+        macro_rules! integer {
+            ($ty:ty, $variant:ident) => {
+                self.as_i128()
+                    .and_then(|v| <$ty>::try_from(v).ok())
+                    .or_else(|| self.as_u128().and_then(|v| <$ty>::try_from(v).ok()))
+                    .map(Self::$variant)
+            };
+        }
+        if self.is_float() {
+            return self.round_float()?.to_integer(tp);
+        }
+        if let Self::Decimal(v) = self {
+            return Self::I128(v.round()).to_integer(tp);
+        }
+        match tp {
+            SqlType::VarChar(..)
+            | SqlType::Char(_)
+            | SqlType::Bool
+            | SqlType::F32
+            | SqlType::F64
+            | SqlType::Decimal { .. }
+            | SqlType::Date
+            | SqlType::Time
+            | SqlType::Timestamp
+            | SqlType::TimestampTz
+            | SqlType::Interval
+            | SqlType::Blob
+            | SqlType::Json
+            | SqlType::Enum(_)
+            | SqlType::Array(_) => None,
+            SqlType::I8 => integer!(i8, I8),
+            SqlType::I16 => integer!(i16, I16),
+            SqlType::I32 => integer!(i32, I32),
+            SqlType::I64 => integer!(i64, I64),
+            SqlType::I128 => integer!(i128, I128),
+            SqlType::U8 => integer!(u8, U8),
+            SqlType::U16 => integer!(u16, U16),
+            SqlType::U32 => integer!(u32, U32),
+            SqlType::U64 => integer!(u64, U64),
+            SqlType::U128 => integer!(u128, U128),
+        }
+    }
+
+    /// Converts the value to `tp` as `CAST` does, following [`SqlType::cast_kind`]. Strings
+    /// longer than the target are truncated, fixed-length strings are padded and lose their
+    /// padding when they become another type, floats and decimals are rounded to the nearest
+    /// integer or to the scale of a decimal, `NULL` and parameters convert to every type.
+    /// # Errors
+    /// Returns an error if the conversion is forbidden, the value doesn't fit an integer type
+    /// or a string can't be read as the target type.
+    pub fn cast(&self, tp: SqlType) -> Result<Self, CastError> {
+        let Some(from) = self.sql_type() else {
+            return Ok(self.clone());
+        };
+        if from.cast_kind(&tp) == CastKind::Forbidden {
+            return Err(CastError::Forbidden { from, to: tp });
+        }
+        if let Self::Char(s) = self {
+            if !matches!(tp, SqlType::Char(_)) {
+                return Self::VarChar(s.trim_end_matches(' ').into()).cast(tp);
+            }
+        }
+        let invalid_text = || CastError::InvalidText(tp.clone());
+        let out_of_range = || CastError::OutOfRange(tp.clone());
+        match (self, &tp) {
+            (_, SqlType::VarChar(size, unit)) => Ok(Self::VarChar(
+                unit.truncate(&self.to_string(), *size).into(),
+            )),
+            (_, SqlType::Char(size)) => Ok(Self::padded(
+                LengthUnit::Chars.truncate(&self.to_string(), *size),
+                *size,
+            )),
+            (Self::VarChar(s), SqlType::Bool) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "t" => Ok(Self::Bool(true)),
+                "false" | "f" => Ok(Self::Bool(false)),
+                _ => Err(invalid_text()),
+            },
+            (Self::VarChar(s), SqlType::F32 | SqlType::F64) => Self::F64(
+                s.trim()
+                    .parse()
+                    .ok()
+                    .filter(|v: &f64| v.is_finite())
+                    .ok_or_else(invalid_text)?,
+            )
+            .to_float(&tp)
+            .ok_or_else(out_of_range),
+            (Self::VarChar(s), SqlType::Decimal { precision, scale }) => {
+                Self::Decimal(s.trim().parse().map_err(|_| invalid_text())?)
+                    .to_decimal(*precision, *scale)
+            }
+            (Self::VarChar(s), SqlType::Blob) => s
+                .trim()
+                .strip_prefix("\\x")
+                .and_then(decode_hex)
+                .map(Self::Blob)
+                .ok_or_else(invalid_text),
+            (Self::VarChar(s), SqlType::Enum(labels)) => labels
+                .contains(s)
+                .then(|| self.clone())
+                .ok_or_else(invalid_text),
+            (Self::VarChar(s), SqlType::Json) => serde_json::from_str(s)
+                .map(Self::Json)
+                .map_err(|_| invalid_text()),
+            (Self::VarChar(s), SqlType::Array(element)) => read_array(element, s)
+                .map(Self::Array)
+                .ok_or_else(invalid_text),
+            (Self::Array(items), SqlType::Array(element)) => items
+                .iter()
+                .map(|item| item.cast((**element).clone()))
+                .collect::<Result<_, _>>()
+                .map(Self::Array),
+            (Self::VarChar(s), tp) if tp.is_temporal() => {
+                Self::read_temporal(tp, s.trim()).map_err(|_| invalid_text())
+            }
+            (Self::VarChar(s), tp) => {
+                let s = s.trim();
+                let value = s
+                    .parse()
+                    .map(Self::I128)
+                    .or_else(|_| s.parse().map(Self::U128))
+                    .map_err(|_| invalid_text())?;
+                value.to_integer(tp).ok_or_else(out_of_range)
+            }
+            (_, to) if to.is_temporal() => self.to_temporal(to).ok_or(CastError::Forbidden {
+                from,
+                to: tp.clone(),
+            }),
+            (Self::Bool(_) | Self::Blob(_) | Self::Json(_), _) => Ok(self.clone()),
+            (_, SqlType::F32 | SqlType::F64) => self.to_float(&tp).ok_or_else(out_of_range),
+            (_, SqlType::Decimal { precision, scale }) => self.to_decimal(*precision, *scale),
+            (_, tp) => self.to_integer(tp).ok_or_else(out_of_range),
+        }
+    }
+
+    /// Converts the value for an assignment, like an `INSERT`. Only implicit conversions are
+    /// accepted, plus narrowing between integers and decimals when the value fits, since
+    /// integer literals are `I64` and decimal literals have their own precision, and `F64`
+    /// to `F32`, since float literals are `F64`, and strings to dates, times, JSON, enums and
+    /// arrays, since they can be written as plain strings. Arrays convert element by element,
+    /// and strings to fixed-length strings as long as they fit without their trailing spaces.
+    /// # Errors
+    /// Returns an error if the conversion is not implicit or the integer doesn't fit.
+    pub fn coerce(&self, tp: SqlType) -> Result<Self, CastError> {
+        if let (Self::Array(items), SqlType::Array(element)) = (self, &tp) {
+            return items
+                .iter()
+                .map(|item| item.coerce((**element).clone()))
+                .collect::<Result<_, _>>()
+                .map(Self::Array);
+        }
+        let Some(from) = self.sql_type() else {
+            return Ok(self.clone());
+        };
+        match from.cast_kind(&tp) {
+            CastKind::Implicit => self.cast(tp),
+            CastKind::Explicit
+                if (from.is_integer() || from.is_decimal())
+                    && (tp.is_integer() || tp.is_decimal()) =>
+            {
+                self.cast(tp)
+            }
+            CastKind::Lossy if from == SqlType::F64 && tp == SqlType::F32 => self.cast(tp),
+            CastKind::Lossy
+                if matches!(
+                    (self, &tp),
+                    (Self::VarChar(s) | Self::Char(s), SqlType::Char(size))
+                        if s.trim_end_matches(' ').chars().count() <= *size
+                ) =>
+            {
+                self.cast(tp)
+            }
+            CastKind::Explicit
+                if matches!(from, SqlType::VarChar(..))
+                    && (tp.is_temporal()
+                        || matches!(tp, SqlType::Json | SqlType::Enum(_) | SqlType::Array(_))) =>
+            {
+                self.cast(tp)
+            }
+            CastKind::Explicit => Err(CastError::NotImplicit { from, to: tp }),
+            CastKind::Lossy => Err(CastError::Lossy { from, to: tp }),
+            CastKind::Forbidden => Err(CastError::Forbidden { from, to: tp }),
+        }
+    }
+
+    /// Brings two numbers to the same type: they keep their type if they already share one,
+    /// otherwise a float and a number both become `F64`, a decimal and an integer both
+    /// become `Decimal`, and two integers become `I128`, or `U128` when a value doesn't fit
+    /// `I128`.
+    fn unify(&self, other: &Self) -> Result<(Self, Self), ParseError> {
+        let is_number = |value: &Self| value.is_integer() || value.is_float() || value.is_decimal();
+        if !is_number(self) || !is_number(other) {
+            return Err(ParseError::InvalidOperand);
+        }
+        if std::mem::discriminant(self) == std::mem::discriminant(other) {
+            return Ok((self.clone(), other.clone()));
+        }
+        if self.is_float() || other.is_float() {
+            return match (self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b)) => Ok((Self::F64(a), Self::F64(b))),
+                _ => Err(ParseError::InvalidOperand),
+            };
+        }
+        if self.is_decimal() || other.is_decimal() {
+            return match (self.as_decimal(), other.as_decimal()) {
+                (Some(a), Some(b)) => Ok((Self::Decimal(a), Self::Decimal(b))),
+                _ => Err(ParseError::ArithmeticOverflow),
+            };
+        }
+        if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+            return Ok((Self::I128(a), Self::I128(b)));
+        }
+        match (self.as_u128(), other.as_u128()) {
+            (Some(a), Some(b)) => Ok((Self::U128(a), Self::U128(b))),
+            _ => Err(ParseError::ArithmeticOverflow),
+        }
+    }
+
+    /// Applies an arithmetic operator, `NULL` operands give `NULL`.
+    /// # Errors
+    /// Returns an error on overflow, division by zero or non-integer operands.
+    pub fn checked_arithmetic(&self, op: BinaryOperator, other: &Self) -> Result<Self, ParseError> {
+        if self.is_null() || other.is_null() {
+            return Ok(Self::Null);
+        }
+        if self.is_temporal() || other.is_temporal() {
+            return self.temporal_arithmetic(op, other);
+        }
+        let (lhs, rhs) = self.unify(other)?;
+        if matches!(op, BinaryOperator::Divide | BinaryOperator::Modulo)
+            && rhs.as_f64().is_some_and(|v| v == 0.0)
+        {
+            return Err(ParseError::DivisionByZero);
+        }
+        // Floats don't wrap, they become infinite.
+        macro_rules! float {
+            ($a:expr, $b:expr) => {
+                match op {
+                    BinaryOperator::Plus => $a + $b,
+                    BinaryOperator::Minus => $a - $b,
+                    BinaryOperator::Multiply => $a * $b,
+                    BinaryOperator::Divide => $a / $b,
+                    BinaryOperator::Modulo => $a % $b,
+                    _ => return Err(ParseError::InvalidOperand),
+                }
+            };
+        }
+        match (&lhs, &rhs) {
+            (Self::F32(a), Self::F32(b)) => {
+                return Some(float!(a, b))
+                    .filter(|v| v.is_finite())
+                    .map(Self::F32)
+                    .ok_or(ParseError::ArithmeticOverflow)
+            }
+            (Self::F64(a), Self::F64(b)) => {
+                return Some(float!(a, b))
+                    .filter(|v| v.is_finite())
+                    .map(Self::F64)
+                    .ok_or(ParseError::ArithmeticOverflow)
+            }
+            (Self::Decimal(a), Self::Decimal(b)) => {
+                return match op {
+                    BinaryOperator::Plus => a.checked_add(b),
+                    BinaryOperator::Minus => a.checked_sub(b),
+                    BinaryOperator::Multiply => a.checked_mul(b),
+                    BinaryOperator::Divide => a.checked_div(b),
+                    BinaryOperator::Modulo => a.checked_rem(b),
+                    _ => return Err(ParseError::InvalidOperand),
+                }
+                .map(Self::Decimal)
+                .ok_or(ParseError::ArithmeticOverflow)
+            }
+            _ => {}
+        }
+        macro_rules! apply {
+            ($($variant:ident),*) => {
+                match (lhs, rhs) {
+                    $((Self::$variant(a), Self::$variant(b)) => match op {
+                        BinaryOperator::Plus => a.checked_add(b),
+                        BinaryOperator::Minus => a.checked_sub(b),
+                        BinaryOperator::Multiply => a.checked_mul(b),
+                        BinaryOperator::Divide => a.checked_div(b),
+                        BinaryOperator::Modulo => a.checked_rem(b),
+                        _ => return Err(ParseError::InvalidOperand),
+                    }
+                    .map(Self::$variant),)*
+                    _ => return Err(ParseError::InvalidOperand),
+                }
+            };
+        }
+        apply!(I8, I16, I32, I64, I128, U8, U16, U32, U64, U128)
+            .ok_or(ParseError::ArithmeticOverflow)
+    }
+
+    /// `self + other`, see [`Value::checked_arithmetic`].
+    /// # Errors
+    /// Returns an error on overflow or operands that can't be added.
+    pub fn add(&self, other: &Self) -> Result<Self, ParseError> {
+        self.checked_arithmetic(BinaryOperator::Plus, other)
+    }
+
+    /// `self - other`, see [`Value::checked_arithmetic`].
+    /// # Errors
+    /// Returns an error on overflow or operands that can't be subtracted.
+    pub fn sub(&self, other: &Self) -> Result<Self, ParseError> {
+        self.checked_arithmetic(BinaryOperator::Minus, other)
+    }
+
+    /// `self * other`, see [`Value::checked_arithmetic`].
+    /// # Errors
+    /// Returns an error on overflow or operands that aren't numbers.
+    pub fn mul(&self, other: &Self) -> Result<Self, ParseError> {
+        self.checked_arithmetic(BinaryOperator::Multiply, other)
+    }
+
+    /// `self / other`, integers are divided truncating toward zero, see
+    /// [`Value::checked_arithmetic`].
+    /// # Errors
+    /// Returns an error on overflow, division by zero or operands that aren't numbers.
+    pub fn div(&self, other: &Self) -> Result<Self, ParseError> {
+        self.checked_arithmetic(BinaryOperator::Divide, other)
+    }
+
+    /// `self % other`, with the sign of `self`, see [`Value::checked_arithmetic`].
+    /// # Errors
+    /// Returns an error on overflow, division by zero or operands that aren't numbers.
+    pub fn rem(&self, other: &Self) -> Result<Self, ParseError> {
+        self.checked_arithmetic(BinaryOperator::Modulo, other)
+    }
+
+    /// Applies an arithmetic operator to dates, times and intervals, following
+    /// [`SqlType::arithmetic`].
+    fn temporal_arithmetic(&self, op: BinaryOperator, other: &Self) -> Result<Self, ParseError> {
+        use BinaryOperator::{Minus, Multiply, Plus};
+        let shift = |date: &Date, days: &Self, sign: i128| {
+            days.as_i128()
+                .and_then(|days| days.checked_mul(sign))
+                .and_then(|days| days.checked_add(i128::from(date.days())))
+                .and_then(|days| i32::try_from(days).ok())
+                .and_then(Date::from_days)
+                .map(Self::Date)
+                .ok_or(ParseError::ArithmeticOverflow)
+        };
+        let interval = |interval: Option<Interval>| {
+            interval
+                .map(Self::Interval)
+                .ok_or(ParseError::ArithmeticOverflow)
+        };
+        match (self, op, other) {
+            (Self::Date(a), Minus, Self::Date(b)) => Ok(Self::I32(a.days() - b.days())),
+            (Self::Date(date), Plus, days) | (days, Plus, Self::Date(date))
+                if days.is_integer() =>
+            {
+                shift(date, days, 1)
+            }
+            (Self::Date(date), Minus, days) if days.is_integer() => shift(date, days, -1),
+            (Self::Interval(a), Plus, Self::Interval(b)) => interval(a.checked_add(b)),
+            (Self::Interval(a), Minus, Self::Interval(b)) => interval(a.checked_sub(b)),
+            (Self::Interval(a), Multiply, factor) | (factor, Multiply, Self::Interval(a))
+                if factor.is_integer() =>
+            {
+                interval(factor.as_i128().and_then(|factor| a.checked_mul(factor)))
+            }
+            (Self::Time(a), Minus, Self::Time(b)) => Ok(Self::Interval(Interval::from_micros(
+                a.micros() - b.micros(),
+            ))),
+            (Self::Timestamp(a), Minus, Self::Timestamp(b))
+            | (Self::TimestampTz(a), Minus, Self::TimestampTz(b)) => Ok(Self::Interval(a.since(b))),
+            (value, Plus, Self::Interval(interval)) | (Self::Interval(interval), Plus, value) => {
+                value.add_interval(interval)
+            }
+            (value, Minus, Self::Interval(interval)) => value.add_interval(
+                &interval
+                    .checked_neg()
+                    .ok_or(ParseError::ArithmeticOverflow)?,
+            ),
+            _ => Err(ParseError::InvalidOperand),
+        }
+    }
+
+    /// Moves a time or a timestamp by `interval`, a date becomes a timestamp at midnight.
+    fn add_interval(&self, interval: &Interval) -> Result<Self, ParseError> {
+        let add = |timestamp: Timestamp| {
+            timestamp
+                .checked_add_interval(interval)
+                .ok_or(ParseError::ArithmeticOverflow)
+        };
+        match self {
+            Self::Time(time) => Ok(Self::Time(time.wrapping_add(interval))),
+            Self::Date(_) | Self::Timestamp(_) => self
+                .as_timestamp()
+                .map_or(Err(ParseError::InvalidOperand), add)
+                .map(Self::Timestamp),
+            Self::TimestampTz(timestamp) => add(*timestamp).map(Self::TimestampTz),
+            _ => Err(ParseError::InvalidOperand),
+        }
+    }
+
+    /// Extracts the field named `key` of a JSON object, or the element at index `key` of a
+    /// JSON array, counting from the end when negative. A missing field or element is `NULL`,
+    /// as text strings lose their quotes and the JSON `null` is `NULL`.
+    /// # Errors
+    /// Returns an error if the value is not JSON or the key is not a string or an integer.
+    pub fn json_get(&self, key: &Self, as_text: bool) -> Result<Self, ParseError> {
+        let element = match (self, key) {
+            (Self::Null, _) | (_, Self::Null) => return Ok(Self::Null),
+            (Self::Json(json), Self::VarChar(key)) => json.get(&**key),
+            (Self::Json(json), key) if key.is_integer() => {
+                let index = key.as_i128().ok_or(ParseError::InvalidOperand)?;
+                json.as_array().and_then(|array| {
+                    let index = if index < 0 {
+                        array
+                            .len()
+                            .checked_sub(usize::try_from(index.unsigned_abs()).ok()?)?
+                    } else {
+                        usize::try_from(index).ok()?
+                    };
+                    array.get(index)
+                })
+            }
+            _ => return Err(ParseError::InvalidOperand),
+        };
+        Ok(match element {
+            None | Some(serde_json::Value::Null) if as_text => Self::Null,
+            None => Self::Null,
+            Some(serde_json::Value::String(s)) if as_text => Self::VarChar(s.as_str().into()),
+            Some(json) if as_text => Self::VarChar(json.to_string().into()),
+            Some(json) => Self::Json(json.clone()),
+        })
+    }
+
+    /// The element of an array at `index`, counting from 1. An index out of the array is
+    /// `NULL`.
+    /// # Errors
+    /// Returns an error if the value is not an array or the index is not an integer.
+    pub fn subscript(&self, index: &Self) -> Result<Self, ParseError> {
+        match (self, index) {
+            (Self::Null, _) | (_, Self::Null) => Ok(Self::Null),
+            (Self::Array(items), index) if index.is_integer() => Ok(index
+                .as_i128()
+                .and_then(|index| usize::try_from(index.checked_sub(1)?).ok())
+                .and_then(|index| items.get(index))
+                .cloned()
+                .unwrap_or(Self::Null)),
+            _ => Err(ParseError::InvalidOperand),
+        }
+    }
+
+    /// Compares two values of compatible types, `None` if either is `NULL`. Trailing spaces
+    /// of fixed-length strings are ignored, also against other strings. JSON documents
+    /// are ordered by their text, arrays element by element with `NULL` elements after the
+    /// others, and an array before the longer arrays it starts.
+    /// # Errors
+    /// Returns an error if the values can't be compared, like a string and an integer.
+    pub fn compare(&self, other: &Self) -> Result<Option<std::cmp::Ordering>, ParseError> {
+        match (self, other) {
+            (Self::Null, _) | (_, Self::Null) => Ok(None),
+            (Self::VarChar(a), Self::VarChar(b)) => Ok(Some(a.cmp(b))),
+            (Self::VarChar(a) | Self::Char(a), Self::Char(b))
+            | (Self::Char(a), Self::VarChar(b)) => {
+                Ok(Some(a.trim_end_matches(' ').cmp(b.trim_end_matches(' '))))
+            }
+            (Self::Bool(a), Self::Bool(b)) => Ok(Some(a.cmp(b))),
+            (Self::Blob(a), Self::Blob(b)) => Ok(Some(a.cmp(b))),
+            (Self::Json(a), Self::Json(b)) => Ok(Some(a.to_string().cmp(&b.to_string()))),
+            (Self::Array(a), Self::Array(b)) => {
+                for (a, b) in a.iter().zip(b.iter()) {
+                    let ordering = match (a.is_null(), b.is_null()) {
+                        (true, true) => std::cmp::Ordering::Equal,
+                        (true, false) => std::cmp::Ordering::Greater,
+                        (false, true) => std::cmp::Ordering::Less,
+                        (false, false) => a.compare(b)?.unwrap_or(std::cmp::Ordering::Equal),
+                    };
+                    if ordering.is_ne() {
+                        return Ok(Some(ordering));
+                    }
+                }
+                Ok(Some(a.len().cmp(&b.len())))
+            }
+            (Self::Time(a), Self::Time(b)) => Ok(Some(a.cmp(b))),
+            (Self::Interval(a), Self::Interval(b)) => Ok(Some(a.cmp_length(b))),
+            // Dates are compared to timestamps at midnight.
+            _ if self.is_temporal() || other.is_temporal() => {
+                match (self.as_timestamp(), other.as_timestamp()) {
+                    (Some(a), Some(b)) => Ok(Some(a.cmp(&b))),
+                    _ => Err(ParseError::InvalidOperand),
+                }
+            }
+            _ if self.is_float() || other.is_float() => match (self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b)) => a
+                    .partial_cmp(&b)
+                    .map(Some)
+                    .ok_or(ParseError::InvalidOperand),
+                _ => Err(ParseError::InvalidOperand),
+            },
+            _ if self.is_decimal() || other.is_decimal() => {
+                match (self.as_decimal(), other.as_decimal()) {
+                    (Some(a), Some(b)) => Ok(Some(a.cmp(&b))),
+                    // An integer too large for a decimal is far enough from it for floats.
+                    _ => match (self.as_f64(), other.as_f64()) {
+                        (Some(a), Some(b)) => Ok(a.partial_cmp(&b)),
+                        _ => Err(ParseError::InvalidOperand),
+                    },
+                }
+            }
+            _ => match (self.as_i128(), other.as_i128()) {
+                (Some(a), Some(b)) => Ok(Some(a.cmp(&b))),
+                // At least one side only fits `u128`, and a negative value is below it.
+                (Some(_), None) if other.is_integer() => Ok(Some(std::cmp::Ordering::Less)),
+                (None, Some(_)) if self.is_integer() => Ok(Some(std::cmp::Ordering::Greater)),
+                _ => match (self.as_u128(), other.as_u128()) {
+                    (Some(a), Some(b)) => Ok(Some(a.cmp(&b))),
+                    _ => Err(ParseError::InvalidOperand),
+                },
+            },
+        }
+    }
+
+    /// Feeds the value to `state` so that the values [`Value::compare`] finds equal hash the
+    /// same, like `1` as an `I32` and `1.0` as an `F64`, or a date and the timestamp at its
+    /// midnight. [`Hash`] tells them apart, as they are not equal.
+    pub fn hash_comparable<H: Hasher>(&self, state: &mut H) {
+        self.sort_rank().0.hash(state);
+        match self {
+            Self::VarChar(s) | Self::Char(s) => s.trim_end_matches(' ').hash(state),
+            Self::Array(values) => {
+                values.len().hash(state);
+                for value in values.iter() {
+                    value.hash_comparable(state);
+                }
+            }
+            Self::Interval(v) => v.length_micros().hash(state),
+            _ if self.is_temporal() => self.as_timestamp().hash(state),
+            _ => match self.as_f64() {
+                Some(v) => f64_bits(v).hash(state),
+                None => self.hash(state),
+            },
+        }
+    }
+
+    /// Compares two values the way `ORDER BY` sorts them: unlike [`Value::compare`] the
+    /// result is final and `NULL` takes part in it. `NULL`s are equal and come before the
+    /// other values when `nulls_first`, after them otherwise, whatever the `order`, which
+    /// only reverses the ordering of two values that are not `NULL`. A float `NaN` is
+    /// larger than every number, strings, also within arrays, are compared by `collation`
+    /// and numbers of any width by value.
+    /// # Errors
+    /// Returns an error if the values can't be compared, like a string and an integer.
+    pub fn sql_cmp(
+        &self,
+        other: &Self,
+        order: SortOrder,
+        nulls_first: bool,
+        collation: Collation,
+    ) -> Result<Ordering, ParseError> {
+        let ordering = match (self, other) {
+            (Self::Null, Self::Null) => return Ok(Ordering::Equal),
+            (Self::Null, _) => {
+                return Ok(if nulls_first {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                })
+            }
+            (_, Self::Null) => {
+                return Ok(if nulls_first {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                })
+            }
+            (Self::VarChar(a), Self::VarChar(b)) => collation.compare(a, b),
+            (Self::VarChar(a) | Self::Char(a), Self::Char(b))
+            | (Self::Char(a), Self::VarChar(b)) => {
+                collation.compare(a.trim_end_matches(' '), b.trim_end_matches(' '))
+            }
+            (Self::Array(a), Self::Array(b)) => {
+                let mut ordering = a.len().cmp(&b.len());
+                for (a, b) in a.iter().zip(b.iter()) {
+                    let element = a.sql_cmp(b, SortOrder::Asc, false, collation)?;
+                    if element.is_ne() {
+                        ordering = element;
+                        break;
+                    }
+                }
+                ordering
+            }
+            _ if self.is_float() || other.is_float() => {
+                let is_nan = |value: &Self| value.as_f64().is_some_and(f64::is_nan);
+                if is_nan(self) || is_nan(other) {
+                    if self.as_f64().is_none() || other.as_f64().is_none() {
+                        return Err(ParseError::InvalidOperand);
+                    }
+                    is_nan(self).cmp(&is_nan(other))
+                } else {
+                    self.compare(other)?.ok_or(ParseError::InvalidOperand)?
+                }
+            }
+            _ => self.compare(other)?.ok_or(ParseError::InvalidOperand)?,
+        };
+        Ok(match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        })
+    }
+
+    /// The kind of the value and its variant within the kind, see the [`Ord`] implementation.
+    pub(crate) const fn sort_rank(&self) -> (u8, u8) {
+        match self {
+            Self::Bool(_) => (0, 0),
+            Self::I8(_) => (1, 0),
+            Self::I16(_) => (1, 1),
+            Self::I32(_) => (1, 2),
+            Self::I64(_) => (1, 3),
+            Self::I128(_) => (1, 4),
+            Self::U8(_) => (1, 5),
+            Self::U16(_) => (1, 6),
+            Self::U32(_) => (1, 7),
+            Self::U64(_) => (1, 8),
+            Self::U128(_) => (1, 9),
+            Self::Decimal(_) => (1, 10),
+            Self::F32(_) => (1, 11),
+            Self::F64(_) => (1, 12),
+            Self::VarChar(_) => (2, 0),
+            Self::Char(_) => (2, 1),
+            Self::Blob(_) => (3, 0),
+            Self::Date(_) => (4, 0),
+            Self::Timestamp(_) => (4, 1),
+            Self::TimestampTz(_) => (4, 2),
+            Self::Time(_) => (5, 0),
+            Self::Interval(_) => (6, 0),
+            Self::Json(_) => (7, 0),
+            Self::Array(_) => (8, 0),
+            Self::Parameter(_) => (9, 0),
+            Self::Null => (10, 0),
+        }
+    }
+
+    /// The value as a truth value of three-valued logic, `NULL` is UNKNOWN (`None`).
+    /// # Errors
+    /// Returns an error if the value is not a boolean.
+    pub const fn truth(&self) -> Result<Option<bool>, ParseError> {
+        match self {
+            Self::Null => Ok(None),
+            Self::Bool(b) => Ok(Some(*b)),
+            _ => Err(ParseError::InvalidOperand),
+        }
+    }
+
+    /// Negates the value, unsigned integers become `I128`. `NULL` stays `NULL`.
+    /// # Errors
+    /// Returns an error on overflow or if the value is not an integer.
+    pub fn checked_neg(&self) -> Result<Self, ParseError> {
+        match self {
+            Self::Null => Some(Self::Null),
+            Self::VarChar(_)
+            | Self::Char(_)
+            | Self::Bool(_)
+            | Self::Date(_)
+            | Self::Time(_)
+            | Self::Timestamp(_)
+            | Self::TimestampTz(_)
+            | Self::Array(_)
+            | Self::Parameter(_) => return Err(ParseError::InvalidOperand),
+            Self::I8(v) => v.checked_neg().map(Self::I8),
+            Self::I16(v) => v.checked_neg().map(Self::I16),
+            Self::I32(v) => v.checked_neg().map(Self::I32),
+            Self::I64(v) => v.checked_neg().map(Self::I64),
+            Self::I128(v) => v.checked_neg().map(Self::I128),
+            Self::F32(v) => Some(Self::F32(-v)),
+            Self::F64(v) => Some(Self::F64(-v)),
+            Self::Interval(v) => v.checked_neg().map(Self::Interval),
+            Self::Decimal(v) => v.checked_neg().map(Self::Decimal),
+            _ => self.as_i128().and_then(i128::checked_neg).map(Self::I128),
+        }
+        .ok_or(ParseError::ArithmeticOverflow)
+    }
+
+    #[must_use]
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Null | Self::Parameter(_) => 0,
+            Self::VarChar(s) | Self::Char(s) => s.len(),
+            Self::Blob(v) => v.len(),
+            Self::Json(v) => v.to_string().len(),
+            Self::Array(v) => v.iter().map(Self::len).sum(),
+            Self::Bool(_) | Self::I8(_) | Self::U8(_) => 1,
             Self::I16(_) | Self::U16(_) => 2,
-            Self::I32(_) | Self::U32(_) => 4,
-            Self::I64(_) | Self::U64(_) => 8,
-            Self::I128(_) | Self::U128(_) => 16,
+            Self::I32(_) | Self::U32(_) | Self::F32(_) | Self::Date(_) => 4,
+            Self::I64(_)
+            | Self::U64(_)
+            | Self::F64(_)
+            | Self::Time(_)
+            | Self::Timestamp(_)
+            | Self::TimestampTz(_) => 8,
+            Self::I128(_) | Self::U128(_) | Self::Decimal(_) | Self::Interval(_) => 16,
         }
     }
 
     #[must_use]
     pub const fn is_empty(&self) -> bool {
         match self {
+            Self::Null => true,
             Self::VarChar(s) => s.is_empty(),
             _ => false,
         }
@@ -98,7 +1712,7 @@ mod tests {
 
     #[allow(clippy::needless_pass_by_value)]
     fn test_case(suffix: &str, tp: SqlType, input: &str) {
-        let (_, v) = Value::parse_with_type(tp, RawSpan::new(input)).unwrap();
+        let (_, v) = Value::parse_with_type(tp.clone(), RawSpan::new(input)).unwrap();
         let mut settings = insta::Settings::new();
         settings.set_snapshot_suffix(suffix);
         settings.set_description(format!("Input: {input}\nType: {tp:?}",));
@@ -109,13 +1723,117 @@ mod tests {
 
     #[test]
     fn test_value_var_char() {
-        test_case("simple-str", SqlType::VarChar(5), "'hello'");
+        use crate::parsers::string::{with_escape_mode, EscapeMode};
+
+        test_case(
+            "simple-str",
+            SqlType::VarChar(5, LengthUnit::Chars),
+            "'hello'",
+        );
+
+        test_case(
+            "simple-str-2",
+            SqlType::VarChar(50, LengthUnit::Chars),
+            "'hello world'",
+        );
+
+        test_case(
+            "simple-str-3",
+            SqlType::VarChar(50, LengthUnit::Chars),
+            "'hello\nworld'",
+        );
+
+        assert!(Value::parse_with_type(
+            SqlType::VarChar(5, LengthUnit::Chars),
+            RawSpan::new("'123456789'")
+        )
+        .is_err());
+        let fits = |tp, input| Value::parse_with_type(tp, RawSpan::new(input)).is_ok();
+        assert!(fits(SqlType::VarChar(5, LengthUnit::Chars), "'héllo'"));
+        assert!(!fits(SqlType::VarChar(5, LengthUnit::Bytes), "'héllo'"));
+        assert!(fits(SqlType::VarChar(6, LengthUnit::Bytes), "'héllo'"));
+
+        let text = |input| {
+            Value::parse_with_type(SqlType::VarChar(4, LengthUnit::Chars), RawSpan::new(input))
+        };
+        assert_eq!(text("'it''s'").unwrap().1 .1, Value::VarChar("it's".into()));
+        assert_eq!(
+            text(r"'a\tb'").unwrap().1 .1,
+            Value::VarChar(r"a\tb".into())
+        );
+        with_escape_mode(EscapeMode::Backslash, || {
+            assert_eq!(text(r"'a\tb'").unwrap().1 .1, Value::VarChar("a\tb".into()));
+            assert!(text(r"'a\xb'").is_err());
+        });
+    }
+
+    #[test]
+    fn test_value_char() {
+        let char = |s: &str| Value::Char(s.into());
+        test_case("char", SqlType::Char(4), "'ab'");
+        assert_eq!(
+            Value::parse_with_type(SqlType::Char(2), RawSpan::new("'ab   '"))
+                .unwrap()
+                .1
+                 .1,
+            char("ab")
+        );
+        assert!(Value::parse_with_type(SqlType::Char(2), RawSpan::new("'abc'")).is_err());
+
+        assert_eq!(char("ab  "), char("ab"));
+        assert_eq!(char("ab").to_string(), "ab");
+        assert_eq!(
+            char("ab  ").compare(&Value::VarChar("ab".into())),
+            Ok(Some(std::cmp::Ordering::Equal))
+        );
+        assert_eq!(
+            char("ab  ").compare(&char("ab c")),
+            Ok(Some(std::cmp::Ordering::Less))
+        );
 
-        test_case("simple-str-2", SqlType::VarChar(50), "'hello world'");
+        assert_eq!(
+            Value::VarChar("ab".into()).coerce(SqlType::Char(4)),
+            Ok(char("ab  "))
+        );
+        assert_eq!(
+            Value::VarChar("ab    ".into()).coerce(SqlType::Char(3)),
+            Ok(char("ab "))
+        );
+        assert!(Value::VarChar("abcd".into())
+            .coerce(SqlType::Char(3))
+            .is_err());
+        assert_eq!(
+            Value::VarChar("abcd".into()).cast(SqlType::Char(3)),
+            Ok(char("abc"))
+        );
+        assert_eq!(Value::I64(7).cast(SqlType::Char(3)), Ok(char("7  ")));
+        assert_eq!(
+            char("ab  ").cast(SqlType::VarChar(10, LengthUnit::Chars)),
+            Ok(Value::VarChar("ab".into()))
+        );
+        assert_eq!(char("12  ").cast(SqlType::I32), Ok(Value::I32(12)));
+        assert_eq!(
+            char("ab").cast(SqlType::Char(4)).unwrap().to_string(),
+            "ab  "
+        );
+    }
+
+    #[test]
+    fn test_value_bool() {
+        test_case("true", SqlType::Bool, "TRUE");
+        assert!(Value::parse_with_type(SqlType::Bool, RawSpan::new("1")).is_err());
+        assert_eq!(
+            Value::parse(RawSpan::new("false")).unwrap().1,
+            Value::Bool(false)
+        );
+    }
 
-        test_case("simple-str-3", SqlType::VarChar(50), "'hello\nworld'");
+    #[test]
+    fn test_value_null() {
+        test_case("null-i32", SqlType::I32, "NULL");
+        test_case("null-str", SqlType::VarChar(5, LengthUnit::Chars), "null");
 
-        assert!(Value::parse_with_type(SqlType::VarChar(5), RawSpan::new("'123456789'")).is_err());
+        assert!(Value::parse_with_type(SqlType::I32, RawSpan::new("nullable")).is_err());
     }
 
     #[test]
@@ -135,5 +1853,825 @@ mod tests {
         test_case("pos-u32", SqlType::U32, "19");
         test_case("pos-u64", SqlType::U64, "19");
         test_case("pos-u128", SqlType::U128, "19");
+        test_case("hex-u8", SqlType::U8, "0xFF");
+
+        let parse = |tp, input| Value::parse_with_type(tp, RawSpan::new(input)).map(|v| v.1 .1);
+        assert_eq!(parse(SqlType::I32, "-0o17").ok(), Some(Value::I32(-15)));
+        assert_eq!(parse(SqlType::U16, "0b1010").ok(), Some(Value::U16(10)));
+        assert!(matches!(
+            parse(SqlType::I8, "0xFF"),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(matches!(
+            parse(SqlType::U8, "-1"),
+            Err(nom::Err::Failure(_))
+        ));
+        assert_eq!(
+            parse(SqlType::I64, "+1_000_000").ok(),
+            Some(Value::I64(1_000_000))
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("1_000")).unwrap().1,
+            Value::I64(1000)
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("1.5")).unwrap().1,
+            Value::Decimal("1.5".parse().unwrap())
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("1e3")).unwrap().1,
+            Value::F64(1000.0)
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("0x10")).unwrap().1,
+            Value::I64(16)
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"))
+                .unwrap()
+                .1,
+            Value::U128(u128::MAX)
+        );
+    }
+
+    #[test]
+    fn test_value_floats() {
+        test_case("f32", SqlType::F32, "-1.5e3");
+        test_case("f64", SqlType::F64, "42");
+        assert!(Value::parse_with_type(SqlType::F32, RawSpan::new("1e39")).is_err());
+        assert_eq!(
+            Value::parse(RawSpan::new("2.5e0")).unwrap().1,
+            Value::F64(2.5)
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new(".5e-1")).unwrap().1,
+            Value::F64(0.05)
+        );
+        assert_eq!(Value::parse(RawSpan::new("7")).unwrap().1, Value::I64(7));
+
+        let hash = |value: &Value| {
+            use std::collections::hash_map::DefaultHasher;
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(Value::F64(0.0), Value::F64(-0.0));
+        assert_eq!(hash(&Value::F64(0.0)), hash(&Value::F64(-0.0)));
+        assert_eq!(Value::F64(f64::NAN), Value::F64(-f64::NAN));
+        assert_eq!(hash(&Value::F64(f64::NAN)), hash(&Value::F64(-f64::NAN)));
+        assert_ne!(Value::F32(1.0), Value::F64(1.0));
+
+        assert_eq!(Value::F64(1.0).to_string(), "1.0");
+        assert_eq!(Value::F64(1e20).to_string(), "1e20");
+        assert_eq!(Value::F64(1.5).coerce(SqlType::F32), Ok(Value::F32(1.5)));
+        assert_eq!(Value::I64(2).coerce(SqlType::F64), Ok(Value::F64(2.0)));
+        assert!(Value::F64(1.5).coerce(SqlType::I64).is_err());
+        assert!(Value::F64(1e300).coerce(SqlType::F32).is_err());
+        assert_eq!(Value::F64(-2.5).cast(SqlType::I8), Ok(Value::I8(-3)));
+        assert!(Value::F64(1e300).cast(SqlType::U128).is_err());
+        assert_eq!(
+            Value::F32(0.5).compare(&Value::I8(1)),
+            Ok(Some(std::cmp::Ordering::Less))
+        );
+        assert_eq!(
+            Value::F32(0.5)
+                .checked_arithmetic(BinaryOperator::Plus, &Value::F32(0.25))
+                .unwrap(),
+            Value::F32(0.75)
+        );
+        assert_eq!(
+            Value::I64(1)
+                .checked_arithmetic(BinaryOperator::Divide, &Value::F32(4.0))
+                .unwrap(),
+            Value::F64(0.25)
+        );
+    }
+
+    #[test]
+    fn test_value_decimals() {
+        let decimal = |s: &str| Value::Decimal(s.parse().unwrap());
+        let tp = SqlType::Decimal {
+            precision: 10,
+            scale: 2,
+        };
+        test_case("decimal", tp.clone(), "-1234.5");
+        assert!(Value::parse_with_type(tp.clone(), RawSpan::new("1.234")).is_err());
+        assert!(Value::parse_with_type(tp.clone(), RawSpan::new("123456789")).is_err());
+        assert_eq!(
+            Value::parse(RawSpan::new("2.50")).unwrap().1,
+            decimal("2.50")
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("0.1234567890123456789012345678901234567890"))
+                .unwrap()
+                .1,
+            Value::F64(0.123_456_789_012_345_68)
+        );
+
+        assert_eq!(decimal("19.9").coerce(tp.clone()), Ok(decimal("19.90")));
+        assert_eq!(Value::I64(7).coerce(tp.clone()), Ok(decimal("7.00")));
+        assert!(decimal("0.125").coerce(tp.clone()).is_err());
+        assert!(Value::F64(1.5).coerce(tp.clone()).is_err());
+        assert_eq!(decimal("0.125").cast(tp.clone()), Ok(decimal("0.13")));
+        assert_eq!(Value::F64(-0.125).cast(tp.clone()), Ok(decimal("-0.13")));
+        assert_eq!(decimal("2.5").cast(SqlType::I8), Ok(Value::I8(3)));
+        assert_eq!(decimal("2.5").cast(SqlType::F32), Ok(Value::F32(2.5)));
+        assert_eq!(
+            Value::VarChar(" 3.14 ".into()).cast(tp.clone()),
+            Ok(decimal("3.14"))
+        );
+        assert!(Value::VarChar("pi".into()).cast(tp.clone()).is_err());
+        assert_eq!(decimal("12.30").to_string(), "12.30");
+        assert_eq!(decimal("1.50"), decimal("1.5"));
+
+        assert_eq!(
+            decimal("0.1")
+                .checked_arithmetic(BinaryOperator::Plus, &Value::I64(2))
+                .unwrap(),
+            decimal("2.1")
+        );
+        assert_eq!(
+            decimal("1.5").compare(&Value::U128(u128::MAX)),
+            Ok(Some(std::cmp::Ordering::Less))
+        );
+        assert_eq!(
+            decimal("-0.5").compare(&Value::I8(0)),
+            Ok(Some(std::cmp::Ordering::Less))
+        );
+    }
+
+    #[test]
+    fn test_value_dates() {
+        let date = |s: &str| Value::Date(s.parse().unwrap());
+        test_case("date", SqlType::Date, "DATE '2024-02-29'");
+        test_case("date-str", SqlType::Date, "'2024-01-31'");
+        assert!(Value::parse_with_type(SqlType::Date, RawSpan::new("'2023-02-29'")).is_err());
+        assert!(Value::parse_with_type(SqlType::Date, RawSpan::new("20240131")).is_err());
+        assert!(matches!(
+            Value::parse(RawSpan::new("date '1900-02-29'")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert_eq!(
+            Value::parse(RawSpan::new("date '2000-02-29'")).unwrap().1,
+            date("2000-02-29")
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("'2000-02-29'")).unwrap().1,
+            Value::VarChar("2000-02-29".into())
+        );
+
+        assert_eq!(
+            Value::VarChar("2024-01-31".into()).coerce(SqlType::Date),
+            Ok(date("2024-01-31"))
+        );
+        assert_eq!(
+            Value::VarChar("2024-02-30".into()).coerce(SqlType::Date),
+            Err(CastError::InvalidText(SqlType::Date))
+        );
+        assert!(date("2024-01-31")
+            .coerce(SqlType::VarChar(10, LengthUnit::Chars))
+            .is_err());
+        assert_eq!(
+            date("2024-01-31").cast(SqlType::VarChar(10, LengthUnit::Chars)),
+            Ok(Value::VarChar("2024-01-31".into()))
+        );
+        assert!(date("2024-01-31").cast(SqlType::I32).is_err());
+        assert_eq!(
+            date("2023-12-31").compare(&date("2024-01-01")),
+            Ok(Some(std::cmp::Ordering::Less))
+        );
+        assert_eq!(
+            date("2024-01-01").checked_arithmetic(BinaryOperator::Plus, &Value::I64(1)),
+            Ok(date("2024-01-02"))
+        );
+        assert!(date("2024-01-01")
+            .checked_arithmetic(BinaryOperator::Plus, &date("2024-01-01"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_value_times() {
+        let timestamp = |s: &str| Value::Timestamp(s.parse().unwrap());
+        let timestamp_tz = |s: &str| Value::TimestampTz(Timestamp::parse_with_offset(s).unwrap());
+        test_case("time", SqlType::Time, "'10:20:30.5'");
+        test_case(
+            "timestamp",
+            SqlType::Timestamp,
+            "TIMESTAMP '2024-01-31 10:20:30.123'",
+        );
+        test_case(
+            "timestamptz",
+            SqlType::TimestampTz,
+            "'2024-01-31 10:20:30.123+02'",
+        );
+        assert!(Value::parse_with_type(SqlType::Time, RawSpan::new("'25:00'")).is_err());
+        assert!(
+            Value::parse_with_type(SqlType::Timestamp, RawSpan::new("'2024-01-31 10:20+02'"))
+                .is_err()
+        );
+        assert_eq!(
+            Value::parse_with_type(SqlType::TimestampTz, RawSpan::new("TIMESTAMP '2024-01-31'"))
+                .unwrap()
+                .1
+                 .1,
+            timestamp_tz("2024-01-31 00:00:00Z")
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new(
+                "timestamp with time zone '2024-01-31 10:20:30-01'"
+            ))
+            .unwrap()
+            .1,
+            timestamp_tz("2024-01-31 11:20:30Z")
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("TIME '10:20'")).unwrap().1,
+            Value::Time("10:20:00".parse().unwrap())
+        );
+
+        assert_eq!(
+            timestamp_tz("2024-01-31 10:20:30.123+02").to_string(),
+            "2024-01-31 08:20:30.123+00"
+        );
+        assert_eq!(
+            timestamp("2024-01-31 10:20:30").cast(SqlType::Date),
+            Ok(Value::Date("2024-01-31".parse().unwrap()))
+        );
+        assert_eq!(
+            timestamp("2024-01-31 10:20:30").cast(SqlType::Time),
+            Ok(Value::Time("10:20:30".parse().unwrap()))
+        );
+        assert_eq!(
+            Value::Date("2024-01-31".parse().unwrap()).coerce(SqlType::Timestamp),
+            Ok(timestamp("2024-01-31 00:00:00"))
+        );
+        assert!(timestamp("2024-01-31 10:20:30")
+            .coerce(SqlType::Date)
+            .is_err());
+        assert_eq!(
+            Value::VarChar("2024-01-31 10:20:30+02".into()).coerce(SqlType::TimestampTz),
+            Ok(timestamp_tz("2024-01-31 08:20:30Z"))
+        );
+        assert_eq!(
+            timestamp("2024-01-31 00:00:00.000001")
+                .compare(&Value::Date("2024-01-31".parse().unwrap())),
+            Ok(Some(std::cmp::Ordering::Greater))
+        );
+        assert!(Value::Time("10:00".parse().unwrap())
+            .compare(&timestamp("2024-01-31 10:00"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_value_intervals() {
+        let interval = |s: &str| Value::Interval(s.parse().unwrap());
+        test_case("interval", SqlType::Interval, "INTERVAL '1 day 2 hours'");
+        test_case("interval-str", SqlType::Interval, "'-3 mons 00:00:01.5'");
+        assert!(Value::parse_with_type(SqlType::Interval, RawSpan::new("'1 eon'")).is_err());
+        assert!(matches!(
+            Value::parse(RawSpan::new("interval 'soon'")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert_eq!(
+            Value::parse(RawSpan::new("INTERVAL '2 weeks'")).unwrap().1,
+            interval("14 days")
+        );
+        assert_eq!(interval("1 day 36 hours").to_string(), "1 day 36:00:00");
+        assert_eq!(
+            interval("1 day").compare(&interval("24 hours")),
+            Ok(Some(std::cmp::Ordering::Equal))
+        );
+        assert_eq!(interval("1 day").checked_neg(), Ok(interval("-1 day")));
+        assert!(interval("1 day").cast(SqlType::Time).is_err());
+        assert_eq!(
+            Value::VarChar("1 hour".into()).coerce(SqlType::Interval),
+            Ok(interval("60 minutes"))
+        );
+    }
+
+    #[test]
+    fn test_value_blobs() {
+        let blob = |bytes: &[u8]| Value::Blob(bytes.into());
+        test_case("blob", SqlType::Blob, "x'DEADbeef'");
+        test_case("blob-escaped", SqlType::Blob, "'\\xdeadbeef'");
+        for input in ["X'ABC'", "x'0g'", "'\\x 00'"] {
+            assert!(matches!(
+                Value::parse_with_type(SqlType::Blob, RawSpan::new(input)),
+                Err(nom::Err::Failure(_))
+            ));
+        }
+        assert_eq!(Value::parse(RawSpan::new("x''")).unwrap().1, blob(&[]));
+        assert_eq!(
+            Value::parse(RawSpan::new("'\\x00ff'")).unwrap().1,
+            blob(&[0, 0xff])
+        );
+        assert_eq!(blob(&[0xde, 0xad, 0x0f]).to_string(), "\\xdead0f");
+        assert_eq!(blob(&[1, 2, 3]).len(), 3);
+        assert_eq!(
+            blob(&[1]).compare(&blob(&[1, 0])),
+            Ok(Some(std::cmp::Ordering::Less))
+        );
+        assert_eq!(
+            Value::VarChar("\\x0A0b".into()).cast(SqlType::Blob),
+            Ok(blob(&[10, 11]))
+        );
+        assert!(Value::VarChar("0A0b".into()).cast(SqlType::Blob).is_err());
+        assert!(blob(&[1]).cast(SqlType::I8).is_err());
+        assert_eq!(
+            blob(&[0xab]).cast(SqlType::VarChar(10, LengthUnit::Chars)),
+            Ok(Value::VarChar("\\xab".into()))
+        );
+    }
+
+    #[test]
+    fn test_value_json() {
+        test_case(
+            "json",
+            SqlType::Json,
+            r#"'{"a": [1, 2.5, "x"], "b": null}'"#,
+        );
+        for input in ["'{\"a\": }'", "'[1, 2'", "'nope'"] {
+            assert!(matches!(
+                Value::parse_with_type(SqlType::Json, RawSpan::new(input)),
+                Err(nom::Err::Failure(_))
+            ));
+        }
+        let json = |s: &str| Value::Json(serde_json::from_str(s).unwrap());
+        assert_eq!(
+            json(r#"{ "b": 1, "a": [true] }"#).to_string(),
+            r#"{"a":[true],"b":1}"#
+        );
+        assert_eq!(json("[1, 2]").len(), 5);
+        assert_eq!(
+            Value::VarChar("[1, 2]".into()).coerce(SqlType::Json),
+            Ok(json("[1,2]"))
+        );
+        assert!(Value::VarChar("[1,".into()).cast(SqlType::Json).is_err());
+        assert!(json("1").cast(SqlType::I64).is_err());
+        assert_eq!(
+            json(r#"{"a": "b"}"#).cast(SqlType::VarChar(100, LengthUnit::Chars)),
+            Ok(Value::VarChar(r#"{"a":"b"}"#.into()))
+        );
+        assert_eq!(
+            json(r#"{"a": 1}"#).json_get(&Value::VarChar("a".into()), false),
+            Ok(json("1"))
+        );
+        assert!(Value::I64(1).json_get(&Value::I64(0), false).is_err());
+    }
+
+    #[test]
+    fn test_value_enums() {
+        let mood = SqlType::Enum(["sad".into(), "ok".into()].into());
+        test_case("enum", mood.clone(), "'ok'");
+        assert!(matches!(
+            Value::parse_with_type(mood.clone(), RawSpan::new("'meh'")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert_eq!(
+            Value::VarChar("sad".into()).coerce(mood.clone()),
+            Ok(Value::VarChar("sad".into()))
+        );
+        assert_eq!(
+            Value::VarChar("meh".into()).cast(mood.clone()),
+            Err(CastError::InvalidText(mood))
+        );
+    }
+
+    #[test]
+    fn test_value_arrays() {
+        let array = |items: &[Value]| Value::Array(items.into());
+        let text = |s: &str| Value::VarChar(s.into());
+        let ints = SqlType::Array(Box::new(SqlType::I32));
+        test_case("array", ints.clone(), "ARRAY[1, NULL, 3]");
+        assert_eq!(
+            Value::parse_with_type(ints.clone(), RawSpan::new("' { 1 ,2,null } '"))
+                .unwrap()
+                .1
+                 .1,
+            array(&[Value::I32(1), Value::I32(2), Value::Null])
+        );
+        assert_eq!(
+            Value::parse_with_type(ints.clone(), RawSpan::new("ARRAY[]"))
+                .unwrap()
+                .1
+                 .1,
+            array(&[])
+        );
+        for input in ["'{1,a}'", "'{1,2'", "'{1,,2}'", "'1,2'", "ARRAY[1, 'a']"] {
+            assert!(
+                matches!(
+                    Value::parse_with_type(ints.clone(), RawSpan::new(input)),
+                    Err(nom::Err::Failure(_))
+                ),
+                "{input}"
+            );
+        }
+
+        assert_eq!(
+            Value::parse(RawSpan::new("ARRAY[1, 2.5, NULL]")).unwrap().1,
+            array(&[
+                Value::Decimal("1.0".parse().unwrap()),
+                Value::Decimal("2.5".parse().unwrap()),
+                Value::Null
+            ])
+        );
+        assert_eq!(
+            Value::parse(RawSpan::new("ARRAY[ARRAY['a'], ARRAY['bc', NULL]]"))
+                .unwrap()
+                .1
+                .sql_type(),
+            Some(SqlType::Array(Box::new(SqlType::Array(Box::new(
+                SqlType::VarChar(2, LengthUnit::Chars)
+            )))))
+        );
+        assert!(matches!(
+            Value::parse(RawSpan::new("ARRAY[1, 'a']")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(Value::parse(RawSpan::new("ARRAY[]")).is_err());
+
+        let strings = array(&[
+            text("a b"),
+            text(""),
+            text("NULL"),
+            text("q\""),
+            Value::Null,
+        ]);
+        assert_eq!(strings.to_string(), r#"{"a b","","NULL","q\"",NULL}"#);
+        let nested = SqlType::Array(Box::new(SqlType::Array(Box::new(SqlType::VarChar(
+            3,
+            LengthUnit::Chars,
+        )))));
+        assert_eq!(
+            text(r#"{{"a b",NULL},{}}"#).cast(nested),
+            Ok(array(&[array(&[text("a b"), Value::Null]), array(&[])]))
+        );
+        assert_eq!(
+            text(&strings.to_string()).cast(SqlType::Array(Box::new(SqlType::VarChar(
+                4,
+                LengthUnit::Chars
+            )))),
+            Ok(strings)
+        );
+        assert_eq!(
+            text("{abcd}").cast(SqlType::Array(Box::new(SqlType::VarChar(
+                3,
+                LengthUnit::Chars
+            )))),
+            Err(CastError::InvalidText(SqlType::Array(Box::new(
+                SqlType::VarChar(3, LengthUnit::Chars)
+            ))))
+        );
+
+        let values = array(&[Value::I64(1), Value::I64(300)]);
+        assert_eq!(
+            values.coerce(ints.clone()),
+            Ok(array(&[Value::I32(1), Value::I32(300)]))
+        );
+        assert!(values
+            .coerce(SqlType::Array(Box::new(SqlType::U8)))
+            .is_err());
+        assert_eq!(
+            values.cast(SqlType::VarChar(10, LengthUnit::Chars)),
+            Ok(Value::VarChar("{1,300}".into()))
+        );
+        assert!(values.cast(SqlType::I64).is_err());
+        assert_eq!(
+            text("{1,2}").coerce(ints),
+            Ok(array(&[Value::I32(1), Value::I32(2)]))
+        );
+
+        assert_eq!(values.subscript(&Value::I64(2)), Ok(Value::I64(300)));
+        assert_eq!(values.subscript(&Value::I64(0)), Ok(Value::Null));
+        assert_eq!(values.subscript(&Value::I64(3)), Ok(Value::Null));
+        assert!(values.subscript(&text("1")).is_err());
+        assert!(Value::I64(1).subscript(&Value::I64(1)).is_err());
+
+        let compare = |a: &[Value], b: &[Value]| array(a).compare(&array(b)).unwrap();
+        assert_eq!(
+            compare(&[Value::I64(1)], &[Value::I64(1), Value::I64(0)]),
+            Some(std::cmp::Ordering::Less)
+        );
+        assert_eq!(
+            compare(&[Value::Null], &[Value::I64(5)]),
+            Some(std::cmp::Ordering::Greater)
+        );
+        assert!(array(&[Value::I64(1)])
+            .compare(&array(&[text("a")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_value_arithmetic() {
+        let add = |a: Value, b: Value| a.checked_arithmetic(BinaryOperator::Plus, &b);
+        assert_eq!(add(Value::I64(1), Value::I64(2)).unwrap(), Value::I64(3));
+        assert_eq!(add(Value::I8(1), Value::U8(2)).unwrap(), Value::I128(3));
+        assert_eq!(add(Value::Null, Value::I64(2)).unwrap(), Value::Null);
+        assert!(add(Value::I64(i64::MAX), Value::I64(1)).is_err());
+        assert!(add(Value::I64(-1), Value::U128(u128::MAX)).is_err());
+        assert!(add(Value::VarChar("a".into()), Value::I64(1)).is_err());
+        assert_eq!(
+            Value::I64(1).rem(&Value::U8(0)),
+            Err(ParseError::DivisionByZero)
+        );
+        assert_eq!(
+            Value::I32(i32::MIN).div(&Value::I32(-1)),
+            Err(ParseError::ArithmeticOverflow)
+        );
+        assert_eq!(Value::I32(-7).div(&Value::I32(2)), Ok(Value::I32(-3)));
+        assert_eq!(Value::I32(-7).rem(&Value::I32(2)), Ok(Value::I32(-1)));
+        assert_eq!(
+            Value::U8(7).sub(&Value::U8(8)),
+            Err(ParseError::ArithmeticOverflow)
+        );
+        assert_eq!(Value::I16(3).mul(&Value::F64(0.5)), Ok(Value::F64(1.5)));
+        assert_eq!(
+            Value::F64(f64::MAX).mul(&Value::F64(2.0)),
+            Err(ParseError::ArithmeticOverflow)
+        );
+        assert_eq!(
+            Value::I8(1).add(&Value::Decimal("0.25".parse().unwrap())),
+            Ok(Value::Decimal("1.25".parse().unwrap()))
+        );
+        assert_eq!(Value::U8(3).checked_neg().unwrap(), Value::I128(-3));
+        assert!(Value::I8(i8::MIN).checked_neg().is_err());
+    }
+
+    #[test]
+    fn test_value_conversions() {
+        assert_eq!(Value::from(7_i32), Value::I32(7));
+        assert_eq!(Value::from("a"), Value::VarChar("a".into()));
+        assert_eq!(Value::from(Some(1.5_f64)), Value::F64(1.5));
+        assert_eq!(Value::from(None::<u8>), Value::Null);
+        assert_eq!(Value::from(vec![0xff_u8]), Value::Blob([0xff].into()));
+        assert_eq!(
+            Value::from(vec![Value::from(1_i64), Value::Null]),
+            Value::Array([Value::I64(1), Value::Null].into())
+        );
+
+        assert_eq!(i64::try_from(Value::I8(-3)), Ok(-3));
+        assert_eq!(i32::try_from(Value::I64(300)), Ok(300));
+        assert_eq!(
+            u8::try_from(Value::I64(300)),
+            Err(CastError::OutOfRange(SqlType::U8))
+        );
+        assert_eq!(
+            i32::try_from(Value::F64(1.5)),
+            Err(CastError::Lossy {
+                from: SqlType::F64,
+                to: SqlType::I32
+            })
+        );
+        assert_eq!(i32::try_from(Value::Null), Err(CastError::Null));
+        assert_eq!(Option::<i32>::try_from(Value::Null), Ok(None));
+        assert_eq!(Option::<i32>::try_from(Value::I16(2)), Ok(Some(2)));
+        assert_eq!(
+            String::try_from(Value::Char("ab ".into())),
+            Ok("ab".to_owned())
+        );
+        assert!(String::try_from(Value::I32(1)).is_err());
+        assert_eq!(
+            Decimal::try_from(Value::I32(2)),
+            Ok("2".parse::<Decimal>().unwrap())
+        );
+        assert_eq!(
+            Date::try_from(Value::VarChar("2024-02-29".into())),
+            Ok("2024-02-29".parse().unwrap())
+        );
+        assert_eq!(
+            Vec::<Value>::try_from(Value::Array([Value::Bool(true)].into())),
+            Ok(vec![Value::Bool(true)])
+        );
+        assert_eq!(
+            Vec::<Value>::try_from(Value::Bool(true)),
+            Err(CastError::Forbidden {
+                from: SqlType::Bool,
+                to: SqlType::Array(Box::new(SqlType::Bool))
+            })
+        );
+    }
+
+    #[test]
+    fn test_value_compare() {
+        use std::cmp::Ordering;
+        assert_eq!(
+            Value::I8(1).compare(&Value::U64(2)),
+            Ok(Some(Ordering::Less))
+        );
+        assert_eq!(
+            Value::I64(-1).compare(&Value::U128(u128::MAX)),
+            Ok(Some(Ordering::Less))
+        );
+        assert_eq!(
+            Value::U128(u128::MAX).compare(&Value::U128(1)),
+            Ok(Some(Ordering::Greater))
+        );
+        assert_eq!(
+            Value::VarChar("b".into()).compare(&Value::VarChar("a".into())),
+            Ok(Some(Ordering::Greater))
+        );
+        assert_eq!(Value::Null.compare(&Value::I8(1)), Ok(None));
+        assert!(Value::VarChar("1".into()).compare(&Value::I8(1)).is_err());
+    }
+
+    #[test]
+    fn test_value_sql_cmp() {
+        let sql_cmp = |a: &Value, b: &Value, order, nulls_first, collation| {
+            a.sql_cmp(b, order, nulls_first, collation).unwrap()
+        };
+        let asc = |a: &Value, b: &Value| sql_cmp(a, b, SortOrder::Asc, false, Collation::Binary);
+        assert_eq!(asc(&Value::I32(2), &Value::I64(10)), Ordering::Less);
+        assert_eq!(asc(&Value::Null, &Value::I64(10)), Ordering::Greater);
+        assert_eq!(asc(&Value::Null, &Value::Null), Ordering::Equal);
+        assert_eq!(asc(&Value::F64(f64::NAN), &Value::I8(1)), Ordering::Greater);
+        assert_eq!(
+            asc(&Value::F64(f64::NAN), &Value::F32(f32::NAN)),
+            Ordering::Equal
+        );
+        let desc = |a: &Value, b: &Value, nulls_first| {
+            sql_cmp(a, b, SortOrder::Desc, nulls_first, Collation::Binary)
+        };
+        assert_eq!(
+            desc(&Value::I32(2), &Value::I64(10), false),
+            Ordering::Greater
+        );
+        assert_eq!(desc(&Value::Null, &Value::I8(1), false), Ordering::Greater);
+        assert_eq!(desc(&Value::Null, &Value::I8(1), true), Ordering::Less);
+
+        let (a, b) = (Value::VarChar("a".into()), Value::Char("B ".into()));
+        assert_eq!(asc(&a, &b), Ordering::Greater);
+        let nocase =
+            |a: &Value, b: &Value| sql_cmp(a, b, SortOrder::Asc, false, Collation::CaseInsensitive);
+        assert_eq!(nocase(&a, &b), Ordering::Less);
+        assert_eq!(nocase(&Value::VarChar("b".into()), &b), Ordering::Equal);
+        assert_eq!(
+            nocase(
+                &Value::Array([a.clone(), Value::Null].into()),
+                &Value::Array([Value::VarChar("A".into()), Value::I8(1)].into())
+            ),
+            Ordering::Greater
+        );
+        assert!(a
+            .sql_cmp(&Value::I8(1), SortOrder::Asc, false, Collation::Binary)
+            .is_err());
+    }
+
+    #[test]
+    fn test_value_ord() {
+        use crate::date::MICROS_PER_DAY;
+        let mut values = vec![
+            Value::Null,
+            Value::VarChar("a".into()),
+            Value::I64(1),
+            Value::F64(f64::NAN),
+            Value::I32(1),
+            Value::F32(0.5),
+            Value::U128(u128::MAX),
+            Value::Bool(true),
+            Value::Interval(Interval::new(0, 0, MICROS_PER_DAY)),
+            Value::Interval(Interval::new(0, 1, 0)),
+            Value::Array([Value::I8(1), Value::Null].into()),
+            Value::Array([Value::I8(1), Value::I8(2)].into()),
+        ];
+        values.sort();
+        assert_eq!(
+            values,
+            [
+                Value::Bool(true),
+                Value::F32(0.5),
+                Value::I32(1),
+                Value::I64(1),
+                Value::U128(u128::MAX),
+                Value::F64(f64::NAN),
+                Value::VarChar("a".into()),
+                Value::Interval(Interval::new(0, 0, MICROS_PER_DAY)),
+                Value::Interval(Interval::new(0, 1, 0)),
+                Value::Array([Value::I8(1), Value::I8(2)].into()),
+                Value::Array([Value::I8(1), Value::Null].into()),
+                Value::Null,
+            ]
+        );
+        assert_eq!(Value::F64(-0.0).cmp(&Value::F64(0.0)), Ordering::Equal);
+        assert_eq!(
+            Value::Char("a ".into()).cmp(&Value::Char("a".into())),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_value_hash_comparable() {
+        use std::hash::DefaultHasher;
+
+        use crate::date::MICROS_PER_DAY;
+        let hash = |value: &Value| {
+            let mut state = DefaultHasher::new();
+            value.hash_comparable(&mut state);
+            state.finish()
+        };
+        let equal = [
+            (Value::I32(1), Value::F64(1.0)),
+            (Value::U128(7), Value::Decimal("7.00".parse().unwrap())),
+            (Value::VarChar("a".into()), Value::Char("a  ".into())),
+            (
+                Value::Date("2024-01-02".parse().unwrap()),
+                Value::Timestamp("2024-01-02 00:00:00".parse().unwrap()),
+            ),
+            (
+                Value::Interval(Interval::new(0, 1, 0)),
+                Value::Interval(Interval::new(0, 0, MICROS_PER_DAY)),
+            ),
+            (
+                Value::Array([Value::I8(1)].into()),
+                Value::Array([Value::I64(1)].into()),
+            ),
+        ];
+        for (a, b) in &equal {
+            assert_eq!(a.compare(b), Ok(Some(Ordering::Equal)));
+            assert_eq!(hash(a), hash(b), "{a:?} {b:?}");
+        }
+        assert_ne!(hash(&Value::I32(1)), hash(&Value::I32(2)));
+        assert_ne!(hash(&Value::I32(1)), hash(&Value::VarChar("1".into())));
+    }
+
+    #[test]
+    fn test_value_coerce() {
+        assert_eq!(Value::I64(19).coerce(SqlType::U8), Ok(Value::U8(19)));
+        assert_eq!(Value::I8(19).coerce(SqlType::I64), Ok(Value::I64(19)));
+        assert_eq!(
+            Value::I64(-19).coerce(SqlType::U8),
+            Err(CastError::OutOfRange(SqlType::U8))
+        );
+        assert!(Value::I64(300).coerce(SqlType::I8).is_err());
+        assert_eq!(Value::Null.coerce(SqlType::I8), Ok(Value::Null));
+        assert_eq!(
+            Value::U128(u128::MAX).coerce(SqlType::U128),
+            Ok(Value::U128(u128::MAX))
+        );
+        assert_eq!(
+            Value::VarChar("hello".into()).coerce(SqlType::VarChar(4, LengthUnit::Chars)),
+            Err(CastError::Lossy {
+                from: SqlType::VarChar(5, LengthUnit::Chars),
+                to: SqlType::VarChar(4, LengthUnit::Chars)
+            })
+        );
+        assert_eq!(
+            Value::I64(1).coerce(SqlType::VarChar(40, LengthUnit::Chars)),
+            Err(CastError::NotImplicit {
+                from: SqlType::I64,
+                to: SqlType::VarChar(40, LengthUnit::Chars)
+            })
+        );
+        assert!(Value::VarChar("1".into()).coerce(SqlType::I8).is_err());
+    }
+
+    #[test]
+    fn test_value_cast() {
+        let cast = |value: Value, tp| value.cast(tp);
+        assert_eq!(
+            cast(Value::I64(-12), SqlType::VarChar(10, LengthUnit::Chars)),
+            Ok(Value::VarChar("-12".into()))
+        );
+        assert_eq!(
+            cast(
+                Value::VarChar("héllo".into()),
+                SqlType::VarChar(2, LengthUnit::Chars)
+            ),
+            Ok(Value::VarChar("hé".into()))
+        );
+        assert_eq!(
+            cast(
+                Value::VarChar("héllo".into()),
+                SqlType::VarChar(2, LengthUnit::Bytes)
+            ),
+            Ok(Value::VarChar("h".into()))
+        );
+        assert_eq!(
+            cast(Value::Bool(false), SqlType::VarChar(10, LengthUnit::Chars)),
+            Ok(Value::VarChar("false".into()))
+        );
+        assert_eq!(
+            cast(Value::VarChar(" 42 ".into()), SqlType::U8),
+            Ok(Value::U8(42))
+        );
+        assert_eq!(
+            cast(Value::VarChar("T".into()), SqlType::Bool),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            cast(Value::VarChar("x".into()), SqlType::I32),
+            Err(CastError::InvalidText(SqlType::I32))
+        );
+        assert_eq!(
+            cast(Value::VarChar("300".into()), SqlType::I8),
+            Err(CastError::OutOfRange(SqlType::I8))
+        );
+        assert_eq!(
+            cast(Value::I8(1), SqlType::Bool),
+            Err(CastError::Forbidden {
+                from: SqlType::I8,
+                to: SqlType::Bool
+            })
+        );
+        assert_eq!(cast(Value::Null, SqlType::Bool), Ok(Value::Null));
     }
 }
@@ -0,0 +1,81 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+};
+
+use rs_db_parser::ast::commands::create::Compression;
+
+use crate::{buffer::PageFile, compress::CompressedFile, error::StorageError, page::Page};
+
+/// Copies the file `from` of `table` to the new file `to`, and flushes it to the disk.
+/// Returns the bytes copied.
+/// # Errors
+/// Returns [`StorageError::TableExists`] if `to` exists, or an error if a file can't be
+/// read or written.
+pub(crate) fn copy_table_file(from: &Path, to: &Path, table: &str) -> Result<u64, StorageError> {
+    let mut target = create_new(to, table)?;
+    let bytes = io::copy(&mut File::open(from)?, &mut target)?;
+    target.sync_all()?;
+    Ok(bytes)
+}
+
+/// Writes `pages` to the new file `path` of `table`, a heap file compressed with
+/// `compression` like the one they were read from, and flushes it to the disk. Returns the
+/// bytes written.
+/// # Errors
+/// Returns [`StorageError::TableExists`] if `path` exists, or an error if the file can't
+/// be written.
+pub(crate) fn write_pages(
+    path: &Path,
+    table: &str,
+    compression: Compression,
+    pages: &[Page],
+) -> Result<u64, StorageError> {
+    let file = create_new(path, table)?;
+    let mut file = match compression {
+        Compression::None => PageFile::Plain(file),
+        _ => PageFile::Compressed(CompressedFile::create(file, compression)?),
+    };
+    for (id, page) in (0..).zip(pages) {
+        file.write_page(id, page)?;
+    }
+    file.sync_all()?;
+    Ok(std::fs::metadata(path)?.len())
+}
+
+/// Creates the new file `path` of `table`.
+/// # Errors
+/// Returns [`StorageError::TableExists`] if the file exists, or an error if it can't be
+/// created.
+pub(crate) fn create_new(path: &Path, table: &str) -> Result<File, StorageError> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            Err(StorageError::TableExists(table.into()))
+        }
+        file => Ok(file?),
+    }
+}
+
+/// Copies the files of the tables of the backup `backup`, the ones named
+/// `<table>.<extension>`, to `dir`, created if it is missing.
+/// # Errors
+/// Returns [`StorageError::TableExists`] if `dir` has the file of a table of the backup, or
+/// an error if a file can't be read or written.
+pub(crate) fn restore(backup: &Path, dir: &Path, extension: &str) -> Result<(), StorageError> {
+    std::fs::create_dir_all(dir)?;
+    for entry in std::fs::read_dir(backup)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+        let (Some(table), Some(name)) = (
+            path.file_stem().and_then(|stem| stem.to_str()),
+            path.file_name(),
+        ) else {
+            continue;
+        };
+        copy_table_file(&path, &dir.join(name), table)?;
+    }
+    Ok(())
+}
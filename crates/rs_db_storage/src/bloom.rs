@@ -0,0 +1,116 @@
+use std::hash::{DefaultHasher, Hasher};
+
+use rs_db_parser::value::Value;
+
+/// The bits a [`BloomFilter`] keeps for each value it is sized for, so that about 1% of
+/// the values not in it are wrongly found in it.
+const BITS_PER_VALUE: usize = 10;
+
+/// The bits a value sets in a [`BloomFilter`], the best count for [`BITS_PER_VALUE`].
+const HASHES: u64 = 7;
+
+/// A set of values that tells whether a value may be in it, in a few bits per value: a
+/// value added is always found, one that wasn't is found only sometimes, more often as
+/// the filter holds more values than it was sized for. The values [`Value::compare`] finds
+/// equal, like `1` as an `I32` and as an `I64`, are the same value, and `NULL`, equal to
+/// nothing, is never in the filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Box<[u64]>,
+    /// The number of values the filter is sized for.
+    capacity: usize,
+    /// The number of values added, counted again when added twice.
+    len: usize,
+}
+
+impl BloomFilter {
+    /// An empty filter sized for `values` values.
+    #[must_use]
+    pub fn new(values: usize) -> Self {
+        let capacity = values.max(1);
+        Self {
+            bits: vec![0; (capacity * BITS_PER_VALUE).div_ceil(64)].into(),
+            capacity,
+            len: 0,
+        }
+    }
+
+    /// A filter sized for the values of `values`, holding those that aren't `NULL`.
+    #[must_use]
+    pub fn from_values<'v>(values: impl ExactSizeIterator<Item = &'v Value>) -> Self {
+        let mut filter = Self::new(values.len());
+        values.for_each(|value| filter.insert(value));
+        filter
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Whether the filter holds the values it was sized for: it finds more of the values
+    /// that weren't added once more are.
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+
+    /// The bits of `value`, by double hashing its hash.
+    fn positions(&self, value: &Value) -> impl Iterator<Item = usize> {
+        let mut state = DefaultHasher::new();
+        value.hash_comparable(&mut state);
+        let hash = state.finish();
+        let (first, step) = (hash, hash.rotate_left(32) | 1);
+        let len = self.bits.len() as u64 * 64;
+        // The position is below the number of bits, which is a `usize`.
+        #[allow(clippy::cast_possible_truncation)]
+        (0..HASHES).map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % len) as usize)
+    }
+
+    /// Adds `value` to the filter, a `NULL` isn't added.
+    pub fn insert(&mut self, value: &Value) {
+        if value.is_null() {
+            return;
+        }
+        for position in self.positions(value) {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+        self.len += 1;
+    }
+
+    /// Whether `value` may be in the filter, `false` only if it was never added.
+    #[must_use]
+    pub fn may_contain(&self, value: &Value) -> bool {
+        !value.is_null()
+            && self
+                .positions(value)
+                .all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter() {
+        let values = (0..1000).map(|i| Value::I32(i * 2)).collect::<Vec<_>>();
+        let filter = BloomFilter::from_values(values.iter());
+        assert!(values.iter().all(|value| filter.may_contain(value)));
+        assert!(filter.may_contain(&Value::I64(10)));
+        assert!(!filter.may_contain(&Value::Null));
+        let false_positives = (0..1000)
+            .filter(|i| filter.may_contain(&Value::I32(i * 2 + 1)))
+            .count();
+        assert!(false_positives < 50, "{false_positives}");
+
+        let mut filter = BloomFilter::new(0);
+        assert!(!filter.may_contain(&Value::VarChar("a".into())));
+        filter.insert(&Value::VarChar("a".into()));
+        filter.insert(&Value::Null);
+        assert!(filter.is_full());
+        assert!(filter.may_contain(&Value::VarChar("a".into())));
+        assert!(filter.may_contain(&Value::Char("a  ".into())));
+    }
+}
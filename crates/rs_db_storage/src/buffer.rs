@@ -0,0 +1,691 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError, RwLock, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    compress::CompressedFile,
+    error::StorageError,
+    heap::PageId,
+    page::{Page, PAGE_SIZE},
+};
+
+/// The number of a file registered with a [`BufferPool`].
+pub type FileId = u32;
+
+/// How a [`BufferPool`] keeps pages in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferPoolConfig {
+    /// The pages kept in memory at once, of [`PAGE_SIZE`] bytes each.
+    pub capacity: usize,
+    /// The accesses to a page that LRU-K remembers, see [`BufferPool`]. `1` is plain LRU.
+    pub k: usize,
+    /// How often a background thread writes the dirty pages to their files, `None` to
+    /// only write them when they are evicted or flushed.
+    pub flush_interval: Option<Duration>,
+}
+
+impl Default for BufferPoolConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            k: 2,
+            flush_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// What a [`BufferPool`] did since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    /// The pins of pages that were in memory.
+    pub hits: u64,
+    /// The pins of pages that were read from their file.
+    pub misses: u64,
+    /// The pages dropped from memory to make room for others.
+    pub evictions: u64,
+    /// The dirty pages written to their file.
+    pub writes: u64,
+}
+
+/// A page in memory, and whether it changed since it was read or last written.
+#[derive(Debug)]
+struct Frame {
+    page: RwLock<Page>,
+    dirty: AtomicBool,
+}
+
+/// A file the pool reads and writes pages of: page `n` of a plain file at the offset
+/// `n * PAGE_SIZE`, or a [`CompressedFile`].
+#[derive(Debug)]
+pub(crate) enum PageFile {
+    Plain(File),
+    Compressed(CompressedFile),
+}
+
+impl PageFile {
+    /// Reads `page`, a corrupted page is reported without the name of the file.
+    fn read_page(&mut self, page: PageId) -> Result<Page, StorageError> {
+        let file = match self {
+            Self::Plain(file) => file,
+            Self::Compressed(file) => return file.read_page(page),
+        };
+        let mut data = vec![0; PAGE_SIZE];
+        file.seek(SeekFrom::Start(u64::from(page) * PAGE_SIZE as u64))?;
+        file.read_exact(&mut data)?;
+        // A page of zeros was added to the file but not written yet when the process
+        // stopped, its records were lost.
+        if data.iter().all(|byte| *byte == 0) {
+            return Ok(Page::new());
+        }
+        Page::from_bytes(data.into()).ok_or(StorageError::CorruptPage {
+            file: "".into(),
+            page: u64::from(page),
+        })
+    }
+
+    pub(crate) fn write_page(&mut self, page: PageId, data: &Page) -> Result<(), StorageError> {
+        match self {
+            Self::Plain(file) => {
+                file.seek(SeekFrom::Start(u64::from(page) * PAGE_SIZE as u64))?;
+                file.write_all(data.as_bytes())?;
+                Ok(())
+            }
+            Self::Compressed(file) => file.write_page(page, data),
+        }
+    }
+
+    pub(crate) fn sync_all(&self) -> Result<(), StorageError> {
+        match self {
+            Self::Plain(file) => Ok(file.sync_all()?),
+            Self::Compressed(file) => file.sync_all(),
+        }
+    }
+}
+
+/// What the pool knows of a frame, kept behind its lock.
+#[derive(Debug)]
+struct FrameState {
+    key: Option<(FileId, PageId)>,
+    frame: Arc<Frame>,
+    pins: usize,
+    /// The times of the last `k` accesses, the latest last.
+    history: VecDeque<u64>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frames: Vec<FrameState>,
+    /// The frame of each page in memory.
+    pages: HashMap<(FileId, PageId), usize>,
+    /// The registered files, with their name for the errors.
+    files: HashMap<FileId, (PageFile, Box<str>)>,
+    next_file: FileId,
+    /// Counts the accesses, the time of the history of the frames.
+    clock: u64,
+    stats: BufferStats,
+}
+
+/// Keeps pages of files in a fixed number of frames of memory, so tables larger than the
+/// memory are read and written a page at a time.
+///
+/// A page is pinned while it is used, [`BufferPool::pin`] returns a guard that unpins it
+/// when dropped, and a pinned page stays in memory. When a page is read and no frame is
+/// free, the unpinned page whose `k`-th latest access is the oldest is evicted, the pages
+/// accessed fewer than `k` times first, the least recently used of them first. A page read
+/// once by a scan is so evicted before the pages used again and again, like the root of an
+/// index, which plain LRU would evict.
+///
+/// A page written through its guard is dirty until it is written to its file: when it is
+/// evicted, flushed, or by the background thread of [`BufferPoolConfig::flush_interval`].
+#[derive(Debug)]
+pub struct BufferPool {
+    capacity: usize,
+    k: usize,
+    state: Mutex<State>,
+    stopped: Arc<AtomicBool>,
+    flusher: Option<thread::Thread>,
+}
+
+impl BufferPool {
+    /// A pool with no page in memory, which starts its flushing thread if `config` has one.
+    ///
+    /// # Panics
+    /// Panics if the capacity or `k` is `0`.
+    #[must_use]
+    pub fn new(config: &BufferPoolConfig) -> Arc<Self> {
+        assert!(config.capacity > 0, "A buffer pool needs a frame");
+        assert!(config.k > 0, "LRU-K needs an access");
+        let stopped = Arc::new(AtomicBool::new(false));
+        Arc::new_cyclic(|pool: &Weak<Self>| {
+            let flusher = config.flush_interval.map(|interval| {
+                let pool = pool.clone();
+                let stopped = Arc::clone(&stopped);
+                thread::spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        thread::park_timeout(interval);
+                        let Some(pool) = pool.upgrade() else {
+                            return;
+                        };
+                        // A page that can't be written stays dirty, the next flush or its
+                        // eviction reports the error.
+                        let _ = pool.flush_dirty(None, false);
+                    }
+                })
+                .thread()
+                .clone()
+            });
+            Self {
+                capacity: config.capacity,
+                k: config.k,
+                state: Mutex::new(State::default()),
+                stopped,
+                flusher,
+            }
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> BufferStats {
+        self.state().stats
+    }
+
+    /// Whether page `page` of `file` is in memory.
+    #[must_use]
+    pub fn contains(&self, file: FileId, page: PageId) -> bool {
+        self.state().pages.contains_key(&(file, page))
+    }
+
+    /// Hands `file` to the pool, which reads and writes its pages from now on. `name`
+    /// names it in the errors.
+    pub fn register(&self, file: File, name: impl Into<Box<str>>) -> FileId {
+        self.register_page_file(PageFile::Plain(file), name)
+    }
+
+    /// Like [`BufferPool::register`], for a file of any format.
+    pub(crate) fn register_page_file(&self, file: PageFile, name: impl Into<Box<str>>) -> FileId {
+        let mut state = self.state();
+        let id = state.next_file;
+        state.next_file += 1;
+        state.files.insert(id, (file, name.into()));
+        id
+    }
+
+    /// Writes the dirty pages of `file`, drops its pages from memory and closes it.
+    /// # Errors
+    /// Returns an error if a page can't be written, the file is kept then.
+    pub fn unregister(&self, file: FileId) -> Result<(), StorageError> {
+        self.flush_dirty(Some(file), true)?;
+        let mut state = self.state();
+        let state = &mut *state;
+        for frame in &mut state.frames {
+            if let Some(key) = frame.key.filter(|(f, _)| *f == file) {
+                debug_assert_eq!(frame.pins, 0, "a page of a closed file is pinned");
+                state.pages.remove(&key);
+                frame.key = None;
+                frame.history.clear();
+            }
+        }
+        state.files.remove(&file);
+        Ok(())
+    }
+
+    /// Cuts `file` to its first `pages` pages, dropping the ones after them from memory
+    /// without writing them. A compressed file keeps its length until it is
+    /// [compacted](BufferPool::compact).
+    /// # Errors
+    /// Returns an error if the file is closed or can't be cut.
+    pub fn truncate(&self, file: FileId, pages: PageId) -> Result<(), StorageError> {
+        let mut state = self.state();
+        let state = &mut *state;
+        for frame in &mut state.frames {
+            if let Some(key) = frame.key.filter(|(f, page)| *f == file && *page >= pages) {
+                debug_assert_eq!(frame.pins, 0, "a page cut from its file is pinned");
+                state.pages.remove(&key);
+                frame.key = None;
+                frame.history.clear();
+                frame.frame.dirty.store(false, Ordering::Relaxed);
+            }
+        }
+        let (handle, _) = state
+            .files
+            .get_mut(&file)
+            .ok_or_else(|| StorageError::Io(format!("File {file} is closed").into()))?;
+        match handle {
+            PageFile::Plain(handle) => handle.set_len(u64::from(pages) * PAGE_SIZE as u64)?,
+            PageFile::Compressed(handle) => handle.truncate(pages)?,
+        }
+        Ok(())
+    }
+
+    /// Writes the dirty pages of `file` and gives back the room the compressed pages no
+    /// longer use, see [`CompressedFile::compact`]. Returns the bytes the file lost, `0`
+    /// for a plain file, whose pages keep their place.
+    /// # Errors
+    /// Returns an error if a page can't be written or the file can't be compacted.
+    pub fn compact(&self, file: FileId) -> Result<u64, StorageError> {
+        self.flush_dirty(Some(file), true)?;
+        match self.state().files.get_mut(&file) {
+            Some((PageFile::Compressed(handle), _)) => handle.compact(),
+            _ => Ok(0),
+        }
+    }
+
+    /// Pins page `page` of `file`, reading it from the file if it isn't in memory.
+    /// # Errors
+    /// Returns [`StorageError::BufferPoolFull`] if every frame holds a pinned page, or an
+    /// error if the page can't be read, or the page it evicts can't be written.
+    pub fn pin(&self, file: FileId, page: PageId) -> Result<PageGuard<'_>, StorageError> {
+        self.pin_with(file, page, false)
+    }
+
+    /// Pins page `page` of `file` as a new empty page, dirty so it is written to the file,
+    /// which is extended up to it.
+    /// # Errors
+    /// Returns the errors of [`BufferPool::pin`].
+    pub fn pin_new(&self, file: FileId, page: PageId) -> Result<PageGuard<'_>, StorageError> {
+        self.pin_with(file, page, true)
+    }
+
+    fn pin_with(
+        &self,
+        file: FileId,
+        page: PageId,
+        new: bool,
+    ) -> Result<PageGuard<'_>, StorageError> {
+        let mut state = self.state();
+        let state = &mut *state;
+        state.clock += 1;
+        let clock = state.clock;
+        let key = (file, page);
+        if let Some(&index) = state.pages.get(&key) {
+            state.stats.hits += 1;
+            let frame = &mut state.frames[index];
+            frame.pins += 1;
+            touch(&mut frame.history, clock, self.k);
+            if new {
+                *frame
+                    .frame
+                    .page
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner) = Page::new();
+                frame.frame.dirty.store(true, Ordering::Relaxed);
+            }
+            return Ok(PageGuard {
+                pool: self,
+                index,
+                frame: Arc::clone(&frame.frame),
+            });
+        }
+        state.stats.misses += 1;
+        let index = self.victim(state)?;
+        let loaded = if new {
+            Page::new()
+        } else {
+            let (handle, name) = state
+                .files
+                .get_mut(&file)
+                .ok_or_else(|| StorageError::Io(format!("File {file} is closed").into()))?;
+            handle.read_page(page).map_err(|error| match error {
+                StorageError::CorruptPage { page, .. } => StorageError::CorruptPage {
+                    file: name.clone(),
+                    page,
+                },
+                error => error,
+            })?
+        };
+        let frame = &mut state.frames[index];
+        if let Some(old) = frame.key.replace(key) {
+            state.pages.remove(&old);
+        }
+        *frame
+            .frame
+            .page
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = loaded;
+        frame.frame.dirty.store(new, Ordering::Relaxed);
+        frame.pins = 1;
+        frame.history.clear();
+        touch(&mut frame.history, clock, self.k);
+        state.pages.insert(key, index);
+        Ok(PageGuard {
+            pool: self,
+            index,
+            frame: Arc::clone(&frame.frame),
+        })
+    }
+
+    /// A frame for a page to read: a free one, or the one of the page to evict, written
+    /// first if it is dirty.
+    fn victim(&self, state: &mut State) -> Result<usize, StorageError> {
+        if let Some(index) = state.frames.iter().position(|frame| frame.key.is_none()) {
+            return Ok(index);
+        }
+        if state.frames.len() < self.capacity {
+            state.frames.push(FrameState {
+                key: None,
+                frame: Arc::new(Frame {
+                    page: RwLock::new(Page::new()),
+                    dirty: AtomicBool::new(false),
+                }),
+                pins: 0,
+                history: VecDeque::with_capacity(self.k),
+            });
+            return Ok(state.frames.len() - 1);
+        }
+        // The frames accessed fewer than `k` times have an infinite backward distance,
+        // they go first, by their first access.
+        let index = state
+            .frames
+            .iter()
+            .enumerate()
+            .filter(|(_, frame)| frame.pins == 0)
+            .min_by_key(|(_, frame)| (frame.history.len() >= self.k, frame.history.front()))
+            .map(|(index, _)| index)
+            .ok_or(StorageError::BufferPoolFull)?;
+        let frame = &state.frames[index];
+        if frame.frame.dirty.load(Ordering::Relaxed) {
+            if let Some((file, page)) = frame.key {
+                let (handle, _) = state
+                    .files
+                    .get_mut(&file)
+                    .ok_or_else(|| StorageError::Io(format!("File {file} is closed").into()))?;
+                handle.write_page(
+                    page,
+                    &frame
+                        .frame
+                        .page
+                        .read()
+                        .unwrap_or_else(PoisonError::into_inner),
+                )?;
+                frame.frame.dirty.store(false, Ordering::Relaxed);
+                state.stats.writes += 1;
+            }
+        }
+        state.stats.evictions += 1;
+        Ok(index)
+    }
+
+    fn unpin(&self, index: usize) {
+        let mut state = self.state();
+        let frame = &mut state.frames[index];
+        debug_assert!(frame.pins > 0);
+        frame.pins -= 1;
+    }
+
+    /// Writes the dirty pages of `file`, or of all the files, to their files. The pages
+    /// being written through a guard are skipped unless `wait` is set.
+    fn flush_dirty(&self, file: Option<FileId>, wait: bool) -> Result<(), StorageError> {
+        let mut state = self.state();
+        let state = &mut *state;
+        for frame in &state.frames {
+            let Some((frame_file, page)) = frame.key else {
+                continue;
+            };
+            if file.is_some_and(|file| file != frame_file)
+                || !frame.frame.dirty.load(Ordering::Relaxed)
+            {
+                continue;
+            }
+            let guard = if wait {
+                frame
+                    .frame
+                    .page
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+            } else {
+                match frame.frame.page.try_read() {
+                    Ok(guard) => guard,
+                    Err(_) => continue,
+                }
+            };
+            let Some((handle, _)) = state.files.get_mut(&frame_file) else {
+                continue;
+            };
+            handle.write_page(page, &guard)?;
+            frame.frame.dirty.store(false, Ordering::Relaxed);
+            state.stats.writes += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the dirty pages of `file` to it, and flushes it to the disk.
+    /// # Errors
+    /// Returns an error if a page can't be written or the file can't be flushed.
+    pub fn flush(&self, file: FileId) -> Result<(), StorageError> {
+        self.flush_dirty(Some(file), true)?;
+        if let Some((handle, _)) = self.state().files.get(&file) {
+            handle.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all the dirty pages to their files.
+    /// # Errors
+    /// Returns an error if a page can't be written.
+    pub fn flush_all(&self) -> Result<(), StorageError> {
+        self.flush_dirty(None, true)
+    }
+}
+
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(flusher) = &self.flusher {
+            flusher.unpark();
+        }
+        // The files are closed by their owners first, which flush them, so only the pages
+        // of the files left open are written here.
+        let _ = self.flush_all();
+    }
+}
+
+/// Records an access at `clock`, forgetting the ones before the last `k`.
+fn touch(history: &mut VecDeque<u64>, clock: u64, k: usize) {
+    if history.len() == k {
+        history.pop_front();
+    }
+    history.push_back(clock);
+}
+
+/// A pinned page, unpinned when the guard is dropped. The page is read and written through
+/// closures, which must not pin other pages of the pool.
+#[derive(Debug)]
+pub struct PageGuard<'p> {
+    pool: &'p BufferPool,
+    index: usize,
+    frame: Arc<Frame>,
+}
+
+impl PageGuard<'_> {
+    /// Calls `f` with the page, while no one writes it.
+    pub fn read<T>(&self, f: impl FnOnce(&Page) -> T) -> T {
+        f(&self
+            .frame
+            .page
+            .read()
+            .unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Calls `f` with the page, while no one else reads or writes it, and marks it dirty.
+    pub fn write<T>(&self, f: impl FnOnce(&mut Page) -> T) -> T {
+        let mut page = self
+            .frame
+            .page
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let result = f(&mut page);
+        self.frame.dirty.store(true, Ordering::Relaxed);
+        result
+    }
+}
+
+impl Drop for PageGuard<'_> {
+    fn drop(&mut self) {
+        self.pool.unpin(self.index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::{fs::OpenOptions, time::Instant};
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn pool(capacity: usize, flush_interval: Option<Duration>) -> Arc<BufferPool> {
+        BufferPool::new(&BufferPoolConfig {
+            capacity,
+            k: 2,
+            flush_interval,
+        })
+    }
+
+    fn open(dir: &TempDir, name: &str) -> File {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.path().join(name))
+            .unwrap()
+    }
+
+    /// Pins `page` and writes `byte` as its only record.
+    fn fill(pool: &BufferPool, file: FileId, page: PageId, byte: u8) {
+        pool.pin_new(file, page)
+            .unwrap()
+            .write(|page| page.insert(&[byte; 100]).unwrap());
+    }
+
+    fn record(pool: &BufferPool, file: FileId, page: PageId) -> Vec<u8> {
+        pool.pin(file, page)
+            .unwrap()
+            .read(|page| page.get(0).unwrap().to_vec())
+    }
+
+    #[test]
+    fn test_buffer_pool() {
+        let dir = TempDir::new();
+        let pool = pool(3, None);
+        let file = pool.register(open(&dir, "a"), "a");
+        for page in 0..3 {
+            fill(&pool, file, page, 1 + u8::try_from(page).unwrap());
+        }
+        assert_eq!(pool.stats().writes, 0);
+
+        // The pinned pages stay in memory, with no frame left for another page.
+        let guards = (0..3)
+            .map(|page| pool.pin(file, page).unwrap())
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            pool.pin(file, 3),
+            Err(StorageError::BufferPoolFull)
+        ));
+        drop(guards);
+
+        // Page 0, whose second latest access is the oldest, is evicted, written as it is
+        // dirty, and read back from the file.
+        fill(&pool, file, 3, 4);
+        assert!(!pool.contains(file, 0));
+        assert_eq!(pool.stats().writes, 1);
+        assert_eq!(record(&pool, file, 0), [1; 100]);
+        assert_eq!(pool.stats().evictions, 2);
+
+        // Flushing writes the dirty pages, which are then read back by another pool.
+        pool.flush(file).unwrap();
+        let other = BufferPool::new(&BufferPoolConfig::default());
+        let copy = other.register(open(&dir, "a"), "a");
+        for page in 0..4 {
+            assert_eq!(
+                record(&other, copy, page),
+                [1 + u8::try_from(page).unwrap(); 100]
+            );
+        }
+        assert_eq!(other.stats().misses, 4);
+        record(&other, copy, 0);
+        assert_eq!(other.stats().hits, 1);
+
+        // The pages cut from the file are dropped, not written.
+        fill(&pool, file, 3, 5);
+        pool.truncate(file, 2).unwrap();
+        assert!(!pool.contains(file, 3));
+        let len = std::fs::metadata(dir.path().join("a")).unwrap().len();
+        assert_eq!(len, 2 * PAGE_SIZE as u64);
+        pool.flush(file).unwrap();
+        assert_eq!(std::fs::metadata(dir.path().join("a")).unwrap().len(), len);
+
+        pool.unregister(file).unwrap();
+        assert!(!pool.contains(file, 1));
+        assert!(pool.pin(file, 0).is_err());
+    }
+
+    #[test]
+    fn test_lru_k_eviction() {
+        let dir = TempDir::new();
+        let pool = pool(4, None);
+        let file = pool.register(open(&dir, "a"), "a");
+        // Pages 0 and 1 are used again and again, like the root of an index.
+        for page in 0..2 {
+            fill(&pool, file, page, 1);
+            record(&pool, file, page);
+        }
+        // A scan reads pages once, each evicting the previous one, not the hot pages,
+        // though they were used before it.
+        for page in 2..20 {
+            fill(&pool, file, page, 2);
+            assert!(pool.contains(file, 0) && pool.contains(file, 1));
+        }
+        assert!(pool.contains(file, 19) && pool.contains(file, 18));
+        assert!(!pool.contains(file, 2));
+
+        // With plain LRU, the scan evicts them.
+        let pool = BufferPool::new(&BufferPoolConfig {
+            capacity: 4,
+            k: 1,
+            flush_interval: None,
+        });
+        let file = pool.register(open(&dir, "b"), "b");
+        for page in 0..2 {
+            fill(&pool, file, page, 1);
+            record(&pool, file, page);
+        }
+        for page in 2..20 {
+            fill(&pool, file, page, 2);
+        }
+        assert!(!pool.contains(file, 0) && !pool.contains(file, 1));
+    }
+
+    #[test]
+    fn test_background_flush() {
+        let dir = TempDir::new();
+        let pool = pool(8, Some(Duration::from_millis(10)));
+        let file = pool.register(open(&dir, "a"), "a");
+        fill(&pool, file, 0, 1);
+        let start = Instant::now();
+        while pool.stats().writes == 0 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(5));
+        }
+        let len = std::fs::metadata(dir.path().join("a")).unwrap().len();
+        assert_eq!(len, PAGE_SIZE as u64);
+        drop(pool);
+    }
+}
@@ -0,0 +1,1142 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    io::Write,
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use rs_db_parser::{
+    ast::commands::create::SqlType,
+    codec::{decode_row, encode_row},
+    value::Value,
+};
+
+use crate::{
+    backup,
+    bloom::BloomFilter,
+    engine::{ColumnRange, Cursor, RowId, ScanOptions, StorageEngine, Table, TableSnapshot},
+    error::StorageError,
+};
+
+/// The number of rows of a segment. The rows added to a table are kept as they are until
+/// there are enough of them to fill one.
+pub const SEGMENT_ROWS: usize = 1024;
+
+/// The first bytes of the file of a table, the last one is the version of its format.
+const MAGIC: &[u8; 8] = b"rsdbcol\x01";
+
+/// How the values of a column of a segment are kept. The one that keeps the fewest values
+/// is chosen when the segment is filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Every value, in the order of the rows.
+    Plain,
+    /// The distinct values, and for each row the position of its own.
+    Dictionary,
+    /// A value for each run of rows that have it, with their number.
+    RunLength,
+}
+
+/// The values of a column of a segment, see [`Encoding`].
+#[derive(Debug)]
+enum ColumnData {
+    Plain(Vec<Value>),
+    Dictionary { values: Vec<Value>, codes: Vec<u32> },
+    RunLength(Vec<(Value, u32)>),
+}
+
+impl ColumnData {
+    /// Encodes `values` as runs when there are few of them, else as a dictionary when
+    /// there are few distinct values, else as they are.
+    #[allow(clippy::cast_possible_truncation)]
+    fn encode(values: Vec<Value>) -> Self {
+        // A segment has at most `SEGMENT_ROWS` values, the counts fit in `u32`.
+        let few = values.len() / 4;
+        let runs = values.chunk_by(|a, b| a == b).count();
+        if runs <= few {
+            return Self::RunLength(
+                values
+                    .chunk_by(|a, b| a == b)
+                    .map(|run| (run[0].clone(), run.len() as u32))
+                    .collect(),
+            );
+        }
+        let mut positions = HashMap::new();
+        let mut distinct = Vec::new();
+        let codes = values
+            .iter()
+            .map(|value| {
+                *positions.entry(value).or_insert_with(|| {
+                    distinct.push(value.clone());
+                    distinct.len() as u32 - 1
+                })
+            })
+            .collect();
+        if distinct.len() <= few {
+            Self::Dictionary {
+                values: distinct,
+                codes,
+            }
+        } else {
+            Self::Plain(values)
+        }
+    }
+
+    const fn encoding(&self) -> Encoding {
+        match self {
+            Self::Plain(_) => Encoding::Plain,
+            Self::Dictionary { .. } => Encoding::Dictionary,
+            Self::RunLength(_) => Encoding::RunLength,
+        }
+    }
+
+    /// The value of the row at `offset` in the segment.
+    fn get(&self, offset: usize) -> Value {
+        match self {
+            Self::Plain(values) => values[offset].clone(),
+            Self::Dictionary { values, codes } => values[codes[offset] as usize].clone(),
+            Self::RunLength(runs) => {
+                let mut start = 0;
+                for (value, len) in runs {
+                    start += *len as usize;
+                    if offset < start {
+                        return value.clone();
+                    }
+                }
+                unreachable!("the runs cover the rows of the segment")
+            }
+        }
+    }
+
+    /// The values of the rows of the segment, in their order.
+    fn decode(&self) -> Vec<Value> {
+        match self {
+            Self::Plain(values) => values.clone(),
+            Self::Dictionary { values, codes } => codes
+                .iter()
+                .map(|code| values[*code as usize].clone())
+                .collect(),
+            Self::RunLength(runs) => runs
+                .iter()
+                .flat_map(|(value, len)| std::iter::repeat_n(value, *len as usize))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// The smallest and largest values of a column of a segment, which a scan skips when none
+/// of its values can be in a [`ColumnRange`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ZoneMap {
+    /// The smallest and largest values that aren't `NULL`, `None` if there is none or
+    /// they can't be compared.
+    pub bounds: Option<(Value, Value)>,
+    /// The number of values that aren't `NULL`.
+    pub values: u32,
+}
+
+impl ZoneMap {
+    #[allow(clippy::cast_possible_truncation)]
+    fn new(values: &[Value]) -> Self {
+        let values = values.iter().filter(|value| !value.is_null());
+        let count = values.clone().count() as u32;
+        let mut bounds: Option<(Value, Value)> = None;
+        for value in values {
+            let Some((min, max)) = &mut bounds else {
+                bounds = Some((value.clone(), value.clone()));
+                continue;
+            };
+            match (value.compare(min), value.compare(max)) {
+                (Ok(Some(below)), Ok(Some(above))) => {
+                    if below.is_lt() {
+                        *min = value.clone();
+                    }
+                    if above.is_gt() {
+                        *max = value.clone();
+                    }
+                }
+                _ => {
+                    return Self {
+                        bounds: None,
+                        values: count,
+                    }
+                }
+            }
+        }
+        Self {
+            bounds,
+            values: count,
+        }
+    }
+
+    /// Whether a value of the column may be in `range`.
+    #[must_use]
+    pub fn may_match(&self, range: &ColumnRange) -> bool {
+        self.values > 0
+            && self
+                .bounds
+                .as_ref()
+                .is_none_or(|(min, max)| range.overlaps(min, max))
+    }
+}
+
+/// A column of a segment with its zone map.
+#[derive(Debug)]
+struct Chunk {
+    data: ColumnData,
+    zone: ZoneMap,
+}
+
+/// [`SEGMENT_ROWS`] rows of a table, kept by column.
+#[derive(Debug)]
+struct Segment {
+    len: usize,
+    columns: Vec<Chunk>,
+}
+
+impl Segment {
+    fn new(rows: Vec<Vec<Value>>, width: usize) -> Self {
+        let len = rows.len();
+        let mut columns = vec![Vec::with_capacity(len); width];
+        for row in rows {
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+        let columns = columns
+            .into_iter()
+            .map(|values| Chunk {
+                zone: ZoneMap::new(&values),
+                data: ColumnData::encode(values),
+            })
+            .collect();
+        Self { len, columns }
+    }
+}
+
+/// The rows of a [`ColumnarTable`]: the full segments, then the rows added since the last
+/// one was filled. The id of a row is its position among them.
+#[derive(Debug, Clone)]
+struct Rows {
+    types: Vec<SqlType>,
+    segments: Vec<Arc<Segment>>,
+    tail: Vec<Vec<Value>>,
+    deleted: BTreeSet<u64>,
+    /// The bloom filters of the values of some columns, by their position, one for each
+    /// segment. They aren't written to the file.
+    bloom_filters: BTreeMap<usize, Vec<BloomFilter>>,
+    /// Whether the rows changed since the file was written.
+    dirty: bool,
+}
+
+impl Rows {
+    fn len(&self) -> u64 {
+        (self.segments.len() * SEGMENT_ROWS + self.tail.len()) as u64
+    }
+
+    /// Whether a row of the segment at `index` may have values in all of `ranges`, `false`
+    /// when the zone map of the column of a range, or its bloom filter, rules it out.
+    fn segment_may_match(&self, index: usize, ranges: &[ColumnRange]) -> bool {
+        let segment = &self.segments[index];
+        ranges.iter().all(|range| {
+            let Some(chunk) = segment.columns.get(range.column) else {
+                return true;
+            };
+            let filter = self
+                .bloom_filters
+                .get(&range.column)
+                .and_then(|filters| filters.get(index));
+            chunk.zone.may_match(range)
+                && range
+                    .value()
+                    .zip(filter)
+                    .is_none_or(|(value, filter)| filter.may_contain(value))
+        })
+    }
+}
+
+/// A table of a [`ColumnarStorage`], the handle of its [`StorageEngine`]. A scan decodes
+/// the columns it reads only, and skips the segments whose [`ZoneMap`] has none of the
+/// values of its ranges.
+///
+/// A deleted row keeps its place in its segment, so the ids of the others don't change,
+/// and is skipped by the scans.
+#[derive(Debug)]
+pub struct ColumnarTable {
+    name: Box<str>,
+    path: PathBuf,
+    rows: Arc<Mutex<Rows>>,
+}
+
+impl ColumnarTable {
+    fn lock(&self) -> MutexGuard<'_, Rows> {
+        self.rows.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The encodings of the columns of each segment.
+    #[must_use]
+    pub fn encodings(&self) -> Vec<Vec<Encoding>> {
+        self.lock()
+            .segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .columns
+                    .iter()
+                    .map(|chunk| chunk.data.encoding())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Writes the rows to the file, if they changed since it was last written. The file is
+    /// written again whole, then renamed over the previous one.
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn sync(&self) -> Result<(), StorageError> {
+        let mut rows = self.lock();
+        if !rows.dirty {
+            return Ok(());
+        }
+        write_file(&self.path, &rows)?;
+        rows.dirty = false;
+        Ok(())
+    }
+
+    /// Keeps a bloom filter of the values of the column at `column` of each segment, see
+    /// [`ColumnarStorage::add_bloom_filter`].
+    fn add_bloom_filter(&self, column: usize) -> Result<(), StorageError> {
+        let mut rows = self.lock();
+        if column >= rows.types.len() {
+            return Err(StorageError::ColumnNotFound(column));
+        }
+        let filters = rows
+            .segments
+            .iter()
+            .map(|segment| BloomFilter::from_values(segment.columns[column].data.decode().iter()))
+            .collect();
+        rows.bloom_filters.insert(column, filters);
+        Ok(())
+    }
+}
+
+impl Table for ColumnarTable {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn insert(&self, row: &[Value]) -> Result<RowId, StorageError> {
+        let mut rows = self.lock();
+        let id = RowId(rows.len());
+        rows.tail.push(row.to_vec());
+        if rows.tail.len() == SEGMENT_ROWS {
+            let Rows {
+                tail,
+                bloom_filters,
+                ..
+            } = &mut *rows;
+            for (column, filters) in bloom_filters {
+                filters.push(BloomFilter::from_values(
+                    tail.iter().map(|row| &row[*column]),
+                ));
+            }
+            let segment = Segment::new(mem::take(&mut rows.tail), rows.types.len());
+            rows.segments.push(Arc::new(segment));
+        }
+        rows.dirty = true;
+        Ok(id)
+    }
+
+    fn get(&self, RowId(id): RowId) -> Result<Option<Vec<Value>>, StorageError> {
+        let rows = self.lock();
+        if id >= rows.len() || rows.deleted.contains(&id) {
+            return Ok(None);
+        }
+        let (segment, offset) = (id as usize / SEGMENT_ROWS, id as usize % SEGMENT_ROWS);
+        Ok(Some(match rows.segments.get(segment) {
+            Some(segment) => segment
+                .columns
+                .iter()
+                .map(|chunk| chunk.data.get(offset))
+                .collect(),
+            None => rows.tail[offset].clone(),
+        }))
+    }
+
+    fn delete(&self, RowId(id): RowId) -> Result<bool, StorageError> {
+        let mut rows = self.lock();
+        if id >= rows.len() || !rows.deleted.insert(id) {
+            return Ok(false);
+        }
+        rows.dirty = true;
+        Ok(true)
+    }
+
+    fn scan(&self) -> Result<Box<dyn Cursor>, StorageError> {
+        self.scan_with(&ScanOptions::default())
+    }
+
+    fn scan_with(&self, options: &ScanOptions) -> Result<Box<dyn Cursor>, StorageError> {
+        Ok(Box::new(Scan {
+            rows: Arc::clone(&self.rows),
+            options: options.clone(),
+            segment: 0,
+            read: VecDeque::new(),
+            done: false,
+        }))
+    }
+
+    fn row_count(&self) -> Option<u64> {
+        let rows = self.lock();
+        Some(rows.len() - rows.deleted.len() as u64)
+    }
+}
+
+/// The rows of a [`ColumnarTable`], a segment at a time. The rows added to a segment the
+/// scan hasn't reached yet, or after the last one, are returned.
+struct Scan {
+    rows: Arc<Mutex<Rows>>,
+    options: ScanOptions,
+    /// The next segment to read.
+    segment: usize,
+    /// The rows of the last segment read not returned yet.
+    read: VecDeque<(RowId, Vec<Value>)>,
+    /// Whether the rows after the last segment were read.
+    done: bool,
+}
+
+impl Scan {
+    /// Reads the rows of the next segment, or the ones after the last segment.
+    fn read_segment(&mut self) {
+        let rows = self.rows.lock().unwrap_or_else(PoisonError::into_inner);
+        let first = self.segment * SEGMENT_ROWS;
+        let Some(segment) = rows.segments.get(self.segment).map(Arc::clone) else {
+            let tail = rows.tail.iter().enumerate();
+            for (offset, row) in tail {
+                let id = (first + offset) as u64;
+                if !rows.deleted.contains(&id) {
+                    self.read
+                        .push_back((RowId(id), self.options.project(row.clone())));
+                }
+            }
+            self.done = true;
+            return;
+        };
+        let end = (first + segment.len) as u64;
+        let deleted = rows
+            .deleted
+            .range(first as u64..end)
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let skip = !rows.segment_may_match(self.segment, &self.options.ranges);
+        drop(rows);
+        self.segment += 1;
+        if skip {
+            return;
+        }
+        let columns = match &self.options.columns {
+            Some(columns) => columns.clone(),
+            None => (0..segment.columns.len()).collect(),
+        };
+        let mut values = columns
+            .iter()
+            .map(|column| segment.columns[*column].data.decode().into_iter())
+            .collect::<Vec<_>>();
+        for offset in 0..segment.len {
+            let row = values
+                .iter_mut()
+                .map(|values| values.next().unwrap_or(Value::Null))
+                .collect();
+            let id = (first + offset) as u64;
+            if !deleted.contains(&id) {
+                self.read.push_back((RowId(id), row));
+            }
+        }
+    }
+}
+
+impl Iterator for Scan {
+    type Item = Result<(RowId, Vec<Value>), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.read.pop_front() {
+                return Some(Ok(row));
+            }
+            if self.done {
+                return None;
+            }
+            self.read_segment();
+        }
+    }
+}
+
+/// Tables kept by column, each in a file of a directory named after the table,
+/// `<table>.col`, for analytic tables whose scans read a few of their columns. The rows are
+/// kept in memory, in [`SEGMENT_ROWS`] rows segments whose columns are encoded on their
+/// own, see [`Encoding`], and written to the files by [`ColumnarStorage::sync`].
+///
+/// Like with a [`DiskStorage`](crate::disk::DiskStorage), the catalog holds the types of
+/// the rows, a table is opened with them. The methods take `&self` and lock the tables,
+/// so the storage can be shared.
+#[derive(Debug)]
+pub struct ColumnarStorage {
+    dir: PathBuf,
+    tables: Mutex<HashMap<Box<str>, Arc<ColumnarTable>>>,
+}
+
+impl ColumnarStorage {
+    /// A storage for the tables of `dir`, created if it is missing. No table is open.
+    /// # Errors
+    /// Returns an error if the directory can't be created.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            tables: Mutex::new(HashMap::new()),
+        })
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, table: &str) -> PathBuf {
+        self.dir.join(format!("{table}.col"))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Box<str>, Arc<ColumnarTable>>> {
+        self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The open table `table`, to read what only a columnar table has, like its encodings.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open.
+    pub fn table(&self, table: &str) -> Result<Arc<ColumnarTable>, StorageError> {
+        self.lock()
+            .get(table)
+            .cloned()
+            .ok_or_else(|| StorageError::TableNotFound(table.into()))
+    }
+
+    /// Creates the file of an empty table whose rows have values of `types`, and opens it.
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if the table is open or its file exists, or
+    /// an error if the file can't be written.
+    pub fn create_table(&self, table: &str, types: Vec<SqlType>) -> Result<(), StorageError> {
+        let mut tables = self.lock();
+        let path = self.path(table);
+        if tables.contains_key(table) || path.exists() {
+            return Err(StorageError::TableExists(table.into()));
+        }
+        let rows = Rows {
+            types,
+            segments: Vec::new(),
+            tail: Vec::new(),
+            deleted: BTreeSet::new(),
+            bloom_filters: BTreeMap::new(),
+            dirty: false,
+        };
+        write_file(&path, &rows)?;
+        tables.insert(
+            table.into(),
+            Arc::new(ColumnarTable {
+                name: table.into(),
+                path,
+                rows: Arc::new(Mutex::new(rows)),
+            }),
+        );
+        Ok(())
+    }
+
+    /// Opens the file of a table created by [`ColumnarStorage::create_table`], whose rows
+    /// have values of `types`, reading its rows.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the file doesn't exist, or an error if it
+    /// can't be read or doesn't hold rows of `types`.
+    pub fn open_table(&self, table: &str, types: Vec<SqlType>) -> Result<(), StorageError> {
+        let path = self.path(table);
+        if !path.exists() {
+            return Err(StorageError::TableNotFound(table.into()));
+        }
+        let rows = read_file(&path, types)?;
+        self.lock().insert(
+            table.into(),
+            Arc::new(ColumnarTable {
+                name: table.into(),
+                path,
+                rows: Arc::new(Mutex::new(rows)),
+            }),
+        );
+        Ok(())
+    }
+
+    /// Closes a table and deletes its file.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, or an error if the
+    /// file can't be deleted.
+    pub fn drop_table(&self, table: &str) -> Result<(), StorageError> {
+        self.lock()
+            .remove(table)
+            .ok_or_else(|| StorageError::TableNotFound(table.into()))?;
+        std::fs::remove_file(self.path(table))?;
+        Ok(())
+    }
+
+    /// The names of the open tables, sorted.
+    #[must_use]
+    pub fn tables(&self) -> Vec<Box<str>> {
+        let mut tables = self.lock().keys().cloned().collect::<Vec<_>>();
+        tables.sort();
+        tables
+    }
+
+    /// Keeps a bloom filter of the values of the column at `column` of each segment of
+    /// `table`, built when the segment is filled, which the scans of an equality on the
+    /// column skip the segment with when it holds none of their value. The filters are kept
+    /// in memory only: they are added again when the table is opened.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, or
+    /// [`StorageError::ColumnNotFound`] if it has no column at `column`.
+    pub fn add_bloom_filter(&self, table: &str, column: usize) -> Result<(), StorageError> {
+        self.table(table)?.add_bloom_filter(column)
+    }
+
+    /// Snapshots the rows of the open tables `tables`, all locked at once while their rows
+    /// are copied, see [`StorageEngine::snapshot`]. The segments are shared with the copy,
+    /// so the copy of a table is the one of the rows after its last segment only.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if a table isn't open.
+    pub fn snapshot(&self, tables: &[&str]) -> Result<Vec<ColumnarSnapshot>, StorageError> {
+        let mut tables = tables.to_vec();
+        tables.sort_unstable();
+        tables.dedup();
+        let handles = tables
+            .iter()
+            .map(|table| self.table(table))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows = handles.iter().map(|table| table.lock()).collect::<Vec<_>>();
+        Ok(handles
+            .iter()
+            .zip(&rows)
+            .map(|(table, rows)| ColumnarSnapshot {
+                table: table.name.clone(),
+                rows: (**rows).clone(),
+            })
+            .collect())
+    }
+
+    /// Writes the rows of `table` to the directory `dir`, created if it is missing, where a
+    /// storage opened at `dir` opens the table, see [`ColumnarStorage::backup`]. Returns
+    /// the bytes written.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open,
+    /// [`StorageError::TableExists`] if `dir` has its file, or an error if it can't be
+    /// written.
+    pub fn backup_table(&self, table: &str, dir: impl AsRef<Path>) -> Result<u64, StorageError> {
+        self.backup_tables(&[table], dir)
+    }
+
+    /// Writes the rows of the open tables to the directory `dir`, a backup the storage is
+    /// restored from by [`ColumnarStorage::restore`]. Returns the bytes written. The
+    /// tables hold the rows they had at one moment, see [`ColumnarStorage::snapshot`]: the
+    /// writes to them wait for the copy, not for the files to be written.
+    /// # Errors
+    /// Returns the errors of [`ColumnarStorage::backup_table`].
+    pub fn backup(&self, dir: impl AsRef<Path>) -> Result<u64, StorageError> {
+        let tables = self.tables();
+        self.backup_tables(&tables.iter().map(AsRef::as_ref).collect::<Vec<_>>(), dir)
+    }
+
+    fn backup_tables(&self, tables: &[&str], dir: impl AsRef<Path>) -> Result<u64, StorageError> {
+        let dir = dir.as_ref();
+        self.snapshot(tables)?
+            .iter()
+            .map(|snapshot| snapshot.write(dir))
+            .sum()
+    }
+
+    /// Copies the files of the tables of the backup `backup`, taken by
+    /// [`ColumnarStorage::backup`], to `dir`, and opens a storage for them there, like
+    /// [`ColumnarStorage::open`].
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if `dir` has the file of a table of the
+    /// backup, or an error if a file can't be copied.
+    pub fn restore(
+        backup: impl AsRef<Path>,
+        dir: impl Into<PathBuf>,
+    ) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        backup::restore(backup.as_ref(), &dir, "col")?;
+        Self::open(dir)
+    }
+
+    /// Writes the open tables that changed to their files, see [`ColumnarTable::sync`].
+    /// # Errors
+    /// Returns an error if a file can't be written.
+    pub fn sync(&self) -> Result<(), StorageError> {
+        let tables = self.lock().values().cloned().collect::<Vec<_>>();
+        for table in tables {
+            table.sync()?;
+        }
+        Ok(())
+    }
+}
+
+impl StorageEngine for ColumnarStorage {
+    fn create_table(&self, name: &str, types: Vec<SqlType>) -> Result<(), StorageError> {
+        Self::create_table(self, name, types)
+    }
+
+    fn drop_table(&self, name: &str) -> Result<(), StorageError> {
+        Self::drop_table(self, name)
+    }
+
+    fn table(&self, name: &str) -> Result<Arc<dyn Table>, StorageError> {
+        Ok(Self::table(self, name)?)
+    }
+
+    fn tables(&self) -> Vec<Box<str>> {
+        Self::tables(self)
+    }
+
+    fn snapshot(&self, names: &[&str]) -> Result<Vec<Box<dyn TableSnapshot>>, StorageError> {
+        Ok(Self::snapshot(self, names)?
+            .into_iter()
+            .map(|snapshot| Box::new(snapshot) as Box<dyn TableSnapshot>)
+            .collect())
+    }
+
+    fn add_bloom_filter(&self, name: &str, column: usize) -> Result<(), StorageError> {
+        Self::add_bloom_filter(self, name, column)
+    }
+}
+
+/// Writes `rows` to `path`, all numbers little-endian:
+///
+/// 1. [`MAGIC`].
+/// 2. The number of segments as 4 bytes, then each one: its number of rows as 4 bytes,
+///    then each column: the number of values of its zone map that aren't `NULL` as 4
+///    bytes, whether it has bounds as a byte and the two bounds, then the [`Encoding`] as
+///    a byte and the values.
+/// 3. The number of rows after the last segment as 4 bytes, then each one as its length
+///    as 4 bytes and the row in the format of [`encode_row`].
+/// 4. The number of deleted rows as 4 bytes, then their id as 8 bytes.
+///
+/// A value is a byte, `0` for `NULL`, then the value as [`Value::encode`] writes it.
+#[allow(clippy::cast_possible_truncation)]
+/// The rows of a table of a [`ColumnarStorage`] at a moment, see
+/// [`ColumnarStorage::snapshot`].
+#[derive(Debug, Clone)]
+pub struct ColumnarSnapshot {
+    table: Box<str>,
+    rows: Rows,
+}
+
+impl TableSnapshot for ColumnarSnapshot {
+    fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Writes the rows to `<table>.col` in `dir`, a file of the format of the one of the
+    /// table.
+    fn write(&self, dir: &Path) -> Result<u64, StorageError> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.col", self.table));
+        if path.exists() {
+            return Err(StorageError::TableExists(self.table.clone()));
+        }
+        write_file(&path, &self.rows)?;
+        Ok(std::fs::metadata(&path)?.len())
+    }
+}
+
+fn write_file(path: &Path, rows: &Rows) -> Result<(), StorageError> {
+    // The counts are bounded by the rows of a segment, or by the memory of the table.
+    fn value(value: &Value, out: &mut Vec<u8>) {
+        out.push(u8::from(!value.is_null()));
+        value.encode(out);
+    }
+    let len = |len: usize, out: &mut Vec<u8>| out.extend((len as u32).to_le_bytes());
+    let mut out = MAGIC.to_vec();
+    len(rows.segments.len(), &mut out);
+    for segment in &rows.segments {
+        len(segment.len, &mut out);
+        for chunk in &segment.columns {
+            out.extend(chunk.zone.values.to_le_bytes());
+            out.push(u8::from(chunk.zone.bounds.is_some()));
+            if let Some((min, max)) = &chunk.zone.bounds {
+                value(min, &mut out);
+                value(max, &mut out);
+            }
+            match &chunk.data {
+                ColumnData::Plain(values) => {
+                    out.push(0);
+                    values.iter().for_each(|v| value(v, &mut out));
+                }
+                ColumnData::Dictionary { values, codes } => {
+                    out.push(1);
+                    len(values.len(), &mut out);
+                    values.iter().for_each(|v| value(v, &mut out));
+                    codes.iter().for_each(|code| out.extend(code.to_le_bytes()));
+                }
+                ColumnData::RunLength(runs) => {
+                    out.push(2);
+                    len(runs.len(), &mut out);
+                    for (v, run) in runs {
+                        value(v, &mut out);
+                        out.extend(run.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+    len(rows.tail.len(), &mut out);
+    for row in &rows.tail {
+        let mut record = Vec::new();
+        encode_row(row, &mut record);
+        len(record.len(), &mut out);
+        out.extend(record);
+    }
+    len(rows.deleted.len(), &mut out);
+    for id in &rows.deleted {
+        out.extend(id.to_le_bytes());
+    }
+
+    let temporary = path.with_extension("col.tmp");
+    let mut file = std::fs::File::create(&temporary)?;
+    file.write_all(&out)?;
+    file.sync_all()?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// Reads the rows of `types` [`write_file`] wrote to `path`.
+fn read_file(path: &Path, types: Vec<SqlType>) -> Result<Rows, StorageError> {
+    let bytes = std::fs::read(path)?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        path,
+    };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(reader.corrupt());
+    }
+    let mut segments = Vec::new();
+    for _ in 0..reader.u32()? {
+        let len = reader.u32()? as usize;
+        if len > SEGMENT_ROWS {
+            return Err(reader.corrupt());
+        }
+        let mut columns = Vec::with_capacity(types.len());
+        for tp in &types {
+            let values = reader.u32()?;
+            let bounds = match reader.take(1)?[0] {
+                0 => None,
+                _ => Some((reader.value(tp)?, reader.value(tp)?)),
+            };
+            let data = match reader.take(1)?[0] {
+                0 => ColumnData::Plain(
+                    (0..len)
+                        .map(|_| reader.value(tp))
+                        .collect::<Result<_, _>>()?,
+                ),
+                1 => {
+                    let count = reader.u32()?;
+                    let values = (0..count)
+                        .map(|_| reader.value(tp))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let codes = (0..len)
+                        .map(|_| reader.u32())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if codes.iter().any(|code| *code >= count) {
+                        return Err(reader.corrupt());
+                    }
+                    ColumnData::Dictionary { values, codes }
+                }
+                2 => {
+                    let runs = (0..reader.u32()?)
+                        .map(|_| Ok((reader.value(tp)?, reader.u32()?)))
+                        .collect::<Result<Vec<_>, StorageError>>()?;
+                    if runs.iter().map(|(_, run)| *run as usize).sum::<usize>() != len {
+                        return Err(reader.corrupt());
+                    }
+                    ColumnData::RunLength(runs)
+                }
+                _ => return Err(reader.corrupt()),
+            };
+            columns.push(Chunk {
+                data,
+                zone: ZoneMap { bounds, values },
+            });
+        }
+        segments.push(Arc::new(Segment { len, columns }));
+    }
+    if segments.iter().any(|segment| segment.len != SEGMENT_ROWS) {
+        return Err(reader.corrupt());
+    }
+    let mut tail = Vec::new();
+    for _ in 0..reader.u32()? {
+        let len = reader.u32()? as usize;
+        tail.push(decode_row(&types, reader.take(len)?)?);
+    }
+    let mut deleted = BTreeSet::new();
+    for _ in 0..reader.u32()? {
+        deleted.insert(u64::from_le_bytes(reader.array()?));
+    }
+    if !reader.bytes.is_empty() {
+        return Err(reader.corrupt());
+    }
+    Ok(Rows {
+        types,
+        segments,
+        tail,
+        deleted,
+        bloom_filters: BTreeMap::new(),
+        dirty: false,
+    })
+}
+
+/// Reads the file of a table from its start.
+struct Reader<'b> {
+    bytes: &'b [u8],
+    path: &'b Path,
+}
+
+impl<'b> Reader<'b> {
+    fn corrupt(&self) -> StorageError {
+        StorageError::CorruptFile(self.path.display().to_string().into())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], StorageError> {
+        if self.bytes.len() < len {
+            return Err(self.corrupt());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StorageError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u32(&mut self) -> Result<u32, StorageError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn value(&mut self, tp: &SqlType) -> Result<Value, StorageError> {
+        if self.take(1)?[0] == 0 {
+            return Ok(Value::Null);
+        }
+        let (value, rest) = Value::decode(tp, self.bytes)?;
+        self.bytes = rest;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::ops::Bound;
+
+    use rs_db_parser::ast::commands::create::LengthUnit;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// A row whose first column increases, so its zone maps don't overlap, whose second
+    /// column has a few distinct values, and whose third is the same in long runs.
+    fn row(i: i32) -> Vec<Value> {
+        let city = ["Lisbon", "Porto", "Faro"][usize::try_from(i % 3).unwrap()];
+        let status = if i % 2000 < 1000 {
+            Value::Bool(true)
+        } else {
+            Value::Null
+        };
+        vec![Value::I32(i), Value::VarChar(city.into()), status]
+    }
+
+    #[test]
+    fn test_columnar_storage() {
+        let dir = TempDir::new();
+        let types = vec![
+            SqlType::I32,
+            SqlType::VarChar(20, LengthUnit::Chars),
+            SqlType::Bool,
+        ];
+        let storage = ColumnarStorage::open(dir.path()).unwrap();
+        storage.create_table("public.t", types.clone()).unwrap();
+        assert_eq!(
+            storage.create_table("public.t", types.clone()),
+            Err(StorageError::TableExists("public.t".into()))
+        );
+        let table = storage.table("public.t").unwrap();
+        let rows = 3 * SEGMENT_ROWS + 100;
+        let ids = (0..i32::try_from(rows).unwrap())
+            .map(|i| table.insert(&row(i)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            table.encodings(),
+            vec![vec![Encoding::Plain, Encoding::Dictionary, Encoding::RunLength]; 3]
+        );
+        assert_eq!(table.get(ids[1500]).unwrap(), Some(row(1500)));
+        assert_eq!(table.get(ids[rows - 1]).unwrap(), Some(row(3171)));
+        assert!(table.delete(ids[1500]).unwrap());
+        assert!(!table.delete(ids[1500]).unwrap());
+        assert!(table.delete(ids[rows - 1]).unwrap());
+        assert_eq!(table.get(ids[1500]).unwrap(), None);
+        assert_eq!(table.row_count(), Some(rows as u64 - 2));
+
+        // A scan reads the columns it asks for, and skips the segments out of its ranges,
+        // not the rows after the last one.
+        let options = ScanOptions {
+            columns: Some(vec![1, 0]),
+            ranges: vec![ColumnRange {
+                column: 0,
+                lower: Bound::Included(Value::I32(1400)),
+                upper: Bound::Excluded(Value::I32(1600)),
+            }],
+        };
+        let scanned = table
+            .scan_with(&options)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(scanned.len(), SEGMENT_ROWS - 1 + 99);
+        assert_eq!(
+            scanned[0],
+            (
+                ids[1024],
+                vec![Value::VarChar("Porto".into()), Value::I32(1024)]
+            )
+        );
+        // The zone maps of the status have TRUE only, a range above it skips them.
+        let status = |lower| ScanOptions {
+            columns: Some(vec![2]),
+            ranges: vec![ColumnRange {
+                column: 2,
+                lower,
+                upper: Bound::Unbounded,
+            }],
+        };
+        let count = |options| table.scan_with(&options).unwrap().count();
+        assert_eq!(count(status(Bound::Excluded(Value::Bool(true)))), 99);
+        assert_eq!(count(status(Bound::Included(Value::Bool(true)))), rows - 2);
+
+        // The rows are read back from the file.
+        storage.sync().unwrap();
+        drop(storage);
+        let storage = ColumnarStorage::open(dir.path()).unwrap();
+        assert!(storage.table("public.t").is_err());
+        storage.open_table("public.t", types.clone()).unwrap();
+        let table = storage.table("public.t").unwrap();
+        let expected = (0..i32::try_from(rows).unwrap() - 1)
+            .filter(|i| *i != 1500)
+            .map(row)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            table
+                .scan()
+                .unwrap()
+                .map(|row| row.unwrap().1)
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(table.get(ids[2000]).unwrap(), Some(row(2000)));
+
+        // A file cut short fails to read.
+        std::fs::write(dir.path().join("public.u.col"), b"rsdbcol\x01\x01").unwrap();
+        assert_eq!(
+            storage.open_table("public.u", types),
+            Err(StorageError::CorruptFile(
+                dir.path().join("public.u.col").display().to_string().into()
+            ))
+        );
+        storage.drop_table("public.t").unwrap();
+        assert!(!dir.path().join("public.t.col").exists());
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let dir = TempDir::new();
+        let types = vec![
+            SqlType::I32,
+            SqlType::VarChar(20, LengthUnit::Chars),
+            SqlType::Bool,
+        ];
+        let storage = ColumnarStorage::open(dir.path()).unwrap();
+        storage.create_table("public.t", types).unwrap();
+        let table = storage.table("public.t").unwrap();
+        let insert = |range: std::ops::Range<usize>| {
+            for i in range {
+                table.insert(&row(i32::try_from(i).unwrap())).unwrap();
+            }
+        };
+        insert(0..2 * SEGMENT_ROWS);
+        let city = |name: &str| ScanOptions {
+            columns: Some(vec![1]),
+            ranges: vec![ColumnRange {
+                column: 1,
+                lower: Bound::Included(Value::VarChar(name.into())),
+                upper: Bound::Included(Value::VarChar(name.into())),
+            }],
+        };
+        let count = |options| table.scan_with(&options).unwrap().count();
+        // The zone maps of the cities, from Faro to Porto, don't rule Madrid out.
+        assert_eq!(count(city("Madrid")), 2 * SEGMENT_ROWS);
+        assert_eq!(
+            storage.add_bloom_filter("public.t", 3),
+            Err(StorageError::ColumnNotFound(3))
+        );
+        storage.add_bloom_filter("public.t", 1).unwrap();
+        // The segments filled later have a filter too, the rows after the last one are
+        // read.
+        insert(2 * SEGMENT_ROWS..3 * SEGMENT_ROWS + 100);
+        assert_eq!(count(city("Madrid")), 100);
+        assert_eq!(count(city("Porto")), 3 * SEGMENT_ROWS + 100);
+    }
+
+    #[test]
+    fn test_backup() {
+        let dir = TempDir::new();
+        let types = vec![
+            SqlType::I32,
+            SqlType::VarChar(20, LengthUnit::Chars),
+            SqlType::Bool,
+        ];
+        let storage = ColumnarStorage::open(dir.path().join("data")).unwrap();
+        storage.create_table("public.t", types.clone()).unwrap();
+        let table = storage.table("public.t").unwrap();
+        for i in 0..1500 {
+            table.insert(&row(i)).unwrap();
+        }
+        table.delete(RowId(7)).unwrap();
+
+        // The rows written once the backup is taken aren't in it.
+        let backup = dir.path().join("backup");
+        assert!(storage.backup(&backup).unwrap() > 0);
+        table.insert(&row(1500)).unwrap();
+        assert_eq!(
+            storage.backup_table("public.t", &backup),
+            Err(StorageError::TableExists("public.t".into()))
+        );
+        let restored = ColumnarStorage::restore(&backup, dir.path().join("restored")).unwrap();
+        restored.open_table("public.t", types).unwrap();
+        assert_eq!(
+            restored
+                .table("public.t")
+                .unwrap()
+                .scan()
+                .unwrap()
+                .map(|row| row.unwrap().1)
+                .collect::<Vec<_>>(),
+            (0..1500).filter(|i| *i != 7).map(row).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use rs_db_parser::ast::commands::create::Compression;
+
+use crate::{
+    error::StorageError,
+    heap::PageId,
+    page::{Page, PAGE_SIZE},
+};
+
+/// The start of a file of compressed pages, followed by the byte of its compression.
+const MAGIC: &[u8; 7] = b"rsdbcmp";
+
+const FILE_HEADER_SIZE: u64 = 8;
+
+/// The page of an extent, its capacity, then the length of the compressed page in it.
+const EXTENT_HEADER_SIZE: u64 = 12;
+
+/// The page of an extent no page is kept in anymore.
+const FREE_EXTENT: u32 = u32::MAX;
+
+/// The capacities of the extents are multiples of it, so a page whose compressed length
+/// grows a little is written again in place.
+const EXTENT_ALIGN: usize = 256;
+
+/// Where a page is in a [`CompressedFile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    /// The offset of its header.
+    offset: u64,
+    /// The bytes after the header the compressed page may take.
+    capacity: u32,
+}
+
+impl Extent {
+    const fn end(self) -> u64 {
+        self.offset + EXTENT_HEADER_SIZE + self.capacity as u64
+    }
+}
+
+const fn compression_byte(compression: Compression) -> u8 {
+    match compression {
+        Compression::None => 0,
+        Compression::Lz4 => 1,
+        Compression::Zstd => 2,
+    }
+}
+
+fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>, StorageError> {
+    Ok(match compression {
+        Compression::None => data.to_vec(),
+        Compression::Lz4 => lz4_flex::compress(data),
+        Compression::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)?,
+    })
+}
+
+/// The page compressed in `data`, `None` if it doesn't decompress to a page.
+fn decompress(compression: Compression, data: &[u8]) -> Option<Page> {
+    let data = match compression {
+        Compression::None => data.to_vec(),
+        Compression::Lz4 => lz4_flex::decompress(data, PAGE_SIZE).ok()?,
+        Compression::Zstd => zstd::bulk::decompress(data, PAGE_SIZE).ok()?,
+    };
+    Page::from_bytes(data.into())
+}
+
+/// A file of pages compressed one by one, so it takes less room on the disk than the
+/// [`PAGE_SIZE`] of each page. Pages are decompressed when they are read into memory and
+/// compressed when they are written back.
+///
+/// The file starts with a header, the magic bytes then the compression. A page is kept in
+/// an extent: a header of its page, its capacity and the length of the compressed page,
+/// 4 bytes each, little-endian, then the compressed page. A page is written again in its
+/// extent when it fits, else in a new extent at the end of the file, its old extent is
+/// marked free. The extents are read when the file is opened, to find the pages, and
+/// [`CompressedFile::compact`] moves them together to give the free ones back.
+#[derive(Debug)]
+pub(crate) struct CompressedFile {
+    file: File,
+    compression: Compression,
+    extents: BTreeMap<PageId, Extent>,
+    /// The end of the last extent.
+    end: u64,
+}
+
+impl CompressedFile {
+    /// Whether `file` starts with the header of a compressed file.
+    /// # Errors
+    /// Returns an error if the file can't be read.
+    pub(crate) fn is_compressed(file: &mut File) -> Result<bool, StorageError> {
+        let mut magic = [0; MAGIC.len()];
+        file.seek(SeekFrom::Start(0))?;
+        let compressed = match file.read_exact(&mut magic) {
+            Ok(()) => magic == *MAGIC,
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => false,
+            Err(error) => return Err(error.into()),
+        };
+        Ok(compressed)
+    }
+
+    /// Writes the header of a file of pages compressed with `compression` to the empty
+    /// `file`.
+    /// # Errors
+    /// Returns an error if the header can't be written.
+    pub(crate) fn create(mut file: File, compression: Compression) -> Result<Self, StorageError> {
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(MAGIC)?;
+        file.write_all(&[compression_byte(compression)])?;
+        Ok(Self {
+            file,
+            compression,
+            extents: BTreeMap::new(),
+            end: FILE_HEADER_SIZE,
+        })
+    }
+
+    /// Reads the header and the extents of `file`, named `name` in the errors.
+    /// # Errors
+    /// Returns [`StorageError::CorruptPage`] if the header or an extent isn't one this
+    /// file writes, or an error if the file can't be read.
+    pub(crate) fn open(mut file: File, name: &str) -> Result<Self, StorageError> {
+        let len = file.metadata()?.len();
+        let corrupt = |page| StorageError::CorruptPage {
+            file: name.into(),
+            page,
+        };
+        let mut header = [0; FILE_HEADER_SIZE as usize];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header).map_err(|_| corrupt(0))?;
+        let compression = [Compression::None, Compression::Lz4, Compression::Zstd]
+            .into_iter()
+            .find(|compression| compression_byte(*compression) == header[MAGIC.len()])
+            .filter(|_| header[..MAGIC.len()] == *MAGIC)
+            .ok_or_else(|| corrupt(0))?;
+        let mut extents = BTreeMap::new();
+        let mut offset = FILE_HEADER_SIZE;
+        while offset < len {
+            let mut header = [0; EXTENT_HEADER_SIZE as usize];
+            file.read_exact(&mut header).map_err(|_| corrupt(offset))?;
+            let [page, capacity, _] = read_extent_header(&header);
+            let extent = Extent { offset, capacity };
+            if extent.end() > len {
+                return Err(corrupt(offset));
+            }
+            if page != FREE_EXTENT && extents.insert(page, extent).is_some() {
+                return Err(corrupt(u64::from(page)));
+            }
+            offset = extent.end();
+            file.seek(SeekFrom::Start(offset))?;
+        }
+        Ok(Self {
+            file,
+            compression,
+            extents,
+            end: offset,
+        })
+    }
+
+    #[must_use]
+    pub(crate) const fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// The number of pages, up to the last one written.
+    #[must_use]
+    pub(crate) fn page_count(&self) -> PageId {
+        self.extents
+            .last_key_value()
+            .map_or(0, |(page, _)| page + 1)
+    }
+
+    /// Reads `page`, an empty page if it wasn't written. A corrupted page is reported
+    /// without the name of the file.
+    /// # Errors
+    /// Returns an error if the page can't be read or decompressed.
+    pub(crate) fn read_page(&mut self, page: PageId) -> Result<Page, StorageError> {
+        let Some(extent) = self.extents.get(&page).copied() else {
+            return Ok(Page::new());
+        };
+        let corrupt = || StorageError::CorruptPage {
+            file: "".into(),
+            page: u64::from(page),
+        };
+        let mut header = [0; EXTENT_HEADER_SIZE as usize];
+        self.file.seek(SeekFrom::Start(extent.offset))?;
+        self.file.read_exact(&mut header)?;
+        let [_, _, len] = read_extent_header(&header);
+        if len > extent.capacity {
+            return Err(corrupt());
+        }
+        let mut data = vec![0; len as usize];
+        self.file.read_exact(&mut data)?;
+        decompress(self.compression, &data).ok_or_else(corrupt)
+    }
+
+    /// Compresses `data` and writes it as `page`, see [`CompressedFile`].
+    /// # Errors
+    /// Returns an error if the page can't be compressed or written.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn write_page(&mut self, page: PageId, data: &Page) -> Result<(), StorageError> {
+        let compressed = compress(self.compression, data.as_bytes())?;
+        // A compressed page is at most a little longer than a page.
+        let len = compressed.len() as u32;
+        let extent = match self.extents.get(&page).copied() {
+            Some(extent) if extent.capacity >= len => extent,
+            old => {
+                if let Some(old) = old {
+                    self.write_header(old.offset, [FREE_EXTENT, old.capacity, 0])?;
+                }
+                let extent = Extent {
+                    offset: self.end,
+                    capacity: compressed.len().next_multiple_of(EXTENT_ALIGN) as u32,
+                };
+                self.end = extent.end();
+                self.extents.insert(page, extent);
+                extent
+            }
+        };
+        self.write_header(extent.offset, [page, extent.capacity, len])?;
+        self.file.write_all(&compressed)?;
+        // The new extent is written up to its end, so the next one starts after it.
+        let padding = extent.capacity - len;
+        self.file.write_all(&vec![0; padding as usize])?;
+        Ok(())
+    }
+
+    fn write_header(&mut self, offset: u64, header: [u32; 3]) -> Result<(), StorageError> {
+        let mut bytes = [0; EXTENT_HEADER_SIZE as usize];
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(header) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Frees the extents of the pages from `pages` on, the file keeps its length until it
+    /// is compacted.
+    /// # Errors
+    /// Returns an error if an extent can't be freed.
+    pub(crate) fn truncate(&mut self, pages: PageId) -> Result<(), StorageError> {
+        let dropped = self.extents.split_off(&pages);
+        for extent in dropped.into_values() {
+            self.write_header(extent.offset, [FREE_EXTENT, extent.capacity, 0])?;
+        }
+        Ok(())
+    }
+
+    /// Moves the extents of the pages towards the start of the file, in their order in it,
+    /// and cuts the free extents left at its end. Returns the bytes the file lost.
+    /// # Errors
+    /// Returns an error if an extent can't be moved or the file can't be cut.
+    pub(crate) fn compact(&mut self) -> Result<u64, StorageError> {
+        let mut extents = self
+            .extents
+            .iter_mut()
+            .map(|(_, extent)| extent)
+            .collect::<Vec<_>>();
+        extents.sort_unstable_by_key(|extent| extent.offset);
+        let mut end = FILE_HEADER_SIZE;
+        // An extent is moved to the end of the one before it, it is never overwritten
+        // before it is moved.
+        for extent in extents {
+            if extent.offset != end {
+                let mut data = vec![0; (extent.end() - extent.offset) as usize];
+                self.file.seek(SeekFrom::Start(extent.offset))?;
+                self.file.read_exact(&mut data)?;
+                self.file.seek(SeekFrom::Start(end))?;
+                self.file.write_all(&data)?;
+                extent.offset = end;
+            }
+            end = extent.end();
+        }
+        self.file.set_len(end)?;
+        let reclaimed = self.end - end;
+        self.end = end;
+        Ok(reclaimed)
+    }
+
+    /// Flushes the file to the disk.
+    /// # Errors
+    /// Returns an error if it can't be flushed.
+    pub(crate) fn sync_all(&self) -> Result<(), StorageError> {
+        Ok(self.file.sync_all()?)
+    }
+}
+
+fn read_extent_header(header: &[u8; EXTENT_HEADER_SIZE as usize]) -> [u32; 3] {
+    let field = |at: usize| {
+        u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    };
+    [field(0), field(4), field(8)]
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::fs::OpenOptions;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn page(records: &[&[u8]]) -> Page {
+        let mut page = Page::new();
+        for record in records {
+            page.insert(record).unwrap();
+        }
+        page
+    }
+
+    #[test]
+    fn test_compressed_file() {
+        let dir = TempDir::new();
+        let path = dir.path().join("t.heap");
+        let open = || {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .unwrap()
+        };
+        for compression in [Compression::Lz4, Compression::Zstd] {
+            std::fs::remove_file(&path).ok();
+            let mut file = CompressedFile::create(open(), compression).unwrap();
+            assert!(CompressedFile::is_compressed(&mut open()).unwrap());
+            let small = page(&[b"a row"]);
+            let large = page(&[&[7; 3000], &[8; 3000]]);
+            file.write_page(0, &small).unwrap();
+            file.write_page(2, &small).unwrap();
+            assert_eq!(file.page_count(), 3);
+            assert_eq!(file.read_page(1).unwrap(), Page::new());
+            assert_eq!(file.read_page(2).unwrap(), small);
+            // The pages take much less than a page each.
+            let len = std::fs::metadata(&path).unwrap().len();
+            assert!(len < PAGE_SIZE as u64 / 4, "{len}");
+
+            // Page 0 grows out of its extent, its new one is at the end.
+            let random = (0..4000u32)
+                .map(|i| u8::try_from(i.wrapping_mul(2_654_435_761) >> 24).unwrap())
+                .collect::<Vec<_>>();
+            let grown = page(&[&random, &[1; 100]]);
+            file.write_page(0, &grown).unwrap();
+            file.write_page(1, &large).unwrap();
+            drop(file);
+
+            let mut file = CompressedFile::open(open(), "t").unwrap();
+            assert_eq!(file.compression(), compression);
+            assert_eq!(file.page_count(), 3);
+            assert_eq!(file.read_page(0).unwrap(), grown);
+            assert_eq!(file.read_page(1).unwrap(), large);
+            assert_eq!(file.read_page(2).unwrap(), small);
+
+            // The free extent of page 0, then the ones of the pages cut, are given back.
+            file.truncate(1).unwrap();
+            assert_eq!(file.page_count(), 1);
+            let before = std::fs::metadata(&path).unwrap().len();
+            let reclaimed = file.compact().unwrap();
+            assert!(reclaimed > 0);
+            let after = std::fs::metadata(&path).unwrap().len();
+            assert_eq!(before - after, reclaimed);
+            drop(file);
+            let mut file = CompressedFile::open(open(), "t").unwrap();
+            assert_eq!(file.page_count(), 1);
+            assert_eq!(file.read_page(0).unwrap(), grown);
+        }
+
+        // A file cut in the middle of an extent is corrupted.
+        let file = open();
+        let len = file.metadata().unwrap().len();
+        file.set_len(len - 1).unwrap();
+        assert!(matches!(
+            CompressedFile::open(open(), "t"),
+            Err(StorageError::CorruptPage { .. })
+        ));
+        let mut empty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.path().join("u"))
+            .unwrap();
+        assert!(!CompressedFile::is_compressed(&mut empty).unwrap());
+    }
+}
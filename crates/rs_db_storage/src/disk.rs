@@ -0,0 +1,968 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use rs_db_parser::{
+    ast::commands::create::{Compression, SqlType},
+    codec::{decode_row, encode_row},
+    value::Value,
+};
+
+use crate::{
+    backup::{self, write_pages},
+    bloom::BloomFilter,
+    buffer::{BufferPool, BufferPoolConfig},
+    engine::{ColumnRange, Cursor, IndexDefinition, RowId, StorageEngine, Table, TableSnapshot},
+    error::StorageError,
+    heap::{HeapFile, PageId, RecordId, VacuumStats},
+    index::OrderedIndex,
+    page::Page,
+};
+
+/// The values the first [`BloomFilter`] of a column of a [`DiskTable`] has room for, at
+/// least.
+const MIN_BLOOM_FILTER_VALUES: usize = 1024;
+
+/// The file of a [`DiskTable`], and the types of its rows in their order.
+#[derive(Debug)]
+struct TableFile {
+    heap: HeapFile,
+    types: Vec<SqlType>,
+    /// The bloom filters of the values of some columns, by their position. A value is
+    /// added to the last filter of its column, followed by one twice as large once it is
+    /// full, so the filters find few values that weren't added however many rows are.
+    bloom_filters: BTreeMap<usize, Vec<BloomFilter>>,
+    /// The indexes of the rows, see [`DiskStorage::add_index`].
+    indexes: Vec<OrderedIndex>,
+}
+
+impl TableFile {
+    /// Adds the values of `row` to the bloom filters.
+    fn add_to_bloom_filters(&mut self, row: &[Value]) {
+        for (column, filters) in &mut self.bloom_filters {
+            let Some(last) = filters.last_mut() else {
+                continue;
+            };
+            if last.is_full() {
+                let filter = BloomFilter::new(last.capacity() * 2);
+                filters.push(filter);
+            }
+            if let (Some(filter), Some(value)) = (filters.last_mut(), row.get(*column)) {
+                filter.insert(value);
+            }
+        }
+    }
+}
+
+/// A table opened by a [`DiskStorage`], the handle of its [`StorageEngine`]. The handle
+/// and its scans keep the file open: once the table is dropped, they still read the rows
+/// it had.
+#[derive(Debug)]
+pub struct DiskTable {
+    name: Box<str>,
+    file: Arc<Mutex<TableFile>>,
+}
+
+impl DiskTable {
+    fn new(name: &str, heap: HeapFile, types: Vec<SqlType>) -> Self {
+        Self {
+            name: name.into(),
+            file: Arc::new(Mutex::new(TableFile {
+                heap,
+                types,
+                bloom_filters: BTreeMap::new(),
+                indexes: Vec::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TableFile> {
+        self.file.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, row: &[Value]) -> Result<RecordId, StorageError> {
+        let mut record = Vec::new();
+        encode_row(row, &mut record);
+        let mut file = self.lock();
+        let id = file.heap.insert(&record)?;
+        file.add_to_bloom_filters(row);
+        for index in &mut file.indexes {
+            index.insert(id.into(), row);
+        }
+        Ok(id)
+    }
+
+    fn get(&self, id: RecordId) -> Result<Option<Vec<Value>>, StorageError> {
+        let file = self.lock();
+        let Some(record) = file.heap.get(id)? else {
+            return Ok(None);
+        };
+        Ok(Some(decode_row(&file.types, &record)?))
+    }
+
+    fn delete(&self, id: RecordId) -> Result<bool, StorageError> {
+        let mut file = self.lock();
+        if file.indexes.is_empty() {
+            return file.heap.delete(id);
+        }
+        // The key of the row is read before it is gone.
+        let Some(record) = file.heap.get(id)? else {
+            return Ok(false);
+        };
+        let row = decode_row(&file.types, &record)?;
+        if !file.heap.delete(id)? {
+            return Ok(false);
+        }
+        for index in &mut file.indexes {
+            index.remove(id.into(), &row);
+        }
+        Ok(true)
+    }
+
+    fn record_count(&self) -> u64 {
+        self.lock().heap.record_count()
+    }
+
+    fn dead_bytes(&self) -> u64 {
+        self.lock().heap.dead_bytes()
+    }
+
+    fn vacuum(&self) -> Result<VacuumStats, StorageError> {
+        self.lock().heap.vacuum()
+    }
+
+    /// Reads the values of the column at `column` of the rows to keep a bloom filter of
+    /// them, see [`DiskStorage::add_bloom_filter`].
+    fn add_bloom_filter(&self, column: usize) -> Result<(), StorageError> {
+        let mut file = self.lock();
+        if column >= file.types.len() {
+            return Err(StorageError::ColumnNotFound(column));
+        }
+        let mut values = Vec::new();
+        for page in 0..file.heap.page_count() {
+            for (_, record) in file.heap.page_records(page)? {
+                values.push(decode_row(&file.types, &record)?.swap_remove(column));
+            }
+        }
+        let mut filter = BloomFilter::new(values.len().max(MIN_BLOOM_FILTER_VALUES));
+        values.iter().for_each(|value| filter.insert(value));
+        file.bloom_filters.insert(column, vec![filter]);
+        Ok(())
+    }
+
+    /// Reads the rows to index them, see [`DiskStorage::add_index`].
+    fn add_index(&self, definition: IndexDefinition) -> Result<(), StorageError> {
+        let mut file = self.lock();
+        if let Some(position) = definition
+            .positions
+            .iter()
+            .find(|position| **position >= file.types.len())
+        {
+            return Err(StorageError::ColumnNotFound(*position));
+        }
+        if file
+            .indexes
+            .iter()
+            .any(|index| index.definition().name == definition.name)
+        {
+            return Err(StorageError::IndexExists(definition.name));
+        }
+        let mut index = OrderedIndex::new(definition);
+        for page in 0..file.heap.page_count() {
+            for (id, record) in file.heap.page_records(page)? {
+                index.insert(id.into(), &decode_row(&file.types, &record)?);
+            }
+        }
+        file.indexes.push(index);
+        Ok(())
+    }
+
+    fn scan(&self) -> Scan {
+        Scan {
+            file: Arc::clone(&self.file),
+            page: 0,
+            rows: VecDeque::new(),
+        }
+    }
+}
+
+impl Table for DiskTable {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn insert(&self, row: &[Value]) -> Result<RowId, StorageError> {
+        Self::insert(self, row).map(RowId::from)
+    }
+
+    fn get(&self, id: RowId) -> Result<Option<Vec<Value>>, StorageError> {
+        Self::get(self, id.into())
+    }
+
+    fn delete(&self, id: RowId) -> Result<bool, StorageError> {
+        Self::delete(self, id.into())
+    }
+
+    fn scan(&self) -> Result<Box<dyn Cursor>, StorageError> {
+        Ok(Box::new(
+            Self::scan(self).map(|row| row.map(|(id, row)| (id.into(), row))),
+        ))
+    }
+
+    /// `false` when the bloom filters of the column of a range hold none of the value it
+    /// keeps alone.
+    fn may_match(&self, ranges: &[ColumnRange]) -> bool {
+        let file = self.lock();
+        ranges.iter().all(
+            |range| match (range.value(), file.bloom_filters.get(&range.column)) {
+                (Some(value), Some(filters)) => {
+                    filters.iter().any(|filter| filter.may_contain(value))
+                }
+                _ => true,
+            },
+        )
+    }
+
+    fn row_count(&self) -> Option<u64> {
+        Some(self.record_count())
+    }
+
+    fn indexes(&self) -> Vec<IndexDefinition> {
+        let file = self.lock();
+        file.indexes
+            .iter()
+            .map(|index| index.definition().clone())
+            .collect()
+    }
+
+    /// Reads the keys of the range from the index, then each row when it is reached: the
+    /// table is locked only while one is read, and a row deleted before is skipped.
+    fn index_scan(
+        &self,
+        index: &str,
+        lower: Bound<&[Value]>,
+        upper: Bound<&[Value]>,
+        key_only: bool,
+    ) -> Result<Box<dyn Cursor>, StorageError> {
+        let entries = self
+            .lock()
+            .indexes
+            .iter()
+            .find(|found| *found.definition().name == *index)
+            .ok_or_else(|| StorageError::IndexNotFound(index.into()))?
+            .range(lower, upper);
+        let file = Arc::clone(&self.file);
+        Ok(Box::new(entries.into_iter().filter_map(
+            move |(key, id)| {
+                if key_only {
+                    return Some(Ok((id, key)));
+                }
+                let file = file.lock().unwrap_or_else(PoisonError::into_inner);
+                match file.heap.get(id.into()) {
+                    Ok(Some(record)) => Some(
+                        decode_row(&file.types, &record)
+                            .map(|row| (id, row))
+                            .map_err(Into::into),
+                    ),
+                    Ok(None) => None,
+                    Err(error) => Some(Err(error)),
+                }
+            },
+        )))
+    }
+
+    fn vacuum(&self) -> Result<u64, StorageError> {
+        Ok(Self::vacuum(self)?.bytes_reclaimed)
+    }
+}
+
+/// Tables kept on the disk, each in a [`HeapFile`] of a directory named after the table,
+/// `<table>.heap`. A record of the file is a row in the format of [`encode_row`], its
+/// [`RowId`] is its [`RecordId`].
+///
+/// The files don't hold the types of the rows, the catalog does: a table is opened with
+/// them. The methods take `&self` and lock the tables, so the storage can be shared. The
+/// pages of the files are kept in a [`BufferPool`] shared by the tables.
+#[derive(Debug)]
+pub struct DiskStorage {
+    dir: PathBuf,
+    pool: Arc<BufferPool>,
+    tables: Mutex<HashMap<Box<str>, Arc<DiskTable>>>,
+}
+
+impl DiskStorage {
+    /// A storage for the tables of `dir`, created if it is missing. No table is open.
+    /// # Errors
+    /// Returns an error if the directory can't be created.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        Self::open_with_pool(dir, BufferPool::new(&BufferPoolConfig::default()))
+    }
+
+    /// Like [`DiskStorage::open`], keeping the pages of the tables in `pool`.
+    /// # Errors
+    /// Returns an error if the directory can't be created.
+    pub fn open_with_pool(
+        dir: impl Into<PathBuf>,
+        pool: Arc<BufferPool>,
+    ) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            pool,
+            tables: Mutex::new(HashMap::new()),
+        })
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    #[must_use]
+    pub fn buffer_pool(&self) -> &Arc<BufferPool> {
+        &self.pool
+    }
+
+    fn path(&self, table: &str) -> PathBuf {
+        self.dir.join(format!("{table}.heap"))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Box<str>, Arc<DiskTable>>> {
+        self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The open table `table`, the tables aren't locked while it is used.
+    fn handle(&self, table: &str) -> Result<Arc<DiskTable>, StorageError> {
+        self.lock()
+            .get(table)
+            .cloned()
+            .ok_or_else(|| StorageError::TableNotFound(table.into()))
+    }
+
+    /// Creates the file of an empty table whose rows have values of `types`, and opens it.
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if the table is open or its file exists, or
+    /// an error if the file can't be created.
+    pub fn create_table(&self, table: &str, types: Vec<SqlType>) -> Result<(), StorageError> {
+        self.create_table_with_compression(table, types, Compression::None)
+    }
+
+    /// Like [`DiskStorage::create_table`], compressing the pages of the file with
+    /// `compression`, the one of the `CREATE TABLE`. The table is opened with the
+    /// compression it was created with.
+    /// # Errors
+    /// Returns the errors of [`DiskStorage::create_table`].
+    pub fn create_table_with_compression(
+        &self,
+        table: &str,
+        types: Vec<SqlType>,
+        compression: Compression,
+    ) -> Result<(), StorageError> {
+        let mut tables = self.lock();
+        let path = self.path(table);
+        if tables.contains_key(table) || path.exists() {
+            return Err(StorageError::TableExists(table.into()));
+        }
+        let heap = HeapFile::create_with_compression(&path, &self.pool, compression)?;
+        tables.insert(table.into(), Arc::new(DiskTable::new(table, heap, types)));
+        Ok(())
+    }
+
+    /// Opens the file of a table created by [`DiskStorage::create_table`], whose rows have
+    /// values of `types`.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the file doesn't exist, or an error if it
+    /// can't be read.
+    pub fn open_table(&self, table: &str, types: Vec<SqlType>) -> Result<(), StorageError> {
+        let path = self.path(table);
+        if !path.exists() {
+            return Err(StorageError::TableNotFound(table.into()));
+        }
+        let heap = HeapFile::open(&path, &self.pool)?;
+        self.lock()
+            .insert(table.into(), Arc::new(DiskTable::new(table, heap, types)));
+        Ok(())
+    }
+
+    /// Closes a table and deletes its file.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, or an error if the
+    /// file can't be deleted.
+    pub fn drop_table(&self, table: &str) -> Result<(), StorageError> {
+        let removed = self
+            .lock()
+            .remove(table)
+            .ok_or_else(|| StorageError::TableNotFound(table.into()))?;
+        drop(removed);
+        std::fs::remove_file(self.path(table))?;
+        Ok(())
+    }
+
+    /// Adds `row`, whose values have the types of the table, to `table`.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, or the errors of
+    /// [`HeapFile::insert`].
+    pub fn insert(&self, table: &str, row: &[Value]) -> Result<RecordId, StorageError> {
+        self.handle(table)?.insert(row)
+    }
+
+    /// The row at `id` in `table`, `None` if it was deleted.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, the errors of
+    /// [`HeapFile::get`], or an error if the record isn't a row of the table.
+    pub fn get(&self, table: &str, id: RecordId) -> Result<Option<Vec<Value>>, StorageError> {
+        self.handle(table)?.get(id)
+    }
+
+    /// Deletes the row at `id` from `table`, returns `false` if it was already deleted.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, or the errors of
+    /// [`HeapFile::delete`].
+    pub fn delete(&self, table: &str, id: RecordId) -> Result<bool, StorageError> {
+        self.handle(table)?.delete(id)
+    }
+
+    /// The rows of `table` with their id, in the order of their pages then of their slots.
+    /// The pages are read one at a time as the rows are, a row added to a page the scan
+    /// hasn't reached yet is returned.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open.
+    pub fn scan(&self, table: &str) -> Result<Scan, StorageError> {
+        Ok(self.handle(table)?.scan())
+    }
+
+    /// The number of rows of `table`, `None` if it isn't open.
+    #[must_use]
+    pub fn row_count(&self, table: &str) -> Option<u64> {
+        self.handle(table).ok().map(|table| table.record_count())
+    }
+
+    /// The bytes of the rows deleted from `table` since it was last vacuumed, `None` if it
+    /// isn't open.
+    #[must_use]
+    pub fn dead_bytes(&self, table: &str) -> Option<u64> {
+        self.handle(table).ok().map(|table| table.dead_bytes())
+    }
+
+    /// The names of the open tables, sorted.
+    #[must_use]
+    pub fn tables(&self) -> Vec<Box<str>> {
+        let mut tables = self.lock().keys().cloned().collect::<Vec<_>>();
+        tables.sort();
+        tables
+    }
+
+    /// Reclaims the space of the rows deleted from `table`, see [`HeapFile::vacuum`].
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open, or the errors of
+    /// [`HeapFile::vacuum`].
+    pub fn vacuum(&self, table: &str) -> Result<VacuumStats, StorageError> {
+        self.handle(table)?.vacuum()
+    }
+
+    /// Keeps a bloom filter of the values of the column at `column` of `table`, added to as
+    /// rows are, which the scans of an equality on the column skip the table with when it
+    /// holds none of their value, like a partition without it. The filter is built from
+    /// the rows of the table, and kept in memory only: it is added again when the table is
+    /// opened. A row deleted stays in it.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open,
+    /// [`StorageError::ColumnNotFound`] if it has no column at `column`, or an error if its
+    /// rows can't be read.
+    pub fn add_bloom_filter(&self, table: &str, column: usize) -> Result<(), StorageError> {
+        self.handle(table)?.add_bloom_filter(column)
+    }
+
+    /// Keeps an index of the rows of `table` by the values of the columns of `index`,
+    /// added to and removed from as rows are, which the scans of a range of its keys read
+    /// instead of the whole file. The index is built from the rows of the table, and kept
+    /// in memory only, like a bloom filter: it is added again when the table is opened.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open,
+    /// [`StorageError::ColumnNotFound`] if it has no column at a position of the index,
+    /// [`StorageError::IndexExists`] if it has an index of the same name, or an error if
+    /// its rows can't be read.
+    pub fn add_index(&self, table: &str, index: IndexDefinition) -> Result<(), StorageError> {
+        self.handle(table)?.add_index(index)
+    }
+
+    /// Snapshots the pages of the open tables `tables`, all locked at once while their
+    /// pages are copied in memory, see [`StorageEngine::snapshot`]. The pages the buffer
+    /// pool holds are copied as they are, written to the file or not.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if a table isn't open, or an error if a page
+    /// can't be read.
+    pub fn snapshot(&self, tables: &[&str]) -> Result<Vec<DiskSnapshot>, StorageError> {
+        let mut tables = tables.to_vec();
+        tables.sort_unstable();
+        tables.dedup();
+        let handles = tables
+            .iter()
+            .map(|table| self.handle(table))
+            .collect::<Result<Vec<_>, _>>()?;
+        let files = handles.iter().map(|table| table.lock()).collect::<Vec<_>>();
+        handles
+            .iter()
+            .zip(&files)
+            .map(|(table, file)| {
+                Ok(DiskSnapshot {
+                    table: table.name.clone(),
+                    compression: file.heap.compression(),
+                    pages: file.heap.snapshot_pages()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Copies the file of `table` to the directory `dir`, created if it is missing, where
+    /// a storage opened at `dir` opens the table, see [`DiskStorage::backup`]. Returns the
+    /// bytes written.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table isn't open,
+    /// [`StorageError::TableExists`] if `dir` has its file, or an error if the file can't
+    /// be written.
+    pub fn backup_table(&self, table: &str, dir: impl AsRef<Path>) -> Result<u64, StorageError> {
+        self.backup_tables(&[table], dir)
+    }
+
+    /// Copies the files of the open tables to the directory `dir`, a backup the storage is
+    /// restored from by [`DiskStorage::restore`]. Returns the bytes written.
+    ///
+    /// The tables hold the rows they had at one moment: they are locked together while
+    /// their pages are copied in memory, see [`DiskStorage::snapshot`], and the files are
+    /// written once they are unlocked, so the writers wait for the copy, not for the disk.
+    /// # Errors
+    /// Returns the errors of [`DiskStorage::backup_table`].
+    pub fn backup(&self, dir: impl AsRef<Path>) -> Result<u64, StorageError> {
+        let tables = self.tables();
+        self.backup_tables(&tables.iter().map(AsRef::as_ref).collect::<Vec<_>>(), dir)
+    }
+
+    fn backup_tables(&self, tables: &[&str], dir: impl AsRef<Path>) -> Result<u64, StorageError> {
+        let dir = dir.as_ref();
+        self.snapshot(tables)?
+            .iter()
+            .map(|snapshot| snapshot.write(dir))
+            .sum()
+    }
+
+    /// Copies the files of the tables of the backup `backup`, taken by
+    /// [`DiskStorage::backup`], to `dir`, and opens a storage for them there, like
+    /// [`DiskStorage::open`]. The backup is left as it is: a storage opened at it reads its
+    /// tables too.
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if `dir` has the file of a table of the
+    /// backup, or an error if a file can't be copied.
+    pub fn restore(
+        backup: impl AsRef<Path>,
+        dir: impl Into<PathBuf>,
+    ) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        backup::restore(backup.as_ref(), &dir, "heap")?;
+        Self::open(dir)
+    }
+
+    /// Writes the dirty pages of the open tables to their files, and flushes them to the
+    /// disk.
+    /// # Errors
+    /// Returns an error if a file can't be flushed.
+    pub fn sync(&self) -> Result<(), StorageError> {
+        let tables = self.lock().values().cloned().collect::<Vec<_>>();
+        for table in tables {
+            table.lock().heap.sync()?;
+        }
+        Ok(())
+    }
+}
+
+impl StorageEngine for DiskStorage {
+    fn create_table(&self, name: &str, types: Vec<SqlType>) -> Result<(), StorageError> {
+        Self::create_table(self, name, types)
+    }
+
+    fn drop_table(&self, name: &str) -> Result<(), StorageError> {
+        Self::drop_table(self, name)
+    }
+
+    fn table(&self, name: &str) -> Result<Arc<dyn Table>, StorageError> {
+        Ok(self.handle(name)?)
+    }
+
+    fn tables(&self) -> Vec<Box<str>> {
+        Self::tables(self)
+    }
+
+    fn snapshot(&self, names: &[&str]) -> Result<Vec<Box<dyn TableSnapshot>>, StorageError> {
+        Ok(Self::snapshot(self, names)?
+            .into_iter()
+            .map(|snapshot| Box::new(snapshot) as Box<dyn TableSnapshot>)
+            .collect())
+    }
+
+    fn add_bloom_filter(&self, name: &str, column: usize) -> Result<(), StorageError> {
+        Self::add_bloom_filter(self, name, column)
+    }
+
+    fn add_index(&self, name: &str, index: IndexDefinition) -> Result<(), StorageError> {
+        Self::add_index(self, name, index)
+    }
+}
+
+/// The pages of a table of a [`DiskStorage`] at a moment, see [`DiskStorage::snapshot`].
+#[derive(Debug, Clone)]
+pub struct DiskSnapshot {
+    table: Box<str>,
+    compression: Compression,
+    pages: Vec<Page>,
+}
+
+impl TableSnapshot for DiskSnapshot {
+    fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Writes the pages to `<table>.heap` in `dir`, compressed like the file of the table.
+    fn write(&self, dir: &Path) -> Result<u64, StorageError> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.heap", self.table));
+        write_pages(&path, &self.table, self.compression, &self.pages)
+    }
+}
+
+/// The rows of a table, see [`DiskStorage::scan`].
+#[derive(Debug)]
+pub struct Scan {
+    file: Arc<Mutex<TableFile>>,
+    /// The next page to read.
+    page: PageId,
+    /// The rows of the last page read not returned yet.
+    rows: VecDeque<(RecordId, Vec<Value>)>,
+}
+
+impl Scan {
+    /// Reads the rows of the next page, returns `false` after the last page.
+    fn read_page(&mut self) -> Result<bool, StorageError> {
+        let file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.page >= file.heap.page_count() {
+            return Ok(false);
+        }
+        let rows = file
+            .heap
+            .page_records(self.page)?
+            .into_iter()
+            .map(|(id, record)| Ok((id, decode_row(&file.types, &record)?)))
+            .collect::<Result<Vec<_>, StorageError>>()?;
+        drop(file);
+        self.page += 1;
+        self.rows.extend(rows);
+        Ok(true)
+    }
+}
+
+impl Iterator for Scan {
+    type Item = Result<(RecordId, Vec<Value>), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.rows.pop_front() {
+                return Some(Ok(row));
+            }
+            match self.read_page() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(error) => {
+                    // The scan ends with the error.
+                    self.page = PageId::MAX;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::ops::Bound;
+
+    use rs_db_parser::ast::commands::create::LengthUnit;
+
+    use super::*;
+    use crate::{engine::ScanOptions, testing::TempDir};
+
+    fn row(i: i32) -> Vec<Value> {
+        let name = if i % 5 == 0 {
+            Value::Null
+        } else {
+            Value::VarChar(
+                format!("name {i}")
+                    .repeat(usize::try_from(i % 7).unwrap())
+                    .into(),
+            )
+        };
+        vec![Value::I32(i), name]
+    }
+
+    #[test]
+    fn test_disk_storage() {
+        let dir = TempDir::new();
+        let types = vec![SqlType::I32, SqlType::VarChar(200, LengthUnit::Chars)];
+        let storage = DiskStorage::open(dir.path().join("data")).unwrap();
+        storage.create_table("public.t", types.clone()).unwrap();
+        assert_eq!(
+            storage.create_table("public.t", types.clone()),
+            Err(StorageError::TableExists("public.t".into()))
+        );
+        assert_eq!(
+            storage.insert("public.u", &row(1)),
+            Err(StorageError::TableNotFound("public.u".into()))
+        );
+        let ids = (0..1000)
+            .map(|i| storage.insert("public.t", &row(i)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(storage.get("public.t", ids[7]).unwrap(), Some(row(7)));
+        assert!(storage.delete("public.t", ids[7]).unwrap());
+        assert_eq!(storage.get("public.t", ids[7]).unwrap(), None);
+        assert_eq!(storage.row_count("public.t"), Some(999));
+        assert!(storage.dead_bytes("public.t").unwrap() > 0);
+        assert_eq!(storage.tables(), ["public.t".into()]);
+
+        let mut scan = storage.scan("public.t").unwrap();
+        let (first, _) = scan.next().unwrap().unwrap();
+        // Rows added during a scan are returned when they go to a page it hasn't read yet,
+        // not when they take the room the deleted row left in the first one.
+        let added = (1000..1100)
+            .map(|i| storage.insert("public.t", &row(i)).unwrap())
+            .collect::<Vec<_>>();
+        let unread = added.iter().filter(|id| id.page > first.page).count();
+        assert!(unread > 0 && unread < added.len(), "{unread}");
+        assert_eq!(scan.map(Result::unwrap).count(), 998 + unread);
+        storage.sync().unwrap();
+        drop(storage);
+
+        // The rows are read back from the files.
+        let storage = DiskStorage::open(dir.path().join("data")).unwrap();
+        assert!(storage.scan("public.t").is_err());
+        storage.open_table("public.t", types.clone()).unwrap();
+        assert_eq!(storage.row_count("public.t"), Some(1099));
+        let mut rows = storage
+            .scan("public.t")
+            .unwrap()
+            .map(|row| row.unwrap().1)
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| match row[0] {
+            Value::I32(i) => i,
+            _ => unreachable!(),
+        });
+        let expected = (0..1100).filter(|i| *i != 7).map(row).collect::<Vec<_>>();
+        assert_eq!(rows, expected);
+
+        // A compressed table is read back compressed.
+        storage
+            .create_table_with_compression("public.z", types.clone(), Compression::Zstd)
+            .unwrap();
+        for i in 0..100 {
+            storage.insert("public.z", &row(i)).unwrap();
+        }
+        storage.sync().unwrap();
+        storage.open_table("public.z", types.clone()).unwrap();
+        assert_eq!(storage.row_count("public.z"), Some(100));
+        assert_eq!(
+            storage
+                .scan("public.z")
+                .unwrap()
+                .map(|row| row.unwrap().1)
+                .collect::<Vec<_>>(),
+            (0..100).map(row).collect::<Vec<_>>()
+        );
+
+        // Rows read with other types fail to decode.
+        storage
+            .create_table("public.v", vec![SqlType::I64])
+            .unwrap();
+        storage.insert("public.v", &[Value::I32(1)]).unwrap();
+        storage.sync().unwrap();
+        storage.open_table("public.v", vec![SqlType::I64]).unwrap();
+        assert!(matches!(
+            storage.scan("public.v").unwrap().next(),
+            Some(Err(StorageError::Decode(_)))
+        ));
+        storage.drop_table("public.v").unwrap();
+        assert!(!dir.path().join("data/public.v.heap").exists());
+        assert_eq!(
+            storage.open_table("public.v", types),
+            Err(StorageError::TableNotFound("public.v".into()))
+        );
+    }
+
+    #[test]
+    fn test_disk_table() {
+        let dir = TempDir::new();
+        let storage = DiskStorage::open(dir.path().join("data")).unwrap();
+        let engine: &dyn StorageEngine = &storage;
+        engine.create_table("public.t", vec![SqlType::I32]).unwrap();
+        assert_eq!(engine.tables(), ["public.t".into()]);
+        let table = engine.table("public.t").unwrap();
+        let ids = (0..500)
+            .map(|i| table.insert(&[Value::I32(i)]).unwrap())
+            .collect::<Vec<_>>();
+        // A row id is the id of the record of the row.
+        let record = storage.insert("public.t", &[Value::I32(500)]).unwrap();
+        assert_eq!(RecordId::from(RowId::from(record)), record);
+        assert_eq!(
+            storage.get("public.t", RecordId::from(ids[300])).unwrap(),
+            Some(vec![Value::I32(300)])
+        );
+        assert!(table.delete(ids[300]).unwrap());
+        assert_eq!(table.get(ids[300]).unwrap(), None);
+        assert!(matches!(
+            table.get(RowId(u64::MAX)),
+            Err(StorageError::RecordNotFound(_))
+        ));
+        assert_eq!(table.row_count(), Some(500));
+        assert!(table.vacuum().unwrap() > 0);
+
+        // The handle and its scans still read the rows once the table is dropped.
+        let scan = table.scan().unwrap();
+        engine.drop_table("public.t").unwrap();
+        assert!(matches!(
+            engine.table("public.t"),
+            Err(StorageError::TableNotFound(_))
+        ));
+        assert_eq!(scan.map(Result::unwrap).count(), 500);
+        assert_eq!(table.name(), "public.t");
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let dir = TempDir::new();
+        let types = vec![SqlType::I32, SqlType::VarChar(200, LengthUnit::Chars)];
+        let storage = DiskStorage::open(dir.path().join("data")).unwrap();
+        storage.create_table("public.t", types).unwrap();
+        for i in 0..100 {
+            storage.insert("public.t", &row(i)).unwrap();
+        }
+        assert_eq!(
+            storage.add_bloom_filter("public.t", 2),
+            Err(StorageError::ColumnNotFound(2))
+        );
+        storage.add_bloom_filter("public.t", 0).unwrap();
+        // The rows added later are in the filters, which grow past the first one.
+        for i in 100..3000 {
+            storage.insert("public.t", &row(i)).unwrap();
+        }
+        let table = StorageEngine::table(&storage, "public.t").unwrap();
+        let equal = |column: usize, value: Value| ScanOptions {
+            columns: Some(vec![0]),
+            ranges: vec![ColumnRange {
+                column,
+                lower: Bound::Included(value.clone()),
+                upper: Bound::Included(value),
+            }],
+        };
+        for i in [0, 50, 1500, 2999] {
+            assert!(table.may_match(&equal(0, Value::I32(i)).ranges));
+            // The rows out of the range are left to the caller to filter.
+            let mut rows = table.scan_with(&equal(0, Value::I32(i))).unwrap();
+            assert!(rows.any(|row| row.unwrap().1 == [Value::I32(i)]));
+        }
+        let absent = (3000..4000)
+            .filter(|i| !table.may_match(&equal(0, Value::I32(*i)).ranges))
+            .count();
+        assert!(absent > 900, "{absent}");
+        assert!(table
+            .scan_with(&equal(0, Value::I32(-1)))
+            .unwrap()
+            .next()
+            .is_none());
+        // A column without a filter, or a range of more than one value, may match.
+        assert!(table.may_match(&equal(1, Value::VarChar("x".into())).ranges));
+        assert!(table.may_match(&[ColumnRange {
+            column: 0,
+            lower: Bound::Included(Value::I32(-10)),
+            upper: Bound::Excluded(Value::I32(-1)),
+        }]));
+    }
+
+    #[test]
+    fn test_backup() {
+        let dir = TempDir::new();
+        let types = vec![SqlType::I32, SqlType::VarChar(200, LengthUnit::Chars)];
+        let storage = Arc::new(DiskStorage::open(dir.path().join("data")).unwrap());
+        storage.create_table("public.t", types.clone()).unwrap();
+        storage
+            .create_table_with_compression("public.z", types.clone(), Compression::Lz4)
+            .unwrap();
+        for i in 0..500 {
+            storage.insert("public.t", &row(i)).unwrap();
+            storage.insert("public.z", &row(i)).unwrap();
+        }
+
+        // The rows written during the backup are in it or not, whole.
+        let writer = {
+            let storage = Arc::clone(&storage);
+            std::thread::spawn(move || {
+                for i in 500..1000 {
+                    storage.insert("public.t", &row(i)).unwrap();
+                }
+            })
+        };
+        let backup = dir.path().join("backup");
+        assert!(storage.backup(&backup).unwrap() > 0);
+        writer.join().unwrap();
+        assert_eq!(
+            storage.backup_table("public.t", &backup),
+            Err(StorageError::TableExists("public.t".into()))
+        );
+        assert_eq!(
+            storage.backup_table("public.u", &backup),
+            Err(StorageError::TableNotFound("public.u".into()))
+        );
+
+        let restored = DiskStorage::restore(&backup, dir.path().join("restored")).unwrap();
+        restored.open_table("public.t", types.clone()).unwrap();
+        restored.open_table("public.z", types.clone()).unwrap();
+        let rows = restored
+            .scan("public.t")
+            .unwrap()
+            .map(|row| row.unwrap().1)
+            .collect::<Vec<_>>();
+        assert!(rows.len() >= 500 && rows.len() <= 1000, "{}", rows.len());
+        assert!(rows
+            .iter()
+            .all(|r| matches!(r[0], Value::I32(i) if r == &row(i))));
+        assert_eq!(restored.row_count("public.z"), Some(500));
+        assert!(matches!(
+            DiskStorage::restore(&backup, dir.path().join("restored")),
+            Err(StorageError::TableExists(_))
+        ));
+
+        // A snapshot holds the rows the tables had when it was taken, the ones written
+        // before it is written aren't in it.
+        let snapshots = storage
+            .snapshot(&["public.z", "public.t", "public.z"])
+            .unwrap();
+        assert_eq!(
+            snapshots
+                .iter()
+                .map(TableSnapshot::table)
+                .collect::<Vec<_>>(),
+            ["public.t", "public.z"]
+        );
+        storage.insert("public.t", &row(1000)).unwrap();
+        storage.insert("public.z", &row(1000)).unwrap();
+        let later = dir.path().join("later");
+        for snapshot in &snapshots {
+            snapshot.write(&later).unwrap();
+        }
+        let restored = DiskStorage::open(&later).unwrap();
+        restored.open_table("public.t", types.clone()).unwrap();
+        restored.open_table("public.z", types).unwrap();
+        assert_eq!(restored.row_count("public.t"), Some(1000));
+        assert_eq!(restored.row_count("public.z"), Some(500));
+    }
+}
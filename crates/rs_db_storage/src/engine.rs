@@ -0,0 +1,283 @@
+use std::{cmp::Ordering, fmt, ops::Bound, path::Path, sync::Arc};
+
+use rs_db_parser::{ast::commands::create::SqlType, value::Value};
+
+use crate::error::StorageError;
+
+/// The id of a row in its [`Table`], given by the engine when the row is added. It
+/// doesn't change while the row exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RowId(pub u64);
+
+impl fmt::Display for RowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The rows of a table with their id, see [`Table::scan`]. Any iterator of them is a
+/// cursor, one that fails ends after its error.
+pub trait Cursor: Iterator<Item = Result<(RowId, Vec<Value>), StorageError>> {}
+
+impl<I: Iterator<Item = Result<(RowId, Vec<Value>), StorageError>>> Cursor for I {}
+
+/// The values of a column a scan keeps, `column` being its position in the rows of the
+/// table. `NULL` is in no range, as no comparison with it is TRUE.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ColumnRange {
+    pub column: usize,
+    pub lower: Bound<Value>,
+    pub upper: Bound<Value>,
+}
+
+impl ColumnRange {
+    /// Whether some value between `min` and `max`, both included, may be in the range. A
+    /// bound that can't be compared with them, like a string and an integer, may be.
+    #[must_use]
+    pub fn overlaps(&self, min: &Value, max: &Value) -> bool {
+        // A range is out of reach when a comparison says so, not when it fails.
+        let ordering = |value: &Value, bound: &Value| value.compare(bound).ok().flatten();
+        let above = match &self.lower {
+            Bound::Included(lower) => ordering(max, lower) != Some(Ordering::Less),
+            Bound::Excluded(lower) => !ordering(max, lower).is_some_and(Ordering::is_le),
+            Bound::Unbounded => true,
+        };
+        let below = match &self.upper {
+            Bound::Included(upper) => ordering(min, upper) != Some(Ordering::Greater),
+            Bound::Excluded(upper) => !ordering(min, upper).is_some_and(Ordering::is_ge),
+            Bound::Unbounded => true,
+        };
+        above && below
+    }
+
+    /// The value the range keeps alone, when its bounds are the same value, both included,
+    /// like for `column = value`.
+    #[must_use]
+    pub fn value(&self) -> Option<&Value> {
+        match (&self.lower, &self.upper) {
+            (Bound::Included(lower), Bound::Included(upper))
+                if lower.compare(upper) == Ok(Some(Ordering::Equal)) =>
+            {
+                Some(lower)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What a scan reads, see [`Table::scan_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScanOptions {
+    /// The positions of the columns of the rows returned, in their order, `None` for all
+    /// of them.
+    pub columns: Option<Vec<usize>>,
+    /// The ranges the values of the rows are in. The engine may skip the rows that have a
+    /// value out of one, but needn't: the caller filters the rows again.
+    pub ranges: Vec<ColumnRange>,
+}
+
+impl ScanOptions {
+    /// The values of `row` at the columns of the options.
+    #[must_use]
+    pub fn project(&self, row: Vec<Value>) -> Vec<Value> {
+        match &self.columns {
+            Some(columns) => columns.iter().map(|i| row[*i].clone()).collect(),
+            None => row,
+        }
+    }
+}
+
+/// An ordered index of a table, see [`StorageEngine::add_index`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexDefinition {
+    pub name: Box<str>,
+    /// The names of the columns of the key, the most significant first. The engine only
+    /// keeps them for the caller, it knows a column by its position.
+    pub columns: Vec<Box<str>>,
+    /// The positions of the columns of the key in the rows of the table, in their order.
+    pub positions: Vec<usize>,
+}
+
+/// A table of a [`StorageEngine`], whose rows hold values of the types it was created
+/// with, in the order of its columns. A handle doesn't borrow its engine, nor do its
+/// cursors, so a scan may run while other tables are created or dropped.
+pub trait Table: Send + Sync {
+    /// The name the table was created with.
+    fn name(&self) -> &str;
+
+    /// Adds `row` to the table.
+    /// # Errors
+    /// Returns an error if the row can't be written.
+    fn insert(&self, row: &[Value]) -> Result<RowId, StorageError>;
+
+    /// The row `id`, `None` if it was deleted.
+    /// # Errors
+    /// Returns an error if the row can't be read.
+    fn get(&self, id: RowId) -> Result<Option<Vec<Value>>, StorageError>;
+
+    /// Deletes the row `id`, returns `false` if it was already deleted.
+    /// # Errors
+    /// Returns an error if the row can't be deleted.
+    fn delete(&self, id: RowId) -> Result<bool, StorageError>;
+
+    /// The rows of the table, in the order the engine keeps them.
+    /// # Errors
+    /// Returns an error if the scan can't start.
+    fn scan(&self) -> Result<Box<dyn Cursor>, StorageError>;
+
+    /// Like [`Table::scan`], reading what `options` asks for. An engine that keeps the
+    /// values of a column together reads only the columns of the options, and skips the
+    /// values out of their ranges without reading them; the others project the rows of
+    /// [`Table::scan`], or read none when [`Table::may_match`] rules the ranges out.
+    /// # Errors
+    /// Returns an error if the scan can't start.
+    fn scan_with(&self, options: &ScanOptions) -> Result<Box<dyn Cursor>, StorageError> {
+        if !self.may_match(&options.ranges) {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let options = options.clone();
+        Ok(Box::new(self.scan()?.map(move |row| {
+            row.map(|(id, row)| (id, options.project(row)))
+        })))
+    }
+
+    /// Whether a row of the table may have values in all of `ranges`, `false` only when
+    /// none has, like when the [`BloomFilter`](crate::bloom::BloomFilter) of a column holds
+    /// none of the value a range keeps alone. The engine answers without reading the rows.
+    fn may_match(&self, _ranges: &[ColumnRange]) -> bool {
+        true
+    }
+
+    /// The number of rows, if the engine knows it without reading them.
+    fn row_count(&self) -> Option<u64> {
+        None
+    }
+
+    /// The indexes of the table, see [`StorageEngine::add_index`].
+    fn indexes(&self) -> Vec<IndexDefinition> {
+        Vec::new()
+    }
+
+    /// The rows whose key in the index `index` is between `lower` and `upper`, in the
+    /// order of the keys, see [`OrderedIndex::range`](crate::index::OrderedIndex::range).
+    /// With `key_only`, a row only has the values of the key, read from the index alone.
+    /// The engine may return keys out of the range, the caller checks them again.
+    /// # Errors
+    /// Returns [`StorageError::IndexNotFound`] if the table has no index `index`,
+    /// [`StorageError::Unsupported`] if the engine keeps no index, or an error if a row
+    /// can't be read.
+    fn index_scan(
+        &self,
+        _index: &str,
+        _lower: Bound<&[Value]>,
+        _upper: Bound<&[Value]>,
+        _key_only: bool,
+    ) -> Result<Box<dyn Cursor>, StorageError> {
+        Err(StorageError::Unsupported("An index scan"))
+    }
+
+    /// Reclaims the room of the deleted rows, for `VACUUM`, and returns the bytes
+    /// reclaimed. An engine that frees it when a row is deleted has nothing to do.
+    /// # Errors
+    /// Returns an error if the table can't be written.
+    fn vacuum(&self) -> Result<u64, StorageError> {
+        Ok(0)
+    }
+}
+
+/// The rows a table had when [`StorageEngine::snapshot`] took it, kept in memory until
+/// they are written to a backup. The table isn't locked meanwhile.
+pub trait TableSnapshot: Send + Sync {
+    /// The name of the table.
+    fn table(&self) -> &str;
+
+    /// Writes the table to the directory `dir`, created if it is missing, where an engine
+    /// of the same kind opened at `dir` finds it. Returns the bytes written.
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if `dir` has the table, or an error if it
+    /// can't be written.
+    fn write(&self, dir: &Path) -> Result<u64, StorageError>;
+}
+
+/// Where the tables are kept, the boundary between the executor and a backend: the
+/// executor reads and writes the tables of any engine, like the
+/// [`DiskStorage`](crate::disk::DiskStorage) and the
+/// [`MemoryStorage`](crate::memory::MemoryStorage) of this crate, or one kept in another
+/// database or in an object store. A table is named `schema.table`.
+///
+/// The methods take `&self`, an engine keeps its state behind a lock so that it and its
+/// tables can be shared by threads.
+pub trait StorageEngine: Send + Sync {
+    /// Creates an empty table whose rows have values of `types`.
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if the table exists, or an error if it can't
+    /// be created.
+    fn create_table(&self, name: &str, types: Vec<SqlType>) -> Result<(), StorageError>;
+
+    /// Drops a table and its rows.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist, or an error if
+    /// it can't be dropped.
+    fn drop_table(&self, name: &str) -> Result<(), StorageError>;
+
+    /// A handle to the table `name`.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist.
+    fn table(&self, name: &str) -> Result<Arc<dyn Table>, StorageError>;
+
+    /// The names of the tables, sorted.
+    fn tables(&self) -> Vec<Box<str>>;
+
+    /// Snapshots the tables `names` for `BACKUP`, at one moment: they are all locked while
+    /// their rows are copied in memory, then unlocked before any snapshot is written, see
+    /// [`TableSnapshot::write`]. The snapshots are in the order of the names, sorted, a
+    /// name given twice is snapshot once. The tables are locked in that order, so two
+    /// snapshots taken at once don't wait for each other.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if a table doesn't exist,
+    /// [`StorageError::Unsupported`] if the engine keeps no files, or an error if a table
+    /// can't be read.
+    fn snapshot(&self, _names: &[&str]) -> Result<Vec<Box<dyn TableSnapshot>>, StorageError> {
+        Err(StorageError::Unsupported("BACKUP"))
+    }
+
+    /// Copies the table `name` to the directory `dir`, for `BACKUP`, where an engine of
+    /// the same kind opened at `dir` finds it, see [`StorageEngine::snapshot`]. Returns the
+    /// bytes written.
+    /// # Errors
+    /// Returns the errors of [`StorageEngine::snapshot`] and of [`TableSnapshot::write`].
+    fn backup_table(&self, name: &str, dir: &Path) -> Result<u64, StorageError> {
+        self.snapshot(&[name])?
+            .iter()
+            .map(|snapshot| snapshot.write(dir))
+            .sum()
+    }
+
+    /// Keeps a [`BloomFilter`](crate::bloom::BloomFilter) of the values of the column at
+    /// `column` of the table `name`, so that the scans of an equality on it skip the rows
+    /// that can't match. The filters aren't written to the files, the table is given them
+    /// again once it is opened, like the types of its columns. An engine that keeps no
+    /// filter only checks the table exists.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist,
+    /// [`StorageError::ColumnNotFound`] if it has no column at `column`, or an error if its
+    /// rows can't be read.
+    fn add_bloom_filter(&self, name: &str, _column: usize) -> Result<(), StorageError> {
+        self.table(name).map(drop)
+    }
+
+    /// Keeps an [`OrderedIndex`](crate::index::OrderedIndex) of the rows of the table
+    /// `name` by the values of the columns of `index`, added to and removed from as rows
+    /// are, which [`Table::index_scan`] reads. Like a bloom filter, the index isn't written
+    /// to the files: the table is given it again once it is opened.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist,
+    /// [`StorageError::ColumnNotFound`] if it has no column at a position of the index,
+    /// [`StorageError::IndexExists`] if it has an index of the same name,
+    /// [`StorageError::Unsupported`] if the engine keeps no index, or an error if its rows
+    /// can't be read.
+    fn add_index(&self, name: &str, _index: IndexDefinition) -> Result<(), StorageError> {
+        self.table(name)?;
+        Err(StorageError::Unsupported("An index"))
+    }
+}
@@ -0,0 +1,64 @@
+use rs_db_parser::errors::DecodeError;
+
+use crate::heap::RecordId;
+
+/// Why the storage could not read or write a table.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum StorageError {
+    #[error("Table `{0}` not found")]
+    TableNotFound(Box<str>),
+
+    #[error("Table `{0}` already exists")]
+    TableExists(Box<str>),
+
+    #[error("Index `{0}` not found")]
+    IndexNotFound(Box<str>),
+
+    #[error("Index `{0}` already exists")]
+    IndexExists(Box<str>),
+
+    /// A column position past the last column of a table.
+    #[error("Column {0} not found")]
+    ColumnNotFound(usize),
+
+    /// A record longer than [`MAX_RECORD_SIZE`](crate::page::MAX_RECORD_SIZE), which
+    /// doesn't fit in a page.
+    #[error("A record of {0} bytes doesn't fit in a page")]
+    RecordTooLarge(usize),
+
+    /// A record id whose page is past the end of its file.
+    #[error("Record {0} not found")]
+    RecordNotFound(RecordId),
+
+    /// A page, or the length of the file it is read from, doesn't hold what the storage
+    /// wrote.
+    #[error("Page {page} of `{file}` is corrupted")]
+    CorruptPage { file: Box<str>, page: u64 },
+
+    /// A file other than a file of pages, like the one of a
+    /// [`ColumnarTable`](crate::columnar::ColumnarTable), doesn't hold what the storage
+    /// wrote.
+    #[error("`{0}` is corrupted")]
+    CorruptFile(Box<str>),
+
+    /// An engine can't do what was asked, like copying a table it keeps in no file.
+    #[error("{0} is not supported by the storage engine")]
+    Unsupported(&'static str),
+
+    /// Every frame of the [`BufferPool`](crate::buffer::BufferPool) holds a pinned page.
+    #[error("Every page of the buffer pool is pinned")]
+    BufferPoolFull,
+
+    #[error("I/O error: {0}")]
+    Io(Box<str>),
+
+    /// A record doesn't hold a row of the types of the columns of its table.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.to_string().into())
+    }
+}
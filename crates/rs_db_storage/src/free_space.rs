@@ -0,0 +1,146 @@
+use crate::{heap::PageId, page::PAGE_SIZE};
+
+/// The free space of each page of a [`HeapFile`](crate::heap::HeapFile), so an insert
+/// finds a page with room for its record without reading the others. It is kept in memory
+/// and rebuilt from the pages when the file is opened.
+///
+/// The free spaces are the leaves of a tree whose nodes hold the largest free space below
+/// them, so the first page with enough room is found in a number of steps that grows with
+/// the logarithm of the number of pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FreeSpaceMap {
+    /// The nodes of the tree, the root at `1` and the children of `i` at `2 * i` and
+    /// `2 * i + 1`. The leaves start at `capacity`, a power of two.
+    nodes: Vec<u16>,
+    capacity: usize,
+    len: usize,
+}
+
+impl FreeSpaceMap {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of pages.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The free space of `page`, `0` past the last page.
+    #[must_use]
+    pub fn get(&self, page: PageId) -> usize {
+        let index = page as usize;
+        if index >= self.len {
+            return 0;
+        }
+        usize::from(self.nodes[self.capacity + index])
+    }
+
+    /// Records the free space of `page`, adding pages with no free space up to it.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn set(&mut self, page: PageId, free: usize) {
+        let index = page as usize;
+        if index >= self.capacity {
+            self.grow(index + 1);
+        }
+        self.len = self.len.max(index + 1);
+        let mut node = self.capacity + index;
+        self.nodes[node] = free.min(PAGE_SIZE) as u16;
+        while node > 1 {
+            node /= 2;
+            self.nodes[node] = self.nodes[2 * node].max(self.nodes[2 * node + 1]);
+        }
+    }
+
+    /// Drops the pages from `len` on.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn truncate(&mut self, len: usize) {
+        for index in len..self.len {
+            // Pages are only added through `set`, with a `PageId`.
+            self.set(index as PageId, 0);
+        }
+        self.len = self.len.min(len);
+    }
+
+    /// Makes room for `len` pages, keeping the free space of the current ones.
+    fn grow(&mut self, len: usize) {
+        let capacity = len.next_power_of_two();
+        let mut nodes = vec![0; 2 * capacity];
+        nodes[capacity..capacity + self.len]
+            .copy_from_slice(&self.nodes[self.capacity..self.capacity + self.len]);
+        for node in (1..capacity).rev() {
+            nodes[node] = nodes[2 * node].max(nodes[2 * node + 1]);
+        }
+        self.nodes = nodes;
+        self.capacity = capacity;
+    }
+
+    /// The first page with at least `len` bytes of free space.
+    #[must_use]
+    pub fn find(&self, len: usize) -> Option<PageId> {
+        if self.is_empty() || usize::from(self.nodes[1]) < len {
+            return None;
+        }
+        let mut node = 1;
+        while node < self.capacity {
+            node = if usize::from(self.nodes[2 * node]) >= len {
+                2 * node
+            } else {
+                2 * node + 1
+            };
+        }
+        PageId::try_from(node - self.capacity).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_free_space_map() {
+        let mut map = FreeSpaceMap::new();
+        assert_eq!(map.find(0), None);
+        map.set(0, 100);
+        assert_eq!(map.find(50), Some(0));
+        assert_eq!(map.find(101), None);
+        // Setting a page past the end adds the pages before it.
+        map.set(4, 3000);
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.get(2), 0);
+        assert_eq!(map.get(9), 0);
+        map.set(2, 500);
+        assert_eq!(map.find(10), Some(0));
+        assert_eq!(map.find(200), Some(2));
+        assert_eq!(map.find(600), Some(4));
+        map.set(0, 0);
+        map.set(2, 0);
+        assert_eq!(map.find(1), Some(4));
+        assert_eq!(map.find(3001), None);
+
+        for page in 5..1000 {
+            map.set(page, usize::try_from(page).unwrap());
+        }
+        assert_eq!(map.find(3001), None);
+        assert_eq!(map.find(700), Some(4));
+        map.set(4, 0);
+        assert_eq!(map.find(700), Some(700));
+        assert_eq!(map.find(999), Some(999));
+        assert_eq!(map.find(1000), None);
+
+        // The pages dropped aren't found anymore.
+        map.truncate(600);
+        assert_eq!(map.len(), 600);
+        assert_eq!(map.find(700), None);
+        assert_eq!(map.find(599), Some(599));
+        assert_eq!(map.get(999), 0);
+    }
+}
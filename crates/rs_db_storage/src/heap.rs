@@ -0,0 +1,534 @@
+use std::{
+    fmt,
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rs_db_parser::ast::commands::create::Compression;
+
+use crate::{
+    buffer::{BufferPool, FileId, PageFile},
+    compress::CompressedFile,
+    engine::RowId,
+    error::StorageError,
+    free_space::FreeSpaceMap,
+    page::{Page, SlotId, MAX_RECORD_SIZE, PAGE_SIZE},
+};
+
+/// The number of a page in its file, the first one is `0`.
+pub type PageId = u32;
+
+/// Where a record is: its page, and its slot in the page. It doesn't change while the
+/// record exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordId {
+    pub page: PageId,
+    pub slot: SlotId,
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.page, self.slot)
+    }
+}
+
+/// The id of a row of a [`DiskTable`](crate::disk::DiskTable) is its record id, the page
+/// in the high bits and the slot in the low 16.
+impl From<RecordId> for RowId {
+    fn from(id: RecordId) -> Self {
+        Self(u64::from(id.page) << 16 | u64::from(id.slot))
+    }
+}
+
+/// An id with a page past the last one gets the last page, which no file reaches.
+impl From<RowId> for RecordId {
+    fn from(RowId(id): RowId) -> Self {
+        Self {
+            page: PageId::try_from(id >> 16).unwrap_or(PageId::MAX),
+            slot: (id & 0xffff) as SlotId,
+        }
+    }
+}
+
+/// What [`HeapFile::vacuum`] reclaimed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VacuumStats {
+    /// The pages whose deleted records were reclaimed.
+    pub pages_compacted: u64,
+    /// The empty pages cut from the end of the file.
+    pub pages_truncated: u64,
+    /// The bytes of the deleted records and of the pages cut.
+    pub bytes_reclaimed: u64,
+}
+
+/// The records of a table in a file of [pages](crate::page::Page), page `n` at the offset
+/// `n * PAGE_SIZE`. An insert puts its record in the first page with room for it, found
+/// with a [`FreeSpaceMap`], or in a new page at the end of the file, so the records are in
+/// no particular order.
+///
+/// The pages are read and written through a [`BufferPool`], which writes them to the file
+/// when it evicts or flushes them. The file is closed, its dirty pages written, when the
+/// heap is dropped. A file created with a [`Compression`] keeps its pages compressed, see
+/// [`CompressedFile`], it is found to be when it is opened.
+#[derive(Debug)]
+pub struct HeapFile {
+    pool: Arc<BufferPool>,
+    file: FileId,
+    path: PathBuf,
+    compression: Compression,
+    free_space: FreeSpaceMap,
+    records: u64,
+    /// The bytes of the records deleted since the last vacuum.
+    dead_bytes: u64,
+}
+
+impl HeapFile {
+    /// Creates an empty file at `path`, whose pages are kept in `pool`.
+    /// # Errors
+    /// Returns an error if the file already exists or can't be created.
+    pub fn create(path: &Path, pool: &Arc<BufferPool>) -> Result<Self, StorageError> {
+        Self::create_with_compression(path, pool, Compression::None)
+    }
+
+    /// Like [`HeapFile::create`], writing the pages compressed with `compression`.
+    /// # Errors
+    /// Returns an error if the file already exists or can't be created.
+    pub fn create_with_compression(
+        path: &Path,
+        pool: &Arc<BufferPool>,
+        compression: Compression,
+    ) -> Result<Self, StorageError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        let file = match compression {
+            Compression::None => PageFile::Plain(file),
+            _ => PageFile::Compressed(CompressedFile::create(file, compression)?),
+        };
+        Ok(Self {
+            pool: Arc::clone(pool),
+            file: pool.register_page_file(file, path.display().to_string()),
+            path: path.to_path_buf(),
+            compression,
+            free_space: FreeSpaceMap::new(),
+            records: 0,
+            dead_bytes: 0,
+        })
+    }
+
+    /// Opens the file at `path`, whose pages are kept in `pool`, reading all of its pages
+    /// to find their free space.
+    /// # Errors
+    /// Returns an error if the file can't be read, or if it isn't made of pages.
+    pub fn open(path: &Path, pool: &Arc<BufferPool>) -> Result<Self, StorageError> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let name = path.display().to_string();
+        let (file, pages) = if CompressedFile::is_compressed(&mut file)? {
+            let file = CompressedFile::open(file, &name)?;
+            let pages = file.page_count();
+            (PageFile::Compressed(file), pages)
+        } else {
+            let len = file.metadata()?.len();
+            let corrupt = |page| StorageError::CorruptPage {
+                file: name.as_str().into(),
+                page,
+            };
+            if !len.is_multiple_of(PAGE_SIZE as u64) {
+                return Err(corrupt(len / PAGE_SIZE as u64));
+            }
+            let pages = PageId::try_from(len / PAGE_SIZE as u64)
+                .map_err(|_| corrupt(u64::from(PageId::MAX)))?;
+            (PageFile::Plain(file), pages)
+        };
+        let compression = match &file {
+            PageFile::Plain(_) => Compression::None,
+            PageFile::Compressed(file) => file.compression(),
+        };
+        let mut heap = Self {
+            pool: Arc::clone(pool),
+            file: pool.register_page_file(file, name),
+            path: path.to_path_buf(),
+            compression,
+            free_space: FreeSpaceMap::new(),
+            records: 0,
+            dead_bytes: 0,
+        };
+        for id in 0..pages {
+            let (free, records, garbage) = heap
+                .pool
+                .pin(heap.file, id)?
+                .read(|page| (page.free_space(), page.record_count(), page.garbage()));
+            heap.free_space.set(id, free);
+            heap.records += records as u64;
+            heap.dead_bytes += garbage as u64;
+        }
+        Ok(heap)
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How the pages are compressed in the file.
+    #[must_use]
+    pub const fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// The number of pages.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn page_count(&self) -> PageId {
+        // Pages are only added through `set`, with a `PageId`.
+        self.free_space.len() as PageId
+    }
+
+    /// The number of records.
+    #[must_use]
+    pub const fn record_count(&self) -> u64 {
+        self.records
+    }
+
+    /// The bytes of the records deleted since the file was last vacuumed, or found in its
+    /// pages when it was opened.
+    #[must_use]
+    pub const fn dead_bytes(&self) -> u64 {
+        self.dead_bytes
+    }
+
+    /// Adds `record` to the file, see [`HeapFile`].
+    /// # Errors
+    /// Returns [`StorageError::RecordTooLarge`] if the record is longer than
+    /// [`MAX_RECORD_SIZE`], or an error if a page can't be pinned.
+    pub fn insert(&mut self, record: &[u8]) -> Result<RecordId, StorageError> {
+        if record.len() > MAX_RECORD_SIZE {
+            return Err(StorageError::RecordTooLarge(record.len()));
+        }
+        if let Some(id) = self.free_space.find(record.len()) {
+            let inserted = self.pool.pin(self.file, id)?.write(|page| {
+                let slot = page.insert(record)?;
+                Some((slot, page.free_space()))
+            });
+            match inserted {
+                Some((slot, free)) => {
+                    self.free_space.set(id, free);
+                    self.records += 1;
+                    return Ok(RecordId { page: id, slot });
+                }
+                // A page with free space but no room for another slot.
+                None => self.free_space.set(id, 0),
+            }
+        }
+        let id = self.page_count();
+        let (slot, free) = self
+            .pool
+            .pin_new(self.file, id)?
+            .write(|page| {
+                let slot = page.insert(record)?;
+                Some((slot, page.free_space()))
+            })
+            .ok_or(StorageError::RecordTooLarge(record.len()))?;
+        self.free_space.set(id, free);
+        self.records += 1;
+        Ok(RecordId { page: id, slot })
+    }
+
+    /// The record at `id`, `None` if it was deleted.
+    /// # Errors
+    /// Returns [`StorageError::RecordNotFound`] if the page of `id` is past the end of the
+    /// file, or an error if it can't be pinned.
+    pub fn get(&self, id: RecordId) -> Result<Option<Vec<u8>>, StorageError> {
+        if id.page >= self.page_count() {
+            return Err(StorageError::RecordNotFound(id));
+        }
+        Ok(self
+            .pool
+            .pin(self.file, id.page)?
+            .read(|page| page.get(id.slot).map(<[u8]>::to_vec)))
+    }
+
+    /// Deletes the record at `id`, returns `false` if it was already deleted.
+    /// # Errors
+    /// Returns [`StorageError::RecordNotFound`] if the page of `id` is past the end of the
+    /// file, or an error if it can't be pinned.
+    pub fn delete(&mut self, id: RecordId) -> Result<bool, StorageError> {
+        if id.page >= self.page_count() {
+            return Err(StorageError::RecordNotFound(id));
+        }
+        let guard = self.pool.pin(self.file, id.page)?;
+        if guard.read(|page| page.get(id.slot).is_none()) {
+            return Ok(false);
+        }
+        let (free, len) = guard.write(|page| {
+            let len = page.get(id.slot).map_or(0, <[u8]>::len);
+            page.delete(id.slot);
+            (page.free_space(), len)
+        });
+        self.free_space.set(id.page, free);
+        self.records -= 1;
+        self.dead_bytes += len as u64;
+        Ok(true)
+    }
+
+    /// The records of page `id` with their id, in the order of their slots.
+    /// # Errors
+    /// Returns an error if the page can't be pinned.
+    pub fn page_records(&self, id: PageId) -> Result<Vec<(RecordId, Vec<u8>)>, StorageError> {
+        Ok(self.pool.pin(self.file, id)?.read(|page| {
+            page.records()
+                .map(|(slot, record)| (RecordId { page: id, slot }, record.to_vec()))
+                .collect()
+        }))
+    }
+
+    /// Reclaims the space of the deleted records: cuts the empty pages at the end of the
+    /// file, and compacts the pages left whose records were deleted, zeroing their free
+    /// space. The records keep their id, they aren't moved to another page. A compressed
+    /// file is then [compacted](BufferPool::compact), the bytes it loses are reclaimed
+    /// instead of the pages cut.
+    /// # Errors
+    /// Returns an error if a page can't be pinned or the file can't be cut.
+    pub fn vacuum(&mut self) -> Result<VacuumStats, StorageError> {
+        let mut stats = VacuumStats::default();
+        let mut pages = self.page_count();
+        while pages > 0
+            && self
+                .pool
+                .pin(self.file, pages - 1)?
+                .read(|page| page.record_count() == 0)
+        {
+            pages -= 1;
+        }
+        if pages < self.page_count() {
+            self.pool.truncate(self.file, pages)?;
+            stats.pages_truncated = u64::from(self.page_count() - pages);
+            if self.compression == Compression::None {
+                stats.bytes_reclaimed = stats.pages_truncated * PAGE_SIZE as u64;
+            }
+            self.free_space.truncate(pages as usize);
+        }
+        for id in 0..pages {
+            let guard = self.pool.pin(self.file, id)?;
+            let garbage = guard.read(Page::garbage);
+            if garbage > 0 {
+                guard.write(Page::compact);
+                stats.pages_compacted += 1;
+                stats.bytes_reclaimed += garbage as u64;
+            }
+        }
+        stats.bytes_reclaimed += self.pool.compact(self.file)?;
+        self.dead_bytes = 0;
+        Ok(stats)
+    }
+
+    /// A copy of the pages, the ones written but not flushed yet included, for a backup
+    /// written once the file can be written to again, see [`backup::write_pages`].
+    ///
+    /// [`backup::write_pages`]: crate::backup::write_pages
+    /// # Errors
+    /// Returns an error if a page can't be pinned.
+    pub fn snapshot_pages(&self) -> Result<Vec<Page>, StorageError> {
+        (0..self.page_count())
+            .map(|id| Ok(self.pool.pin(self.file, id)?.read(Page::clone)))
+            .collect()
+    }
+
+    /// Writes the dirty pages to the file, and flushes it to the disk.
+    /// # Errors
+    /// Returns an error if a page can't be written or the file can't be flushed.
+    pub fn sync(&self) -> Result<(), StorageError> {
+        self.pool.flush(self.file)
+    }
+}
+
+impl Drop for HeapFile {
+    fn drop(&mut self) {
+        // The pages that can't be written are lost, `sync` reports the error.
+        let _ = self.pool.unregister(self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::io::Write;
+
+    use super::*;
+    use crate::{buffer::BufferPoolConfig, testing::TempDir};
+
+    #[test]
+    fn test_heap_file() {
+        let dir = TempDir::new();
+        let path = dir.path().join("t.heap");
+        // Fewer frames than pages, so pages are evicted and read again.
+        let pool = BufferPool::new(&BufferPoolConfig {
+            capacity: 2,
+            flush_interval: None,
+            ..BufferPoolConfig::default()
+        });
+        let mut heap = HeapFile::create(&path, &pool).unwrap();
+        assert!(HeapFile::create(&path, &pool).is_err());
+        let records = (0..100u32)
+            .map(|i| vec![u8::try_from(i).unwrap(); 200 + i as usize * 3])
+            .collect::<Vec<_>>();
+        let ids = records
+            .iter()
+            .map(|record| heap.insert(record).unwrap())
+            .collect::<Vec<_>>();
+        assert!(heap.page_count() > 1);
+        assert_eq!(heap.record_count(), 100);
+        for (id, record) in ids.iter().zip(&records) {
+            assert_eq!(heap.get(*id).unwrap().as_ref(), Some(record));
+        }
+
+        // A deleted record leaves room for the next ones in its page.
+        let pages = heap.page_count();
+        assert!(heap.delete(ids[10]).unwrap());
+        assert!(!heap.delete(ids[10]).unwrap());
+        assert_eq!(heap.get(ids[10]).unwrap(), None);
+        let id = heap.insert(&[1; 100]).unwrap();
+        assert_eq!(id.page, ids[10].page);
+        assert_eq!(heap.page_count(), pages);
+        assert_eq!(
+            heap.insert(&vec![0; MAX_RECORD_SIZE + 1]),
+            Err(StorageError::RecordTooLarge(MAX_RECORD_SIZE + 1))
+        );
+        let missing = RecordId {
+            page: pages,
+            slot: 0,
+        };
+        assert_eq!(
+            heap.get(missing),
+            Err(StorageError::RecordNotFound(missing))
+        );
+        heap.sync().unwrap();
+        drop(heap);
+
+        // The free space and the records are found again when the file is opened.
+        let mut heap = HeapFile::open(&path, &pool).unwrap();
+        assert_eq!(heap.record_count(), 100);
+        assert_eq!(heap.page_count(), pages);
+        let mut found = (0..pages)
+            .flat_map(|page| heap.page_records(page).unwrap())
+            .map(|(_, record)| record)
+            .collect::<Vec<_>>();
+        found.sort();
+        let mut expected = records.clone();
+        expected.remove(10);
+        expected.push(vec![1; 100]);
+        expected.sort();
+        assert_eq!(found, expected);
+        let id = heap.insert(&[2; 10]).unwrap();
+        assert!(id.page < pages);
+
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"torn")
+            .unwrap();
+        assert!(matches!(
+            HeapFile::open(&path, &pool),
+            Err(StorageError::CorruptPage { .. })
+        ));
+    }
+
+    #[test]
+    fn test_vacuum() {
+        let dir = TempDir::new();
+        let path = dir.path().join("t.heap");
+        let pool = BufferPool::new(&BufferPoolConfig {
+            flush_interval: None,
+            ..BufferPoolConfig::default()
+        });
+        let mut heap = HeapFile::create(&path, &pool).unwrap();
+        let ids = (0..40u8)
+            .map(|i| heap.insert(&[i; 1000]).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(heap.page_count(), 5);
+        assert_eq!(ids[1].page, 0);
+        assert_eq!(ids[24].page, 3);
+        for id in [ids[1], ids[9]].iter().chain(&ids[24..]) {
+            heap.delete(*id).unwrap();
+        }
+        assert_eq!(heap.dead_bytes(), 18 * 1000);
+
+        // The empty pages at the end are cut, the pages with deleted records compacted.
+        let stats = heap.vacuum().unwrap();
+        assert_eq!(
+            stats,
+            VacuumStats {
+                pages_compacted: 2,
+                pages_truncated: 2,
+                bytes_reclaimed: 2 * PAGE_SIZE as u64 + 2000,
+            }
+        );
+        assert_eq!(heap.dead_bytes(), 0);
+        assert_eq!(heap.page_count(), 3);
+        assert_eq!(heap.record_count(), 22);
+        for (i, id) in ids[..24].iter().enumerate() {
+            let expected = (i != 1 && i != 9).then(|| vec![u8::try_from(i).unwrap(); 1000]);
+            assert_eq!(heap.get(*id).unwrap(), expected);
+        }
+        assert_eq!(heap.vacuum().unwrap(), VacuumStats::default());
+        heap.sync().unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            3 * PAGE_SIZE as u64
+        );
+
+        // The pages cut are added again by the next inserts.
+        for i in 0..10 {
+            heap.insert(&[i; 1000]).unwrap();
+        }
+        assert_eq!(heap.page_count(), 4);
+        drop(heap);
+        let heap = HeapFile::open(&path, &pool).unwrap();
+        assert_eq!(heap.record_count(), 32);
+        assert_eq!(heap.dead_bytes(), 0);
+    }
+
+    #[test]
+    fn test_compressed_heap_file() {
+        let dir = TempDir::new();
+        let pool = BufferPool::new(&BufferPoolConfig {
+            capacity: 4,
+            flush_interval: None,
+            ..BufferPoolConfig::default()
+        });
+        let records = (0..500u32)
+            .map(|i| format!("user {i} lives at {} Main Street", i % 40).repeat(4))
+            .collect::<Vec<_>>();
+        let mut lens = Vec::new();
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let path = dir.path().join(format!("{}.heap", compression.name()));
+            let mut heap = HeapFile::create_with_compression(&path, &pool, compression).unwrap();
+            let ids = records
+                .iter()
+                .map(|record| heap.insert(record.as_bytes()).unwrap())
+                .collect::<Vec<_>>();
+            for id in &ids[100..] {
+                heap.delete(*id).unwrap();
+            }
+            let stats = heap.vacuum().unwrap();
+            assert!(stats.pages_truncated > 0);
+            heap.sync().unwrap();
+            lens.push(std::fs::metadata(&path).unwrap().len());
+            drop(heap);
+
+            let heap = HeapFile::open(&path, &pool).unwrap();
+            assert_eq!(heap.compression(), compression);
+            assert_eq!(heap.record_count(), 100);
+            for (id, record) in ids.iter().zip(&records).take(100) {
+                assert_eq!(heap.get(*id).unwrap().as_deref(), Some(record.as_bytes()));
+            }
+        }
+        // The repetitive records take a fraction of the room compressed.
+        assert!(lens[1] * 2 < lens[0], "{lens:?}");
+        assert!(lens[2] * 2 < lens[0], "{lens:?}");
+    }
+}
@@ -0,0 +1,186 @@
+use std::{cmp::Ordering, collections::BTreeSet, ops::Bound};
+
+use rs_db_parser::value::Value;
+
+use crate::engine::{IndexDefinition, RowId};
+
+/// An ordered index of the rows of a table, like a B+tree: the key of each row, the values
+/// of the columns of the index, with its id, sorted by the [`Ord`] of [`Value`]. The keys
+/// in a range are found without reading the other rows, see [`OrderedIndex::range`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderedIndex {
+    definition: IndexDefinition,
+    entries: BTreeSet<(Vec<Value>, RowId)>,
+}
+
+impl OrderedIndex {
+    /// An empty index of the columns of `definition`.
+    #[must_use]
+    pub const fn new(definition: IndexDefinition) -> Self {
+        Self {
+            definition,
+            entries: BTreeSet::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    /// The number of rows in the index.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The key of `row`, its values at the columns of the index.
+    #[must_use]
+    pub fn key(&self, row: &[Value]) -> Vec<Value> {
+        self.definition
+            .positions
+            .iter()
+            .map(|i| row.get(*i).cloned().unwrap_or(Value::Null))
+            .collect()
+    }
+
+    /// Adds the row `id`, whose values are `row`.
+    pub fn insert(&mut self, id: RowId, row: &[Value]) {
+        self.entries.insert((self.key(row), id));
+    }
+
+    /// Removes the row `id`, whose values are `row`, returns `false` if it isn't in the
+    /// index.
+    pub fn remove(&mut self, id: RowId, row: &[Value]) -> bool {
+        self.entries.remove(&(self.key(row), id))
+    }
+
+    /// The keys between `lower` and `upper` with the id of their row, in the order of the
+    /// keys. A bound is compared with as many values of the start of a key as it has, with
+    /// [`Value::compare`].
+    ///
+    /// The index seeks the first key of the range and stops past the last one, but the
+    /// keys between may be out of the range, like those that can't be compared with a
+    /// bound or have a `NULL` after the values compared with the lower one: the caller
+    /// checks them again.
+    #[must_use]
+    pub fn range(
+        &self,
+        lower: Bound<&[Value]>,
+        upper: Bound<&[Value]>,
+    ) -> Vec<(Vec<Value>, RowId)> {
+        let start = match lower {
+            Bound::Included(bound) | Bound::Excluded(bound) => {
+                let seek = (bound.to_vec(), RowId(0));
+                // The keys equal to the bound but of a variant sorted before it, like `1`
+                // as an `I32` for a bound of `1` as an `I64`, are before it.
+                let equal = self
+                    .entries
+                    .range(..&seek)
+                    .rev()
+                    .take_while(|(key, _)| compare_prefix(key, bound) == Some(Ordering::Equal))
+                    .last()
+                    .cloned();
+                Bound::Included(equal.unwrap_or(seek))
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.entries
+            .range((start, Bound::Unbounded))
+            .take_while(|(key, _)| match upper {
+                // The keys with a `NULL` first are last, in no range.
+                Bound::Included(_) | Bound::Excluded(_)
+                    if key.first().is_some_and(Value::is_null) =>
+                {
+                    false
+                }
+                Bound::Included(bound) => compare_prefix(key, bound) != Some(Ordering::Greater),
+                Bound::Excluded(bound) => !compare_prefix(key, bound).is_some_and(Ordering::is_ge),
+                Bound::Unbounded => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Compares the start of `key` with `bound`, `None` if a value is `NULL` or can't be
+/// compared with the one of the bound.
+fn compare_prefix(key: &[Value], bound: &[Value]) -> Option<Ordering> {
+    for (value, bound) in key.iter().zip(bound) {
+        match value.compare(bound).ok()?? {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+    }
+    Some(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn ids(entries: &[(Vec<Value>, RowId)]) -> Vec<u64> {
+        entries.iter().map(|(_, id)| id.0).collect()
+    }
+
+    #[test]
+    fn test_ordered_index() {
+        let mut index = OrderedIndex::new(IndexDefinition {
+            name: "t_a_b".into(),
+            columns: vec!["a".into(), "b".into()],
+            positions: vec![1, 0],
+        });
+        // Row `i` has the key (i % 5, i).
+        for i in 0..20 {
+            index.insert(
+                RowId(i),
+                &[
+                    Value::I64(i.try_into().unwrap()),
+                    Value::I32((i % 5).try_into().unwrap()),
+                ],
+            );
+        }
+        index.insert(RowId(20), &[Value::I64(20), Value::Null]);
+        assert_eq!(index.len(), 21);
+
+        let one = [Value::I32(1)];
+        assert_eq!(
+            ids(&index.range(Bound::Included(&one), Bound::Included(&one))),
+            [1, 6, 11, 16]
+        );
+        // A bound of another variant finds the keys it equals.
+        let one = [Value::I64(1)];
+        assert_eq!(
+            ids(&index.range(Bound::Included(&one), Bound::Included(&one))),
+            [1, 6, 11, 16]
+        );
+        let (low, high) = ([Value::I32(3), Value::I64(8)], [Value::I32(4)]);
+        assert_eq!(
+            ids(&index.range(Bound::Included(&low), Bound::Excluded(&high))),
+            [8, 13, 18]
+        );
+        assert_eq!(
+            ids(&index.range(Bound::Unbounded, Bound::Excluded(&one))),
+            [0, 5, 10, 15]
+        );
+        // The `NULL` key is only read without an upper bound.
+        let four = [Value::I32(4)];
+        assert_eq!(
+            ids(&index.range(Bound::Included(&four), Bound::Unbounded)),
+            [4, 9, 14, 19, 20]
+        );
+
+        assert!(index.remove(RowId(6), &[Value::I64(6), Value::I32(1)]));
+        assert!(!index.remove(RowId(6), &[Value::I64(6), Value::I32(1)]));
+        assert_eq!(
+            ids(&index.range(Bound::Included(&one), Bound::Included(&one))),
+            [1, 11, 16]
+        );
+    }
+}
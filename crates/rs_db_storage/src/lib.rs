@@ -0,0 +1,48 @@
+pub(crate) mod backup;
+pub mod bloom;
+pub mod buffer;
+pub mod columnar;
+pub mod compress;
+pub mod disk;
+pub mod engine;
+pub mod error;
+pub mod free_space;
+pub mod heap;
+pub mod index;
+pub mod memory;
+pub mod page;
+pub mod vacuum;
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    /// Numbers the directories of the tests of the process.
+    static NEXT_DIR: AtomicU64 = AtomicU64::new(0);
+
+    /// A directory for the files of a test, deleted with them when it is dropped.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new() -> Self {
+            let id = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+            let path =
+                std::env::temp_dir().join(format!("rs_db_storage-{}-{id}", std::process::id()));
+            std::fs::create_dir_all(&path).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+            Self(path)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+}
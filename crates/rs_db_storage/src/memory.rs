@@ -0,0 +1,351 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use rs_db_parser::{ast::commands::create::SqlType, value::Value};
+
+use crate::{
+    engine::{Cursor, IndexDefinition, RowId, StorageEngine, Table},
+    error::StorageError,
+    index::OrderedIndex,
+};
+
+/// The rows of a [`MemoryTable`] by their id, given in the order they are added. An id
+/// isn't given again.
+#[derive(Debug, Default)]
+struct Rows {
+    rows: BTreeMap<RowId, Vec<Value>>,
+    next_id: RowId,
+    /// The indexes of the rows, see [`MemoryStorage::add_index`].
+    indexes: Vec<OrderedIndex>,
+}
+
+/// A table of a [`MemoryStorage`], the handle of its [`StorageEngine`]. The handle and its
+/// scans keep the rows: once the table is dropped, they still read them.
+#[derive(Debug)]
+pub struct MemoryTable {
+    name: Box<str>,
+    rows: Arc<Mutex<Rows>>,
+}
+
+impl MemoryTable {
+    fn lock(&self) -> MutexGuard<'_, Rows> {
+        self.rows.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn scan(&self) -> Scan {
+        Scan {
+            end: self.lock().next_id,
+            rows: Arc::clone(&self.rows),
+            next: RowId(0),
+        }
+    }
+
+    /// Indexes the rows of the table, see [`MemoryStorage::add_index`].
+    fn add_index(&self, definition: IndexDefinition) -> Result<(), StorageError> {
+        let mut rows = self.lock();
+        if rows
+            .indexes
+            .iter()
+            .any(|index| index.definition().name == definition.name)
+        {
+            return Err(StorageError::IndexExists(definition.name));
+        }
+        let mut index = OrderedIndex::new(definition);
+        for (id, row) in &rows.rows {
+            index.insert(*id, row);
+        }
+        rows.indexes.push(index);
+        Ok(())
+    }
+}
+
+impl Table for MemoryTable {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn insert(&self, row: &[Value]) -> Result<RowId, StorageError> {
+        let mut rows = self.lock();
+        let id = rows.next_id;
+        rows.next_id.0 += 1;
+        for index in &mut rows.indexes {
+            index.insert(id, row);
+        }
+        rows.rows.insert(id, row.to_vec());
+        Ok(id)
+    }
+
+    fn get(&self, id: RowId) -> Result<Option<Vec<Value>>, StorageError> {
+        Ok(self.lock().rows.get(&id).cloned())
+    }
+
+    fn delete(&self, id: RowId) -> Result<bool, StorageError> {
+        let mut rows = self.lock();
+        let Some(row) = rows.rows.remove(&id) else {
+            return Ok(false);
+        };
+        for index in &mut rows.indexes {
+            index.remove(id, &row);
+        }
+        Ok(true)
+    }
+
+    fn scan(&self) -> Result<Box<dyn Cursor>, StorageError> {
+        Ok(Box::new(Self::scan(self)))
+    }
+
+    fn row_count(&self) -> Option<u64> {
+        Some(self.lock().rows.len() as u64)
+    }
+
+    fn indexes(&self) -> Vec<IndexDefinition> {
+        let rows = self.lock();
+        rows.indexes
+            .iter()
+            .map(|index| index.definition().clone())
+            .collect()
+    }
+
+    /// Reads the keys of the range from the index, then each row when it is reached: a row
+    /// deleted before is skipped.
+    fn index_scan(
+        &self,
+        index: &str,
+        lower: Bound<&[Value]>,
+        upper: Bound<&[Value]>,
+        key_only: bool,
+    ) -> Result<Box<dyn Cursor>, StorageError> {
+        let entries = self
+            .lock()
+            .indexes
+            .iter()
+            .find(|found| *found.definition().name == *index)
+            .ok_or_else(|| StorageError::IndexNotFound(index.into()))?
+            .range(lower, upper);
+        let rows = Arc::clone(&self.rows);
+        Ok(Box::new(entries.into_iter().filter_map(
+            move |(key, id)| {
+                if key_only {
+                    return Some(Ok((id, key)));
+                }
+                let rows = rows.lock().unwrap_or_else(PoisonError::into_inner);
+                rows.rows.get(&id).map(|row| Ok((id, row.clone())))
+            },
+        )))
+    }
+}
+
+/// Tables kept in memory, each a [`BTreeMap`] of its rows by [`RowId`], so they are
+/// scanned in the order they were added. Nothing is written to a file, the tables are lost
+/// with the storage: for tests, caches, and targets with no file system like WASM.
+///
+/// The methods are the ones of [`DiskStorage`](crate::disk::DiskStorage) that don't deal
+/// with files, a table is created without the types of its rows, which are kept as they
+/// are. The methods take `&self` and lock the tables, so the storage can be shared.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    tables: Mutex<HashMap<Box<str>, Arc<MemoryTable>>>,
+}
+
+impl MemoryStorage {
+    /// A storage with no table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Box<str>, Arc<MemoryTable>>> {
+        self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The table `table`, the tables aren't locked while it is used.
+    fn handle(&self, table: &str) -> Result<Arc<MemoryTable>, StorageError> {
+        self.lock()
+            .get(table)
+            .cloned()
+            .ok_or_else(|| StorageError::TableNotFound(table.into()))
+    }
+
+    /// Creates an empty table.
+    /// # Errors
+    /// Returns [`StorageError::TableExists`] if the table exists.
+    pub fn create_table(&self, table: &str) -> Result<(), StorageError> {
+        let mut tables = self.lock();
+        if tables.contains_key(table) {
+            return Err(StorageError::TableExists(table.into()));
+        }
+        let handle = MemoryTable {
+            name: table.into(),
+            rows: Arc::default(),
+        };
+        tables.insert(table.into(), Arc::new(handle));
+        Ok(())
+    }
+
+    /// Drops a table and its rows.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist.
+    pub fn drop_table(&self, table: &str) -> Result<(), StorageError> {
+        self.lock()
+            .remove(table)
+            .map(drop)
+            .ok_or_else(|| StorageError::TableNotFound(table.into()))
+    }
+
+    /// The names of the tables, sorted.
+    #[must_use]
+    pub fn tables(&self) -> Vec<Box<str>> {
+        let mut tables = self.lock().keys().cloned().collect::<Vec<_>>();
+        tables.sort();
+        tables
+    }
+
+    /// Adds `row` to `table`.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist.
+    pub fn insert(&self, table: &str, row: &[Value]) -> Result<RowId, StorageError> {
+        self.handle(table)?.insert(row)
+    }
+
+    /// The row `id` of `table`, `None` if it was deleted or never added.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist.
+    pub fn get(&self, table: &str, id: RowId) -> Result<Option<Vec<Value>>, StorageError> {
+        self.handle(table)?.get(id)
+    }
+
+    /// Deletes the row `id` of `table`, returns `false` if there is none.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist.
+    pub fn delete(&self, table: &str, id: RowId) -> Result<bool, StorageError> {
+        self.handle(table)?.delete(id)
+    }
+
+    /// The rows of `table` with their id, in the order they were added. The rows added
+    /// once the scan started aren't returned, the ones deleted before it reaches them
+    /// aren't either.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist.
+    pub fn scan(&self, table: &str) -> Result<Scan, StorageError> {
+        Ok(self.handle(table)?.scan())
+    }
+
+    /// The number of rows of `table`, `None` if it doesn't exist.
+    #[must_use]
+    pub fn row_count(&self, table: &str) -> Option<u64> {
+        self.handle(table).ok()?.row_count()
+    }
+
+    /// Keeps an index of the rows of `table` by the values of the columns of `index`,
+    /// built from its rows and added to and removed from as rows are. The rows are kept
+    /// whatever their types, a row without a column of the key has a `NULL` in it.
+    /// # Errors
+    /// Returns [`StorageError::TableNotFound`] if the table doesn't exist, or
+    /// [`StorageError::IndexExists`] if it has an index of the same name.
+    pub fn add_index(&self, table: &str, index: IndexDefinition) -> Result<(), StorageError> {
+        self.handle(table)?.add_index(index)
+    }
+}
+
+impl StorageEngine for MemoryStorage {
+    /// Creates an empty table, the rows are kept as they are added whatever `types` are.
+    fn create_table(&self, name: &str, _types: Vec<SqlType>) -> Result<(), StorageError> {
+        Self::create_table(self, name)
+    }
+
+    fn drop_table(&self, name: &str) -> Result<(), StorageError> {
+        Self::drop_table(self, name)
+    }
+
+    fn table(&self, name: &str) -> Result<Arc<dyn Table>, StorageError> {
+        Ok(self.handle(name)?)
+    }
+
+    fn tables(&self) -> Vec<Box<str>> {
+        Self::tables(self)
+    }
+
+    fn add_index(&self, name: &str, index: IndexDefinition) -> Result<(), StorageError> {
+        Self::add_index(self, name, index)
+    }
+}
+
+/// The rows of a table, see [`MemoryStorage::scan`].
+#[derive(Debug)]
+pub struct Scan {
+    rows: Arc<Mutex<Rows>>,
+    /// The first id not returned yet.
+    next: RowId,
+    /// The id of the first row added once the scan started.
+    end: RowId,
+}
+
+impl Iterator for Scan {
+    type Item = Result<(RowId, Vec<Value>), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rows = self.rows.lock().unwrap_or_else(PoisonError::into_inner);
+        let (id, row) = rows.rows.range(self.next..self.end).next()?;
+        self.next = RowId(id.0 + 1);
+        Some(Ok((*id, row.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_memory_storage() {
+        let storage = MemoryStorage::new();
+        storage.create_table("public.t").unwrap();
+        assert_eq!(
+            storage.create_table("public.t"),
+            Err(StorageError::TableExists("public.t".into()))
+        );
+        assert_eq!(
+            storage.insert("public.u", &[]),
+            Err(StorageError::TableNotFound("public.u".into()))
+        );
+        let ids = (0..10)
+            .map(|i| storage.insert("public.t", &[Value::I32(i)]).unwrap())
+            .collect::<Vec<_>>();
+        assert!(storage.delete("public.t", ids[3]).unwrap());
+        assert!(!storage.delete("public.t", ids[3]).unwrap());
+        assert_eq!(storage.get("public.t", ids[3]).unwrap(), None);
+        assert_eq!(
+            storage.get("public.t", ids[4]).unwrap(),
+            Some(vec![Value::I32(4)])
+        );
+        assert_eq!(storage.row_count("public.t"), Some(9));
+
+        // The rows added during a scan aren't returned, the ones deleted before it reaches
+        // them aren't either.
+        let mut scan = storage.scan("public.t").unwrap();
+        assert_eq!(
+            scan.next().unwrap().unwrap(),
+            (RowId(0), vec![Value::I32(0)])
+        );
+        storage.insert("public.t", &[Value::I32(10)]).unwrap();
+        storage.delete("public.t", ids[9]).unwrap();
+        let rest = scan
+            .map(|row| row.unwrap().1[0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rest,
+            (1..9)
+                .filter(|i| *i != 3)
+                .map(Value::I32)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(storage.tables(), ["public.t".into()]);
+        storage.drop_table("public.t").unwrap();
+        assert!(storage.scan("public.t").is_err());
+        assert_eq!(storage.row_count("public.t"), None);
+    }
+}
@@ -0,0 +1,327 @@
+use std::fmt;
+
+/// The bytes of a page, the unit the files of the tables are read and written in.
+pub const PAGE_SIZE: usize = 8192;
+
+/// The number of a slot in its page, see [`Page`].
+pub type SlotId = u16;
+
+/// The number of slots, then the offset of the first byte of the records.
+const HEADER_SIZE: usize = 4;
+
+/// The offset of a record, then its length.
+const SLOT_SIZE: usize = 4;
+
+/// The bytes of the longest record, which fills a page on its own.
+pub const MAX_RECORD_SIZE: usize = PAGE_SIZE - HEADER_SIZE - SLOT_SIZE;
+
+/// A slotted page: records of any length, each found through a slot of the directory that
+/// grows from the start of the page while the records grow from its end. A record keeps its
+/// slot while the page is compacted, so the ids that point at it stay valid.
+///
+/// The page starts with a header of the number of slots, then the offset of the first byte
+/// of the records. Each slot holds the offset of its record, then its length. A free slot,
+/// whose record was deleted, has the offset `0` and is taken by the next insert. Each
+/// number is 2 bytes, little-endian.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Page {
+    data: Box<[u8]>,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Page")
+            .field("slots", &self.slot_count())
+            .field("records", &self.record_count())
+            .field("free_space", &self.free_space())
+            .finish()
+    }
+}
+
+impl Page {
+    /// An empty page.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut page = Self {
+            data: vec![0; PAGE_SIZE].into(),
+        };
+        page.set_data_start(PAGE_SIZE);
+        page
+    }
+
+    /// The page held by `data`, as [`Page::as_bytes`] returns it. `None` if `data` isn't
+    /// [`PAGE_SIZE`] bytes, or if its header or a slot points outside of it.
+    #[must_use]
+    pub fn from_bytes(data: Box<[u8]>) -> Option<Self> {
+        if data.len() != PAGE_SIZE {
+            return None;
+        }
+        let page = Self { data };
+        let slots_end = HEADER_SIZE + usize::from(page.slot_count()) * SLOT_SIZE;
+        let data_start = page.data_start();
+        if slots_end > data_start || data_start > PAGE_SIZE {
+            return None;
+        }
+        let valid = (0..page.slot_count()).all(|slot| {
+            let (offset, len) = page.slot(slot);
+            offset == 0 || (offset >= data_start && offset + len <= PAGE_SIZE)
+        });
+        valid.then_some(page)
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn read_u16(&self, at: usize) -> usize {
+        usize::from(u16::from_le_bytes([self.data[at], self.data[at + 1]]))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn write_u16(&mut self, at: usize, value: usize) {
+        debug_assert!(value <= PAGE_SIZE);
+        self.data[at..at + 2].copy_from_slice(&(value as u16).to_le_bytes());
+    }
+
+    /// The number of slots, free or not.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn slot_count(&self) -> SlotId {
+        self.read_u16(0) as SlotId
+    }
+
+    fn set_slot_count(&mut self, count: usize) {
+        self.write_u16(0, count);
+    }
+
+    fn data_start(&self) -> usize {
+        self.read_u16(2)
+    }
+
+    fn set_data_start(&mut self, start: usize) {
+        self.write_u16(2, start);
+    }
+
+    /// The offset and the length of the record of `slot`.
+    fn slot(&self, slot: SlotId) -> (usize, usize) {
+        let at = HEADER_SIZE + usize::from(slot) * SLOT_SIZE;
+        (self.read_u16(at), self.read_u16(at + 2))
+    }
+
+    fn set_slot(&mut self, slot: SlotId, offset: usize, len: usize) {
+        let at = HEADER_SIZE + usize::from(slot) * SLOT_SIZE;
+        self.write_u16(at, offset);
+        self.write_u16(at + 2, len);
+    }
+
+    fn free_slot(&self) -> Option<SlotId> {
+        (0..self.slot_count()).find(|slot| self.slot(*slot).0 == 0)
+    }
+
+    /// The number of records.
+    #[must_use]
+    pub fn record_count(&self) -> usize {
+        self.records().count()
+    }
+
+    /// The length of the longest record [`Page::insert`] accepts, `None` if the page can't
+    /// take another slot.
+    fn room(&self) -> Option<usize> {
+        let used = HEADER_SIZE
+            + usize::from(self.slot_count()) * SLOT_SIZE
+            + self
+                .records()
+                .map(|(_, record)| record.len())
+                .sum::<usize>();
+        let new_slot = if self.free_slot().is_some() {
+            0
+        } else {
+            SLOT_SIZE
+        };
+        PAGE_SIZE.checked_sub(used + new_slot)
+    }
+
+    /// The bytes of the deleted records still between the live ones, which
+    /// [`Page::compact`] reclaims.
+    #[must_use]
+    pub fn garbage(&self) -> usize {
+        let live = self
+            .records()
+            .map(|(_, record)| record.len())
+            .sum::<usize>();
+        PAGE_SIZE - self.data_start() - live
+    }
+
+    /// The length of the longest record [`Page::insert`] accepts.
+    #[must_use]
+    pub fn free_space(&self) -> usize {
+        self.room().unwrap_or(0)
+    }
+
+    /// Adds `record` to the page, compacting it if the free space is split between the
+    /// records, and returns its slot. `None` if it doesn't fit, see [`Page::free_space`].
+    pub fn insert(&mut self, record: &[u8]) -> Option<SlotId> {
+        if self.room().is_none_or(|room| record.len() > room) {
+            return None;
+        }
+        let free_slot = self.free_slot();
+        let slots_end = HEADER_SIZE
+            + usize::from(self.slot_count()) * SLOT_SIZE
+            + if free_slot.is_some() { 0 } else { SLOT_SIZE };
+        if self.data_start() < slots_end + record.len() {
+            self.compact();
+        }
+        let start = self.data_start() - record.len();
+        self.data[start..start + record.len()].copy_from_slice(record);
+        self.set_data_start(start);
+        let slot = free_slot.unwrap_or_else(|| {
+            let slot = self.slot_count();
+            self.set_slot_count(usize::from(slot) + 1);
+            slot
+        });
+        self.set_slot(slot, start, record.len());
+        Some(slot)
+    }
+
+    /// The record of `slot`, `None` if the slot is free or past the last one.
+    #[must_use]
+    pub fn get(&self, slot: SlotId) -> Option<&[u8]> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+        match self.slot(slot) {
+            (0, _) => None,
+            (offset, len) => Some(&self.data[offset..offset + len]),
+        }
+    }
+
+    /// Frees `slot`, returns `false` if it was already free. The space of its record is
+    /// reclaimed when the page is compacted.
+    pub fn delete(&mut self, slot: SlotId) -> bool {
+        if self.get(slot).is_none() {
+            return false;
+        }
+        self.set_slot(slot, 0, 0);
+        // Trailing free slots are dropped, the slots before them keep their number.
+        let mut count = self.slot_count();
+        while count > 0 && self.slot(count - 1).0 == 0 {
+            count -= 1;
+        }
+        self.set_slot_count(usize::from(count));
+        true
+    }
+
+    /// The records with their slot, in the order of the slots.
+    pub fn records(&self) -> impl Iterator<Item = (SlotId, &[u8])> + '_ {
+        (0..self.slot_count()).filter_map(|slot| Some((slot, self.get(slot)?)))
+    }
+
+    /// Moves the records to the end of the page, so the free space is in one piece between
+    /// them and the slots, and zeroes it.
+    pub fn compact(&mut self) {
+        let mut records = (0..self.slot_count())
+            .filter_map(|slot| match self.slot(slot) {
+                (0, _) => None,
+                (offset, len) => Some((slot, offset, len)),
+            })
+            .collect::<Vec<_>>();
+        // The records are moved towards the end, the one nearest to it first, so none is
+        // overwritten before it is moved.
+        records.sort_unstable_by_key(|(_, offset, _)| std::cmp::Reverse(*offset));
+        let mut start = PAGE_SIZE;
+        for (slot, offset, len) in records {
+            start -= len;
+            self.data.copy_within(offset..offset + len, start);
+            self.set_slot(slot, start, len);
+        }
+        self.set_data_start(start);
+        let slots_end = HEADER_SIZE + usize::from(self.slot_count()) * SLOT_SIZE;
+        self.data[slots_end..start].fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_page() {
+        let mut page = Page::new();
+        assert_eq!(page.free_space(), MAX_RECORD_SIZE);
+        assert_eq!(page.insert(b"first"), Some(0));
+        assert_eq!(page.insert(b""), Some(1));
+        assert_eq!(page.insert(b"third"), Some(2));
+        assert_eq!(page.get(0), Some(&b"first"[..]));
+        assert_eq!(page.get(1), Some(&b""[..]));
+        assert_eq!(page.get(3), None);
+
+        // A deleted record frees its slot for the next one.
+        assert!(page.delete(0));
+        assert!(!page.delete(0));
+        assert_eq!(page.get(0), None);
+        assert_eq!(page.insert(b"again"), Some(0));
+        assert_eq!(
+            page.records().collect::<Vec<_>>(),
+            [(0, &b"again"[..]), (1, b""), (2, b"third")]
+        );
+        // The trailing free slots are dropped.
+        assert!(page.delete(2));
+        assert_eq!(page.slot_count(), 2);
+
+        let copy = Page::from_bytes(page.as_bytes().into()).unwrap();
+        assert_eq!(copy, page);
+        assert!(Page::from_bytes(vec![0; PAGE_SIZE].into()).is_none());
+        assert!(Page::from_bytes(vec![0; 10].into()).is_none());
+        let mut corrupted = page.as_bytes().to_vec();
+        corrupted[HEADER_SIZE..HEADER_SIZE + 2].copy_from_slice(&1u16.to_le_bytes());
+        assert!(Page::from_bytes(corrupted.into()).is_none());
+    }
+
+    #[test]
+    fn test_page_compaction() {
+        let mut page = Page::new();
+        let record = vec![7; 1000];
+        let mut slots = Vec::new();
+        while let Some(slot) = page.insert(&record) {
+            slots.push(slot);
+        }
+        assert_eq!(slots.len(), 8);
+        assert!(page.free_space() < record.len());
+        // The space of the deleted records is split, the insert compacts the page.
+        assert!(page.delete(1));
+        assert!(page.delete(4));
+        assert_eq!(page.garbage(), 2000);
+        let long = vec![9; 1900];
+        assert_eq!(page.insert(&long), Some(1));
+        assert_eq!(page.get(1), Some(&long[..]));
+        for slot in [0, 2, 3, 5, 6, 7] {
+            assert_eq!(page.get(slot), Some(&record[..]));
+        }
+        assert_eq!(page.record_count(), 7);
+        assert_eq!(page.garbage(), 0);
+        assert!(page.delete(7));
+        assert_eq!(page.garbage(), 1000);
+        page.compact();
+        assert_eq!(page.garbage(), 0);
+        assert_eq!(page.slot_count(), 7);
+        assert!(
+            page.as_bytes()[HEADER_SIZE + 7 * SLOT_SIZE..PAGE_SIZE - 6900]
+                .iter()
+                .all(|byte| *byte == 0)
+        );
+
+        let mut page = Page::new();
+        assert_eq!(page.insert(&vec![1; MAX_RECORD_SIZE]), Some(0));
+        assert_eq!(page.free_space(), 0);
+        assert_eq!(page.insert(b""), None);
+        assert!(Page::new().insert(&vec![1; MAX_RECORD_SIZE + 1]).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{disk::DiskStorage, page::PAGE_SIZE};
+
+/// When an [`AutoVacuum`] vacuums the tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoVacuumConfig {
+    /// How often the tables are checked.
+    pub interval: Duration,
+    /// The bytes of deleted rows a table has before it is vacuumed.
+    pub min_dead_bytes: u64,
+}
+
+impl Default for AutoVacuumConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            min_dead_bytes: PAGE_SIZE as u64,
+        }
+    }
+}
+
+/// A background thread that [vacuums](DiskStorage::vacuum) the open tables of a
+/// [`DiskStorage`] with at least [`AutoVacuumConfig::min_dead_bytes`] of deleted rows. It
+/// stops when it is dropped, or when the storage is.
+#[derive(Debug)]
+pub struct AutoVacuum {
+    stopped: Arc<AtomicBool>,
+    reclaimed: Arc<AtomicU64>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AutoVacuum {
+    /// Starts the thread vacuuming the tables of `storage`.
+    #[must_use]
+    pub fn start(storage: &Arc<DiskStorage>, config: &AutoVacuumConfig) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let reclaimed = Arc::new(AtomicU64::new(0));
+        let thread = {
+            let storage = Arc::downgrade(storage);
+            let stopped = Arc::clone(&stopped);
+            let reclaimed = Arc::clone(&reclaimed);
+            let config = config.clone();
+            thread::spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    thread::park_timeout(config.interval);
+                    let Some(storage) = storage.upgrade() else {
+                        return;
+                    };
+                    for table in storage.tables() {
+                        if stopped.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        if storage
+                            .dead_bytes(&table)
+                            .is_none_or(|dead| dead < config.min_dead_bytes.max(1))
+                        {
+                            continue;
+                        }
+                        // A table that can't be vacuumed, or was dropped meanwhile, is
+                        // tried again the next time.
+                        if let Ok(stats) = storage.vacuum(&table) {
+                            reclaimed.fetch_add(stats.bytes_reclaimed, Ordering::Relaxed);
+                        }
+                    }
+                }
+            })
+        };
+        Self {
+            stopped,
+            reclaimed,
+            thread: Some(thread),
+        }
+    }
+
+    /// The bytes the thread reclaimed since it started.
+    #[must_use]
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.reclaimed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for AutoVacuum {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::time::Instant;
+
+    use rs_db_parser::{ast::commands::create::SqlType, value::Value};
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_auto_vacuum() {
+        let dir = TempDir::new();
+        let storage = Arc::new(DiskStorage::open(dir.path()).unwrap());
+        storage
+            .create_table("public.t", vec![SqlType::I64])
+            .unwrap();
+        storage
+            .create_table("public.u", vec![SqlType::I64])
+            .unwrap();
+        let ids = (0..2000)
+            .map(|i| storage.insert("public.t", &[Value::I64(i)]).unwrap())
+            .collect::<Vec<_>>();
+        storage.insert("public.u", &[Value::I64(1)]).unwrap();
+        let pages = ids.last().unwrap().page + 1;
+        assert!(pages > 1);
+        for id in &ids[1..] {
+            storage.delete("public.t", *id).unwrap();
+        }
+
+        let vacuum = AutoVacuum::start(
+            &storage,
+            &AutoVacuumConfig {
+                interval: Duration::from_millis(10),
+                min_dead_bytes: 1,
+            },
+        );
+        let start = Instant::now();
+        while storage.dead_bytes("public.t") != Some(0) {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(vacuum.bytes_reclaimed() >= u64::from(pages - 1) * PAGE_SIZE as u64);
+        assert_eq!(storage.row_count("public.t"), Some(1));
+        assert_eq!(
+            storage.get("public.t", ids[0]).unwrap(),
+            Some(vec![Value::I64(0)])
+        );
+        drop(vacuum);
+
+        // The thread stops with the storage too.
+        let vacuum = AutoVacuum::start(&storage, &AutoVacuumConfig::default());
+        drop(storage);
+        drop(vacuum);
+    }
+}